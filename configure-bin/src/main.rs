@@ -1,13 +1,62 @@
 use crate::settings::CommandArguments;
+use crate::settings::CommandModes;
 use crate::settings::ConfigureAppSettings;
 use crate::settings::FullConfigurationSettings;
+use crate::wizard::run_wizard;
 use anyhow::bail;
 use anyhow::Result;
 use clap::Parser;
 use log::{debug, info};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
 use std::time::SystemTime;
 
 mod settings;
+mod wizard;
+
+/// Write the configuration file atomically, so an interrupted write
+/// never leaves a partial file behind. An existing file is only
+/// replaced when 'force' is true, after it is backed up.
+fn write_configuration_file(file_path: &Path, text: &str, force: bool) -> Result<()> {
+    let mut backup_file_path = file_path.as_os_str().to_owned();
+    backup_file_path.push(".bak");
+    let backup_file_path = PathBuf::from(backup_file_path);
+
+    if file_path.exists() {
+        if !force {
+            bail!(
+                "Configuration file {:?} already exists; use '--force' to overwrite it.",
+                file_path
+            );
+        }
+        fs::copy(file_path, &backup_file_path)?;
+        println!(
+            "Backed up existing configuration file to {}.",
+            backup_file_path.display()
+        );
+    }
+
+    if let Some(parent_dir) = file_path.parent() {
+        fs::create_dir_all(parent_dir)?;
+    }
+
+    // Write a temporary file next to the configuration file, then
+    // rename it, which replaces the file in one step.
+    let mut temp_file_path = file_path.as_os_str().to_owned();
+    temp_file_path.push(".tmp");
+    let temp_file_path = PathBuf::from(temp_file_path);
+    {
+        let mut file = fs::File::create(&temp_file_path)?;
+        file.write_all(text.as_bytes())?;
+        file.sync_all()?;
+    }
+    fs::rename(&temp_file_path, file_path)?;
+
+    println!("Configuration file written to {}.", file_path.display());
+    Ok(())
+}
 
 fn main() -> Result<()> {
     let env = env_logger::Env::default()
@@ -31,16 +80,35 @@ fn main() -> Result<()> {
         if full_settings.is_err() {
             bail!("Configuration structure is invalid: {:?}", full_settings);
         }
-        let full_settings = full_settings.unwrap();
+        let mut full_settings = full_settings.unwrap();
         debug!("Configuration structure validated: {:#?}", full_settings);
 
-        let toml = toml::to_string(&full_settings)?;
-        info!("Dumping configuration file (in TOML format)...");
-        print!("{}", toml);
+        // The wizard always writes the configuration file.
+        let is_wizard = matches!(args.command, Some(CommandModes::Wizard));
+        if is_wizard {
+            run_wizard(&mut full_settings)?;
+        }
 
-        // TODO: Get the file name to write out.
+        let toml = toml::to_string(&full_settings)?;
 
-        // TODO: Write out the file.
+        let output_file_path = match (&args.output, args.install || is_wizard) {
+            (Some(output), _) => Some(PathBuf::from(output)),
+            (None, true) => Some(
+                Path::new(&settings.configure.config_dir)
+                    .join(&settings.configure.config_file_name),
+            ),
+            (None, false) => None,
+        };
+        match output_file_path {
+            Some(file_path) => {
+                info!("Writing configuration file (in TOML format)...");
+                write_configuration_file(&file_path, &toml, args.force)?;
+            }
+            None => {
+                info!("Dumping configuration file (in TOML format)...");
+                print!("{}", toml);
+            }
+        }
 
         let duration = now.elapsed()?.as_secs_f32();
         debug!("Time taken: {:.1} seconds", duration);
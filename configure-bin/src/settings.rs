@@ -1,29 +1,69 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use config::ConfigError;
 use serde_derive::{Deserialize, Serialize};
-use timetracker_core::filesystem::find_existing_configuration_directory_path;
+use std::collections::HashMap;
+use timetracker_core::filesystem::find_default_directory_path;
 use timetracker_core::settings::new_core_settings;
 use timetracker_core::settings::new_print_settings;
+use timetracker_core::settings::new_recorder_settings;
 use timetracker_core::settings::validate_core_settings;
 use timetracker_core::settings::CoreSettings;
 use timetracker_core::settings::PrintSettings;
+use timetracker_core::settings::RecorderSettings;
+use timetracker_core::settings::StorageSettings;
 use timetracker_core::settings::DEFAULT_CONFIG_FILE_NAME;
+use timetracker_core::settings::MISSING_DIRECTORY_MESSAGE;
 
 #[derive(Parser, Debug)]
 #[clap(author = "David Cattermole, Copyright 2023-2024", version, about)]
 pub struct CommandArguments {
+    #[clap(subcommand)]
+    pub command: Option<CommandModes>,
+
     /// If true, ignore any user configuration files and return
     /// default configuration options.
     #[clap(long, value_parser, default_value_t = false)]
     pub defaults: bool,
 
-    /// Override the directory to search for the database file.
+    /// Override the directory to write the configuration file into
+    /// (with '--install').
     #[clap(long, value_parser)]
     pub config_dir: Option<String>,
 
     /// Override the name of the configuration file.
     #[clap(long, value_parser)]
     pub config_file_name: Option<String>,
+
+    /// Write the configuration file to this path, instead of
+    /// printing it.
+    #[clap(
+        short = 'o',
+        long,
+        value_parser,
+        global = true,
+        conflicts_with = "install"
+    )]
+    pub output: Option<String>,
+
+    /// Write the configuration file into the configuration directory
+    /// (such as '~/.config/.timetracker.toml'), where it is found by
+    /// the other Timetracker commands.
+    #[clap(long, value_parser, global = true, default_value_t = false)]
+    pub install: bool,
+
+    /// Overwrite an existing configuration file. The existing file is
+    /// first backed up (with a '.bak' extension added).
+    #[clap(long, value_parser, global = true, default_value_t = false)]
+    pub force: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CommandModes {
+    /// Interactively ask for the database directory, recorded
+    /// environment variables and displayed presets, then write the
+    /// configuration file (into the configuration directory, unless
+    /// '--output' is given).
+    Wizard,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -44,15 +84,19 @@ impl ConfigureAppSettings {
     pub fn new(arguments: &CommandArguments) -> Result<Self, ConfigError> {
         let mut builder = new_core_settings(None, None, arguments.defaults)?;
 
-        let default_config_dir = find_existing_configuration_directory_path()
-            .expect("Could not find a default config directory ($HOME, $HOME/.config or $XDG_CONFIG_HOME).")
-        .into_os_string()
-        .into_string()
-        .unwrap();
+        let default_config_dir = find_default_directory_path()
+            .ok_or_else(|| ConfigError::Message(MISSING_DIRECTORY_MESSAGE.to_string()))?
+            .to_string_lossy()
+            .into_owned();
 
         builder = builder
             .set_default("configure.config_dir", default_config_dir)?
-            .set_default("configure.config_file_name", DEFAULT_CONFIG_FILE_NAME)?;
+            .set_default("configure.config_file_name", DEFAULT_CONFIG_FILE_NAME)?
+            .set_override_option("configure.config_dir", arguments.config_dir.clone())?
+            .set_override_option(
+                "configure.config_file_name",
+                arguments.config_file_name.clone(),
+            )?;
 
         let settings: Self = builder.build()?.try_deserialize()?;
         validate_core_settings(&settings.core).unwrap();
@@ -65,12 +109,16 @@ impl ConfigureAppSettings {
 pub struct FullConfigurationSettings {
     pub core: CoreSettings,
     pub print: PrintSettings,
+    pub recorder: RecorderSettings,
+    pub storage: StorageSettings,
+    pub workspace_projects: HashMap<String, String>,
 }
 
 impl FullConfigurationSettings {
     pub fn new(defaults: bool) -> Result<Self, ConfigError> {
         let mut builder = new_core_settings(None, None, defaults)?;
         builder = new_print_settings(builder)?;
+        builder = new_recorder_settings(builder)?;
 
         let settings: Self = builder.build()?.try_deserialize()?;
         validate_core_settings(&settings.core).unwrap();
@@ -0,0 +1,125 @@
+use crate::settings::FullConfigurationSettings;
+use anyhow::{bail, Result};
+use std::io::BufRead;
+use std::io::Write;
+use std::path::PathBuf;
+use timetracker_core::settings::validate_core_settings;
+
+/// Ask a question, returning the (trimmed) answer, or the default
+/// when the answer is empty.
+fn prompt(question: &str, default: &str) -> Result<String> {
+    print!("{} [{}]: ", question, default);
+    std::io::stdout().flush()?;
+
+    let mut answer = String::new();
+    let byte_count = std::io::stdin().lock().read_line(&mut answer)?;
+    if byte_count == 0 {
+        bail!("No answer given for {:?}; the input was closed.", question);
+    }
+
+    let answer = answer.trim();
+    match answer.is_empty() {
+        true => Ok(default.to_string()),
+        false => Ok(answer.to_string()),
+    }
+}
+
+/// Split a comma-separated answer into the (non-empty) values.
+fn split_list(answer: &str) -> Vec<String> {
+    answer
+        .split(',')
+        .map(|x| x.trim().to_string())
+        .filter(|x| !x.is_empty())
+        .collect()
+}
+
+/// Expand a leading '~' to the home directory, so the directory can
+/// be checked.
+fn expand_home_dir(path: &str) -> PathBuf {
+    match (path.strip_prefix('~'), dirs::home_dir()) {
+        (Some(rest), Some(home_dir)) => home_dir.join(rest.trim_start_matches('/')),
+        _ => PathBuf::from(path),
+    }
+}
+
+fn ask_database_dir(full_settings: &mut FullConfigurationSettings) -> Result<()> {
+    loop {
+        let answer = prompt(
+            "Directory to store the database file in",
+            &full_settings.core.database_dir,
+        )?;
+        if !expand_home_dir(&answer).is_dir() {
+            println!("Directory {:?} does not exist, please try again.", answer);
+            continue;
+        }
+
+        let old_value = std::mem::replace(&mut full_settings.core.database_dir, answer);
+        match validate_core_settings(&full_settings.core) {
+            Ok(()) => return Ok(()),
+            Err(err) => {
+                println!("{}", err);
+                full_settings.core.database_dir = old_value;
+            }
+        }
+    }
+}
+
+fn ask_environment_variable_names(full_settings: &mut FullConfigurationSettings) -> Result<()> {
+    loop {
+        let default = full_settings.core.environment_variables.names.join(", ");
+        let answer = prompt(
+            "Environment variables to record, such as PROJECT or PWD (comma-separated)",
+            &default,
+        )?;
+
+        let old_value = std::mem::replace(
+            &mut full_settings.core.environment_variables.names,
+            split_list(&answer),
+        );
+        match validate_core_settings(&full_settings.core) {
+            Ok(()) => return Ok(()),
+            Err(err) => {
+                println!("{}", err);
+                full_settings.core.environment_variables.names = old_value;
+            }
+        }
+    }
+}
+
+fn ask_display_presets(full_settings: &mut FullConfigurationSettings) -> Result<()> {
+    let mut all_preset_names: Vec<&String> = full_settings.print.presets.keys().collect();
+    all_preset_names.sort();
+    println!("Available presets: {:?}", all_preset_names);
+
+    loop {
+        let default = full_settings.print.display_presets.join(", ");
+        let answer = prompt("Presets displayed by default (comma-separated)", &default)?;
+
+        let display_presets = split_list(&answer);
+        let missing_preset_names: Vec<&String> = display_presets
+            .iter()
+            .filter(|x| !full_settings.print.presets.contains_key(*x))
+            .collect();
+        if !missing_preset_names.is_empty() {
+            println!(
+                "Preset names {:?} are invalid, please try again.",
+                missing_preset_names
+            );
+            continue;
+        }
+
+        full_settings.print.display_presets = display_presets;
+        return Ok(());
+    }
+}
+
+/// Interactively ask for the most important settings (the database
+/// directory, the recorded environment variables and the displayed
+/// presets), validating each answer.
+pub fn run_wizard(full_settings: &mut FullConfigurationSettings) -> Result<()> {
+    println!("Timetracker configuration wizard; press Enter to use the [default] value.");
+    ask_database_dir(full_settings)?;
+    ask_environment_variable_names(full_settings)?;
+    ask_display_presets(full_settings)?;
+    Ok(())
+}
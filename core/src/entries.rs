@@ -1,22 +1,49 @@
 use log::debug;
+use serde_derive::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 pub use crate::settings::CoreSettings;
 
+/// The executable name of entries inserted manually (such as a
+/// meeting), rather than recorded.
+pub const MANUAL_ENTRY_EXECUTABLE_NAME: &str = "manual";
+
+/// The variable name storing the note of a manually inserted entry.
+pub const MANUAL_ENTRY_NOTE_VARIABLE_NAME: &str = "NOTE";
+
+/// The value recorded in place of a variable value denied by the
+/// recorder's variable filters.
+pub const FILTERED_VARIABLE_VALUE: &str = "<filtered>";
+
+/// The executable name recorded for ignored activity (see the
+/// 'recorder.ignore' settings).
+pub const IGNORED_EXECUTABLE_NAME: &str = "<ignored>";
+
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub enum RecordRowStatus {
     New,
     Existing,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, FromPrimitive, ToPrimitive)]
+#[derive(
+    Debug, Copy, Clone, PartialEq, PartialOrd, FromPrimitive, ToPrimitive, Serialize, Deserialize,
+)]
 pub enum EntryStatus {
     Uninitialized = 0,
     Active = 1,
     Idle = 2,
+    /// Recording was paused by the user (such as for personal
+    /// tasks); no activity is recorded.
+    Paused = 3,
+    /// The screen was locked (or the screen saver was active), such
+    /// as when the user stepped away from the computer.
+    Locked = 4,
+    /// The machine was suspended (or hibernated), so nothing was
+    /// recorded.
+    Suspended = 5,
 }
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct EntryVariablesList {
     pub executable: Option<String>,
     pub var1_name: Option<String>,
@@ -29,6 +56,28 @@ pub struct EntryVariablesList {
     pub var3_value: Option<String>,
     pub var4_value: Option<String>,
     pub var5_value: Option<String>,
+    /// The title of the focused window (such as the document or web
+    /// page), when recorded.
+    #[serde(default)]
+    pub window_title: Option<String>,
+    /// The current working directory of the focused process (read
+    /// from the process, not the "PWD" variable), when recorded.
+    #[serde(default)]
+    pub working_directory: Option<String>,
+    /// The file path of the document open in the focused
+    /// application, when recorded.
+    #[serde(default)]
+    pub document_path: Option<String>,
+    /// The name of the machine the entry was recorded on.
+    #[serde(default)]
+    pub hostname: Option<String>,
+    /// The name of the user the entry was recorded for.
+    #[serde(default)]
+    pub username: Option<String>,
+    /// The X11 display (such as ":0") of the focused window, when
+    /// the recorder is given multiple displays.
+    #[serde(default)]
+    pub display: Option<String>,
 }
 
 fn set_variable_from_environ_vars(
@@ -52,6 +101,7 @@ fn set_variable_from_environ_vars(
 }
 
 impl EntryVariablesList {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         executable: Option<String>,
         var1_name: Option<String>,
@@ -77,6 +127,12 @@ impl EntryVariablesList {
             var3_value,
             var4_value,
             var5_value,
+            window_title: None,
+            working_directory: None,
+            document_path: None,
+            hostname: None,
+            username: None,
+            display: None,
         }
     }
 
@@ -93,6 +149,12 @@ impl EntryVariablesList {
             var3_value: None,
             var4_value: None,
             var5_value: None,
+            window_title: None,
+            working_directory: None,
+            document_path: None,
+            hostname: None,
+            username: None,
+            display: None,
         }
     }
 
@@ -103,14 +165,87 @@ impl EntryVariablesList {
         set_variable_from_environ_vars(&self.var4_name, &mut self.var4_value, environ_vars);
         set_variable_from_environ_vars(&self.var5_name, &mut self.var5_value, environ_vars);
     }
+
+    /// Set the value of the variable named 'name', only if the
+    /// variable does not already have a value.
+    pub fn set_missing_variable_value(&mut self, name: &str, value: &str) {
+        let name_values = [
+            (&self.var1_name, &mut self.var1_value),
+            (&self.var2_name, &mut self.var2_value),
+            (&self.var3_name, &mut self.var3_value),
+            (&self.var4_name, &mut self.var4_value),
+            (&self.var5_name, &mut self.var5_value),
+        ];
+        for (var_name, var_value) in name_values {
+            if var_name.as_deref() == Some(name) && var_value.is_none() {
+                *var_value = Some(value.to_string());
+            }
+        }
+    }
+
+    /// Set the value of the variable named 'name', or add the
+    /// variable when there is an unused variable. Returns false when
+    /// all variables are used by other names.
+    pub fn set_variable_value(&mut self, name: &str, value: &str) -> bool {
+        let mut name_values = [
+            (&mut self.var1_name, &mut self.var1_value),
+            (&mut self.var2_name, &mut self.var2_value),
+            (&mut self.var3_name, &mut self.var3_value),
+            (&mut self.var4_name, &mut self.var4_value),
+            (&mut self.var5_name, &mut self.var5_value),
+        ];
+        let index = name_values
+            .iter()
+            .position(|(var_name, _)| var_name.as_deref() == Some(name))
+            .or_else(|| {
+                name_values
+                    .iter()
+                    .position(|(var_name, _)| var_name.is_none())
+            });
+        match index {
+            Some(index) => {
+                let (var_name, var_value) = &mut name_values[index];
+                **var_name = Some(name.to_string());
+                **var_value = Some(value.to_string());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Call 'replace' with the name and value of each variable with
+    /// a value, replacing the value when 'replace' returns a new
+    /// value.
+    pub fn replace_variable_values<F>(&mut self, mut replace: F)
+    where
+        F: FnMut(&str, &str) -> Option<String>,
+    {
+        let name_values = [
+            (&self.var1_name, &mut self.var1_value),
+            (&self.var2_name, &mut self.var2_value),
+            (&self.var3_name, &mut self.var3_value),
+            (&self.var4_name, &mut self.var4_value),
+            (&self.var5_name, &mut self.var5_value),
+        ];
+        for (var_name, var_value) in name_values {
+            if let (Some(name), Some(value)) = (var_name, var_value.as_ref()) {
+                if let Some(new_value) = replace(name, value) {
+                    *var_value = Some(new_value);
+                }
+            }
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Entry {
     pub utc_time_seconds: u64, // Assumed to be UTC time.
     pub duration_seconds: u64,
     pub status: EntryStatus,
     pub vars: EntryVariablesList,
+    /// The local timezone offset from UTC (in seconds, east of UTC
+    /// is positive) when the entry was recorded, if known.
+    pub utc_offset_seconds: Option<i32>,
 }
 
 impl Entry {
@@ -125,27 +260,375 @@ impl Entry {
             duration_seconds,
             status,
             vars,
+            utc_offset_seconds: None,
         }
     }
 
+    /// Set the local timezone offset from UTC when the entry was
+    /// recorded.
+    pub fn with_utc_offset_seconds(mut self, utc_offset_seconds: Option<i32>) -> Entry {
+        self.utc_offset_seconds = utc_offset_seconds;
+        self
+    }
+
+    /// Create an active entry for time away from the computer (such
+    /// as a meeting or phone call), described by 'note'.
+    pub fn new_manual(utc_time_seconds: u64, duration_seconds: u64, note: &str) -> Entry {
+        let mut vars = EntryVariablesList::empty();
+        vars.executable = Some(MANUAL_ENTRY_EXECUTABLE_NAME.to_string());
+        vars.var1_name = Some(MANUAL_ENTRY_NOTE_VARIABLE_NAME.to_string());
+        vars.var1_value = Some(note.to_string());
+        Entry::new(
+            utc_time_seconds,
+            duration_seconds,
+            EntryStatus::Active,
+            vars,
+        )
+    }
+
     pub fn empty() -> Entry {
         Entry {
             utc_time_seconds: 0_u64,
             duration_seconds: 0_u64,
             status: EntryStatus::Uninitialized,
             vars: EntryVariablesList::empty(),
+            utc_offset_seconds: None,
         }
     }
 }
 
+/// The version of an executable file, detected by the recorder.
+///
+/// The version is cached against the executable file path and the
+/// file's modification time, so a version is only probed once for
+/// each file installed on disk.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ExecutableVersion {
+    pub executable: String,
+    pub executable_path: String,
+    pub modified_time_seconds: u64,
+    pub version: Option<String>,
+    pub first_utc_time_seconds: u64, // Assumed to be UTC time.
+    pub last_utc_time_seconds: u64,  // Assumed to be UTC time.
+}
+
+impl ExecutableVersion {
+    pub fn new(
+        executable: String,
+        executable_path: String,
+        modified_time_seconds: u64,
+        version: Option<String>,
+        utc_time_seconds: u64,
+    ) -> ExecutableVersion {
+        ExecutableVersion {
+            executable,
+            executable_path,
+            modified_time_seconds,
+            version,
+            first_utc_time_seconds: utc_time_seconds,
+            last_utc_time_seconds: utc_time_seconds,
+        }
+    }
+}
+
+/// A free-text note written by the user for a specific (local) date.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DayNote {
+    pub date: chrono::NaiveDate,
+    pub note: String,
+}
+
+impl DayNote {
+    pub fn new(date: chrono::NaiveDate, note: String) -> DayNote {
+        DayNote { date, note }
+    }
+}
+
+/// The user's consent to recording, captured the first time the
+/// user is shown what is tracked.
+///
+/// The 'notice_version' is the version of the privacy notice the
+/// user agreed to, so consent can be asked again when the notice
+/// changes.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PrivacyConsent {
+    pub utc_time_seconds: u64, // Assumed to be UTC time.
+    pub notice_version: u32,
+}
+
+impl PrivacyConsent {
+    pub fn new(utc_time_seconds: u64, notice_version: u32) -> PrivacyConsent {
+        PrivacyConsent {
+            utc_time_seconds,
+            notice_version,
+        }
+    }
+}
+
+/// A record of an operation changing the database (such as writing
+/// or deleting entries), so accidental data loss can be traced in
+/// shared databases, and manual edits can be undone.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AuditLogEntry {
+    pub utc_time_seconds: u64, // Assumed to be UTC time.
+    pub user_name: String,
+    pub operation: String,
+    pub filter: String,
+    pub row_count: u64,
+    /// The identifier of the operation in the database file, or None
+    /// for operations logged by older versions.
+    pub id: Option<u64>,
+    /// The program making the change, such as "recorder", "edit" or
+    /// "dump" (importing entries).
+    pub source: Option<String>,
+    /// When the operation was undone, if it was.
+    pub undo_utc_time_seconds: Option<u64>,
+    /// The changed entries were saved, so the operation can be undone.
+    pub can_undo: bool,
+}
+
+impl AuditLogEntry {
+    pub fn new(
+        utc_time_seconds: u64,
+        user_name: String,
+        operation: String,
+        filter: String,
+        row_count: u64,
+    ) -> AuditLogEntry {
+        AuditLogEntry {
+            utc_time_seconds,
+            user_name,
+            operation,
+            filter,
+            row_count,
+            id: None,
+            source: None,
+            undo_utc_time_seconds: None,
+            can_undo: false,
+        }
+    }
+}
+
+/// The planned active duration of a value of a variable (such as a
+/// project) for a week, written by the user to compare with the
+/// recorded time.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct WeekPlan {
+    /// The first (local) date of the week, a Monday.
+    pub week_start_date: chrono::NaiveDate,
+    pub variable_name: String,
+    pub variable_value: String,
+    pub planned_seconds: u64,
+}
+
+impl WeekPlan {
+    pub fn new(
+        week_start_date: chrono::NaiveDate,
+        variable_name: String,
+        variable_value: String,
+        planned_seconds: u64,
+    ) -> WeekPlan {
+        WeekPlan {
+            week_start_date,
+            variable_name,
+            variable_value,
+            planned_seconds,
+        }
+    }
+}
+
+/// A period of time the machine was busy (such as running a local
+/// render), detected by the recorder, used to explain idle time.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct HostBusyPeriod {
+    pub utc_time_seconds: u64, // Assumed to be UTC time.
+    pub duration_seconds: u64,
+    pub reason: String,
+}
+
+impl HostBusyPeriod {
+    pub fn new(utc_time_seconds: u64, duration_seconds: u64, reason: String) -> HostBusyPeriod {
+        HostBusyPeriod {
+            utc_time_seconds,
+            duration_seconds,
+            reason,
+        }
+    }
+
+    pub fn end_utc_time_seconds(&self) -> u64 {
+        self.utc_time_seconds + self.duration_seconds
+    }
+
+    /// Extend this period to include 'other', if both have the same
+    /// reason and 'other' starts within 'gap_seconds' of the end of
+    /// this period. Returns true if the period was extended.
+    pub fn try_extend(&mut self, other: &HostBusyPeriod, gap_seconds: u64) -> bool {
+        let end_utc_time_seconds = self.end_utc_time_seconds();
+        let is_contiguous = other.utc_time_seconds >= self.utc_time_seconds
+            && other.utc_time_seconds <= end_utc_time_seconds + gap_seconds;
+        if self.reason != other.reason || !is_contiguous {
+            return false;
+        }
+        let new_end_utc_time_seconds =
+            std::cmp::max(end_utc_time_seconds, other.end_utc_time_seconds());
+        self.duration_seconds = new_end_utc_time_seconds - self.utc_time_seconds;
+        true
+    }
+}
+
+/// A period of time a window of an executable was focused, with the
+/// dominant color sampled from the window, so timelines can render
+/// a colored band per application.
+///
+/// Only the color is stored, never the pixels of the window.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct WindowColorPeriod {
+    pub utc_time_seconds: u64, // Assumed to be UTC time.
+    pub duration_seconds: u64,
+    pub executable: String,
+    /// The color as a 24-bit 0xRRGGBB value.
+    pub color: u32,
+}
+
+impl WindowColorPeriod {
+    pub fn new(
+        utc_time_seconds: u64,
+        duration_seconds: u64,
+        executable: String,
+        color: u32,
+    ) -> WindowColorPeriod {
+        WindowColorPeriod {
+            utc_time_seconds,
+            duration_seconds,
+            executable,
+            color,
+        }
+    }
+
+    pub fn end_utc_time_seconds(&self) -> u64 {
+        self.utc_time_seconds + self.duration_seconds
+    }
+
+    /// The color as a hex string, such as "#1f77b4".
+    pub fn color_hex(&self) -> String {
+        format!("#{:06x}", self.color & 0xffffff)
+    }
+
+    /// Extend this period to include 'other', if both have the same
+    /// executable and color, and 'other' starts within 'gap_seconds'
+    /// of the end of this period. Returns true if the period was
+    /// extended.
+    pub fn try_extend(&mut self, other: &WindowColorPeriod, gap_seconds: u64) -> bool {
+        let end_utc_time_seconds = self.end_utc_time_seconds();
+        let is_contiguous = other.utc_time_seconds >= self.utc_time_seconds
+            && other.utc_time_seconds <= end_utc_time_seconds + gap_seconds;
+        if self.executable != other.executable || self.color != other.color || !is_contiguous {
+            return false;
+        }
+        let new_end_utc_time_seconds =
+            std::cmp::max(end_utc_time_seconds, other.end_utc_time_seconds());
+        self.duration_seconds = new_end_utc_time_seconds - self.utc_time_seconds;
+        true
+    }
+}
+
+/// Shift a UTC time so it displays the same local (wall-clock) time
+/// using the current offset as it did using the recorded offset.
+///
+/// This is used to reconstruct the local day an entry was recorded in,
+/// after the machine's timezone has changed (or when the timezone
+/// data was stale when recording).
+pub fn shift_utc_time_to_recorded_offset(
+    utc_time_seconds: u64,
+    recorded_utc_offset_seconds: i32,
+    current_utc_offset_seconds: i32,
+) -> u64 {
+    let difference = recorded_utc_offset_seconds as i64 - current_utc_offset_seconds as i64;
+    (utc_time_seconds as i64 + difference).max(0) as u64
+}
+
+/// A jump of the wall clock longer than this many record intervals
+/// between two samples (such as when the machine was suspended) is a
+/// gap in the recording.
+pub const RECORDING_GAP_INTERVAL_COUNT: u64 = 5;
+
+/// Find the gap in the recording before a sample ending at
+/// 'sample_end_utc_time_seconds', when the previous sample ended more
+/// than 'gap_limit_seconds' before the sample would start (one record
+/// interval before the sample end).
+///
+/// Returns the start and end of the gap; the previous entry ends at
+/// the start of the gap (such as the time the machine was suspended)
+/// and a new entry starts at the end of the gap (when the machine was
+/// resumed), rather than an entry covering the gap.
+pub fn find_recording_gap(
+    previous_sample_end_utc_time_seconds: u64,
+    sample_end_utc_time_seconds: u64,
+    record_interval_seconds: u64,
+    gap_limit_seconds: u64,
+) -> Option<(u64, u64)> {
+    let gap_end_utc_time_seconds =
+        sample_end_utc_time_seconds.saturating_sub(record_interval_seconds);
+    let gap_seconds = gap_end_utc_time_seconds.saturating_sub(previous_sample_end_utc_time_seconds);
+    match gap_seconds > gap_limit_seconds {
+        true => Some((
+            previous_sample_end_utc_time_seconds,
+            gap_end_utc_time_seconds,
+        )),
+        false => None,
+    }
+}
+
+/// Combine the entries of multiple databases (such as copies of a
+/// database from different machines) in time order.
+///
+/// An entry found in more than one database (with the same start
+/// time, status and variables) is only kept once, using the longest
+/// duration, because a copy of a database may have been taken before
+/// the last entry was extended. Entries recorded at the same time
+/// with different variables (such as on another machine) are kept.
+pub fn merge_entries(mut entries: Vec<Entry>) -> Vec<Entry> {
+    entries.sort_by(|a, b| {
+        a.utc_time_seconds
+            .cmp(&b.utc_time_seconds)
+            .then_with(|| b.duration_seconds.cmp(&a.duration_seconds))
+    });
+
+    let mut merged_entries = Vec::<Entry>::with_capacity(entries.len());
+    let mut group_start_index = 0;
+    for entry in entries {
+        let is_same_time = merged_entries
+            .last()
+            .is_some_and(|x: &Entry| x.utc_time_seconds == entry.utc_time_seconds);
+        if !is_same_time {
+            group_start_index = merged_entries.len();
+        }
+
+        let is_duplicate = merged_entries[group_start_index..].iter().any(|x| {
+            x.status == entry.status
+                && x.vars == entry.vars
+                && x.utc_offset_seconds == entry.utc_offset_seconds
+        });
+        if !is_duplicate {
+            merged_entries.push(entry);
+        }
+    }
+    merged_entries
+}
+
 /// Remove duplicate values that repeat for multiple seconds in a row.
 ///
 /// Used to reduce the number of entries and save disk-space and processing
 /// time.
+///
+/// Merged entries are never longer than 'max_merged_entry_seconds';
+/// an entry that would make a merged entry longer is split at the
+/// boundary, so the time information is not lost.
 pub fn deduplicate_entries(
     last_entry: &Entry,
     entries: &Vec<Entry>,
     record_interval_seconds: u64,
+    max_merged_entry_seconds: u64,
     entries_dedup: &mut Vec<Entry>,
     entry_row_statuses: &mut Vec<RecordRowStatus>,
 ) {
@@ -162,24 +645,50 @@ pub fn deduplicate_entries(
 
     let mut last_index = 0;
     let mut last_index_mut = 0;
-    let mut last_entry_duration_seconds = last_entry.duration_seconds;
     let mut current_index = 1;
     while current_index < new_entries.len() {
         let last_entry = &new_entries[last_index];
         let current_entry = &new_entries[current_index];
 
-        let last_entry_time = last_entry.utc_time_seconds + last_entry_duration_seconds;
-        let current_entry_time = current_entry.utc_time_seconds + current_entry.duration_seconds;
-        if last_entry.status != EntryStatus::Uninitialized
-            && last_entry_time.abs_diff(current_entry_time) <= record_interval_seconds
-            && last_entry.status == current_entry.status
-            && last_entry.vars == current_entry.vars
-        {
-            entries_dedup[last_index_mut].duration_seconds += current_entry.duration_seconds;
-            last_entry_duration_seconds = entries_dedup[last_index_mut].duration_seconds;
+        let can_merge = last_entry.status != EntryStatus::Uninitialized && {
+            let merged_entry = &entries_dedup[last_index_mut];
+            let last_entry_time = merged_entry.utc_time_seconds + merged_entry.duration_seconds;
+            let current_entry_time =
+                current_entry.utc_time_seconds + current_entry.duration_seconds;
+            last_entry_time.abs_diff(current_entry_time) <= record_interval_seconds
+                && last_entry.status == current_entry.status
+                && last_entry.vars == current_entry.vars
+                && last_entry.utc_offset_seconds == current_entry.utc_offset_seconds
+        };
+
+        if can_merge {
+            let merged_entry = &mut entries_dedup[last_index_mut];
+            let merged_duration_seconds =
+                merged_entry.duration_seconds + current_entry.duration_seconds;
+            if merged_duration_seconds <= max_merged_entry_seconds {
+                merged_entry.duration_seconds = merged_duration_seconds;
+            } else {
+                // Fill the merged entry up to the maximum, and start a
+                // new entry with the remaining time.
+                let fill_duration_seconds = max_merged_entry_seconds
+                    .saturating_sub(merged_entry.duration_seconds)
+                    .min(current_entry.duration_seconds);
+                merged_entry.duration_seconds += fill_duration_seconds;
+
+                let mut split_entry = current_entry.clone();
+                split_entry.utc_time_seconds =
+                    merged_entry.utc_time_seconds + merged_entry.duration_seconds;
+                split_entry.duration_seconds =
+                    current_entry.duration_seconds - fill_duration_seconds;
+
+                last_index_mut = entries_dedup.len();
+                entries_dedup.push(split_entry);
+                entry_row_statuses.push(RecordRowStatus::New);
+
+                last_index = current_index;
+            }
         } else {
             last_index_mut = entries_dedup.len();
-            last_entry_duration_seconds = current_entry.duration_seconds;
             entries_dedup.push(current_entry.clone());
             entry_row_statuses.push(RecordRowStatus::New);
 
@@ -191,6 +700,7 @@ pub fn deduplicate_entries(
 }
 
 #[cfg(test)]
+#[allow(clippy::vec_init_then_push)]
 mod tests {
 
     use crate::entries::*;
@@ -219,10 +729,12 @@ mod tests {
         entries.push(Entry::new(123456791, 1, EntryStatus::Active, vars.clone()));
 
         let record_interval_seconds = 1;
+        let max_merged_entry_seconds = u64::MAX;
         deduplicate_entries(
             &last_entry,
             &entries,
             record_interval_seconds,
+            max_merged_entry_seconds,
             &mut entries_dedup,
             &mut entry_row_statuses,
         );
@@ -260,10 +772,12 @@ mod tests {
         entries.push(Entry::new(123456791, 1, EntryStatus::Active, vars.clone()));
 
         let record_interval_seconds = 1;
+        let max_merged_entry_seconds = u64::MAX;
         deduplicate_entries(
             &last_entry,
             &entries,
             record_interval_seconds,
+            max_merged_entry_seconds,
             &mut entries_dedup,
             &mut entry_row_statuses,
         );
@@ -325,10 +839,12 @@ mod tests {
         ));
 
         let record_interval_seconds = 1;
+        let max_merged_entry_seconds = u64::MAX;
         deduplicate_entries(
             &last_entry,
             &entries,
             record_interval_seconds,
+            max_merged_entry_seconds,
             &mut entries_dedup,
             &mut entry_row_statuses,
         );
@@ -392,10 +908,12 @@ mod tests {
         ));
 
         let record_interval_seconds = 1;
+        let max_merged_entry_seconds = u64::MAX;
         deduplicate_entries(
             &last_entry,
             &entries,
             record_interval_seconds,
+            max_merged_entry_seconds,
             &mut entries_dedup,
             &mut entry_row_statuses,
         );
@@ -435,10 +953,12 @@ mod tests {
         entries.push(Entry::new(123456801, 1, EntryStatus::Active, vars.clone()));
 
         let record_interval_seconds = 1;
+        let max_merged_entry_seconds = u64::MAX;
         deduplicate_entries(
             &last_entry,
             &entries,
             record_interval_seconds,
+            max_merged_entry_seconds,
             &mut entries_dedup,
             &mut entry_row_statuses,
         );
@@ -455,4 +975,269 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_deduplication_split_at_max_merged_duration() -> Result<()> {
+        let mut vars = EntryVariablesList::empty();
+        vars.executable = Some("bash".to_string());
+
+        let mut entries_dedup = Vec::<Entry>::new();
+        let mut entry_row_statuses = Vec::<RecordRowStatus>::new();
+
+        let last_entry = Entry::new(123456788, 2, EntryStatus::Active, vars.clone());
+
+        let entries = vec![
+            Entry::new(123456790, 2, EntryStatus::Active, vars.clone()),
+            Entry::new(123456792, 2, EntryStatus::Active, vars.clone()),
+            Entry::new(123456794, 2, EntryStatus::Active, vars.clone()),
+        ];
+
+        let record_interval_seconds = 2;
+        let max_merged_entry_seconds = 5;
+        deduplicate_entries(
+            &last_entry,
+            &entries,
+            record_interval_seconds,
+            max_merged_entry_seconds,
+            &mut entries_dedup,
+            &mut entry_row_statuses,
+        );
+
+        debug!("entries dedup: {:?}", entries_dedup);
+        debug!("entry_row_statuses: {:?}", entry_row_statuses);
+
+        assert_eq!(entries_dedup.len(), 2);
+        assert_eq!(entry_row_statuses.len(), 2);
+        assert_eq!(entries_dedup[0].utc_time_seconds, 123456788);
+        assert_eq!(entries_dedup[0].duration_seconds, 5);
+        assert_eq!(entries_dedup[1].utc_time_seconds, 123456793);
+        assert_eq!(entries_dedup[1].duration_seconds, 3);
+        assert_eq!(entry_row_statuses[0], RecordRowStatus::Existing);
+        assert_eq!(entry_row_statuses[1], RecordRowStatus::New);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_missing_variable_value() {
+        let mut vars = EntryVariablesList::empty();
+        vars.var1_name = Some("PWD".to_string());
+        vars.var1_value = Some("/home/user".to_string());
+        vars.var2_name = Some("PROJECT".to_string());
+
+        vars.set_missing_variable_value("PROJECT", "ACME");
+        vars.set_missing_variable_value("PWD", "/tmp");
+        vars.set_missing_variable_value("SHOT", "sh010");
+
+        assert_eq!(vars.var1_value, Some("/home/user".to_string()));
+        assert_eq!(vars.var2_value, Some("ACME".to_string()));
+        assert_eq!(vars.var3_value, None);
+    }
+
+    #[test]
+    fn test_set_variable_value() {
+        let mut vars = EntryVariablesList::empty();
+        vars.var1_name = Some("PWD".to_string());
+        vars.var1_value = Some("/projects/acme/shots".to_string());
+        vars.var2_name = Some("SHOW".to_string());
+
+        assert!(vars.set_variable_value("SHOW", "acme"));
+        assert!(vars.set_variable_value("SEQUENCE", "sq010"));
+        assert_eq!(vars.var2_value, Some("acme".to_string()));
+        assert_eq!(vars.var3_name, Some("SEQUENCE".to_string()));
+        assert_eq!(vars.var3_value, Some("sq010".to_string()));
+
+        vars.var4_name = Some("USER".to_string());
+        vars.var5_name = Some("TERM".to_string());
+        assert!(!vars.set_variable_value("SHOT", "sh010"));
+    }
+
+    #[test]
+    fn test_replace_variable_values() {
+        let mut vars = EntryVariablesList::empty();
+        vars.var1_name = Some("PWD".to_string());
+        vars.var1_value = Some("/home/user/secret-token".to_string());
+        vars.var2_name = Some("PROJECT".to_string());
+        vars.var2_value = Some("ACME".to_string());
+        vars.var3_name = Some("SHOT".to_string());
+
+        let mut names = Vec::new();
+        vars.replace_variable_values(|name, value| {
+            names.push(name.to_string());
+            match value.contains("secret") {
+                true => Some(FILTERED_VARIABLE_VALUE.to_string()),
+                false => None,
+            }
+        });
+
+        // Variables without a value are not replaced.
+        assert_eq!(names, vec!["PWD".to_string(), "PROJECT".to_string()]);
+        assert_eq!(vars.var1_value, Some(FILTERED_VARIABLE_VALUE.to_string()));
+        assert_eq!(vars.var2_value, Some("ACME".to_string()));
+        assert_eq!(vars.var3_value, None);
+    }
+
+    #[test]
+    fn test_host_busy_period_extend() {
+        let mut period = HostBusyPeriod::new(100, 10, "load".to_string());
+
+        let next = HostBusyPeriod::new(111, 1, "load".to_string());
+        assert!(period.try_extend(&next, 2));
+        assert_eq!(period.duration_seconds, 12);
+
+        let other_reason = HostBusyPeriod::new(112, 1, "blender".to_string());
+        assert!(!period.try_extend(&other_reason, 2));
+
+        let after_gap = HostBusyPeriod::new(120, 1, "load".to_string());
+        assert!(!period.try_extend(&after_gap, 2));
+        assert_eq!(period.end_utc_time_seconds(), 112);
+    }
+
+    #[test]
+    fn test_window_color_period_extend() {
+        let mut period = WindowColorPeriod::new(100, 10, "blender".to_string(), 0x1f77b4);
+        assert_eq!(period.color_hex(), "#1f77b4");
+
+        let next = WindowColorPeriod::new(111, 1, "blender".to_string(), 0x1f77b4);
+        assert!(period.try_extend(&next, 2));
+        assert_eq!(period.duration_seconds, 12);
+
+        let other_color = WindowColorPeriod::new(112, 1, "blender".to_string(), 0xff7f0e);
+        assert!(!period.try_extend(&other_color, 2));
+
+        let other_executable = WindowColorPeriod::new(112, 1, "nuke".to_string(), 0x1f77b4);
+        assert!(!period.try_extend(&other_executable, 2));
+        assert_eq!(period.end_utc_time_seconds(), 112);
+    }
+
+    #[test]
+    fn test_deduplication_utc_offset_changed() -> Result<()> {
+        let mut vars = EntryVariablesList::empty();
+        vars.executable = Some("bash".to_string());
+
+        let mut entries_dedup = Vec::<Entry>::new();
+        let mut entry_row_statuses = Vec::<RecordRowStatus>::new();
+
+        let last_entry = Entry::empty();
+        let entries = vec![
+            Entry::new(123456790, 1, EntryStatus::Active, vars.clone())
+                .with_utc_offset_seconds(Some(36000)),
+            Entry::new(123456791, 1, EntryStatus::Active, vars.clone())
+                .with_utc_offset_seconds(Some(36000)),
+            Entry::new(123456792, 1, EntryStatus::Active, vars.clone())
+                .with_utc_offset_seconds(Some(0)),
+        ];
+
+        deduplicate_entries(
+            &last_entry,
+            &entries,
+            1,
+            u64::MAX,
+            &mut entries_dedup,
+            &mut entry_row_statuses,
+        );
+
+        assert_eq!(entries_dedup.len(), 2);
+        assert_eq!(entries_dedup[0].duration_seconds, 2);
+        assert_eq!(entries_dedup[0].utc_offset_seconds, Some(36000));
+        assert_eq!(entries_dedup[1].duration_seconds, 1);
+        assert_eq!(entries_dedup[1].utc_offset_seconds, Some(0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_shift_utc_time_to_recorded_offset_near_midnight() {
+        // Recorded at 23:30 on 2024-03-10 in UTC+10:00 (13:30 UTC).
+        let recorded_utc_offset_seconds = 10 * 60 * 60;
+        let recorded_local = chrono::NaiveDate::from_ymd_opt(2024, 3, 10)
+            .unwrap()
+            .and_hms_opt(23, 30, 0)
+            .unwrap();
+        let utc_time_seconds =
+            (recorded_local.and_utc().timestamp() - recorded_utc_offset_seconds as i64) as u64;
+
+        // Reported on a machine now in UTC+12:00, the entry would be
+        // at 01:30 on 2024-03-11 without the shift. With the shift,
+        // the entry is displayed at the original local time and day.
+        let current_utc_offset_seconds = 12 * 60 * 60;
+        let shifted_utc_time_seconds = shift_utc_time_to_recorded_offset(
+            utc_time_seconds,
+            recorded_utc_offset_seconds,
+            current_utc_offset_seconds,
+        );
+        let displayed_local = chrono::DateTime::from_timestamp(
+            shifted_utc_time_seconds as i64 + current_utc_offset_seconds as i64,
+            0,
+        )
+        .unwrap()
+        .naive_utc();
+        assert_eq!(displayed_local, recorded_local);
+
+        // The same offset does not change the time.
+        assert_eq!(
+            shift_utc_time_to_recorded_offset(utc_time_seconds, 3600, 3600),
+            utc_time_seconds
+        );
+        // Times never go before the epoch.
+        assert_eq!(shift_utc_time_to_recorded_offset(10, -3600, 0), 0);
+    }
+
+    #[test]
+    fn test_merge_entries() {
+        let mut vars = EntryVariablesList::empty();
+        vars.executable = Some("blender".to_string());
+        let mut other_vars = EntryVariablesList::empty();
+        other_vars.executable = Some("nuke".to_string());
+
+        // The second database is a (later) copy of the first, and
+        // the third database is from another machine.
+        let first_entries = vec![
+            Entry::new(100, 50, EntryStatus::Active, vars.clone()),
+            Entry::new(150, 20, EntryStatus::Idle, vars.clone()),
+        ];
+        let second_entries = vec![
+            Entry::new(100, 50, EntryStatus::Active, vars.clone()),
+            Entry::new(150, 40, EntryStatus::Idle, vars.clone()),
+        ];
+        let third_entries = vec![Entry::new(100, 10, EntryStatus::Active, other_vars.clone())];
+
+        let mut entries = Vec::new();
+        entries.extend(first_entries);
+        entries.extend(third_entries);
+        entries.extend(second_entries);
+        let merged_entries = merge_entries(entries);
+
+        assert_eq!(merged_entries.len(), 3);
+        assert_eq!(merged_entries[0].vars, vars);
+        assert_eq!(merged_entries[0].duration_seconds, 50);
+        assert_eq!(merged_entries[1].vars, other_vars);
+        assert_eq!(merged_entries[2].utc_time_seconds, 150);
+        assert_eq!(merged_entries[2].duration_seconds, 40);
+    }
+
+    #[test]
+    fn test_find_recording_gap() {
+        let gap_limit_seconds = RECORDING_GAP_INTERVAL_COUNT;
+
+        // Contiguous samples, and a delayed timer, are not a gap.
+        assert_eq!(find_recording_gap(100, 101, 1, gap_limit_seconds), None);
+        assert_eq!(find_recording_gap(100, 104, 1, gap_limit_seconds), None);
+        // The wall clock going backwards is not a gap.
+        assert_eq!(find_recording_gap(100, 50, 1, gap_limit_seconds), None);
+
+        // A suspend overnight ends the previous entry at the suspend,
+        // and starts a new entry one interval before the sample end.
+        assert_eq!(
+            find_recording_gap(100, 28_900, 1, gap_limit_seconds),
+            Some((100, 28_899))
+        );
+        assert_eq!(
+            find_recording_gap(100, 200, 5, 5 * gap_limit_seconds),
+            Some((100, 195))
+        );
+
+        // A known suspend is a gap, however short.
+        assert_eq!(find_recording_gap(100, 103, 1, 0), Some((100, 102)));
+    }
 }
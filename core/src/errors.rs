@@ -0,0 +1,73 @@
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// The errors returned when reading or writing the database.
+#[derive(Debug, Error)]
+pub enum StorageError {
+    /// The database file does not exist, and was not created.
+    #[error("Database storage file does not exist: {}", .0.display())]
+    Missing(PathBuf),
+
+    /// The database is locked by another connection (such as the
+    /// recorder writing entries).
+    #[error("Database is locked: {0}")]
+    Locked(rusqlite::Error),
+
+    /// The database file is corrupt, or is not a database.
+    #[error("Database is corrupt: {0}")]
+    Corrupt(rusqlite::Error),
+
+    /// A value stored in (or given to) the database is invalid.
+    #[error("Invalid value: {0}")]
+    InvalidValue(String),
+
+    /// Any other database error.
+    #[error("Database error: {0}")]
+    Sqlite(rusqlite::Error),
+
+    /// A remote storage server could not be reached, or returned an
+    /// error.
+    #[error("Remote storage error: {0}")]
+    Remote(String),
+
+    #[error("Could not get the current time: {0}")]
+    Time(#[from] std::time::SystemTimeError),
+
+    /// A file (such as a backup file) could not be created.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(err: rusqlite::Error) -> Self {
+        match err.sqlite_error_code() {
+            Some(rusqlite::ErrorCode::DatabaseBusy) | Some(rusqlite::ErrorCode::DatabaseLocked) => {
+                StorageError::Locked(err)
+            }
+            Some(rusqlite::ErrorCode::DatabaseCorrupt)
+            | Some(rusqlite::ErrorCode::NotADatabase) => StorageError::Corrupt(err),
+            _ => StorageError::Sqlite(err),
+        }
+    }
+}
+
+/// The errors returned when settings are invalid.
+#[derive(Debug, Error)]
+pub enum SettingsError {
+    /// The configuration could not be read or deserialized.
+    #[error(transparent)]
+    Config(#[from] config::ConfigError),
+
+    /// A setting value is invalid.
+    #[error("{0}")]
+    InvalidValue(String),
+
+    /// The configuration file could not be read or written.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    /// The configuration file is not valid TOML, so it cannot be
+    /// changed.
+    #[error(transparent)]
+    Toml(#[from] toml_edit::TomlError),
+}
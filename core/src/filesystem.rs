@@ -1,10 +1,30 @@
+use chrono::Datelike;
+use chrono::TimeZone;
 use dirs;
-use log::{debug, error};
+use log::{debug, error, warn};
 use shellexpand;
+use std::path::Path;
 use std::path::PathBuf;
 
-/// Search for an existing file in the home directory, config
-/// directory and user directory override.
+/// The environment variable overriding the directory of the
+/// configuration and database files, for environments without a
+/// home directory (such as containers, or systemd services with
+/// 'DynamicUser').
+pub const TIMETRACKER_HOME_ENV_VAR_NAME: &str = "TIMETRACKER_HOME";
+
+/// The directory given by the "TIMETRACKER_HOME" environment
+/// variable, if it is set.
+pub fn get_timetracker_home_directory_path() -> Option<PathBuf> {
+    let value = std::env::var(TIMETRACKER_HOME_ENV_VAR_NAME).ok()?;
+    if value.is_empty() {
+        return None;
+    }
+    let value = shellexpand::full(&value).ok()?.into_owned();
+    Some(PathBuf::from(value))
+}
+
+/// Search for an existing file in the user directory override,
+/// "TIMETRACKER_HOME", config directory and home directory.
 pub fn find_existing_file_path(user_dir_path: Option<String>, file_name: &str) -> Option<PathBuf> {
     if let Some(value) = user_dir_path {
         let value = shellexpand::full(&value).ok()?.into_owned();
@@ -16,6 +36,13 @@ pub fn find_existing_file_path(user_dir_path: Option<String>, file_name: &str) -
         }
     }
 
+    if let Some(value) = get_timetracker_home_directory_path() {
+        let path = value.join(file_name);
+        if path.is_file() {
+            return Some(path);
+        }
+    }
+
     // $XDG_CONFIG_HOME or $HOME/.config (on Linux)
     if let Some(value) = dirs::config_dir() {
         let mut path = PathBuf::new();
@@ -40,7 +67,15 @@ pub fn find_existing_file_path(user_dir_path: Option<String>, file_name: &str) -
 }
 
 /// Search for an existing default configuration directory.
+///
+/// The "TIMETRACKER_HOME" directory is used when it is set, even
+/// when it does not exist yet, so the error of a mistyped path is
+/// reported when the directory is used.
 pub fn find_existing_configuration_directory_path() -> Option<PathBuf> {
+    if let Some(value) = get_timetracker_home_directory_path() {
+        return Some(value);
+    }
+
     // $XDG_CONFIG_HOME or $HOME/.config (on Linux)
     if let Some(value) = dirs::config_dir() {
         let mut path = PathBuf::new();
@@ -62,6 +97,25 @@ pub fn find_existing_configuration_directory_path() -> Option<PathBuf> {
     None
 }
 
+/// Find the default directory of the configuration and database
+/// files, falling back to the current directory when there is no
+/// home (or configuration) directory.
+///
+/// None is returned when no directory can be found at all.
+pub fn find_default_directory_path() -> Option<PathBuf> {
+    if let Some(value) = find_existing_configuration_directory_path() {
+        return Some(value);
+    }
+
+    let current_dir = std::env::current_dir().ok()?;
+    warn!(
+        "No home directory found; using the current directory {:?}. \
+         Set {} (or give '--database-dir') to choose the directory.",
+        current_dir, TIMETRACKER_HOME_ENV_VAR_NAME
+    );
+    Some(current_dir)
+}
+
 pub fn construct_file_path(user_dir_path: &Option<String>, file_name: &str) -> Option<PathBuf> {
     if let Some(value) = user_dir_path {
         let value = shellexpand::full(&value).ok()?.into_owned();
@@ -93,3 +147,106 @@ pub fn get_database_file_path(
     }
     database_file_path
 }
+
+/// Get the file name of the database for a single year, such as
+/// ".timetracker-2025.sqlite3" for ".timetracker.sqlite3".
+///
+/// The year is inserted before the file extension.
+pub fn get_year_database_file_name(database_file_name: &str, year: i32) -> String {
+    // A leading '.' (hidden file) is not the start of an extension.
+    let search_start = usize::from(database_file_name.starts_with('.'));
+    match database_file_name[search_start..].find('.') {
+        Some(index) => {
+            let (stem, extension) = database_file_name.split_at(search_start + index);
+            format!("{}-{}{}", stem, year, extension)
+        }
+        None => format!("{}-{}", database_file_name, year),
+    }
+}
+
+/// Get the file path of the database for a single year, in the same
+/// directory as the (un-rotated) database file path.
+pub fn get_year_database_file_path(database_file_path: &Path, year: i32) -> PathBuf {
+    let file_name = database_file_path
+        .file_name()
+        .map(|x| x.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    database_file_path.with_file_name(get_year_database_file_name(&file_name, year))
+}
+
+fn utc_seconds_to_local_year(utc_time_seconds: u64) -> i32 {
+    chrono::Local
+        .timestamp_opt(utc_time_seconds as i64, 0)
+        .earliest()
+        .map_or(1970, |x| x.year())
+}
+
+/// Get the database file path to write the data recorded at
+/// 'utc_time_seconds'.
+///
+/// When 'database_per_year' is enabled, each year is written to a
+/// different file, so the recorder switches files at the year
+/// boundary.
+pub fn get_database_file_path_for_time(
+    database_file_path: &Path,
+    database_per_year: bool,
+    utc_time_seconds: u64,
+) -> PathBuf {
+    match database_per_year {
+        true => {
+            let year = utc_seconds_to_local_year(utc_time_seconds);
+            get_year_database_file_path(database_file_path, year)
+        }
+        false => database_file_path.to_path_buf(),
+    }
+}
+
+/// Get the file path of the archive database for a single year, in
+/// the archive directory, such as
+/// "<archive_dir>/.timetracker-2025.sqlite3".
+pub fn get_archive_database_file_path(
+    archive_dir: &Path,
+    database_file_name: &str,
+    year: i32,
+) -> PathBuf {
+    archive_dir.join(get_year_database_file_name(database_file_name, year))
+}
+
+/// Get all the database file paths that may contain data between
+/// the start and end times; the (un-rotated) database file path,
+/// followed by each year's database file path, and each year's
+/// archive database file path (when 'archive_dir' is not empty).
+///
+/// The un-rotated database file is always included, so data recorded
+/// before 'database_per_year' was enabled can still be read.
+pub fn get_database_file_paths_for_range(
+    database_file_path: &Path,
+    database_per_year: bool,
+    archive_dir: &str,
+    start_utc_time_seconds: u64,
+    end_utc_time_seconds: u64,
+) -> Vec<PathBuf> {
+    let start_year = utc_seconds_to_local_year(start_utc_time_seconds);
+    let end_year = utc_seconds_to_local_year(end_utc_time_seconds);
+
+    let mut database_file_paths = vec![database_file_path.to_path_buf()];
+    if database_per_year {
+        for year in start_year..=end_year {
+            database_file_paths.push(get_year_database_file_path(database_file_path, year));
+        }
+    }
+    if !archive_dir.is_empty() {
+        let database_file_name = database_file_path
+            .file_name()
+            .map(|x| x.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        for year in start_year..=end_year {
+            database_file_paths.push(get_archive_database_file_path(
+                Path::new(archive_dir),
+                &database_file_name,
+                year,
+            ));
+        }
+    }
+    database_file_paths
+}
@@ -1,4 +1,5 @@
 use chrono;
+use chrono::Datelike;
 use chrono::TimeZone;
 use clap::ValueEnum;
 use config::ValueKind;
@@ -64,6 +65,31 @@ impl From<DurationFormat> for ValueKind {
     }
 }
 
+/// The calendar periods a displayed range can be split into, so the
+/// same presets are generated once for each period.
+#[derive(Debug, Copy, Clone, ValueEnum, Serialize, Deserialize)]
+pub enum Granularity {
+    /// Each day, 00:00 AM to 23:59 PM.
+    Day,
+
+    /// Each week, starting on the first day of the week (usually
+    /// Monday).
+    Week,
+
+    /// Each calendar month.
+    Month,
+}
+
+impl fmt::Display for Granularity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Granularity::Day => write!(f, "Day"),
+            Granularity::Week => write!(f, "Week"),
+            Granularity::Month => write!(f, "Month"),
+        }
+    }
+}
+
 /// The options for representing a duration of time.
 #[derive(Debug, Copy, Clone, ValueEnum, Serialize, Deserialize)]
 pub enum TimeScale {
@@ -74,6 +100,13 @@ pub enum TimeScale {
     /// A week duration (usually Monday to Sunday), split into each day
     /// 00:00 AM) to 23:59 PM.
     Weekday,
+
+    /// Two weeks; the week being displayed and the previous week.
+    Fortnight,
+
+    /// A calendar month duration, from the first day of the month at
+    /// 00:00 AM to the last day of the month at 23:59 PM.
+    Month,
 }
 
 impl fmt::Display for TimeScale {
@@ -83,6 +116,8 @@ impl fmt::Display for TimeScale {
             TimeScale::Weekday => {
                 write!(f, "Weekday")
             }
+            TimeScale::Fortnight => write!(f, "Fortnight"),
+            TimeScale::Month => write!(f, "Month"),
         }
     }
 }
@@ -137,6 +172,67 @@ pub fn format_duration(duration: chrono::Duration, duration_format: DurationForm
     }
 }
 
+/// Parse a duration written by the user, such as "20h", "7.5h",
+/// "1h30m" or "90m". A number without a unit is a number of hours.
+pub fn parse_duration(text: &str) -> Option<chrono::Duration> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    if let Ok(hours) = text.parse::<f64>() {
+        return hours_to_duration(hours);
+    }
+
+    let mut total_seconds = 0.0;
+    let mut number = String::new();
+    for c in text.chars() {
+        match c {
+            '0'..='9' | '.' => number.push(c),
+            'h' | 'm' | 's' => {
+                let value = number.parse::<f64>().ok()?;
+                let unit_seconds = match c {
+                    'h' => 60.0 * 60.0,
+                    'm' => 60.0,
+                    _ => 1.0,
+                };
+                total_seconds += value * unit_seconds;
+                number.clear();
+            }
+            ' ' => (),
+            _ => return None,
+        }
+    }
+    if !number.is_empty() {
+        return None;
+    }
+    Some(chrono::Duration::seconds(total_seconds.round() as i64))
+}
+
+/// Parse a local date and time written by the user, as
+/// "YYYY-MM-DD HH:MM", or "HH:MM" for today.
+pub fn parse_local_datetime(text: &str) -> Option<chrono::DateTime<chrono::Local>> {
+    let text = text.trim();
+    let naive_datetime = match chrono::NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M") {
+        Ok(value) => value,
+        Err(_) => {
+            let time = chrono::NaiveTime::parse_from_str(text, "%H:%M").ok()?;
+            chrono::Local::now().date_naive().and_time(time)
+        }
+    };
+    chrono::Local
+        .from_local_datetime(&naive_datetime)
+        .earliest()
+}
+
+fn hours_to_duration(hours: f64) -> Option<chrono::Duration> {
+    if !hours.is_finite() || hours < 0.0 {
+        return None;
+    }
+    Some(chrono::Duration::seconds(
+        (hours * 60.0 * 60.0).round() as i64
+    ))
+}
+
 pub fn format_time_no_seconds<Tz: TimeZone>(
     datetime: chrono::DateTime<Tz>,
     datetime_format: DateTimeFormat,
@@ -253,6 +349,14 @@ pub enum PrintType {
     Activity,
     Variables,
     Software,
+    Utilization,
+    Overtime,
+    WindowTitle,
+    Churn,
+    WorkingDirectory,
+    DocumentPath,
+    Plan,
+    Statistics,
 }
 
 impl fmt::Display for PrintType {
@@ -264,6 +368,14 @@ impl fmt::Display for PrintType {
             }
             PrintType::Variables => write!(f, "Variables"),
             PrintType::Software => write!(f, "Software"),
+            PrintType::Utilization => write!(f, "Utilization"),
+            PrintType::Overtime => write!(f, "Overtime"),
+            PrintType::WindowTitle => write!(f, "WindowTitle"),
+            PrintType::Churn => write!(f, "Churn"),
+            PrintType::WorkingDirectory => write!(f, "WorkingDirectory"),
+            PrintType::DocumentPath => write!(f, "DocumentPath"),
+            PrintType::Plan => write!(f, "Plan"),
+            PrintType::Statistics => write!(f, "Statistics"),
         }
     }
 }
@@ -274,6 +386,142 @@ impl From<PrintType> for ValueKind {
     }
 }
 
+/// What the recorder does with the oldest buffered entries, when
+/// the buffer is full because the database cannot be written to.
+#[derive(Debug, Copy, Clone, PartialEq, ValueEnum, Serialize, Deserialize)]
+pub enum BufferOverflowPolicy {
+    /// Discard the oldest entries (and count them).
+    DropOldest,
+
+    /// Append the oldest entries to a journal file, which is written
+    /// to the database once the database can be written again.
+    SpillToJournal,
+}
+
+impl fmt::Display for BufferOverflowPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BufferOverflowPolicy::DropOldest => write!(f, "DropOldest"),
+            BufferOverflowPolicy::SpillToJournal => write!(f, "SpillToJournal"),
+        }
+    }
+}
+
+impl From<BufferOverflowPolicy> for ValueKind {
+    fn from(value: BufferOverflowPolicy) -> Self {
+        ValueKind::String(format!("{}", value))
+    }
+}
+
+/// The order the aggregated durations (such as of each executable,
+/// or variable value) are printed in.
+#[derive(Debug, Default, Copy, Clone, PartialEq, ValueEnum, Serialize, Deserialize)]
+pub enum SortBy {
+    /// Alphabetical order of the names.
+    #[default]
+    Name,
+
+    /// The longest duration first.
+    Duration,
+}
+
+impl fmt::Display for SortBy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SortBy::Name => write!(f, "Name"),
+            SortBy::Duration => write!(f, "Duration"),
+        }
+    }
+}
+
+impl From<SortBy> for ValueKind {
+    fn from(value: SortBy) -> Self {
+        ValueKind::String(format!("{}", value))
+    }
+}
+
+/// The day each week starts on, such as to align reports with the
+/// weeks of a payroll. Weeks are numbered by the ISO week (starting
+/// on Monday) that the start of the week is in, or that follows it.
+#[derive(Debug, Default, Copy, Clone, PartialEq, ValueEnum, Serialize, Deserialize)]
+pub enum FirstDayOfWeek {
+    #[default]
+    Monday,
+    Sunday,
+    Saturday,
+}
+
+impl FirstDayOfWeek {
+    pub fn weekday(self) -> chrono::Weekday {
+        match self {
+            FirstDayOfWeek::Monday => chrono::Weekday::Mon,
+            FirstDayOfWeek::Sunday => chrono::Weekday::Sun,
+            FirstDayOfWeek::Saturday => chrono::Weekday::Sat,
+        }
+    }
+
+    /// The number of days from the first day of the week to the
+    /// (following) Monday.
+    pub fn days_before_monday(self) -> u32 {
+        (7 - self.weekday().num_days_from_monday()) % 7
+    }
+
+    /// The number of days since the first day of the week, from '0'
+    /// to '6'.
+    pub fn num_days_from_first_day(self, weekday: chrono::Weekday) -> u32 {
+        (weekday.num_days_from_monday() + self.days_before_monday()) % 7
+    }
+
+    /// The first date of the week that 'date' is in.
+    pub fn week_start_date(self, date: chrono::NaiveDate) -> chrono::NaiveDate {
+        date - chrono::Duration::days(self.num_days_from_first_day(date.weekday()).into())
+    }
+}
+
+impl fmt::Display for FirstDayOfWeek {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FirstDayOfWeek::Monday => write!(f, "Monday"),
+            FirstDayOfWeek::Sunday => write!(f, "Sunday"),
+            FirstDayOfWeek::Saturday => write!(f, "Saturday"),
+        }
+    }
+}
+
+impl From<FirstDayOfWeek> for ValueKind {
+    fn from(value: FirstDayOfWeek) -> Self {
+        ValueKind::String(format!("{}", value))
+    }
+}
+
+/// What the recorder does with activity matching the
+/// 'recorder.ignore' settings.
+#[derive(Debug, Default, Copy, Clone, PartialEq, ValueEnum, Serialize, Deserialize)]
+pub enum IgnoreAction {
+    /// Record the time, as a generic "<ignored>" executable without
+    /// any variables.
+    #[default]
+    Record,
+
+    /// Do not record the time at all.
+    Skip,
+}
+
+impl fmt::Display for IgnoreAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IgnoreAction::Record => write!(f, "Record"),
+            IgnoreAction::Skip => write!(f, "Skip"),
+        }
+    }
+}
+
+impl From<IgnoreAction> for ValueKind {
+    fn from(value: IgnoreAction) -> Self {
+        ValueKind::String(format!("{}", value))
+    }
+}
+
 #[derive(Debug, Copy, Clone, ValueEnum, Serialize, Deserialize)]
 pub enum ColorMode {
     Auto,
@@ -450,4 +698,54 @@ mod tests {
         let datetime_text = format_datetime(datetime, DateTimeFormat::UsaMonthDayYear);
         assert_eq!(datetime_text, "07/08/2016 09:10:11 AM");
     }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("20h"), Some(chrono::Duration::hours(20)));
+        assert_eq!(parse_duration("7.5h"), Some(chrono::Duration::minutes(450)));
+        assert_eq!(parse_duration("1h30m"), Some(chrono::Duration::minutes(90)));
+        assert_eq!(parse_duration("90m"), Some(chrono::Duration::minutes(90)));
+        assert_eq!(parse_duration("10"), Some(chrono::Duration::hours(10)));
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("h"), None);
+        assert_eq!(parse_duration("20x"), None);
+        assert_eq!(parse_duration("-2h"), None);
+    }
+
+    #[test]
+    fn test_parse_local_datetime() {
+        let datetime = parse_local_datetime("2024-01-03 09:30").unwrap();
+        assert_eq!(
+            datetime.naive_local(),
+            chrono::NaiveDate::from_ymd_opt(2024, 1, 3)
+                .unwrap()
+                .and_hms_opt(9, 30, 0)
+                .unwrap()
+        );
+        let datetime = parse_local_datetime(" 17:05 ").unwrap();
+        assert_eq!(datetime.date_naive(), chrono::Local::now().date_naive());
+        assert_eq!(
+            datetime.time(),
+            chrono::NaiveTime::from_hms_opt(17, 5, 0).unwrap()
+        );
+        assert_eq!(parse_local_datetime("2024-01-03"), None);
+        assert_eq!(parse_local_datetime("25:00"), None);
+    }
+
+    #[test]
+    fn test_first_day_of_week_start_date() {
+        // 2024-01-03 is a Wednesday.
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 1, 3).unwrap();
+        let expected = |day| chrono::NaiveDate::from_ymd_opt(2024, 1, day).unwrap();
+        assert_eq!(FirstDayOfWeek::Monday.week_start_date(date), expected(1));
+        let expected = |day| chrono::NaiveDate::from_ymd_opt(2023, 12, day).unwrap();
+        assert_eq!(FirstDayOfWeek::Sunday.week_start_date(date), expected(31));
+        assert_eq!(FirstDayOfWeek::Saturday.week_start_date(date), expected(30));
+
+        // The first day is the start of its own week.
+        let date = chrono::NaiveDate::from_ymd_opt(2023, 12, 30).unwrap();
+        assert_eq!(FirstDayOfWeek::Saturday.week_start_date(date), date);
+        assert_eq!(FirstDayOfWeek::Sunday.days_before_monday(), 1);
+        assert_eq!(FirstDayOfWeek::Saturday.days_before_monday(), 2);
+    }
 }
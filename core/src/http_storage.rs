@@ -0,0 +1,138 @@
+use crate::entries::Entry;
+use crate::errors::StorageError;
+use crate::storage::utc_seconds_to_datetime_local;
+use crate::storage::Entries;
+use crate::storage::StorageBackend;
+use log::debug;
+use std::path::Path;
+
+/// The URL path of the entries, on a 'timetracker-server'.
+///
+/// Entries are written by POSTing a JSON array of entries, and read
+/// by GETting the range of
+/// "?start_utc_time_seconds=...&end_utc_time_seconds=...".
+pub const SYNC_ENTRIES_URL_PATH: &str = "/entries";
+
+/// How long a request to the server may take, so an unreachable
+/// server does not block the caller for long.
+const REQUEST_TIMEOUT_SECONDS: u64 = 5;
+
+/// The HTTP header used to authorize requests, with the
+/// "Bearer <token>" value.
+pub const SYNC_AUTHORIZATION_HEADER: &str = "Authorization";
+
+fn remote_error(err: ureq::Error) -> StorageError {
+    match err {
+        ureq::Error::Status(code, response) => StorageError::Remote(format!(
+            "Server returned status {} ({}).",
+            code,
+            response.status_text()
+        )),
+        ureq::Error::Transport(transport) => StorageError::Remote(transport.to_string()),
+    }
+}
+
+/// Stores entries on a 'timetracker-server', so the entries of
+/// multiple computers can be read as a single consolidated
+/// database.
+///
+/// Entries that could not be written stay pending, and are written
+/// with the next entries.
+pub struct HttpStorage {
+    agent: ureq::Agent,
+    host: String,
+    token: String,
+    entries: Vec<Entry>,
+}
+
+impl HttpStorage {
+    /// The 'host' is the URL of the server, such as
+    /// "http://example.com:9797". An empty 'token' sends requests
+    /// without authorization.
+    pub fn new(host: &str, token: &str) -> HttpStorage {
+        HttpStorage {
+            agent: ureq::AgentBuilder::new()
+                .timeout(std::time::Duration::from_secs(REQUEST_TIMEOUT_SECONDS))
+                .build(),
+            host: host.trim_end_matches('/').to_string(),
+            token: token.to_string(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// The number of inserted entries not yet written.
+    pub fn pending_entry_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Drop the oldest pending entries, so no more than
+    /// 'max_entries' are pending. Returns the number of entries
+    /// dropped.
+    pub fn limit_pending_entries(&mut self, max_entries: usize) -> usize {
+        let count = self.entries.len().saturating_sub(max_entries);
+        self.entries.drain(..count);
+        count
+    }
+
+    fn request(&self, method: &str) -> ureq::Request {
+        let url = format!("{}{}", self.host, SYNC_ENTRIES_URL_PATH);
+        let request = self.agent.request(method, &url);
+        match self.token.is_empty() {
+            true => request,
+            false => request.set(SYNC_AUTHORIZATION_HEADER, &format!("Bearer {}", self.token)),
+        }
+    }
+}
+
+impl StorageBackend for HttpStorage {
+    /// The file path is used as the host (URL) of the server, without
+    /// a token.
+    fn open(
+        database_file_path: &Path,
+        _record_interval_seconds: u64,
+        _auto_create_database_file: bool,
+    ) -> Result<HttpStorage, StorageError> {
+        Ok(HttpStorage::new(&database_file_path.to_string_lossy(), ""))
+    }
+
+    fn insert_entries(&mut self, entries: &[Entry]) {
+        self.entries.extend_from_slice(entries);
+    }
+
+    fn read_entries(
+        &mut self,
+        start_utc_time_seconds: u64,
+        end_utc_time_seconds: u64,
+    ) -> Result<Entries, StorageError> {
+        let entries: Vec<Entry> = self
+            .request("GET")
+            .query(
+                "start_utc_time_seconds",
+                &start_utc_time_seconds.to_string(),
+            )
+            .query("end_utc_time_seconds", &end_utc_time_seconds.to_string())
+            .call()
+            .map_err(remote_error)?
+            .into_json()
+            .map_err(|err| StorageError::Remote(format!("Invalid response: {}", err)))?;
+        debug!("Read {} entries from {:?}.", entries.len(), self.host);
+
+        Ok(Entries::builder()
+            .start_datetime(utc_seconds_to_datetime_local(start_utc_time_seconds))
+            .end_datetime(utc_seconds_to_datetime_local(end_utc_time_seconds))
+            .entries(entries)
+            .build())
+    }
+
+    fn write_entries(&mut self) -> Result<(), StorageError> {
+        if self.entries.is_empty() {
+            return Ok(());
+        }
+        self.request("POST")
+            .send_json(&self.entries)
+            .map_err(remote_error)?;
+        debug!("Written {} entries to {:?}.", self.entries.len(), self.host);
+        self.entries.clear();
+        Ok(())
+    }
+}
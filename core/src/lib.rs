@@ -1,14 +1,17 @@
 #[macro_use]
 extern crate num_derive;
 
-use log::debug;
-use terminfo;
-
 pub mod entries;
+pub mod errors;
 pub mod filesystem;
 pub mod format;
+pub mod http_storage;
+pub mod memory_storage;
+pub mod multi_storage;
+pub mod schema;
 pub mod settings;
 pub mod storage;
+pub mod term;
 
 /// Removes flags from the executable command name. Only the
 /// executable file path should be retained.
@@ -39,15 +42,3 @@ pub fn format_short_executable_name(name: &str) -> &str {
         None => strip_end,
     }
 }
-
-pub fn terminal_supports_color() -> bool {
-    let info = terminfo::Database::from_env().unwrap();
-    let terminal_max_colors = info.get::<terminfo::capability::MaxColors>();
-    debug!("terminal_max_colors={:?}", terminal_max_colors);
-    let color_is_supported = match terminal_max_colors {
-        Some(n) => n.0 > 0,
-        None => false,
-    };
-    debug!("terminal_supports_color={}", color_is_supported);
-    color_is_supported
-}
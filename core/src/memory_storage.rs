@@ -0,0 +1,200 @@
+use crate::entries::deduplicate_entries;
+use crate::entries::Entry;
+use crate::entries::RecordRowStatus;
+use crate::errors::StorageError;
+use crate::settings::DEFAULT_MAX_MERGED_ENTRY_SECONDS;
+use crate::storage::clamp_entry_time_range;
+use crate::storage::is_entry_in_time_range;
+use crate::storage::utc_seconds_to_datetime_local;
+use crate::storage::Entries;
+use crate::storage::StorageBackend;
+use log::debug;
+use std::path::Path;
+
+/// Stores entries in memory, without touching the filesystem, such
+/// as for tests.
+///
+/// Entries are merged the same as 'Storage'. Day notes and recorded
+/// periods are not stored.
+#[derive(Debug, Clone)]
+pub struct MemoryStorage {
+    written_entries: Vec<Entry>,
+    entries: Vec<Entry>,
+    record_interval_seconds: u64,
+    max_merged_entry_seconds: u64,
+}
+
+impl MemoryStorage {
+    pub fn new(record_interval_seconds: u64) -> MemoryStorage {
+        MemoryStorage {
+            written_entries: Vec::new(),
+            entries: Vec::new(),
+            record_interval_seconds,
+            max_merged_entry_seconds: DEFAULT_MAX_MERGED_ENTRY_SECONDS,
+        }
+    }
+
+    /// Set the maximum duration of an entry, when entries are merged
+    /// together by 'write_entries'.
+    pub fn set_max_merged_entry_seconds(&mut self, value: u64) {
+        self.max_merged_entry_seconds = value;
+    }
+
+    /// All the written entries, in the order they were written.
+    pub fn written_entries(&self) -> &[Entry] {
+        &self.written_entries[..]
+    }
+}
+
+impl StorageBackend for MemoryStorage {
+    /// The file path is ignored; the storage always starts empty.
+    fn open(
+        _database_file_path: &Path,
+        record_interval_seconds: u64,
+        _auto_create_database_file: bool,
+    ) -> Result<MemoryStorage, StorageError> {
+        Ok(MemoryStorage::new(record_interval_seconds))
+    }
+
+    fn insert_entries(&mut self, entries: &[Entry]) {
+        for entry in entries {
+            debug!("Insert Entry: {:?}", entry);
+            self.entries.push(entry.clone());
+        }
+    }
+
+    fn read_entries(
+        &mut self,
+        start_utc_time_seconds: u64,
+        end_utc_time_seconds: u64,
+    ) -> Result<Entries, StorageError> {
+        let entries = self
+            .written_entries
+            .iter()
+            .filter(|entry| {
+                is_entry_in_time_range(
+                    entry.utc_time_seconds,
+                    entry.duration_seconds,
+                    start_utc_time_seconds,
+                    end_utc_time_seconds,
+                )
+            })
+            .map(|entry| {
+                let (utc_time_seconds, duration_seconds) = clamp_entry_time_range(
+                    entry.utc_time_seconds,
+                    entry.duration_seconds,
+                    start_utc_time_seconds,
+                    end_utc_time_seconds,
+                );
+                let mut entry = entry.clone();
+                entry.utc_time_seconds = utc_time_seconds;
+                entry.duration_seconds = duration_seconds;
+                entry
+            })
+            .collect();
+
+        Ok(Entries::builder()
+            .start_datetime(utc_seconds_to_datetime_local(start_utc_time_seconds))
+            .end_datetime(utc_seconds_to_datetime_local(end_utc_time_seconds))
+            .entries(entries)
+            .build())
+    }
+
+    fn write_entries(&mut self) -> Result<(), StorageError> {
+        let last_entry = self
+            .written_entries
+            .last()
+            .cloned()
+            .unwrap_or_else(Entry::empty);
+
+        let mut entries_dedup = Vec::<Entry>::new();
+        let mut entry_row_statuses = Vec::<RecordRowStatus>::new();
+        deduplicate_entries(
+            &last_entry,
+            &self.entries,
+            self.record_interval_seconds,
+            self.max_merged_entry_seconds,
+            &mut entries_dedup,
+            &mut entry_row_statuses,
+        );
+
+        for (entry, row_status) in entries_dedup.into_iter().zip(entry_row_statuses) {
+            match row_status {
+                // The last written entry, extended by the new entries.
+                RecordRowStatus::Existing => {
+                    if let Some(last_written_entry) = self.written_entries.last_mut() {
+                        *last_written_entry = entry;
+                    }
+                }
+                RecordRowStatus::New => self.written_entries.push(entry),
+            }
+        }
+        self.entries.clear();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entries::EntryStatus;
+    use crate::entries::EntryVariablesList;
+
+    fn new_entry(utc_time_seconds: u64, executable: &str) -> Entry {
+        let mut vars = EntryVariablesList::empty();
+        vars.executable = Some(executable.to_string());
+        Entry::new(utc_time_seconds, 10, EntryStatus::Active, vars)
+    }
+
+    #[test]
+    fn test_memory_storage_merges_and_clamps_entries() -> Result<(), StorageError> {
+        let mut storage = MemoryStorage::open(Path::new(""), 10, true)?;
+        storage.insert_entries(&[new_entry(1000, "maya"), new_entry(1010, "maya")]);
+        storage.write_entries()?;
+        // Continues the last written entry.
+        storage.insert_entries(&[new_entry(1020, "maya"), new_entry(1030, "nuke")]);
+        storage.write_entries()?;
+
+        let written_entries = storage.written_entries();
+        assert_eq!(written_entries.len(), 2);
+        assert_eq!(written_entries[0].duration_seconds, 30);
+        assert_eq!(written_entries[1].utc_time_seconds, 1030);
+
+        let entries = storage.read_entries(900, 1035)?;
+        let durations: Vec<u64> = entries
+            .all_entries()
+            .iter()
+            .map(|x| x.duration_seconds)
+            .collect();
+        assert_eq!(durations, vec![30, 5]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_memory_storage_clamps_entries_spanning_the_range() -> Result<(), StorageError> {
+        let mut storage = MemoryStorage::open(Path::new(""), 10, true)?;
+        storage.insert_entries(&[new_entry(1000, "maya"), new_entry(1010, "maya")]);
+        storage.write_entries()?;
+
+        // Only the portion of the entry after the start is read (such
+        // as the minutes after midnight).
+        let entries = storage.read_entries(1005, 1100)?;
+        let times: Vec<(u64, u64)> = entries
+            .all_entries()
+            .iter()
+            .map(|x| (x.utc_time_seconds, x.duration_seconds))
+            .collect();
+        assert_eq!(times, vec![(1005, 15)]);
+
+        // An entry spanning the whole range is clamped at both ends.
+        let entries = storage.read_entries(1005, 1012)?;
+        assert_eq!(entries.all_entries()[0].utc_time_seconds, 1005);
+        assert_eq!(entries.all_entries()[0].duration_seconds, 7);
+
+        // The entry ends at the start of the range.
+        let entries = storage.read_entries(1020, 1100)?;
+        assert!(entries.all_entries().is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,307 @@
+use crate::entries::AuditLogEntry;
+use crate::entries::DayNote;
+use crate::entries::Entry;
+use crate::entries::EntryStatus;
+use crate::entries::ExecutableVersion;
+use crate::entries::HostBusyPeriod;
+use crate::entries::WeekPlan;
+use crate::entries::WindowColorPeriod;
+use crate::errors::StorageError;
+use crate::storage::Entries;
+use crate::storage::Storage;
+use log::debug;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Reads (and deletes) entries spanning multiple database files (such
+/// as the per-year database files), as if they were a single
+/// database.
+///
+/// Database files that do not exist are skipped.
+pub struct MultiStorage {
+    database_file_paths: Vec<PathBuf>,
+    storages: Vec<Storage>,
+}
+
+impl MultiStorage {
+    fn open(
+        database_file_paths: &[PathBuf],
+        record_interval_seconds: u64,
+        read_only: bool,
+    ) -> Result<MultiStorage, StorageError> {
+        let mut existing_file_paths = Vec::new();
+        let mut storages = Vec::new();
+        for database_file_path in database_file_paths {
+            if !database_file_path.is_file() {
+                debug!("Skipping missing database file: {:?}", database_file_path);
+                continue;
+            }
+            let auto_create_database_file = !read_only;
+            let storage = Storage::open(
+                database_file_path,
+                record_interval_seconds,
+                auto_create_database_file,
+            )?;
+            existing_file_paths.push(database_file_path.clone());
+            storages.push(storage);
+        }
+
+        if storages.is_empty() {
+            // Report the first database file that was expected.
+            let database_file_path = database_file_paths.first().cloned().unwrap_or_default();
+            return Err(StorageError::Missing(database_file_path));
+        }
+
+        Ok(MultiStorage {
+            database_file_paths: existing_file_paths,
+            storages,
+        })
+    }
+
+    pub fn open_as_read_only(
+        database_file_paths: &[PathBuf],
+        record_interval_seconds: u64,
+    ) -> Result<MultiStorage, StorageError> {
+        let read_only = true;
+        MultiStorage::open(database_file_paths, record_interval_seconds, read_only)
+    }
+
+    /// Open the existing database files for writing. Database files
+    /// are never created.
+    pub fn open_as_read_write(
+        database_file_paths: &[PathBuf],
+        record_interval_seconds: u64,
+    ) -> Result<MultiStorage, StorageError> {
+        let read_only = false;
+        MultiStorage::open(database_file_paths, record_interval_seconds, read_only)
+    }
+
+    /// The database file paths that were opened.
+    pub fn database_file_paths(&self) -> &[PathBuf] {
+        &self.database_file_paths[..]
+    }
+
+    /// See 'Storage::set_use_recorded_utc_offset'.
+    pub fn set_use_recorded_utc_offset(&mut self, value: bool) {
+        for storage in &mut self.storages {
+            storage.set_use_recorded_utc_offset(value);
+        }
+    }
+
+    /// Read the entries from all database files, sorted by time.
+    ///
+    /// When the same day has a note (or the same week has a plan of
+    /// a value) in multiple files, the one from the last database
+    /// file is used.
+    pub fn read_entries(
+        &mut self,
+        start_utc_time_seconds: u64,
+        end_utc_time_seconds: u64,
+    ) -> Result<Entries, StorageError> {
+        let mut start_datetime = None;
+        let mut end_datetime = None;
+        let mut all_entries = Vec::<Entry>::new();
+        let mut all_day_notes = Vec::<DayNote>::new();
+        let mut all_host_busy_periods = Vec::<HostBusyPeriod>::new();
+        let mut all_window_color_periods = Vec::<WindowColorPeriod>::new();
+        let mut all_plans = Vec::<WeekPlan>::new();
+        for storage in &mut self.storages {
+            let entries = storage.read_entries(start_utc_time_seconds, end_utc_time_seconds)?;
+            start_datetime = Some(entries.start_datetime());
+            end_datetime = Some(entries.end_datetime());
+            all_entries.extend_from_slice(entries.all_entries());
+            all_host_busy_periods.extend_from_slice(entries.host_busy_periods());
+            all_window_color_periods.extend_from_slice(entries.window_color_periods());
+            for day_note in entries.day_notes() {
+                all_day_notes.retain(|x| x.date != day_note.date);
+                all_day_notes.push(day_note.clone());
+            }
+            for plan in entries.plans() {
+                all_plans.retain(|x| {
+                    x.week_start_date != plan.week_start_date
+                        || x.variable_name != plan.variable_name
+                        || x.variable_value != plan.variable_value
+                });
+                all_plans.push(plan.clone());
+            }
+        }
+        all_entries.sort_by_key(|x| x.utc_time_seconds);
+        all_day_notes.sort_by_key(|x| x.date);
+        all_host_busy_periods.sort_by_key(|x| x.utc_time_seconds);
+        all_window_color_periods.sort_by_key(|x| x.utc_time_seconds);
+        all_plans.sort_by(|a, b| {
+            (a.week_start_date, &a.variable_name, &a.variable_value).cmp(&(
+                b.week_start_date,
+                &b.variable_name,
+                &b.variable_value,
+            ))
+        });
+
+        let mut builder = Entries::builder()
+            .entries(all_entries)
+            .day_notes(all_day_notes)
+            .host_busy_periods(all_host_busy_periods)
+            .window_color_periods(all_window_color_periods)
+            .plans(all_plans);
+        if let (Some(start_datetime), Some(end_datetime)) = (start_datetime, end_datetime) {
+            builder = builder
+                .start_datetime(start_datetime)
+                .end_datetime(end_datetime);
+        }
+        Ok(builder.build())
+    }
+
+    /// Call the callback with each entry of each database file, in
+    /// the order of the database files; see 'Storage::stream_entries'.
+    ///
+    /// Unlike 'read_entries', the entries are not sorted across the
+    /// database files.
+    pub fn stream_entries<F, E>(
+        &self,
+        start_utc_time_seconds: u64,
+        end_utc_time_seconds: u64,
+        mut callback: F,
+    ) -> Result<(), E>
+    where
+        F: FnMut(Entry) -> Result<(), E>,
+        E: From<StorageError>,
+    {
+        for storage in &self.storages {
+            storage.stream_entries(start_utc_time_seconds, end_utc_time_seconds, &mut callback)?;
+        }
+        Ok(())
+    }
+
+    /// Sum the durations of all database files; see
+    /// 'Storage::sum_durations_grouped_by'. The values are sorted.
+    pub fn sum_durations_grouped_by(
+        &self,
+        column_name: &str,
+        start_utc_time_seconds: u64,
+        end_utc_time_seconds: u64,
+        status: EntryStatus,
+    ) -> Result<Vec<(Option<String>, u64)>, StorageError> {
+        let mut all_durations = BTreeMap::<Option<String>, u64>::new();
+        for storage in &self.storages {
+            for (value, duration_seconds) in storage.sum_durations_grouped_by(
+                column_name,
+                start_utc_time_seconds,
+                end_utc_time_seconds,
+                status,
+            )? {
+                *all_durations.entry(value).or_insert(0) += duration_seconds;
+            }
+        }
+        Ok(all_durations.into_iter().collect())
+    }
+
+    /// See 'Storage::delete_entries'.
+    pub fn delete_entries(
+        &mut self,
+        start_utc_time_seconds: u64,
+        end_utc_time_seconds: u64,
+    ) -> Result<usize, StorageError> {
+        let mut count = 0;
+        for storage in &mut self.storages {
+            count += storage.delete_entries(start_utc_time_seconds, end_utc_time_seconds)?;
+        }
+        Ok(count)
+    }
+
+    /// See 'Storage::update_entries_values'.
+    pub fn update_entries_values(
+        &mut self,
+        start_utc_time_seconds: u64,
+        end_utc_time_seconds: u64,
+        where_values: &[(String, Option<String>)],
+        set_values: &[(String, Option<String>)],
+    ) -> Result<usize, StorageError> {
+        let mut count = 0;
+        for storage in &mut self.storages {
+            count += storage.update_entries_values(
+                start_utc_time_seconds,
+                end_utc_time_seconds,
+                where_values,
+                set_values,
+            )?;
+        }
+        Ok(count)
+    }
+
+    /// See 'Storage::set_record_undo'.
+    pub fn set_record_undo(&mut self, value: bool) {
+        for storage in &mut self.storages {
+            storage.set_record_undo(value);
+        }
+    }
+
+    /// Undo the most recent operation that can be undone, in any of
+    /// the database files, returning the operation and the number
+    /// of entry rows restored, or None if there is no operation to
+    /// undo. See 'Storage::undo_operation'.
+    pub fn undo_last_operation(&mut self) -> Result<Option<(AuditLogEntry, usize)>, StorageError> {
+        let mut last: Option<(usize, AuditLogEntry)> = None;
+        for (index, storage) in self.storages.iter().enumerate() {
+            if let Some(audit_log_entry) = storage.last_undoable_audit_log_entry()? {
+                let is_later = match &last {
+                    Some((_, x)) => audit_log_entry.utc_time_seconds >= x.utc_time_seconds,
+                    None => true,
+                };
+                if is_later {
+                    last = Some((index, audit_log_entry));
+                }
+            }
+        }
+
+        match last {
+            Some((index, audit_log_entry)) => {
+                let id = audit_log_entry.id.unwrap_or(0);
+                let count = self.storages[index].undo_operation(id)?;
+                Ok(Some((audit_log_entry, count)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// See 'Storage::delete_all_data'.
+    pub fn delete_all_data(&mut self) -> Result<(), StorageError> {
+        for storage in &mut self.storages {
+            storage.delete_all_data()?;
+        }
+        Ok(())
+    }
+
+    /// Read the executable versions from all database files, sorted
+    /// by the first time the executable was seen.
+    pub fn read_executable_versions(&mut self) -> Result<Vec<ExecutableVersion>, StorageError> {
+        let mut all_executable_versions = Vec::new();
+        for storage in &mut self.storages {
+            all_executable_versions.extend(storage.read_executable_versions()?);
+        }
+        all_executable_versions.sort_by_key(|x| x.first_utc_time_seconds);
+        Ok(all_executable_versions)
+    }
+
+    /// Read the audit logs from all database files, sorted by time.
+    pub fn read_audit_log(&self) -> Result<Vec<AuditLogEntry>, StorageError> {
+        let mut all_audit_log_entries = Vec::new();
+        for storage in &self.storages {
+            all_audit_log_entries.extend(storage.read_audit_log()?);
+        }
+        all_audit_log_entries.sort_by_key(|x| x.utc_time_seconds);
+        Ok(all_audit_log_entries)
+    }
+
+    /// Close all the database files, returning the first error
+    /// after attempting to close every file.
+    pub fn close(self) -> Result<(), StorageError> {
+        let mut result = Ok(());
+        for storage in self.storages {
+            let close_result = storage.close();
+            if result.is_ok() {
+                result = close_result;
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,407 @@
+//! The SQLite database schema.
+//!
+//! The tables and views here are a public contract; external tools
+//! (such as studio pipelines) may query the database directly. Tables
+//! and columns are only ever added, and the documented views keep the
+//! same columns (in the same order) between schema versions, so SQL
+//! written against the views keeps working.
+//!
+//! The version of each database is stored in the 'schema_version'
+//! table, and databases are migrated to the current version when
+//! opened for writing (see 'Storage').
+
+/// The version of the schema described by this module.
+pub const SCHEMA_VERSION: u32 = 13;
+
+pub const TABLE_RECORDS: &str = "records";
+pub const TABLE_EXECUTABLE_VERSIONS: &str = "executable_versions";
+pub const TABLE_DAY_NOTES: &str = "day_notes";
+pub const TABLE_PRIVACY_CONSENT: &str = "privacy_consent";
+pub const TABLE_HOST_BUSY: &str = "host_busy";
+pub const TABLE_WINDOW_COLORS: &str = "window_colors";
+pub const TABLE_AUDIT_LOG: &str = "audit_log";
+pub const TABLE_PLANS: &str = "plans";
+pub const TABLE_UNDO_RECORDS: &str = "undo_records";
+pub const TABLE_SCHEMA_VERSION: &str = "schema_version";
+
+/// A view of the recorded entries, with the status as text
+/// ("uninitialized", "active", "idle", "paused", "locked" or
+/// "suspended") and the end time of each entry.
+pub const VIEW_ENTRIES: &str = "entries_view";
+
+/// A view of the recorded variables, with one row per variable of
+/// each entry, so the time spent per variable value can be summed
+/// with a 'GROUP BY'.
+pub const VIEW_ENTRY_VARIABLES: &str = "entry_variables_view";
+
+/// The documented (stable) columns of 'entries_view'.
+pub const VIEW_ENTRIES_COLUMNS: &[&str] = &[
+    "utc_time_seconds",
+    "end_utc_time_seconds",
+    "duration_seconds",
+    "status",
+    "executable",
+    "var1_name",
+    "var1_value",
+    "var2_name",
+    "var2_value",
+    "var3_name",
+    "var3_value",
+    "var4_name",
+    "var4_value",
+    "var5_name",
+    "var5_value",
+    "utc_offset_seconds",
+];
+
+/// The documented (stable) columns of 'entry_variables_view'.
+pub const VIEW_ENTRY_VARIABLES_COLUMNS: &[&str] = &[
+    "utc_time_seconds",
+    "duration_seconds",
+    "status",
+    "executable",
+    "name",
+    "value",
+];
+
+pub const RECORDS_TABLE_DDL: &str = "CREATE TABLE IF NOT EXISTS records (
+              utc_time_seconds INTEGER,
+              duration_seconds INTEGER,
+              status           INTEGER,
+              executable       TEXT,
+              var1_name        VARCHAR(255),
+              var2_name        VARCHAR(255),
+              var3_name        VARCHAR(255),
+              var4_name        VARCHAR(255),
+              var5_name        VARCHAR(255),
+              var1_value       TEXT,
+              var2_value       TEXT,
+              var3_value       TEXT,
+              var4_value       TEXT,
+              var5_value       TEXT,
+              utc_offset_seconds INTEGER,
+              window_title     TEXT,
+              working_directory TEXT,
+              document_path    TEXT,
+              hostname         TEXT,
+              username         TEXT,
+              record_interval_seconds INTEGER,
+              display          TEXT
+         );";
+
+pub const EXECUTABLE_VERSIONS_TABLE_DDL: &str = "CREATE TABLE IF NOT EXISTS executable_versions (
+              executable             TEXT,
+              executable_path        TEXT,
+              modified_time_seconds  INTEGER,
+              version                TEXT,
+              first_utc_time_seconds INTEGER,
+              last_utc_time_seconds  INTEGER,
+              UNIQUE(executable_path, modified_time_seconds)
+         );";
+
+pub const DAY_NOTES_TABLE_DDL: &str = "CREATE TABLE IF NOT EXISTS day_notes (
+              date TEXT PRIMARY KEY,
+              note TEXT
+         );";
+
+pub const PRIVACY_CONSENT_TABLE_DDL: &str = "CREATE TABLE IF NOT EXISTS privacy_consent (
+              utc_time_seconds INTEGER,
+              notice_version INTEGER
+         );";
+
+pub const HOST_BUSY_TABLE_DDL: &str = "CREATE TABLE IF NOT EXISTS host_busy (
+              utc_time_seconds INTEGER,
+              duration_seconds INTEGER,
+              reason TEXT
+         );";
+
+pub const WINDOW_COLORS_TABLE_DDL: &str = "CREATE TABLE IF NOT EXISTS window_colors (
+              utc_time_seconds INTEGER,
+              duration_seconds INTEGER,
+              executable TEXT,
+              color INTEGER
+         );";
+
+/// The operations changing the entries (and other data). The 'id'
+/// increases with each operation (and is NULL for operations logged
+/// before schema version 11), the 'source' is the program that made
+/// the change (such as "recorder" or "edit"), and
+/// 'undo_utc_time_seconds' is the time the operation was undone.
+pub const AUDIT_LOG_TABLE_DDL: &str = "CREATE TABLE IF NOT EXISTS audit_log (
+              utc_time_seconds INTEGER,
+              user_name TEXT,
+              operation TEXT,
+              filter TEXT,
+              row_count INTEGER,
+              id INTEGER,
+              source TEXT,
+              undo_utc_time_seconds INTEGER
+         );";
+
+/// The entry rows of an operation that can be undone (the 'id' of
+/// the 'audit_log'); the rows before the operation ('is_after' is 0)
+/// and the rows changed by the operation ('is_after' is 1).
+pub const UNDO_RECORDS_TABLE_DDL: &str = "CREATE TABLE IF NOT EXISTS undo_records (
+              audit_log_id     INTEGER,
+              is_after         INTEGER,
+              record_rowid     INTEGER,
+              utc_time_seconds INTEGER,
+              duration_seconds INTEGER,
+              status           INTEGER,
+              executable       TEXT,
+              var1_name        VARCHAR(255),
+              var2_name        VARCHAR(255),
+              var3_name        VARCHAR(255),
+              var4_name        VARCHAR(255),
+              var5_name        VARCHAR(255),
+              var1_value       TEXT,
+              var2_value       TEXT,
+              var3_value       TEXT,
+              var4_value       TEXT,
+              var5_value       TEXT,
+              utc_offset_seconds INTEGER,
+              window_title     TEXT,
+              working_directory TEXT,
+              document_path    TEXT,
+              hostname         TEXT,
+              username         TEXT,
+              record_interval_seconds INTEGER,
+              display          TEXT
+         );";
+
+/// The planned duration of each variable value for a week. The week
+/// start date is ISO 8601 text ("YYYY-MM-DD"), in the user's local
+/// timezone.
+pub const PLANS_TABLE_DDL: &str = "CREATE TABLE IF NOT EXISTS plans (
+              week_start_date TEXT,
+              variable_name   TEXT,
+              variable_value  TEXT,
+              planned_seconds INTEGER,
+              PRIMARY KEY(week_start_date, variable_name, variable_value)
+         );";
+
+/// One row for each migration applied to the database; the schema
+/// version of the database is the largest version.
+pub const SCHEMA_VERSION_TABLE_DDL: &str = "CREATE TABLE IF NOT EXISTS schema_version (
+              version          INTEGER,
+              utc_time_seconds INTEGER,
+              description      TEXT
+         );";
+
+/// Entries are almost always queried by a range of time.
+pub const RECORDS_UTC_TIME_SECONDS_INDEX_DDL: &str =
+    "CREATE INDEX IF NOT EXISTS records_utc_time_seconds_index
+         ON records (utc_time_seconds) ;";
+
+pub const ENTRIES_VIEW_DDL: &str = "CREATE VIEW IF NOT EXISTS entries_view AS
+         SELECT utc_time_seconds,
+                utc_time_seconds + duration_seconds AS end_utc_time_seconds,
+                duration_seconds,
+                CASE status
+                     WHEN 1 THEN 'active'
+                     WHEN 2 THEN 'idle'
+                     WHEN 3 THEN 'paused'
+                     WHEN 4 THEN 'locked'
+                     WHEN 5 THEN 'suspended'
+                     ELSE 'uninitialized'
+                END AS status,
+                executable,
+                var1_name,
+                var1_value,
+                var2_name,
+                var2_value,
+                var3_name,
+                var3_value,
+                var4_name,
+                var4_value,
+                var5_name,
+                var5_value,
+                utc_offset_seconds
+         FROM records ;";
+
+pub const ENTRY_VARIABLES_VIEW_DDL: &str = "CREATE VIEW IF NOT EXISTS entry_variables_view AS
+         SELECT utc_time_seconds, duration_seconds, status, executable,
+                var1_name AS name, var1_value AS value
+         FROM entries_view WHERE var1_name IS NOT NULL
+         UNION ALL
+         SELECT utc_time_seconds, duration_seconds, status, executable,
+                var2_name AS name, var2_value AS value
+         FROM entries_view WHERE var2_name IS NOT NULL
+         UNION ALL
+         SELECT utc_time_seconds, duration_seconds, status, executable,
+                var3_name AS name, var3_value AS value
+         FROM entries_view WHERE var3_name IS NOT NULL
+         UNION ALL
+         SELECT utc_time_seconds, duration_seconds, status, executable,
+                var4_name AS name, var4_value AS value
+         FROM entries_view WHERE var4_name IS NOT NULL
+         UNION ALL
+         SELECT utc_time_seconds, duration_seconds, status, executable,
+                var5_name AS name, var5_value AS value
+         FROM entries_view WHERE var5_name IS NOT NULL ;";
+
+/// The statements creating every table, in the order they are
+/// created.
+pub const TABLES_DDL: &[&str] = &[
+    SCHEMA_VERSION_TABLE_DDL,
+    RECORDS_TABLE_DDL,
+    EXECUTABLE_VERSIONS_TABLE_DDL,
+    DAY_NOTES_TABLE_DDL,
+    HOST_BUSY_TABLE_DDL,
+    WINDOW_COLORS_TABLE_DDL,
+    PRIVACY_CONSENT_TABLE_DDL,
+    AUDIT_LOG_TABLE_DDL,
+    PLANS_TABLE_DDL,
+    UNDO_RECORDS_TABLE_DDL,
+];
+
+/// The statements creating every index. Indexes are created after
+/// the tables.
+pub const INDEXES_DDL: &[&str] = &[RECORDS_UTC_TIME_SECONDS_INDEX_DDL];
+
+/// The statements creating every view. Views depend on the tables,
+/// so are created after them.
+pub const VIEWS_DDL: &[&str] = &[ENTRIES_VIEW_DDL, ENTRY_VARIABLES_VIEW_DDL];
+
+/// Get the canonical DDL (the SQL statements creating every table,
+/// index and view) of the current schema version.
+pub fn canonical_ddl() -> String {
+    let mut lines = vec![format!(
+        "-- Timetracker database schema version {}.",
+        SCHEMA_VERSION
+    )];
+    for statement in TABLES_DDL
+        .iter()
+        .chain(INDEXES_DDL.iter())
+        .chain(VIEWS_DDL.iter())
+    {
+        lines.push("".to_string());
+        lines.push(statement.to_string());
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schema::*;
+    use crate::storage::Storage;
+    use anyhow::Result;
+    use std::path::PathBuf;
+
+    fn temp_database_file_path(name: &str) -> PathBuf {
+        let file_name = format!("timetracker-schema-{}-{}.sqlite3", name, std::process::id());
+        let path = std::env::temp_dir().join(file_name);
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn column_names(connection: &rusqlite::Connection, name: &str) -> Result<Vec<String>> {
+        let mut statement = connection.prepare("SELECT name FROM pragma_table_info(?1) ;")?;
+        let rows = statement.query_map([name], |row| row.get(0))?;
+        let mut names = Vec::new();
+        for row in rows {
+            names.push(row?);
+        }
+        Ok(names)
+    }
+
+    fn assert_documented_views(connection: &rusqlite::Connection) -> Result<()> {
+        assert_eq!(
+            column_names(connection, VIEW_ENTRIES)?,
+            VIEW_ENTRIES_COLUMNS.to_vec()
+        );
+        assert_eq!(
+            column_names(connection, VIEW_ENTRY_VARIABLES)?,
+            VIEW_ENTRY_VARIABLES_COLUMNS.to_vec()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_canonical_ddl_creates_documented_views() -> Result<()> {
+        let connection = rusqlite::Connection::open_in_memory()?;
+        connection.execute_batch(&canonical_ddl())?;
+        assert_documented_views(&connection)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_new_database_matches_canonical_ddl() -> Result<()> {
+        let database_file_path = temp_database_file_path("new");
+        let storage = Storage::open_as_read_write(&database_file_path, 1)?;
+        storage.close()?;
+
+        let connection = rusqlite::Connection::open(&database_file_path)?;
+        assert_documented_views(&connection)?;
+        for table_name in [
+            TABLE_RECORDS,
+            TABLE_EXECUTABLE_VERSIONS,
+            TABLE_DAY_NOTES,
+            TABLE_PRIVACY_CONSENT,
+            TABLE_HOST_BUSY,
+            TABLE_WINDOW_COLORS,
+            TABLE_AUDIT_LOG,
+            TABLE_PLANS,
+            TABLE_UNDO_RECORDS,
+            TABLE_SCHEMA_VERSION,
+        ] {
+            assert!(!column_names(&connection, table_name)?.is_empty());
+        }
+
+        let storage = Storage::open_as_read_only(&database_file_path, 1)?;
+        assert_eq!(storage.schema_version()?, SCHEMA_VERSION);
+        storage.close()?;
+        std::fs::remove_file(&database_file_path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrated_database_keeps_documented_views() -> Result<()> {
+        // A database created before the local offset was recorded.
+        let database_file_path = temp_database_file_path("migrated");
+        {
+            let connection = rusqlite::Connection::open(&database_file_path)?;
+            connection.execute_batch(
+                "CREATE TABLE records (
+                      utc_time_seconds INTEGER,
+                      duration_seconds INTEGER,
+                      status           INTEGER,
+                      executable       TEXT,
+                      var1_name        VARCHAR(255),
+                      var2_name        VARCHAR(255),
+                      var3_name        VARCHAR(255),
+                      var4_name        VARCHAR(255),
+                      var5_name        VARCHAR(255),
+                      var1_value       TEXT,
+                      var2_value       TEXT,
+                      var3_value       TEXT,
+                      var4_value       TEXT,
+                      var5_value       TEXT
+                 );
+                 INSERT INTO records (utc_time_seconds, duration_seconds, status,
+                                      executable, var1_name, var1_value)
+                 VALUES (100, 10, 1, 'blender', 'PROJECT', 'ACME');",
+            )?;
+        }
+        let storage = Storage::open_as_read_only(&database_file_path, 1)?;
+        assert_eq!(storage.schema_version()?, 0);
+        storage.close()?;
+
+        let storage = Storage::open_as_read_write(&database_file_path, 1)?;
+        assert_eq!(storage.schema_version()?, SCHEMA_VERSION);
+        storage.close()?;
+
+        let connection = rusqlite::Connection::open(&database_file_path)?;
+        assert_documented_views(&connection)?;
+        let (status, name, value): (String, String, String) = connection.query_row(
+            "SELECT status, name, value FROM entry_variables_view ;",
+            (),
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        assert_eq!(status, "active");
+        assert_eq!(name, "PROJECT");
+        assert_eq!(value, "ACME");
+        std::fs::remove_file(&database_file_path)?;
+        Ok(())
+    }
+}
@@ -1,12 +1,16 @@
-use crate::filesystem::find_existing_configuration_directory_path;
+use crate::errors::SettingsError;
+use crate::filesystem::find_default_directory_path;
 use crate::filesystem::find_existing_file_path;
+use crate::format::BufferOverflowPolicy;
 use crate::format::DateTimeFormat;
 use crate::format::DurationFormat;
+use crate::format::FirstDayOfWeek;
+use crate::format::IgnoreAction;
 use crate::format::PrintType;
+use crate::format::SortBy;
 use crate::format::TimeBlockUnit;
 use crate::format::TimeScale;
 use crate::storage::ENVIRONMENT_VARIABLE_NAMES_MAX_COUNT;
-use anyhow::bail;
 use config::{
     builder::DefaultState, Config, ConfigBuilder, ConfigError, Environment, File, FileFormat,
     Value, ValueKind,
@@ -14,13 +18,70 @@ use config::{
 use log::error;
 use serde_derive::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
 
-/// How often will the recorder query the system to find data?
-pub const RECORD_INTERVAL_SECONDS: u64 = 1;
+/// How often will the recorder query the system to find data, unless
+/// 'recorder.record_interval_seconds' is set?
+pub const DEFAULT_RECORD_INTERVAL_SECONDS: u64 = 1;
+
+/// The longest duration (in seconds) of an entry in the database,
+/// when many entries are merged into one.
+pub const DEFAULT_MAX_MERGED_ENTRY_SECONDS: u64 = 15 * 60;
+
+/// The 1-minute load average at which the machine is considered to
+/// be busy (for example, rendering).
+pub const DEFAULT_HOST_BUSY_LOAD_AVERAGE: f64 = 4.0;
+
+/// Idle time shorter than this is counted as presence (such as time
+/// spent thinking or reading) when calculating utilization.
+pub const DEFAULT_UTILIZATION_IDLE_THRESHOLD_SECONDS: u64 = 15 * 60;
+
+/// The active time expected on each weekday (Monday to Friday), used
+/// to calculate overtime.
+pub const DEFAULT_WORK_HOURS_PER_DAY: f64 = 8.0;
+
+/// The most entries the recorder keeps in memory while the database
+/// cannot be written to (one hour of entries).
+pub const DEFAULT_RECORDER_BUFFER_MAX_ENTRIES: usize = 60 * 60;
+
+/// How often the recorder samples the system when running on battery
+/// (and 'recorder.power.enabled' is set).
+pub const DEFAULT_RECORDER_BATTERY_RECORD_INTERVAL_SECONDS: u64 = 5;
+
+/// How many entries the recorder buffers in memory before writing to
+/// the database, when running on battery.
+pub const DEFAULT_RECORDER_BATTERY_WRITE_ENTRY_COUNT: usize = 60;
+
+/// How long the shell prompt segment is cached for.
+pub const DEFAULT_PROMPT_SEGMENT_CACHE_SECONDS: u64 = 10;
+
+/// The font size (in points) of the report text of
+/// 'timetracker-print-gui', unless 'gui.font_size' is set.
+pub const DEFAULT_GUI_FONT_SIZE: u32 = 10;
+
+/// The address 'timetracker-server' listens on for synchronized
+/// entries.
+pub const DEFAULT_SYNC_LISTEN_ADDRESS: &str = "127.0.0.1:9797";
+
+/// The URL of the 'timetracker-server' the entries are synchronized
+/// with.
+pub const DEFAULT_SYNC_HOST: &str = "http://127.0.0.1:9797";
+
+/// How many minutes the user is continuously active before a break is
+/// suggested, unless 'notifications.break_reminder_minutes' is set.
+pub const DEFAULT_NOTIFICATIONS_BREAK_REMINDER_MINUTES: u64 = 60;
+
+/// How many minutes the user must be inactive (idle, locked or
+/// paused) for a break, unless 'notifications.break_minimum_minutes'
+/// is set.
+pub const DEFAULT_NOTIFICATIONS_BREAK_MINIMUM_MINUTES: u64 = 5;
 
 /// How many seconds does the user need to be idle before we consider
-/// the user to be in an idle state?
-pub const USER_IS_IDLE_LIMIT_SECONDS: u64 = 30;
+/// the user to be in an idle state, unless
+/// 'recorder.user_is_idle_limit_seconds' is set?
+pub const DEFAULT_USER_IS_IDLE_LIMIT_SECONDS: u64 = 30;
 
 /// The name of the file used to save timetracker data.
 const DEFAULT_DATABASE_FILE_NAME: &str = ".timetracker.sqlite3";
@@ -29,17 +90,33 @@ const DEFAULT_DATABASE_FILE_NAME: &str = ".timetracker.sqlite3";
 ///
 /// The configuration file is found by searching in the
 /// "TIMETRACKER_CONFIG_PATH" environment variable (if it exists),
-/// then in the home directory.
+/// then the "TIMETRACKER_HOME" directory, then in the home directory.
 pub const DEFAULT_CONFIG_FILE_NAME: &str = ".timetracker.toml";
 
 const PRESET_SUMMARY_WEEK: &str = "summary_week";
 const PRESET_SUMMARY_WEEKDAYS: &str = "summary_weekdays";
+const PRESET_SUMMARY_FORTNIGHT: &str = "summary_fortnight";
+const PRESET_SUMMARY_MONTH: &str = "summary_month";
 const PRESET_SOFTWARE_WEEK: &str = "software_week";
 const PRESET_SOFTWARE_WEEKDAYS: &str = "software_weekdays";
 const PRESET_ACTIVITY_WEEK: &str = "activity_week";
 const PRESET_ACTIVITY_WEEKDAYS: &str = "activity_weekdays";
 const PRESET_WORKING_DIRECTORY_WEEK: &str = "working_directory_week";
 const PRESET_WORKING_DIRECTORY_WEEKDAYS: &str = "working_directory_weekdays";
+const PRESET_UTILIZATION_WEEK: &str = "utilization_week";
+const PRESET_UTILIZATION_WEEKDAYS: &str = "utilization_weekdays";
+const PRESET_OVERTIME_WEEK: &str = "overtime_week";
+const PRESET_OVERTIME_WEEKDAYS: &str = "overtime_weekdays";
+const PRESET_OVERTIME_MONTH: &str = "overtime_month";
+const PRESET_WINDOW_TITLE_WEEK: &str = "window_title_week";
+const PRESET_WINDOW_TITLE_WEEKDAYS: &str = "window_title_weekdays";
+const PRESET_CHURN_WEEK: &str = "churn_week";
+const PRESET_PROCESS_WORKING_DIRECTORY_WEEK: &str = "process_working_directory_week";
+const PRESET_PROCESS_WORKING_DIRECTORY_WEEKDAYS: &str = "process_working_directory_weekdays";
+const PRESET_DOCUMENT_PATH_WEEK: &str = "document_path_week";
+const PRESET_DOCUMENT_PATH_WEEKDAYS: &str = "document_path_weekdays";
+const PRESET_PLAN_WEEK: &str = "plan_week";
+const PRESET_STATISTICS_WEEK: &str = "statistics_week";
 
 const DEFAULT_PRESET_NAMES: [&str; 4] = [
     PRESET_SUMMARY_WEEK,
@@ -48,18 +125,162 @@ const DEFAULT_PRESET_NAMES: [&str; 4] = [
     PRESET_SOFTWARE_WEEK,
 ];
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EnvVarSettings {
     pub names: Vec<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CoreSettings {
     pub database_dir: String,
     pub database_file_name: String,
+    pub database_per_year: bool,
+    /// The directory of the per-year archive database files, written
+    /// by 'timetracker-dump archive --older-than'. The archive files
+    /// are read with the database files, when the range read covers
+    /// the year of an archive file. Empty for no archive files.
+    pub archive_dir: String,
+    /// Report entries in the local time they were recorded in (using
+    /// the offset from UTC stored with each entry), rather than the
+    /// current timezone.
+    pub use_recorded_utc_offset: bool,
+    /// The active time expected on each weekday (Monday to Friday),
+    /// used to calculate overtime. No time is expected on the
+    /// weekend.
+    pub work_hours_per_day: f64,
+    /// The day each week starts on, for the weeks of reports (and
+    /// the weeks plans are made for).
+    pub first_day_of_week: FirstDayOfWeek,
     pub environment_variables: EnvVarSettings,
 }
 
+/// The error given when there is no directory for the database (or
+/// configuration) files.
+pub const MISSING_DIRECTORY_MESSAGE: &str =
+    "Could not find a directory for the Timetracker files; \
+     $HOME, $XDG_CONFIG_HOME and the current directory are unavailable. \
+     Set the TIMETRACKER_HOME environment variable, or give '--database-dir'.";
+
+/// Search for the existing configuration file that is read for the
+/// settings, using the "TIMETRACKER_CONFIG_PATH" environment variable
+/// (if it exists), then the default directories.
+pub fn find_existing_configuration_file_path() -> Option<PathBuf> {
+    let env_config_path = std::env::var("TIMETRACKER_CONFIG_PATH");
+    let user_config_path: Option<String> = env_config_path.ok();
+    find_existing_file_path(user_config_path, DEFAULT_CONFIG_FILE_NAME)
+}
+
+/// The configuration file that settings are written to; the existing
+/// configuration file, or a new file in the default directory (where
+/// it is found by 'find_existing_configuration_file_path').
+pub fn find_configuration_file_path_to_write() -> Option<PathBuf> {
+    match find_existing_configuration_file_path() {
+        Some(value) => Some(value),
+        None => Some(find_default_directory_path()?.join(DEFAULT_CONFIG_FILE_NAME)),
+    }
+}
+
+/// A setting value written into the configuration file, by
+/// 'write_configuration_file_values'.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SettingValue {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    StringList(Vec<String>),
+}
+
+impl SettingValue {
+    fn to_toml_item(&self) -> toml_edit::Item {
+        match self {
+            SettingValue::Bool(value) => toml_edit::value(*value),
+            SettingValue::Integer(value) => toml_edit::value(*value),
+            SettingValue::Float(value) => toml_edit::value(*value),
+            SettingValue::String(value) => toml_edit::value(value.as_str()),
+            SettingValue::StringList(values) => {
+                let array: toml_edit::Array = values.iter().map(|x| x.as_str()).collect();
+                toml_edit::value(array)
+            }
+        }
+    }
+}
+
+/// Set the value of the setting, with a full (dotted) name such as
+/// "print.display_presets", creating the tables of the name as
+/// needed.
+fn set_document_value(
+    document: &mut toml_edit::Document,
+    name: &str,
+    value: &SettingValue,
+) -> Result<(), SettingsError> {
+    let mut table_names: Vec<&str> = name.split('.').collect();
+    let key = table_names.pop().unwrap_or_default();
+    if key.is_empty() || table_names.iter().any(|x| x.is_empty()) {
+        return Err(SettingsError::InvalidValue(format!(
+            "Setting name {:?} is invalid.",
+            name
+        )));
+    }
+
+    let mut table = document.as_table_mut();
+    for table_name in table_names {
+        let item = table.entry(table_name).or_insert_with(toml_edit::table);
+        table = item.as_table_mut().ok_or_else(|| {
+            SettingsError::InvalidValue(format!(
+                "Setting {:?} of {:?} is not a table in the configuration file.",
+                table_name, name
+            ))
+        })?;
+    }
+    // The comments of an existing setting are kept.
+    let mut item = value.to_toml_item();
+    if let (Some(existing_value), Some(new_value)) = (
+        table.get(key).and_then(|x| x.as_value()),
+        item.as_value_mut(),
+    ) {
+        *new_value.decor_mut() = existing_value.decor().clone();
+    }
+    table[key] = item;
+    Ok(())
+}
+
+/// Write the setting values (with full names such as
+/// "print.display_presets") into the configuration file, creating
+/// the file if needed.
+///
+/// The other settings (and comments) of an existing file are kept.
+/// The file is written atomically, so an interrupted write never
+/// leaves a partial file behind.
+pub fn write_configuration_file_values(
+    file_path: &Path,
+    values: &[(&str, SettingValue)],
+) -> Result<(), SettingsError> {
+    let text = match std::fs::read_to_string(file_path) {
+        Ok(value) => value,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err.into()),
+    };
+    let mut document: toml_edit::Document = text.parse()?;
+    for (name, value) in values {
+        set_document_value(&mut document, name, value)?;
+    }
+
+    if let Some(parent_dir) = file_path.parent() {
+        std::fs::create_dir_all(parent_dir)?;
+    }
+    let mut temp_file_path = file_path.as_os_str().to_owned();
+    temp_file_path.push(".tmp");
+    let temp_file_path = PathBuf::from(temp_file_path);
+    {
+        let mut file = std::fs::File::create(&temp_file_path)?;
+        file.write_all(document.to_string().as_bytes())?;
+        file.sync_all()?;
+    }
+    std::fs::rename(&temp_file_path, file_path)?;
+    Ok(())
+}
+
 pub fn new_core_settings(
     database_dir: Option<String>,
     database_file_name: Option<String>,
@@ -67,15 +288,24 @@ pub fn new_core_settings(
 ) -> Result<ConfigBuilder<DefaultState>, ConfigError> {
     let env_var_names = vec!["PWD".to_string(); 1];
 
-    let default_database_dir = find_existing_configuration_directory_path()
-        .expect("Could not find a default database directory ($HOME, $HOME/.config or $XDG_CONFIG_HOME).")
-        .into_os_string()
-        .into_string()
-        .unwrap();
+    // The default directory is not needed (and may not exist, such
+    // as in a container) when the directory is given.
+    let default_database_dir = match &database_dir {
+        Some(value) => value.clone(),
+        None => find_default_directory_path()
+            .ok_or_else(|| ConfigError::Message(MISSING_DIRECTORY_MESSAGE.to_string()))?
+            .to_string_lossy()
+            .into_owned(),
+    };
 
     let mut builder = Config::builder()
         .set_default("core.database_dir", default_database_dir)?
         .set_default("core.database_file_name", DEFAULT_DATABASE_FILE_NAME)?
+        .set_default("core.database_per_year", false)?
+        .set_default("core.archive_dir", "")?
+        .set_default("core.use_recorded_utc_offset", false)?
+        .set_default("core.work_hours_per_day", DEFAULT_WORK_HOURS_PER_DAY)?
+        .set_default("core.first_day_of_week", FirstDayOfWeek::Monday)?
         .set_default("core.environment_variables.names", env_var_names)?
         //
         // Allows settings from environment variables (with a prefix
@@ -89,14 +319,7 @@ pub fn new_core_settings(
 
     // Runtime configuration file options.
     if !defaults {
-        let config_file_name = DEFAULT_CONFIG_FILE_NAME;
-        let env_config_path = std::env::var("TIMETRACKER_CONFIG_PATH");
-        let user_config_path: Option<String> = match env_config_path {
-            Ok(value) => Some(value),
-            Err(..) => None,
-        };
-        let config_file_path = find_existing_file_path(user_config_path, config_file_name);
-        if let Some(file_path) = config_file_path {
+        if let Some(file_path) = find_existing_configuration_file_path() {
             if let Some(file_path) = file_path.to_str() {
                 builder =
                     builder.add_source(File::new(file_path, FileFormat::Toml).required(false));
@@ -107,7 +330,7 @@ pub fn new_core_settings(
     Result::Ok(builder)
 }
 
-pub fn validate_core_settings(settings: &CoreSettings) -> Result<(), anyhow::Error> {
+pub fn validate_core_settings(settings: &CoreSettings) -> Result<(), SettingsError> {
     let envvar_name_count = settings.environment_variables.names.len();
     if envvar_name_count > ENVIRONMENT_VARIABLE_NAMES_MAX_COUNT {
         let msg = format!(
@@ -117,9 +340,9 @@ pub fn validate_core_settings(settings: &CoreSettings) -> Result<(), anyhow::Err
             settings.environment_variables.names
         );
         // We want a nice user error and date/time, so we
-        // error. 'bail!' doesn't have that.
+        // error. The returned error doesn't have that.
         error!("{}", msg);
-        bail!("{}", msg);
+        Err(SettingsError::InvalidValue(msg))
     } else {
         Result::Ok(())
     }
@@ -135,9 +358,43 @@ pub struct PrintPresetSettings {
     pub bar_graph_character_num_width: Option<u8>,
     pub use_color: Option<bool>,
     pub variable_names: Option<Vec<String>>,
+    /// Use unicode block characters to display activity density.
+    pub activity_use_unicode_blocks: Option<bool>,
+    /// The characters used to display activity density, from least
+    /// to most dense. Overrides 'activity_use_unicode_blocks'.
+    pub activity_ramp_characters: Option<Vec<String>>,
+    /// The density ratios (0.0 to 1.0) between each activity
+    /// character; one less than the number of characters.
+    pub activity_ramp_thresholds: Option<Vec<f32>>,
+    /// The color names (or "#RRGGBB") for each activity character.
+    pub activity_ramp_colors: Option<Vec<String>>,
+    /// The IANA timezone name (such as "America/Los_Angeles") used to
+    /// divide the days of the preset, instead of the local timezone.
+    pub timezone: Option<String>,
+    /// The order of the durations printed by the "Software" and
+    /// "Variables" print types.
+    pub sort_by: Option<SortBy>,
+    /// Only print the N longest durations of the "Software" and
+    /// "Variables" print types; the others are printed as one
+    /// "other" line.
+    pub top_n: Option<u32>,
+    /// Only print the durations of the "Software" and "Variables"
+    /// print types that are at least this many minutes; the others
+    /// are printed as one "other" line.
+    pub min_duration_minutes: Option<u64>,
+    /// Print the share of the total active time of each duration of
+    /// the "Software" and "Variables" print types, as a percentage.
+    pub show_percentage: Option<bool>,
+    /// The color name (or "#RRGGBB") of the preset, used instead of
+    /// the default color when 'use_color' is true.
+    pub color: Option<String>,
+    /// The number of weeks (ending with the displayed week) used by
+    /// the "Statistics" print type.
+    pub statistics_weeks: Option<u32>,
 }
 
 impl PrintPresetSettings {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         print_type: Option<PrintType>,
         time_scale: Option<TimeScale>,
@@ -157,6 +414,17 @@ impl PrintPresetSettings {
             bar_graph_character_num_width,
             use_color,
             variable_names,
+            activity_use_unicode_blocks: None,
+            activity_ramp_characters: None,
+            activity_ramp_thresholds: None,
+            activity_ramp_colors: None,
+            timezone: None,
+            sort_by: None,
+            top_n: None,
+            min_duration_minutes: None,
+            show_percentage: None,
+            color: None,
+            statistics_weeks: None,
         }
     }
 }
@@ -246,10 +514,7 @@ impl From<PrintPresetSettings> for ValueKind {
         match preset.use_color {
             Some(value) => map.insert(
                 "use_color".to_string(),
-                Value::new(
-                    Some(&"use_color".to_string()),
-                    ValueKind::Boolean(value as bool),
-                ),
+                Value::new(Some(&"use_color".to_string()), ValueKind::Boolean(value)),
             ),
             None => map.insert("use_color".to_string(), Value::new(None, ValueKind::Nil)),
         };
@@ -274,11 +539,198 @@ impl From<PrintPresetSettings> for ValueKind {
             ),
         };
 
+        match preset.activity_use_unicode_blocks {
+            Some(value) => map.insert(
+                "activity_use_unicode_blocks".to_string(),
+                Value::new(
+                    Some(&"activity_use_unicode_blocks".to_string()),
+                    ValueKind::Boolean(value),
+                ),
+            ),
+            None => map.insert(
+                "activity_use_unicode_blocks".to_string(),
+                Value::new(None, ValueKind::Nil),
+            ),
+        };
+
+        match preset.activity_ramp_characters {
+            Some(value) => {
+                let characters_array: Vec<_> = value
+                    .iter()
+                    .map(|x| Value::new(None, ValueKind::String(x.clone())))
+                    .collect();
+                map.insert(
+                    "activity_ramp_characters".to_string(),
+                    Value::new(
+                        Some(&"activity_ramp_characters".to_string()),
+                        ValueKind::Array(characters_array),
+                    ),
+                )
+            }
+            None => map.insert(
+                "activity_ramp_characters".to_string(),
+                Value::new(None, ValueKind::Nil),
+            ),
+        };
+
+        match preset.activity_ramp_thresholds {
+            Some(value) => {
+                let thresholds_array: Vec<_> = value
+                    .iter()
+                    .map(|x| Value::new(None, ValueKind::Float(*x as f64)))
+                    .collect();
+                map.insert(
+                    "activity_ramp_thresholds".to_string(),
+                    Value::new(
+                        Some(&"activity_ramp_thresholds".to_string()),
+                        ValueKind::Array(thresholds_array),
+                    ),
+                )
+            }
+            None => map.insert(
+                "activity_ramp_thresholds".to_string(),
+                Value::new(None, ValueKind::Nil),
+            ),
+        };
+
+        match preset.activity_ramp_colors {
+            Some(value) => {
+                let colors_array: Vec<_> = value
+                    .iter()
+                    .map(|x| Value::new(None, ValueKind::String(x.clone())))
+                    .collect();
+                map.insert(
+                    "activity_ramp_colors".to_string(),
+                    Value::new(
+                        Some(&"activity_ramp_colors".to_string()),
+                        ValueKind::Array(colors_array),
+                    ),
+                )
+            }
+            None => map.insert(
+                "activity_ramp_colors".to_string(),
+                Value::new(None, ValueKind::Nil),
+            ),
+        };
+
+        match preset.timezone {
+            Some(value) => map.insert(
+                "timezone".to_string(),
+                Value::new(Some(&"timezone".to_string()), ValueKind::String(value)),
+            ),
+            None => map.insert("timezone".to_string(), Value::new(None, ValueKind::Nil)),
+        };
+
+        match preset.sort_by {
+            Some(value) => map.insert(
+                "sort_by".to_string(),
+                Value::new(
+                    Some(&"sort_by".to_string()),
+                    ValueKind::String(value.to_string()),
+                ),
+            ),
+            None => map.insert("sort_by".to_string(), Value::new(None, ValueKind::Nil)),
+        };
+
+        match preset.top_n {
+            Some(value) => map.insert(
+                "top_n".to_string(),
+                Value::new(Some(&"top_n".to_string()), ValueKind::U64(value as u64)),
+            ),
+            None => map.insert("top_n".to_string(), Value::new(None, ValueKind::Nil)),
+        };
+
+        match preset.min_duration_minutes {
+            Some(value) => map.insert(
+                "min_duration_minutes".to_string(),
+                Value::new(
+                    Some(&"min_duration_minutes".to_string()),
+                    ValueKind::U64(value),
+                ),
+            ),
+            None => map.insert(
+                "min_duration_minutes".to_string(),
+                Value::new(None, ValueKind::Nil),
+            ),
+        };
+
+        match preset.show_percentage {
+            Some(value) => map.insert(
+                "show_percentage".to_string(),
+                Value::new(
+                    Some(&"show_percentage".to_string()),
+                    ValueKind::Boolean(value),
+                ),
+            ),
+            None => map.insert(
+                "show_percentage".to_string(),
+                Value::new(None, ValueKind::Nil),
+            ),
+        };
+
+        match preset.color {
+            Some(value) => map.insert(
+                "color".to_string(),
+                Value::new(Some(&"color".to_string()), ValueKind::String(value)),
+            ),
+            None => map.insert("color".to_string(), Value::new(None, ValueKind::Nil)),
+        };
+
+        match preset.statistics_weeks {
+            Some(value) => map.insert(
+                "statistics_weeks".to_string(),
+                Value::new(
+                    Some(&"statistics_weeks".to_string()),
+                    ValueKind::U64(value as u64),
+                ),
+            ),
+            None => map.insert(
+                "statistics_weeks".to_string(),
+                Value::new(None, ValueKind::Nil),
+            ),
+        };
+
         ValueKind::Table(map)
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrintUtilizationSettings {
+    /// Utilization is the active time divided by the presence time,
+    /// where presence is the active time plus any period of idle time
+    /// shorter than this.
+    pub idle_threshold_seconds: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrintPromptSegmentSettings {
+    /// The variable displayed as the current project, such as
+    /// "PROJECT".
+    pub variable_name: String,
+    /// The prompt segment is re-used from a cache file for this
+    /// duration, so a shell prompt does not query the database
+    /// every time it is displayed.
+    pub cache_seconds: u64,
+}
+
+/// A rule creating a derived variable from the value of another
+/// variable, applied before printing, such as a clean project name
+/// from a working directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrintMappingSettings {
+    /// The name of the derived variable, as used in presets.
+    pub name: String,
+    /// The variable the value is read from, such as "PWD" or
+    /// "working_directory".
+    pub variable: String,
+    /// The regular expression matched against the value.
+    pub pattern: String,
+    /// The derived value, with "$name" (or "${name}") replaced by
+    /// the named (or numbered) groups captured by 'pattern'.
+    pub replace: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PrintSettings {
     pub time_scale: TimeScale,
     pub format_datetime: DateTimeFormat,
@@ -286,8 +738,209 @@ pub struct PrintSettings {
     pub time_block_unit: TimeBlockUnit,
     pub bar_graph_character_num_width: u8,
     pub use_color: bool,
+    pub sort_by: SortBy,
     pub display_presets: Vec<String>,
     pub presets: HashMap<String, PrintPresetSettings>,
+    /// Fail (rather than warn) when a displayed preset is unknown,
+    /// has no 'print_type', or uses a variable that is not recorded.
+    pub strict: bool,
+    pub utilization: PrintUtilizationSettings,
+    pub prompt_segment: PrintPromptSegmentSettings,
+    /// The derived variables, created in order (so a mapping may use
+    /// the variables derived before it).
+    pub mappings: Vec<PrintMappingSettings>,
+    /// The color names (or "#RRGGBB") of executables, such as
+    /// "firefox" = "bright blue", used for the lines of the
+    /// executables in the "Software" print type.
+    pub colors: HashMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecorderSettings {
+    /// How often (in seconds) the recorder queries the system to
+    /// find data. The interval is stored with each entry, so entries
+    /// recorded with a different interval are still merged correctly.
+    pub record_interval_seconds: u64,
+    /// How many seconds the user must be idle before the user is
+    /// considered idle.
+    pub user_is_idle_limit_seconds: u64,
+    /// Detect and record the version of each executable used, for
+    /// software usage audits.
+    pub record_executable_versions: bool,
+    /// The variable name given a value from the 'workspace_projects'
+    /// mapping, when the variable is missing from the environment.
+    pub workspace_project_variable: String,
+    /// Use the time actually elapsed between samples as the duration
+    /// of each entry, rather than the fixed record interval.
+    pub compensate_sampling_jitter: bool,
+    /// When the active window is a terminal running tmux (or GNU
+    /// screen), record the session and window names as the
+    /// variables "TMUX_SESSION", "TMUX_WINDOW" (or "SCREEN_SESSION").
+    pub record_terminal_multiplexer: bool,
+    /// Record a locked screen (or an active screen saver) as
+    /// "Locked", rather than "Idle", so time away from the computer
+    /// is told apart from idle time at the computer (such as
+    /// reading).
+    pub record_screen_lock: bool,
+    /// Record the time the machine was suspended (or hibernated) as
+    /// "Suspended", rather than leaving a gap in the recording.
+    pub record_suspend: bool,
+    /// Record when the machine is busy (such as rendering) so idle
+    /// time can be explained in reports.
+    pub record_host_busy: bool,
+    /// The machine is busy when the 1-minute load average (from
+    /// "/proc/loadavg") is at least this value.
+    pub host_busy_load_average: f64,
+    /// The machine is busy when a process with one of these
+    /// executable names is running, such as "blender" or "nuke".
+    pub host_busy_executable_names: Vec<String>,
+    /// Sample the dominant color of the focused window, so timelines
+    /// can render a colored band per application. Only the color is
+    /// stored, never the pixels of the window. Disabled by default.
+    pub record_window_color: bool,
+    /// Record the title of the focused window (such as the document
+    /// or web page), not just the executable. Disabled by default.
+    pub record_window_title: bool,
+    /// Record the current working directory of the focused process
+    /// (from "/proc/<pid>/cwd"), which is correct even when the
+    /// "PWD" variable is stale (such as for applications started
+    /// from a desktop launcher).
+    pub track_process_cwd: bool,
+    /// Record the file path of the document open in the focused
+    /// application, from the applications listed in the
+    /// recently-used files ("recently-used.xbel"). Disabled by
+    /// default.
+    pub record_document_path: bool,
+    /// Record the name of the machine, so entries from multiple
+    /// machines can be told apart (such as after merging databases).
+    pub record_hostname: bool,
+    /// Record the name of the user, so entries of multiple users
+    /// (such as with a shared home directory) can be told apart.
+    pub record_username: bool,
+    /// The X11 displays (such as ":0" and ":1", or screens such as
+    /// ":0.1") the active window is found on, for multi-seat (or
+    /// VNC) setups. The display the user is active on is recorded
+    /// with each entry. An empty list uses the "DISPLAY" variable.
+    pub displays: Vec<String>,
+    /// Show an icon in the system tray (notification area) with the
+    /// status of the user and the active time of today, and a menu
+    /// to open the report, pause recording or quit the recorder.
+    pub tray_icon: bool,
+    /// The most entries kept in memory while the database cannot be
+    /// written to (such as when an NFS home directory is
+    /// unavailable).
+    pub buffer_max_entries: usize,
+    /// What happens to the oldest entries when the buffer is full.
+    pub buffer_overflow_policy: BufferOverflowPolicy,
+    /// The journal file that overflowing entries are spilled to. An
+    /// empty path uses a file next to the database file.
+    pub journal_file_path: String,
+    /// Write each entry to a buffer file (next to the journal file)
+    /// before it is kept in memory, so the buffered entries are not
+    /// lost when the recorder is killed or the machine loses power.
+    /// The buffer file is written to the database when the recorder
+    /// next starts.
+    pub crash_safe_buffer: bool,
+    /// Activity that is never recorded in detail, such as a password
+    /// manager.
+    pub ignore: RecorderIgnoreSettings,
+    /// How the recorder reduces its work when running on battery.
+    pub power: RecorderPowerSettings,
+}
+
+/// The recorder samples (and writes to the database) less often when
+/// the machine is running on battery, read from the power supplies
+/// ("/sys/class/power_supply").
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecorderPowerSettings {
+    /// Reduce the work of the recorder when running on battery.
+    pub enabled: bool,
+    /// How often (in seconds) the system is sampled when running on
+    /// battery, rather than 'recorder.record_interval_seconds'.
+    pub battery_record_interval_seconds: u64,
+    /// How many entries are buffered in memory before they are
+    /// written to the database, when running on battery.
+    pub battery_write_entry_count: usize,
+}
+
+/// The activity ignored by the recorder, matched with glob patterns
+/// ("*" matches any characters, "?" matches one character).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RecorderIgnoreSettings {
+    /// The executable names to ignore, such as "keepassxc".
+    #[serde(default)]
+    pub executables: Vec<String>,
+    /// The environment variable values to ignore, for each variable
+    /// name, such as 'PWD = ["/home/*/private/*"]'. The variables
+    /// do not need to be recorded.
+    #[serde(default)]
+    pub variables: HashMap<String, Vec<String>>,
+    /// Record the ignored time as a generic entry, or skip it.
+    pub action: IgnoreAction,
+}
+
+/// Regular expressions filtering the recorded values of a variable,
+/// so values that may contain secrets (such as tokens) are never
+/// stored.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VariableFilterSettings {
+    /// When not empty, a value is only recorded if it matches one of
+    /// these regular expressions.
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// A value matching any of these regular expressions is not
+    /// recorded.
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StorageSettings {
+    /// When entries are merged together when written, the merged
+    /// entry is split so it is never longer than this duration.
+    pub max_merged_entry_seconds: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncSettings {
+    /// Send the recorded entries to a 'timetracker-server', so the
+    /// entries of multiple computers can be printed as one report.
+    pub enabled: bool,
+    /// The URL of the server, such as "http://example.com:9797".
+    pub host: String,
+    /// The token the server requires to read or write entries. Empty
+    /// when the server does not require a token.
+    pub token: String,
+    /// The address (and port) 'timetracker-server' listens on.
+    pub listen_address: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationsSettings {
+    /// Send desktop notifications from the recorder (using
+    /// 'notify-send').
+    pub enabled: bool,
+    /// Notify once a day when the active time of today reaches this
+    /// many hours. Zero disables the notification.
+    pub daily_target_hours: f64,
+    /// Suggest a break after being continuously active for this many
+    /// minutes, repeated every this many minutes until a break is
+    /// taken. Zero disables the notification.
+    pub break_reminder_minutes: u64,
+    /// How many minutes the user must be inactive (idle, locked or
+    /// paused) for the time to count as a break.
+    pub break_minimum_minutes: u64,
+    /// Notify when the recorder cannot write to storage, and again
+    /// when writing works again.
+    pub storage_failure: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuiSettings {
+    /// The font size (in points) of the report text.
+    pub font_size: u32,
+    /// Display the report text with light text on a dark background.
+    pub dark_mode: bool,
 }
 
 fn new_default_preset_names() -> Vec<String> {
@@ -325,6 +978,33 @@ fn new_default_presets() -> HashMap<String, PrintPresetSettings> {
         ),
     );
 
+    presets.insert(
+        PRESET_SUMMARY_FORTNIGHT.to_string(),
+        PrintPresetSettings::new(
+            Some(PrintType::Summary),
+            Some(TimeScale::Fortnight),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        ),
+    );
+    presets.insert(
+        PRESET_SUMMARY_MONTH.to_string(),
+        PrintPresetSettings::new(
+            Some(PrintType::Summary),
+            Some(TimeScale::Month),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        ),
+    );
+
     presets.insert(
         PRESET_ACTIVITY_WEEK.to_string(),
         PrintPresetSettings::new(
@@ -408,6 +1088,202 @@ fn new_default_presets() -> HashMap<String, PrintPresetSettings> {
         ),
     );
 
+    presets.insert(
+        PRESET_UTILIZATION_WEEK.to_string(),
+        PrintPresetSettings::new(
+            Some(PrintType::Utilization),
+            Some(TimeScale::Week),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        ),
+    );
+
+    presets.insert(
+        PRESET_UTILIZATION_WEEKDAYS.to_string(),
+        PrintPresetSettings::new(
+            Some(PrintType::Utilization),
+            Some(TimeScale::Weekday),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        ),
+    );
+
+    presets.insert(
+        PRESET_OVERTIME_WEEK.to_string(),
+        PrintPresetSettings::new(
+            Some(PrintType::Overtime),
+            Some(TimeScale::Week),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        ),
+    );
+
+    presets.insert(
+        PRESET_OVERTIME_WEEKDAYS.to_string(),
+        PrintPresetSettings::new(
+            Some(PrintType::Overtime),
+            Some(TimeScale::Weekday),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        ),
+    );
+
+    presets.insert(
+        PRESET_OVERTIME_MONTH.to_string(),
+        PrintPresetSettings::new(
+            Some(PrintType::Overtime),
+            Some(TimeScale::Month),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        ),
+    );
+
+    presets.insert(
+        PRESET_WINDOW_TITLE_WEEK.to_string(),
+        PrintPresetSettings::new(
+            Some(PrintType::WindowTitle),
+            Some(TimeScale::Week),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        ),
+    );
+
+    presets.insert(
+        PRESET_WINDOW_TITLE_WEEKDAYS.to_string(),
+        PrintPresetSettings::new(
+            Some(PrintType::WindowTitle),
+            Some(TimeScale::Weekday),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        ),
+    );
+
+    presets.insert(
+        PRESET_PROCESS_WORKING_DIRECTORY_WEEK.to_string(),
+        PrintPresetSettings::new(
+            Some(PrintType::WorkingDirectory),
+            Some(TimeScale::Week),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        ),
+    );
+
+    presets.insert(
+        PRESET_PROCESS_WORKING_DIRECTORY_WEEKDAYS.to_string(),
+        PrintPresetSettings::new(
+            Some(PrintType::WorkingDirectory),
+            Some(TimeScale::Weekday),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        ),
+    );
+
+    presets.insert(
+        PRESET_DOCUMENT_PATH_WEEK.to_string(),
+        PrintPresetSettings::new(
+            Some(PrintType::DocumentPath),
+            Some(TimeScale::Week),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        ),
+    );
+
+    presets.insert(
+        PRESET_DOCUMENT_PATH_WEEKDAYS.to_string(),
+        PrintPresetSettings::new(
+            Some(PrintType::DocumentPath),
+            Some(TimeScale::Weekday),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        ),
+    );
+
+    presets.insert(
+        PRESET_PLAN_WEEK.to_string(),
+        PrintPresetSettings::new(
+            Some(PrintType::Plan),
+            Some(TimeScale::Week),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        ),
+    );
+
+    presets.insert(
+        PRESET_CHURN_WEEK.to_string(),
+        PrintPresetSettings::new(
+            Some(PrintType::Churn),
+            Some(TimeScale::Week),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        ),
+    );
+
+    presets.insert(
+        PRESET_STATISTICS_WEEK.to_string(),
+        PrintPresetSettings::new(
+            Some(PrintType::Statistics),
+            Some(TimeScale::Week),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        ),
+    );
+
     presets
 }
 
@@ -423,14 +1299,112 @@ pub fn new_print_settings(
         .set_default("print.time_block_unit", "SixtyMinutes")?
         .set_default("print.bar_graph_character_num_width", 60)?
         .set_default("print.use_color", true)?
+        .set_default("print.sort_by", SortBy::Name)?
         .set_default("print.display_presets", preset_names)?
-        .set_default("print.presets", presets)?;
+        .set_default("print.presets", presets)?
+        .set_default("print.strict", false)?
+        .set_default("print.mappings", Vec::<Value>::new())?
+        .set_default("print.colors", HashMap::<String, Value>::new())?
+        .set_default(
+            "print.utilization.idle_threshold_seconds",
+            DEFAULT_UTILIZATION_IDLE_THRESHOLD_SECONDS,
+        )?
+        .set_default("print.prompt_segment.variable_name", "PROJECT")?
+        .set_default(
+            "print.prompt_segment.cache_seconds",
+            DEFAULT_PROMPT_SEGMENT_CACHE_SECONDS,
+        )?;
     Result::Ok(config_builder)
 }
 
 pub fn new_recorder_settings(
     config_builder: ConfigBuilder<DefaultState>,
 ) -> Result<ConfigBuilder<DefaultState>, ConfigError> {
+    let config_builder = config_builder
+        .set_default(
+            "recorder.record_interval_seconds",
+            DEFAULT_RECORD_INTERVAL_SECONDS,
+        )?
+        .set_default(
+            "recorder.user_is_idle_limit_seconds",
+            DEFAULT_USER_IS_IDLE_LIMIT_SECONDS,
+        )?
+        .set_default("recorder.record_executable_versions", false)?
+        .set_default("recorder.workspace_project_variable", "PROJECT")?
+        .set_default("recorder.compensate_sampling_jitter", true)?
+        .set_default("recorder.record_terminal_multiplexer", false)?
+        .set_default("recorder.record_screen_lock", true)?
+        .set_default("recorder.record_suspend", true)?
+        .set_default("recorder.record_host_busy", false)?
+        .set_default(
+            "recorder.host_busy_load_average",
+            DEFAULT_HOST_BUSY_LOAD_AVERAGE,
+        )?
+        .set_default("recorder.host_busy_executable_names", Vec::<String>::new())?
+        .set_default("recorder.record_window_color", false)?
+        .set_default("recorder.record_window_title", false)?
+        .set_default("recorder.track_process_cwd", false)?
+        .set_default("recorder.record_document_path", false)?
+        .set_default("recorder.record_hostname", true)?
+        .set_default("recorder.record_username", true)?
+        .set_default("recorder.displays", Vec::<String>::new())?
+        .set_default("recorder.tray_icon", false)?
+        .set_default(
+            "recorder.buffer_max_entries",
+            DEFAULT_RECORDER_BUFFER_MAX_ENTRIES as u64,
+        )?
+        .set_default(
+            "recorder.buffer_overflow_policy",
+            BufferOverflowPolicy::SpillToJournal,
+        )?
+        .set_default("recorder.journal_file_path", "")?
+        .set_default("recorder.crash_safe_buffer", true)?
+        .set_default("recorder.ignore.executables", Vec::<String>::new())?
+        .set_default(
+            "recorder.ignore.variables",
+            config::Map::<String, Value>::new(),
+        )?
+        .set_default("recorder.ignore.action", IgnoreAction::Record)?
+        .set_default("recorder.power.enabled", false)?
+        .set_default(
+            "recorder.power.battery_record_interval_seconds",
+            DEFAULT_RECORDER_BATTERY_RECORD_INTERVAL_SECONDS,
+        )?
+        .set_default(
+            "recorder.power.battery_write_entry_count",
+            DEFAULT_RECORDER_BATTERY_WRITE_ENTRY_COUNT as u64,
+        )?
+        .set_default(
+            "storage.max_merged_entry_seconds",
+            DEFAULT_MAX_MERGED_ENTRY_SECONDS,
+        )?
+        .set_default("notifications.enabled", false)?
+        .set_default(
+            "notifications.daily_target_hours",
+            DEFAULT_WORK_HOURS_PER_DAY,
+        )?
+        .set_default(
+            "notifications.break_reminder_minutes",
+            DEFAULT_NOTIFICATIONS_BREAK_REMINDER_MINUTES,
+        )?
+        .set_default(
+            "notifications.break_minimum_minutes",
+            DEFAULT_NOTIFICATIONS_BREAK_MINIMUM_MINUTES,
+        )?
+        .set_default("notifications.storage_failure", true)?
+        .set_default("workspace_projects", config::Map::<String, Value>::new())?
+        .set_default("variable_filters", config::Map::<String, Value>::new())?;
+    Result::Ok(config_builder)
+}
+
+pub fn new_sync_settings(
+    config_builder: ConfigBuilder<DefaultState>,
+) -> Result<ConfigBuilder<DefaultState>, ConfigError> {
+    let config_builder = config_builder
+        .set_default("sync.enabled", false)?
+        .set_default("sync.host", DEFAULT_SYNC_HOST)?
+        .set_default("sync.token", "")?
+        .set_default("sync.listen_address", DEFAULT_SYNC_LISTEN_ADDRESS)?;
     Result::Ok(config_builder)
 }
 
@@ -446,7 +1420,72 @@ pub fn new_print_gui_settings(
         .set_default("print.time_block_unit", "SixtyMinutes")?
         .set_default("print.bar_graph_character_num_width", 60)?
         .set_default("print.use_color", false)?
+        .set_default("print.sort_by", SortBy::Name)?
         .set_default("print.display_presets", preset_names)?
-        .set_default("print.presets", presets)?;
+        .set_default("print.presets", presets)?
+        .set_default("print.strict", false)?
+        .set_default("print.mappings", Vec::<Value>::new())?
+        .set_default("print.colors", HashMap::<String, Value>::new())?
+        .set_default(
+            "print.utilization.idle_threshold_seconds",
+            DEFAULT_UTILIZATION_IDLE_THRESHOLD_SECONDS,
+        )?
+        .set_default("print.prompt_segment.variable_name", "PROJECT")?
+        .set_default(
+            "print.prompt_segment.cache_seconds",
+            DEFAULT_PROMPT_SEGMENT_CACHE_SECONDS,
+        )?
+        .set_default("gui.font_size", DEFAULT_GUI_FONT_SIZE)?
+        .set_default("gui.dark_mode", false)?;
     Result::Ok(config_builder)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_configuration_file_values() -> Result<(), SettingsError> {
+        let file_name = format!("timetracker-settings-{}.toml", std::process::id());
+        let file_path = std::env::temp_dir().join(file_name);
+        std::fs::write(
+            &file_path,
+            "# My settings.\n[core]\ndatabase_dir = \"~/data\"\n\n[print]\n# The presets.\ndisplay_presets = [\"summary_week\"]\n",
+        )?;
+
+        write_configuration_file_values(
+            &file_path,
+            &[
+                (
+                    "print.display_presets",
+                    SettingValue::StringList(vec![
+                        "software_week".to_string(),
+                        "summary_week".to_string(),
+                    ]),
+                ),
+                (
+                    "print.bar_graph_character_num_width",
+                    SettingValue::Integer(40),
+                ),
+                ("recorder.record_interval_seconds", SettingValue::Integer(2)),
+            ],
+        )?;
+        let text = std::fs::read_to_string(&file_path)?;
+
+        assert!(text.starts_with("# My settings.\n[core]\ndatabase_dir = \"~/data\"\n"));
+        assert!(text
+            .contains("# The presets.\ndisplay_presets = [\"software_week\", \"summary_week\"]\n"));
+        assert!(text.contains("bar_graph_character_num_width = 40\n"));
+        assert!(text.contains("[recorder]\nrecord_interval_seconds = 2\n"));
+
+        assert!(matches!(
+            write_configuration_file_values(
+                &file_path,
+                &[("core.database_dir.name", SettingValue::Bool(true))]
+            ),
+            Err(SettingsError::InvalidValue(_))
+        ));
+        std::fs::remove_file(&file_path)?;
+        Ok(())
+    }
+}
@@ -1,19 +1,34 @@
 use crate::entries::deduplicate_entries;
+use crate::entries::shift_utc_time_to_recorded_offset;
+use crate::entries::AuditLogEntry;
+use crate::entries::DayNote;
 use crate::entries::Entry;
 use crate::entries::EntryStatus;
 use crate::entries::EntryVariablesList;
+use crate::entries::ExecutableVersion;
+use crate::entries::HostBusyPeriod;
+use crate::entries::PrivacyConsent;
 use crate::entries::RecordRowStatus;
+use crate::entries::WeekPlan;
+use crate::entries::WindowColorPeriod;
+use crate::errors::StorageError;
 use crate::format_short_executable_name;
-use anyhow::{anyhow, Result};
+use crate::schema;
+use crate::settings::DEFAULT_MAX_MERGED_ENTRY_SECONDS;
+use crate::settings::DEFAULT_RECORD_INTERVAL_SECONDS;
 use chrono;
-use log::debug;
+use log::{debug, warn};
 use num_traits::FromPrimitive;
 use num_traits::ToPrimitive;
 use rusqlite;
 use rusqlite::named_params;
+use rusqlite::OptionalExtension;
 use std::fs::File;
+use std::os::unix::fs::OpenOptionsExt;
 use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
 
 // The indexes of the fields in the database, used to index into
 // queried rows.
@@ -31,47 +46,706 @@ const INDEX_VAR2_VALUE: usize = 10;
 const INDEX_VAR3_VALUE: usize = 11;
 const INDEX_VAR4_VALUE: usize = 12;
 const INDEX_VAR5_VALUE: usize = 13;
+const INDEX_UTC_OFFSET_SECONDS: usize = 14;
+const INDEX_WINDOW_TITLE: usize = 15;
+const INDEX_WORKING_DIRECTORY: usize = 16;
+const INDEX_DOCUMENT_PATH: usize = 17;
+const INDEX_HOSTNAME: usize = 18;
+const INDEX_USERNAME: usize = 19;
+const INDEX_DISPLAY: usize = 20;
+const INDEX_RECORD_INTERVAL_SECONDS: usize = 21;
+
+/// The largest difference between two UTC offsets (from UTC-12:00 to
+/// UTC+14:00), used to widen queries when entries are shifted by
+/// their recorded offset.
+const MAX_UTC_OFFSET_DIFFERENCE_SECONDS: u64 = 26 * 60 * 60;
+
+/// The number of prepared statements cached by each connection, so
+/// statements are not prepared again each time they are used. Must
+/// be larger than the number of statements cached with
+/// 'prepare_cached'.
+const PREPARED_STATEMENT_CACHE_CAPACITY: usize = 32;
+
+/// How long a connection waits for another connection (such as the
+/// recorder writing entries) to release a lock, before failing with
+/// 'StorageError::Locked'.
+const BUSY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// The format of dates stored in the 'day_notes' and 'plans' tables.
+const DAY_NOTE_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// The columns of the 'records' table that
+/// 'Storage::sum_durations_grouped_by' can group the durations by.
+pub const GROUPED_BY_COLUMN_NAMES: &[&str] = &[
+    "executable",
+    "window_title",
+    "working_directory",
+    "document_path",
+    "hostname",
+    "username",
+    "display",
+];
+
+/// The columns of the 'records' table that
+/// 'Storage::update_entries_values' can match and change.
+pub const EDITABLE_COLUMN_NAMES: &[&str] = &[
+    "executable",
+    "var1_name",
+    "var2_name",
+    "var3_name",
+    "var4_name",
+    "var5_name",
+    "var1_value",
+    "var2_value",
+    "var3_value",
+    "var4_value",
+    "var5_value",
+    "window_title",
+    "working_directory",
+    "document_path",
+    "hostname",
+    "username",
+    "display",
+];
 
 /// The maximum number of environment variables that can be stored in
 /// the database.
 pub const ENVIRONMENT_VARIABLE_NAMES_MAX_COUNT: usize = 5;
 
-fn initialize_database(connection: &rusqlite::Connection) -> Result<()> {
-    debug!("Initialize Database...");
+fn initialize_views(connection: &rusqlite::Connection) -> Result<(), StorageError> {
+    // Views are stable for external SQL consumers, see the 'schema'
+    // module.
+    for statement in schema::VIEWS_DDL {
+        connection.execute(statement, ())?;
+    }
+
+    Ok(())
+}
+
+fn table_column_exists(
+    connection: &rusqlite::Connection,
+    table_name: &str,
+    column_name: &str,
+) -> Result<bool, StorageError> {
+    let mut statement = connection
+        .prepare_cached("SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name = ?2 ;")?;
+    let count: i64 = statement.query_row([table_name, column_name], |row| row.get(0))?;
+    Ok(count > 0)
+}
+
+fn records_column_exists(
+    connection: &rusqlite::Connection,
+    column_name: &str,
+) -> Result<bool, StorageError> {
+    table_column_exists(connection, "records", column_name)
+}
+
+fn initialize_records_utc_offset_column(
+    connection: &rusqlite::Connection,
+) -> Result<(), StorageError> {
+    // Databases created before the local offset was recorded do
+    // not have the column; those entries have NULL offsets.
+    if !records_column_exists(connection, "utc_offset_seconds")? {
+        connection.execute(
+            "ALTER TABLE records ADD COLUMN utc_offset_seconds INTEGER ;",
+            (),
+        )?;
+    }
+
+    Ok(())
+}
+
+fn initialize_records_window_title_column(
+    connection: &rusqlite::Connection,
+) -> Result<(), StorageError> {
+    // Databases created before window titles were recorded do not
+    // have the column; those entries have NULL window titles.
+    if !records_column_exists(connection, "window_title")? {
+        connection.execute("ALTER TABLE records ADD COLUMN window_title TEXT ;", ())?;
+    }
+
+    Ok(())
+}
+
+fn initialize_records_working_directory_column(
+    connection: &rusqlite::Connection,
+) -> Result<(), StorageError> {
+    if !records_column_exists(connection, "working_directory")? {
+        connection.execute(
+            "ALTER TABLE records ADD COLUMN working_directory TEXT ;",
+            (),
+        )?;
+    }
+
+    Ok(())
+}
+
+fn initialize_records_document_path_column(
+    connection: &rusqlite::Connection,
+) -> Result<(), StorageError> {
+    if !records_column_exists(connection, "document_path")? {
+        connection.execute("ALTER TABLE records ADD COLUMN document_path TEXT ;", ())?;
+    }
+
+    Ok(())
+}
+
+fn initialize_records_hostname_username_columns(
+    connection: &rusqlite::Connection,
+) -> Result<(), StorageError> {
+    if !records_column_exists(connection, "hostname")? {
+        connection.execute("ALTER TABLE records ADD COLUMN hostname TEXT ;", ())?;
+    }
+    if !records_column_exists(connection, "username")? {
+        connection.execute("ALTER TABLE records ADD COLUMN username TEXT ;", ())?;
+    }
+
+    Ok(())
+}
+
+fn initialize_records_display_column(
+    connection: &rusqlite::Connection,
+) -> Result<(), StorageError> {
+    if !records_column_exists(connection, "display")? {
+        connection.execute("ALTER TABLE records ADD COLUMN display TEXT ;", ())?;
+    }
+    // The entry rows of undo operations have the same columns.
+    if database_table_exists(connection, schema::TABLE_UNDO_RECORDS)?
+        && !table_column_exists(connection, schema::TABLE_UNDO_RECORDS, "display")?
+    {
+        connection.execute("ALTER TABLE undo_records ADD COLUMN display TEXT ;", ())?;
+    }
+
+    Ok(())
+}
+
+fn initialize_records_record_interval_column(
+    connection: &rusqlite::Connection,
+) -> Result<(), StorageError> {
+    // Databases created before the record interval was configurable
+    // do not have the column; those entries were recorded with
+    // 'DEFAULT_RECORD_INTERVAL_SECONDS'.
+    if !records_column_exists(connection, "record_interval_seconds")? {
+        connection.execute(
+            "ALTER TABLE records ADD COLUMN record_interval_seconds INTEGER ;",
+            (),
+        )?;
+    }
+
+    Ok(())
+}
+
+fn initialize_executable_versions_table(
+    connection: &rusqlite::Connection,
+) -> Result<(), StorageError> {
+    // The table is created when missing, so that databases created
+    // before executable versions were recorded can be used.
+    connection.execute(schema::EXECUTABLE_VERSIONS_TABLE_DDL, ())?;
+
+    Ok(())
+}
+
+fn initialize_day_notes_table(connection: &rusqlite::Connection) -> Result<(), StorageError> {
+    // Dates are stored as ISO 8601 text ("YYYY-MM-DD"), in the
+    // user's local timezone.
+    connection.execute(schema::DAY_NOTES_TABLE_DDL, ())?;
+
+    Ok(())
+}
+
+fn initialize_privacy_consent_table(connection: &rusqlite::Connection) -> Result<(), StorageError> {
+    connection.execute(schema::PRIVACY_CONSENT_TABLE_DDL, ())?;
+
+    Ok(())
+}
+
+fn initialize_host_busy_table(connection: &rusqlite::Connection) -> Result<(), StorageError> {
+    connection.execute(schema::HOST_BUSY_TABLE_DDL, ())?;
+
+    Ok(())
+}
+
+fn initialize_window_colors_table(connection: &rusqlite::Connection) -> Result<(), StorageError> {
+    connection.execute(schema::WINDOW_COLORS_TABLE_DDL, ())?;
+
+    Ok(())
+}
+
+fn initialize_audit_log_table(connection: &rusqlite::Connection) -> Result<(), StorageError> {
+    connection.execute(schema::AUDIT_LOG_TABLE_DDL, ())?;
+
+    Ok(())
+}
+
+fn initialize_plans_table(connection: &rusqlite::Connection) -> Result<(), StorageError> {
+    connection.execute(schema::PLANS_TABLE_DDL, ())?;
+
+    Ok(())
+}
+
+fn initialize_audit_log_undo_columns(
+    connection: &rusqlite::Connection,
+) -> Result<(), StorageError> {
+    // Operations logged before the columns existed have NULL values,
+    // and cannot be undone.
+    for (column_name, column_type) in [
+        ("id", "INTEGER"),
+        ("source", "TEXT"),
+        ("undo_utc_time_seconds", "INTEGER"),
+    ] {
+        if !table_column_exists(connection, "audit_log", column_name)? {
+            connection.execute(
+                &format!(
+                    "ALTER TABLE audit_log ADD COLUMN {} {} ;",
+                    column_name, column_type
+                ),
+                (),
+            )?;
+        }
+    }
+    connection.execute(schema::UNDO_RECORDS_TABLE_DDL, ())?;
+
+    Ok(())
+}
+
+/// A change to the database schema, from the previous version to
+/// 'version'.
+struct Migration {
+    version: u32,
+    description: &'static str,
+    apply: fn(&rusqlite::Connection) -> Result<(), StorageError>,
+}
+
+/// Create the tables of the first versioned schema. Databases created
+/// before the schema was versioned may have some (or all) of these
+/// tables already, so this must be safe to run on those databases.
+fn migrate_to_version_1(connection: &rusqlite::Connection) -> Result<(), StorageError> {
+    connection.execute(schema::RECORDS_TABLE_DDL, ())?;
+    initialize_executable_versions_table(connection)?;
+    initialize_day_notes_table(connection)?;
+    initialize_host_busy_table(connection)?;
+    initialize_window_colors_table(connection)?;
+    initialize_privacy_consent_table(connection)?;
+    initialize_audit_log_table(connection)?;
+    initialize_records_utc_offset_column(connection)?;
+
+    Ok(())
+}
+
+/// Add an index on the time of each entry, so reading a range of
+/// entries does not scan the whole table.
+fn migrate_to_version_2(connection: &rusqlite::Connection) -> Result<(), StorageError> {
+    connection.execute(schema::RECORDS_UTC_TIME_SECONDS_INDEX_DDL, ())?;
+
+    Ok(())
+}
+
+/// Add the title of the focused window to each entry.
+fn migrate_to_version_3(connection: &rusqlite::Connection) -> Result<(), StorageError> {
+    initialize_records_window_title_column(connection)?;
+
+    Ok(())
+}
+
+/// Add the working directory of the focused process to each entry.
+fn migrate_to_version_4(connection: &rusqlite::Connection) -> Result<(), StorageError> {
+    initialize_records_working_directory_column(connection)?;
+
+    Ok(())
+}
+
+/// Add the document path of the focused application to each entry.
+fn migrate_to_version_5(connection: &rusqlite::Connection) -> Result<(), StorageError> {
+    initialize_records_document_path_column(connection)?;
+
+    Ok(())
+}
+
+/// Add the machine and user names to each entry, so entries from
+/// multiple machines (or users) can be told apart.
+fn migrate_to_version_6(connection: &rusqlite::Connection) -> Result<(), StorageError> {
+    initialize_records_hostname_username_columns(connection)?;
+
+    Ok(())
+}
+
+/// Add the planned durations of each week.
+fn migrate_to_version_7(connection: &rusqlite::Connection) -> Result<(), StorageError> {
+    initialize_plans_table(connection)?;
+
+    Ok(())
+}
+
+/// Add the record interval used by the recorder to each entry, so
+/// entries are merged correctly after the interval is changed.
+fn migrate_to_version_8(connection: &rusqlite::Connection) -> Result<(), StorageError> {
+    initialize_records_record_interval_column(connection)?;
+
+    Ok(())
+}
+
+/// Drop the views, so they are created again (with the current
+/// definitions) after the migrations.
+fn drop_views(connection: &rusqlite::Connection) -> Result<(), StorageError> {
+    connection.execute("DROP VIEW IF EXISTS entry_variables_view ;", ())?;
+    connection.execute("DROP VIEW IF EXISTS entries_view ;", ())?;
 
-    // Create database tables to be used for storage.
+    Ok(())
+}
+
+/// Describe the paused entries in the views.
+fn migrate_to_version_9(connection: &rusqlite::Connection) -> Result<(), StorageError> {
+    drop_views(connection)?;
+
+    Ok(())
+}
+
+/// Describe the locked entries in the views.
+fn migrate_to_version_10(connection: &rusqlite::Connection) -> Result<(), StorageError> {
+    drop_views(connection)?;
+
+    Ok(())
+}
+
+/// Log the source of each operation, and keep the entries changed by
+/// manual edits so the edits can be undone.
+fn migrate_to_version_11(connection: &rusqlite::Connection) -> Result<(), StorageError> {
+    initialize_audit_log_undo_columns(connection)?;
+
+    Ok(())
+}
+
+/// Describe the suspended entries in the views.
+fn migrate_to_version_12(connection: &rusqlite::Connection) -> Result<(), StorageError> {
+    drop_views(connection)?;
+
+    Ok(())
+}
+
+/// Add the X11 display of the focused window to each entry, for
+/// recorders watching multiple displays (or seats).
+fn migrate_to_version_13(connection: &rusqlite::Connection) -> Result<(), StorageError> {
+    initialize_records_display_column(connection)?;
+
+    Ok(())
+}
+
+/// The schema migrations, in the order they are applied. The
+/// version of the last migration must be 'schema::SCHEMA_VERSION'.
+///
+/// Migrations are never changed or removed once released; schema
+/// changes are made by adding a new migration.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Create the initial tables.",
+        apply: migrate_to_version_1,
+    },
+    Migration {
+        version: 2,
+        description: "Index the time of each entry.",
+        apply: migrate_to_version_2,
+    },
+    Migration {
+        version: 3,
+        description: "Record the window title of each entry.",
+        apply: migrate_to_version_3,
+    },
+    Migration {
+        version: 4,
+        description: "Record the process working directory of each entry.",
+        apply: migrate_to_version_4,
+    },
+    Migration {
+        version: 5,
+        description: "Record the document path of each entry.",
+        apply: migrate_to_version_5,
+    },
+    Migration {
+        version: 6,
+        description: "Record the hostname and username of each entry.",
+        apply: migrate_to_version_6,
+    },
+    Migration {
+        version: 7,
+        description: "Store the planned durations of each week.",
+        apply: migrate_to_version_7,
+    },
+    Migration {
+        version: 8,
+        description: "Record the record interval of each entry.",
+        apply: migrate_to_version_8,
+    },
+    Migration {
+        version: 9,
+        description: "Describe the paused entries in the views.",
+        apply: migrate_to_version_9,
+    },
+    Migration {
+        version: 10,
+        description: "Describe the locked entries in the views.",
+        apply: migrate_to_version_10,
+    },
+    Migration {
+        version: 11,
+        description: "Log the source of each operation, and store the undo of manual edits.",
+        apply: migrate_to_version_11,
+    },
+    Migration {
+        version: 12,
+        description: "Describe the suspended entries in the views.",
+        apply: migrate_to_version_12,
+    },
+    Migration {
+        version: 13,
+        description: "Record the X11 display of each entry.",
+        apply: migrate_to_version_13,
+    },
+];
+
+/// Read the schema version of the database; 0 for databases created
+/// before the schema was versioned.
+fn read_schema_version(connection: &rusqlite::Connection) -> Result<u32, StorageError> {
+    if !database_table_exists(connection, schema::TABLE_SCHEMA_VERSION)? {
+        return Ok(0);
+    }
+    let version: u32 = connection.query_row(
+        "SELECT COALESCE(MAX(version), 0) FROM schema_version ;",
+        (),
+        |row| row.get(0),
+    )?;
+    Ok(version)
+}
+
+/// Apply the migrations newer than the schema version of the
+/// database. Each migration runs inside a transaction, so a failed
+/// migration leaves the database at the previous version.
+fn migrate_database(connection: &rusqlite::Connection) -> Result<u32, StorageError> {
+    connection.execute(schema::SCHEMA_VERSION_TABLE_DDL, ())?;
+
+    let mut version = read_schema_version(connection)?;
+    if version > schema::SCHEMA_VERSION {
+        warn!(
+            "Database schema version {} is newer than the supported version {}.",
+            version,
+            schema::SCHEMA_VERSION
+        );
+    }
+
+    let database_version = version;
+    for migration in MIGRATIONS.iter().filter(|x| x.version > database_version) {
+        debug!(
+            "Migrate database schema to version {}: {}",
+            migration.version, migration.description
+        );
+        let transaction = connection.unchecked_transaction()?;
+        (migration.apply)(&transaction)?;
+        let now_utc_time_seconds = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        transaction.execute(
+            "INSERT INTO schema_version (version, utc_time_seconds, description)
+             VALUES (:version, :utc_time_seconds, :description) ;",
+            named_params! {
+                ":version": migration.version,
+                ":utc_time_seconds": now_utc_time_seconds,
+                ":description": migration.description,
+            },
+        )?;
+        transaction.commit()?;
+        version = migration.version;
+    }
+
+    Ok(version)
+}
+
+/// The name of the user running the current process, for the audit
+/// log.
+fn get_current_user_name() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// The name of the program changing the database, for the audit
+/// log, such as "recorder" for 'timetracker-recorder'.
+fn get_current_source_name() -> String {
+    std::env::current_exe()
+        .ok()
+        .and_then(|path| path.file_stem().map(|x| x.to_string_lossy().into_owned()))
+        .map(|name| match name.strip_prefix("timetracker-") {
+            Some(value) => value.to_string(),
+            None => name,
+        })
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// The 'id' of the next operation written to the audit log.
+fn next_audit_log_id(connection: &rusqlite::Connection) -> Result<u64, StorageError> {
+    let id: u64 = connection.query_row(
+        "SELECT COALESCE(MAX(id), 0) + 1 FROM audit_log ;",
+        (),
+        |row| row.get(0),
+    )?;
+    Ok(id)
+}
+
+/// Record an operation in the audit log, returning the 'id' of the
+/// operation. Called inside the same transaction as the operation,
+/// so the audit log is only written if the operation succeeds.
+fn insert_audit_log_entry(
+    connection: &rusqlite::Connection,
+    operation: &str,
+    filter: &str,
+    row_count: usize,
+) -> Result<u64, StorageError> {
+    let now_utc_time_seconds = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let id = next_audit_log_id(connection)?;
     connection.execute(
-        "CREATE TABLE records (
-              utc_time_seconds INTEGER,
-              duration_seconds INTEGER,
-              status           INTEGER,
-              executable       TEXT,
-              var1_name        VARCHAR(255),
-              var2_name        VARCHAR(255),
-              var3_name        VARCHAR(255),
-              var4_name        VARCHAR(255),
-              var5_name        VARCHAR(255),
-              var1_value       TEXT,
-              var2_value       TEXT,
-              var3_value       TEXT,
-              var4_value       TEXT,
-              var5_value       TEXT
-         );",
-        (), // no parameters needed to create a table.
+        "INSERT INTO audit_log (utc_time_seconds, user_name, operation, filter, row_count,
+                                id, source)
+         VALUES (:utc_time_seconds, :user_name, :operation, :filter, :row_count,
+                 :id, :source) ;",
+        named_params! {
+            ":utc_time_seconds": now_utc_time_seconds,
+            ":user_name": get_current_user_name(),
+            ":operation": operation,
+            ":filter": filter,
+            ":row_count": row_count,
+            ":id": id,
+            ":source": get_current_source_name(),
+        },
     )?;
+    Ok(id)
+}
+
+/// Consecutive writes of entries (such as by the recorder, every few
+/// seconds) within this duration are logged as one operation.
+const AUDIT_LOG_WRITE_ENTRIES_SECONDS: u64 = 60 * 60;
 
+/// Record a write of entries in the audit log, adding to the last
+/// operation when it is a recent write by the same user and source,
+/// so the audit log does not grow with every write of the recorder.
+fn insert_write_entries_audit_log_entry(
+    connection: &rusqlite::Connection,
+    row_count: usize,
+) -> Result<(), StorageError> {
+    let now_utc_time_seconds = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let count = connection.execute(
+        "UPDATE audit_log
+         SET row_count = row_count + :row_count
+         WHERE id = (SELECT MAX(id) FROM audit_log)
+               AND operation = 'write_entries'
+               AND user_name = :user_name
+               AND source = :source
+               AND utc_time_seconds >= :start_utc_time_seconds ;",
+        named_params! {
+            ":row_count": row_count,
+            ":user_name": get_current_user_name(),
+            ":source": get_current_source_name(),
+            ":start_utc_time_seconds": now_utc_time_seconds
+                .saturating_sub(AUDIT_LOG_WRITE_ENTRIES_SECONDS),
+        },
+    )?;
+    if count == 0 {
+        insert_audit_log_entry(connection, "write_entries", "", row_count)?;
+    }
     Ok(())
 }
 
-fn get_last_database_entry(connection: &rusqlite::Connection) -> Result<Entry> {
-    let mut statement = connection.prepare(
-        "SELECT utc_time_seconds, duration_seconds, status, executable, var1_name, var2_name, var3_name, var4_name, var5_name, var1_value, var2_value, var3_value, var4_value, var5_value
+/// The entry rows changed by an operation (such as a manual edit),
+/// saved in the 'undo_records' table so the operation can be undone
+/// with 'Storage::undo_operation'.
+///
+/// Called inside the same transaction as the operation; the rows are
+/// saved before the operation with 'save_before' (using the 'id' the
+/// audit log entry of the operation will have), and after with
+/// 'save_after'. Row ids are only used within the transaction, as
+/// SQLite may change them when vacuuming.
+struct UndoSnapshot {
+    audit_log_id: u64,
+    max_rowid: i64,
+}
+
+impl UndoSnapshot {
+    fn save_before(
+        connection: &rusqlite::Connection,
+        where_sql: &str,
+        parameters: &[rusqlite::types::Value],
+    ) -> Result<UndoSnapshot, StorageError> {
+        let audit_log_id = next_audit_log_id(connection)?;
+        let max_rowid: i64 =
+            connection.query_row("SELECT COALESCE(MAX(rowid), 0) FROM records ;", (), |row| {
+                row.get(0)
+            })?;
+
+        let column_names = INSERT_RECORDS_COLUMN_NAMES.join(", ");
+        let mut all_parameters: Vec<rusqlite::types::Value> = vec![(audit_log_id as i64).into()];
+        all_parameters.extend(parameters.iter().cloned());
+        connection.execute(
+            &format!(
+                "INSERT INTO undo_records (audit_log_id, is_after, record_rowid, {})
+                 SELECT ?, 0, rowid, {} FROM records WHERE {} ;",
+                column_names, column_names, where_sql
+            ),
+            rusqlite::params_from_iter(all_parameters),
+        )?;
+
+        Ok(UndoSnapshot {
+            audit_log_id,
+            max_rowid,
+        })
+    }
+
+    /// Save the rows changed by the operation; the rows saved before
+    /// (that were not deleted), and the new rows.
+    fn save_after(&self, connection: &rusqlite::Connection) -> Result<(), StorageError> {
+        let column_names = INSERT_RECORDS_COLUMN_NAMES.join(", ");
+        connection.execute(
+            &format!(
+                "INSERT INTO undo_records (audit_log_id, is_after, record_rowid, {})
+                 SELECT :audit_log_id, 1, rowid, {}
+                 FROM records
+                 WHERE rowid > :max_rowid
+                       OR rowid IN (SELECT record_rowid
+                                    FROM undo_records
+                                    WHERE audit_log_id = :audit_log_id
+                                          AND is_after = 0) ;",
+                column_names, column_names
+            ),
+            named_params! {
+                ":audit_log_id": self.audit_log_id,
+                ":max_rowid": self.max_rowid,
+            },
+        )?;
+        Ok(())
+    }
+}
+
+fn database_table_exists(
+    connection: &rusqlite::Connection,
+    table_name: &str,
+) -> Result<bool, StorageError> {
+    let mut statement = connection.prepare_cached(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = :table_name ;",
+    )?;
+    let count: i64 =
+        statement.query_row(named_params! {":table_name": table_name}, |row| row.get(0))?;
+    Ok(count > 0)
+}
+
+/// Get the last entry of the database, and the record interval the
+/// entry was recorded with (if known).
+fn get_last_database_entry(
+    connection: &rusqlite::Connection,
+) -> Result<(Entry, Option<u64>), StorageError> {
+    let mut statement = connection.prepare_cached(
+        "SELECT utc_time_seconds, duration_seconds, status, executable, var1_name, var2_name, var3_name, var4_name, var5_name, var1_value, var2_value, var3_value, var4_value, var5_value, utc_offset_seconds, window_title, working_directory, document_path, hostname, username, display, record_interval_seconds
          FROM records
          ORDER BY utc_time_seconds DESC
          LIMIT 1 ;"
     )?;
 
     let mut last_entry = Entry::empty();
+    let mut last_record_interval_seconds = None;
     let mut rows = statement.query([])?;
     while let Some(row) = rows.next()? {
         last_entry.utc_time_seconds = row.get_unwrap::<usize, u64>(INDEX_UTC_TIME_SECONDS);
@@ -89,13 +763,63 @@ fn get_last_database_entry(connection: &rusqlite::Connection) -> Result<Entry> {
         last_entry.vars.var3_value = row.get_unwrap::<usize, Option<String>>(INDEX_VAR3_VALUE);
         last_entry.vars.var4_value = row.get_unwrap::<usize, Option<String>>(INDEX_VAR4_VALUE);
         last_entry.vars.var5_value = row.get_unwrap::<usize, Option<String>>(INDEX_VAR5_VALUE);
+        last_entry.utc_offset_seconds =
+            row.get_unwrap::<usize, Option<i32>>(INDEX_UTC_OFFSET_SECONDS);
+        last_entry.vars.window_title = row.get_unwrap::<usize, Option<String>>(INDEX_WINDOW_TITLE);
+        last_entry.vars.working_directory =
+            row.get_unwrap::<usize, Option<String>>(INDEX_WORKING_DIRECTORY);
+        last_entry.vars.document_path =
+            row.get_unwrap::<usize, Option<String>>(INDEX_DOCUMENT_PATH);
+        last_entry.vars.hostname = row.get_unwrap::<usize, Option<String>>(INDEX_HOSTNAME);
+        last_entry.vars.username = row.get_unwrap::<usize, Option<String>>(INDEX_USERNAME);
+        last_entry.vars.display = row.get_unwrap::<usize, Option<String>>(INDEX_DISPLAY);
+        last_record_interval_seconds =
+            row.get_unwrap::<usize, Option<u64>>(INDEX_RECORD_INTERVAL_SECONDS);
     }
     debug!("Last Entry: {:?}", last_entry);
 
-    Ok(last_entry)
+    Ok((last_entry, last_record_interval_seconds))
+}
+
+/// Clamp the entry times at the start/end times, returning the
+/// clamped time and duration.
+///
+/// For example, if an entry spans from Monday 11:50pm to Tuesday
+/// 0:10am, this entry may be skipped or included. What we want is to
+/// cut off such an entry and "clamp" the time values of the entries
+/// to be only with-in the start/end time parameters. An entry
+/// spanning both the start and end times is clamped at both.
+pub(crate) fn clamp_entry_time_range(
+    utc_time_seconds: u64,
+    duration_seconds: u64,
+    start_utc_time_seconds: u64,
+    end_utc_time_seconds: u64,
+) -> (u64, u64) {
+    let first_utc_time_seconds = std::cmp::max(utc_time_seconds, start_utc_time_seconds);
+    let last_utc_time_seconds =
+        std::cmp::min(utc_time_seconds + duration_seconds, end_utc_time_seconds);
+    (
+        first_utc_time_seconds,
+        last_utc_time_seconds.saturating_sub(first_utc_time_seconds),
+    )
+}
+
+/// Does any of the entry time overlap the start/end times? Entries
+/// starting before the start time (such as before midnight) are read
+/// with the portion inside the range, by 'clamp_entry_time_range'.
+pub(crate) fn is_entry_in_time_range(
+    utc_time_seconds: u64,
+    duration_seconds: u64,
+    start_utc_time_seconds: u64,
+    end_utc_time_seconds: u64,
+) -> bool {
+    utc_time_seconds < end_utc_time_seconds
+        && utc_time_seconds + duration_seconds > start_utc_time_seconds
 }
 
-fn utc_seconds_to_datetime_local(utc_time_seconds: u64) -> chrono::DateTime<chrono::Local> {
+pub(crate) fn utc_seconds_to_datetime_local(
+    utc_time_seconds: u64,
+) -> chrono::DateTime<chrono::Local> {
     chrono::DateTime::<chrono::Utc>::from_naive_utc_and_offset(
         chrono::NaiveDateTime::from_timestamp_opt(utc_time_seconds.try_into().unwrap(), 0).unwrap(),
         chrono::Utc,
@@ -106,61 +830,14 @@ fn utc_seconds_to_datetime_local(utc_time_seconds: u64) -> chrono::DateTime<chro
 fn update_existing_entry_rows_into_database(
     connection: &rusqlite::Connection,
     existing_entries_dedup: &Vec<Entry>,
-) -> Result<()> {
-    let mut statement = connection.prepare(
+) -> Result<(), StorageError> {
+    let mut statement = connection.prepare_cached(
         "UPDATE records
              SET duration_seconds = :duration_seconds
              WHERE utc_time_seconds = :utc_time_seconds ;",
     )?;
     for entry in existing_entries_dedup {
-        let datetime = utc_seconds_to_datetime_local(entry.utc_time_seconds);
-
-        let duration = chrono::Duration::seconds(entry.duration_seconds.try_into().unwrap());
-        let duration_formatted = crate::format::format_duration(
-            duration,
-            crate::format::DurationFormat::HoursMinutesSeconds,
-        );
-        let time_formatted =
-            crate::format::format_datetime(datetime, crate::format::DateTimeFormat::Iso);
-
-        let executable = match &entry.vars.executable {
-            Some(value) => {
-                let executable_name = format_short_executable_name(value);
-                rusqlite::types::Value::Text(executable_name.to_string())
-            }
-            None => rusqlite::types::Value::Null,
-        };
-
-        let var1_name = convert_entry_var_to_sql_string_value(&entry.vars.var1_name);
-        let var2_name = convert_entry_var_to_sql_string_value(&entry.vars.var2_name);
-        let var3_name = convert_entry_var_to_sql_string_value(&entry.vars.var3_name);
-        let var4_name = convert_entry_var_to_sql_string_value(&entry.vars.var4_name);
-        let var5_name = convert_entry_var_to_sql_string_value(&entry.vars.var5_name);
-
-        let var1_value = convert_entry_var_to_sql_string_value(&entry.vars.var1_value);
-        let var2_value = convert_entry_var_to_sql_string_value(&entry.vars.var2_value);
-        let var3_value = convert_entry_var_to_sql_string_value(&entry.vars.var3_value);
-        let var4_value = convert_entry_var_to_sql_string_value(&entry.vars.var4_value);
-        let var5_value = convert_entry_var_to_sql_string_value(&entry.vars.var5_value);
-
-        debug!(
-            "UPDATE Entry [ Time: {}, Duration: {}, Status: {:?}, Executable: {:?}, Var1: {:?} = {:?}, Var2: {:?} = {:?}, Var3: {:?} = {:?}, Var4: {:?} = {:?}, Var5: {:?} = {:?} ]",
-            time_formatted,
-            duration_formatted,
-            entry.status,
-            executable,
-            var1_name,
-            var1_value,
-            var2_name,
-            var2_value,
-            var3_name,
-            var3_value,
-            var4_name,
-            var4_value,
-            var5_name,
-            var5_value,
-        );
-
+        debug_entry_row("UPDATE", entry);
         statement.execute(named_params! {
             ":utc_time_seconds": rusqlite::types::Value::Integer(entry.utc_time_seconds as i64),
             ":duration_seconds": rusqlite::types::Value::Integer(entry.duration_seconds as i64)
@@ -187,122 +864,200 @@ fn convert_sql_value_to_option_string(sql_value: &rusqlite::types::Value) -> Opt
     }
 }
 
-fn insert_new_entry_rows_into_database(
-    connection: &rusqlite::Connection,
-    new_entries_dedup: &Vec<Entry>,
-) -> Result<()> {
-    let mut statement = connection.prepare(
-        "INSERT INTO records (utc_time_seconds,
-                                  duration_seconds,
-                                  status,
-                                  executable,
-                                  var1_name,
-                                  var2_name,
-                                  var3_name,
-                                  var4_name,
-                                  var5_name,
-                                  var1_value,
-                                  var2_value,
-                                  var3_value,
-                                  var4_value,
-                                  var5_value)
-             VALUES (:utc_time_seconds,
-                     :duration_seconds,
-                     :status,
-                     :executable,
-                     :var1_name,
-                     :var2_name,
-                     :var3_name,
-                     :var4_name,
-                     :var5_name,
-                     :var1_value,
-                     :var2_value,
-                     :var3_value,
-                     :var4_value,
-                     :var5_value)",
-    )?;
+/// How long to wait before retrying a backup step, when the database
+/// is locked by another connection.
+const BACKUP_RETRY_PAUSE: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Copy all the pages of the backup, retrying while the database is
+/// locked (up to 'BUSY_TIMEOUT').
+///
+/// All the pages are copied in one step (in one read transaction),
+/// so writes by other processes cannot restart the backup part way.
+fn run_backup_steps(backup: &rusqlite::backup::Backup) -> Result<(), StorageError> {
+    let start_time = std::time::Instant::now();
+    loop {
+        match backup.step(-1)? {
+            rusqlite::backup::StepResult::Done => return Ok(()),
+            rusqlite::backup::StepResult::More => (),
+            _ if start_time.elapsed() < BUSY_TIMEOUT => std::thread::sleep(BACKUP_RETRY_PAUSE),
+            _ => {
+                return Err(StorageError::Locked(rusqlite::Error::SqliteFailure(
+                    rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_BUSY),
+                    Some("Timed out waiting for the database lock.".to_string()),
+                )))
+            }
+        }
+    }
+}
 
-    for entry in new_entries_dedup {
-        let datetime = chrono::DateTime::<chrono::Utc>::from_naive_utc_and_offset(
-            chrono::NaiveDateTime::from_timestamp_opt(
-                entry.utc_time_seconds.try_into().unwrap(),
-                0,
-            )
-            .unwrap(),
-            chrono::Utc,
-        )
-        .with_timezone(&chrono::Local);
+/// The columns of the 'records' table written for each new entry, in
+/// the order of the values given by 'new_entry_row_values'.
+const INSERT_RECORDS_COLUMN_NAMES: &[&str] = &[
+    "utc_time_seconds",
+    "duration_seconds",
+    "status",
+    "executable",
+    "var1_name",
+    "var2_name",
+    "var3_name",
+    "var4_name",
+    "var5_name",
+    "var1_value",
+    "var2_value",
+    "var3_value",
+    "var4_value",
+    "var5_value",
+    "utc_offset_seconds",
+    "window_title",
+    "working_directory",
+    "document_path",
+    "hostname",
+    "username",
+    "display",
+    "record_interval_seconds",
+];
+
+/// The number of rows inserted by each multi-row INSERT statement,
+/// keeping the number of parameters (22 for each row) below the
+/// smallest limit of SQLite versions (999).
+const INSERT_RECORDS_ROWS_PER_STATEMENT: usize = 40;
+
+/// An INSERT statement writing the number of rows to the 'records'
+/// table.
+fn insert_records_statement_sql(row_count: usize) -> String {
+    let row_parameters = format!(
+        "({})",
+        vec!["?"; INSERT_RECORDS_COLUMN_NAMES.len()].join(", ")
+    );
+    format!(
+        "INSERT INTO records ({}) VALUES {} ;",
+        INSERT_RECORDS_COLUMN_NAMES.join(", "),
+        vec![row_parameters; row_count].join(", ")
+    )
+}
 
-        let duration = chrono::Duration::seconds(entry.duration_seconds.try_into().unwrap());
-        let duration_formatted = crate::format::format_duration(
-            duration,
-            crate::format::DurationFormat::HoursMinutesSeconds,
-        );
-        let time_formatted =
-            crate::format::format_datetime(datetime, crate::format::DateTimeFormat::Iso);
+/// The values of the row of the new entry, in the order of
+/// 'INSERT_RECORDS_COLUMN_NAMES'.
+fn new_entry_row_values(
+    entry: &Entry,
+    record_interval_seconds: Option<u64>,
+) -> Vec<rusqlite::types::Value> {
+    let status_num = match entry.status.to_i64() {
+        Some(value) => value,
+        None => panic!("Invalid EntryStatus."),
+    };
+    let executable = match &entry.vars.executable {
+        Some(value) => {
+            let executable_name = format_short_executable_name(value);
+            rusqlite::types::Value::Text(executable_name.to_string())
+        }
+        None => rusqlite::types::Value::Null,
+    };
+    let utc_offset_seconds = match entry.utc_offset_seconds {
+        Some(value) => rusqlite::types::Value::Integer(value.into()),
+        None => rusqlite::types::Value::Null,
+    };
+    let record_interval_seconds = match record_interval_seconds {
+        Some(value) => rusqlite::types::Value::Integer(value as i64),
+        None => rusqlite::types::Value::Null,
+    };
+
+    vec![
+        rusqlite::types::Value::Integer(entry.utc_time_seconds as i64),
+        rusqlite::types::Value::Integer(entry.duration_seconds as i64),
+        rusqlite::types::Value::Integer(status_num),
+        executable,
+        convert_entry_var_to_sql_string_value(&entry.vars.var1_name),
+        convert_entry_var_to_sql_string_value(&entry.vars.var2_name),
+        convert_entry_var_to_sql_string_value(&entry.vars.var3_name),
+        convert_entry_var_to_sql_string_value(&entry.vars.var4_name),
+        convert_entry_var_to_sql_string_value(&entry.vars.var5_name),
+        convert_entry_var_to_sql_string_value(&entry.vars.var1_value),
+        convert_entry_var_to_sql_string_value(&entry.vars.var2_value),
+        convert_entry_var_to_sql_string_value(&entry.vars.var3_value),
+        convert_entry_var_to_sql_string_value(&entry.vars.var4_value),
+        convert_entry_var_to_sql_string_value(&entry.vars.var5_value),
+        utc_offset_seconds,
+        convert_entry_var_to_sql_string_value(&entry.vars.window_title),
+        convert_entry_var_to_sql_string_value(&entry.vars.working_directory),
+        convert_entry_var_to_sql_string_value(&entry.vars.document_path),
+        convert_entry_var_to_sql_string_value(&entry.vars.hostname),
+        convert_entry_var_to_sql_string_value(&entry.vars.username),
+        convert_entry_var_to_sql_string_value(&entry.vars.display),
+        record_interval_seconds,
+    ]
+}
 
-        let utc_time_seconds = rusqlite::types::Value::Integer(entry.utc_time_seconds as i64);
-        let duration_seconds = rusqlite::types::Value::Integer(entry.duration_seconds as i64);
+/// Log the entry written to a row, with the SQL operation (such as
+/// "INSERT").
+fn debug_entry_row(operation: &str, entry: &Entry) {
+    // Formatting the time and duration is slow compared to writing
+    // the row, so it is skipped unless debug messages are logged.
+    if !log::log_enabled!(log::Level::Debug) {
+        return;
+    }
 
-        let status_num = match entry.status.to_i64() {
-            Some(value) => value,
-            None => panic!("Invalid EntryStatus."),
-        };
-        let status = rusqlite::types::Value::Integer(status_num);
+    let datetime = utc_seconds_to_datetime_local(entry.utc_time_seconds);
+    let duration = chrono::Duration::seconds(entry.duration_seconds.try_into().unwrap());
+    let duration_formatted = crate::format::format_duration(
+        duration,
+        crate::format::DurationFormat::HoursMinutesSeconds,
+    );
+    let time_formatted =
+        crate::format::format_datetime(datetime, crate::format::DateTimeFormat::Iso);
+
+    debug!(
+        "{} Entry [ Time: {}, Duration: {}, Status: {:?}, Executable: {:?}, Var1: {:?} = {:?}, Var2: {:?} = {:?}, Var3: {:?} = {:?}, Var4: {:?} = {:?}, Var5: {:?} = {:?} ]",
+        operation,
+        time_formatted,
+        duration_formatted,
+        entry.status,
+        entry.vars.executable.as_deref().map(format_short_executable_name),
+        entry.vars.var1_name,
+        entry.vars.var1_value,
+        entry.vars.var2_name,
+        entry.vars.var2_value,
+        entry.vars.var3_name,
+        entry.vars.var3_value,
+        entry.vars.var4_name,
+        entry.vars.var4_value,
+        entry.vars.var5_name,
+        entry.vars.var5_value,
+    );
+}
 
-        let executable = match &entry.vars.executable {
-            Some(value) => {
-                let executable_name = format_short_executable_name(value);
-                rusqlite::types::Value::Text(executable_name.to_string())
+fn insert_new_entry_rows_into_database(
+    connection: &rusqlite::Connection,
+    new_entries_dedup: &[Entry],
+    record_interval_seconds: Option<u64>,
+) -> Result<(), StorageError> {
+    // The entries are inserted in chunks of rows, with a single
+    // statement for each chunk, and the remaining entries are
+    // inserted one row at a time; so only two statements are
+    // prepared (and cached).
+    let mut chunks = new_entries_dedup.chunks_exact(INSERT_RECORDS_ROWS_PER_STATEMENT);
+    if chunks.len() > 0 {
+        let mut statement = connection.prepare_cached(&insert_records_statement_sql(
+            INSERT_RECORDS_ROWS_PER_STATEMENT,
+        ))?;
+        for chunk in &mut chunks {
+            let mut values = Vec::with_capacity(chunk.len() * INSERT_RECORDS_COLUMN_NAMES.len());
+            for entry in chunk {
+                debug_entry_row("INSERT", entry);
+                values.extend(new_entry_row_values(entry, record_interval_seconds));
             }
-            None => rusqlite::types::Value::Null,
-        };
-
-        let var1_name = convert_entry_var_to_sql_string_value(&entry.vars.var1_name);
-        let var2_name = convert_entry_var_to_sql_string_value(&entry.vars.var2_name);
-        let var3_name = convert_entry_var_to_sql_string_value(&entry.vars.var3_name);
-        let var4_name = convert_entry_var_to_sql_string_value(&entry.vars.var4_name);
-        let var5_name = convert_entry_var_to_sql_string_value(&entry.vars.var5_name);
-
-        let var1_value = convert_entry_var_to_sql_string_value(&entry.vars.var1_value);
-        let var2_value = convert_entry_var_to_sql_string_value(&entry.vars.var2_value);
-        let var3_value = convert_entry_var_to_sql_string_value(&entry.vars.var3_value);
-        let var4_value = convert_entry_var_to_sql_string_value(&entry.vars.var4_value);
-        let var5_value = convert_entry_var_to_sql_string_value(&entry.vars.var5_value);
-
-        debug!("INSERT Entry [ Time: {}, Duration: {}, Status: {:?}, Executable: {:?}, Var1: {:?} = {:?}, Var2: {:?} = {:?}, Var3: {:?} = {:?}, Var4: {:?} = {:?}, Var5: {:?} = {:?} ]",
-               time_formatted,
-               duration_formatted,
-               entry.status,
-               &executable,
-               var1_name,
-               var1_value,
-               var2_name,
-               var2_value,
-               var3_name,
-               var3_value,
-               var4_name,
-               var4_value,
-               var5_name,
-               var5_value,
-        );
-
-        statement.execute(named_params! {
-            ":utc_time_seconds": utc_time_seconds,
-            ":duration_seconds": duration_seconds,
-            ":status": status,
-            ":executable": executable,
-            ":var1_name": var1_name,
-            ":var2_name": var2_name,
-            ":var3_name": var3_name,
-            ":var4_name": var4_name,
-            ":var5_name": var5_name,
-            ":var1_value": var1_value,
-            ":var2_value": var2_value,
-            ":var3_value": var3_value,
-            ":var4_value": var4_value,
-            ":var5_value": var5_value,
-        })?;
+            statement.execute(rusqlite::params_from_iter(values))?;
+        }
+    }
+
+    let remaining_entries = chunks.remainder();
+    if !remaining_entries.is_empty() {
+        let mut statement = connection.prepare_cached(&insert_records_statement_sql(1))?;
+        for entry in remaining_entries {
+            debug_entry_row("INSERT", entry);
+            let values = new_entry_row_values(entry, record_interval_seconds);
+            statement.execute(rusqlite::params_from_iter(values))?;
+        }
     }
 
     Ok(())
@@ -317,6 +1072,10 @@ pub struct Entries {
     start_datetime: chrono::DateTime<chrono::Local>,
     end_datetime: chrono::DateTime<chrono::Local>,
     entries: Vec<Entry>,
+    day_notes: Vec<DayNote>,
+    host_busy_periods: Vec<HostBusyPeriod>,
+    window_color_periods: Vec<WindowColorPeriod>,
+    plans: Vec<WeekPlan>,
 }
 
 impl Entries {
@@ -337,7 +1096,12 @@ impl Entries {
         &self.entries[..]
     }
 
-    // Get a slice of the entries for the datetime range given.
+    /// Get a slice of the entries starting with-in the datetime range
+    /// given, including the entries starting at the start and end
+    /// datetimes.
+    ///
+    /// The entries are sorted by time, so the slice is found with a
+    /// binary search.
     pub fn datetime_range_entries(
         &self,
         start_datetime: chrono::DateTime<chrono::Local>,
@@ -346,29 +1110,13 @@ impl Entries {
         let start_of_time = start_datetime.timestamp() as u64;
         let end_of_time = end_datetime.timestamp() as u64;
 
-        let mut count: usize = 0;
-        let mut start_index: usize = usize::MAX;
-        let mut end_index: usize = usize::MIN;
-        for (i, entry) in self.entries.iter().enumerate() {
-            if (entry.utc_time_seconds > start_of_time) && (entry.utc_time_seconds < end_of_time) {
-                start_index = std::cmp::min(start_index, i);
-                end_index = std::cmp::max(end_index, i);
-                count = count + 1;
-            }
-        }
-
-        if count == 0 {
-            if self.entries.is_empty() {
-                // The full range of entries, when entries is empty is
-                // an empty slice.
-                &self.entries[..]
-            } else {
-                // There is at least one entry, which we can use.
-                &self.entries[0..0]
-            }
-        } else {
-            &self.entries[start_index..end_index]
-        }
+        let start_index = self
+            .entries
+            .partition_point(|x| x.utc_time_seconds < start_of_time);
+        let end_index = self
+            .entries
+            .partition_point(|x| x.utc_time_seconds <= end_of_time);
+        &self.entries[start_index..std::cmp::max(start_index, end_index)]
     }
 
     pub fn is_datetime_range_empty(
@@ -383,6 +1131,41 @@ impl Entries {
     pub fn is_empty(&self) -> bool {
         self.entries.is_empty()
     }
+
+    // Get all the day notes.
+    pub fn day_notes(&self) -> &[DayNote] {
+        &self.day_notes[..]
+    }
+
+    // Get the note for the date given, if the user wrote one.
+    pub fn day_note(&self, date: chrono::NaiveDate) -> Option<&DayNote> {
+        self.day_notes.iter().find(|x| x.date == date)
+    }
+
+    // Get the periods the machine was busy, sorted by time.
+    pub fn host_busy_periods(&self) -> &[HostBusyPeriod] {
+        &self.host_busy_periods[..]
+    }
+
+    // Get the colors sampled from the focused windows, sorted by
+    // time.
+    pub fn window_color_periods(&self) -> &[WindowColorPeriod] {
+        &self.window_color_periods[..]
+    }
+
+    // Get the planned durations of the weeks, sorted by week.
+    pub fn plans(&self) -> &[WeekPlan] {
+        &self.plans[..]
+    }
+
+    // Get the planned durations of the week starting on the date
+    // given.
+    pub fn week_plans(&self, week_start_date: chrono::NaiveDate) -> Vec<&WeekPlan> {
+        self.plans
+            .iter()
+            .filter(|x| x.week_start_date == week_start_date)
+            .collect()
+    }
 }
 
 #[derive(Default)]
@@ -390,6 +1173,10 @@ pub struct EntriesBuilder {
     start_datetime: chrono::DateTime<chrono::Local>,
     end_datetime: chrono::DateTime<chrono::Local>,
     entries: Vec<Entry>,
+    day_notes: Vec<DayNote>,
+    host_busy_periods: Vec<HostBusyPeriod>,
+    window_color_periods: Vec<WindowColorPeriod>,
+    plans: Vec<WeekPlan>,
 }
 
 impl EntriesBuilder {
@@ -398,6 +1185,10 @@ impl EntriesBuilder {
             start_datetime: chrono::DateTime::<chrono::Local>::MIN_UTC.into(),
             end_datetime: chrono::DateTime::<chrono::Local>::MAX_UTC.into(),
             entries: Vec::new(),
+            day_notes: Vec::new(),
+            host_busy_periods: Vec::new(),
+            window_color_periods: Vec::new(),
+            plans: Vec::new(),
         }
     }
 
@@ -416,50 +1207,131 @@ impl EntriesBuilder {
         self
     }
 
+    pub fn day_notes(mut self, day_notes: Vec<DayNote>) -> EntriesBuilder {
+        self.day_notes = day_notes;
+        self
+    }
+
+    pub fn host_busy_periods(mut self, host_busy_periods: Vec<HostBusyPeriod>) -> EntriesBuilder {
+        self.host_busy_periods = host_busy_periods;
+        self
+    }
+
+    pub fn window_color_periods(
+        mut self,
+        window_color_periods: Vec<WindowColorPeriod>,
+    ) -> EntriesBuilder {
+        self.window_color_periods = window_color_periods;
+        self
+    }
+
+    pub fn plans(mut self, plans: Vec<WeekPlan>) -> EntriesBuilder {
+        self.plans = plans;
+        self
+    }
+
+    /// Build the entries, sorted by time.
     pub fn build(self) -> Entries {
+        let mut entries = self.entries;
+        entries.sort_by_key(|x| x.utc_time_seconds);
         Entries {
             start_datetime: self.start_datetime,
             end_datetime: self.end_datetime,
-            entries: self.entries,
+            entries,
+            day_notes: self.day_notes,
+            host_busy_periods: self.host_busy_periods,
+            window_color_periods: self.window_color_periods,
+            plans: self.plans,
         }
     }
 }
 
+/// The storage of recorded entries.
+///
+/// 'Storage' (a SQLite database file) is the backend used by the
+/// applications. 'MemoryStorage' keeps the entries in memory, for
+/// users (such as tests) that should not touch the filesystem.
+pub trait StorageBackend {
+    /// Open the storage at the file path, creating the storage if
+    /// 'auto_create_database_file' is true.
+    fn open(
+        database_file_path: &Path,
+        record_interval_seconds: u64,
+        auto_create_database_file: bool,
+    ) -> Result<Self, StorageError>
+    where
+        Self: Sized;
+
+    /// Add entries to be written by 'write_entries'.
+    fn insert_entries(&mut self, entries: &[Entry]);
+
+    /// Read the entries between the start and end times, clamped to
+    /// the start and end times.
+    fn read_entries(
+        &mut self,
+        start_utc_time_seconds: u64,
+        end_utc_time_seconds: u64,
+    ) -> Result<Entries, StorageError>;
+
+    /// Write the inserted entries, merging entries that continue
+    /// the last written entry.
+    fn write_entries(&mut self) -> Result<(), StorageError>;
+}
+
 pub struct Storage {
     connection: rusqlite::Connection,
     entries: Vec<Entry>,
     record_interval_seconds: u64,
+    max_merged_entry_seconds: u64,
+    has_utc_offset_column: bool,
+    has_window_title_column: bool,
+    has_working_directory_column: bool,
+    has_document_path_column: bool,
+    has_hostname_username_columns: bool,
+    has_display_column: bool,
+    use_recorded_utc_offset: bool,
+    optimize_on_close: bool,
+    record_undo: bool,
 }
 
 impl Storage {
-    fn open(
+    pub(crate) fn open(
         database_file_path: &Path,
         record_interval_seconds: u64,
         auto_create_database_file: bool,
-    ) -> Result<Storage> {
+    ) -> Result<Storage, StorageError> {
         debug!("Opened Time Tracker Storage.");
 
         debug!("Storage file: {:?}", database_file_path);
         let file_exists = database_file_path.is_file();
 
         if !auto_create_database_file && !file_exists {
-            return Err(anyhow!(
-                "Database storage file does not exist: {}",
-                database_file_path.display()
-            ));
+            return Err(StorageError::Missing(database_file_path.to_path_buf()));
         }
 
-        let db_open_flags = rusqlite::OpenFlags::SQLITE_OPEN_CREATE
-            | rusqlite::OpenFlags::SQLITE_OPEN_READ_WRITE
-            | rusqlite::OpenFlags::SQLITE_OPEN_NO_MUTEX;
+        // Read-only connections never take the write lock, so readers
+        // (such as the GUI) do not block the recorder.
+        let db_open_flags = match auto_create_database_file {
+            true => {
+                rusqlite::OpenFlags::SQLITE_OPEN_CREATE
+                    | rusqlite::OpenFlags::SQLITE_OPEN_READ_WRITE
+                    | rusqlite::OpenFlags::SQLITE_OPEN_NO_MUTEX
+            }
+            false => {
+                rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY
+                    | rusqlite::OpenFlags::SQLITE_OPEN_NO_MUTEX
+            }
+        };
         let connection = rusqlite::Connection::open_with_flags(database_file_path, db_open_flags)?;
+        connection.set_prepared_statement_cache_capacity(PREPARED_STATEMENT_CACHE_CAPACITY);
+        connection.busy_timeout(BUSY_TIMEOUT)?;
 
         if !file_exists {
-            initialize_database(&connection)?;
-
             // Change the permissions on the database file, so
             // that ONLY the current user can read it. This
-            // reduces the issue of privacy.
+            // reduces the issue of privacy. The permissions are
+            // set before anything is written, so the journal files
+            // created by SQLite get the same permissions.
             let f =
                 File::open(database_file_path).expect("Could not open file to set permissions.");
             let mut permissions = f
@@ -471,30 +1343,63 @@ impl Storage {
                 .expect("Could not open file to set permissions.");
         }
 
+        if auto_create_database_file {
+            // The journal mode is stored in the database file, so
+            // the read-only connections use it too.
+            let journal_mode: String =
+                connection
+                    .pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get(0))?;
+            debug!("Journal mode: {:?}", journal_mode);
+            migrate_database(&connection)?;
+            initialize_views(&connection)?;
+        }
+
+        let has_utc_offset_column = records_column_exists(&connection, "utc_offset_seconds")?;
+        let has_window_title_column = records_column_exists(&connection, "window_title")?;
+        let has_working_directory_column = records_column_exists(&connection, "working_directory")?;
+        let has_document_path_column = records_column_exists(&connection, "document_path")?;
+        let has_hostname_username_columns = records_column_exists(&connection, "hostname")?
+            && records_column_exists(&connection, "username")?;
+        let has_display_column = records_column_exists(&connection, "display")?;
+
         let entries = Vec::<_>::new();
         Ok(Storage {
             connection,
             entries,
             record_interval_seconds,
+            max_merged_entry_seconds: DEFAULT_MAX_MERGED_ENTRY_SECONDS,
+            has_utc_offset_column,
+            has_window_title_column,
+            has_working_directory_column,
+            has_document_path_column,
+            has_hostname_username_columns,
+            has_display_column,
+            use_recorded_utc_offset: false,
+            optimize_on_close: false,
+            record_undo: false,
         })
     }
 
+    /// Open the existing database file for reading only. The file is
+    /// never created (or migrated), and the write methods are not
+    /// available on the returned storage.
     pub fn open_as_read_only(
         database_file_path: &Path,
         record_interval_seconds: u64,
-    ) -> Result<Storage> {
+    ) -> Result<ReadOnlyStorage, StorageError> {
         let auto_create_database_file = false;
-        Storage::open(
+        let storage = Storage::open(
             database_file_path,
             record_interval_seconds,
             auto_create_database_file,
-        )
+        )?;
+        Ok(ReadOnlyStorage { storage })
     }
 
     pub fn open_as_read_write(
         database_file_path: &Path,
         record_interval_seconds: u64,
-    ) -> Result<Storage> {
+    ) -> Result<Storage, StorageError> {
         let auto_create_database_file = true;
         Storage::open(
             database_file_path,
@@ -503,56 +1408,166 @@ impl Storage {
         )
     }
 
-    pub fn insert_entries(&mut self, entries: &Vec<Entry>) {
+    /// The schema version of the database; 0 for databases created
+    /// before the schema was versioned (and not yet opened for
+    /// writing).
+    pub fn schema_version(&self) -> Result<u32, StorageError> {
+        read_schema_version(&self.connection)
+    }
+
+    /// Save the entries changed by 'delete_entries',
+    /// 'update_entries_values' and 'insert_manual_entry', so the
+    /// changes can be undone with 'undo_operation'. Used for manual
+    /// edits by the user; off by default.
+    pub fn set_record_undo(&mut self, value: bool) {
+        self.record_undo = value;
+    }
+
+    /// Set the maximum duration of an entry, when entries are merged
+    /// together by 'write_entries'.
+    pub fn set_max_merged_entry_seconds(&mut self, value: u64) {
+        self.max_merged_entry_seconds = value;
+    }
+
+    /// Read entries in the local time they were recorded in (using
+    /// the recorded offset from UTC), rather than the machine's
+    /// current timezone, so entries stay in the original local day
+    /// after the timezone changes.
+    pub fn set_use_recorded_utc_offset(&mut self, value: bool) {
+        self.use_recorded_utc_offset = value;
+    }
+
+    /// Run 'PRAGMA optimize' when the storage is closed, so SQLite
+    /// can keep the query planner statistics up to date. Useful for
+    /// long running writers, such as the recorder.
+    pub fn set_optimize_on_close(&mut self, value: bool) {
+        self.optimize_on_close = value;
+    }
+
+    pub fn insert_entries(&mut self, entries: &[Entry]) {
         for entry in entries {
             debug!("Insert Entry: {:?}", entry);
             self.entries.push(entry.clone());
         }
     }
 
-    pub fn read_entries(
-        &mut self,
+    /// Call the callback with each entry between the start and end
+    /// times (clamped to the start and end times), as the entries are
+    /// read from the database, so the entries of a large range are
+    /// never all in memory at once. Stops at the first error returned
+    /// by the callback.
+    ///
+    /// The entries are given in order of their (recorded) time; when
+    /// entries are read in the recorded offset from UTC, the shifted
+    /// times may not be in order.
+    pub fn stream_entries<F, E>(
+        &self,
         start_utc_time_seconds: u64,
         end_utc_time_seconds: u64,
-    ) -> Result<Entries> {
-        let mut statement = self.connection.prepare(
-            "SELECT utc_time_seconds, duration_seconds, status,
+        mut callback: F,
+    ) -> Result<(), E>
+    where
+        F: FnMut(Entry) -> Result<(), E>,
+        E: From<StorageError>,
+    {
+        let utc_offset_column = match self.has_utc_offset_column {
+            true => "utc_offset_seconds",
+            false => "NULL AS utc_offset_seconds",
+        };
+        let window_title_column = match self.has_window_title_column {
+            true => "window_title",
+            false => "NULL AS window_title",
+        };
+        let working_directory_column = match self.has_working_directory_column {
+            true => "working_directory",
+            false => "NULL AS working_directory",
+        };
+        let document_path_column = match self.has_document_path_column {
+            true => "document_path",
+            false => "NULL AS document_path",
+        };
+        let hostname_username_columns = match self.has_hostname_username_columns {
+            true => "hostname, username",
+            false => "NULL AS hostname, NULL AS username",
+        };
+        let display_column = match self.has_display_column {
+            true => "display",
+            false => "NULL AS display",
+        };
+
+        // Entries shifted by their recorded offset may move into (or
+        // out of) the range, so the query must include them.
+        let (query_start_utc_time_seconds, query_end_utc_time_seconds) =
+            match self.use_recorded_utc_offset {
+                true => (
+                    start_utc_time_seconds.saturating_sub(MAX_UTC_OFFSET_DIFFERENCE_SECONDS),
+                    end_utc_time_seconds.saturating_add(MAX_UTC_OFFSET_DIFFERENCE_SECONDS),
+                ),
+                false => (start_utc_time_seconds, end_utc_time_seconds),
+            };
+
+        let mut statement = self
+            .connection
+            .prepare_cached(&format!(
+                "SELECT utc_time_seconds, duration_seconds, status,
                         executable,
                         var1_name, var2_name, var3_name, var4_name, var5_name,
-                        var1_value, var2_value, var3_value, var4_value, var5_value
+                        var1_value, var2_value, var3_value, var4_value, var5_value,
+                        {}, {}, {}, {}, {}, {}
                  FROM records
-                 WHERE utc_time_seconds > :start_utc_time_seconds
+                 WHERE utc_time_seconds + duration_seconds > :start_utc_time_seconds
                        AND utc_time_seconds < :end_utc_time_seconds
                  ORDER BY utc_time_seconds ASC ;",
-        )?;
-        let mut rows = statement.query(named_params! {
-            ":start_utc_time_seconds": rusqlite::types::Value::Integer(start_utc_time_seconds as i64),
-            ":end_utc_time_seconds": rusqlite::types::Value::Integer(end_utc_time_seconds as i64),
-        })?;
-
-        let mut entries = Vec::<Entry>::new();
-        while let Some(row) = rows.next()? {
+                utc_offset_column,
+                window_title_column,
+                working_directory_column,
+                document_path_column,
+                hostname_username_columns,
+                display_column
+            ))
+            .map_err(StorageError::from)?;
+        let mut rows = statement
+            .query(named_params! {
+                ":start_utc_time_seconds": rusqlite::types::Value::Integer(query_start_utc_time_seconds as i64),
+                ":end_utc_time_seconds": rusqlite::types::Value::Integer(query_end_utc_time_seconds as i64),
+            })
+            .map_err(StorageError::from)?;
+
+        while let Some(row) = rows.next().map_err(StorageError::from)? {
             let mut utc_time_seconds: u64 = row.get_unwrap(INDEX_UTC_TIME_SECONDS);
-            let mut duration_seconds: u64 = row.get_unwrap(INDEX_DURATION_SECONDS);
+            let duration_seconds: u64 = row.get_unwrap(INDEX_DURATION_SECONDS);
             let status_num: u64 = row.get_unwrap(INDEX_STATUS);
             let status: EntryStatus = FromPrimitive::from_u64(status_num).unwrap();
-
-            // Clamp the entries at the start/end times.
-            //
-            // For example, if an entry spans from Monday 11:50pm to
-            // Tuesday 0:10am, this entry may be skipped or
-            // included. What we want is to cut off such an entry and
-            // "clamp" the time values of the entries to be only
-            // with-in the start/end time parameters.
-            let last_utc_time_seconds = utc_time_seconds + duration_seconds;
-            if utc_time_seconds < start_utc_time_seconds {
-                let difference = start_utc_time_seconds - utc_time_seconds;
-                utc_time_seconds = start_utc_time_seconds;
-                duration_seconds = duration_seconds - difference
-            } else if last_utc_time_seconds > end_utc_time_seconds {
-                let difference = last_utc_time_seconds - end_utc_time_seconds;
-                duration_seconds = duration_seconds - difference
+            let utc_offset_seconds: Option<i32> = row.get_unwrap(INDEX_UTC_OFFSET_SECONDS);
+
+            if self.use_recorded_utc_offset {
+                if let Some(recorded_utc_offset_seconds) = utc_offset_seconds {
+                    let current_utc_offset_seconds =
+                        utc_seconds_to_datetime_local(utc_time_seconds)
+                            .offset()
+                            .local_minus_utc();
+                    utc_time_seconds = shift_utc_time_to_recorded_offset(
+                        utc_time_seconds,
+                        recorded_utc_offset_seconds,
+                        current_utc_offset_seconds,
+                    );
+                }
             }
+            if !is_entry_in_time_range(
+                utc_time_seconds,
+                duration_seconds,
+                start_utc_time_seconds,
+                end_utc_time_seconds,
+            ) {
+                continue;
+            }
+
+            let (utc_time_seconds, duration_seconds) = clamp_entry_time_range(
+                utc_time_seconds,
+                duration_seconds,
+                start_utc_time_seconds,
+                end_utc_time_seconds,
+            );
 
             let mut vars = EntryVariablesList::empty();
             vars.executable = convert_sql_value_to_option_string(&row.get_unwrap(INDEX_EXECUTABLE));
@@ -566,59 +1581,2101 @@ impl Storage {
             vars.var3_value = convert_sql_value_to_option_string(&row.get_unwrap(INDEX_VAR3_VALUE));
             vars.var4_value = convert_sql_value_to_option_string(&row.get_unwrap(INDEX_VAR4_VALUE));
             vars.var5_value = convert_sql_value_to_option_string(&row.get_unwrap(INDEX_VAR5_VALUE));
+            vars.window_title =
+                convert_sql_value_to_option_string(&row.get_unwrap(INDEX_WINDOW_TITLE));
+            vars.working_directory =
+                convert_sql_value_to_option_string(&row.get_unwrap(INDEX_WORKING_DIRECTORY));
+            vars.document_path =
+                convert_sql_value_to_option_string(&row.get_unwrap(INDEX_DOCUMENT_PATH));
+            vars.hostname = convert_sql_value_to_option_string(&row.get_unwrap(INDEX_HOSTNAME));
+            vars.username = convert_sql_value_to_option_string(&row.get_unwrap(INDEX_USERNAME));
+            vars.display = convert_sql_value_to_option_string(&row.get_unwrap(INDEX_DISPLAY));
+
+            let entry = Entry::new(utc_time_seconds, duration_seconds, status, vars)
+                .with_utc_offset_seconds(utc_offset_seconds);
+            callback(entry)?;
+        }
+
+        Ok(())
+    }
 
-            let entry = Entry::new(utc_time_seconds, duration_seconds, status, vars);
+    pub fn read_entries(
+        &mut self,
+        start_utc_time_seconds: u64,
+        end_utc_time_seconds: u64,
+    ) -> Result<Entries, StorageError> {
+        let mut entries = Vec::<Entry>::new();
+        self.stream_entries(start_utc_time_seconds, end_utc_time_seconds, |entry| {
             entries.push(entry);
+            Ok::<(), StorageError>(())
+        })?;
+        if self.use_recorded_utc_offset {
+            entries.sort_by_key(|x| x.utc_time_seconds);
         }
 
+        let start_datetime = utc_seconds_to_datetime_local(start_utc_time_seconds);
+        let end_datetime = utc_seconds_to_datetime_local(end_utc_time_seconds);
+        let day_notes =
+            self.read_day_notes(start_datetime.date_naive(), end_datetime.date_naive())?;
+        let host_busy_periods =
+            self.read_host_busy_periods(start_utc_time_seconds, end_utc_time_seconds)?;
+        let window_color_periods =
+            self.read_window_color_periods(start_utc_time_seconds, end_utc_time_seconds)?;
+        // Include the plan of a week that starts before the range.
+        let plans = self.read_plans(
+            start_datetime.date_naive() - chrono::Duration::days(6),
+            end_datetime.date_naive(),
+        )?;
+
         Ok(Entries::builder()
-            .start_datetime(utc_seconds_to_datetime_local(start_utc_time_seconds))
-            .end_datetime(utc_seconds_to_datetime_local(end_utc_time_seconds))
+            .start_datetime(start_datetime)
+            .end_datetime(end_datetime)
             .entries(entries)
+            .day_notes(day_notes)
+            .host_busy_periods(host_busy_periods)
+            .window_color_periods(window_color_periods)
+            .plans(plans)
             .build())
     }
 
-    pub fn write_entries(&mut self) -> Result<()> {
-        // Execute the entires and close the SQLite database
-        // connection.
+    /// Sum the duration of the entries with the status between the
+    /// start and end times, clamped to the start and end times.
+    ///
+    /// The sum is calculated by SQLite, without reading each entry,
+    /// so it is fast enough to run often (such as in a shell prompt).
+    pub fn sum_entries_duration_seconds(
+        &self,
+        start_utc_time_seconds: u64,
+        end_utc_time_seconds: u64,
+        status: EntryStatus,
+    ) -> Result<u64, StorageError> {
+        let status_num = status.to_i64().ok_or_else(|| {
+            StorageError::InvalidValue(format!("Invalid entry status {:?}.", status))
+        })?;
+        let mut statement = self.connection.prepare_cached(
+            "SELECT COALESCE(SUM(MIN(utc_time_seconds + duration_seconds, :end_utc_time_seconds)
+                                 - MAX(utc_time_seconds, :start_utc_time_seconds)), 0)
+             FROM records
+             WHERE status = :status
+                   AND utc_time_seconds + duration_seconds > :start_utc_time_seconds
+                   AND utc_time_seconds < :end_utc_time_seconds ;",
+        )?;
+        let duration_seconds: i64 = statement.query_row(
+            named_params! {
+                ":start_utc_time_seconds": start_utc_time_seconds,
+                ":end_utc_time_seconds": end_utc_time_seconds,
+                ":status": status_num,
+            },
+            |row| row.get(0),
+        )?;
+        duration_seconds.try_into().map_err(|_| {
+            StorageError::InvalidValue(format!("Invalid duration {}.", duration_seconds))
+        })
+    }
+
+    /// Sum the duration of the entries with the status between the
+    /// start and end times, clamped to the start and end times, for
+    /// each value of the column, such as "executable". Entries without
+    /// a value are summed as None.
+    ///
+    /// Like 'sum_entries_duration_seconds', the sums are calculated by
+    /// SQLite, without reading each entry, and match the durations of
+    /// the entries given by 'read_entries'. The column must be one of
+    /// 'GROUPED_BY_COLUMN_NAMES'.
+    pub fn sum_durations_grouped_by(
+        &self,
+        column_name: &str,
+        start_utc_time_seconds: u64,
+        end_utc_time_seconds: u64,
+        status: EntryStatus,
+    ) -> Result<Vec<(Option<String>, u64)>, StorageError> {
+        if !GROUPED_BY_COLUMN_NAMES.contains(&column_name) {
+            return Err(StorageError::InvalidValue(format!(
+                "Invalid column {:?} to group by, expected one of {:?}.",
+                column_name, GROUPED_BY_COLUMN_NAMES
+            )));
+        }
+        let status_num = status.to_i64().ok_or_else(|| {
+            StorageError::InvalidValue(format!("Invalid entry status {:?}.", status))
+        })?;
+
+        // Older databases may not have the column; the entries have
+        // no values.
+        let column = match records_column_exists(&self.connection, column_name)? {
+            true => column_name,
+            false => "NULL",
+        };
+        let mut statement = self.connection.prepare_cached(&format!(
+            "SELECT {}, SUM(MIN(utc_time_seconds + duration_seconds, :end_utc_time_seconds)
+                            - MAX(utc_time_seconds, :start_utc_time_seconds))
+             FROM records
+             WHERE status = :status
+                   AND utc_time_seconds + duration_seconds > :start_utc_time_seconds
+                   AND utc_time_seconds < :end_utc_time_seconds
+             GROUP BY 1 ;",
+            column
+        ))?;
+        let rows = statement.query_map(
+            named_params! {
+                ":start_utc_time_seconds": start_utc_time_seconds,
+                ":end_utc_time_seconds": end_utc_time_seconds,
+                ":status": status_num,
+            },
+            |row| {
+                Ok((
+                    row.get::<usize, Option<String>>(0)?,
+                    row.get::<usize, i64>(1)?,
+                ))
+            },
+        )?;
+
+        let mut durations = Vec::new();
+        for row in rows {
+            let (value, duration_seconds) = row?;
+            let duration_seconds = duration_seconds.try_into().map_err(|_| {
+                StorageError::InvalidValue(format!("Invalid duration {}.", duration_seconds))
+            })?;
+            durations.push((value, duration_seconds));
+        }
+        Ok(durations)
+    }
+
+    /// Read the time, duration, status and executable of the most
+    /// recent entry (the other variables are not read), or None if
+    /// there are no entries.
+    pub fn read_last_entry(&self) -> Result<Option<Entry>, StorageError> {
+        let mut statement = self.connection.prepare_cached(
+            "SELECT utc_time_seconds, duration_seconds, status, executable
+             FROM records
+             ORDER BY utc_time_seconds DESC
+             LIMIT 1 ;",
+        )?;
+        let row = statement
+            .query_row([], |row| {
+                Ok((
+                    row.get::<usize, u64>(0)?,
+                    row.get::<usize, u64>(1)?,
+                    row.get::<usize, i64>(2)?,
+                    row.get::<usize, Option<String>>(3)?,
+                ))
+            })
+            .optional()?;
+        let (utc_time_seconds, duration_seconds, status_num, executable) = match row {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        let status = FromPrimitive::from_i64(status_num).ok_or_else(|| {
+            StorageError::InvalidValue(format!("Invalid entry status {}.", status_num))
+        })?;
+
+        let mut vars = EntryVariablesList::empty();
+        vars.executable = executable;
+        Ok(Some(Entry::new(
+            utc_time_seconds,
+            duration_seconds,
+            status,
+            vars,
+        )))
+    }
+
+    /// Read the value of the variable name in the most recent entry
+    /// with the status, after the start time.
+    pub fn read_last_entry_variable_value(
+        &self,
+        start_utc_time_seconds: u64,
+        status: EntryStatus,
+        variable_name: &str,
+    ) -> Result<Option<String>, StorageError> {
+        let status_num = status.to_i64().ok_or_else(|| {
+            StorageError::InvalidValue(format!("Invalid entry status {:?}.", status))
+        })?;
+        let mut statement = self.connection.prepare_cached(
+            "SELECT CASE :variable_name
+                        WHEN var1_name THEN var1_value
+                        WHEN var2_name THEN var2_value
+                        WHEN var3_name THEN var3_value
+                        WHEN var4_name THEN var4_value
+                        WHEN var5_name THEN var5_value
+                    END
+             FROM records
+             WHERE status = :status
+                   AND utc_time_seconds >= :start_utc_time_seconds
+             ORDER BY utc_time_seconds DESC
+             LIMIT 1 ;",
+        )?;
+        let value: Option<Option<String>> = statement
+            .query_row(
+                named_params! {
+                    ":variable_name": variable_name,
+                    ":status": status_num,
+                    ":start_utc_time_seconds": start_utc_time_seconds,
+                },
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(value.flatten())
+    }
+
+    /// Read the day notes between the start and end dates
+    /// (inclusive), sorted by date.
+    pub fn read_day_notes(
+        &self,
+        start_date: chrono::NaiveDate,
+        end_date: chrono::NaiveDate,
+    ) -> Result<Vec<DayNote>, StorageError> {
+        // Older databases do not have any notes.
+        if !database_table_exists(&self.connection, "day_notes")? {
+            return Ok(Vec::new());
+        }
+
+        let mut statement = self.connection.prepare_cached(
+            "SELECT date, note
+             FROM day_notes
+             WHERE date >= :start_date AND date <= :end_date
+             ORDER BY date ASC ;",
+        )?;
+        let mut rows = statement.query(named_params! {
+            ":start_date": start_date.format(DAY_NOTE_DATE_FORMAT).to_string(),
+            ":end_date": end_date.format(DAY_NOTE_DATE_FORMAT).to_string(),
+        })?;
+
+        let mut day_notes = Vec::new();
+        while let Some(row) = rows.next()? {
+            let date: String = row.get_unwrap(0);
+            let note: String = row.get_unwrap(1);
+            let date =
+                chrono::NaiveDate::parse_from_str(&date, DAY_NOTE_DATE_FORMAT).map_err(|err| {
+                    StorageError::InvalidValue(format!("Invalid date {:?}; {}", date, err))
+                })?;
+            day_notes.push(DayNote::new(date, note));
+        }
+
+        Ok(day_notes)
+    }
+
+    /// Write (or replace) the note for a date.
+    pub fn write_day_note(&mut self, day_note: &DayNote) -> Result<(), StorageError> {
+        self.connection.execute(
+            "INSERT INTO day_notes (date, note) VALUES (:date, :note)
+             ON CONFLICT(date) DO UPDATE SET note = excluded.note ;",
+            named_params! {
+                ":date": day_note.date.format(DAY_NOTE_DATE_FORMAT).to_string(),
+                ":note": day_note.note,
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Read the plans of the weeks starting between the start and end
+    /// dates (inclusive), sorted by week and value.
+    pub fn read_plans(
+        &self,
+        start_date: chrono::NaiveDate,
+        end_date: chrono::NaiveDate,
+    ) -> Result<Vec<WeekPlan>, StorageError> {
+        // Older databases do not have any plans.
+        if !database_table_exists(&self.connection, "plans")? {
+            return Ok(Vec::new());
+        }
+
+        let mut statement = self.connection.prepare_cached(
+            "SELECT week_start_date, variable_name, variable_value, planned_seconds
+             FROM plans
+             WHERE week_start_date >= :start_date AND week_start_date <= :end_date
+             ORDER BY week_start_date ASC, variable_name ASC, variable_value ASC ;",
+        )?;
+        let mut rows = statement.query(named_params! {
+            ":start_date": start_date.format(DAY_NOTE_DATE_FORMAT).to_string(),
+            ":end_date": end_date.format(DAY_NOTE_DATE_FORMAT).to_string(),
+        })?;
+
+        let mut plans = Vec::new();
+        while let Some(row) = rows.next()? {
+            let date: String = row.get_unwrap(0);
+            let date =
+                chrono::NaiveDate::parse_from_str(&date, DAY_NOTE_DATE_FORMAT).map_err(|err| {
+                    StorageError::InvalidValue(format!("Invalid date {:?}; {}", date, err))
+                })?;
+            plans.push(WeekPlan::new(
+                date,
+                row.get_unwrap(1),
+                row.get_unwrap(2),
+                row.get_unwrap(3),
+            ));
+        }
+
+        Ok(plans)
+    }
+
+    /// Write (or replace) the planned duration of a variable value
+    /// for a week.
+    pub fn write_plan(&mut self, plan: &WeekPlan) -> Result<(), StorageError> {
+        self.connection.execute(
+            "INSERT INTO plans (week_start_date, variable_name, variable_value, planned_seconds)
+             VALUES (:week_start_date, :variable_name, :variable_value, :planned_seconds)
+             ON CONFLICT(week_start_date, variable_name, variable_value)
+             DO UPDATE SET planned_seconds = excluded.planned_seconds ;",
+            named_params! {
+                ":week_start_date": plan.week_start_date.format(DAY_NOTE_DATE_FORMAT).to_string(),
+                ":variable_name": plan.variable_name,
+                ":variable_value": plan.variable_value,
+                ":planned_seconds": plan.planned_seconds,
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Remove the plans of a week, returning the number of planned
+    /// values removed.
+    pub fn delete_plans(
+        &mut self,
+        week_start_date: chrono::NaiveDate,
+    ) -> Result<usize, StorageError> {
+        let date_text = week_start_date.format(DAY_NOTE_DATE_FORMAT).to_string();
         self.connection.execute("BEGIN TRANSACTION;", ())?;
+        let count = self.connection.execute(
+            "DELETE FROM plans WHERE week_start_date = :week_start_date ;",
+            named_params! {
+                ":week_start_date": date_text,
+            },
+        )?;
+        insert_audit_log_entry(
+            &self.connection,
+            "delete_plans",
+            &format!("week_start_date = {}", date_text),
+            count,
+        )?;
+        self.connection.execute("COMMIT;", ())?;
+        Ok(count)
+    }
 
-        let last_entry = get_last_database_entry(&self.connection)?;
+    /// Remove the note for a date, returning true if a note was
+    /// removed.
+    pub fn delete_day_note(&mut self, date: chrono::NaiveDate) -> Result<bool, StorageError> {
+        let date_text = date.format(DAY_NOTE_DATE_FORMAT).to_string();
+        self.connection.execute("BEGIN TRANSACTION;", ())?;
+        let count = self.connection.execute(
+            "DELETE FROM day_notes WHERE date = :date ;",
+            named_params! {
+                ":date": date_text,
+            },
+        )?;
+        insert_audit_log_entry(
+            &self.connection,
+            "delete_day_note",
+            &format!("date = {}", date_text),
+            count,
+        )?;
+        self.connection.execute("COMMIT;", ())?;
+        Ok(count > 0)
+    }
 
-        let mut entries_dedup = Vec::<Entry>::new();
-        let mut entry_row_statuses = Vec::<RecordRowStatus>::new();
+    /// Read the most recent privacy consent given by the user, if
+    /// any.
+    pub fn read_privacy_consent(&self) -> Result<Option<PrivacyConsent>, StorageError> {
+        // Older databases do not record consent.
+        if !database_table_exists(&self.connection, "privacy_consent")? {
+            return Ok(None);
+        }
 
-        deduplicate_entries(
-            &last_entry,
-            &self.entries,
-            self.record_interval_seconds,
-            &mut entries_dedup,
-            &mut entry_row_statuses,
-        );
+        let privacy_consent = self
+            .connection
+            .query_row(
+                "SELECT utc_time_seconds, notice_version
+                 FROM privacy_consent
+                 ORDER BY utc_time_seconds DESC
+                 LIMIT 1 ;",
+                (),
+                |row| Ok(PrivacyConsent::new(row.get_unwrap(0), row.get_unwrap(1))),
+            )
+            .optional()?;
+        Ok(privacy_consent)
+    }
 
-        let new_entries_dedup: Vec<Entry> = entries_dedup
-            .iter()
-            .zip(&entry_row_statuses)
-            .filter(|x| x.1 == &RecordRowStatus::New)
-            .map(|x| x.0.clone())
-            .collect();
-        let existing_entries_dedup: Vec<Entry> = entries_dedup
+    /// Record the privacy consent given by the user.
+    pub fn write_privacy_consent(
+        &mut self,
+        privacy_consent: &PrivacyConsent,
+    ) -> Result<(), StorageError> {
+        self.connection.execute(
+            "INSERT INTO privacy_consent (utc_time_seconds, notice_version)
+             VALUES (:utc_time_seconds, :notice_version) ;",
+            named_params! {
+                ":utc_time_seconds": privacy_consent.utc_time_seconds,
+                ":notice_version": privacy_consent.notice_version,
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Delete the recorded entries and host busy periods between the
+    /// start and end times. Rows overlapping the start time are
+    /// shortened to end at the start time. Returns the number of
+    /// entry rows deleted.
+    ///
+    /// Deleted data is overwritten in the database file (using
+    /// SQLite's 'secure_delete'), rather than only being unlinked.
+    pub fn delete_entries(
+        &mut self,
+        start_utc_time_seconds: u64,
+        end_utc_time_seconds: u64,
+    ) -> Result<usize, StorageError> {
+        let params = named_params! {
+            ":start_utc_time_seconds": start_utc_time_seconds,
+            ":end_utc_time_seconds": end_utc_time_seconds,
+        };
+
+        self.connection
+            .execute_batch("PRAGMA secure_delete = ON;")?;
+        self.connection.execute("BEGIN TRANSACTION;", ())?;
+
+        let undo_snapshot = match self.record_undo {
+            true => Some(UndoSnapshot::save_before(
+                &self.connection,
+                "utc_time_seconds < ?
+                 AND (utc_time_seconds >= ? OR utc_time_seconds + duration_seconds > ?)",
+                &[
+                    (end_utc_time_seconds as i64).into(),
+                    (start_utc_time_seconds as i64).into(),
+                    (start_utc_time_seconds as i64).into(),
+                ],
+            )?),
+            false => None,
+        };
+
+        let count = self.connection.execute(
+            "DELETE FROM records
+             WHERE utc_time_seconds >= :start_utc_time_seconds
+                   AND utc_time_seconds < :end_utc_time_seconds ;",
+            params,
+        )?;
+        self.connection.execute(
+            "UPDATE records
+             SET duration_seconds = :start_utc_time_seconds - utc_time_seconds
+             WHERE utc_time_seconds < :start_utc_time_seconds
+                   AND utc_time_seconds + duration_seconds > :start_utc_time_seconds
+                   AND :end_utc_time_seconds > :start_utc_time_seconds ;",
+            params,
+        )?;
+
+        for table_name in ["host_busy", "window_colors"] {
+            if !database_table_exists(&self.connection, table_name)? {
+                continue;
+            }
+            self.connection.execute(
+                &format!(
+                    "DELETE FROM {}
+                     WHERE utc_time_seconds >= :start_utc_time_seconds
+                           AND utc_time_seconds < :end_utc_time_seconds ;",
+                    table_name
+                ),
+                params,
+            )?;
+            self.connection.execute(
+                &format!(
+                    "UPDATE {}
+                     SET duration_seconds = :start_utc_time_seconds - utc_time_seconds
+                     WHERE utc_time_seconds < :start_utc_time_seconds
+                           AND utc_time_seconds + duration_seconds > :start_utc_time_seconds
+                           AND :end_utc_time_seconds > :start_utc_time_seconds ;",
+                    table_name
+                ),
+                params,
+            )?;
+        }
+
+        if let Some(undo_snapshot) = undo_snapshot {
+            undo_snapshot.save_after(&self.connection)?;
+        }
+        insert_audit_log_entry(
+            &self.connection,
+            "delete_entries",
+            &format!(
+                "utc_time_seconds >= {} AND utc_time_seconds < {}",
+                start_utc_time_seconds, end_utc_time_seconds
+            ),
+            count,
+        )?;
+        self.connection.execute("COMMIT;", ())?;
+
+        Ok(count)
+    }
+
+    /// Change the values of the recorded entries starting between the
+    /// start and end times, that have all the 'where_values', to the
+    /// 'set_values', such as changing the "var1_value" of the
+    /// "firefox" executable entries to "research". Returns the number
+    /// of entry rows changed.
+    ///
+    /// Each value is a column name (one of 'EDITABLE_COLUMN_NAMES')
+    /// and a value, where None matches (or sets) no value.
+    pub fn update_entries_values(
+        &mut self,
+        start_utc_time_seconds: u64,
+        end_utc_time_seconds: u64,
+        where_values: &[(String, Option<String>)],
+        set_values: &[(String, Option<String>)],
+    ) -> Result<usize, StorageError> {
+        if set_values.is_empty() {
+            return Err(StorageError::InvalidValue(
+                "No values given to change.".to_string(),
+            ));
+        }
+        for (column_name, _value) in where_values.iter().chain(set_values) {
+            if !EDITABLE_COLUMN_NAMES.contains(&column_name.as_str()) {
+                return Err(StorageError::InvalidValue(format!(
+                    "Invalid column {:?}, expected one of {:?}.",
+                    column_name, EDITABLE_COLUMN_NAMES
+                )));
+            }
+        }
+
+        // The column names are validated above, so are safe to format
+        // into the statement; the values are all parameters.
+        let mut parameters: Vec<rusqlite::types::Value> = Vec::new();
+        let set_sql: Vec<String> = set_values
             .iter()
-            .zip(&entry_row_statuses)
-            .filter(|x| x.1 == &RecordRowStatus::Existing)
-            .map(|x| x.0.clone())
+            .map(|(column_name, value)| {
+                parameters.push(value.clone().into());
+                format!("{} = ?", column_name)
+            })
             .collect();
+        parameters.push((start_utc_time_seconds as i64).into());
+        parameters.push((end_utc_time_seconds as i64).into());
+        let mut where_sql = vec![
+            "utc_time_seconds >= ?".to_string(),
+            "utc_time_seconds < ?".to_string(),
+        ];
+        for (column_name, value) in where_values {
+            match value {
+                Some(value) => {
+                    parameters.push(value.clone().into());
+                    where_sql.push(format!("{} = ?", column_name));
+                }
+                None => where_sql.push(format!("{} IS NULL", column_name)),
+            }
+        }
 
-        update_existing_entry_rows_into_database(&self.connection, &existing_entries_dedup)?;
-        insert_new_entry_rows_into_database(&self.connection, &new_entries_dedup)?;
+        self.connection.execute("BEGIN TRANSACTION;", ())?;
+        let undo_snapshot = match self.record_undo {
+            true => Some(UndoSnapshot::save_before(
+                &self.connection,
+                &where_sql.join(" AND "),
+                &parameters[set_values.len()..],
+            )?),
+            false => None,
+        };
+        let count = self.connection.execute(
+            &format!(
+                "UPDATE records SET {} WHERE {} ;",
+                set_sql.join(", "),
+                where_sql.join(" AND ")
+            ),
+            rusqlite::params_from_iter(parameters),
+        )?;
+        if let Some(undo_snapshot) = undo_snapshot {
+            undo_snapshot.save_after(&self.connection)?;
+        }
 
-        self.connection.execute("END TRANSACTION;", ())?;
+        let format_values = |values: &[(String, Option<String>)]| {
+            values
+                .iter()
+                .map(|(column_name, value)| format!("{}={:?}", column_name, value))
+                .collect::<Vec<String>>()
+                .join(" ")
+        };
+        insert_audit_log_entry(
+            &self.connection,
+            "update_entries_values",
+            &format!(
+                "utc_time_seconds >= {} AND utc_time_seconds < {} AND {} SET {}",
+                start_utc_time_seconds,
+                end_utc_time_seconds,
+                format_values(where_values),
+                format_values(set_values)
+            ),
+            count,
+        )?;
+        self.connection.execute("COMMIT;", ())?;
 
-        Ok(())
+        Ok(count)
     }
 
-    pub fn close(&mut self) {
-        // close the SQLite database connection.
-        debug!("Closed Time Tracker Storage.");
+    /// Insert an entry that was not recorded, such as a meeting or
+    /// phone call away from the computer, covering an idle gap.
+    ///
+    /// The idle entries overlapping the manual entry are shortened
+    /// (or split) so the time is not counted twice. Returns the
+    /// number of idle entry rows changed. Fails if the manual entry
+    /// overlaps any active entries.
+    pub fn insert_manual_entry(&mut self, entry: &Entry) -> Result<usize, StorageError> {
+        let start_utc_time_seconds = entry.utc_time_seconds;
+        let end_utc_time_seconds = entry.utc_time_seconds + entry.duration_seconds;
+        if entry.duration_seconds == 0 {
+            return Err(StorageError::InvalidValue(
+                "Manual entry duration must be greater than zero.".to_string(),
+            ));
+        }
+
+        let params = named_params! {
+            ":start_utc_time_seconds": start_utc_time_seconds as i64,
+            ":end_utc_time_seconds": end_utc_time_seconds as i64,
+            ":status": EntryStatus::Idle as i64,
+        };
+
+        let active_count: u64 = self.connection.query_row(
+            "SELECT COUNT(*)
+             FROM records
+             WHERE status = :status
+                   AND utc_time_seconds < :end_utc_time_seconds
+                   AND utc_time_seconds + duration_seconds > :start_utc_time_seconds ;",
+            named_params! {
+                ":start_utc_time_seconds": start_utc_time_seconds as i64,
+                ":end_utc_time_seconds": end_utc_time_seconds as i64,
+                ":status": EntryStatus::Active as i64,
+            },
+            |row| row.get(0),
+        )?;
+        if active_count > 0 {
+            return Err(StorageError::InvalidValue(format!(
+                "Manual entry overlaps {} active entries.",
+                active_count
+            )));
+        }
+
+        self.connection.execute("BEGIN TRANSACTION;", ())?;
+
+        let undo_snapshot = match self.record_undo {
+            true => Some(UndoSnapshot::save_before(
+                &self.connection,
+                "utc_time_seconds < ? AND utc_time_seconds + duration_seconds > ?",
+                &[
+                    (end_utc_time_seconds as i64).into(),
+                    (start_utc_time_seconds as i64).into(),
+                ],
+            )?),
+            false => None,
+        };
+
+        // Idle entries covering the whole manual entry are split in
+        // two; the part after the manual entry is copied here, and
+        // the part before is shortened below.
+        let mut count = self.connection.execute(
+            "INSERT INTO records (utc_time_seconds,
+                                  duration_seconds,
+                                  status,
+                                  executable,
+                                  var1_name,
+                                  var2_name,
+                                  var3_name,
+                                  var4_name,
+                                  var5_name,
+                                  var1_value,
+                                  var2_value,
+                                  var3_value,
+                                  var4_value,
+                                  var5_value,
+                                  utc_offset_seconds,
+                                  window_title,
+                                  working_directory,
+                                  document_path,
+                                  hostname,
+                                  username,
+                                  display)
+             SELECT :end_utc_time_seconds,
+                    utc_time_seconds + duration_seconds - :end_utc_time_seconds,
+                    status,
+                    executable,
+                    var1_name,
+                    var2_name,
+                    var3_name,
+                    var4_name,
+                    var5_name,
+                    var1_value,
+                    var2_value,
+                    var3_value,
+                    var4_value,
+                    var5_value,
+                    utc_offset_seconds,
+                    window_title,
+                    working_directory,
+                    document_path,
+                    hostname,
+                    username,
+                    display
+             FROM records
+             WHERE status = :status
+                   AND utc_time_seconds < :start_utc_time_seconds
+                   AND utc_time_seconds + duration_seconds > :end_utc_time_seconds ;",
+            params,
+        )?;
+        count += self.connection.execute(
+            "UPDATE records
+             SET duration_seconds = :start_utc_time_seconds - utc_time_seconds
+             WHERE status = :status
+                   AND utc_time_seconds < :start_utc_time_seconds
+                   AND utc_time_seconds + duration_seconds > :start_utc_time_seconds
+                   AND :end_utc_time_seconds > :start_utc_time_seconds ;",
+            params,
+        )?;
+        count += self.connection.execute(
+            "UPDATE records
+             SET duration_seconds = utc_time_seconds + duration_seconds - :end_utc_time_seconds,
+                 utc_time_seconds = :end_utc_time_seconds
+             WHERE status = :status
+                   AND utc_time_seconds >= :start_utc_time_seconds
+                   AND utc_time_seconds < :end_utc_time_seconds
+                   AND utc_time_seconds + duration_seconds > :end_utc_time_seconds ;",
+            params,
+        )?;
+        count += self.connection.execute(
+            "DELETE FROM records
+             WHERE status = :status
+                   AND utc_time_seconds >= :start_utc_time_seconds
+                   AND utc_time_seconds < :end_utc_time_seconds ;",
+            params,
+        )?;
+
+        // Manual entries are not sampled, so have no record interval.
+        insert_new_entry_rows_into_database(&self.connection, std::slice::from_ref(entry), None)?;
+
+        if let Some(undo_snapshot) = undo_snapshot {
+            undo_snapshot.save_after(&self.connection)?;
+        }
+        insert_audit_log_entry(
+            &self.connection,
+            "insert_manual_entry",
+            &format!(
+                "utc_time_seconds >= {} AND utc_time_seconds < {}",
+                start_utc_time_seconds, end_utc_time_seconds
+            ),
+            count,
+        )?;
+        self.connection.execute("COMMIT;", ())?;
+
+        Ok(count)
+    }
+
+    /// Delete all recorded data; entries, host busy periods, window
+    /// colors, executable versions and day notes. The privacy consent and
+    /// audit log are kept.
+    pub fn delete_all_data(&mut self) -> Result<(), StorageError> {
+        self.connection
+            .execute_batch("PRAGMA secure_delete = ON;")?;
+        self.connection.execute("BEGIN TRANSACTION;", ())?;
+        let mut count = 0;
+        for table_name in [
+            "records",
+            "host_busy",
+            "window_colors",
+            "executable_versions",
+            "day_notes",
+        ] {
+            if database_table_exists(&self.connection, table_name)? {
+                count += self
+                    .connection
+                    .execute(&format!("DELETE FROM {} ;", table_name), ())?;
+            }
+        }
+        insert_audit_log_entry(&self.connection, "delete_all_data", "all", count)?;
+        self.connection.execute("COMMIT;", ())?;
+
+        // Shrink the file so deleted data is not left in free pages.
+        self.connection.execute_batch("VACUUM;")?;
+
+        Ok(())
+    }
+
+    /// Read the audit log of operations, sorted by time.
+    pub fn read_audit_log(&self) -> Result<Vec<AuditLogEntry>, StorageError> {
+        // Older databases do not have an audit log, or do not record
+        // the source of operations.
+        if !database_table_exists(&self.connection, "audit_log")? {
+            return Ok(Vec::new());
+        }
+        let sql = match table_column_exists(&self.connection, "audit_log", "source")? {
+            true => {
+                "SELECT utc_time_seconds, user_name, operation, filter, row_count,
+                        id, source, undo_utc_time_seconds,
+                        EXISTS(SELECT 1 FROM undo_records WHERE audit_log_id = audit_log.id)
+                 FROM audit_log
+                 ORDER BY utc_time_seconds ASC, id ASC ;"
+            }
+            false => {
+                "SELECT utc_time_seconds, user_name, operation, filter, row_count,
+                        NULL, NULL, NULL, 0
+                 FROM audit_log
+                 ORDER BY utc_time_seconds ASC ;"
+            }
+        };
+
+        let mut statement = self.connection.prepare_cached(sql)?;
+        let mut rows = statement.query(())?;
+
+        let mut audit_log_entries = Vec::new();
+        while let Some(row) = rows.next()? {
+            let mut audit_log_entry = AuditLogEntry::new(
+                row.get_unwrap(0),
+                row.get_unwrap(1),
+                row.get_unwrap(2),
+                row.get_unwrap(3),
+                row.get_unwrap(4),
+            );
+            audit_log_entry.id = row.get_unwrap(5);
+            audit_log_entry.source = row.get_unwrap(6);
+            audit_log_entry.undo_utc_time_seconds = row.get_unwrap(7);
+            audit_log_entry.can_undo = row.get_unwrap(8);
+            audit_log_entries.push(audit_log_entry);
+        }
+
+        Ok(audit_log_entries)
+    }
+
+    /// The most recent operation that can be undone (with
+    /// 'undo_operation'), if any.
+    pub fn last_undoable_audit_log_entry(&self) -> Result<Option<AuditLogEntry>, StorageError> {
+        let audit_log_entries = self.read_audit_log()?;
+        Ok(audit_log_entries
+            .into_iter()
+            .filter(|x| x.can_undo && x.undo_utc_time_seconds.is_none())
+            .max_by_key(|x| (x.utc_time_seconds, x.id)))
+    }
+
+    /// Revert the entries changed by the operation (saved when
+    /// 'set_record_undo' is enabled) to the entries before the
+    /// operation. Returns the number of entry rows restored.
+    ///
+    /// Fails without changing anything when the entries were changed
+    /// again after the operation (such as by a later edit), as the
+    /// changed entries are needed to revert the operation.
+    pub fn undo_operation(&mut self, audit_log_id: u64) -> Result<usize, StorageError> {
+        let after_comparisons: Vec<String> = INSERT_RECORDS_COLUMN_NAMES
+            .iter()
+            .map(|column_name| format!("records.{0} IS undo_records.{0}", column_name))
+            .collect();
+        let column_names = INSERT_RECORDS_COLUMN_NAMES.join(", ");
+
+        self.connection.execute("BEGIN TRANSACTION;", ())?;
+
+        let already_undone: Option<Option<u64>> = self
+            .connection
+            .query_row(
+                "SELECT undo_utc_time_seconds FROM audit_log WHERE id = ?1 ;",
+                [audit_log_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if already_undone.flatten().is_some() {
+            self.connection.execute("ROLLBACK;", ())?;
+            return Err(StorageError::InvalidValue(format!(
+                "Operation {} is already undone.",
+                audit_log_id
+            )));
+        }
+
+        // Each entry row after the operation is removed once; the
+        // rows are matched by their values, as the row ids may have
+        // changed since.
+        let mut statement = self.connection.prepare_cached(
+            "SELECT record_rowid FROM undo_records WHERE audit_log_id = ?1 AND is_after = 1 ;",
+        )?;
+        let after_rowids: Vec<i64> = statement
+            .query_map([audit_log_id], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        for after_rowid in &after_rowids {
+            let count = self.connection.execute(
+                &format!(
+                    "DELETE FROM records
+                     WHERE rowid = (SELECT records.rowid
+                                    FROM records, undo_records
+                                    WHERE undo_records.audit_log_id = ?1
+                                          AND undo_records.is_after = 1
+                                          AND undo_records.record_rowid = ?2
+                                          AND {}
+                                    LIMIT 1) ;",
+                    after_comparisons.join(" AND ")
+                ),
+                rusqlite::params![audit_log_id, after_rowid],
+            )?;
+            if count == 0 {
+                self.connection.execute("ROLLBACK;", ())?;
+                return Err(StorageError::InvalidValue(format!(
+                    "Cannot undo operation {}; the entries were changed after the operation.",
+                    audit_log_id
+                )));
+            }
+        }
+
+        let count = self.connection.execute(
+            &format!(
+                "INSERT INTO records ({})
+                 SELECT {} FROM undo_records WHERE audit_log_id = ?1 AND is_after = 0 ;",
+                column_names, column_names
+            ),
+            [audit_log_id],
+        )?;
+
+        let now_utc_time_seconds = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        self.connection.execute(
+            "UPDATE audit_log SET undo_utc_time_seconds = ?1 WHERE id = ?2 ;",
+            rusqlite::params![now_utc_time_seconds, audit_log_id],
+        )?;
+        self.connection.execute(
+            "DELETE FROM undo_records WHERE audit_log_id = ?1 ;",
+            [audit_log_id],
+        )?;
+        insert_audit_log_entry(
+            &self.connection,
+            "undo",
+            &format!("id = {}", audit_log_id),
+            count,
+        )?;
+        self.connection.execute("COMMIT;", ())?;
+
+        Ok(count)
+    }
+
+    /// Read the colors of the focused windows that overlap the start
+    /// and end times, clamped to the start and end times.
+    pub fn read_window_color_periods(
+        &self,
+        start_utc_time_seconds: u64,
+        end_utc_time_seconds: u64,
+    ) -> Result<Vec<WindowColorPeriod>, StorageError> {
+        // Older databases do not record window colors.
+        if !database_table_exists(&self.connection, "window_colors")? {
+            return Ok(Vec::new());
+        }
+
+        let mut statement = self.connection.prepare_cached(
+            "SELECT utc_time_seconds, duration_seconds, executable, color
+             FROM window_colors
+             WHERE utc_time_seconds < :end_utc_time_seconds
+                   AND (utc_time_seconds + duration_seconds) > :start_utc_time_seconds
+             ORDER BY utc_time_seconds ASC ;",
+        )?;
+        let mut rows = statement.query(named_params! {
+            ":start_utc_time_seconds": start_utc_time_seconds as i64,
+            ":end_utc_time_seconds": end_utc_time_seconds as i64,
+        })?;
+
+        let mut window_color_periods = Vec::new();
+        while let Some(row) = rows.next()? {
+            let utc_time_seconds: u64 = row.get_unwrap(0);
+            let duration_seconds: u64 = row.get_unwrap(1);
+            let executable: String = row.get_unwrap(2);
+            let color: u32 = row.get_unwrap(3);
+
+            let start = std::cmp::max(utc_time_seconds, start_utc_time_seconds);
+            let end = std::cmp::min(utc_time_seconds + duration_seconds, end_utc_time_seconds);
+            window_color_periods.push(WindowColorPeriod::new(
+                start,
+                end - start,
+                executable,
+                color,
+            ));
+        }
+
+        Ok(window_color_periods)
+    }
+
+    /// Write the colors of the focused windows, extending the last
+    /// written period when a period continues it.
+    pub fn write_window_color_periods(
+        &mut self,
+        window_color_periods: &[WindowColorPeriod],
+    ) -> Result<(), StorageError> {
+        // Allow a missed sample between periods.
+        let gap_seconds = self.record_interval_seconds * 2;
+
+        self.connection.execute("BEGIN TRANSACTION;", ())?;
+        for window_color_period in window_color_periods {
+            let last_period = self
+                .connection
+                .query_row(
+                    "SELECT rowid, utc_time_seconds, duration_seconds, executable, color
+                     FROM window_colors
+                     ORDER BY utc_time_seconds DESC
+                     LIMIT 1 ;",
+                    (),
+                    |row| {
+                        let row_id: i64 = row.get(0)?;
+                        let period = WindowColorPeriod::new(
+                            row.get(1)?,
+                            row.get(2)?,
+                            row.get(3)?,
+                            row.get(4)?,
+                        );
+                        Ok((row_id, period))
+                    },
+                )
+                .optional()?;
+
+            let extended_period = last_period.and_then(|(row_id, mut period)| {
+                period
+                    .try_extend(window_color_period, gap_seconds)
+                    .then_some((row_id, period))
+            });
+            match extended_period {
+                Some((row_id, period)) => {
+                    self.connection.execute(
+                        "UPDATE window_colors
+                         SET duration_seconds = :duration_seconds
+                         WHERE rowid = :row_id ;",
+                        named_params! {
+                            ":duration_seconds": period.duration_seconds as i64,
+                            ":row_id": row_id,
+                        },
+                    )?;
+                }
+                None => {
+                    self.connection.execute(
+                        "INSERT INTO window_colors (utc_time_seconds, duration_seconds, executable, color)
+                         VALUES (:utc_time_seconds, :duration_seconds, :executable, :color) ;",
+                        named_params! {
+                            ":utc_time_seconds": window_color_period.utc_time_seconds as i64,
+                            ":duration_seconds": window_color_period.duration_seconds as i64,
+                            ":executable": window_color_period.executable,
+                            ":color": window_color_period.color,
+                        },
+                    )?;
+                }
+            }
+        }
+        self.connection.execute("COMMIT;", ())?;
+
+        Ok(())
+    }
+
+    /// Read the periods the machine was busy that overlap the start
+    /// and end times, clamped to the start and end times.
+    pub fn read_host_busy_periods(
+        &self,
+        start_utc_time_seconds: u64,
+        end_utc_time_seconds: u64,
+    ) -> Result<Vec<HostBusyPeriod>, StorageError> {
+        // Older databases do not record the host being busy.
+        if !database_table_exists(&self.connection, "host_busy")? {
+            return Ok(Vec::new());
+        }
+
+        let mut statement = self.connection.prepare_cached(
+            "SELECT utc_time_seconds, duration_seconds, reason
+             FROM host_busy
+             WHERE utc_time_seconds < :end_utc_time_seconds
+                   AND (utc_time_seconds + duration_seconds) > :start_utc_time_seconds
+             ORDER BY utc_time_seconds ASC ;",
+        )?;
+        let mut rows = statement.query(named_params! {
+            ":start_utc_time_seconds": start_utc_time_seconds as i64,
+            ":end_utc_time_seconds": end_utc_time_seconds as i64,
+        })?;
+
+        let mut host_busy_periods = Vec::new();
+        while let Some(row) = rows.next()? {
+            let utc_time_seconds: u64 = row.get_unwrap(0);
+            let duration_seconds: u64 = row.get_unwrap(1);
+            let reason: String = row.get_unwrap(2);
+
+            let start = std::cmp::max(utc_time_seconds, start_utc_time_seconds);
+            let end = std::cmp::min(utc_time_seconds + duration_seconds, end_utc_time_seconds);
+            host_busy_periods.push(HostBusyPeriod::new(start, end - start, reason));
+        }
+
+        Ok(host_busy_periods)
+    }
+
+    /// Write the periods the machine was busy, extending the last
+    /// written period when a period continues it.
+    pub fn write_host_busy_periods(
+        &mut self,
+        host_busy_periods: &[HostBusyPeriod],
+    ) -> Result<(), StorageError> {
+        // Allow a missed sample between periods.
+        let gap_seconds = self.record_interval_seconds * 2;
+
+        self.connection.execute("BEGIN TRANSACTION;", ())?;
+        for host_busy_period in host_busy_periods {
+            let last_period = self
+                .connection
+                .query_row(
+                    "SELECT rowid, utc_time_seconds, duration_seconds, reason
+                     FROM host_busy
+                     ORDER BY utc_time_seconds DESC
+                     LIMIT 1 ;",
+                    (),
+                    |row| {
+                        let row_id: i64 = row.get(0)?;
+                        let period = HostBusyPeriod::new(row.get(1)?, row.get(2)?, row.get(3)?);
+                        Ok((row_id, period))
+                    },
+                )
+                .optional()?;
+
+            let extended_period = last_period.and_then(|(row_id, mut period)| {
+                period
+                    .try_extend(host_busy_period, gap_seconds)
+                    .then_some((row_id, period))
+            });
+            match extended_period {
+                Some((row_id, period)) => {
+                    self.connection.execute(
+                        "UPDATE host_busy
+                         SET duration_seconds = :duration_seconds
+                         WHERE rowid = :row_id ;",
+                        named_params! {
+                            ":duration_seconds": period.duration_seconds as i64,
+                            ":row_id": row_id,
+                        },
+                    )?;
+                }
+                None => {
+                    self.connection.execute(
+                        "INSERT INTO host_busy (utc_time_seconds, duration_seconds, reason)
+                         VALUES (:utc_time_seconds, :duration_seconds, :reason) ;",
+                        named_params! {
+                            ":utc_time_seconds": host_busy_period.utc_time_seconds as i64,
+                            ":duration_seconds": host_busy_period.duration_seconds as i64,
+                            ":reason": host_busy_period.reason,
+                        },
+                    )?;
+                }
+            }
+        }
+        self.connection.execute("COMMIT;", ())?;
+
+        Ok(())
+    }
+
+    pub fn write_entries(&mut self) -> Result<(), StorageError> {
+        // Execute the entires and close the SQLite database
+        // connection.
+        self.connection.execute("BEGIN TRANSACTION;", ())?;
+
+        let (last_entry, last_record_interval_seconds) = get_last_database_entry(&self.connection)?;
+
+        // The last entry may have been recorded with a different
+        // record interval (before the interval was changed), so the
+        // larger interval is allowed between the entries.
+        let record_interval_seconds = std::cmp::max(
+            self.record_interval_seconds,
+            last_record_interval_seconds.unwrap_or(DEFAULT_RECORD_INTERVAL_SECONDS),
+        );
+
+        let mut entries_dedup = Vec::<Entry>::new();
+        let mut entry_row_statuses = Vec::<RecordRowStatus>::new();
+
+        deduplicate_entries(
+            &last_entry,
+            &self.entries,
+            record_interval_seconds,
+            self.max_merged_entry_seconds,
+            &mut entries_dedup,
+            &mut entry_row_statuses,
+        );
+
+        let new_entries_dedup: Vec<Entry> = entries_dedup
+            .iter()
+            .zip(&entry_row_statuses)
+            .filter(|x| x.1 == &RecordRowStatus::New)
+            .map(|x| x.0.clone())
+            .collect();
+        let existing_entries_dedup: Vec<Entry> = entries_dedup
+            .iter()
+            .zip(&entry_row_statuses)
+            .filter(|x| x.1 == &RecordRowStatus::Existing)
+            .map(|x| x.0.clone())
+            .collect();
+
+        update_existing_entry_rows_into_database(&self.connection, &existing_entries_dedup)?;
+        insert_new_entry_rows_into_database(
+            &self.connection,
+            &new_entries_dedup,
+            Some(self.record_interval_seconds),
+        )?;
+        if !self.entries.is_empty() {
+            insert_write_entries_audit_log_entry(&self.connection, self.entries.len())?;
+        }
+
+        self.connection.execute("END TRANSACTION;", ())?;
+        self.entries.clear();
+
+        Ok(())
+    }
+
+    /// Add or update the version details of an executable.
+    ///
+    /// If the executable file (path and modification time) is
+    /// already known, the last time the executable was seen is
+    /// extended.
+    pub fn write_executable_version(
+        &mut self,
+        executable_version: &ExecutableVersion,
+    ) -> Result<(), StorageError> {
+        let mut statement = self.connection.prepare_cached(
+            "INSERT INTO executable_versions (executable,
+                                              executable_path,
+                                              modified_time_seconds,
+                                              version,
+                                              first_utc_time_seconds,
+                                              last_utc_time_seconds)
+             VALUES (:executable,
+                     :executable_path,
+                     :modified_time_seconds,
+                     :version,
+                     :first_utc_time_seconds,
+                     :last_utc_time_seconds)
+             ON CONFLICT(executable_path, modified_time_seconds) DO UPDATE SET
+                 version = excluded.version,
+                 first_utc_time_seconds = MIN(first_utc_time_seconds, excluded.first_utc_time_seconds),
+                 last_utc_time_seconds = MAX(last_utc_time_seconds, excluded.last_utc_time_seconds) ;",
+        )?;
+
+        debug!("Write Executable Version: {:?}", executable_version);
+        statement.execute(named_params! {
+            ":executable": executable_version.executable,
+            ":executable_path": executable_version.executable_path,
+            ":modified_time_seconds": executable_version.modified_time_seconds as i64,
+            ":version": executable_version.version,
+            ":first_utc_time_seconds": executable_version.first_utc_time_seconds as i64,
+            ":last_utc_time_seconds": executable_version.last_utc_time_seconds as i64,
+        })?;
+
+        Ok(())
+    }
+
+    /// Read all the known executable versions, sorted by the first
+    /// time the executable was seen.
+    pub fn read_executable_versions(&mut self) -> Result<Vec<ExecutableVersion>, StorageError> {
+        // Older databases may not have been written to by a recorder
+        // that knows about executable versions.
+        if !database_table_exists(&self.connection, "executable_versions")? {
+            return Ok(Vec::new());
+        }
+
+        let mut statement = self.connection.prepare_cached(
+            "SELECT executable, executable_path, modified_time_seconds, version,
+                    first_utc_time_seconds, last_utc_time_seconds
+             FROM executable_versions
+             ORDER BY first_utc_time_seconds ASC ;",
+        )?;
+        let mut rows = statement.query([])?;
+
+        let mut executable_versions = Vec::new();
+        while let Some(row) = rows.next()? {
+            executable_versions.push(ExecutableVersion {
+                executable: row.get_unwrap(0),
+                executable_path: row.get_unwrap(1),
+                modified_time_seconds: row.get_unwrap(2),
+                version: row.get_unwrap(3),
+                first_utc_time_seconds: row.get_unwrap(4),
+                last_utc_time_seconds: row.get_unwrap(5),
+            });
+        }
+
+        Ok(executable_versions)
+    }
+
+    /// Write any entries not yet written, finish any pending
+    /// transaction and close the SQLite database connection.
+    ///
+    /// A transaction left open by a failed write is rolled back, so
+    /// partially written data is never committed.
+    /// Check the database file for corruption (using SQLite's
+    /// 'integrity_check'), returning the problems found; empty when
+    /// the database is intact.
+    pub fn check_integrity(&self) -> Result<Vec<String>, StorageError> {
+        let mut statement = self.connection.prepare("PRAGMA integrity_check ;")?;
+        let mut rows = statement.query([])?;
+        let mut problems = Vec::new();
+        while let Some(row) = rows.next()? {
+            let message: String = row.get(0)?;
+            if message != "ok" {
+                problems.push(message);
+            }
+        }
+        Ok(problems)
+    }
+
+    /// The number of rows of each table in the database, sorted by
+    /// the table name.
+    pub fn read_table_row_counts(&self) -> Result<Vec<(String, u64)>, StorageError> {
+        let mut statement = self.connection.prepare(
+            "SELECT name FROM sqlite_master
+             WHERE type = 'table' AND name NOT LIKE 'sqlite_%'
+             ORDER BY name ;",
+        )?;
+        let table_names = statement
+            .query_map([], |row| row.get::<usize, String>(0))?
+            .collect::<Result<Vec<String>, rusqlite::Error>>()?;
+
+        let mut table_row_counts = Vec::new();
+        for table_name in table_names {
+            let count: u64 = self.connection.query_row(
+                &format!("SELECT COUNT(*) FROM \"{}\" ;", table_name),
+                [],
+                |row| row.get(0),
+            )?;
+            table_row_counts.push((table_name, count));
+        }
+        Ok(table_row_counts)
+    }
+
+    /// The start of the first entry and the end of the last entry in
+    /// the database (as UTC seconds), or None without any entries.
+    pub fn read_entries_time_range(&self) -> Result<Option<(u64, u64)>, StorageError> {
+        let time_range: (Option<u64>, Option<u64>) = self.connection.query_row(
+            "SELECT MIN(utc_time_seconds), MAX(utc_time_seconds + duration_seconds)
+             FROM records ;",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        match time_range {
+            (Some(start_utc_time_seconds), Some(end_utc_time_seconds)) => {
+                Ok(Some((start_utc_time_seconds, end_utc_time_seconds)))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Rebuild the indexes (repairing corrupted indexes) and vacuum
+    /// the database, rewriting the file without the space left by
+    /// deleted rows.
+    pub fn rebuild(&mut self) -> Result<(), StorageError> {
+        if !self.entries.is_empty() {
+            self.write_entries()?;
+        }
+        // VACUUM cannot run inside a transaction. The rewritten
+        // pages are copied from the write-ahead log into the database
+        // file straight away, so the file is compacted now.
+        self.connection
+            .execute_batch("REINDEX; VACUUM; PRAGMA wal_checkpoint(TRUNCATE);")?;
+        Ok(())
+    }
+
+    /// Copy the database to a new file, using SQLite's online backup,
+    /// so the copy is consistent even while another process (such
+    /// as the recorder) is writing to the database.
+    ///
+    /// Like the database file, only the current user can read the
+    /// backup file.
+    pub fn backup_to_file(&self, backup_file_path: &Path) -> Result<(), StorageError> {
+        if backup_file_path.exists() {
+            return Err(StorageError::InvalidValue(format!(
+                "Backup file {:?} already exists.",
+                backup_file_path
+            )));
+        }
+        std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(backup_file_path)?;
+
+        let mut backup_connection = rusqlite::Connection::open(backup_file_path)?;
+        {
+            let backup = rusqlite::backup::Backup::new(&self.connection, &mut backup_connection)?;
+            run_backup_steps(&backup)?;
+        }
+        backup_connection
+            .close()
+            .map_err(|(_connection, err)| StorageError::from(err))?;
+        Ok(())
+    }
+
+    /// Replace the contents of the database with a backup file
+    /// (written by 'backup_to_file'), using SQLite's online backup,
+    /// so other connections see either the old or the restored
+    /// database. Backups of older schema versions are migrated, and
+    /// the restore is recorded in the audit log of the backup.
+    pub fn restore_from_file(&mut self, backup_file_path: &Path) -> Result<(), StorageError> {
+        if !self.entries.is_empty() {
+            self.write_entries()?;
+        }
+
+        let backup_connection = rusqlite::Connection::open_with_flags(
+            backup_file_path,
+            rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY | rusqlite::OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?;
+        {
+            let backup = rusqlite::backup::Backup::new(&backup_connection, &mut self.connection)?;
+            run_backup_steps(&backup)?;
+        }
+        backup_connection
+            .close()
+            .map_err(|(_connection, err)| StorageError::from(err))?;
+
+        migrate_database(&self.connection)?;
+        initialize_views(&self.connection)?;
+        insert_audit_log_entry(
+            &self.connection,
+            "restore_from_file",
+            &backup_file_path.display().to_string(),
+            0,
+        )?;
+        Ok(())
+    }
+
+    pub fn close(mut self) -> Result<(), StorageError> {
+        if !self.entries.is_empty() {
+            self.write_entries()?;
+        }
+
+        if !self.connection.is_autocommit() {
+            warn!("Rolling back unfinished database transaction.");
+            self.connection.execute_batch("ROLLBACK;")?;
+        }
+
+        if self.optimize_on_close {
+            self.connection.execute_batch("PRAGMA optimize;")?;
+        }
+
+        self.connection
+            .close()
+            .map_err(|(_connection, err)| StorageError::from(err))?;
+        debug!("Closed Time Tracker Storage.");
+        Ok(())
+    }
+}
+
+/// A read-only connection to a database file, opened with
+/// 'Storage::open_as_read_only'. Only the methods reading the database
+/// are available, so readers (such as the print and GUI tools) cannot
+/// change the database.
+pub struct ReadOnlyStorage {
+    storage: Storage,
+}
+
+impl ReadOnlyStorage {
+    /// See 'Storage::schema_version'.
+    pub fn schema_version(&self) -> Result<u32, StorageError> {
+        self.storage.schema_version()
+    }
+
+    /// See 'Storage::set_use_recorded_utc_offset'.
+    pub fn set_use_recorded_utc_offset(&mut self, value: bool) {
+        self.storage.set_use_recorded_utc_offset(value);
+    }
+
+    /// See 'Storage::read_entries'.
+    pub fn read_entries(
+        &mut self,
+        start_utc_time_seconds: u64,
+        end_utc_time_seconds: u64,
+    ) -> Result<Entries, StorageError> {
+        self.storage
+            .read_entries(start_utc_time_seconds, end_utc_time_seconds)
+    }
+
+    /// See 'Storage::stream_entries'.
+    pub fn stream_entries<F, E>(
+        &self,
+        start_utc_time_seconds: u64,
+        end_utc_time_seconds: u64,
+        callback: F,
+    ) -> Result<(), E>
+    where
+        F: FnMut(Entry) -> Result<(), E>,
+        E: From<StorageError>,
+    {
+        self.storage
+            .stream_entries(start_utc_time_seconds, end_utc_time_seconds, callback)
+    }
+
+    /// See 'Storage::sum_entries_duration_seconds'.
+    pub fn sum_entries_duration_seconds(
+        &self,
+        start_utc_time_seconds: u64,
+        end_utc_time_seconds: u64,
+        status: EntryStatus,
+    ) -> Result<u64, StorageError> {
+        self.storage.sum_entries_duration_seconds(
+            start_utc_time_seconds,
+            end_utc_time_seconds,
+            status,
+        )
+    }
+
+    /// See 'Storage::sum_durations_grouped_by'.
+    pub fn sum_durations_grouped_by(
+        &self,
+        column_name: &str,
+        start_utc_time_seconds: u64,
+        end_utc_time_seconds: u64,
+        status: EntryStatus,
+    ) -> Result<Vec<(Option<String>, u64)>, StorageError> {
+        self.storage.sum_durations_grouped_by(
+            column_name,
+            start_utc_time_seconds,
+            end_utc_time_seconds,
+            status,
+        )
+    }
+
+    /// See 'Storage::read_last_entry'.
+    pub fn read_last_entry(&self) -> Result<Option<Entry>, StorageError> {
+        self.storage.read_last_entry()
+    }
+
+    /// See 'Storage::read_last_entry_variable_value'.
+    pub fn read_last_entry_variable_value(
+        &self,
+        start_utc_time_seconds: u64,
+        status: EntryStatus,
+        variable_name: &str,
+    ) -> Result<Option<String>, StorageError> {
+        self.storage
+            .read_last_entry_variable_value(start_utc_time_seconds, status, variable_name)
+    }
+
+    /// See 'Storage::read_day_notes'.
+    pub fn read_day_notes(
+        &self,
+        start_date: chrono::NaiveDate,
+        end_date: chrono::NaiveDate,
+    ) -> Result<Vec<DayNote>, StorageError> {
+        self.storage.read_day_notes(start_date, end_date)
+    }
+
+    /// See 'Storage::read_plans'.
+    pub fn read_plans(
+        &self,
+        start_date: chrono::NaiveDate,
+        end_date: chrono::NaiveDate,
+    ) -> Result<Vec<WeekPlan>, StorageError> {
+        self.storage.read_plans(start_date, end_date)
+    }
+
+    /// See 'Storage::read_privacy_consent'.
+    pub fn read_privacy_consent(&self) -> Result<Option<PrivacyConsent>, StorageError> {
+        self.storage.read_privacy_consent()
+    }
+
+    /// See 'Storage::read_audit_log'.
+    pub fn read_audit_log(&self) -> Result<Vec<AuditLogEntry>, StorageError> {
+        self.storage.read_audit_log()
+    }
+
+    /// See 'Storage::read_window_color_periods'.
+    pub fn read_window_color_periods(
+        &self,
+        start_utc_time_seconds: u64,
+        end_utc_time_seconds: u64,
+    ) -> Result<Vec<WindowColorPeriod>, StorageError> {
+        self.storage
+            .read_window_color_periods(start_utc_time_seconds, end_utc_time_seconds)
+    }
+
+    /// See 'Storage::read_host_busy_periods'.
+    pub fn read_host_busy_periods(
+        &self,
+        start_utc_time_seconds: u64,
+        end_utc_time_seconds: u64,
+    ) -> Result<Vec<HostBusyPeriod>, StorageError> {
+        self.storage
+            .read_host_busy_periods(start_utc_time_seconds, end_utc_time_seconds)
+    }
+
+    /// See 'Storage::read_executable_versions'.
+    pub fn read_executable_versions(&mut self) -> Result<Vec<ExecutableVersion>, StorageError> {
+        self.storage.read_executable_versions()
+    }
+
+    /// See 'Storage::check_integrity'.
+    pub fn check_integrity(&self) -> Result<Vec<String>, StorageError> {
+        self.storage.check_integrity()
+    }
+
+    /// See 'Storage::backup_to_file'.
+    pub fn backup_to_file(&self, backup_file_path: &Path) -> Result<(), StorageError> {
+        self.storage.backup_to_file(backup_file_path)
+    }
+
+    /// See 'Storage::read_table_row_counts'.
+    pub fn read_table_row_counts(&self) -> Result<Vec<(String, u64)>, StorageError> {
+        self.storage.read_table_row_counts()
+    }
+
+    /// See 'Storage::read_entries_time_range'.
+    pub fn read_entries_time_range(&self) -> Result<Option<(u64, u64)>, StorageError> {
+        self.storage.read_entries_time_range()
+    }
+
+    pub fn close(self) -> Result<(), StorageError> {
+        self.storage.close()
+    }
+}
+
+impl StorageBackend for Storage {
+    fn open(
+        database_file_path: &Path,
+        record_interval_seconds: u64,
+        auto_create_database_file: bool,
+    ) -> Result<Storage, StorageError> {
+        Storage::open(
+            database_file_path,
+            record_interval_seconds,
+            auto_create_database_file,
+        )
+    }
+
+    fn insert_entries(&mut self, entries: &[Entry]) {
+        Storage::insert_entries(self, entries)
+    }
+
+    fn read_entries(
+        &mut self,
+        start_utc_time_seconds: u64,
+        end_utc_time_seconds: u64,
+    ) -> Result<Entries, StorageError> {
+        Storage::read_entries(self, start_utc_time_seconds, end_utc_time_seconds)
+    }
+
+    fn write_entries(&mut self) -> Result<(), StorageError> {
+        Storage::write_entries(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_entries(utc_times_seconds: &[u64]) -> Entries {
+        let entries = utc_times_seconds
+            .iter()
+            .map(|x| Entry::new(*x, 10, EntryStatus::Active, EntryVariablesList::empty()))
+            .collect();
+        Entries::builder().entries(entries).build()
+    }
+
+    fn range_times(entries: &Entries, start_of_time: u64, end_of_time: u64) -> Vec<u64> {
+        entries
+            .datetime_range_entries(
+                utc_seconds_to_datetime_local(start_of_time),
+                utc_seconds_to_datetime_local(end_of_time),
+            )
+            .iter()
+            .map(|x| x.utc_time_seconds)
+            .collect()
+    }
+
+    fn temp_database_file_path(name: &str) -> std::path::PathBuf {
+        let file_name = format!(
+            "timetracker-storage-{}-{}.sqlite3",
+            name,
+            std::process::id()
+        );
+        let path = std::env::temp_dir().join(file_name);
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn test_open_as_read_only_missing_file() {
+        let database_file_path = temp_database_file_path("missing");
+        let result = Storage::open_as_read_only(&database_file_path, 10);
+        assert!(matches!(result, Err(StorageError::Missing(_))));
+        assert!(!database_file_path.exists());
+    }
+
+    #[test]
+    fn test_read_while_writing() -> Result<(), StorageError> {
+        let database_file_path = temp_database_file_path("wal");
+        let mut writer = Storage::open_as_read_write(&database_file_path, 10)?;
+        writer.insert_entries(new_entries(&[100]).all_entries());
+        writer.write_entries()?;
+        let journal_mode: String =
+            writer
+                .connection
+                .query_row("PRAGMA journal_mode ;", [], |row| row.get(0))?;
+        assert_eq!(journal_mode, "wal");
+
+        // The reader is not blocked by the unfinished write, and
+        // reads the last committed entries.
+        writer
+            .connection
+            .execute_batch("BEGIN IMMEDIATE; DELETE FROM records;")?;
+        let mut reader = Storage::open_as_read_only(&database_file_path, 10)?;
+        assert_eq!(reader.read_entries(0, 1000)?.all_entries().len(), 1);
+        reader.close()?;
+        writer.connection.execute_batch("ROLLBACK;")?;
+        writer.close()?;
+
+        std::fs::remove_file(&database_file_path).unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_entries_in_chunks() -> Result<(), StorageError> {
+        let database_file_path = temp_database_file_path("chunks");
+        // More entries than two chunks of rows, spaced apart so the
+        // entries are not merged.
+        let utc_times_seconds: Vec<u64> = (0..(INSERT_RECORDS_ROWS_PER_STATEMENT as u64 * 2 + 5))
+            .map(|x| 1000 + (x * 100))
+            .collect();
+        let mut storage = Storage::open_as_read_write(&database_file_path, 10)?;
+        storage.insert_entries(new_entries(&utc_times_seconds).all_entries());
+        storage.write_entries()?;
+
+        let entries = storage.read_entries(0, u32::MAX as u64)?;
+        let read_utc_times_seconds: Vec<u64> = entries
+            .all_entries()
+            .iter()
+            .map(|x| x.utc_time_seconds)
+            .collect();
+        assert_eq!(read_utc_times_seconds, utc_times_seconds);
+        assert!(entries
+            .all_entries()
+            .iter()
+            .all(|x| x.duration_seconds == 10));
+        storage.close()?;
+
+        std::fs::remove_file(&database_file_path).unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_entries() -> Result<(), StorageError> {
+        let database_file_path = temp_database_file_path("stream");
+        let mut storage = Storage::open_as_read_write(&database_file_path, 10)?;
+        storage.insert_entries(new_entries(&[100, 200, 300]).all_entries());
+        storage.write_entries()?;
+
+        // The entries overlapping the range are clamped to the range.
+        let mut entries = Vec::new();
+        storage.stream_entries(105, 1000, |entry| {
+            entries.push((entry.utc_time_seconds, entry.duration_seconds));
+            Ok::<(), StorageError>(())
+        })?;
+        assert_eq!(entries, vec![(105, 5), (200, 10), (300, 10)]);
+
+        // Streaming stops at the first error of the callback.
+        let mut count = 0;
+        let result = storage.stream_entries(0, 1000, |_entry| {
+            count += 1;
+            Err(StorageError::InvalidValue("stop".to_string()))
+        });
+        assert!(result.is_err());
+        assert_eq!(count, 1);
+        storage.close()?;
+
+        std::fs::remove_file(&database_file_path).unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_entry_display() -> Result<(), StorageError> {
+        let database_file_path = temp_database_file_path("display");
+        let mut vars = EntryVariablesList::empty();
+        vars.display = Some(":1".to_string());
+        let mut storage = Storage::open_as_read_write(&database_file_path, 10)?;
+        storage.insert_entries(&[
+            Entry::new(100, 10, EntryStatus::Active, vars),
+            Entry::new(200, 10, EntryStatus::Active, EntryVariablesList::empty()),
+        ]);
+        storage.write_entries()?;
+
+        let entries = storage.read_entries(0, 1000)?;
+        let displays: Vec<Option<String>> = entries
+            .all_entries()
+            .iter()
+            .map(|x| x.vars.display.clone())
+            .collect();
+        assert_eq!(displays, vec![Some(":1".to_string()), None]);
+        storage.close()?;
+
+        std::fs::remove_file(&database_file_path).unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn test_sum_durations_grouped_by() -> Result<(), StorageError> {
+        let database_file_path = temp_database_file_path("grouped");
+        let mut storage = Storage::open_as_read_write(&database_file_path, 10)?;
+        let new_entry = |utc_time_seconds, executable: Option<&str>| {
+            let mut vars = EntryVariablesList::empty();
+            vars.executable = executable.map(|x| x.to_string());
+            Entry::new(utc_time_seconds, 10, EntryStatus::Active, vars)
+        };
+        storage.insert_entries(&[
+            new_entry(100, Some("firefox")),
+            new_entry(200, Some("emacs")),
+            new_entry(300, Some("firefox")),
+            new_entry(400, None),
+        ]);
+        storage.write_entries()?;
+
+        // The entry crossing the start of the range is clamped.
+        let mut durations =
+            storage.sum_durations_grouped_by("executable", 105, 1000, EntryStatus::Active)?;
+        durations.sort();
+        assert_eq!(
+            durations,
+            vec![
+                (None, 10),
+                (Some("emacs".to_string()), 10),
+                (Some("firefox".to_string()), 15),
+            ]
+        );
+        assert!(storage
+            .sum_durations_grouped_by("executable", 0, 1000, EntryStatus::Idle)?
+            .is_empty());
+        assert!(storage
+            .sum_durations_grouped_by("status", 0, 1000, EntryStatus::Active)
+            .is_err());
+        storage.close()?;
+
+        std::fs::remove_file(&database_file_path).unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn test_sum_durations_grouped_by_matches_read_entries() -> Result<(), StorageError> {
+        let database_file_path = temp_database_file_path("grouped-clamped");
+        let mut storage = Storage::open_as_read_write(&database_file_path, 10)?;
+        let new_entry = |utc_time_seconds, duration_seconds, executable: &str| {
+            let mut vars = EntryVariablesList::empty();
+            vars.executable = Some(executable.to_string());
+            Entry::new(
+                utc_time_seconds,
+                duration_seconds,
+                EntryStatus::Active,
+                vars,
+            )
+        };
+        // The entries cross the start and end of the range.
+        storage.insert_entries(&[
+            new_entry(90, 20, "firefox"),
+            new_entry(150, 10, "emacs"),
+            new_entry(190, 30, "firefox"),
+        ]);
+        storage.write_entries()?;
+
+        let (start_utc_time_seconds, end_utc_time_seconds) = (100, 200);
+        let mut durations = storage.sum_durations_grouped_by(
+            "executable",
+            start_utc_time_seconds,
+            end_utc_time_seconds,
+            EntryStatus::Active,
+        )?;
+        durations.sort();
+
+        let entries = storage.read_entries(start_utc_time_seconds, end_utc_time_seconds)?;
+        let mut expected = std::collections::BTreeMap::<Option<String>, u64>::new();
+        for entry in entries.all_entries() {
+            *expected.entry(entry.vars.executable.clone()).or_insert(0) += entry.duration_seconds;
+        }
+        assert_eq!(durations, expected.into_iter().collect::<Vec<_>>());
+        assert_eq!(
+            durations,
+            vec![
+                (Some("emacs".to_string()), 10),
+                (Some("firefox".to_string()), 20),
+            ]
+        );
+        storage.close()?;
+
+        std::fs::remove_file(&database_file_path).unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_entries_values() -> Result<(), StorageError> {
+        let database_file_path = temp_database_file_path("update");
+        let mut storage = Storage::open_as_read_write(&database_file_path, 10)?;
+        let new_entry = |utc_time_seconds, executable: &str| {
+            let mut vars = EntryVariablesList::empty();
+            vars.executable = Some(executable.to_string());
+            Entry::new(utc_time_seconds, 10, EntryStatus::Active, vars)
+        };
+        storage.insert_entries(&[
+            new_entry(100, "firefox"),
+            new_entry(200, "emacs"),
+            new_entry(300, "firefox"),
+            new_entry(400, "firefox"),
+        ]);
+        storage.write_entries()?;
+
+        // Only the matching entries in the range are changed.
+        let count = storage.update_entries_values(
+            0,
+            350,
+            &[("executable".to_string(), Some("firefox".to_string()))],
+            &[
+                ("var1_name".to_string(), Some("PROJECT".to_string())),
+                ("var1_value".to_string(), Some("research".to_string())),
+            ],
+        )?;
+        assert_eq!(count, 2);
+        let values: Vec<(u64, Option<String>)> = storage
+            .read_entries(0, 1000)?
+            .all_entries()
+            .iter()
+            .map(|x| (x.utc_time_seconds, x.vars.var1_value.clone()))
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                (100, Some("research".to_string())),
+                (200, None),
+                (300, Some("research".to_string())),
+                (400, None),
+            ]
+        );
+
+        // Unknown columns are never used in the statement.
+        let result = storage.update_entries_values(
+            0,
+            1000,
+            &[],
+            &[("status; DROP TABLE records".to_string(), None)],
+        );
+        assert!(matches!(result, Err(StorageError::InvalidValue(_))));
+        assert!(storage.update_entries_values(0, 1000, &[], &[]).is_err());
+        storage.close()?;
+
+        std::fs::remove_file(&database_file_path).unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn test_undo_operation() -> Result<(), StorageError> {
+        let database_file_path = temp_database_file_path("undo");
+        let mut storage = Storage::open_as_read_write(&database_file_path, 10)?;
+        storage.insert_entries(new_entries(&[100, 200, 300]).all_entries());
+        storage.write_entries()?;
+        let read_times = |storage: &mut Storage| -> Result<Vec<(u64, u64)>, StorageError> {
+            Ok(storage
+                .read_entries(0, 1000)?
+                .all_entries()
+                .iter()
+                .map(|x| (x.utc_time_seconds, x.duration_seconds))
+                .collect())
+        };
+        let original_times = read_times(&mut storage)?;
+
+        // Operations are only saved to be undone when enabled.
+        storage.delete_entries(300, 400)?;
+        assert_eq!(storage.last_undoable_audit_log_entry()?, None);
+
+        storage.set_record_undo(true);
+        storage.delete_entries(105, 250)?;
+        assert_eq!(read_times(&mut storage)?, vec![(100, 5)]);
+        let audit_log_entry = storage.last_undoable_audit_log_entry()?.unwrap();
+        assert_eq!(audit_log_entry.operation, "delete_entries");
+
+        let count = storage.undo_operation(audit_log_entry.id.unwrap())?;
+        assert_eq!(count, 2);
+        assert_eq!(read_times(&mut storage)?, original_times[..2].to_vec());
+        assert_eq!(storage.last_undoable_audit_log_entry()?, None);
+        assert!(storage.undo_operation(audit_log_entry.id.unwrap()).is_err());
+
+        // Entries changed again after the operation cannot be undone.
+        let set_executable = |storage: &mut Storage, executable: &str| {
+            storage.update_entries_values(
+                0,
+                1000,
+                &[],
+                &[("executable".to_string(), Some(executable.to_string()))],
+            )
+        };
+        set_executable(&mut storage, "emacs")?;
+        storage.set_record_undo(false);
+        set_executable(&mut storage, "firefox")?;
+        let audit_log_entry = storage.last_undoable_audit_log_entry()?.unwrap();
+        assert_eq!(audit_log_entry.operation, "update_entries_values");
+        assert!(storage.undo_operation(audit_log_entry.id.unwrap()).is_err());
+        assert_eq!(read_times(&mut storage)?, original_times[..2].to_vec());
+        storage.close()?;
+
+        std::fs::remove_file(&database_file_path).unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_and_rebuild_database() -> Result<(), StorageError> {
+        let database_file_path = temp_database_file_path("check");
+
+        let mut storage = Storage::open_as_read_write(&database_file_path, 10)?;
+        assert_eq!(storage.read_entries_time_range()?, None);
+        storage.insert_entries(new_entries(&[100, 200]).all_entries());
+        storage.write_entries()?;
+        storage.delete_entries(200, 300)?;
+        storage.rebuild()?;
+
+        assert!(storage.check_integrity()?.is_empty());
+        assert_eq!(storage.read_entries_time_range()?, Some((100, 110)));
+        let table_row_counts = storage.read_table_row_counts()?;
+        assert!(table_row_counts.contains(&("records".to_string(), 1)));
+        // The write of the entries, and the delete.
+        assert!(table_row_counts.contains(&("audit_log".to_string(), 2)));
+        storage.close()?;
+
+        std::fs::remove_file(&database_file_path).unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn test_backup_and_restore() -> Result<(), StorageError> {
+        let database_file_path = temp_database_file_path("backup-source");
+        let backup_file_path = temp_database_file_path("backup");
+
+        let mut storage = Storage::open_as_read_write(&database_file_path, 10)?;
+        storage.insert_entries(new_entries(&[100, 200]).all_entries());
+        storage.write_entries()?;
+        storage.backup_to_file(&backup_file_path)?;
+        assert!(storage.backup_to_file(&backup_file_path).is_err());
+
+        storage.delete_entries(0, 1000)?;
+        assert_eq!(storage.read_entries_time_range()?, None);
+        storage.restore_from_file(&backup_file_path)?;
+        assert_eq!(storage.read_entries_time_range()?, Some((100, 210)));
+        assert!(storage.check_integrity()?.is_empty());
+        storage.close()?;
+
+        let backup_storage = Storage::open_as_read_only(&backup_file_path, 10)?;
+        assert!(backup_storage.check_integrity()?.is_empty());
+        backup_storage.close()?;
+
+        std::fs::remove_file(&database_file_path).unwrap();
+        std::fs::remove_file(&backup_file_path).unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn test_datetime_range_entries() {
+        let entries = new_entries(&[100, 110, 120, 130]);
+        assert_eq!(range_times(&entries, 0, 1000), vec![100, 110, 120, 130]);
+        // The first and last matching entries are not the first and
+        // last entries.
+        assert_eq!(range_times(&entries, 105, 125), vec![110, 120]);
+        // Entries starting at the start and end are included.
+        assert_eq!(range_times(&entries, 110, 120), vec![110, 120]);
+        assert_eq!(range_times(&entries, 130, 130), vec![130]);
+    }
+
+    #[test]
+    fn test_datetime_range_entries_empty() {
+        let entries = new_entries(&[100, 110, 120, 130]);
+        assert!(range_times(&entries, 0, 99).is_empty());
+        assert!(range_times(&entries, 131, 1000).is_empty());
+        assert!(range_times(&entries, 111, 119).is_empty());
+        // The end is before the start.
+        assert!(range_times(&entries, 120, 110).is_empty());
+
+        let entries = new_entries(&[]);
+        assert!(range_times(&entries, 0, 1000).is_empty());
+    }
+
+    #[test]
+    fn test_datetime_range_entries_single_entry() {
+        let entries = new_entries(&[100]);
+        assert_eq!(range_times(&entries, 0, 1000), vec![100]);
+        assert_eq!(range_times(&entries, 100, 100), vec![100]);
+        assert!(range_times(&entries, 101, 1000).is_empty());
+
+        // Unsorted entries are sorted when built.
+        let entries = new_entries(&[120, 100, 110]);
+        assert_eq!(range_times(&entries, 105, 1000), vec![110, 120]);
     }
 }
@@ -0,0 +1,217 @@
+//! Terminal capability detection.
+//!
+//! The capabilities of the terminal do not change while running, so
+//! they are detected once and cached.
+
+use log::debug;
+use std::io::IsTerminal;
+use std::sync::OnceLock;
+
+// The Operating System Command (OSC) 8 escape sequence for
+// hyperlinks, described here:
+// https://gist.github.com/egmontkob/eb114294efbcd5adb1944c9f3cb5feda
+const OSC8_START: &str = "\x1b]8;;";
+const OSC8_END: &str = "\x1b\\";
+
+static SUPPORTS_COLOR: OnceLock<bool> = OnceLock::new();
+static SUPPORTS_HYPERLINKS: OnceLock<bool> = OnceLock::new();
+
+fn detect_color_support() -> bool {
+    if !std::io::stdout().is_terminal() {
+        return false;
+    }
+    let info = match terminfo::Database::from_env() {
+        Ok(value) => value,
+        Err(err) => {
+            debug!("Could not read terminfo database: {:?}", err);
+            return false;
+        }
+    };
+    let terminal_max_colors = info.get::<terminfo::capability::MaxColors>();
+    debug!("terminal_max_colors={:?}", terminal_max_colors);
+    match terminal_max_colors {
+        Some(n) => n.0 > 0,
+        None => false,
+    }
+}
+
+/// Does the terminal support colored text? Terminals without a
+/// terminfo entry are assumed to not support color, and so is a
+/// standard output that is not a terminal (such as a file or pipe).
+pub fn supports_color() -> bool {
+    *SUPPORTS_COLOR.get_or_init(|| {
+        let color_is_supported = detect_color_support();
+        debug!("terminal_supports_color={}", color_is_supported);
+        color_is_supported
+    })
+}
+
+fn detect_hyperlink_support() -> bool {
+    if !std::io::stdout().is_terminal() {
+        return false;
+    }
+    let env_var = |name: &str| std::env::var(name).unwrap_or_default();
+
+    let term = env_var("TERM");
+    if term == "dumb" || term.starts_with("screen") || term.starts_with("tmux") {
+        // Terminal multiplexers may not pass the sequences through.
+        return false;
+    }
+    if ["xterm-kitty", "foot", "alacritty", "wezterm"]
+        .iter()
+        .any(|x| term.starts_with(x))
+    {
+        return true;
+    }
+    if ["iTerm.app", "WezTerm", "vscode"].contains(&env_var("TERM_PROGRAM").as_str()) {
+        return true;
+    }
+    if !env_var("WT_SESSION").is_empty() || !env_var("KONSOLE_VERSION").is_empty() {
+        return true;
+    }
+
+    // VTE based terminals (such as GNOME Terminal) support
+    // hyperlinks since VTE 0.50.
+    env_var("VTE_VERSION")
+        .parse::<u32>()
+        .map(|version| version >= 5000)
+        .unwrap_or(false)
+}
+
+/// Does the terminal support OSC 8 hyperlinks? Detected from the
+/// environment variables set by terminals known to support them.
+pub fn supports_hyperlinks() -> bool {
+    *SUPPORTS_HYPERLINKS.get_or_init(|| {
+        let hyperlinks_are_supported = detect_hyperlink_support();
+        debug!("terminal_supports_hyperlinks={}", hyperlinks_are_supported);
+        hyperlinks_are_supported
+    })
+}
+
+/// Format the text as a hyperlink to the URL, when the terminal
+/// supports hyperlinks, otherwise the text is returned as-is.
+pub fn hyperlink(url: &str, text: &str) -> String {
+    match supports_hyperlinks() {
+        true => format!("{OSC8_START}{url}{OSC8_END}{text}{OSC8_START}{OSC8_END}"),
+        false => text.to_string(),
+    }
+}
+
+/// The width of the terminal (the number of columns), or None if
+/// the standard output is not a terminal.
+///
+/// The width can change while running (when the terminal is
+/// resized), so it is not cached.
+pub fn width() -> Option<usize> {
+    if !std::io::stdout().is_terminal() {
+        return None;
+    }
+
+    if let Some(columns) = std::env::var("COLUMNS")
+        .ok()
+        .and_then(|x| x.parse::<usize>().ok())
+        .filter(|x| *x > 0)
+    {
+        return Some(columns);
+    }
+
+    let mut window_size: libc::winsize = unsafe { std::mem::zeroed() };
+    let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut window_size) };
+    match result == 0 && window_size.ws_col > 0 {
+        true => Some(window_size.ws_col as usize),
+        false => None,
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum EscapeState {
+    Text,
+    // After the "ESC" character.
+    Start,
+    // Inside a Control Sequence, such as a color "ESC [ 31 m".
+    ControlSequence,
+    // Inside an Operating System Command, such as a hyperlink,
+    // ending with "ESC \" or "BEL".
+    OperatingSystemCommand,
+    OperatingSystemCommandEscape,
+}
+
+fn next_escape_state(state: EscapeState, c: char) -> EscapeState {
+    match (state, c) {
+        (EscapeState::Text, '\x1b') => EscapeState::Start,
+        (EscapeState::Text, _) => EscapeState::Text,
+        (EscapeState::Start, '[') => EscapeState::ControlSequence,
+        (EscapeState::Start, ']') => EscapeState::OperatingSystemCommand,
+        (EscapeState::Start, _) => EscapeState::Text,
+        (EscapeState::ControlSequence, c) if ('@'..='~').contains(&c) => EscapeState::Text,
+        (EscapeState::ControlSequence, _) => EscapeState::ControlSequence,
+        (EscapeState::OperatingSystemCommand, '\x07') => EscapeState::Text,
+        (EscapeState::OperatingSystemCommand, '\x1b') => EscapeState::OperatingSystemCommandEscape,
+        (EscapeState::OperatingSystemCommand, _) => EscapeState::OperatingSystemCommand,
+        (EscapeState::OperatingSystemCommandEscape, _) => EscapeState::Text,
+    }
+}
+
+/// The number of characters displayed, excluding escape sequences.
+pub fn visible_width(line: &str) -> usize {
+    let mut width = 0;
+    let mut state = EscapeState::Text;
+    for c in line.chars() {
+        let previous_state = state;
+        state = next_escape_state(state, c);
+        if previous_state == EscapeState::Text && state == EscapeState::Text {
+            width += 1;
+        }
+    }
+    width
+}
+
+/// Truncate a line of text to the width (in characters), ending
+/// with "…" when truncated.
+///
+/// Escape sequences (such as colors) do not count towards the width,
+/// and are kept, so colors are still reset at the end of the line.
+pub fn truncate_to_width(line: &str, width: usize) -> String {
+    if visible_width(line) <= width {
+        return line.to_string();
+    }
+
+    let kept_width = width.saturating_sub(1);
+    let mut text = String::with_capacity(line.len());
+    let mut char_count = 0;
+    let mut has_ellipsis = false;
+    let mut state = EscapeState::Text;
+    for c in line.chars() {
+        let previous_state = state;
+        state = next_escape_state(state, c);
+        let is_text = previous_state == EscapeState::Text && state == EscapeState::Text;
+        if !is_text {
+            text.push(c);
+        } else if char_count < kept_width {
+            text.push(c);
+            char_count += 1;
+        } else if !has_ellipsis {
+            text.push('…');
+            has_ellipsis = true;
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_to_width_keeps_escape_sequences() {
+        assert_eq!(truncate_to_width("abcdef", 6), "abcdef");
+        assert_eq!(truncate_to_width("abcdef", 4), "abc…");
+
+        let line = "\x1b[31mabc\x1b[0mdef";
+        assert_eq!(visible_width(line), 6);
+        assert_eq!(truncate_to_width(line, 4), "\x1b[31mabc\x1b[0m…");
+
+        let link = "\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x1b\\ text";
+        assert_eq!(visible_width(link), 9);
+    }
+}
@@ -0,0 +1,325 @@
+use crate::settings::CommandArguments;
+use crate::settings::CommandModes;
+use crate::settings::DbAppSettings;
+use anyhow::{anyhow, bail, Result};
+use chrono::TimeZone;
+use clap::Parser;
+use log::debug;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+use timetracker_core::filesystem::get_database_file_path;
+use timetracker_core::filesystem::get_database_file_paths_for_range;
+use timetracker_core::format::format_datetime;
+use timetracker_core::format::DateTimeFormat;
+use timetracker_core::multi_storage::MultiStorage;
+use timetracker_core::schema;
+use timetracker_core::settings::DEFAULT_RECORD_INTERVAL_SECONDS;
+use timetracker_core::storage::ReadOnlyStorage;
+use timetracker_core::storage::Storage;
+
+mod settings;
+
+fn find_database_file_path(settings: &DbAppSettings) -> Result<PathBuf> {
+    get_database_file_path(
+        &settings.core.database_dir,
+        &settings.core.database_file_name,
+    )
+    .ok_or_else(|| {
+        anyhow!(
+            "Database file {:?} not found in {:?}",
+            &settings.core.database_file_name,
+            &settings.core.database_dir
+        )
+    })
+}
+
+/// Open all the database files (including every per-year database
+/// file).
+fn open_all_database_files(settings: &DbAppSettings, read_only: bool) -> Result<MultiStorage> {
+    let database_file_path = find_database_file_path(settings)?;
+    let now_utc_time_seconds = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let database_file_paths = get_database_file_paths_for_range(
+        &database_file_path,
+        settings.core.database_per_year,
+        &settings.core.archive_dir,
+        0,
+        now_utc_time_seconds,
+    );
+    let storage = match read_only {
+        true => {
+            MultiStorage::open_as_read_only(&database_file_paths, DEFAULT_RECORD_INTERVAL_SECONDS)?
+        }
+        false => {
+            MultiStorage::open_as_read_write(&database_file_paths, DEFAULT_RECORD_INTERVAL_SECONDS)?
+        }
+    };
+    Ok(storage)
+}
+
+fn print_audit_log(settings: &DbAppSettings, limit: Option<usize>) -> Result<()> {
+    let storage = open_all_database_files(settings, true)?;
+    let audit_log_entries = storage.read_audit_log()?;
+    storage.close()?;
+
+    if audit_log_entries.is_empty() {
+        println!("No operations recorded.");
+        return Ok(());
+    }
+
+    let skip_count = match limit {
+        Some(limit) => audit_log_entries.len().saturating_sub(limit),
+        None => 0,
+    };
+    for audit_log_entry in audit_log_entries.iter().skip(skip_count) {
+        let datetime = chrono::Local
+            .timestamp_opt(audit_log_entry.utc_time_seconds as i64, 0)
+            .unwrap();
+        let id = match audit_log_entry.id {
+            Some(id) => id.to_string(),
+            None => "-".to_string(),
+        };
+        let undo_status = match (
+            audit_log_entry.undo_utc_time_seconds,
+            audit_log_entry.can_undo,
+        ) {
+            (Some(undo_utc_time_seconds), _) => {
+                format!(
+                    " | undone {}",
+                    format_utc_time_seconds(undo_utc_time_seconds)
+                )
+            }
+            (None, true) => " | can undo".to_string(),
+            (None, false) => "".to_string(),
+        };
+        println!(
+            "{} | {} | {} | {} | {} | {} | {} rows{}",
+            id,
+            format_datetime(datetime, DateTimeFormat::Iso),
+            audit_log_entry.user_name,
+            audit_log_entry.source.as_deref().unwrap_or("-"),
+            audit_log_entry.operation,
+            audit_log_entry.filter,
+            audit_log_entry.row_count,
+            undo_status
+        );
+    }
+
+    Ok(())
+}
+
+fn undo_last_operation(settings: &DbAppSettings) -> Result<()> {
+    let mut storage = open_all_database_files(settings, false)?;
+    let result = storage.undo_last_operation()?;
+    storage.close()?;
+
+    match result {
+        Some((audit_log_entry, count)) => println!(
+            "Undone {} ({}) from {}, restoring {} entries.",
+            audit_log_entry.operation,
+            audit_log_entry.filter,
+            format_utc_time_seconds(audit_log_entry.utc_time_seconds),
+            count
+        ),
+        None => println!("No operations to undo."),
+    }
+    Ok(())
+}
+
+fn format_utc_time_seconds(utc_time_seconds: u64) -> String {
+    let datetime = chrono::Local
+        .timestamp_opt(utc_time_seconds as i64, 0)
+        .unwrap();
+    format_datetime(datetime, DateTimeFormat::Iso)
+}
+
+fn print_integrity_problems(problems: &[String]) {
+    match problems.len() {
+        0 => println!("Integrity: ok"),
+        count => {
+            println!("Integrity: {} problems", count);
+            for problem in problems {
+                println!("  {}", problem.replace('\n', "\n  "));
+            }
+        }
+    }
+}
+
+/// Print the number of rows of each table, and the dates covered by
+/// the entries.
+fn print_database_contents(storage: &ReadOnlyStorage) -> Result<()> {
+    for (table_name, count) in storage.read_table_row_counts()? {
+        println!("Table {}: {} rows", table_name, count);
+    }
+    match storage.read_entries_time_range()? {
+        Some((start_utc_time_seconds, end_utc_time_seconds)) => println!(
+            "Entries: {} to {}",
+            format_utc_time_seconds(start_utc_time_seconds),
+            format_utc_time_seconds(end_utc_time_seconds)
+        ),
+        None => println!("Entries: none"),
+    }
+    Ok(())
+}
+
+/// Rebuild the database file, returning true when the database file
+/// is intact afterwards.
+fn repair_database_file(database_file_path: &Path) -> Result<bool> {
+    let mut storage =
+        Storage::open_as_read_write(database_file_path, DEFAULT_RECORD_INTERVAL_SECONDS)?;
+    let file_size_before = std::fs::metadata(database_file_path)?.len();
+    if let Err(err) = storage.rebuild() {
+        println!("Failed to rebuild: {}", err);
+        storage.close()?;
+        return Ok(false);
+    }
+    let file_size_after = std::fs::metadata(database_file_path)?.len();
+    println!(
+        "Rebuilt indexes and vacuumed: {} bytes to {} bytes.",
+        file_size_before, file_size_after
+    );
+
+    let problems = storage.check_integrity()?;
+    print_integrity_problems(&problems);
+    storage.close()?;
+    Ok(problems.is_empty())
+}
+
+/// Check the database file, and rebuild it when repairing. Returns
+/// true when the database file is intact (after repairing).
+fn check_database_file(database_file_path: &Path, repair: bool) -> Result<bool> {
+    println!("Database file: {:?}", database_file_path);
+    let storage =
+        match Storage::open_as_read_only(database_file_path, DEFAULT_RECORD_INTERVAL_SECONDS) {
+            Ok(storage) => storage,
+            Err(err) => {
+                println!("Failed to open: {}", err);
+                return Ok(false);
+            }
+        };
+
+    let problems = storage.check_integrity()?;
+    print_integrity_problems(&problems);
+    // The contents of a corrupted database may not be readable.
+    if let Err(err) = print_database_contents(&storage) {
+        println!("Failed to read: {}", err);
+    }
+    storage.close()?;
+
+    match repair {
+        true => repair_database_file(database_file_path),
+        false => Ok(problems.is_empty()),
+    }
+}
+
+fn check_database_files(settings: &DbAppSettings, repair: bool) -> Result<()> {
+    let database_file_path = find_database_file_path(settings)?;
+    let now_utc_time_seconds = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let database_file_paths = get_database_file_paths_for_range(
+        &database_file_path,
+        settings.core.database_per_year,
+        &settings.core.archive_dir,
+        0,
+        now_utc_time_seconds,
+    );
+
+    let mut corrupted_count = 0;
+    for (index, database_file_path) in database_file_paths
+        .iter()
+        .filter(|x| x.is_file())
+        .enumerate()
+    {
+        if index > 0 {
+            println!();
+        }
+        if !check_database_file(database_file_path, repair)? {
+            corrupted_count += 1;
+        }
+    }
+
+    if corrupted_count > 0 {
+        match repair {
+            true => bail!(
+                "{} database files could not be repaired; restore them from a backup, or recover the readable rows with the 'sqlite3' '.recover' command.",
+                corrupted_count
+            ),
+            false => bail!(
+                "{} database files are corrupted; repair with 'timetracker-db check --repair'.",
+                corrupted_count
+            ),
+        }
+    }
+    Ok(())
+}
+
+fn print_schema(settings: &DbAppSettings, ddl: bool) -> Result<()> {
+    if ddl {
+        println!("{}", schema::canonical_ddl());
+        return Ok(());
+    }
+
+    println!("Schema version: {}", schema::SCHEMA_VERSION);
+    if let Ok(database_file_path) = find_database_file_path(settings) {
+        let storage =
+            Storage::open_as_read_only(&database_file_path, DEFAULT_RECORD_INTERVAL_SECONDS)?;
+        let version = storage.schema_version()?;
+        storage.close()?;
+        println!(
+            "Database schema version: {} ({:?})",
+            version, database_file_path
+        );
+    }
+    println!(
+        "Tables: {}",
+        [
+            schema::TABLE_RECORDS,
+            schema::TABLE_EXECUTABLE_VERSIONS,
+            schema::TABLE_DAY_NOTES,
+            schema::TABLE_HOST_BUSY,
+            schema::TABLE_WINDOW_COLORS,
+            schema::TABLE_PRIVACY_CONSENT,
+            schema::TABLE_AUDIT_LOG,
+            schema::TABLE_SCHEMA_VERSION,
+        ]
+        .join(", ")
+    );
+    for (view_name, column_names) in [
+        (schema::VIEW_ENTRIES, schema::VIEW_ENTRIES_COLUMNS),
+        (
+            schema::VIEW_ENTRY_VARIABLES,
+            schema::VIEW_ENTRY_VARIABLES_COLUMNS,
+        ),
+    ] {
+        println!("View {}: {}", view_name, column_names.join(", "));
+    }
+
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let env = env_logger::Env::default()
+        .filter_or("TIMETRACKER_LOG", "warn")
+        .write_style("TIMETRACKER_LOG_STYLE");
+    env_logger::init_from_env(env);
+
+    let args = CommandArguments::parse();
+
+    let settings = DbAppSettings::new(&args);
+    if settings.is_err() {
+        bail!("Settings are invalid: {:?}", settings);
+    }
+    let settings = settings?;
+    debug!("Settings validated: {:#?}", settings);
+
+    match &args.command {
+        CommandModes::Audit { limit, undo } => match undo {
+            Some(_) => undo_last_operation(&settings)?,
+            None => print_audit_log(&settings, *limit)?,
+        },
+        CommandModes::Check { repair } => check_database_files(&settings, *repair)?,
+        CommandModes::Schema { ddl } => print_schema(&settings, *ddl)?,
+    }
+
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use clap::{Parser, Subcommand};
+use config::ConfigError;
+use serde_derive::Deserialize;
+use timetracker_core::settings::new_core_settings;
+use timetracker_core::settings::validate_core_settings;
+use timetracker_core::settings::CoreSettings;
+
+#[derive(Parser, Debug)]
+#[clap(author = "David Cattermole, Copyright 2023-2024", version, about)]
+#[clap(propagate_version = true)]
+pub struct CommandArguments {
+    #[clap(subcommand)]
+    pub command: CommandModes,
+
+    /// Override the directory to search for the database file.
+    #[clap(long, value_parser)]
+    pub database_dir: Option<String>,
+
+    /// Override the name of the database file to open.
+    #[clap(long, value_parser)]
+    pub database_file_name: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CommandModes {
+    /// Print the audit log of operations (such as written, edited
+    /// or deleted entries), recorded in the database.
+    Audit {
+        /// Only print the most recent operations.
+        #[clap(long, value_parser)]
+        limit: Option<usize>,
+
+        /// Undo the last manual edit of the entries (made with
+        /// 'timetracker-edit'), restoring the entries before the
+        /// edit. Stop the recorder first.
+        #[clap(long, value_parser = clap::builder::PossibleValuesParser::new(["last"]))]
+        undo: Option<String>,
+    },
+    /// Check each database file for corruption (such as after a
+    /// power loss), and print the number of rows of each table and
+    /// the dates covered by the entries.
+    Check {
+        /// Rebuild the indexes and vacuum each database file after
+        /// checking, repairing corrupted indexes and compacting the
+        /// file. Stop the recorder first.
+        #[clap(long, value_parser, default_value_t = false)]
+        repair: bool,
+    },
+    /// Print the tables and views of the database schema, which
+    /// external tools may query directly.
+    Schema {
+        /// Print the SQL statements (DDL) creating every table and
+        /// view of the current schema version.
+        #[clap(long, value_parser, default_value_t = false)]
+        ddl: bool,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(unused)]
+pub struct DbAppSettings {
+    pub core: CoreSettings,
+}
+
+impl DbAppSettings {
+    pub fn new(arguments: &CommandArguments) -> Result<Self, ConfigError> {
+        let builder = new_core_settings(
+            arguments.database_dir.clone(),
+            arguments.database_file_name.clone(),
+            false,
+        )?;
+
+        let settings: Self = builder.build()?.try_deserialize()?;
+        validate_core_settings(&settings.core).unwrap();
+
+        Ok(settings)
+    }
+}
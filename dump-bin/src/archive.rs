@@ -0,0 +1,247 @@
+use crate::settings::DumpAppSettings;
+use anyhow::{anyhow, bail, Result};
+use chrono::Datelike;
+use std::collections::BTreeMap;
+use std::path::Path;
+use timetracker_core::entries::Entry;
+use timetracker_core::entries::HostBusyPeriod;
+use timetracker_core::entries::WindowColorPeriod;
+use timetracker_core::filesystem::get_archive_database_file_path;
+use timetracker_core::filesystem::get_database_file_path;
+use timetracker_core::filesystem::get_database_file_paths_for_range;
+use timetracker_core::settings::DEFAULT_MAX_MERGED_ENTRY_SECONDS;
+use timetracker_core::settings::DEFAULT_RECORD_INTERVAL_SECONDS;
+use timetracker_core::storage::Storage;
+use timetracker_print_lib::datetime::get_datetime_range;
+use timetracker_print_lib::datetime::utc_seconds_to_datetime_local;
+
+/// Entries are read this long past the start of the archived range,
+/// so the entries overlapping the start are moved whole (rather than
+/// clamped to the start).
+const ENTRY_OVERLAP_SECONDS: u64 = 24 * 60 * 60;
+
+/// The entries and recorded periods moved to the archive database
+/// file of a year.
+#[derive(Default)]
+struct ArchiveYear {
+    entries: Vec<Entry>,
+    host_busy_periods: Vec<HostBusyPeriod>,
+    window_color_periods: Vec<WindowColorPeriod>,
+}
+
+/// Parse an age, such as "6months", "8weeks", "90days" or "1year"
+/// (with an optional space before the unit), returning the date the
+/// age before 'today'.
+fn parse_age_start_date(text: &str, today: chrono::NaiveDate) -> Result<chrono::NaiveDate> {
+    let invalid_age = || {
+        anyhow!(
+            "Invalid age {:?}, expected a number and unit, such as \"6months\", \"8weeks\", \"90days\" or \"1year\".",
+            text
+        )
+    };
+    let text = text.trim();
+    let unit_index = text
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid_age)?;
+    let (number, unit) = text.split_at(unit_index);
+    let number: u32 = number.parse().map_err(|_| invalid_age())?;
+    match unit.trim() {
+        "d" | "day" | "days" => today.checked_sub_days(chrono::Days::new(number.into())),
+        "w" | "week" | "weeks" => today.checked_sub_days(chrono::Days::new(u64::from(number) * 7)),
+        "month" | "months" => today.checked_sub_months(chrono::Months::new(number)),
+        "y" | "year" | "years" => number
+            .checked_mul(12)
+            .and_then(|months| today.checked_sub_months(chrono::Months::new(months))),
+        _ => None,
+    }
+    .ok_or_else(invalid_age)
+}
+
+/// Write the entries and recorded periods to the archive database
+/// file, creating the file if needed.
+///
+/// The entries already in the archive (such as when an earlier
+/// archive was interrupted before the entries were deleted) are
+/// skipped.
+fn write_archive_year(archive_file_path: &Path, archive_year: &ArchiveYear) -> Result<usize> {
+    let mut storage =
+        Storage::open_as_read_write(archive_file_path, DEFAULT_RECORD_INTERVAL_SECONDS)?;
+    storage.set_max_merged_entry_seconds(DEFAULT_MAX_MERGED_ENTRY_SECONDS);
+    let last_utc_time_seconds = storage.read_last_entry()?.map(|x| x.utc_time_seconds);
+    let is_new = |utc_time_seconds: u64| match last_utc_time_seconds {
+        Some(last_utc_time_seconds) => utc_time_seconds > last_utc_time_seconds,
+        None => true,
+    };
+
+    let entries: Vec<Entry> = archive_year
+        .entries
+        .iter()
+        .filter(|x| is_new(x.utc_time_seconds))
+        .cloned()
+        .collect();
+    let host_busy_periods: Vec<HostBusyPeriod> = archive_year
+        .host_busy_periods
+        .iter()
+        .filter(|x| is_new(x.utc_time_seconds))
+        .cloned()
+        .collect();
+    let window_color_periods: Vec<WindowColorPeriod> = archive_year
+        .window_color_periods
+        .iter()
+        .filter(|x| is_new(x.utc_time_seconds))
+        .cloned()
+        .collect();
+
+    storage.insert_entries(&entries);
+    storage.write_entries()?;
+    storage.write_host_busy_periods(&host_busy_periods)?;
+    storage.write_window_color_periods(&window_color_periods)?;
+    storage.close()?;
+    Ok(entries.len())
+}
+
+/// Move the entries and recorded periods starting before the end time
+/// from the database file into the archive database file of each
+/// year. Returns the number of entries moved.
+fn archive_database_file(
+    database_file_path: &Path,
+    archive_dir: &Path,
+    database_file_name: &str,
+    end_utc_time_seconds: u64,
+) -> Result<usize> {
+    let mut storage =
+        Storage::open_as_read_write(database_file_path, DEFAULT_RECORD_INTERVAL_SECONDS)?;
+    let entries = storage.read_entries(0, end_utc_time_seconds + ENTRY_OVERLAP_SECONDS)?;
+
+    let year_of = |utc_time_seconds: u64| utc_seconds_to_datetime_local(utc_time_seconds).year();
+    let mut archive_years = BTreeMap::<i32, ArchiveYear>::new();
+    for entry in entries.all_entries() {
+        if entry.utc_time_seconds < end_utc_time_seconds {
+            let archive_year = archive_years.entry(year_of(entry.utc_time_seconds));
+            archive_year.or_default().entries.push(entry.clone());
+        }
+    }
+    for period in entries.host_busy_periods() {
+        if period.utc_time_seconds < end_utc_time_seconds {
+            let archive_year = archive_years.entry(year_of(period.utc_time_seconds));
+            archive_year
+                .or_default()
+                .host_busy_periods
+                .push(period.clone());
+        }
+    }
+    for period in entries.window_color_periods() {
+        if period.utc_time_seconds < end_utc_time_seconds {
+            let archive_year = archive_years.entry(year_of(period.utc_time_seconds));
+            archive_year
+                .or_default()
+                .window_color_periods
+                .push(period.clone());
+        }
+    }
+    if archive_years.is_empty() {
+        storage.close()?;
+        return Ok(0);
+    }
+
+    // The archive files are written before the entries are deleted,
+    // so an error never loses any entries.
+    for (year, archive_year) in &archive_years {
+        let archive_file_path =
+            get_archive_database_file_path(archive_dir, database_file_name, *year);
+        let count = write_archive_year(&archive_file_path, archive_year)?;
+        println!(
+            "Moving {} entries from {:?} to {:?}.",
+            count, database_file_path, archive_file_path
+        );
+    }
+    let count = storage.delete_entries(0, end_utc_time_seconds)?;
+    storage.close()?;
+    Ok(count)
+}
+
+/// Move the entries (and recorded periods) older than the age into
+/// the per-year archive database files, in the 'archive_dir' (or the
+/// 'core.archive_dir' setting).
+///
+/// The entries are moved from the start of the local day, so a day
+/// is never split between the archive and the database. Day notes
+/// and plans are kept in the database.
+pub fn archive_older_entries(
+    settings: &DumpAppSettings,
+    older_than: &str,
+    archive_dir: Option<&str>,
+) -> Result<()> {
+    let archive_dir = archive_dir.unwrap_or(&settings.core.archive_dir);
+    if archive_dir.is_empty() {
+        bail!("No archive directory; give '--to' or set 'core.archive_dir'.");
+    }
+    let archive_dir = Path::new(archive_dir);
+
+    let today = chrono::Local::now().date_naive();
+    let start_date = parse_age_start_date(older_than, today)?;
+    let (start_datetime, _) = get_datetime_range(start_date, start_date)?;
+    let end_utc_time_seconds = start_datetime.timestamp() as u64;
+
+    let database_file_path = get_database_file_path(
+        &settings.core.database_dir,
+        &settings.core.database_file_name,
+    )
+    .expect("Database file path should be valid");
+    let database_file_paths = get_database_file_paths_for_range(
+        &database_file_path,
+        settings.core.database_per_year,
+        "",
+        0,
+        end_utc_time_seconds,
+    );
+
+    std::fs::create_dir_all(archive_dir)?;
+    let mut count = 0;
+    for database_file_path in database_file_paths {
+        if database_file_path.is_file() {
+            count += archive_database_file(
+                &database_file_path,
+                archive_dir,
+                &settings.core.database_file_name,
+                end_utc_time_seconds,
+            )?;
+        }
+    }
+
+    println!(
+        "Archived {} entries before {} to {:?}.",
+        count, start_date, archive_dir
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_age_start_date() {
+        let today = chrono::NaiveDate::from_ymd_opt(2024, 8, 31).unwrap();
+        let date = |year, month, day| chrono::NaiveDate::from_ymd_opt(year, month, day).unwrap();
+        assert_eq!(
+            parse_age_start_date("6months", today).unwrap(),
+            date(2024, 2, 29)
+        );
+        assert_eq!(
+            parse_age_start_date("2 weeks", today).unwrap(),
+            date(2024, 8, 17)
+        );
+        assert_eq!(
+            parse_age_start_date("90days", today).unwrap(),
+            date(2024, 6, 2)
+        );
+        assert_eq!(
+            parse_age_start_date("1year", today).unwrap(),
+            date(2023, 8, 31)
+        );
+        assert!(parse_age_start_date("6", today).is_err());
+        assert!(parse_age_start_date("months", today).is_err());
+        assert!(parse_age_start_date("6fortnights", today).is_err());
+    }
+}
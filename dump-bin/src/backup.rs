@@ -0,0 +1,246 @@
+use crate::settings::DumpAppSettings;
+use anyhow::{bail, Result};
+use chrono::Datelike;
+use std::fs::File;
+use std::io::prelude::*;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+use std::path::PathBuf;
+use timetracker_core::filesystem::get_database_file_path;
+use timetracker_core::filesystem::get_year_database_file_path;
+use timetracker_core::settings::DEFAULT_RECORD_INTERVAL_SECONDS;
+use timetracker_core::storage::Storage;
+
+/// The first bytes of a gzip compressed file.
+const GZIP_MAGIC_BYTES: [u8; 2] = [0x1f, 0x8b];
+
+/// The database file to back up (or restore); the file of the year
+/// when 'core.database_per_year' is enabled, defaulting to the
+/// current year.
+fn find_database_file_path(settings: &DumpAppSettings, year: Option<i32>) -> Result<PathBuf> {
+    let database_file_path = get_database_file_path(
+        &settings.core.database_dir,
+        &settings.core.database_file_name,
+    )
+    .expect("Database file path should be valid");
+    match (settings.core.database_per_year, year) {
+        (true, year) => {
+            let year = year.unwrap_or_else(|| chrono::Local::now().year());
+            Ok(get_year_database_file_path(&database_file_path, year))
+        }
+        (false, Some(_)) => {
+            bail!("'--year' can only be given when 'core.database_per_year' is enabled.")
+        }
+        (false, None) => Ok(database_file_path),
+    }
+}
+
+/// The path of a file written before being moved to the file path,
+/// so a failure never leaves an incomplete file at the file path.
+fn partial_file_path(file_path: &Path) -> PathBuf {
+    let mut file_name = file_path.as_os_str().to_owned();
+    file_name.push(".partial");
+    PathBuf::from(file_name)
+}
+
+/// Create a new file that only the current user can read, like the
+/// database file.
+fn create_private_file(file_path: &Path) -> Result<File> {
+    let file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(file_path)?;
+    Ok(file)
+}
+
+fn is_gzip_file(file_path: &Path) -> Result<bool> {
+    let mut magic_bytes = [0; 2];
+    let mut file = File::open(file_path)?;
+    match file.read_exact(&mut magic_bytes) {
+        Ok(()) => Ok(magic_bytes == GZIP_MAGIC_BYTES),
+        Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn compress_file(input_file_path: &Path, output_file_path: &Path) -> Result<()> {
+    let mut input_file = File::open(input_file_path)?;
+    let output_file = create_private_file(output_file_path)?;
+    let mut encoder = flate2::write::GzEncoder::new(output_file, flate2::Compression::default());
+    std::io::copy(&mut input_file, &mut encoder)?;
+    encoder.finish()?.sync_all()?;
+    Ok(())
+}
+
+fn decompress_file(input_file_path: &Path, output_file_path: &Path) -> Result<()> {
+    let mut decoder = flate2::read::GzDecoder::new(File::open(input_file_path)?);
+    let mut output_file = create_private_file(output_file_path)?;
+    std::io::copy(&mut decoder, &mut output_file)?;
+    output_file.sync_all()?;
+    Ok(())
+}
+
+/// Check the database file is not corrupt (using SQLite's
+/// 'integrity_check').
+fn verify_database_file(database_file_path: &Path) -> Result<()> {
+    let storage = Storage::open_as_read_only(database_file_path, DEFAULT_RECORD_INTERVAL_SECONDS)?;
+    let problems = storage.check_integrity()?;
+    storage.close()?;
+    if !problems.is_empty() {
+        bail!(
+            "Database file {:?} is corrupt: {}",
+            database_file_path,
+            problems.join("; ")
+        );
+    }
+    Ok(())
+}
+
+/// Remove the file (if written) when the result is an error.
+fn remove_file_on_error<T>(result: Result<T>, file_path: &Path) -> Result<T> {
+    if result.is_err() && file_path.exists() {
+        let _ = std::fs::remove_file(file_path);
+    }
+    result
+}
+
+/// Back up the database file to a new file, using SQLite's online
+/// backup so the backup is consistent while the recorder is writing.
+/// The backup is verified before it is written to the file path.
+pub fn backup_database(
+    settings: &DumpAppSettings,
+    backup_file: &str,
+    gzip: bool,
+    year: Option<i32>,
+) -> Result<()> {
+    let database_file_path = find_database_file_path(settings, year)?;
+    let backup_file_path = Path::new(backup_file);
+    if backup_file_path.exists() {
+        bail!("Backup file {:?} already exists.", backup_file_path);
+    }
+
+    let partial_backup_file_path = partial_file_path(backup_file_path);
+    let result = (|| {
+        let storage =
+            Storage::open_as_read_only(&database_file_path, DEFAULT_RECORD_INTERVAL_SECONDS)?;
+        storage.backup_to_file(&partial_backup_file_path)?;
+        storage.close()?;
+        verify_database_file(&partial_backup_file_path)?;
+
+        match gzip {
+            true => {
+                compress_file(&partial_backup_file_path, backup_file_path)?;
+                std::fs::remove_file(&partial_backup_file_path)?;
+            }
+            false => std::fs::rename(&partial_backup_file_path, backup_file_path)?,
+        }
+        Ok(())
+    })();
+    remove_file_on_error(result, &partial_backup_file_path)?;
+
+    println!(
+        "Backed up {:?} to {:?}.",
+        database_file_path, backup_file_path
+    );
+    Ok(())
+}
+
+/// Replace the database file with a backup (written by
+/// 'backup_database', compressed or not). The backup is verified
+/// before the database file is changed.
+pub fn restore_database(
+    settings: &DumpAppSettings,
+    backup_file: &str,
+    year: Option<i32>,
+) -> Result<()> {
+    let database_file_path = find_database_file_path(settings, year)?;
+    let backup_file_path = Path::new(backup_file);
+    if !backup_file_path.is_file() {
+        bail!("Backup file {:?} does not exist.", backup_file_path);
+    }
+
+    // Compressed backups are decompressed next to the database file.
+    let decompressed_file_path = partial_file_path(&database_file_path);
+    let is_compressed = is_gzip_file(backup_file_path)?;
+    let result = (|| {
+        let restore_file_path = match is_compressed {
+            true => {
+                decompress_file(backup_file_path, &decompressed_file_path)?;
+                decompressed_file_path.as_path()
+            }
+            false => backup_file_path,
+        };
+        verify_database_file(restore_file_path)?;
+
+        let mut storage =
+            Storage::open_as_read_write(&database_file_path, DEFAULT_RECORD_INTERVAL_SECONDS)?;
+        storage.restore_from_file(restore_file_path)?;
+        let problems = storage.check_integrity()?;
+        storage.close()?;
+        if !problems.is_empty() {
+            bail!(
+                "Restored database file {:?} is corrupt: {}",
+                database_file_path,
+                problems.join("; ")
+            );
+        }
+        Ok(())
+    })();
+    if is_compressed && decompressed_file_path.exists() {
+        std::fs::remove_file(&decompressed_file_path)?;
+    }
+    result?;
+
+    println!(
+        "Restored {:?} from {:?}.",
+        database_file_path, backup_file_path
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compress_and_decompress_file() -> Result<()> {
+        let temp_file_path = |name: &str| {
+            let path = std::env::temp_dir().join(format!(
+                "timetracker-backup-{}-{}",
+                name,
+                std::process::id()
+            ));
+            let _ = std::fs::remove_file(&path);
+            path
+        };
+        let input_file_path = temp_file_path("input");
+        let compressed_file_path = temp_file_path("compressed");
+        let output_file_path = temp_file_path("output");
+        std::fs::write(&input_file_path, b"SQLite format 3\0 and more data")?;
+
+        compress_file(&input_file_path, &compressed_file_path)?;
+        assert!(is_gzip_file(&compressed_file_path)?);
+        assert!(!is_gzip_file(&input_file_path)?);
+        decompress_file(&compressed_file_path, &output_file_path)?;
+        assert_eq!(
+            std::fs::read(&output_file_path)?,
+            std::fs::read(&input_file_path)?
+        );
+        // Existing files are never overwritten.
+        assert!(compress_file(&input_file_path, &compressed_file_path).is_err());
+
+        for path in [input_file_path, compressed_file_path, output_file_path] {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_partial_file_path() {
+        assert_eq!(
+            partial_file_path(Path::new("/backups/timetracker.sqlite3.gz")),
+            PathBuf::from("/backups/timetracker.sqlite3.gz.partial")
+        );
+    }
+}
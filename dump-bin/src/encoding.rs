@@ -0,0 +1,143 @@
+use crate::settings::DumpEncoding;
+use log::warn;
+use std::io::prelude::*;
+
+/// The UTF-8 encoding of the byte order mark (U+FEFF).
+const UTF8_BYTE_ORDER_MARK: &[u8] = b"\xef\xbb\xbf";
+
+/// The character written (when encoding as Latin-1) in place of a
+/// character that cannot be encoded.
+const LATIN1_REPLACEMENT_BYTE: u8 = b'?';
+
+/// Encode text as Latin-1 (ISO-8859-1), returning the bytes and the
+/// number of characters that could not be encoded.
+fn encode_latin1(text: &str) -> (Vec<u8>, usize) {
+    let mut replaced_count = 0;
+    let bytes = text
+        .chars()
+        .map(|c| match u8::try_from(u32::from(c)) {
+            Ok(byte) => byte,
+            Err(_) => {
+                replaced_count += 1;
+                LATIN1_REPLACEMENT_BYTE
+            }
+        })
+        .collect();
+    (bytes, replaced_count)
+}
+
+/// Writes (UTF-8) text to the inner writer with the encoding.
+///
+/// Text may be written in any number of pieces; a character split
+/// between two writes is encoded once the rest of the character is
+/// written.
+pub struct EncodingWriter<W: Write> {
+    inner: W,
+    encoding: DumpEncoding,
+    pending: Vec<u8>,
+    has_started: bool,
+    replaced_count: usize,
+}
+
+impl<W: Write> EncodingWriter<W> {
+    pub fn new(inner: W, encoding: DumpEncoding) -> EncodingWriter<W> {
+        EncodingWriter {
+            inner,
+            encoding,
+            pending: Vec::new(),
+            has_started: false,
+            replaced_count: 0,
+        }
+    }
+
+    fn write_text(&mut self, text: &str) -> std::io::Result<()> {
+        match self.encoding {
+            DumpEncoding::Utf8 | DumpEncoding::Utf8Bom => self.inner.write_all(text.as_bytes()),
+            DumpEncoding::Latin1 => {
+                let (bytes, replaced_count) = encode_latin1(text);
+                self.replaced_count += replaced_count;
+                self.inner.write_all(&bytes)
+            }
+        }
+    }
+}
+
+impl<W: Write> Write for EncodingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if !self.has_started {
+            self.has_started = true;
+            if self.encoding == DumpEncoding::Utf8Bom {
+                self.inner.write_all(UTF8_BYTE_ORDER_MARK)?;
+            }
+        }
+
+        self.pending.extend_from_slice(buf);
+        let valid_length = match std::str::from_utf8(&self.pending) {
+            Ok(text) => text.len(),
+            // The end of the buffer is the start of a character.
+            Err(err) if err.error_len().is_none() => err.valid_up_to(),
+            Err(err) => {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, err));
+            }
+        };
+
+        let pending = std::mem::take(&mut self.pending);
+        let text = std::str::from_utf8(&pending[..valid_length]).expect("Text should be UTF-8");
+        self.write_text(text)?;
+        self.pending = pending[valid_length..].to_vec();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Drop for EncodingWriter<W> {
+    fn drop(&mut self) {
+        if self.replaced_count > 0 {
+            warn!(
+                "{} characters could not be encoded as {:?} and were replaced with {:?}.",
+                self.replaced_count, self.encoding, LATIN1_REPLACEMENT_BYTE as char
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(pieces: &[&[u8]], encoding: DumpEncoding) -> Vec<u8> {
+        let mut output = Vec::new();
+        {
+            let mut writer = EncodingWriter::new(&mut output, encoding);
+            for piece in pieces {
+                writer.write_all(piece).unwrap();
+            }
+            writer.flush().unwrap();
+        }
+        output
+    }
+
+    #[test]
+    fn test_encoding_writer_non_ascii() {
+        let text = "/home/josé/naïve_日本.blend";
+        // Split the text inside the (multi-byte) "é" character.
+        let split_index = "/home/jos".len() + 1;
+        let pieces = [
+            &text.as_bytes()[..split_index],
+            &text.as_bytes()[split_index..],
+        ];
+
+        assert_eq!(encode(&pieces, DumpEncoding::Utf8), text.as_bytes());
+
+        let mut expected = UTF8_BYTE_ORDER_MARK.to_vec();
+        expected.extend_from_slice(text.as_bytes());
+        assert_eq!(encode(&pieces, DumpEncoding::Utf8Bom), expected);
+
+        let mut expected = b"/home/jos\xe9/na\xefve_".to_vec();
+        expected.extend_from_slice(b"??.blend");
+        assert_eq!(encode(&pieces, DumpEncoding::Latin1), expected);
+    }
+}
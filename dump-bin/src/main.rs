@@ -1,109 +1,584 @@
+use crate::archive::archive_older_entries;
+use crate::backup::backup_database;
+use crate::backup::restore_database;
+use crate::encoding::EncodingWriter;
+use crate::ods::write_ods_tables;
+#[cfg(feature = "parquet")]
+use crate::parquet_file::write_parquet_table;
 use crate::settings::CommandArguments;
+use crate::settings::CsvDelimiter;
 use crate::settings::DumpAppSettings;
+use crate::settings::DumpCommand;
+use crate::settings::DumpEncoding;
+use crate::settings::DumpFormat;
+use crate::settings::FilterArguments;
+use crate::settings::FormatArguments;
+use crate::settings::RangeArguments;
+use crate::table::generate_aggregate_table;
+use crate::table::generate_entries_table;
+use crate::table::generate_summary_table;
+use crate::table::generate_versions_table;
+use crate::table::Table;
+use crate::table::TableValue;
 use anyhow::bail;
 use anyhow::Result;
 use clap::Parser;
 use log::debug;
 use std::io::prelude::*;
+use std::path::Path;
 use std::time::SystemTime;
+use timetracker_core::entries::merge_entries;
+use timetracker_core::entries::DayNote;
+use timetracker_core::entries::Entry;
+use timetracker_core::entries::ExecutableVersion;
+use timetracker_core::entries::WeekPlan;
 use timetracker_core::filesystem::get_database_file_path;
-use timetracker_core::settings::RECORD_INTERVAL_SECONDS;
+use timetracker_core::filesystem::get_database_file_paths_for_range;
+use timetracker_core::format::FirstDayOfWeek;
+use timetracker_core::multi_storage::MultiStorage;
+use timetracker_core::settings::DEFAULT_MAX_MERGED_ENTRY_SECONDS;
+use timetracker_core::settings::DEFAULT_RECORD_INTERVAL_SECONDS;
 use timetracker_core::storage::Entries;
 use timetracker_core::storage::Storage;
+use timetracker_print_lib::datetime::get_datetime_range;
+use timetracker_print_lib::datetime::get_relative_month_start_end;
+use timetracker_print_lib::datetime::parse_date;
+use timetracker_print_lib::datetime::DateTimeLocalPair;
+use timetracker_print_lib::filter::EntryFilter;
 use timetracker_print_lib::print::get_relative_week_start_end;
+use timetracker_print_lib::variable::Variable;
 
+mod archive;
+mod backup;
+mod encoding;
+mod ods;
+#[cfg(feature = "parquet")]
+mod parquet_file;
 mod settings;
+mod table;
 
-// CSV Spec: Each record is located on a separate line,
-// delimited by a line break (CRLF).
-static LINE_END: &[u8] = "\r\n".as_bytes();
+fn convert_to_json_string_value(value: &TableValue) -> Result<String> {
+    let value = match value {
+        TableValue::Text(value) => serde_json::to_string(value)?,
+        TableValue::Integer(value) => value.to_string(),
+        TableValue::Float(value) => serde_json::to_string(value)?,
+    };
+    Ok(value)
+}
+
+fn convert_to_csv_string_value(value: &TableValue) -> String {
+    match value {
+        TableValue::Text(value) => value.to_string(),
+        TableValue::Integer(value) => value.to_string(),
+        TableValue::Float(value) => value.to_string(),
+    }
+}
 
-// The CSV File Format header is described here:
+// The CSV File Format is described here:
 // https://www.rfc-editor.org/rfc/rfc4180#section-2
-static HEADER_LINE: &[u8] = concat!(
-    "utc_time_seconds,duration_seconds,",
-    "status,executable,",
-    "var1_name,var1_value,",
-    "var2_name,var2_value,",
-    "var3_name,var3_value,",
-    "var4_name,var4_value,",
-    "var5_name,var5_value",
-)
-.as_bytes();
-
-fn convert_to_csv_string_value(entry_var_name: &Option<String>) -> String {
-    match &entry_var_name {
-        Some(value) => value.to_string(),
-        None => "".to_string(),
-    }
-}
-
-fn generate_csv_formated_lines(entries: &Entries, lines: &mut Vec<String>) -> Result<()> {
-    for entry in entries.all_entries() {
-        let line = format!(
-            concat!(
-                "{utc_time_seconds},{duration_seconds},",
-                "{status:?},{executable},",
-                "{var1_name},{var1_value},",
-                "{var2_name},{var2_value},",
-                "{var3_name},{var3_value},",
-                "{var4_name},{var4_value},",
-                "{var5_name},{var5_value}"
-            ),
-            utc_time_seconds = entry.utc_time_seconds,
-            duration_seconds = entry.duration_seconds,
-            status = entry.status,
-            executable = convert_to_csv_string_value(&entry.vars.executable),
-            var1_name = convert_to_csv_string_value(&entry.vars.var1_name),
-            var1_value = convert_to_csv_string_value(&entry.vars.var1_value),
-            var2_name = convert_to_csv_string_value(&entry.vars.var2_name),
-            var2_value = convert_to_csv_string_value(&entry.vars.var2_value),
-            var3_name = convert_to_csv_string_value(&entry.vars.var3_name),
-            var3_value = convert_to_csv_string_value(&entry.vars.var3_value),
-            var4_name = convert_to_csv_string_value(&entry.vars.var4_name),
-            var4_value = convert_to_csv_string_value(&entry.vars.var4_value),
-            var5_name = convert_to_csv_string_value(&entry.vars.var5_name),
-            var5_value = convert_to_csv_string_value(&entry.vars.var5_value),
-        );
-        lines.push(line);
+//
+// Each record is located on a separate line, delimited by a line
+// break (CRLF), and fields containing the delimiter, quotes or line
+// breaks are quoted.
+fn write_csv_table(writer: &mut impl Write, table: &Table, delimiter: CsvDelimiter) -> Result<()> {
+    write_csv_rows(writer, table, delimiter, true)
+}
+
+/// Write the rows of the table as CSV records, after the column
+/// names if 'write_headers' is true.
+fn write_csv_rows(
+    writer: &mut impl Write,
+    table: &Table,
+    delimiter: CsvDelimiter,
+    write_headers: bool,
+) -> Result<()> {
+    let mut csv_writer = csv::WriterBuilder::new()
+        .delimiter(delimiter.as_byte())
+        .terminator(csv::Terminator::CRLF)
+        .from_writer(writer);
+    if write_headers {
+        csv_writer.write_record(&table.headers)?;
+    }
+    for row in &table.rows {
+        csv_writer.write_record(row.iter().map(convert_to_csv_string_value))?;
+    }
+    csv_writer.flush()?;
+    Ok(())
+}
+
+// The JSON Lines format is described here:
+// https://jsonlines.org/
+//
+// Each row is a JSON object on a separate line, with the fields in
+// the same order as the columns.
+fn write_json_lines_table(writer: &mut impl Write, table: &Table) -> Result<()> {
+    for row in &table.rows {
+        let fields = table
+            .headers
+            .iter()
+            .zip(row)
+            .map(|(header, value)| {
+                Ok(format!(
+                    "{}:{}",
+                    serde_json::to_string(header)?,
+                    convert_to_json_string_value(value)?
+                ))
+            })
+            .collect::<Result<Vec<String>>>()?;
+        writeln!(writer, "{{{}}}", fields.join(","))?;
     }
     Ok(())
 }
 
-fn dump_database(
-    args: &CommandArguments,
+/// The range of datetimes to dump; the current week by default.
+fn get_range_datetime_pair(
+    range: &RangeArguments,
+    first_day_of_week: FirstDayOfWeek,
+) -> Result<DateTimeLocalPair> {
+    if let Some(start) = &range.start {
+        let start_date = parse_date(start)?;
+        let end_date = match &range.end {
+            Some(end) => parse_date(end)?,
+            None => chrono::Local::now().date_naive(),
+        };
+        return Ok(get_datetime_range(start_date, end_date)?);
+    }
+    if let Some(relative_month) = range.month {
+        return Ok(get_relative_month_start_end(relative_month)?);
+    }
+
+    // 'relative_week' is added to the week number to find. A value of
+    // '-1' will get the previous week, a value of '0' will get the
+    // current week, and a value of '1' will get the next week (which
+    // shouldn't really give any results, so it's probably pointless).
+    let relative_week = match range.last_week {
+        true => -1,
+        false => range.week.unwrap_or(0),
+    };
+    Ok(get_relative_week_start_end(
+        relative_week,
+        first_day_of_week,
+    )?)
+}
+
+/// The number of entries written together when streaming entries.
+const STREAM_CHUNK_ENTRY_COUNT: usize = 1024;
+
+fn read_database(
     settings: &DumpAppSettings,
-    output_lines: &mut Vec<String>,
-) -> Result<()> {
+    range: &RangeArguments,
+    filter: &FilterArguments,
+) -> Result<Entries> {
+    let entry_filter = EntryFilter::new(&filter.executables, &filter.variables)?;
+    let (start_datetime, end_datetime) =
+        get_range_datetime_pair(range, settings.core.first_day_of_week)?;
+    read_database_range(
+        settings,
+        &entry_filter,
+        start_datetime.timestamp() as u64,
+        end_datetime.timestamp() as u64,
+    )
+}
+
+/// Open the database files covering the range.
+fn open_database_range(
+    settings: &DumpAppSettings,
+    start_of_time: u64,
+    end_of_time: u64,
+) -> Result<MultiStorage> {
     let database_file_path = get_database_file_path(
         &settings.core.database_dir,
         &settings.core.database_file_name,
+    )
+    .expect("Database file path should be valid");
+
+    let database_file_paths = get_database_file_paths_for_range(
+        &database_file_path,
+        settings.core.database_per_year,
+        &settings.core.archive_dir,
+        start_of_time,
+        end_of_time,
     );
+    let mut storage =
+        MultiStorage::open_as_read_only(&database_file_paths, DEFAULT_RECORD_INTERVAL_SECONDS)?;
+    storage.set_use_recorded_utc_offset(settings.core.use_recorded_utc_offset);
+    Ok(storage)
+}
 
-    let mut storage = Storage::open_as_read_only(
-        &database_file_path.expect("Database file path should be valid"),
-        RECORD_INTERVAL_SECONDS,
-    )?;
+fn read_database_range(
+    settings: &DumpAppSettings,
+    entry_filter: &EntryFilter,
+    start_of_time: u64,
+    end_of_time: u64,
+) -> Result<Entries> {
+    let mut storage = open_database_range(settings, start_of_time, end_of_time)?;
+    let entries = storage.read_entries(start_of_time, end_of_time)?;
+    storage.close()?;
+    Ok(entry_filter.filter_entries(entries))
+}
+
+/// Read the entries of the range, and the executable versions of the
+/// database files covering the range.
+fn read_database_versions(
+    settings: &DumpAppSettings,
+    range: &RangeArguments,
+    filter: &FilterArguments,
+) -> Result<(Entries, Vec<ExecutableVersion>)> {
+    let entry_filter = EntryFilter::new(&filter.executables, &filter.variables)?;
+    let (start_datetime, end_datetime) =
+        get_range_datetime_pair(range, settings.core.first_day_of_week)?;
+    let start_of_time = start_datetime.timestamp() as u64;
+    let end_of_time = end_datetime.timestamp() as u64;
+    let mut storage = open_database_range(settings, start_of_time, end_of_time)?;
+    let entries = storage.read_entries(start_of_time, end_of_time)?;
+    let executable_versions = storage.read_executable_versions()?;
+    storage.close()?;
+    Ok((entry_filter.filter_entries(entries), executable_versions))
+}
+
+fn write_table(writer: &mut impl Write, format: &FormatArguments, tables: &[Table]) -> Result<()> {
+    let table = &tables[0];
+    match format.format {
+        DumpFormat::Csv => {
+            // The column names are written even without rows.
+            let mut encoding_writer = EncodingWriter::new(writer, format.encoding);
+            write_csv_table(&mut encoding_writer, table, format.delimiter)?;
+        }
+        DumpFormat::Ods => write_ods_tables(writer, tables)?,
+        DumpFormat::JsonLines => write_json_lines_table(writer, table)?,
+        #[cfg(feature = "parquet")]
+        DumpFormat::Parquet => write_parquet_table(writer, table)?,
+        #[cfg(not(feature = "parquet"))]
+        DumpFormat::Parquet => bail!(
+            "Parquet export is not available; build timetracker-dump with the 'parquet' feature."
+        ),
+    }
+    Ok(())
+}
+
+/// Write the tables to the output file (or the standard output).
+///
+/// Only the ODS format can contain more than one table; the other
+/// formats are given the first table.
+fn dump_tables(format: &FormatArguments, tables: &[Table]) -> Result<()> {
+    let is_text_format = matches!(format.format, DumpFormat::Csv);
+    if !is_text_format && format.encoding != DumpEncoding::Utf8 {
+        bail!(
+            "The {:?} encoding can only be used with the CSV format.",
+            format.encoding
+        );
+    }
+
+    match &format.output_file {
+        Some(file_path) => {
+            let f = std::fs::File::create(file_path)?;
+            let mut writer = std::io::BufWriter::new(f);
+            write_table(&mut writer, format, tables)?;
+        }
+        None => {
+            let mut stdout = std::io::stdout().lock();
+            write_table(&mut stdout, format, tables)?;
+        }
+    }
+    Ok(())
+}
+
+/// Write the entries as rows of the entries table, in the CSV or
+/// JSON Lines format. The CSV column names are only written before
+/// the first row.
+fn write_entries_rows(
+    writer: &mut impl Write,
+    format: &FormatArguments,
+    entries: Vec<Entry>,
+    has_written_headers: &mut bool,
+) -> Result<()> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+
+    let table = generate_entries_table(&Entries::builder().entries(entries).build());
+    match format.format {
+        DumpFormat::Csv => {
+            write_csv_rows(writer, &table, format.delimiter, !*has_written_headers)?;
+            *has_written_headers = true;
+        }
+        DumpFormat::JsonLines => write_json_lines_table(writer, &table)?,
+        _ => bail!(
+            "The {:?} format cannot be written row by row.",
+            format.format
+        ),
+    }
+    Ok(())
+}
+
+/// Dump the entries of the range as they are read from the database,
+/// a chunk of entries at a time, so the memory used does not grow
+/// with the size of the range (such as a full year of entries).
+fn stream_entries(
+    settings: &DumpAppSettings,
+    range: &RangeArguments,
+    filter: &FilterArguments,
+    format: &FormatArguments,
+) -> Result<()> {
+    if !matches!(format.format, DumpFormat::Csv) && format.encoding != DumpEncoding::Utf8 {
+        bail!(
+            "The {:?} encoding can only be used with the CSV format.",
+            format.encoding
+        );
+    }
 
-    let relative_week = if args.last_week {
-        -1
-    } else {
-        args.relative_week
+    let writer: Box<dyn Write> = match &format.output_file {
+        Some(file_path) => Box::new(std::io::BufWriter::new(std::fs::File::create(file_path)?)),
+        None => Box::new(std::io::stdout().lock()),
     };
+    let mut writer = EncodingWriter::new(writer, format.encoding);
+    let mut has_written_headers = false;
 
-    // 'relative_week' is added to the week number to find. A value of
-    // '-1' will get the previous week, a value of '0' will get the
-    // current week, and a value of '1' will get the next week (which
-    // shouldn't really give any results, so it's probably pointless).
-    let week_datetime_pair = get_relative_week_start_end(relative_week)?;
+    let entry_filter = EntryFilter::new(&filter.executables, &filter.variables)?;
+    let (start_datetime, end_datetime) =
+        get_range_datetime_pair(range, settings.core.first_day_of_week)?;
+    let start_of_time = start_datetime.timestamp() as u64;
+    let end_of_time = end_datetime.timestamp() as u64;
+    let storage = open_database_range(settings, start_of_time, end_of_time)?;
+
+    let mut entries = Vec::with_capacity(STREAM_CHUNK_ENTRY_COUNT);
+    storage.stream_entries(start_of_time, end_of_time, |entry| -> Result<()> {
+        if entry_filter.matches(&entry) {
+            entries.push(entry);
+        }
+        if entries.len() >= STREAM_CHUNK_ENTRY_COUNT {
+            let chunk_entries = std::mem::take(&mut entries);
+            write_entries_rows(&mut writer, format, chunk_entries, &mut has_written_headers)?;
+        }
+        Ok(())
+    })?;
+    write_entries_rows(&mut writer, format, entries, &mut has_written_headers)?;
+    if matches!(format.format, DumpFormat::Csv) && !has_written_headers {
+        // No entries were dumped, but the column names are written,
+        // the same as an empty table.
+        let table = generate_entries_table(&Entries::builder().entries(Vec::new()).build());
+        write_csv_rows(&mut writer, &table, format.delimiter, true)?;
+    }
+    storage.close()?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Dump the entries of the range, then keep dumping the entries
+/// written after the last dumped entry, checking the database every
+/// 'interval_seconds', until the process is interrupted.
+///
+/// An entry is dumped once, when it is first written; when the
+/// recorder later extends the duration of the entry, it is not
+/// dumped again.
+fn follow_entries(
+    settings: &DumpAppSettings,
+    range: &RangeArguments,
+    filter: &FilterArguments,
+    format: &FormatArguments,
+    interval_seconds: u64,
+) -> Result<()> {
+    let is_csv_format = match format.format {
+        DumpFormat::Csv => true,
+        DumpFormat::JsonLines => false,
+        _ => bail!("The {:?} format cannot be followed.", format.format),
+    };
+    if !is_csv_format && format.encoding != DumpEncoding::Utf8 {
+        bail!(
+            "The {:?} encoding can only be used with the CSV format.",
+            format.encoding
+        );
+    }
+
+    let writer: Box<dyn Write> = match &format.output_file {
+        Some(file_path) => Box::new(std::fs::File::create(file_path)?),
+        None => Box::new(std::io::stdout()),
+    };
+    let mut writer = EncodingWriter::new(writer, format.encoding);
+    let mut has_written_headers = false;
+
+    let entry_filter = EntryFilter::new(&filter.executables, &filter.variables)?;
+    let (start_datetime, end_datetime) =
+        get_range_datetime_pair(range, settings.core.first_day_of_week)?;
+    let mut entries = read_database_range(
+        settings,
+        &entry_filter,
+        start_datetime.timestamp() as u64,
+        end_datetime.timestamp() as u64,
+    )?;
+    let mut next_start_of_time = start_datetime.timestamp() as u64;
+    loop {
+        let new_entries: Vec<Entry> = entries
+            .all_entries()
+            .iter()
+            .filter(|x| x.utc_time_seconds >= next_start_of_time)
+            .cloned()
+            .collect();
+        if let Some(last_entry) = new_entries.iter().max_by_key(|x| x.utc_time_seconds) {
+            next_start_of_time = last_entry.utc_time_seconds + 1;
+
+            write_entries_rows(&mut writer, format, new_entries, &mut has_written_headers)?;
+            writer.flush()?;
+        }
+
+        std::thread::sleep(std::time::Duration::from_secs(interval_seconds));
+        let now = chrono::Utc::now().timestamp() as u64;
+        // The entries overlapping the start of the range are clamped
+        // to start at the start, so the range starts at the last
+        // dumped entry, and the (already dumped) entries extended by
+        // the recorder are skipped.
+        entries = read_database_range(
+            settings,
+            &entry_filter,
+            next_start_of_time.saturating_sub(1),
+            now + 1,
+        )?;
+    }
+}
+
+fn group_by_variables(names: &[String]) -> Vec<Variable> {
+    if names.is_empty() {
+        return vec![Variable::Executable];
+    }
+    names.iter().map(|name| Variable::from_name(name)).collect()
+}
+
+/// Copy the entries (and the day notes, periods and plans) to a new
+/// database file.
+fn archive_entries(entries: &Entries, output_file: &str) -> Result<()> {
+    let output_file_path = Path::new(output_file);
+    if output_file_path.exists() {
+        bail!("Archive file {:?} already exists.", output_file_path);
+    }
+
+    let mut storage =
+        Storage::open_as_read_write(output_file_path, DEFAULT_RECORD_INTERVAL_SECONDS)?;
+    storage.set_max_merged_entry_seconds(DEFAULT_MAX_MERGED_ENTRY_SECONDS);
+    storage.insert_entries(entries.all_entries());
+    storage.write_entries()?;
+    for day_note in entries.day_notes() {
+        storage.write_day_note(day_note)?;
+    }
+    for plan in entries.plans() {
+        storage.write_plan(plan)?;
+    }
+    storage.write_host_busy_periods(entries.host_busy_periods())?;
+    storage.write_window_color_periods(entries.window_color_periods())?;
+    storage.close()?;
 
-    let (week_start_datetime, week_end_datetime) = week_datetime_pair;
+    println!(
+        "Archived {} entries to {:?}.",
+        entries.all_entries().len(),
+        output_file_path
+    );
+    Ok(())
+}
+
+/// Read all of the entries (and the day notes, periods and plans) of each
+/// database file, and write the combined data to a new database file.
+///
+/// Entries found in more than one database file (such as a copy of
+/// the same database) are only written once.
+fn merge_databases(input_files: &[String], output_file: &str) -> Result<()> {
+    let output_file_path = Path::new(output_file);
+    if output_file_path.exists() {
+        bail!("Merged file {:?} already exists.", output_file_path);
+    }
+
+    // The latest time that can be stored is used, so no entries are
+    // missed when the clocks of the machines differ.
+    let start_of_time = 0;
+    let end_of_time = u32::MAX as u64;
+
+    let mut entries = Vec::new();
+    let mut day_notes = Vec::<DayNote>::new();
+    let mut host_busy_periods = Vec::new();
+    let mut window_color_periods = Vec::new();
+    let mut plans = Vec::<WeekPlan>::new();
+    for input_file in input_files {
+        let input_file_path = Path::new(input_file);
+        if !input_file_path.is_file() {
+            bail!("Database file {:?} does not exist.", input_file_path);
+        }
+        let mut storage =
+            Storage::open_as_read_only(input_file_path, DEFAULT_RECORD_INTERVAL_SECONDS)?;
+        let input_entries = storage.read_entries(start_of_time, end_of_time)?;
+        storage.close()?;
+        debug!(
+            "Read {} entries from {:?}.",
+            input_entries.all_entries().len(),
+            input_file_path
+        );
+
+        entries.extend_from_slice(input_entries.all_entries());
+        host_busy_periods.extend_from_slice(input_entries.host_busy_periods());
+        window_color_periods.extend_from_slice(input_entries.window_color_periods());
+        // The plan of a value in a later file replaces the plan in an
+        // earlier file.
+        for plan in input_entries.plans() {
+            plans.retain(|x| {
+                x.week_start_date != plan.week_start_date
+                    || x.variable_name != plan.variable_name
+                    || x.variable_value != plan.variable_value
+            });
+            plans.push(plan.clone());
+        }
+
+        // Different notes for the same date are joined, so no note
+        // is lost.
+        for day_note in input_entries.day_notes() {
+            match day_notes.iter_mut().find(|x| x.date == day_note.date) {
+                Some(existing) if existing.note != day_note.note => {
+                    existing.note = format!("{}\n{}", existing.note, day_note.note);
+                }
+                Some(_) => (),
+                None => day_notes.push(day_note.clone()),
+            }
+        }
+    }
+    let input_entry_count = entries.len();
+    let entries = merge_entries(entries);
 
-    let week_start_of_time = week_start_datetime.timestamp() as u64;
-    let week_end_of_time = week_end_datetime.timestamp() as u64;
-    let week_entries = storage.read_entries(week_start_of_time, week_end_of_time)?;
+    host_busy_periods.sort_by(|a, b| {
+        (a.utc_time_seconds, &a.reason, a.duration_seconds).cmp(&(
+            b.utc_time_seconds,
+            &b.reason,
+            b.duration_seconds,
+        ))
+    });
+    host_busy_periods.dedup();
+    window_color_periods.sort_by(|a, b| {
+        (a.utc_time_seconds, &a.executable, a.duration_seconds).cmp(&(
+            b.utc_time_seconds,
+            &b.executable,
+            b.duration_seconds,
+        ))
+    });
+    window_color_periods.dedup();
 
-    generate_csv_formated_lines(&week_entries, output_lines)
+    let mut storage =
+        Storage::open_as_read_write(output_file_path, DEFAULT_RECORD_INTERVAL_SECONDS)?;
+    storage.set_max_merged_entry_seconds(DEFAULT_MAX_MERGED_ENTRY_SECONDS);
+    storage.insert_entries(&entries);
+    storage.write_entries()?;
+    for day_note in &day_notes {
+        storage.write_day_note(day_note)?;
+    }
+    for plan in &plans {
+        storage.write_plan(plan)?;
+    }
+    storage.write_host_busy_periods(&host_busy_periods)?;
+    storage.write_window_color_periods(&window_color_periods)?;
+    storage.close()?;
+
+    println!(
+        "Merged {} entries ({} duplicates removed) from {} database files to {:?}.",
+        entries.len(),
+        input_entry_count - entries.len(),
+        input_files.len(),
+        output_file_path
+    );
+    Ok(())
 }
 
 fn main() -> Result<()> {
@@ -123,33 +598,72 @@ fn main() -> Result<()> {
 
     let now = SystemTime::now();
 
-    let mut lines = Vec::new();
-    dump_database(&args, &settings, &mut lines)?;
-
-    if !lines.is_empty() {
-        match args.output_file {
-            Some(file_path) => {
-                let f = std::fs::File::create(file_path)?;
-                let mut writer = std::io::BufWriter::new(f);
-                writer.write(HEADER_LINE)?;
-                writer.write(LINE_END)?;
-                for line in &lines {
-                    writer.write(line.as_bytes())?;
-                    writer.write(LINE_END)?;
-                }
-                writer.flush()?;
+    match &args.command {
+        DumpCommand::Raw {
+            range,
+            filter,
+            format,
+            follow,
+            follow_interval,
+        } => {
+            if *follow {
+                follow_entries(&settings, range, filter, format, *follow_interval)?;
+            } else if matches!(format.format, DumpFormat::Csv | DumpFormat::JsonLines) {
+                // Only the ODS format includes the summary table, so
+                // the other formats are streamed.
+                stream_entries(&settings, range, filter, format)?;
+            } else {
+                let entries = read_database(&settings, range, filter)?;
+                let entries_table = generate_entries_table(&entries);
+                let summary_table = generate_summary_table(&entries);
+                dump_tables(format, &[entries_table, summary_table])?;
             }
-            None => {
-                let mut stdout = std::io::stdout().lock();
-                stdout.write(HEADER_LINE)?;
-                stdout.write(LINE_END)?;
-                for line in &lines {
-                    stdout.write(line.as_bytes())?;
-                    stdout.write(LINE_END)?;
-                }
-                stdout.flush()?;
+        }
+        DumpCommand::Aggregate {
+            range,
+            filter,
+            format,
+            group_by,
+        } => {
+            let entries = read_database(&settings, range, filter)?;
+            let variables = group_by_variables(group_by);
+            dump_tables(format, &[generate_aggregate_table(&entries, &variables)])?;
+        }
+        DumpCommand::Versions {
+            range,
+            filter,
+            format,
+        } => {
+            let (entries, executable_versions) = read_database_versions(&settings, range, filter)?;
+            dump_tables(
+                format,
+                &[generate_versions_table(&entries, &executable_versions)],
+            )?;
+        }
+        DumpCommand::Archive {
+            range,
+            filter,
+            output_file,
+            older_than,
+            to,
+        } => match (older_than, output_file) {
+            (Some(older_than), _) => {
+                archive_older_entries(&settings, older_than, to.as_deref())?;
             }
+            (None, Some(output_file)) => {
+                let entries = read_database(&settings, range, filter)?;
+                archive_entries(&entries, output_file)?;
+            }
+            (None, None) => bail!("An output file or '--older-than' age is required."),
+        },
+        DumpCommand::Merge {
+            input_files,
+            output_file,
+        } => {
+            merge_databases(input_files, output_file)?;
         }
+        DumpCommand::Backup { to, gzip, year } => backup_database(&settings, to, *gzip, *year)?,
+        DumpCommand::Restore { from, year } => restore_database(&settings, from, *year)?,
     }
 
     let duration = now.elapsed()?.as_secs_f32();
@@ -157,3 +671,27 @@ fn main() -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_table_csv_empty() {
+        let format = FormatArguments {
+            format: DumpFormat::Csv,
+            delimiter: CsvDelimiter::Comma,
+            encoding: DumpEncoding::Utf8,
+            output_file: None,
+        };
+        let table = generate_entries_table(&Entries::builder().entries(Vec::new()).build());
+        assert!(table.rows.is_empty());
+
+        let mut output = Vec::new();
+        write_table(&mut output, &format, &[table]).unwrap();
+        let text = String::from_utf8(output).unwrap();
+        assert!(text.starts_with("utc_time_seconds,duration_seconds,status,executable,"));
+        assert!(text.ends_with(",display\r\n"));
+        assert_eq!(text.lines().count(), 1);
+    }
+}
@@ -0,0 +1,121 @@
+use crate::table::Table;
+use crate::table::TableValue;
+use anyhow::Result;
+use std::io::Write;
+
+// The OpenDocument format is described here:
+// https://docs.oasis-open.org/office/OpenDocument/v1.3/
+static MIMETYPE: &str = "application/vnd.oasis.opendocument.spreadsheet";
+
+static MANIFEST_XML: &str = concat!(
+    r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+    r#"<manifest:manifest xmlns:manifest="urn:oasis:names:tc:opendocument:xmlns:manifest:1.0" manifest:version="1.2">"#,
+    r#"<manifest:file-entry manifest:full-path="/" manifest:version="1.2" manifest:media-type="application/vnd.oasis.opendocument.spreadsheet"/>"#,
+    r#"<manifest:file-entry manifest:full-path="content.xml" manifest:media-type="text/xml"/>"#,
+    r#"</manifest:manifest>"#,
+);
+
+static CONTENT_XML_START: &str = concat!(
+    r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+    r#"<office:document-content"#,
+    r#" xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0""#,
+    r#" xmlns:table="urn:oasis:names:tc:opendocument:xmlns:table:1.0""#,
+    r#" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0""#,
+    r#" office:version="1.2">"#,
+    r#"<office:body><office:spreadsheet>"#,
+);
+
+static CONTENT_XML_END: &str = "</office:spreadsheet></office:body></office:document-content>";
+
+fn escape_xml_text(value: &str) -> String {
+    let mut text = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => text.push_str("&amp;"),
+            '<' => text.push_str("&lt;"),
+            '>' => text.push_str("&gt;"),
+            '"' => text.push_str("&quot;"),
+            '\'' => text.push_str("&apos;"),
+            // Control characters are not allowed in XML.
+            '\t' | '\n' | '\r' => text.push(c),
+            c if c.is_control() => (),
+            c => text.push(c),
+        }
+    }
+    text
+}
+
+fn generate_cell_xml(value: &TableValue) -> String {
+    match value {
+        TableValue::Text(value) => format!(
+            r#"<table:table-cell office:value-type="string"><text:p>{}</text:p></table:table-cell>"#,
+            escape_xml_text(value)
+        ),
+        TableValue::Integer(value) => format!(
+            r#"<table:table-cell office:value-type="float" office:value="{0}"><text:p>{0}</text:p></table:table-cell>"#,
+            value
+        ),
+        TableValue::Float(value) => format!(
+            r#"<table:table-cell office:value-type="float" office:value="{0}"><text:p>{0}</text:p></table:table-cell>"#,
+            value
+        ),
+    }
+}
+
+fn generate_content_xml(tables: &[Table]) -> String {
+    let mut content = CONTENT_XML_START.to_string();
+    for table in tables {
+        content.push_str(&format!(
+            r#"<table:table table:name="{}">"#,
+            escape_xml_text(&table.name)
+        ));
+
+        content.push_str("<table:table-row>");
+        for header in &table.headers {
+            content.push_str(&generate_cell_xml(&TableValue::Text(header.clone())));
+        }
+        content.push_str("</table:table-row>");
+
+        for row in &table.rows {
+            content.push_str("<table:table-row>");
+            for value in row {
+                content.push_str(&generate_cell_xml(value));
+            }
+            content.push_str("</table:table-row>");
+        }
+
+        content.push_str("</table:table>");
+    }
+    content.push_str(CONTENT_XML_END);
+    content
+}
+
+/// Write the tables as sheets of an OpenDocument Spreadsheet (.ods)
+/// file.
+pub fn write_ods_tables(writer: &mut impl Write, tables: &[Table]) -> Result<()> {
+    let mut buffer = std::io::Cursor::new(Vec::new());
+    {
+        let mut zip = zip::ZipWriter::new(&mut buffer);
+
+        // The 'mimetype' file must be the first file in the archive,
+        // and must not be compressed.
+        let stored =
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        zip.start_file("mimetype", stored)?;
+        zip.write_all(MIMETYPE.as_bytes())?;
+
+        let deflated =
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        zip.start_file("META-INF/manifest.xml", deflated)?;
+        zip.write_all(MANIFEST_XML.as_bytes())?;
+
+        zip.start_file("content.xml", deflated)?;
+        zip.write_all(generate_content_xml(tables).as_bytes())?;
+
+        zip.finish()?;
+    }
+
+    writer.write_all(buffer.get_ref())?;
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,143 @@
+use crate::table::Table;
+use crate::table::TableValue;
+use anyhow::{bail, Result};
+use parquet::basic::Compression;
+use parquet::data_type::ByteArray;
+use parquet::data_type::ByteArrayType;
+use parquet::data_type::DoubleType;
+use parquet::data_type::Int64Type;
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use std::io::Write;
+use std::sync::Arc;
+
+// Large tables are split into row groups, so readers do not need to
+// hold the full table in memory.
+const ROW_GROUP_MAX_ROW_COUNT: usize = 100_000;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum ColumnType {
+    Text,
+    Integer,
+    Float,
+}
+
+fn table_value_column_type(value: &TableValue) -> ColumnType {
+    match value {
+        TableValue::Text(_) => ColumnType::Text,
+        TableValue::Integer(_) => ColumnType::Integer,
+        TableValue::Float(_) => ColumnType::Float,
+    }
+}
+
+/// The type of each column, taken from the first row. All rows must
+/// have the same types.
+fn table_column_types(table: &Table) -> Result<Vec<ColumnType>> {
+    let column_types: Vec<ColumnType> = match table.rows.first() {
+        Some(row) => row.iter().map(table_value_column_type).collect(),
+        None => vec![ColumnType::Text; table.headers.len()],
+    };
+    for row in &table.rows {
+        let row_types = row.iter().map(table_value_column_type);
+        if row.len() != column_types.len() || !row_types.eq(column_types.iter().copied()) {
+            bail!(
+                "Table {:?} has rows with different column types.",
+                table.name
+            );
+        }
+    }
+    Ok(column_types)
+}
+
+fn table_message_type(table: &Table, column_types: &[ColumnType]) -> String {
+    let fields: Vec<String> = table
+        .headers
+        .iter()
+        .zip(column_types)
+        .map(|(header, column_type)| match column_type {
+            ColumnType::Text => format!("REQUIRED BYTE_ARRAY {} (UTF8);", header),
+            ColumnType::Integer => format!("REQUIRED INT64 {};", header),
+            ColumnType::Float => format!("REQUIRED DOUBLE {};", header),
+        })
+        .collect();
+    format!(
+        "message {} {{ {} }}",
+        table.name.to_lowercase(),
+        fields.join(" ")
+    )
+}
+
+/// Write the table as an Apache Parquet file.
+///
+/// Parquet files contain a single table, with a typed column for
+/// each table header.
+pub fn write_parquet_table(writer: &mut impl Write, table: &Table) -> Result<()> {
+    let column_types = table_column_types(table)?;
+    let schema = Arc::new(parse_message_type(&table_message_type(
+        table,
+        &column_types,
+    ))?);
+    let properties = Arc::new(
+        WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build(),
+    );
+
+    // The Parquet writer needs to own the output, so the file is
+    // written into memory, then copied to the writer.
+    let mut buffer = Vec::new();
+    let mut file_writer = SerializedFileWriter::new(&mut buffer, schema, properties)?;
+    for rows in table.rows.chunks(ROW_GROUP_MAX_ROW_COUNT) {
+        let mut row_group_writer = file_writer.next_row_group()?;
+        for (column_index, column_type) in column_types.iter().enumerate() {
+            let mut column_writer = match row_group_writer.next_column()? {
+                Some(value) => value,
+                None => bail!("Parquet schema has fewer columns than the table."),
+            };
+            let values = rows.iter().map(|row| &row[column_index]);
+            match column_type {
+                ColumnType::Text => {
+                    let values: Vec<ByteArray> = values
+                        .map(|x| match x {
+                            TableValue::Text(value) => ByteArray::from(value.as_str()),
+                            _ => ByteArray::from(""),
+                        })
+                        .collect();
+                    column_writer
+                        .typed::<ByteArrayType>()
+                        .write_batch(&values, None, None)?;
+                }
+                ColumnType::Integer => {
+                    let values: Vec<i64> = values
+                        .map(|x| match x {
+                            TableValue::Integer(value) => *value,
+                            _ => 0,
+                        })
+                        .collect();
+                    column_writer
+                        .typed::<Int64Type>()
+                        .write_batch(&values, None, None)?;
+                }
+                ColumnType::Float => {
+                    let values: Vec<f64> = values
+                        .map(|x| match x {
+                            TableValue::Float(value) => *value,
+                            _ => 0.0,
+                        })
+                        .collect();
+                    column_writer
+                        .typed::<DoubleType>()
+                        .write_batch(&values, None, None)?;
+                }
+            }
+            column_writer.close()?;
+        }
+        row_group_writer.close()?;
+    }
+    file_writer.close()?;
+
+    writer.write_all(&buffer)?;
+    writer.flush()?;
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use clap::Parser;
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use config::ConfigError;
 use serde_derive::Deserialize;
 use timetracker_core::settings::new_core_settings;
@@ -7,29 +7,298 @@ use timetracker_core::settings::validate_core_settings;
 use timetracker_core::settings::CoreSettings;
 use timetracker_core::settings::PrintSettings;
 
-#[derive(Parser, Debug)]
-#[clap(author = "David Cattermole, Copyright 2023-2024", version, about)]
-pub struct CommandArguments {
-    /// Return the last week's results, shortcut for
-    /// '--relative-week=-1'.
-    #[clap(long, value_parser, default_value_t = false)]
+/// The file formats that can be dumped.
+#[derive(Debug, Copy, Clone, ValueEnum)]
+pub enum DumpFormat {
+    /// Comma-separated values, with a line for each entry.
+    Csv,
+
+    /// OpenDocument Spreadsheet, with sheets for the entries and a
+    /// summary.
+    Ods,
+
+    /// Apache Parquet, with the entries as typed columns. Requires
+    /// the 'parquet' feature when building.
+    Parquet,
+
+    /// JSON Lines, with a JSON object (keyed by the column names) on
+    /// a line for each row.
+    JsonLines,
+}
+
+/// The character separating the fields of CSV files.
+#[derive(Debug, Copy, Clone, ValueEnum)]
+pub enum CsvDelimiter {
+    /// ',' (the CSV standard).
+    Comma,
+
+    /// A tab character, for tab-separated values.
+    Tab,
+
+    /// ';' as used by spreadsheet applications in locales that use
+    /// ',' as the decimal separator.
+    Semicolon,
+}
+
+impl CsvDelimiter {
+    pub fn as_byte(self) -> u8 {
+        match self {
+            CsvDelimiter::Comma => b',',
+            CsvDelimiter::Tab => b'\t',
+            CsvDelimiter::Semicolon => b';',
+        }
+    }
+}
+
+/// The character encoding of text file formats (such as CSV).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum DumpEncoding {
+    /// UTF-8, without a byte order mark.
+    Utf8,
+
+    /// UTF-8 starting with a byte order mark, so Microsoft Excel
+    /// detects the encoding of non-ASCII text (such as file paths).
+    Utf8Bom,
+
+    /// ISO-8859-1 (Latin-1), for older tools. Characters that cannot
+    /// be encoded are replaced with '?'.
+    Latin1,
+}
+
+/// The range of dates to dump.
+#[derive(Args, Debug)]
+pub struct RangeArguments {
+    /// The first date to dump, in "YYYY-MM-DD" format, instead of a
+    /// week. Used with '--end' to dump an arbitrary date range.
+    #[clap(long, value_parser, conflicts_with_all = &["week", "last-week", "month"])]
+    pub start: Option<String>,
+
+    /// The last date to dump (inclusive), in "YYYY-MM-DD" format.
+    /// Defaults to today.
+    #[clap(long, value_parser, requires = "start")]
+    pub end: Option<String>,
+
+    /// Relative week number. '0' is the current week (the default),
+    /// '-1' is the previous week, etc.
+    #[clap(
+        short = 'w',
+        long,
+        value_parser,
+        allow_hyphen_values = true,
+        conflicts_with_all = &["last-week", "month"]
+    )]
+    pub week: Option<i32>,
+
+    /// Dump the last week, shortcut for '--week=-1'.
+    #[clap(long, value_parser, default_value_t = false, conflicts_with = "month")]
     pub last_week: bool,
 
-    /// Relative week number. '0' is the current week, '-1' is the
-    /// previous week, etc.
-    #[clap(short = 'w', long, value_parser, default_value_t = 0)]
-    pub relative_week: i32,
+    /// Relative month number. '0' is the current month, '-1' is the
+    /// previous month, etc.
+    #[clap(long, value_parser, allow_hyphen_values = true)]
+    pub month: Option<i32>,
+}
 
-    /// Output file path.
+/// Only dump the matching entries; the same filters as
+/// 'timetracker-print'.
+#[derive(Args, Debug)]
+pub struct FilterArguments {
+    /// Only dump the entries of an executable. May be given more
+    /// than once, to dump the entries of any of the executables.
+    #[clap(long = "executable", value_parser)]
+    pub executables: Vec<String>,
+
+    /// Only dump the entries with a variable value, as "NAME=VALUE",
+    /// such as "PROJECT=rocket" or "hostname=workstation". May be
+    /// given more than once, to match all the values.
+    #[clap(long = "variable", value_parser)]
+    pub variables: Vec<String>,
+}
+
+/// The format and destination of a dumped table.
+#[derive(Args, Debug)]
+pub struct FormatArguments {
+    /// The format of the dumped data.
+    #[clap(long, value_enum, default_value_t = DumpFormat::Csv)]
+    pub format: DumpFormat,
+
+    /// The field delimiter used with the CSV format.
+    #[clap(long, value_enum, default_value_t = CsvDelimiter::Comma)]
+    pub delimiter: CsvDelimiter,
+
+    /// The character encoding used with the CSV format. The other
+    /// formats are always UTF-8.
+    #[clap(long, value_enum, default_value_t = DumpEncoding::Utf8)]
+    pub encoding: DumpEncoding,
+
+    /// Output file path. Defaults to the standard output.
     #[clap(short = 'o', long, value_parser)]
     pub output_file: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum DumpCommand {
+    /// Dump the raw entries, with a row for each entry.
+    Raw {
+        #[clap(flatten)]
+        range: RangeArguments,
+
+        #[clap(flatten)]
+        filter: FilterArguments,
+
+        #[clap(flatten)]
+        format: FormatArguments,
+
+        /// After dumping the entries, keep running and dump the new
+        /// entries as the recorder writes them (until interrupted).
+        /// Only the CSV and JSON Lines formats can be followed.
+        #[clap(long, value_parser, default_value_t = false)]
+        follow: bool,
+
+        /// The number of seconds between checking for new entries,
+        /// when following.
+        #[clap(long, value_parser, default_value_t = 5, requires = "follow")]
+        follow_interval: u64,
+    },
+
+    /// Dump the total active duration of each executable, or of the
+    /// values of the '--group-by' variables.
+    Aggregate {
+        #[clap(flatten)]
+        range: RangeArguments,
+
+        #[clap(flatten)]
+        filter: FilterArguments,
+
+        #[clap(flatten)]
+        format: FormatArguments,
+
+        /// Group the entries by a variable; "executable",
+        /// "window_title", "working_directory", "document_path",
+        /// "hostname", "username", "display" or an environment
+        /// variable name.
+        /// May be given more than once, to group by the combination
+        /// of the values.
+        #[clap(long, value_parser)]
+        group_by: Vec<String>,
+    },
+
+    /// Dump the total active duration of each version of each
+    /// executable, such as the hours spent in two releases of the
+    /// same software. Requires 'recorder.record_executable_versions'.
+    Versions {
+        #[clap(flatten)]
+        range: RangeArguments,
+
+        #[clap(flatten)]
+        filter: FilterArguments,
+
+        #[clap(flatten)]
+        format: FormatArguments,
+    },
+
+    /// Copy the entries, day notes and recorded periods to a new
+    /// database file, which can be opened with '--database-dir'
+    /// and '--database-file-name'.
+    ///
+    /// With '--older-than', the old entries and recorded periods are
+    /// moved (rather than copied) into a per-year archive database
+    /// file, which is read with the database file when a range
+    /// covers the year.
+    Archive {
+        #[clap(flatten)]
+        range: RangeArguments,
+
+        #[clap(flatten)]
+        filter: FilterArguments,
+
+        /// The database file to create.
+        #[clap(
+            short = 'o',
+            long,
+            value_parser,
+            required_unless_present = "older-than",
+            conflicts_with = "older-than"
+        )]
+        output_file: Option<String>,
+
+        /// Move the entries older than the age, such as "6months",
+        /// "8weeks", "90days" or "1year", into the archive database
+        /// files.
+        #[clap(
+            long,
+            value_parser,
+            conflicts_with_all = &["start", "week", "last-week", "month", "executables", "variables"]
+        )]
+        older_than: Option<String>,
+
+        /// The directory of the archive database files. Defaults to
+        /// 'core.archive_dir'.
+        #[clap(long, value_parser, requires = "older-than")]
+        to: Option<String>,
+    },
+
+    /// Merge the entries, day notes and recorded periods of two or
+    /// more database files (such as copies from different machines)
+    /// into a new database file.
+    Merge {
+        /// The database files to merge.
+        #[clap(value_parser, required = true, min_values = 2)]
+        input_files: Vec<String>,
+
+        /// The database file to create.
+        #[clap(short = 'o', long, value_parser)]
+        output_file: String,
+    },
+
+    /// Back up the database file, using SQLite's online backup, so
+    /// the backup is consistent while the recorder is writing. The
+    /// backup is checked for corruption before it is written.
+    Backup {
+        /// The backup file to create.
+        #[clap(long, value_parser)]
+        to: String,
+
+        /// Compress the backup file with gzip.
+        #[clap(long, value_parser, default_value_t = false)]
+        gzip: bool,
+
+        /// The year of the database file to back up, when
+        /// 'core.database_per_year' is enabled. Defaults to the
+        /// current year.
+        #[clap(long, value_parser)]
+        year: Option<i32>,
+    },
+
+    /// Replace the database file with a backup (compressed or not),
+    /// written by 'backup'. The backup is checked for corruption
+    /// before the database file is changed. The entries written
+    /// since the backup are lost.
+    Restore {
+        /// The backup file to restore.
+        #[clap(long, value_parser)]
+        from: String,
+
+        /// The year of the database file to restore, when
+        /// 'core.database_per_year' is enabled. Defaults to the
+        /// current year.
+        #[clap(long, value_parser)]
+        year: Option<i32>,
+    },
+}
+
+#[derive(Parser, Debug)]
+#[clap(author = "David Cattermole, Copyright 2023-2024", version, about)]
+pub struct CommandArguments {
+    #[clap(subcommand)]
+    pub command: DumpCommand,
 
     /// Override the directory to search for the database file.
-    #[clap(long, value_parser)]
+    #[clap(long, value_parser, global = true)]
     pub database_dir: Option<String>,
 
     /// Override the name of the database file to open.
-    #[clap(long, value_parser)]
+    #[clap(long, value_parser, global = true)]
     pub database_file_name: Option<String>,
 }
 
@@ -0,0 +1,305 @@
+use std::collections::BTreeMap;
+use timetracker_core::entries::Entry;
+use timetracker_core::entries::EntryStatus;
+use timetracker_core::entries::ExecutableVersion;
+use timetracker_core::format_short_executable_name;
+use timetracker_core::storage::Entries;
+use timetracker_print_lib::variable::combine_variable_values;
+use timetracker_print_lib::variable::Variable;
+
+/// A single value in a table.
+#[derive(Debug, Clone)]
+pub enum TableValue {
+    Text(String),
+    Integer(i64),
+    Float(f64),
+}
+
+/// A named table of values, used as the common model for all the
+/// tabular export formats.
+#[derive(Debug, Clone)]
+pub struct Table {
+    pub name: String,
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<TableValue>>,
+}
+
+fn option_string_to_value(value: &Option<String>) -> TableValue {
+    match value {
+        Some(value) => TableValue::Text(value.to_string()),
+        None => TableValue::Text("".to_string()),
+    }
+}
+
+/// Create a table with a row for each (raw) entry.
+pub fn generate_entries_table(entries: &Entries) -> Table {
+    let headers = [
+        "utc_time_seconds",
+        "duration_seconds",
+        "status",
+        "executable",
+        "var1_name",
+        "var1_value",
+        "var2_name",
+        "var2_value",
+        "var3_name",
+        "var3_value",
+        "var4_name",
+        "var4_value",
+        "var5_name",
+        "var5_value",
+        "window_title",
+        "working_directory",
+        "document_path",
+        "hostname",
+        "username",
+        "display",
+    ];
+
+    let mut rows = Vec::new();
+    for entry in entries.all_entries() {
+        let row = vec![
+            TableValue::Integer(entry.utc_time_seconds as i64),
+            TableValue::Integer(entry.duration_seconds as i64),
+            TableValue::Text(format!("{:?}", entry.status)),
+            option_string_to_value(&entry.vars.executable),
+            option_string_to_value(&entry.vars.var1_name),
+            option_string_to_value(&entry.vars.var1_value),
+            option_string_to_value(&entry.vars.var2_name),
+            option_string_to_value(&entry.vars.var2_value),
+            option_string_to_value(&entry.vars.var3_name),
+            option_string_to_value(&entry.vars.var3_value),
+            option_string_to_value(&entry.vars.var4_name),
+            option_string_to_value(&entry.vars.var4_value),
+            option_string_to_value(&entry.vars.var5_name),
+            option_string_to_value(&entry.vars.var5_value),
+            option_string_to_value(&entry.vars.window_title),
+            option_string_to_value(&entry.vars.working_directory),
+            option_string_to_value(&entry.vars.document_path),
+            option_string_to_value(&entry.vars.hostname),
+            option_string_to_value(&entry.vars.username),
+            option_string_to_value(&entry.vars.display),
+        ];
+        rows.push(row);
+    }
+
+    Table {
+        name: "Entries".to_string(),
+        headers: headers.iter().map(|x| x.to_string()).collect(),
+        rows,
+    }
+}
+
+/// The name of the table column of a variable.
+pub fn variable_column_name(variable: &Variable) -> String {
+    match variable {
+        Variable::Executable => "executable".to_string(),
+        Variable::WindowTitle => "window_title".to_string(),
+        Variable::WorkingDirectory => "working_directory".to_string(),
+        Variable::DocumentPath => "document_path".to_string(),
+        Variable::Hostname => "hostname".to_string(),
+        Variable::Username => "username".to_string(),
+        Variable::Display => "display".to_string(),
+        Variable::VariableName(name) => name.to_string(),
+    }
+}
+
+/// Create a table with the total active duration of each
+/// combination of the values of the variables, with a column for
+/// each variable.
+pub fn generate_aggregate_table(entries: &Entries, variables: &[Variable]) -> Table {
+    let mut headers: Vec<String> = variables.iter().map(variable_column_name).collect();
+    headers.push("active_seconds".to_string());
+    headers.push("active_hours".to_string());
+
+    let mut duration_map = BTreeMap::<Vec<String>, i64>::new();
+    for entry in entries.all_entries() {
+        if entry.status != EntryStatus::Active {
+            continue;
+        }
+        let values: Vec<String> = variables
+            .iter()
+            .map(|variable| combine_variable_values(entry, std::slice::from_ref(variable)))
+            .collect();
+        *duration_map.entry(values).or_insert(0) += entry.duration_seconds as i64;
+    }
+
+    let mut rows = Vec::new();
+    for (values, seconds) in duration_map {
+        let mut row: Vec<TableValue> = values.into_iter().map(TableValue::Text).collect();
+        row.push(TableValue::Integer(seconds));
+        row.push(TableValue::Float((seconds as f64) / (60.0 * 60.0)));
+        rows.push(row);
+    }
+
+    Table {
+        name: "Summary".to_string(),
+        headers,
+        rows,
+    }
+}
+
+/// Create a table with the total active duration of each
+/// executable.
+pub fn generate_summary_table(entries: &Entries) -> Table {
+    generate_aggregate_table(entries, &[Variable::Executable])
+}
+
+/// Find the version of the executable of the entry; the version of
+/// the same executable seen for the longest time during the entry.
+fn find_entry_executable_version<'a>(
+    entry: &Entry,
+    executable_versions: &'a [ExecutableVersion],
+) -> Option<&'a ExecutableVersion> {
+    let executable = format_short_executable_name(entry.vars.executable.as_deref()?);
+    let entry_start = entry.utc_time_seconds;
+    let entry_end = entry.utc_time_seconds + entry.duration_seconds;
+    executable_versions
+        .iter()
+        .filter(|x| x.executable == executable)
+        .filter(|x| x.first_utc_time_seconds <= entry_end && entry_start <= x.last_utc_time_seconds)
+        .max_by_key(|x| {
+            let overlap_start = x.first_utc_time_seconds.max(entry_start);
+            let overlap_end = x.last_utc_time_seconds.min(entry_end);
+            (
+                overlap_end.saturating_sub(overlap_start),
+                x.last_utc_time_seconds,
+            )
+        })
+}
+
+/// Create a table with the total active duration of each version of
+/// each executable, such as to compare the time spent in two
+/// releases of the same software.
+///
+/// Entries are matched to the executable versions seen (by the
+/// recorder) during the entry, by the short executable name; the
+/// version of entries without a matching executable version is
+/// empty.
+pub fn generate_versions_table(
+    entries: &Entries,
+    executable_versions: &[ExecutableVersion],
+) -> Table {
+    let headers = [
+        "executable",
+        "version",
+        "executable_path",
+        "active_seconds",
+        "active_hours",
+    ];
+
+    let mut duration_map = BTreeMap::<(String, String, String), i64>::new();
+    for entry in entries.all_entries() {
+        if entry.status != EntryStatus::Active {
+            continue;
+        }
+        let key = match find_entry_executable_version(entry, executable_versions) {
+            Some(executable_version) => (
+                executable_version.executable.clone(),
+                executable_version.version.clone().unwrap_or_default(),
+                executable_version.executable_path.clone(),
+            ),
+            None => (
+                format_short_executable_name(entry.vars.executable.as_deref().unwrap_or(""))
+                    .to_string(),
+                "".to_string(),
+                "".to_string(),
+            ),
+        };
+        *duration_map.entry(key).or_insert(0) += entry.duration_seconds as i64;
+    }
+
+    let mut rows = Vec::new();
+    for ((executable, version, executable_path), seconds) in duration_map {
+        rows.push(vec![
+            TableValue::Text(executable),
+            TableValue::Text(version),
+            TableValue::Text(executable_path),
+            TableValue::Integer(seconds),
+            TableValue::Float((seconds as f64) / (60.0 * 60.0)),
+        ]);
+    }
+
+    Table {
+        name: "Versions".to_string(),
+        headers: headers.iter().map(|x| x.to_string()).collect(),
+        rows,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use timetracker_core::entries::EntryVariablesList;
+
+    #[test]
+    fn test_generate_versions_table() {
+        let new_entry = |utc_time_seconds: u64, executable: &str| {
+            let mut vars = EntryVariablesList::empty();
+            vars.executable = Some(executable.to_string());
+            Entry::new(utc_time_seconds, 100, EntryStatus::Active, vars)
+        };
+        let mut version_2024 = ExecutableVersion::new(
+            "maya.bin".to_string(),
+            "/usr/autodesk/maya2024/bin/maya.bin".to_string(),
+            1,
+            Some("2024".to_string()),
+            1000,
+        );
+        version_2024.last_utc_time_seconds = 1990;
+        let mut version_2025 = ExecutableVersion::new(
+            "maya.bin".to_string(),
+            "/usr/autodesk/maya2025/bin/maya.bin".to_string(),
+            2,
+            Some("2025".to_string()),
+            1980,
+        );
+        version_2025.last_utc_time_seconds = 3000;
+        let entries = Entries::builder()
+            .entries(vec![
+                new_entry(1000, "/usr/autodesk/maya2024/bin/maya.bin"),
+                // Overlaps both versions, mostly the 2025 version.
+                new_entry(1950, "/usr/autodesk/maya2025/bin/maya.bin"),
+                new_entry(2500, "maya.bin"),
+                // Not seen while the versions were recorded.
+                new_entry(5000, "maya.bin"),
+                new_entry(1000, "firefox"),
+            ])
+            .build();
+
+        let table = generate_versions_table(&entries, &[version_2024, version_2025]);
+        let rows: Vec<Vec<String>> = table
+            .rows
+            .iter()
+            .map(|row| {
+                row[..4]
+                    .iter()
+                    .map(|value| match value {
+                        TableValue::Text(value) => value.to_string(),
+                        TableValue::Integer(value) => value.to_string(),
+                        TableValue::Float(value) => value.to_string(),
+                    })
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                vec!["firefox", "", "", "100"],
+                vec!["maya.bin", "", "", "100"],
+                vec![
+                    "maya.bin",
+                    "2024",
+                    "/usr/autodesk/maya2024/bin/maya.bin",
+                    "100"
+                ],
+                vec![
+                    "maya.bin",
+                    "2025",
+                    "/usr/autodesk/maya2025/bin/maya.bin",
+                    "200"
+                ],
+            ]
+        );
+    }
+}
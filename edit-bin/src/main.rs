@@ -0,0 +1,382 @@
+use crate::settings::CommandArguments;
+use crate::settings::CommandModes;
+use crate::settings::EditAppSettings;
+use anyhow::{anyhow, bail, Result};
+use chrono::Datelike;
+use clap::Parser;
+use log::debug;
+use std::path::PathBuf;
+use timetracker_core::entries::DayNote;
+use timetracker_core::entries::Entry;
+use timetracker_core::entries::EntryStatus;
+use timetracker_core::entries::EntryVariablesList;
+use timetracker_core::entries::WeekPlan;
+use timetracker_core::filesystem::get_database_file_path;
+use timetracker_core::filesystem::get_database_file_path_for_time;
+use timetracker_core::filesystem::get_database_file_paths_for_range;
+use timetracker_core::filesystem::get_year_database_file_path;
+use timetracker_core::format::format_duration;
+use timetracker_core::format::parse_duration;
+use timetracker_core::format::parse_local_datetime;
+use timetracker_core::format::DurationFormat;
+use timetracker_core::multi_storage::MultiStorage;
+use timetracker_core::settings::DEFAULT_RECORD_INTERVAL_SECONDS;
+use timetracker_core::storage::Storage;
+use timetracker_core::storage::ENVIRONMENT_VARIABLE_NAMES_MAX_COUNT;
+use timetracker_print_lib::print::get_relative_week_start_end;
+
+mod settings;
+
+fn find_database_file_path(settings: &EditAppSettings) -> Result<PathBuf> {
+    get_database_file_path(
+        &settings.core.database_dir,
+        &settings.core.database_file_name,
+    )
+    .ok_or_else(|| {
+        anyhow!(
+            "Database file {:?} not found in {:?}",
+            &settings.core.database_file_name,
+            &settings.core.database_dir
+        )
+    })
+}
+
+fn edit_day_note(
+    settings: &EditAppSettings,
+    date: &str,
+    text: &Option<String>,
+    clear: bool,
+) -> Result<()> {
+    let date = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|err| anyhow!("Invalid date {:?}, expected \"YYYY-MM-DD\"; {}", date, err))?;
+
+    // Notes are stored with the entries of the same year.
+    let mut database_file_path = find_database_file_path(settings)?;
+    if settings.core.database_per_year {
+        database_file_path = get_year_database_file_path(&database_file_path, date.year());
+    }
+    let mut storage =
+        Storage::open_as_read_write(&database_file_path, DEFAULT_RECORD_INTERVAL_SECONDS)?;
+
+    match (text, clear) {
+        (Some(_), true) => bail!("A note text cannot be given with --clear."),
+        (Some(text), false) => {
+            let day_note = DayNote::new(date, text.clone());
+            storage.write_day_note(&day_note)?;
+            println!("Note written for {}.", date);
+        }
+        (None, true) => match storage.delete_day_note(date)? {
+            true => println!("Note removed for {}.", date),
+            false => println!("No note found for {}.", date),
+        },
+        (None, false) => {
+            let day_notes = storage.read_day_notes(date, date)?;
+            match day_notes.first() {
+                Some(day_note) => println!("{}: {}", day_note.date, day_note.note),
+                None => println!("No note found for {}.", date),
+            }
+        }
+    }
+
+    Ok(storage.close()?)
+}
+
+/// Parse the "VALUE=DURATION" planned durations.
+fn parse_planned_durations(durations: &[String]) -> Result<Vec<(String, u64)>> {
+    let mut planned_durations = Vec::new();
+    for value_duration in durations {
+        let (value, duration_text) = match value_duration.split_once('=') {
+            Some((value, duration_text)) if !value.is_empty() => (value, duration_text),
+            _ => bail!(
+                "Invalid planned duration {:?}, expected \"VALUE=DURATION\".",
+                value_duration
+            ),
+        };
+        let duration = parse_duration(duration_text).ok_or_else(|| {
+            anyhow!(
+                "Invalid duration {:?}, expected a duration such as \"20h\" or \"7h30m\".",
+                duration_text
+            )
+        })?;
+        planned_durations.push((value.to_string(), duration.num_seconds() as u64));
+    }
+    Ok(planned_durations)
+}
+
+fn edit_week_plan(
+    settings: &EditAppSettings,
+    relative_week: i32,
+    variable_name: &str,
+    durations: &[String],
+    clear: bool,
+) -> Result<()> {
+    let (week_start_datetime, _week_end_datetime) =
+        get_relative_week_start_end(relative_week, settings.core.first_day_of_week)?;
+    let week_start_date = week_start_datetime.date_naive();
+    let planned_durations = parse_planned_durations(durations)?;
+
+    // Plans are stored with the entries of the year the week starts
+    // in.
+    let mut database_file_path = find_database_file_path(settings)?;
+    if settings.core.database_per_year {
+        database_file_path =
+            get_year_database_file_path(&database_file_path, week_start_date.year());
+    }
+    let mut storage =
+        Storage::open_as_read_write(&database_file_path, DEFAULT_RECORD_INTERVAL_SECONDS)?;
+
+    match (planned_durations.is_empty(), clear) {
+        (false, true) => bail!("Planned durations cannot be given with --clear."),
+        (false, false) => {
+            for (value, planned_seconds) in planned_durations {
+                let plan = WeekPlan::new(
+                    week_start_date,
+                    variable_name.to_string(),
+                    value,
+                    planned_seconds,
+                );
+                storage.write_plan(&plan)?;
+            }
+            println!("Plan written for the week of {}.", week_start_date);
+        }
+        (true, true) => match storage.delete_plans(week_start_date)? {
+            0 => println!("No plan found for the week of {}.", week_start_date),
+            _ => println!("Plan removed for the week of {}.", week_start_date),
+        },
+        (true, false) => {
+            let plans = storage.read_plans(week_start_date, week_start_date)?;
+            if plans.is_empty() {
+                println!("No plan found for the week of {}.", week_start_date);
+            }
+            for plan in plans {
+                println!(
+                    "{}: {}={} {}",
+                    plan.week_start_date,
+                    plan.variable_name,
+                    plan.variable_value,
+                    format_duration(
+                        chrono::Duration::seconds(plan.planned_seconds as i64),
+                        DurationFormat::HoursMinutes
+                    )
+                );
+            }
+        }
+    }
+
+    Ok(storage.close()?)
+}
+
+fn parse_datetime(text: &str) -> Result<chrono::DateTime<chrono::Local>> {
+    parse_local_datetime(text).ok_or_else(|| {
+        anyhow!(
+            "Could not parse {:?}, expected \"YYYY-MM-DD HH:MM\" or \"HH:MM\".",
+            text
+        )
+    })
+}
+
+fn parse_datetime_range(
+    from: &str,
+    to: &str,
+) -> Result<(
+    chrono::DateTime<chrono::Local>,
+    chrono::DateTime<chrono::Local>,
+)> {
+    let start_datetime = parse_datetime(from)?;
+    let end_datetime = parse_datetime(to)?;
+    if end_datetime <= start_datetime {
+        bail!(
+            "The end time {:?} must be after the start time {:?}.",
+            to,
+            from
+        );
+    }
+    Ok((start_datetime, end_datetime))
+}
+
+/// Parse the "NAME=VALUE" values, such as the variables of an entry.
+fn parse_name_values(name_values: &[String]) -> Result<Vec<(String, String)>> {
+    name_values
+        .iter()
+        .map(|name_value| match name_value.split_once('=') {
+            Some((name, value)) if !name.is_empty() => Ok((name.to_string(), value.to_string())),
+            _ => bail!("Invalid value {:?}, expected \"NAME=VALUE\".", name_value),
+        })
+        .collect()
+}
+
+/// Open the database files of the entries between the start and end
+/// times (including the per-year and archived database files).
+///
+/// The changed entries are saved, so the edit can be undone with
+/// 'timetracker-db audit --undo last'.
+fn open_multi_storage(
+    settings: &EditAppSettings,
+    start_utc_time_seconds: u64,
+    end_utc_time_seconds: u64,
+) -> Result<MultiStorage> {
+    let database_file_paths = get_database_file_paths_for_range(
+        &find_database_file_path(settings)?,
+        settings.core.database_per_year,
+        &settings.core.archive_dir,
+        start_utc_time_seconds,
+        end_utc_time_seconds,
+    );
+    let mut storage =
+        MultiStorage::open_as_read_write(&database_file_paths, DEFAULT_RECORD_INTERVAL_SECONDS)?;
+    storage.set_record_undo(true);
+    Ok(storage)
+}
+
+fn add_entry(
+    settings: &EditAppSettings,
+    from: &str,
+    to: &str,
+    executable: &str,
+    variables: &[String],
+) -> Result<()> {
+    let (start_datetime, end_datetime) = parse_datetime_range(from, to)?;
+    let start_utc_time_seconds = start_datetime.timestamp() as u64;
+    let end_utc_time_seconds = end_datetime.timestamp() as u64;
+
+    let mut vars = EntryVariablesList::empty();
+    vars.executable = Some(executable.to_string());
+    for (name, value) in parse_name_values(variables)? {
+        if !vars.set_variable_value(&name, &value) {
+            bail!(
+                "Too many variables, at most {} can be given.",
+                ENVIRONMENT_VARIABLE_NAMES_MAX_COUNT
+            );
+        }
+    }
+    let entry = Entry::new(
+        start_utc_time_seconds,
+        end_utc_time_seconds - start_utc_time_seconds,
+        EntryStatus::Active,
+        vars,
+    )
+    .with_utc_offset_seconds(Some(start_datetime.offset().local_minus_utc()));
+
+    // Entries are stored in the database of the year they start in.
+    let database_file_path = get_database_file_path_for_time(
+        &find_database_file_path(settings)?,
+        settings.core.database_per_year,
+        start_utc_time_seconds,
+    );
+    let mut storage =
+        Storage::open_as_read_write(&database_file_path, DEFAULT_RECORD_INTERVAL_SECONDS)?;
+    storage.set_record_undo(true);
+    let count = storage.insert_manual_entry(&entry)?;
+    storage.close()?;
+
+    println!(
+        "Added {:?} from {} to {} (adjusting {} idle entries).",
+        executable,
+        start_datetime.format("%Y-%m-%d %H:%M"),
+        end_datetime.format("%Y-%m-%d %H:%M"),
+        count
+    );
+    Ok(())
+}
+
+fn delete_entries(settings: &EditAppSettings, from: &str, to: &str) -> Result<()> {
+    let (start_datetime, end_datetime) = parse_datetime_range(from, to)?;
+    let start_utc_time_seconds = start_datetime.timestamp() as u64;
+    let end_utc_time_seconds = end_datetime.timestamp() as u64;
+
+    let mut storage = open_multi_storage(settings, start_utc_time_seconds, end_utc_time_seconds)?;
+    let count = storage.delete_entries(start_utc_time_seconds, end_utc_time_seconds)?;
+    storage.close()?;
+
+    println!(
+        "Deleted {} entries from {} to {}.",
+        count,
+        start_datetime.format("%Y-%m-%d %H:%M"),
+        end_datetime.format("%Y-%m-%d %H:%M")
+    );
+    Ok(())
+}
+
+fn retag_entries(
+    settings: &EditAppSettings,
+    where_values: &[String],
+    set_values: &[String],
+    from: &Option<String>,
+    to: &Option<String>,
+) -> Result<()> {
+    // Without a time range, all the entries (until now) are changed.
+    let start_utc_time_seconds = match from {
+        Some(from) => parse_datetime(from)?.timestamp() as u64,
+        None => 0,
+    };
+    let end_utc_time_seconds = match to {
+        Some(to) => parse_datetime(to)?.timestamp() as u64,
+        None => chrono::Utc::now().timestamp() as u64,
+    };
+    if end_utc_time_seconds <= start_utc_time_seconds {
+        bail!("The end time must be after the start time.");
+    }
+
+    // An empty value is no value.
+    let to_optional_values = |name_values: Vec<(String, String)>| {
+        name_values
+            .into_iter()
+            .map(|(name, value)| (name, Some(value).filter(|x| !x.is_empty())))
+            .collect::<Vec<(String, Option<String>)>>()
+    };
+    let where_values = to_optional_values(parse_name_values(where_values)?);
+    let set_values = to_optional_values(parse_name_values(set_values)?);
+
+    let mut storage = open_multi_storage(settings, start_utc_time_seconds, end_utc_time_seconds)?;
+    let count = storage.update_entries_values(
+        start_utc_time_seconds,
+        end_utc_time_seconds,
+        &where_values,
+        &set_values,
+    )?;
+    storage.close()?;
+
+    println!("Changed {} entries.", count);
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let env = env_logger::Env::default()
+        .filter_or("TIMETRACKER_LOG", "warn")
+        .write_style("TIMETRACKER_LOG_STYLE");
+    env_logger::init_from_env(env);
+
+    let args = CommandArguments::parse();
+
+    let settings = EditAppSettings::new(&args);
+    if settings.is_err() {
+        bail!("Settings are invalid: {:?}", settings);
+    }
+    let settings = settings?;
+    debug!("Settings validated: {:#?}", settings);
+
+    match &args.command {
+        CommandModes::Note { date, text, clear } => edit_day_note(&settings, date, text, *clear)?,
+        CommandModes::Plan {
+            week,
+            variable_name,
+            durations,
+            clear,
+        } => edit_week_plan(&settings, *week, variable_name, durations, *clear)?,
+        CommandModes::Add {
+            from,
+            to,
+            executable,
+            variables,
+        } => add_entry(&settings, from, to, executable, variables)?,
+        CommandModes::Delete { from, to } => delete_entries(&settings, from, to)?,
+        CommandModes::Retag {
+            where_values,
+            set_values,
+            from,
+            to,
+        } => retag_entries(&settings, where_values, set_values, from, to)?,
+    }
+
+    Ok(())
+}
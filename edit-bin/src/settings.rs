@@ -0,0 +1,155 @@
+use clap::{Parser, Subcommand};
+use config::ConfigError;
+use serde_derive::Deserialize;
+use timetracker_core::settings::new_core_settings;
+use timetracker_core::settings::validate_core_settings;
+use timetracker_core::settings::CoreSettings;
+
+#[derive(Parser, Debug)]
+#[clap(author = "David Cattermole, Copyright 2023-2024", version, about)]
+#[clap(propagate_version = true)]
+pub struct CommandArguments {
+    #[clap(subcommand)]
+    pub command: CommandModes,
+
+    /// Override the directory to search for the database file.
+    #[clap(long, value_parser)]
+    pub database_dir: Option<String>,
+
+    /// Override the name of the database file to open.
+    #[clap(long, value_parser)]
+    pub database_file_name: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CommandModes {
+    /// Write, show or remove the note for a day.
+    Note {
+        /// The date of the note, in "YYYY-MM-DD" format.
+        #[clap(value_parser)]
+        date: String,
+
+        /// The text of the note. When not given, the existing note
+        /// is printed.
+        #[clap(value_parser)]
+        text: Option<String>,
+
+        /// Remove the note for the day.
+        #[clap(long, value_parser, default_value_t = false)]
+        clear: bool,
+    },
+
+    /// Write, show or remove the planned durations of a week, such as
+    /// the hours planned for each project.
+    Plan {
+        /// Relative week number. '1' (or '+1') is the next week (the
+        /// default), '0' is the current week, '-1' is the previous
+        /// week, etc.
+        #[clap(
+            short = 'w',
+            long,
+            value_parser,
+            allow_hyphen_values = true,
+            default_value_t = 1
+        )]
+        week: i32,
+
+        /// The variable of the planned values, such as "PROJECT", or
+        /// "executable".
+        #[clap(long, value_parser, default_value = "PROJECT")]
+        variable_name: String,
+
+        /// The planned durations, as "VALUE=DURATION", such as
+        /// "ACME=20h" or "RND=7h30m". When not given, the existing
+        /// plan is printed.
+        #[clap(value_parser)]
+        durations: Vec<String>,
+
+        /// Remove the plan for the week.
+        #[clap(long, value_parser, default_value_t = false)]
+        clear: bool,
+    },
+
+    /// Add an active entry that was not recorded (such as when the
+    /// recorder was not running), replacing any idle entries in the
+    /// period.
+    Add {
+        /// The start of the entry, as "YYYY-MM-DD HH:MM", or "HH:MM"
+        /// for today.
+        #[clap(long, value_parser)]
+        from: String,
+
+        /// The end of the entry, as "YYYY-MM-DD HH:MM", or "HH:MM"
+        /// for today.
+        #[clap(long, value_parser)]
+        to: String,
+
+        /// The executable of the entry, such as "meeting".
+        #[clap(long, value_parser, default_value = "manual")]
+        executable: String,
+
+        /// The variables of the entry, as "NAME=VALUE", such as
+        /// "PWD=/projects/foo". At most 5 variables can be given.
+        #[clap(long = "var", value_parser)]
+        variables: Vec<String>,
+    },
+
+    /// Delete the entries starting in a period.
+    Delete {
+        /// The start of the period, as "YYYY-MM-DD HH:MM", or "HH:MM"
+        /// for today.
+        #[clap(long, value_parser)]
+        from: String,
+
+        /// The end of the period, as "YYYY-MM-DD HH:MM", or "HH:MM"
+        /// for today.
+        #[clap(long, value_parser)]
+        to: String,
+    },
+
+    /// Change the values of the entries with the matching values,
+    /// such as '--where executable=firefox --set var1_value=research'.
+    Retag {
+        /// The matched values, as "COLUMN=VALUE", such as
+        /// "executable=firefox". An empty value matches entries
+        /// without a value. All the values must match.
+        #[clap(long = "where", value_parser)]
+        where_values: Vec<String>,
+
+        /// The changed values, as "COLUMN=VALUE", such as
+        /// "var1_value=research". An empty value removes the value.
+        #[clap(long = "set", value_parser, required = true)]
+        set_values: Vec<String>,
+
+        /// Only change the entries starting after this time, as
+        /// "YYYY-MM-DD HH:MM", or "HH:MM" for today.
+        #[clap(long, value_parser)]
+        from: Option<String>,
+
+        /// Only change the entries starting before this time, as
+        /// "YYYY-MM-DD HH:MM", or "HH:MM" for today.
+        #[clap(long, value_parser)]
+        to: Option<String>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(unused)]
+pub struct EditAppSettings {
+    pub core: CoreSettings,
+}
+
+impl EditAppSettings {
+    pub fn new(arguments: &CommandArguments) -> Result<Self, ConfigError> {
+        let builder = new_core_settings(
+            arguments.database_dir.clone(),
+            arguments.database_file_name.clone(),
+            false,
+        )?;
+
+        let settings: Self = builder.build()?.try_deserialize()?;
+        validate_core_settings(&settings.core).unwrap();
+
+        Ok(settings)
+    }
+}
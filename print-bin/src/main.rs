@@ -1,20 +1,58 @@
+use crate::output::write_html_report;
+use crate::output::write_rows;
+use crate::prompt_segment::print_prompt_segment;
 use crate::settings::CommandArguments;
+use crate::settings::OutputFormat;
 use crate::settings::PrintAppSettings;
+use crate::timeline::run_timeline;
 use anyhow::bail;
+use anyhow::Context;
 use anyhow::Result;
 use clap::Parser;
 use log::{debug, warn};
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
 use std::time::SystemTime;
 use timetracker_core::filesystem::get_database_file_path;
+use timetracker_core::filesystem::get_database_file_paths_for_range;
 use timetracker_core::format::format_datetime;
-use timetracker_core::settings::RECORD_INTERVAL_SECONDS;
-use timetracker_core::storage::Storage;
+use timetracker_core::format::PrintType;
+use timetracker_core::http_storage::HttpStorage;
+use timetracker_core::multi_storage::MultiStorage;
+use timetracker_core::settings::DEFAULT_RECORD_INTERVAL_SECONDS;
+use timetracker_core::storage::StorageBackend;
+use timetracker_core::term;
 use timetracker_print_lib::aggregate::get_map_keys_sorted_strings;
+use timetracker_print_lib::datetime::get_datetime_range;
+use timetracker_print_lib::datetime::get_granularity_datetime_ranges;
+use timetracker_print_lib::datetime::parse_date;
+use timetracker_print_lib::datetime::parse_weekday_time_range;
+use timetracker_print_lib::filter::EntryFilter;
+use timetracker_print_lib::mapping::VariableMappings;
+use timetracker_print_lib::preset::can_generate_presets_from_durations;
 use timetracker_print_lib::preset::create_presets;
+use timetracker_print_lib::preset::find_preset_problems;
 use timetracker_print_lib::preset::generate_presets;
+use timetracker_print_lib::preset::generate_presets_comparison;
+use timetracker_print_lib::preset::generate_presets_from_durations;
+use timetracker_print_lib::preset::generate_presets_per_period;
+use timetracker_print_lib::preset::get_presets_datetime_range;
+use timetracker_print_lib::preset::DEFAULT_COLOR;
+use timetracker_print_lib::print::generate_idle_lines;
+use timetracker_print_lib::print::generate_inspect_lines;
 use timetracker_print_lib::print::get_relative_week_start_end;
+use timetracker_print_lib::ramp::ExecutableColors;
+use timetracker_print_lib::report::generate_report;
+use timetracker_print_lib::rows::generate_presets_activity_rows;
+use timetracker_print_lib::rows::generate_presets_rows;
+use timetracker_print_lib::template::render_report_template_file;
 
+mod output;
+mod prompt_segment;
 mod settings;
+mod timeline;
 
 fn print_presets(args: &CommandArguments, settings: &PrintAppSettings) -> Result<()> {
     let now = SystemTime::now();
@@ -22,44 +60,58 @@ fn print_presets(args: &CommandArguments, settings: &PrintAppSettings) -> Result
         &settings.core.database_dir,
         &settings.core.database_file_name,
     );
-    if database_file_path.is_some() {
-        println!(
-            "Database file path: {}",
-            database_file_path.as_ref().unwrap().display()
-        );
-    } else {
-        warn!(
+    // Template (and machine-readable) output is written as-is, so it
+    // can be redirected to a file, and the timeline uses the full
+    // terminal.
+    let print_header =
+        args.template.is_none() && !args.timeline && args.output_format == OutputFormat::Text;
+    let database_file_path = match database_file_path {
+        Some(value) => value,
+        None => bail!(
             "Database file {:?} not found in {:?}",
-            &settings.core.database_file_name, &settings.core.database_dir
-        );
-    }
+            &settings.core.database_file_name,
+            &settings.core.database_dir
+        ),
+    };
     let duration = now.elapsed()?.as_secs_f32();
     debug!("Time taken (find database): {:.4} seconds", duration);
 
-    let mut storage = Storage::open_as_read_only(
-        &database_file_path.expect("Database file path should be valid"),
-        RECORD_INTERVAL_SECONDS,
-    )?;
-    let duration = now.elapsed()?.as_secs_f32();
-    debug!("Time taken (open database): {:.4} seconds", duration);
+    let first_day_of_week = settings.core.first_day_of_week;
+    let datetime_pair = match &args.from {
+        Some(from) => {
+            let start_date = parse_date(from)?;
+            let end_date = match &args.to {
+                Some(to) => parse_date(to)?,
+                None => chrono::Local::now().date_naive(),
+            };
+            get_datetime_range(start_date, end_date)?
+        }
+        None => {
+            let relative_week = if args.last_week {
+                -1
+            } else {
+                args.relative_week
+            };
 
-    let relative_week = if args.last_week {
-        -1
-    } else {
-        args.relative_week
+            // 'relative_week' is added to the week number to find. A
+            // value of '-1' will get the previous week, a value of
+            // '0' will get the current week, and a value of '1' will
+            // get the next week (which shouldn't really give any
+            // results, so it's probably pointless).
+            get_relative_week_start_end(relative_week, first_day_of_week)?
+        }
     };
-
-    // 'relative_week' is added to the week number to find. A value of
-    // '-1' will get the previous week, a value of '0' will get the
-    // current week, and a value of '1' will get the next week (which
-    // shouldn't really give any results, so it's probably pointless).
-    let week_datetime_pair = get_relative_week_start_end(relative_week)?;
-    println!(
-        "Gathering data from {} to {}.",
-        format_datetime(week_datetime_pair.0, settings.print.format_datetime),
-        format_datetime(week_datetime_pair.1, settings.print.format_datetime),
-    );
-    println!("");
+    let against_datetime_pair = match args.against {
+        Some(against_week) => Some(get_relative_week_start_end(
+            against_week,
+            first_day_of_week,
+        )?),
+        None => None,
+    };
+    let entry_filter = EntryFilter::new(&args.executables, &args.variables)?;
+    let variable_mappings = VariableMappings::new(&settings.print.mappings)?;
+    let variable_names =
+        variable_mappings.variable_names(&settings.core.environment_variables.names)?;
 
     let now = SystemTime::now();
     let (presets, missing_preset_names) = create_presets(
@@ -69,6 +121,7 @@ fn print_presets(args: &CommandArguments, settings: &PrintAppSettings) -> Result
         settings.print.time_block_unit,
         settings.print.bar_graph_character_num_width,
         settings.print.use_color,
+        settings.print.sort_by,
         &settings.core.environment_variables.names,
         &settings.print.display_presets,
         &settings.print.presets,
@@ -76,22 +129,347 @@ fn print_presets(args: &CommandArguments, settings: &PrintAppSettings) -> Result
     let duration = now.elapsed()?.as_secs_f32();
     debug!("Time taken (create presets): {:.4} seconds", duration);
 
-    let now = SystemTime::now();
-    let (week_start_datetime, week_end_datetime) = week_datetime_pair;
-    let week_start_of_time = week_start_datetime.timestamp() as u64;
-    let week_end_of_time = week_end_datetime.timestamp() as u64;
-    let week_entries = storage.read_entries(week_start_of_time, week_end_of_time)?;
-    let duration = now.elapsed()?.as_secs_f32();
-    debug!("Time taken (read database): {:.4} seconds", duration);
+    if settings.print.strict {
+        let problems = find_preset_problems(
+            &settings.print.display_presets,
+            &presets,
+            &missing_preset_names,
+            &variable_names,
+        );
+        if !problems.is_empty() {
+            bail!(
+                "Presets are invalid (strict mode):\n{}",
+                problems.join("\n")
+            );
+        }
+    }
+
+    // Presets may need entries outside of the displayed range (such
+    // as a whole month).
+    let print_presets =
+        args.inspect.is_none() && args.template.is_none() && !args.timeline && !args.show_idle;
+    let read_datetime_pair = match (print_presets, args.granularity) {
+        (true, Some(granularity)) => {
+            let mut read_datetime_pair = datetime_pair;
+            for (_heading, period_datetime_pair) in get_granularity_datetime_ranges(
+                granularity,
+                datetime_pair,
+                settings.print.format_datetime,
+                first_day_of_week,
+            ) {
+                let (start_datetime, end_datetime) =
+                    get_presets_datetime_range(&presets, period_datetime_pair, first_day_of_week)?;
+                read_datetime_pair = (
+                    std::cmp::min(read_datetime_pair.0, start_datetime),
+                    std::cmp::max(read_datetime_pair.1, end_datetime),
+                );
+            }
+            read_datetime_pair
+        }
+        (true, None) => get_presets_datetime_range(&presets, datetime_pair, first_day_of_week)?,
+        (false, _) => datetime_pair,
+    };
+    // The compared week is read too.
+    let read_datetime_pair = match against_datetime_pair {
+        Some(against_datetime_pair) => (
+            std::cmp::min(read_datetime_pair.0, against_datetime_pair.0),
+            std::cmp::max(read_datetime_pair.1, against_datetime_pair.1),
+        ),
+        None => read_datetime_pair,
+    };
+    let (start_datetime, end_datetime) = read_datetime_pair;
+    let start_of_time = start_datetime.timestamp() as u64;
+    let end_of_time = end_datetime.timestamp() as u64;
+
+    // The durations of summary presets can be summed in the database,
+    // without reading the entries, when every entry is counted as
+    // recorded (the entries are not mapped, filtered or shifted to the
+    // recorded UTC offset).
+    let sum_in_database = print_presets
+        && !args.remote
+        && args.output_format == OutputFormat::Text
+        && args.granularity.is_none()
+        && against_datetime_pair.is_none()
+        && entry_filter.is_empty()
+        && variable_mappings.is_empty()
+        && !settings.core.use_recorded_utc_offset
+        && can_generate_presets_from_durations(&presets);
+
+    // The 'colored' crate also reads the "NO_COLOR" and "CLICOLOR"
+    // environment variables, which must not override '--color'.
+    if args.color.is_some() {
+        colored::control::set_override(settings.print.use_color);
+    }
+    let color = settings.print.use_color.then_some(DEFAULT_COLOR);
+    let executable_colors = ExecutableColors::new(&settings.print.colors)?;
+
+    let print_gathering_header = || {
+        println!(
+            "Gathering data from {} to {}.",
+            format_datetime(read_datetime_pair.0, settings.print.format_datetime),
+            format_datetime(read_datetime_pair.1, settings.print.format_datetime),
+        );
+        println!();
+    };
+
+    let entries = match args.remote {
+        true => {
+            if print_header {
+                println!("Sync server: {}", settings.sync.host);
+                print_gathering_header();
+            }
+
+            let now = SystemTime::now();
+            let mut storage = HttpStorage::new(&settings.sync.host, &settings.sync.token);
+            let entries = storage.read_entries(start_of_time, end_of_time)?;
+            let duration = now.elapsed()?.as_secs_f32();
+            debug!("Time taken (read sync server): {:.4} seconds", duration);
+            entries
+        }
+        false => {
+            let now = SystemTime::now();
+            let database_file_paths = get_database_file_paths_for_range(
+                &database_file_path,
+                settings.core.database_per_year,
+                &settings.core.archive_dir,
+                start_of_time,
+                end_of_time,
+            );
+            let mut storage = MultiStorage::open_as_read_only(
+                &database_file_paths,
+                DEFAULT_RECORD_INTERVAL_SECONDS,
+            )?;
+            storage.set_use_recorded_utc_offset(settings.core.use_recorded_utc_offset);
+            let duration = now.elapsed()?.as_secs_f32();
+            debug!("Time taken (open database): {:.4} seconds", duration);
+
+            if print_header {
+                for database_file_path in storage.database_file_paths() {
+                    println!("Database file path: {}", database_file_path.display());
+                }
+                print_gathering_header();
+            }
+
+            if sum_in_database {
+                let now = SystemTime::now();
+                let lines = generate_presets_from_durations(
+                    &presets,
+                    &storage,
+                    datetime_pair,
+                    first_day_of_week,
+                    color,
+                    &executable_colors,
+                )?;
+                storage.close()?;
+                let duration = now.elapsed()?.as_secs_f32();
+                debug!(
+                    "Time taken (sum database durations): {:.4} seconds",
+                    duration
+                );
+
+                return print_lines(args, settings, &lines, &missing_preset_names);
+            }
+
+            let now = SystemTime::now();
+            let entries = storage.read_entries(start_of_time, end_of_time)?;
+            storage.close()?;
+            let duration = now.elapsed()?.as_secs_f32();
+            debug!("Time taken (read database): {:.4} seconds", duration);
+            entries
+        }
+    };
+    let entries = variable_mappings.map_entries(entries);
+    let entries = entry_filter.filter_entries(entries);
+
+    if args.timeline {
+        return run_timeline(&entries, settings);
+    }
+
+    if args.output_format != OutputFormat::Text {
+        let period_datetime_pairs = match args.granularity {
+            Some(granularity) => get_granularity_datetime_ranges(
+                granularity,
+                datetime_pair,
+                settings.print.format_datetime,
+                first_day_of_week,
+            )
+            .into_iter()
+            .map(|(_heading, period_datetime_pair)| period_datetime_pair)
+            .collect(),
+            None => vec![datetime_pair],
+        };
+
+        // The HTML report draws the "Activity" presets as bar charts,
+        // and the other formats have no rows for them.
+        let (activity_presets, row_presets): (Vec<_>, Vec<_>) = settings
+            .print
+            .display_presets
+            .iter()
+            .cloned()
+            .zip(presets.iter().cloned())
+            .partition(|(_preset_name, preset)| {
+                args.output_format == OutputFormat::Html
+                    && matches!(preset.print_type, Some(PrintType::Activity))
+            });
+        let (row_preset_names, row_presets): (Vec<_>, Vec<_>) = row_presets.into_iter().unzip();
+        let (activity_preset_names, activity_presets): (Vec<_>, Vec<_>) =
+            activity_presets.into_iter().unzip();
+
+        let mut rows = Vec::new();
+        let mut activity_rows = Vec::new();
+        for period_datetime_pair in period_datetime_pairs {
+            rows.extend(generate_presets_rows(
+                &row_preset_names,
+                &row_presets,
+                &entries,
+                period_datetime_pair,
+                first_day_of_week,
+            )?);
+            activity_rows.extend(generate_presets_activity_rows(
+                &activity_preset_names,
+                &activity_presets,
+                &entries,
+                period_datetime_pair,
+                first_day_of_week,
+            )?);
+        }
+
+        let mut writer: Box<dyn Write> = match &args.output {
+            Some(file_path) => Box::new(BufWriter::new(
+                File::create(file_path)
+                    .with_context(|| format!("Could not create output file {:?}", file_path))?,
+            )),
+            None => Box::new(std::io::stdout().lock()),
+        };
+        match args.output_format {
+            OutputFormat::Html => {
+                let title = format!(
+                    "Timetracker Report {} to {}",
+                    datetime_pair.0.format("%Y-%m-%d"),
+                    datetime_pair.1.format("%Y-%m-%d")
+                );
+                let report = generate_report(
+                    &entries,
+                    &variable_names,
+                    settings.print.format_datetime,
+                    settings.print.format_duration,
+                    settings.print.time_block_unit,
+                );
+                write_html_report(
+                    &mut writer,
+                    &title,
+                    &settings.print.display_presets,
+                    &rows,
+                    &activity_rows,
+                    &report,
+                    settings.print.format_duration,
+                )?;
+            }
+            _ => write_rows(&mut writer, args.output_format, &rows)?,
+        }
+        writer.flush()?;
+        return Ok(());
+    }
 
     let now = SystemTime::now();
-    let lines = generate_presets(&presets, &week_entries)?;
+    let lines = match (&args.inspect, &args.template) {
+        (_, Some(template)) => {
+            let report = generate_report(
+                &entries,
+                &variable_names,
+                settings.print.format_datetime,
+                settings.print.format_duration,
+                settings.print.time_block_unit,
+            );
+            let text = render_report_template_file(Path::new(template), &report)?;
+            vec![text.trim_matches('\n').to_string()]
+        }
+        (None, None) if args.show_idle => {
+            let mut lines = Vec::new();
+            generate_idle_lines(
+                &entries,
+                &mut lines,
+                datetime_pair,
+                settings.print.format_datetime,
+                settings.print.format_duration,
+                settings.print.utilization.idle_threshold_seconds,
+            )?;
+            lines
+        }
+        (Some(inspect), None) => {
+            let block_datetime_pair = parse_weekday_time_range(inspect, datetime_pair)?;
+            let mut lines = Vec::new();
+            generate_inspect_lines(
+                &entries,
+                &mut lines,
+                block_datetime_pair,
+                settings.print.format_datetime,
+                settings.print.format_duration,
+            )?;
+            lines
+        }
+        (None, None) if against_datetime_pair.is_some() => generate_presets_comparison(
+            &presets,
+            &entries,
+            datetime_pair,
+            against_datetime_pair.unwrap(),
+        )?,
+        (None, None) => match args.granularity {
+            Some(granularity) => generate_presets_per_period(
+                &presets,
+                &entries,
+                datetime_pair,
+                granularity,
+                settings.print.format_datetime,
+                &settings.print.utilization,
+                settings.core.work_hours_per_day,
+                first_day_of_week,
+                color,
+                &executable_colors,
+            )?,
+            None => generate_presets(
+                &presets,
+                &entries,
+                datetime_pair,
+                &settings.print.utilization,
+                settings.core.work_hours_per_day,
+                first_day_of_week,
+                color,
+                &executable_colors,
+            )?,
+        },
+    };
     let duration = now.elapsed()?.as_secs_f32();
-    debug!("Time taken (generate presets): {:.4} seconds", duration);
+    debug!("Time taken (generate lines): {:.4} seconds", duration);
+
+    print_lines(args, settings, &lines, &missing_preset_names)
+}
+
+/// Print the generated lines, and warn about the presets that could
+/// not be found.
+fn print_lines(
+    args: &CommandArguments,
+    settings: &PrintAppSettings,
+    lines: &[String],
+    missing_preset_names: &[String],
+) -> Result<()> {
+    // Long lines (such as window titles) are truncated to fit the
+    // terminal; the full lines are printed when redirected.
+    let terminal_width = match args.template {
+        Some(_) => None,
+        None => term::width(),
+    };
 
     let now = SystemTime::now();
-    for line in &lines {
-        println!("{}", line);
+    for line in lines {
+        match terminal_width {
+            Some(width) => {
+                for line in line.split('\n') {
+                    println!("{}", term::truncate_to_width(line, width));
+                }
+            }
+            None => println!("{}", line),
+        }
     }
     let duration = now.elapsed()?.as_secs_f32();
     debug!("Time taken (print to terminal): {:.4} seconds", duration);
@@ -133,9 +511,10 @@ fn main() -> Result<()> {
 
     let now = SystemTime::now();
 
-    match &args.list_presets {
-        true => list_presets(&settings)?,
-        false => print_presets(&args, &settings)?,
+    match (&args.list_presets, &args.prompt_segment) {
+        (true, _) => list_presets(&settings)?,
+        (false, true) => print_prompt_segment(&settings)?,
+        (false, false) => print_presets(&args, &settings)?,
     };
 
     let duration = now.elapsed()?.as_secs_f32();
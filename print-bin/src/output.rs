@@ -0,0 +1,611 @@
+use crate::settings::OutputFormat;
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+use std::io::prelude::*;
+use timetracker_core::format::format_duration;
+use timetracker_core::format::DurationFormat;
+use timetracker_print_lib::report::Report;
+use timetracker_print_lib::report::ReportBlock;
+use timetracker_print_lib::report::ReportDay;
+use timetracker_print_lib::rows::format_row_percentage;
+use timetracker_print_lib::rows::write_rows_csv;
+use timetracker_print_lib::rows::ActivityBlockRow;
+use timetracker_print_lib::rows::PresetRow;
+
+fn write_json_rows(writer: &mut impl Write, rows: &[PresetRow]) -> Result<()> {
+    serde_json::to_writer_pretty(&mut *writer, rows)?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+/// Escape the text for a Markdown table cell.
+fn escape_markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+/// Write a Markdown table for each preset and period, with a heading
+/// before each table.
+fn write_markdown_rows(writer: &mut impl Write, rows: &[PresetRow]) -> Result<()> {
+    let mut last_heading = None;
+    for row in rows {
+        let heading = format!(
+            "## {} ({} to {})",
+            escape_markdown_cell(&row.preset),
+            row.start_date,
+            row.end_date
+        );
+        if last_heading.as_ref() != Some(&heading) {
+            if last_heading.is_some() {
+                writeln!(writer)?;
+            }
+            writeln!(writer, "{}", heading)?;
+            writeln!(writer)?;
+
+            let mut columns: Vec<String> =
+                row.names.iter().map(|x| escape_markdown_cell(x)).collect();
+            columns.push("Duration".to_string());
+            columns.push("Percentage".to_string());
+            writeln!(writer, "| {} |", columns.join(" | "))?;
+            let separators = vec!["---"; columns.len()];
+            writeln!(writer, "| {} |", separators.join(" | "))?;
+            last_heading = Some(heading);
+        }
+
+        let mut cells: Vec<String> = row.values.iter().map(|x| escape_markdown_cell(x)).collect();
+        cells.push(row.duration.clone());
+        cells.push(format!("{}%", format_row_percentage(row.percentage)));
+        writeln!(writer, "| {} |", cells.join(" | "))?;
+    }
+    Ok(())
+}
+
+/// Write the rows of the presets in the (machine-readable) format.
+pub fn write_rows(writer: &mut impl Write, format: OutputFormat, rows: &[PresetRow]) -> Result<()> {
+    match format {
+        OutputFormat::Csv => Ok(write_rows_csv(writer, rows)?),
+        OutputFormat::Json => write_json_rows(writer, rows),
+        OutputFormat::Markdown => write_markdown_rows(writer, rows),
+        OutputFormat::Text => bail!("Text output is printed as lines, not rows."),
+        OutputFormat::Html => bail!("HTML output is written as a report, not rows."),
+    }
+}
+
+const HTML_STYLE: &str = "
+body { font-family: sans-serif; margin: 2em auto; max-width: 60em; color: #222; }
+h2 { border-bottom: 1px solid #ccc; padding-bottom: 0.2em; margin-top: 2em; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { text-align: left; padding: 0.2em 0.8em; border-bottom: 1px solid #eee; }
+td.duration { font-family: monospace; white-space: nowrap; }
+.bar { display: inline-block; width: 10em; height: 0.8em; background: #eee; }
+.bar span { display: block; height: 100%; background: #4a8fd9; }
+.chart { display: flex; align-items: flex-end; height: 6em; background: #f4f4f4; }
+.chart span { flex: 1; background: #4a8fd9; min-width: 1px; }
+.axis { display: flex; justify-content: space-between; font-size: 0.8em; color: #666; }
+.note { font-style: italic; }
+";
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// An 'id' attribute value for a section (with only lowercase
+/// letters, digits and dashes), so it can be linked to with a
+/// fragment, such as "#preset-software-week".
+fn html_anchor_id(parts: &[&str]) -> String {
+    let mut id = String::new();
+    for part in parts {
+        if !id.is_empty() {
+            id.push('-');
+        }
+        for character in part.chars() {
+            match character.is_ascii_alphanumeric() {
+                true => id.push(character.to_ascii_lowercase()),
+                false => id.push('-'),
+            }
+        }
+    }
+    id
+}
+
+fn write_html_bar(writer: &mut impl Write, ratio: f64) -> Result<()> {
+    write!(
+        writer,
+        "<span class=\"bar\"><span style=\"width: {:.1}%\"></span></span>",
+        ratio.clamp(0.0, 1.0) * 100.0
+    )?;
+    Ok(())
+}
+
+/// Write a table for each period of the preset, with a bar for the
+/// percentage of each value.
+fn write_html_preset_rows(
+    writer: &mut impl Write,
+    preset_name: &str,
+    rows: &[&PresetRow],
+) -> Result<()> {
+    let mut last_period = None;
+    for row in rows {
+        let period = (&row.start_date, &row.end_date);
+        if last_period != Some(period) {
+            if last_period.is_some() {
+                writeln!(writer, "</table>")?;
+            }
+            let anchor_id = html_anchor_id(&["preset", preset_name, &row.start_date]);
+            writeln!(
+                writer,
+                "<h3 id=\"{}\">{} to {}</h3>",
+                anchor_id, row.start_date, row.end_date
+            )?;
+            writeln!(writer, "<table>")?;
+            write!(writer, "<tr>")?;
+            for name in &row.names {
+                write!(writer, "<th>{}</th>", escape_html(name))?;
+            }
+            writeln!(writer, "<th>Duration</th><th>Percentage</th></tr>")?;
+            last_period = Some(period);
+        }
+
+        write!(writer, "<tr>")?;
+        for value in &row.values {
+            write!(writer, "<td>{}</td>", escape_html(value))?;
+        }
+        write!(
+            writer,
+            "<td class=\"duration\">{}</td><td>",
+            escape_html(&row.duration)
+        )?;
+        write_html_bar(writer, row.percentage / 100.0)?;
+        writeln!(
+            writer,
+            " {}%</td></tr>",
+            format_row_percentage(row.percentage)
+        )?;
+    }
+    if last_period.is_some() {
+        writeln!(writer, "</table>")?;
+    }
+    Ok(())
+}
+
+/// Write a table of the active time of each day, linking to a bar
+/// chart of the blocks of time of each day.
+fn write_html_activity_rows(
+    writer: &mut impl Write,
+    preset_name: &str,
+    rows: &[&ActivityBlockRow],
+    duration_format: DurationFormat,
+) -> Result<()> {
+    let mut dates = Vec::<&String>::new();
+    let mut date_seconds = HashMap::<&String, i64>::new();
+    for row in rows {
+        if !date_seconds.contains_key(&row.date) {
+            dates.push(&row.date);
+        }
+        *date_seconds.entry(&row.date).or_insert(0) += row.active_seconds;
+    }
+
+    writeln!(writer, "<table>")?;
+    writeln!(writer, "<tr><th>Date</th><th>Duration</th></tr>")?;
+    for date in &dates {
+        let duration = chrono::Duration::seconds(date_seconds[date]);
+        writeln!(
+            writer,
+            "<tr><td><a href=\"#{}\">{}</a></td><td class=\"duration\">{}</td></tr>",
+            html_anchor_id(&["preset", preset_name, date]),
+            date,
+            escape_html(&format_duration(duration, duration_format))
+        )?;
+    }
+    writeln!(writer, "</table>")?;
+
+    for date in &dates {
+        let day_rows: Vec<&&ActivityBlockRow> = rows.iter().filter(|x| &x.date == *date).collect();
+        writeln!(
+            writer,
+            "<h3 id=\"{}\">{}</h3>",
+            html_anchor_id(&["preset", preset_name, date]),
+            date
+        )?;
+        write!(writer, "<div class=\"chart\">")?;
+        for row in &day_rows {
+            write!(
+                writer,
+                "<span style=\"height: {:.1}%\" title=\"{} {}\"></span>",
+                row.ratio.clamp(0.0, 1.0) * 100.0,
+                row.time,
+                escape_html(&row.duration)
+            )?;
+        }
+        writeln!(writer, "</div>")?;
+        if let (Some(first), Some(last)) = (day_rows.first(), day_rows.last()) {
+            writeln!(
+                writer,
+                "<div class=\"axis\"><span>{}</span><span>{}</span></div>",
+                first.time, last.time
+            )?;
+        }
+        writeln!(
+            writer,
+            "<p><a href=\"#{}\">Blocks and entries of {}</a></p>",
+            html_anchor_id(&["day", date]),
+            date
+        )?;
+    }
+    Ok(())
+}
+
+/// The names (and durations) of the items, longest first, such as
+/// "firefox 1h 20m, bash 5m".
+fn format_html_items(block: &ReportBlock) -> String {
+    let mut items = block.executables.clone();
+    items.sort_by_key(|x| std::cmp::Reverse(x.active_seconds));
+    items
+        .iter()
+        .map(|x| format!("{} {}", escape_html(&x.name), escape_html(&x.duration)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Write the entries of a block of time, with a link back to the
+/// day.
+fn write_html_block(writer: &mut impl Write, day: &ReportDay, block: &ReportBlock) -> Result<()> {
+    writeln!(
+        writer,
+        "<section id=\"{}\">",
+        html_anchor_id(&["block", &block.id])
+    )?;
+    writeln!(
+        writer,
+        "<h4>{} {} {} to {} ({})</h4>",
+        escape_html(&day.weekday),
+        escape_html(&day.date),
+        escape_html(&block.start_time),
+        escape_html(&block.end_time),
+        escape_html(&block.duration)
+    )?;
+    writeln!(
+        writer,
+        "<p><a href=\"#{}\">Back to {} {}</a></p>",
+        html_anchor_id(&["day", &day.id]),
+        escape_html(&day.weekday),
+        escape_html(&day.date)
+    )?;
+    writeln!(writer, "<table>")?;
+    writeln!(
+        writer,
+        "<tr><th>Time</th><th>Duration</th><th>Status</th><th>Executable</th><th>Window Title</th></tr>"
+    )?;
+    for entry in &block.entries {
+        writeln!(
+            writer,
+            "<tr><td>{}</td><td class=\"duration\">{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            escape_html(&entry.time),
+            escape_html(&entry.duration),
+            escape_html(&entry.status),
+            escape_html(&entry.executable),
+            escape_html(&entry.window_title)
+        )?;
+    }
+    writeln!(writer, "</table>")?;
+    writeln!(writer, "</section>")?;
+    Ok(())
+}
+
+/// Write the days of the report; a summary table linking to a
+/// section for each day, with a table of the blocks of time of the
+/// day linking to the entries of each block.
+fn write_html_days(writer: &mut impl Write, report: &Report) -> Result<()> {
+    writeln!(writer, "<section id=\"days\">")?;
+    writeln!(writer, "<h2>Days</h2>")?;
+    if report.days.is_empty() {
+        writeln!(writer, "<p>No entries.</p>")?;
+        writeln!(writer, "</section>")?;
+        return Ok(());
+    }
+
+    writeln!(writer, "<table>")?;
+    writeln!(
+        writer,
+        "<tr><th>Day</th><th>Duration</th><th>Note</th></tr>"
+    )?;
+    for day in &report.days {
+        writeln!(
+            writer,
+            "<tr><td><a href=\"#{}\">{} {}</a></td><td class=\"duration\">{}</td><td class=\"note\">{}</td></tr>",
+            html_anchor_id(&["day", &day.id]),
+            escape_html(&day.weekday),
+            escape_html(&day.date),
+            escape_html(&day.duration),
+            escape_html(day.note.as_deref().unwrap_or(""))
+        )?;
+    }
+    writeln!(
+        writer,
+        "<tr><th>Total</th><td class=\"duration\">{}</td></tr>",
+        escape_html(&report.duration)
+    )?;
+    writeln!(writer, "</table>")?;
+
+    for day in &report.days {
+        writeln!(
+            writer,
+            "<section id=\"{}\">",
+            html_anchor_id(&["day", &day.id])
+        )?;
+        writeln!(
+            writer,
+            "<h3>{} {} ({})</h3>",
+            escape_html(&day.weekday),
+            escape_html(&day.date),
+            escape_html(&day.duration)
+        )?;
+        if let Some(note) = &day.note {
+            writeln!(writer, "<p class=\"note\">{}</p>", escape_html(note))?;
+        }
+        // Days with only a note have no blocks.
+        if day.blocks.is_empty() {
+            writeln!(writer, "</section>")?;
+            continue;
+        }
+        writeln!(writer, "<table>")?;
+        writeln!(
+            writer,
+            "<tr><th>Block</th><th>Duration</th><th>Executables</th></tr>"
+        )?;
+        for block in &day.blocks {
+            writeln!(
+                writer,
+                "<tr><td><a href=\"#{}\">{} to {}</a></td><td class=\"duration\">{}</td><td>{}</td></tr>",
+                html_anchor_id(&["block", &block.id]),
+                escape_html(&block.start_time),
+                escape_html(&block.end_time),
+                escape_html(&block.duration),
+                format_html_items(block)
+            )?;
+        }
+        writeln!(writer, "</table>")?;
+        for block in &day.blocks {
+            write_html_block(writer, day, block)?;
+        }
+        writeln!(writer, "</section>")?;
+    }
+    writeln!(writer, "</section>")?;
+    Ok(())
+}
+
+/// Write a standalone HTML page, with a section for each preset (in
+/// the order of 'preset_names'), the days of the report (drilling
+/// down to the blocks of each day, and the entries of each block),
+/// and a list of contents linking to the sections.
+pub fn write_html_report(
+    writer: &mut impl Write,
+    title: &str,
+    preset_names: &[String],
+    rows: &[PresetRow],
+    activity_rows: &[ActivityBlockRow],
+    report: &Report,
+    duration_format: DurationFormat,
+) -> Result<()> {
+    writeln!(writer, "<!DOCTYPE html>")?;
+    writeln!(writer, "<html lang=\"en\">")?;
+    writeln!(writer, "<head>")?;
+    writeln!(writer, "<meta charset=\"utf-8\">")?;
+    writeln!(writer, "<title>{}</title>", escape_html(title))?;
+    writeln!(writer, "<style>{}</style>", HTML_STYLE)?;
+    writeln!(writer, "</head>")?;
+    writeln!(writer, "<body>")?;
+    writeln!(writer, "<h1>{}</h1>", escape_html(title))?;
+
+    writeln!(writer, "<nav>")?;
+    writeln!(writer, "<ul>")?;
+    for preset_name in preset_names {
+        writeln!(
+            writer,
+            "<li><a href=\"#{}\">{}</a></li>",
+            html_anchor_id(&["preset", preset_name]),
+            escape_html(preset_name)
+        )?;
+    }
+    writeln!(writer, "<li><a href=\"#days\">Days</a></li>")?;
+    writeln!(writer, "</ul>")?;
+    writeln!(writer, "</nav>")?;
+
+    for preset_name in preset_names {
+        writeln!(
+            writer,
+            "<section id=\"{}\">",
+            html_anchor_id(&["preset", preset_name])
+        )?;
+        writeln!(writer, "<h2>{}</h2>", escape_html(preset_name))?;
+
+        let preset_rows: Vec<&PresetRow> =
+            rows.iter().filter(|x| &x.preset == preset_name).collect();
+        let preset_activity_rows: Vec<&ActivityBlockRow> = activity_rows
+            .iter()
+            .filter(|x| &x.preset == preset_name)
+            .collect();
+        if !preset_activity_rows.is_empty() {
+            write_html_activity_rows(writer, preset_name, &preset_activity_rows, duration_format)?;
+        } else if !preset_rows.is_empty() {
+            write_html_preset_rows(writer, preset_name, &preset_rows)?;
+        } else {
+            writeln!(writer, "<p>No active time.</p>")?;
+        }
+        writeln!(writer, "</section>")?;
+    }
+    write_html_days(writer, report)?;
+
+    writeln!(writer, "</body>")?;
+    writeln!(writer, "</html>")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use timetracker_core::entries::DayNote;
+    use timetracker_core::entries::Entry;
+    use timetracker_core::entries::EntryStatus;
+    use timetracker_core::entries::EntryVariablesList;
+    use timetracker_core::format::DateTimeFormat;
+    use timetracker_core::format::TimeBlockUnit;
+    use timetracker_core::storage::Entries;
+    use timetracker_print_lib::report::generate_report;
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html("<a href=\"x\">R&D</a>"),
+            "&lt;a href=&quot;x&quot;&gt;R&amp;D&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn test_html_anchor_id() {
+        assert_eq!(
+            html_anchor_id(&["preset", "Software Week", "2024-09-09"]),
+            "preset-software-week-2024-09-09"
+        );
+        assert_eq!(
+            html_anchor_id(&["block", "2024-09-09-0900"]),
+            "block-2024-09-09-0900"
+        );
+    }
+
+    #[test]
+    fn test_write_html_report() -> Result<()> {
+        // Monday.
+        let start_datetime = chrono::Local.with_ymd_and_hms(2024, 9, 9, 0, 0, 0).unwrap();
+        let end_datetime = chrono::Local
+            .with_ymd_and_hms(2024, 9, 9, 23, 59, 59)
+            .unwrap();
+        let mut vars = EntryVariablesList::empty();
+        vars.executable = Some("blender".to_string());
+        vars.window_title = Some("<shot_010>".to_string());
+        let nine_o_clock = start_datetime.timestamp() as u64 + (9 * 60 * 60);
+        let entries = Entries::builder()
+            .start_datetime(start_datetime)
+            .end_datetime(end_datetime)
+            .entries(vec![Entry::new(
+                nine_o_clock + (10 * 60),
+                30 * 60,
+                EntryStatus::Active,
+                vars,
+            )])
+            .day_notes(vec![DayNote::new(
+                start_datetime.date_naive(),
+                "Review & \"notes\"".to_string(),
+            )])
+            .build();
+        let report = generate_report(
+            &entries,
+            &[],
+            DateTimeFormat::Iso,
+            DurationFormat::HoursMinutes,
+            TimeBlockUnit::SixtyMinutes,
+        );
+        let rows = vec![PresetRow {
+            preset: "software".to_string(),
+            print_type: "Software".to_string(),
+            start_date: "2024-09-09".to_string(),
+            end_date: "2024-09-09".to_string(),
+            names: vec!["Executable".to_string()],
+            values: vec!["<blender>".to_string()],
+            active_seconds: 30 * 60,
+            duration: "00h 30m".to_string(),
+            percentage: 100.0,
+        }];
+        let activity_rows = vec![ActivityBlockRow {
+            preset: "activity".to_string(),
+            date: "2024-09-09".to_string(),
+            time: "09:00".to_string(),
+            active_seconds: 30 * 60,
+            duration: "00h 30m".to_string(),
+            ratio: 0.5,
+        }];
+
+        let mut output = Vec::new();
+        write_html_report(
+            &mut output,
+            "Report",
+            &["activity".to_string(), "software".to_string()],
+            &rows,
+            &activity_rows,
+            &report,
+            DurationFormat::HoursMinutes,
+        )?;
+        let html = String::from_utf8(output)?;
+
+        let expected = format!(
+            r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>Report</title>
+<style>{}</style>
+</head>
+<body>
+<h1>Report</h1>
+<nav>
+<ul>
+<li><a href="#preset-activity">activity</a></li>
+<li><a href="#preset-software">software</a></li>
+<li><a href="#days">Days</a></li>
+</ul>
+</nav>
+<section id="preset-activity">
+<h2>activity</h2>
+<table>
+<tr><th>Date</th><th>Duration</th></tr>
+<tr><td><a href="#preset-activity-2024-09-09">2024-09-09</a></td><td class="duration">00h 30m</td></tr>
+</table>
+<h3 id="preset-activity-2024-09-09">2024-09-09</h3>
+<div class="chart"><span style="height: 50.0%" title="09:00 00h 30m"></span></div>
+<div class="axis"><span>09:00</span><span>09:00</span></div>
+<p><a href="#day-2024-09-09">Blocks and entries of 2024-09-09</a></p>
+</section>
+<section id="preset-software">
+<h2>software</h2>
+<h3 id="preset-software-2024-09-09">2024-09-09 to 2024-09-09</h3>
+<table>
+<tr><th>Executable</th><th>Duration</th><th>Percentage</th></tr>
+<tr><td>&lt;blender&gt;</td><td class="duration">00h 30m</td><td><span class="bar"><span style="width: 100.0%"></span></span> 100.0%</td></tr>
+</table>
+</section>
+<section id="days">
+<h2>Days</h2>
+<table>
+<tr><th>Day</th><th>Duration</th><th>Note</th></tr>
+<tr><td><a href="#day-2024-09-09">Mon 2024-09-09</a></td><td class="duration">00h 30m</td><td class="note">Review &amp; &quot;notes&quot;</td></tr>
+<tr><th>Total</th><td class="duration">00h 30m</td></tr>
+</table>
+<section id="day-2024-09-09">
+<h3>Mon 2024-09-09 (00h 30m)</h3>
+<p class="note">Review &amp; &quot;notes&quot;</p>
+<table>
+<tr><th>Block</th><th>Duration</th><th>Executables</th></tr>
+<tr><td><a href="#block-2024-09-09-0900">09:00 to 10:00</a></td><td class="duration">00h 30m</td><td>blender 00h 30m</td></tr>
+</table>
+<section id="block-2024-09-09-0900">
+<h4>Mon 2024-09-09 09:00 to 10:00 (00h 30m)</h4>
+<p><a href="#day-2024-09-09">Back to Mon 2024-09-09</a></p>
+<table>
+<tr><th>Time</th><th>Duration</th><th>Status</th><th>Executable</th><th>Window Title</th></tr>
+<tr><td>09:10:00</td><td class="duration">00h 30m</td><td>Active</td><td>blender</td><td>&lt;shot_010&gt;</td></tr>
+</table>
+</section>
+</section>
+</section>
+</body>
+</html>
+"##,
+            HTML_STYLE
+        );
+        assert_eq!(html, expected);
+        Ok(())
+    }
+}
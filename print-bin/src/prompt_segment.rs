@@ -0,0 +1,137 @@
+use crate::settings::PrintAppSettings;
+use anyhow::{bail, Result};
+use log::debug;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+use timetracker_core::entries::EntryStatus;
+use timetracker_core::filesystem::get_database_file_path;
+use timetracker_core::filesystem::get_database_file_path_for_time;
+use timetracker_core::format::format_duration;
+use timetracker_core::settings::DEFAULT_RECORD_INTERVAL_SECONDS;
+use timetracker_core::storage::Storage;
+use timetracker_print_lib::datetime::get_datetime_range;
+
+const CACHE_FILE_NAME: &str = "timetracker-prompt-segment.txt";
+
+fn get_cache_file_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|x| x.join(CACHE_FILE_NAME))
+}
+
+/// Read the cached prompt segment, if it was written recently for
+/// the same database file.
+fn read_cached_prompt_segment(
+    cache_file_path: &Path,
+    database_file_path: &Path,
+    cache_seconds: u64,
+) -> Option<String> {
+    let age = fs::metadata(cache_file_path)
+        .ok()?
+        .modified()
+        .ok()?
+        .elapsed()
+        .ok()?;
+    if age.as_secs() >= cache_seconds {
+        return None;
+    }
+
+    let text = fs::read_to_string(cache_file_path).ok()?;
+    let (cached_database_file_path, prompt_segment) = text.split_once('\n')?;
+    match Path::new(cached_database_file_path) == database_file_path {
+        true => Some(prompt_segment.to_string()),
+        false => None,
+    }
+}
+
+fn write_cached_prompt_segment(
+    cache_file_path: &Path,
+    database_file_path: &Path,
+    prompt_segment: &str,
+) -> Result<()> {
+    if let Some(cache_dir) = cache_file_path.parent() {
+        fs::create_dir_all(cache_dir)?;
+    }
+    let text = format!("{}\n{}", database_file_path.display(), prompt_segment);
+    fs::write(cache_file_path, text)?;
+    Ok(())
+}
+
+/// Generate today's total active duration and the current project,
+/// such as "03h 12m ACME".
+///
+/// The totals are summed by the database, so the (potentially
+/// large) list of entries is never read.
+fn generate_prompt_segment(
+    database_file_path: &Path,
+    settings: &PrintAppSettings,
+) -> Result<String> {
+    let now_utc_time_seconds = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let today = chrono::Local::now().date_naive();
+    let (start_datetime, end_datetime) = get_datetime_range(today, today)?;
+    let start_of_time = start_datetime.timestamp() as u64;
+    let end_of_time = end_datetime.timestamp() as u64;
+
+    let database_file_path = get_database_file_path_for_time(
+        database_file_path,
+        settings.core.database_per_year,
+        now_utc_time_seconds,
+    );
+    let storage = Storage::open_as_read_only(&database_file_path, DEFAULT_RECORD_INTERVAL_SECONDS)?;
+    let duration_seconds =
+        storage.sum_entries_duration_seconds(start_of_time, end_of_time, EntryStatus::Active)?;
+    let project = storage.read_last_entry_variable_value(
+        start_of_time,
+        EntryStatus::Active,
+        &settings.print.prompt_segment.variable_name,
+    )?;
+    storage.close()?;
+
+    let duration = chrono::Duration::seconds(duration_seconds.try_into()?);
+    let duration_text = format_duration(duration, settings.print.format_duration);
+    match project.filter(|x| !x.is_empty()) {
+        Some(project) => Ok(format!("{} {}", duration_text, project)),
+        None => Ok(duration_text),
+    }
+}
+
+/// Print a single line summary for a shell prompt (or tmux status
+/// bar), re-using a recently cached summary when possible.
+pub fn print_prompt_segment(settings: &PrintAppSettings) -> Result<()> {
+    let database_file_path = match get_database_file_path(
+        &settings.core.database_dir,
+        &settings.core.database_file_name,
+    ) {
+        Some(value) => value,
+        None => bail!(
+            "Database file {:?} not found in {:?}",
+            &settings.core.database_file_name,
+            &settings.core.database_dir
+        ),
+    };
+
+    let cache_seconds = settings.print.prompt_segment.cache_seconds;
+    let cache_file_path = get_cache_file_path().filter(|_| cache_seconds > 0);
+    if let Some(cache_file_path) = &cache_file_path {
+        if let Some(prompt_segment) =
+            read_cached_prompt_segment(cache_file_path, &database_file_path, cache_seconds)
+        {
+            println!("{}", prompt_segment);
+            return Ok(());
+        }
+    }
+
+    let prompt_segment = generate_prompt_segment(&database_file_path, settings)?;
+    println!("{}", prompt_segment);
+
+    if let Some(cache_file_path) = &cache_file_path {
+        if let Err(err) =
+            write_cached_prompt_segment(cache_file_path, &database_file_path, &prompt_segment)
+        {
+            debug!("Could not write prompt segment cache file: {:?}", err);
+        }
+    }
+
+    Ok(())
+}
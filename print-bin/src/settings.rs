@@ -1,16 +1,43 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use config::ConfigError;
 use serde_derive::Deserialize;
 use timetracker_core::format::color_mode_to_use_color;
 use timetracker_core::format::ColorMode;
 use timetracker_core::format::DateTimeFormat;
 use timetracker_core::format::DurationFormat;
+use timetracker_core::format::FirstDayOfWeek;
+use timetracker_core::format::Granularity;
+use timetracker_core::format::SortBy;
 use timetracker_core::settings::new_core_settings;
 use timetracker_core::settings::new_print_settings;
+use timetracker_core::settings::new_sync_settings;
 use timetracker_core::settings::validate_core_settings;
 use timetracker_core::settings::CoreSettings;
 use timetracker_core::settings::PrintSettings;
-use timetracker_core::terminal_supports_color;
+use timetracker_core::settings::SyncSettings;
+use timetracker_core::term;
+
+/// The formats the presets can be printed as.
+#[derive(Debug, Copy, Clone, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    /// Lines of (human-readable) text.
+    Text,
+
+    /// Comma-separated values, with a line for the duration of each
+    /// value of each preset and period.
+    Csv,
+
+    /// A JSON array, with an object for the duration of each value of
+    /// each preset and period.
+    Json,
+
+    /// A Markdown table for each preset and period.
+    Markdown,
+
+    /// A standalone HTML page, with a table for each preset and
+    /// period, and bar charts of the "Activity" presets.
+    Html,
+}
 
 #[derive(Parser, Debug)]
 #[clap(author = "David Cattermole, Copyright 2023-2024", version, about)]
@@ -25,10 +52,107 @@ pub struct CommandArguments {
     #[clap(short = 'w', long, value_parser, default_value_t = 0)]
     pub relative_week: i32,
 
+    /// Compare the printed week to another relative week (such as
+    /// '-2'), with the durations of the "Software" and "Variables"
+    /// presets of both weeks side by side.
+    #[clap(
+        long,
+        value_parser,
+        allow_hyphen_values = true,
+        conflicts_with_all = &["from", "granularity", "inspect", "output-format", "show-idle", "template", "timeline"]
+    )]
+    pub against: Option<i32>,
+
+    /// The first date to print, in "YYYY-MM-DD" format, instead of
+    /// a week. Used with '--to' to print an arbitrary date range.
+    #[clap(long, value_parser, conflicts_with_all = &["last-week", "relative-week"])]
+    pub from: Option<String>,
+
+    /// The last date to print (inclusive), in "YYYY-MM-DD" format.
+    /// Defaults to today.
+    #[clap(long, value_parser, requires = "from")]
+    pub to: Option<String>,
+
+    /// Only use the entries of an executable. May be given more than
+    /// once, to use the entries of any of the executables.
+    #[clap(long = "executable", value_parser)]
+    pub executables: Vec<String>,
+
+    /// Only use the entries with a variable value, as "NAME=VALUE",
+    /// such as "PROJECT=rocket" or "hostname=workstation". May be
+    /// given more than once, to match all the values.
+    #[clap(long = "variable", value_parser)]
+    pub variables: Vec<String>,
+
+    /// Print the presets once for each day, week or month of the
+    /// displayed range, as one report with a section per period.
+    #[clap(
+        long,
+        value_enum,
+        conflicts_with_all = &["inspect", "show-idle", "template", "timeline"]
+    )]
+    pub granularity: Option<Granularity>,
+
+    /// Print the durations of the presets as text, or as rows of a
+    /// machine-readable format (such as for spreadsheets). Only the
+    /// "Software", "Variables", "WindowTitle", "WorkingDirectory"
+    /// and "DocumentPath" presets have rows, and the HTML report also
+    /// has bar charts of the "Activity" presets.
+    #[clap(
+        long,
+        value_enum,
+        default_value_t = OutputFormat::Text,
+        conflicts_with_all = &["inspect", "show-idle", "template", "timeline"]
+    )]
+    pub output_format: OutputFormat,
+
+    /// Write the '--output-format' output to this file, rather than
+    /// to the terminal, such as "report.html".
+    #[clap(short = 'o', long, value_parser, requires = "output-format")]
+    pub output: Option<String>,
+
     /// Which presets to print with?
     #[clap(short = 'p', long, value_parser)]
     pub presets: Option<Vec<String>>,
 
+    /// List the raw entries inside a block of time in the week,
+    /// such as "Tue 14:00-15:00", instead of printing presets.
+    #[clap(long, value_parser, conflicts_with = "show-idle")]
+    pub inspect: Option<String>,
+
+    /// List the periods of idle time (such as meetings away from the
+    /// computer), instead of printing presets. Only periods at least
+    /// 'print.utilization.idle_threshold_seconds' long are listed.
+    #[clap(
+        long,
+        value_parser,
+        default_value_t = false,
+        conflicts_with_all = &["template", "timeline"]
+    )]
+    pub show_idle: bool,
+
+    /// Render the report of the week with a Tera template file,
+    /// instead of printing presets. HTML and XML templates are
+    /// escaped automatically.
+    #[clap(long, value_parser)]
+    pub template: Option<String>,
+
+    /// Open an interactive timeline of the week, to scrub through
+    /// the time blocks of each day.
+    #[clap(long, value_parser, default_value_t = false)]
+    pub timeline: bool,
+
+    /// Print a single line summary of today (the total active time
+    /// and current project), for a shell prompt or tmux status bar.
+    #[clap(long, value_parser, default_value_t = false)]
+    pub prompt_segment: bool,
+
+    /// Fail when a preset is unknown, has no 'print_type', or uses a
+    /// variable that is not recorded, instead of warning. Useful to
+    /// verify a shared configuration file.
+    #[clap(long, value_parser, default_value_t = false)]
+    pub strict: bool,
+
     /// List all available preset names.
     #[clap(long, value_parser, default_value_t = false)]
     pub list_presets: bool,
@@ -41,11 +165,27 @@ pub struct CommandArguments {
     #[clap(long, value_enum)]
     pub format_duration: Option<DurationFormat>,
 
-    /// Show colored text?
+    /// Print the durations of the "Software" and "Variables" presets
+    /// by name, or by the longest duration first.
+    #[clap(long, value_enum)]
+    pub sort_by: Option<SortBy>,
+
+    /// The day each week starts on, overriding
+    /// 'core.first_day_of_week'.
+    #[clap(long, value_enum)]
+    pub first_day_of_week: Option<FirstDayOfWeek>,
+
+    /// Show colored text? "auto" uses color only when printing to a
+    /// terminal that supports color.
     // Similar to 'git diff --color' flag.
     #[clap(long, value_enum)]
     pub color: Option<ColorMode>,
 
+    /// Read the entries from the sync server ('sync.host'), such as
+    /// the entries of all the computers recording to the server.
+    #[clap(long, value_parser, default_value_t = false)]
+    pub remote: bool,
+
     /// Override the directory to search for the database file.
     #[clap(long, value_parser)]
     pub database_dir: Option<String>,
@@ -60,6 +200,7 @@ pub struct CommandArguments {
 pub struct PrintAppSettings {
     pub core: CoreSettings,
     pub print: PrintSettings,
+    pub sync: SyncSettings,
 }
 
 impl PrintAppSettings {
@@ -69,22 +210,33 @@ impl PrintAppSettings {
             arguments.database_file_name.clone(),
             false,
         )?;
-        let mut builder = new_print_settings(builder)?;
+        let builder = new_print_settings(builder)?;
+        let mut builder = new_sync_settings(builder)?;
 
         // Use command line 'arguments' to override the default
         // values. These will always override any configuration file
         // or environment variable.
-        let supports_color = terminal_supports_color();
-        let use_color = color_mode_to_use_color(arguments.color, supports_color, supports_color);
         builder = builder
             .set_override_option("print.display_presets", arguments.presets.clone())?
             .set_override_option("print.format_datetime", arguments.format_datetime)?
             .set_override_option("print.format_duration", arguments.format_duration)?
-            .set_override_option("print.use_color", Some(use_color))?;
+            .set_override_option("print.sort_by", arguments.sort_by)?
+            .set_override_option("core.first_day_of_week", arguments.first_day_of_week)?
+            .set_override_option("print.strict", arguments.strict.then_some(true))?;
 
-        let settings: Self = builder.build()?.try_deserialize()?;
+        let mut settings: Self = builder.build()?.try_deserialize()?;
         validate_core_settings(&settings.core).unwrap();
 
+        // Without '--color', the 'print.use_color' setting is only
+        // used when the output is a terminal supporting color, so
+        // redirecting the output to a file has no color codes.
+        let supports_color = term::supports_color();
+        settings.print.use_color = color_mode_to_use_color(
+            arguments.color,
+            supports_color,
+            settings.print.use_color && supports_color,
+        );
+
         Ok(settings)
     }
 }
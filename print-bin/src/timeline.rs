@@ -0,0 +1,290 @@
+use crate::settings::PrintAppSettings;
+use anyhow::Result;
+use crossterm::cursor;
+use crossterm::event;
+use crossterm::event::Event;
+use crossterm::event::KeyCode;
+use crossterm::event::KeyEventKind;
+use crossterm::queue;
+use crossterm::style;
+use crossterm::terminal;
+use std::io::Write;
+use timetracker_core::format::format_date;
+use timetracker_core::format::format_duration;
+use timetracker_core::format::format_time_no_seconds;
+use timetracker_core::storage::Entries;
+use timetracker_print_lib::datetime::get_weekdays_datetime_local;
+use timetracker_print_lib::timeline::generate_timeline_block_lines;
+use timetracker_print_lib::timeline::generate_timeline_blocks;
+use timetracker_print_lib::timeline::TimelineBlock;
+
+const HELP_TEXT: &str = "Left/Right: day | Up/Down: block | PgUp/PgDn: jump | q: quit";
+const BAR_WIDTH: usize = 10;
+const BAR_CHARACTER: &str = "#";
+const PANEL_SEPARATOR: &str = " | ";
+
+struct TimelineDay {
+    heading: String,
+    blocks: Vec<TimelineBlock>,
+}
+
+struct TimelineState {
+    days: Vec<TimelineDay>,
+    day_index: usize,
+    block_index: usize,
+}
+
+impl TimelineState {
+    fn day(&self) -> &TimelineDay {
+        &self.days[self.day_index]
+    }
+
+    fn move_day(&mut self, offset: isize) {
+        let last = self.days.len() as isize - 1;
+        self.day_index = (self.day_index as isize + offset).clamp(0, last) as usize;
+        self.clamp_block_index();
+    }
+
+    fn move_block(&mut self, offset: isize) {
+        let last = self.day().blocks.len() as isize - 1;
+        self.block_index = (self.block_index as isize + offset).clamp(0, last.max(0)) as usize;
+    }
+
+    fn clamp_block_index(&mut self) {
+        let count = self.day().blocks.len();
+        self.block_index = std::cmp::min(self.block_index, count.saturating_sub(1));
+    }
+}
+
+fn generate_days(entries: &Entries, settings: &PrintAppSettings) -> Vec<TimelineDay> {
+    let mut days = Vec::new();
+    for (weekday, day_datetime_pair) in
+        get_weekdays_datetime_local(entries.start_datetime(), entries.end_datetime())
+    {
+        let blocks = generate_timeline_blocks(
+            entries,
+            day_datetime_pair,
+            settings.print.time_block_unit,
+            &settings.core.environment_variables.names,
+            settings.print.format_duration,
+        );
+        let heading = format!(
+            "{} {}",
+            weekday,
+            format_date(day_datetime_pair.0, settings.print.format_datetime)
+        );
+        days.push(TimelineDay { heading, blocks });
+    }
+    days
+}
+
+/// Start on the first block with activity of today, or the first day
+/// with any activity.
+fn initial_state(days: Vec<TimelineDay>) -> TimelineState {
+    let today = chrono::Local::now().date_naive();
+    let has_activity = |day: &TimelineDay| day.blocks.iter().any(|x| x.duration.num_seconds() > 0);
+    let day_index = days
+        .iter()
+        .position(|day| {
+            day.blocks
+                .first()
+                .is_some_and(|x| x.start_datetime.date_naive() == today)
+                && has_activity(day)
+        })
+        .or_else(|| days.iter().position(has_activity))
+        .unwrap_or(0);
+    let block_index = days[day_index]
+        .blocks
+        .iter()
+        .position(|x| x.duration.num_seconds() > 0)
+        .unwrap_or(0);
+
+    TimelineState {
+        days,
+        day_index,
+        block_index,
+    }
+}
+
+fn timeline_block_text(block: &TimelineBlock, settings: &PrintAppSettings) -> String {
+    let filled = (block.active_ratio() * BAR_WIDTH as f32).round() as usize;
+    let bar = format!(
+        "{}{}",
+        BAR_CHARACTER.repeat(filled),
+        " ".repeat(BAR_WIDTH - filled)
+    );
+    format!(
+        "{} |{}| {}",
+        format_time_no_seconds(block.start_datetime, settings.print.format_datetime),
+        bar,
+        format_duration(block.duration, settings.print.format_duration)
+    )
+}
+
+fn truncate_text(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}
+
+fn draw<W: Write>(
+    writer: &mut W,
+    state: &TimelineState,
+    settings: &PrintAppSettings,
+) -> Result<()> {
+    let (width, height) = terminal::size()?;
+    let (width, height) = (width as usize, height as usize);
+
+    queue!(writer, terminal::Clear(terminal::ClearType::All))?;
+
+    let day = state.day();
+    let heading = format!(
+        "{} ({}/{})",
+        day.heading,
+        state.day_index + 1,
+        state.days.len()
+    );
+    queue!(
+        writer,
+        cursor::MoveTo(0, 0),
+        style::SetAttribute(style::Attribute::Bold),
+        style::Print(truncate_text(&heading, width)),
+        style::SetAttribute(style::Attribute::Reset),
+    )?;
+
+    // Rows for the heading, a blank line and the help text.
+    let list_height = height.saturating_sub(3).max(1);
+    let scroll = match state.block_index >= list_height {
+        true => state.block_index + 1 - list_height,
+        false => 0,
+    };
+
+    let block_texts: Vec<String> = day
+        .blocks
+        .iter()
+        .map(|block| timeline_block_text(block, settings))
+        .collect();
+    let list_width = block_texts
+        .iter()
+        .map(|x| x.chars().count())
+        .max()
+        .unwrap_or(0);
+    let panel_width = width.saturating_sub(list_width + PANEL_SEPARATOR.len());
+
+    let panel_lines = match day.blocks.get(state.block_index) {
+        Some(block) => generate_timeline_block_lines(
+            block,
+            settings.print.format_datetime,
+            settings.print.format_duration,
+        ),
+        None => vec!["No time blocks.".to_string()],
+    };
+
+    for row in 0..list_height {
+        let y = (row + 2) as u16;
+        queue!(writer, cursor::MoveTo(0, y))?;
+
+        let block_index = row + scroll;
+        if let Some(text) = block_texts.get(block_index) {
+            let text = format!("{:<width$}", text, width = list_width);
+            let text = truncate_text(&text, width);
+            if block_index == state.block_index {
+                queue!(
+                    writer,
+                    style::SetAttribute(style::Attribute::Reverse),
+                    style::Print(text),
+                    style::SetAttribute(style::Attribute::Reset),
+                )?;
+            } else {
+                queue!(writer, style::Print(text))?;
+            }
+        } else {
+            queue!(writer, style::Print(" ".repeat(list_width.min(width))))?;
+        }
+
+        if let Some(line) = panel_lines.get(row) {
+            if panel_width > 0 {
+                queue!(
+                    writer,
+                    style::Print(PANEL_SEPARATOR),
+                    style::Print(truncate_text(line, panel_width)),
+                )?;
+            }
+        }
+    }
+
+    queue!(
+        writer,
+        cursor::MoveTo(0, height.saturating_sub(1) as u16),
+        style::Print(truncate_text(HELP_TEXT, width)),
+    )?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn run_event_loop<W: Write>(
+    writer: &mut W,
+    state: &mut TimelineState,
+    settings: &PrintAppSettings,
+) -> Result<()> {
+    let page_size = 8;
+    loop {
+        draw(writer, state, settings)?;
+        match event::read()? {
+            Event::Key(key) if key.kind != KeyEventKind::Release => match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => break,
+                KeyCode::Left | KeyCode::Char('h') => state.move_day(-1),
+                KeyCode::Right | KeyCode::Char('l') => state.move_day(1),
+                KeyCode::Up | KeyCode::Char('k') => state.move_block(-1),
+                KeyCode::Down | KeyCode::Char('j') => state.move_block(1),
+                KeyCode::PageUp => state.move_block(-page_size),
+                KeyCode::PageDown => state.move_block(page_size),
+                KeyCode::Home => state.block_index = 0,
+                KeyCode::End => state.move_block(isize::MAX / 2),
+                _ => (),
+            },
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+/// Puts the terminal into raw mode on the alternate screen, and
+/// restores the terminal when dropped, so the terminal is restored
+/// after any error (or panic) while the timeline is shown.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> Result<TerminalGuard> {
+        terminal::enable_raw_mode()?;
+        // Created once raw mode is enabled, so a failure to enter the
+        // alternate screen still disables raw mode.
+        let terminal_guard = TerminalGuard;
+        let mut stdout = std::io::stdout();
+        queue!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+        stdout.flush()?;
+        Ok(terminal_guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        // Errors are ignored; there is nothing more to do when the
+        // terminal cannot be restored.
+        let mut stdout = std::io::stdout();
+        let _ = queue!(stdout, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = stdout.flush();
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Run an interactive terminal timeline of the week's entries, where
+/// the arrow keys scrub through the time blocks of each day and a
+/// side panel shows the executables and variables of the highlighted
+/// block.
+pub fn run_timeline(entries: &Entries, settings: &PrintAppSettings) -> Result<()> {
+    let days = generate_days(entries, settings);
+    let mut state = initial_state(days);
+
+    let _terminal_guard = TerminalGuard::enter()?;
+    let mut stdout = std::io::stdout();
+    run_event_loop(&mut stdout, &mut state, settings)
+}
@@ -0,0 +1,112 @@
+use gtk::cairo;
+use gtk::prelude::*;
+use gtk::DrawingArea;
+use timetracker_print_lib::chart::StackedBarChart;
+
+const CHART_LABEL_WIDTH: f64 = 130.0;
+const CHART_TOTAL_WIDTH: f64 = 80.0;
+const CHART_MAX_ROW_HEIGHT: f64 = 26.0;
+const CHART_ROW_SPACING: f64 = 4.0;
+const CHART_LEGEND_HEIGHT: f64 = 24.0;
+const CHART_LEGEND_SWATCH_SIZE: f64 = 12.0;
+const CHART_FONT_SIZE: f64 = 12.0;
+
+fn set_source_color(context: &cairo::Context, color: u32) {
+    let red = ((color >> 16) & 0xff) as f64 / 255.0;
+    let green = ((color >> 8) & 0xff) as f64 / 255.0;
+    let blue = (color & 0xff) as f64 / 255.0;
+    context.set_source_rgb(red, green, blue);
+}
+
+/// Draw text vertically centered on 'y'.
+fn draw_text(context: &cairo::Context, text: &str, x: f64, y: f64) -> Result<(), cairo::Error> {
+    let extents = context.text_extents(text)?;
+    context.move_to(x, y - (extents.height() / 2.0) - extents.y_bearing());
+    context.show_text(text)
+}
+
+/// Draw a horizontal stacked bar for each day of the chart, with a
+/// legend of the categories below the bars.
+pub fn draw_stacked_bar_chart(
+    drawing_area: &DrawingArea,
+    context: &cairo::Context,
+    chart: Option<&StackedBarChart>,
+) -> Result<(), cairo::Error> {
+    let width = drawing_area.allocated_width() as f64;
+    let height = drawing_area.allocated_height() as f64;
+
+    // Use the text color of the theme, so the chart is readable with
+    // light and dark themes.
+    let text_color = drawing_area.style_context().color(gtk::StateFlags::NORMAL);
+    let set_text_color = || {
+        context.set_source_rgba(
+            text_color.red(),
+            text_color.green(),
+            text_color.blue(),
+            text_color.alpha(),
+        )
+    };
+    context.set_font_size(CHART_FONT_SIZE);
+
+    let chart = match chart {
+        Some(value) if !value.bars.is_empty() && value.max_active_seconds > 0 => value,
+        _ => {
+            set_text_color();
+            return draw_text(context, "No activity recorded.", 0.0, height / 2.0);
+        }
+    };
+
+    let bar_width = (width - CHART_LABEL_WIDTH - CHART_TOTAL_WIDTH).max(1.0);
+    let row_count = chart.bars.len() as f64;
+    let row_height = ((height - CHART_LEGEND_HEIGHT) / row_count).min(CHART_MAX_ROW_HEIGHT);
+    for (row_index, bar) in chart.bars.iter().enumerate() {
+        let y = row_index as f64 * row_height;
+        let center_y = y + (row_height / 2.0);
+
+        set_text_color();
+        draw_text(
+            context,
+            &format!("{} {}", bar.weekday, bar.date),
+            0.0,
+            center_y,
+        )?;
+
+        let mut x = CHART_LABEL_WIDTH;
+        for segment in &bar.segments {
+            let segment_width = (segment.percentage / 100.0) * bar_width;
+            set_source_color(context, chart.categories[segment.category_index].color);
+            context.rectangle(
+                x,
+                y + (CHART_ROW_SPACING / 2.0),
+                segment_width,
+                row_height - CHART_ROW_SPACING,
+            );
+            context.fill()?;
+            x += segment_width;
+        }
+
+        set_text_color();
+        draw_text(context, &bar.duration, x + CHART_ROW_SPACING, center_y)?;
+    }
+
+    let legend_y = (row_count * row_height) + (CHART_LEGEND_HEIGHT / 2.0);
+    let mut x = 0.0;
+    for category in &chart.categories {
+        set_source_color(context, category.color);
+        context.rectangle(
+            x,
+            legend_y - (CHART_LEGEND_SWATCH_SIZE / 2.0),
+            CHART_LEGEND_SWATCH_SIZE,
+            CHART_LEGEND_SWATCH_SIZE,
+        );
+        context.fill()?;
+        x += CHART_LEGEND_SWATCH_SIZE + CHART_ROW_SPACING;
+
+        let text = format!("{} ({})", category.name, category.duration);
+        set_text_color();
+        draw_text(context, &text, x, legend_y)?;
+        x += context.text_extents(&text)?.x_advance() + (CHART_ROW_SPACING * 4.0);
+    }
+
+    Ok(())
+}
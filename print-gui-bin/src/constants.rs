@@ -1,10 +1,19 @@
 pub const APPLICATION_ID: &str = "org.timetracker.print-gui";
-pub const MAIN_WINDOW_GLADE: &'static str = include_str!("main_window.glade");
+pub const MAIN_WINDOW_GLADE: &str = include_str!("main_window.glade");
 
 pub const WINDOW_TITLE: &str = "Timetracker Print GUI";
 pub const WINDOW_DEFAULT_WIDTH: i32 = 1260;
 pub const WINDOW_DEFAULT_HEIGHT: i32 = 800;
 
+pub const PREFERENCES_DIALOG_TITLE: &str = "Timetracker Preferences";
+
+// The range of font sizes (in points) of the report text.
+pub const REPORT_FONT_SIZE_MIN: u32 = 6;
+pub const REPORT_FONT_SIZE_MAX: u32 = 48;
+
+// The CSS class of the report text view, styled by 'report_css'.
+pub const REPORT_CSS_CLASS: &str = "report";
+
 // Follows the ISO8601 standard.
 pub const DATETIME_FORMAT_ISO_ID: &str = "DateTimeFormat::Iso";
 pub const DATETIME_FORMAT_ISO_LABEL: &str = "ISO (ISO8601 standard)";
@@ -28,3 +37,44 @@ pub const DURATION_FORMAT_HOURS_MINUTES_SECONDS_LABEL: &str = "Hours Minutes Sec
 // Hours as decimal number rounded to 6 minute increments.
 pub const DURATION_FORMAT_DECIMAL_HOURS_ID: &str = "DurationFormat::DecimalHours";
 pub const DURATION_FORMAT_DECIMAL_HOURS_LABEL: &str = "Decimal Hours (12.5)";
+
+// The length of the displayed period of time.
+pub const DISPLAY_PERIOD_WEEK_ID: &str = "DisplayPeriod::Week";
+pub const DISPLAY_PERIOD_WEEK_LABEL: &str = "Week";
+pub const DISPLAY_PERIOD_MONTH_ID: &str = "DisplayPeriod::Month";
+pub const DISPLAY_PERIOD_MONTH_LABEL: &str = "Month";
+pub const DISPLAY_PERIOD_QUARTER_ID: &str = "DisplayPeriod::Quarter";
+pub const DISPLAY_PERIOD_QUARTER_LABEL: &str = "Quarter";
+
+// The length of each block of time, of "Activity" presets.
+pub const TIME_BLOCK_UNIT_FIVE_MINUTES_ID: &str = "TimeBlockUnit::FiveMinutes";
+pub const TIME_BLOCK_UNIT_FIVE_MINUTES_LABEL: &str = "5 Minutes";
+pub const TIME_BLOCK_UNIT_TEN_MINUTES_ID: &str = "TimeBlockUnit::TenMinutes";
+pub const TIME_BLOCK_UNIT_TEN_MINUTES_LABEL: &str = "10 Minutes";
+pub const TIME_BLOCK_UNIT_FIFTEEN_MINUTES_ID: &str = "TimeBlockUnit::FifteenMinutes";
+pub const TIME_BLOCK_UNIT_FIFTEEN_MINUTES_LABEL: &str = "15 Minutes";
+pub const TIME_BLOCK_UNIT_THIRTY_MINUTES_ID: &str = "TimeBlockUnit::ThirtyMinutes";
+pub const TIME_BLOCK_UNIT_THIRTY_MINUTES_LABEL: &str = "30 Minutes";
+pub const TIME_BLOCK_UNIT_SIXTY_MINUTES_ID: &str = "TimeBlockUnit::SixtyMinutes";
+pub const TIME_BLOCK_UNIT_SIXTY_MINUTES_LABEL: &str = "60 Minutes";
+
+// The drag and drop target of the preset buttons; the dragged data
+// is the preset name.
+pub const PRESET_DRAG_TARGET_NAME: &str = "timetracker/preset-name";
+
+// Increase the version when the privacy notice changes, so that the
+// user is asked for consent again.
+pub const PRIVACY_NOTICE_VERSION: u32 = 1;
+pub const PRIVACY_NOTICE_TITLE: &str = "Timetracker Privacy Notice";
+pub const PRIVACY_NOTICE_TEXT: &str = "\
+Timetracker records how you use this computer, so you can see where your time was spent.
+
+Every few seconds the recorder stores:
+ - whether you are active or idle (not what you type or click),
+ - the name of the active (focused) program,
+ - the values of the configured environment variables of that program,
+ - the version of the program, and when the machine is busy.
+
+The data is stored only on this computer, in a database file that only you can read. \
+Nothing is sent over the network. Data is kept until you delete it; use the purge \
+buttons in the 'Privacy' section to delete the last hour, the last day or all data.";
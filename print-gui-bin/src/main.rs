@@ -17,10 +17,15 @@ use log::debug;
 use std::cell::RefCell;
 use std::rc::Rc;
 
+mod chart;
 mod constants;
 mod main_window;
+mod preferences;
+mod privacy;
 mod settings;
+mod style;
 mod utils;
+mod worker;
 
 fn main() -> Result<()> {
     let env = env_logger::Env::default()
@@ -1,3 +1,4 @@
+use crate::chart::draw_stacked_bar_chart;
 use crate::constants;
 use crate::constants::DATETIME_FORMAT_ISO_ID;
 use crate::constants::DATETIME_FORMAT_ISO_LABEL;
@@ -5,43 +6,81 @@ use crate::constants::DATETIME_FORMAT_LOCALE_ID;
 use crate::constants::DATETIME_FORMAT_LOCALE_LABEL;
 use crate::constants::DATETIME_FORMAT_USA_MONTH_DAY_YEAR_ID;
 use crate::constants::DATETIME_FORMAT_USA_MONTH_DAY_YEAR_LABEL;
+use crate::constants::DISPLAY_PERIOD_MONTH_ID;
+use crate::constants::DISPLAY_PERIOD_MONTH_LABEL;
+use crate::constants::DISPLAY_PERIOD_QUARTER_ID;
+use crate::constants::DISPLAY_PERIOD_QUARTER_LABEL;
+use crate::constants::DISPLAY_PERIOD_WEEK_ID;
+use crate::constants::DISPLAY_PERIOD_WEEK_LABEL;
 use crate::constants::DURATION_FORMAT_DECIMAL_HOURS_ID;
 use crate::constants::DURATION_FORMAT_DECIMAL_HOURS_LABEL;
 use crate::constants::DURATION_FORMAT_HOURS_MINUTES_ID;
 use crate::constants::DURATION_FORMAT_HOURS_MINUTES_LABEL;
 use crate::constants::DURATION_FORMAT_HOURS_MINUTES_SECONDS_ID;
 use crate::constants::DURATION_FORMAT_HOURS_MINUTES_SECONDS_LABEL;
+use crate::constants::PRIVACY_NOTICE_TEXT;
+use crate::constants::PRIVACY_NOTICE_TITLE;
+use crate::preferences::run_preferences_dialog;
+use crate::preferences::Preferences;
+use crate::privacy::has_privacy_consent;
+use crate::privacy::purge_data;
+use crate::privacy::write_privacy_consent;
+use crate::privacy::PurgeRange;
 use crate::settings::PrintGuiAppSettings;
+use crate::style::apply_bar_graph_tag;
+use crate::style::bar_graph_color;
+use crate::style::report_css;
 use crate::utils::datetime_format_as_id;
+use crate::utils::display_period_as_id;
 use crate::utils::duration_format_as_id;
-use crate::utils::get_absolute_week_start_end;
+use crate::utils::get_absolute_period_start_end;
 use crate::utils::id_as_datetime_format;
+use crate::utils::id_as_display_period;
 use crate::utils::id_as_duration_format;
+use crate::utils::DisplayPeriod;
+use crate::worker::create_display_presets;
+use crate::worker::find_cached_entries;
+use crate::worker::spawn_load_period;
+use crate::worker::LoadMessage;
+use crate::worker::LoadRequest;
+use crate::worker::MapPeriodEntries;
 use crate::CommandArguments;
 
 use anyhow::Result;
 use chrono::Datelike;
+use gtk::gdk::{DragAction, ModifierType};
+use gtk::glib;
 use gtk::glib::clone;
 use gtk::prelude::*;
 use gtk::{
-    Application, ApplicationWindow, Box, Builder, ComboBoxText, Label, SpinButton, Statusbar,
-    TextBuffer, TextView, ToggleButton,
+    Application, ApplicationWindow, Box, Builder, Button, ButtonsType, CheckMenuItem, ComboBoxText,
+    CssProvider, DestDefaults, DialogFlags, DrawingArea, FileChooserAction, FileChooserDialog,
+    Label, MenuItem, MessageDialog, MessageType, ResponseType, SpinButton, Spinner, Statusbar,
+    TargetEntry, TargetFlags, TextBuffer, TextTag, TextTagTable, TextView, ToggleButton,
+    ToolButton,
 };
 use log::warn;
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
 use std::rc::Rc;
 use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
 
 use timetracker_core::filesystem::get_database_file_path;
+use timetracker_core::filesystem::get_database_file_path_for_time;
 use timetracker_core::format::format_date;
-use timetracker_core::settings::RECORD_INTERVAL_SECONDS;
-use timetracker_core::storage::Entries;
-use timetracker_core::storage::Storage;
-use timetracker_print_lib::aggregate::get_map_keys_sorted_strings;
+use timetracker_core::settings::find_configuration_file_path_to_write;
+use timetracker_core::settings::find_existing_configuration_file_path;
+use timetracker_core::settings::write_configuration_file_values;
+use timetracker_core::settings::SettingValue;
+use timetracker_core::settings::DEFAULT_GUI_FONT_SIZE;
+use timetracker_print_lib::chart::StackedBarChart;
+use timetracker_print_lib::datetime::get_week_number;
 use timetracker_print_lib::datetime::DateTimeLocalPair;
-use timetracker_print_lib::preset::create_presets;
-use timetracker_print_lib::preset::generate_presets;
+use timetracker_print_lib::rows::generate_presets_rows;
+use timetracker_print_lib::rows::write_rows_csv;
 
 /// What state is a Preset in? A user can toggle the Preset on/off.
 #[derive(Debug, Copy, Clone)]
@@ -53,7 +92,6 @@ pub enum PresetState {
 }
 
 type MapStringPresetState = HashMap<String, PresetState>;
-type MapWeekNumEntries = HashMap<u32, Entries>;
 
 pub struct GlobalState {
     settings: PrintGuiAppSettings,
@@ -61,13 +99,54 @@ pub struct GlobalState {
     preset_states: MapStringPresetState,
     window: Option<ApplicationWindow>,
     status_bar: Option<Statusbar>,
-    week_number_spin_button: Option<SpinButton>,
+    copy_button: Option<ToolButton>,
+    save_text_button: Option<ToolButton>,
+    export_csv_button: Option<ToolButton>,
+    save_layout_button: Option<ToolButton>,
+    preferences_button: Option<ToolButton>,
+    increase_font_size_menu_item: Option<MenuItem>,
+    decrease_font_size_menu_item: Option<MenuItem>,
+    reset_font_size_menu_item: Option<MenuItem>,
+    dark_mode_menu_item: Option<CheckMenuItem>,
+    /// Styles the report text view, with the font size and dark (or
+    /// light) colors of the 'gui' settings.
+    css_provider: CssProvider,
+    /// Colors the bar graph characters of the report text.
+    bar_graph_tag: TextTag,
+    /// Spins while a period is loaded in the worker thread.
+    spinner: Option<Spinner>,
+    /// Sends the periods loaded by the worker thread to
+    /// 'period_loaded'.
+    load_sender: Option<glib::Sender<LoadMessage>>,
+    /// The generation of the last 'LoadRequest'.
+    load_generation: u64,
+    period_number_label: Option<Label>,
+    period_number_spin_button: Option<SpinButton>,
+    year_spin_button: Option<SpinButton>,
+    display_period_combo_box: Option<ComboBoxText>,
     format_date_time_combo_box: Option<ComboBoxText>,
     format_duration_combo_box: Option<ComboBoxText>,
     date_range_label: Option<Label>,
     preset_buttons_layout: Option<Box>,
     text_view: Option<TextView>,
-    week_number: u32,
+    chart_drawing_area: Option<DrawingArea>,
+    /// The chart of the displayed period, shared with the draw
+    /// callback of the chart drawing area.
+    chart: Rc<RefCell<Option<StackedBarChart>>>,
+    privacy_consent_label: Option<Label>,
+    purge_last_hour_button: Option<Button>,
+    purge_last_day_button: Option<Button>,
+    purge_all_button: Option<Button>,
+    open_database_folder_button: Option<Button>,
+    open_config_file_button: Option<Button>,
+    /// The displayed period; the week (or month or quarter) number
+    /// of the year.
+    display_period: DisplayPeriod,
+    period_number: u32,
+    year: i32,
+    /// Set while the period widgets are changed by the program, so
+    /// the changes are not handled (and loaded) one at a time.
+    updating_widgets: bool,
     text_buffer: TextBuffer,
 }
 
@@ -78,7 +157,10 @@ impl GlobalState {
         settings: PrintGuiAppSettings,
         args: &CommandArguments,
     ) -> GlobalState {
-        let text_buffer = TextBuffer::builder().build();
+        let bar_graph_tag = TextTag::new(Some("bar_graph"));
+        let tag_table = TextTagTable::new();
+        tag_table.add(&bar_graph_tag);
+        let text_buffer = TextBuffer::builder().tag_table(&tag_table).build();
 
         let mut preset_states = MapStringPresetState::new();
         for preset_name in &settings.print.display_presets {
@@ -107,44 +189,76 @@ impl GlobalState {
             preset_states.insert(preset_name.clone(), PresetState::Disable);
         }
 
-        // Get the current week as the default value.
-        let today_local_timezone = chrono::Local::now();
-
         // Set the default week based on command line argument flag
-        // logic, and ensure the week number does not go below 1, or
-        // above 52.
-        let current_week = today_local_timezone.iso_week().week();
-        let week_number: u32 = if args.last_week {
-            assert!(current_week != 0);
-            if current_week == 1 {
-                52
-            } else {
-                current_week.checked_sub(1).unwrap()
-            }
-        } else {
-            ((current_week as i32) + args.relative_week).wrapping_rem_euclid(52) as u32
+        // logic. The week may be in the previous (or next) year.
+        let relative_week = match args.last_week {
+            true => -1,
+            false => args.relative_week,
         };
+        let today = chrono::Local::now().date_naive();
+        let (year, week_number) = get_week_number(
+            today + chrono::Duration::weeks(relative_week.into()),
+            settings.core.first_day_of_week,
+        );
 
         GlobalState {
-            settings: settings,
-            all_preset_names: all_preset_names,
-            preset_states: preset_states,
+            settings,
+            all_preset_names,
+            preset_states,
             window: None,
             status_bar: None,
-            week_number_spin_button: None,
+            copy_button: None,
+            save_text_button: None,
+            export_csv_button: None,
+            save_layout_button: None,
+            preferences_button: None,
+            increase_font_size_menu_item: None,
+            decrease_font_size_menu_item: None,
+            reset_font_size_menu_item: None,
+            dark_mode_menu_item: None,
+            css_provider: CssProvider::new(),
+            bar_graph_tag,
+            spinner: None,
+            load_sender: None,
+            load_generation: 0,
+            period_number_label: None,
+            period_number_spin_button: None,
+            year_spin_button: None,
+            display_period_combo_box: None,
             format_date_time_combo_box: None,
             format_duration_combo_box: None,
             date_range_label: None,
             preset_buttons_layout: None,
             text_view: None,
-            week_number: week_number,
-            text_buffer: text_buffer,
+            chart_drawing_area: None,
+            chart: Rc::new(RefCell::new(None)),
+            privacy_consent_label: None,
+            purge_last_hour_button: None,
+            purge_last_day_button: None,
+            purge_all_button: None,
+            open_database_folder_button: None,
+            open_config_file_button: None,
+            display_period: DisplayPeriod::Week,
+            period_number: week_number,
+            year,
+            updating_widgets: false,
+            text_buffer,
         }
     }
+
+    /// The first and last datetime of the displayed period.
+    fn period_datetime_pair(&self) -> Result<DateTimeLocalPair> {
+        get_absolute_period_start_end(
+            self.display_period,
+            self.year,
+            self.period_number,
+            self.settings.core.first_day_of_week,
+        )
+    }
 }
 
 pub struct GlobalEntries {
-    map: MapWeekNumEntries,
+    map: MapPeriodEntries,
 }
 
 pub type GlobalEntriesRcRefCell = Rc<RefCell<GlobalEntries>>;
@@ -152,91 +266,20 @@ pub type GlobalEntriesRcRefCell = Rc<RefCell<GlobalEntries>>;
 impl GlobalEntries {
     pub fn new() -> GlobalEntries {
         GlobalEntries {
-            map: MapWeekNumEntries::new(),
-        }
-    }
-}
-
-/// Fetch the Storage entries we will need for a given week, and cache
-/// it for reuse. This ensures we never fetch the same data from the
-/// database twice (while the GUI is running).
-///
-/// Currently, to clear the cache, the program must be restarted.
-///
-/// This optimisation assumes that fetching data from the database is
-/// likely the slowest runtime (which it almost always is, unless a
-/// trivial database entry is used).
-fn query_and_cache_entries(
-    week_number: u32,
-    week_datetime_pair: DateTimeLocalPair,
-    database_dir: &String,
-    database_file_name: &String,
-    entries_cache: &mut MapWeekNumEntries,
-) -> Result<Entries> {
-    match entries_cache.get(&week_number) {
-        Some(week_entries) => Ok(week_entries.clone()),
-        None => {
-            let database_file_path = get_database_file_path(database_dir, database_file_name);
-            if !database_file_path.is_some() {
-                warn!(
-                    "Database file {:?} not found in {:?}",
-                    database_file_name, database_dir
-                );
-            }
-
-            let mut storage = Storage::open_as_read_only(
-                &database_file_path.expect("Database file path should be valid"),
-                RECORD_INTERVAL_SECONDS,
-            )?;
-
-            let (week_start_datetime, week_end_datetime) = week_datetime_pair;
-            let week_start_of_time = week_start_datetime.timestamp() as u64;
-            let week_end_of_time = week_end_datetime.timestamp() as u64;
-
-            let week_entries = storage.read_entries(week_start_of_time, week_end_of_time)?;
-            entries_cache.insert(week_number, week_entries.clone());
-
-            Ok(week_entries)
+            map: MapPeriodEntries::new(),
         }
     }
 }
 
-fn generate_text(week_entries: &Entries, settings: &PrintGuiAppSettings) -> Result<String> {
-    let (presets, missing_preset_names) = create_presets(
-        settings.print.time_scale,
-        settings.print.format_datetime,
-        settings.print.format_duration,
-        settings.print.time_block_unit,
-        settings.print.bar_graph_character_num_width,
-        settings.print.use_color,
-        &settings.core.environment_variables.names,
-        &settings.print.display_presets,
-        &settings.print.presets,
-    )?;
-
-    let lines = generate_presets(&presets, &week_entries)?;
-    let all_lines_text = lines.join("\n");
-
-    if !missing_preset_names.is_empty() {
-        let all_preset_names = get_map_keys_sorted_strings(&settings.print.presets.keys());
-        warn!(
-            "Preset names {:?} are invalid. possible preset names are: {:?}",
-            missing_preset_names, all_preset_names,
-        );
-    }
-
-    Ok(all_lines_text)
-}
-
 fn update_date_range_label(
     date_range_label: &Label,
-    week_datetime_pair: DateTimeLocalPair,
+    period_datetime_pair: DateTimeLocalPair,
     settings: &PrintGuiAppSettings,
 ) -> Result<()> {
     let date_range_string = format!(
         "Date from {} to {}",
-        format_date(week_datetime_pair.0, settings.print.format_datetime),
-        format_date(week_datetime_pair.1, settings.print.format_datetime),
+        format_date(period_datetime_pair.0, settings.print.format_datetime),
+        format_date(period_datetime_pair.1, settings.print.format_datetime),
     )
     .to_string();
     date_range_label.set_text(&date_range_string);
@@ -244,81 +287,238 @@ fn update_date_range_label(
     Ok(())
 }
 
-fn update_text_view(
-    entries: &Entries,
-    status_bar: &Statusbar,
-    text_buffer: &TextBuffer,
-    settings: &PrintGuiAppSettings,
+/// Start loading the entries, text and chart of the displayed period
+/// in the worker thread, showing the spinner until 'period_loaded' is
+/// called with the result.
+fn start_loading_period(
+    borrowed_state: &mut GlobalState,
+    borrowed_entries: &GlobalEntries,
 ) -> Result<()> {
-    let context_id = status_bar.context_id("update_text_view");
+    let period_datetime_pair = borrowed_state.period_datetime_pair()?;
+    let cached_entries = find_cached_entries(
+        period_datetime_pair,
+        &borrowed_state.settings,
+        &borrowed_entries.map,
+    )?;
+
+    borrowed_state.load_generation += 1;
+    let request = LoadRequest {
+        generation: borrowed_state.load_generation,
+        period_datetime_pair,
+        cached_entries,
+        settings: borrowed_state.settings.clone(),
+    };
 
+    let status_bar = borrowed_state.status_bar.as_ref().unwrap();
+    let context_id = status_bar.context_id("update_text_view");
     let msg = format!(
-        "Generating data from {} to {}...",
-        format_date(entries.start_datetime(), settings.print.format_datetime),
-        format_date(entries.end_datetime(), settings.print.format_datetime),
-    )
-    .to_string();
+        "Loading data from {} to {}...",
+        format_date(
+            period_datetime_pair.0,
+            borrowed_state.settings.print.format_datetime
+        ),
+        format_date(
+            period_datetime_pair.1,
+            borrowed_state.settings.print.format_datetime
+        ),
+    );
     status_bar.push(context_id, &msg);
+    borrowed_state.spinner.as_ref().unwrap().start();
+
+    let sender = borrowed_state.load_sender.as_ref().unwrap().clone();
+    spawn_load_period(request, sender);
+
+    Ok(())
+}
+
+/// When the worker thread has loaded a period, display the text and
+/// chart, unless a newer period has been requested since.
+fn period_loaded(
+    message: LoadMessage,
+    global_state: GlobalStateRcRefCell,
+    global_entries: GlobalEntriesRcRefCell,
+) {
+    let borrowed_state = global_state.borrow();
+    if message.generation != borrowed_state.load_generation {
+        return;
+    }
+    borrowed_state.spinner.as_ref().unwrap().stop();
+
+    let status_bar = borrowed_state.status_bar.as_ref().unwrap();
+    let context_id = status_bar.context_id("update_text_view");
+    let loaded_period = match message.result {
+        Ok(value) => value,
+        Err(err) => {
+            warn!("Could not load data: {:?}", err);
+            status_bar.push(context_id, &format!("Could not load data: {}", err));
+            return;
+        }
+    };
 
-    let now = SystemTime::now();
-    let text = generate_text(entries, settings)?;
-    text_buffer.set_text(&text);
-    let duration = now.elapsed()?.as_secs_f32();
+    borrowed_state.text_buffer.set_text(&loaded_period.text);
+    apply_bar_graph_tag(&borrowed_state.text_buffer, &borrowed_state.bar_graph_tag);
+    borrowed_state.chart.replace(Some(loaded_period.chart));
+    borrowed_state
+        .chart_drawing_area
+        .as_ref()
+        .unwrap()
+        .queue_draw();
 
     let msg = format!(
         "Generated data for {} to {} (took {:.4} seconds)",
-        format_date(entries.start_datetime(), settings.print.format_datetime),
-        format_date(entries.end_datetime(), settings.print.format_datetime),
-        duration
+        format_date(
+            loaded_period.entries.start_datetime(),
+            borrowed_state.settings.print.format_datetime
+        ),
+        format_date(
+            loaded_period.entries.end_datetime(),
+            borrowed_state.settings.print.format_datetime
+        ),
+        loaded_period.duration_seconds
     );
     status_bar.push(context_id, &msg);
 
-    Ok(())
+    global_entries
+        .borrow_mut()
+        .map
+        .insert(message.period_datetime_pair, loaded_period.entries);
 }
 
-fn week_number_changed(
+fn period_number_changed(
     widget: &SpinButton,
     global_state: GlobalStateRcRefCell,
     global_entries: GlobalEntriesRcRefCell,
 ) -> Result<()> {
     let mut borrowed_state = global_state.borrow_mut();
-    let mut borrowed_entries = global_entries.borrow_mut();
+    let borrowed_entries = global_entries.borrow();
+    if borrowed_state.updating_widgets {
+        return Ok(());
+    }
 
     let status_bar = borrowed_state.status_bar.as_ref().unwrap();
-    let context_id = status_bar.context_id("week_number_changed");
-    status_bar.push(context_id, "week_number_changed");
-
-    let week_number: u32 = widget.value_as_int().try_into().unwrap();
-    let week_datetime_pair = get_absolute_week_start_end(week_number)?;
-
-    let entries = query_and_cache_entries(
-        week_number,
-        week_datetime_pair,
-        &borrowed_state.settings.core.database_dir,
-        &borrowed_state.settings.core.database_file_name,
-        &mut borrowed_entries.map,
-    )?;
+    let context_id = status_bar.context_id("period_number_changed");
+    status_bar.push(context_id, "period_number_changed");
+
+    borrowed_state.period_number = widget.value_as_int().try_into().unwrap();
+    let period_datetime_pair = borrowed_state.period_datetime_pair()?;
 
     // Update label text with start and end date formatted as user
     // wants it (requires shared settings).
     let date_range_label = borrowed_state.date_range_label.as_ref().unwrap();
     update_date_range_label(
         date_range_label,
-        week_datetime_pair,
+        period_datetime_pair,
         &borrowed_state.settings,
     )?;
 
     // Fetch the database entries and generate the text buffer again.
-    update_text_view(
-        &entries,
-        &status_bar,
-        &borrowed_state.text_buffer,
+    start_loading_period(&mut borrowed_state, &borrowed_entries)?;
+
+    Ok(())
+}
+
+/// Set the label and range of the period number spin button for the
+/// displayed period and year, such as 1 to 12 for the months.
+///
+/// The global state must not be borrowed, because the value of the
+/// spin button is changed when it is outside of the range.
+fn update_period_number_range(global_state: &GlobalStateRcRefCell) {
+    let (label, spin_button, display_period, year) = {
+        let borrowed_state = global_state.borrow();
+        (
+            borrowed_state.period_number_label.clone().unwrap(),
+            borrowed_state.period_number_spin_button.clone().unwrap(),
+            borrowed_state.display_period,
+            borrowed_state.year,
+        )
+    };
+    label.set_text(display_period.number_label());
+    spin_button.set_range(1.0, display_period.count_in_year(year) as f64);
+}
+
+fn year_changed(
+    widget: &SpinButton,
+    global_state: GlobalStateRcRefCell,
+    global_entries: GlobalEntriesRcRefCell,
+) -> Result<()> {
+    let period_number_spin_button = {
+        let mut borrowed_state = global_state.borrow_mut();
+        if borrowed_state.updating_widgets {
+            return Ok(());
+        }
+        borrowed_state.year = widget.value_as_int();
+        borrowed_state.updating_widgets = true;
+        borrowed_state.period_number_spin_button.clone().unwrap()
+    };
+    // The year may have fewer weeks, so the week number is clamped.
+    update_period_number_range(&global_state);
+
+    let mut borrowed_state = global_state.borrow_mut();
+    let borrowed_entries = global_entries.borrow();
+    borrowed_state.updating_widgets = false;
+    borrowed_state.period_number = period_number_spin_button.value_as_int().try_into().unwrap();
+    let period_datetime_pair = borrowed_state.period_datetime_pair()?;
+    let date_range_label = borrowed_state.date_range_label.as_ref().unwrap();
+    update_date_range_label(
+        date_range_label,
+        period_datetime_pair,
         &borrowed_state.settings,
     )?;
 
-    // Update the status bar with text saying ???.
+    start_loading_period(&mut borrowed_state, &borrowed_entries)?;
+
+    Ok(())
+}
+
+/// When the displayed period (week, month or quarter) is changed, the
+/// period containing the start of the old period is displayed.
+fn display_period_changed(
+    widget: &ComboBoxText,
+    global_state: GlobalStateRcRefCell,
+    global_entries: GlobalEntriesRcRefCell,
+) -> Result<()> {
+    let display_period = match id_as_display_period(widget.active_id().as_ref()) {
+        Some(value) => value,
+        None => return Ok(()),
+    };
+    let (year_spin_button, period_number_spin_button, year, period_number) = {
+        let mut borrowed_state = global_state.borrow_mut();
+        let (start_datetime, _end_datetime) = borrowed_state.period_datetime_pair()?;
+        let start_date = start_datetime.date_naive();
+        let (year, period_number) = match display_period {
+            DisplayPeriod::Week => {
+                get_week_number(start_date, borrowed_state.settings.core.first_day_of_week)
+            }
+            DisplayPeriod::Month => (start_date.year(), start_date.month()),
+            DisplayPeriod::Quarter => (start_date.year(), start_date.month0() / 3 + 1),
+        };
+        borrowed_state.display_period = display_period;
+        borrowed_state.year = year;
+        borrowed_state.period_number = period_number;
+        borrowed_state.updating_widgets = true;
+        (
+            borrowed_state.year_spin_button.clone().unwrap(),
+            borrowed_state.period_number_spin_button.clone().unwrap(),
+            year,
+            period_number,
+        )
+    };
+    update_period_number_range(&global_state);
+    year_spin_button.set_value(year as f64);
+    period_number_spin_button.set_value(period_number as f64);
+
+    let mut borrowed_state = global_state.borrow_mut();
+    let borrowed_entries = global_entries.borrow();
+    borrowed_state.updating_widgets = false;
+    let period_datetime_pair = borrowed_state.period_datetime_pair()?;
+    let date_range_label = borrowed_state.date_range_label.as_ref().unwrap();
+    update_date_range_label(
+        date_range_label,
+        period_datetime_pair,
+        &borrowed_state.settings,
+    )?;
 
-    borrowed_state.week_number = week_number;
+    start_loading_period(&mut borrowed_state, &borrowed_entries)?;
 
     Ok(())
 }
@@ -329,44 +529,27 @@ fn format_date_time_changed(
     global_entries: GlobalEntriesRcRefCell,
 ) -> Result<()> {
     let mut borrowed_state = global_state.borrow_mut();
-    let mut borrowed_entries = global_entries.borrow_mut();
+    let borrowed_entries = global_entries.borrow();
 
     let active_id = widget.active_id();
-    match id_as_datetime_format(active_id.as_ref()) {
-        Some(value) => borrowed_state.settings.print.format_datetime = value,
-        None => (),
+    if let Some(value) = id_as_datetime_format(active_id.as_ref()) {
+        borrowed_state.settings.print.format_datetime = value
     }
 
     let status_bar = borrowed_state.status_bar.as_ref().unwrap();
     let context_id = status_bar.context_id("format_date_time_changed");
     status_bar.push(context_id, "format_date_time_changed");
 
-    let week_number: u32 = borrowed_state.week_number;
-    let week_datetime_pair = get_absolute_week_start_end(week_number)?;
-
-    let entries = query_and_cache_entries(
-        week_number,
-        week_datetime_pair,
-        &borrowed_state.settings.core.database_dir,
-        &borrowed_state.settings.core.database_file_name,
-        &mut borrowed_entries.map,
-    )?;
+    let period_datetime_pair = borrowed_state.period_datetime_pair()?;
 
     let date_range_label = borrowed_state.date_range_label.as_ref().unwrap();
     update_date_range_label(
         date_range_label,
-        week_datetime_pair,
+        period_datetime_pair,
         &borrowed_state.settings,
     )?;
 
-    update_text_view(
-        &entries,
-        &status_bar,
-        &borrowed_state.text_buffer,
-        &borrowed_state.settings,
-    )?;
-
-    borrowed_state.week_number = week_number;
+    start_loading_period(&mut borrowed_state, &borrowed_entries)?;
 
     Ok(())
 }
@@ -377,44 +560,27 @@ fn format_duration_changed(
     global_entries: GlobalEntriesRcRefCell,
 ) -> Result<()> {
     let mut borrowed_state = global_state.borrow_mut();
-    let mut borrowed_entries = global_entries.borrow_mut();
+    let borrowed_entries = global_entries.borrow();
 
     let active_id = widget.active_id();
-    match id_as_duration_format(active_id.as_ref()) {
-        Some(value) => borrowed_state.settings.print.format_duration = value,
-        None => (),
+    if let Some(value) = id_as_duration_format(active_id.as_ref()) {
+        borrowed_state.settings.print.format_duration = value
     }
 
     let status_bar = borrowed_state.status_bar.as_ref().unwrap();
     let context_id = status_bar.context_id("format_duration_changed");
     status_bar.push(context_id, "format_duration_changed");
 
-    let week_number: u32 = borrowed_state.week_number;
-    let week_datetime_pair = get_absolute_week_start_end(week_number)?;
-
-    let entries = query_and_cache_entries(
-        week_number,
-        week_datetime_pair,
-        &borrowed_state.settings.core.database_dir,
-        &borrowed_state.settings.core.database_file_name,
-        &mut borrowed_entries.map,
-    )?;
+    let period_datetime_pair = borrowed_state.period_datetime_pair()?;
 
     let date_range_label = borrowed_state.date_range_label.as_ref().unwrap();
     update_date_range_label(
         date_range_label,
-        week_datetime_pair,
+        period_datetime_pair,
         &borrowed_state.settings,
     )?;
 
-    update_text_view(
-        &entries,
-        &status_bar,
-        &borrowed_state.text_buffer,
-        &borrowed_state.settings,
-    )?;
-
-    borrowed_state.week_number = week_number;
+    start_loading_period(&mut borrowed_state, &borrowed_entries)?;
 
     Ok(())
 }
@@ -424,40 +590,425 @@ fn window_startup(
     global_state: GlobalStateRcRefCell,
     global_entries: GlobalEntriesRcRefCell,
 ) -> Result<()> {
-    let borrowed_state = global_state.borrow_mut();
-    let mut borrowed_entries = global_entries.borrow_mut();
+    let mut borrowed_state = global_state.borrow_mut();
+    let borrowed_entries = global_entries.borrow();
 
     let status_bar = borrowed_state.status_bar.as_ref().unwrap();
     let context_id = status_bar.context_id("window_startup");
     status_bar.push(context_id, "window_startup");
 
-    let week_datetime_pair = get_absolute_week_start_end(borrowed_state.week_number)?;
-
-    let entries = query_and_cache_entries(
-        borrowed_state.week_number,
-        week_datetime_pair,
-        &borrowed_state.settings.core.database_dir,
-        &borrowed_state.settings.core.database_file_name,
-        &mut borrowed_entries.map,
-    )?;
+    let period_datetime_pair = borrowed_state.period_datetime_pair()?;
 
     let date_range_label = borrowed_state.date_range_label.as_ref().unwrap();
     update_date_range_label(
         date_range_label,
-        week_datetime_pair,
+        period_datetime_pair,
         &borrowed_state.settings,
     )?;
 
-    update_text_view(
-        &entries,
-        &status_bar,
-        &borrowed_state.text_buffer,
-        &borrowed_state.settings,
+    start_loading_period(&mut borrowed_state, &borrowed_entries)?;
+
+    Ok(())
+}
+
+fn update_privacy_consent_label(privacy_consent_label: &Label, has_consent: bool) {
+    let text = match has_consent {
+        true => "You have agreed to this privacy notice.",
+        false => "You have not agreed to this privacy notice.",
+    };
+    privacy_consent_label.set_text(text);
+}
+
+/// Ask the user to agree to the privacy notice, if they have not
+/// already. Returns false if the user does not agree.
+fn request_privacy_consent(global_state: GlobalStateRcRefCell) -> Result<bool> {
+    let (window, has_consent) = {
+        let borrowed_state = global_state.borrow();
+        (
+            borrowed_state.window.clone(),
+            has_privacy_consent(&borrowed_state.settings.core)?,
+        )
+    };
+    if has_consent {
+        return Ok(true);
+    }
+
+    let dialog = MessageDialog::new(
+        window.as_ref(),
+        DialogFlags::MODAL,
+        MessageType::Info,
+        ButtonsType::None,
+        PRIVACY_NOTICE_TITLE,
+    );
+    dialog.set_secondary_text(Some(PRIVACY_NOTICE_TEXT));
+    dialog.add_button("Quit", ResponseType::Reject);
+    dialog.add_button("I Agree", ResponseType::Accept);
+    let response = dialog.run();
+    dialog.close();
+
+    let has_consent = response == ResponseType::Accept;
+    let borrowed_state = global_state.borrow();
+    if has_consent {
+        write_privacy_consent(&borrowed_state.settings.core)?;
+    }
+
+    let privacy_consent_label = borrowed_state.privacy_consent_label.as_ref().unwrap();
+    update_privacy_consent_label(privacy_consent_label, has_consent);
+
+    Ok(has_consent)
+}
+
+/// When one of the purge buttons is clicked, confirm and then delete
+/// the recorded data.
+fn purge_button_clicked(
+    purge_range: PurgeRange,
+    global_state: GlobalStateRcRefCell,
+    global_entries: GlobalEntriesRcRefCell,
+) -> Result<()> {
+    let window = global_state.borrow().window.clone();
+    let dialog = MessageDialog::new(
+        window.as_ref(),
+        DialogFlags::MODAL,
+        MessageType::Warning,
+        ButtonsType::YesNo,
+        &format!("Delete the data recorded in {}?", purge_range.description()),
+    );
+    dialog.set_secondary_text(Some("Deleted data cannot be recovered."));
+    let response = dialog.run();
+    dialog.close();
+    if response != ResponseType::Yes {
+        return Ok(());
+    }
+
+    let mut borrowed_state = global_state.borrow_mut();
+    let mut borrowed_entries = global_entries.borrow_mut();
+
+    let status_bar = borrowed_state.status_bar.as_ref().unwrap();
+    let context_id = status_bar.context_id("purge_button_clicked");
+    match purge_data(&borrowed_state.settings.core, purge_range) {
+        Ok(()) => {
+            let msg = format!(
+                "Deleted the data recorded in {}.",
+                purge_range.description()
+            );
+            status_bar.push(context_id, &msg);
+        }
+        Err(err) => {
+            warn!("Could not purge data: {:?}", err);
+            let msg = format!("Could not delete data: {}", err);
+            status_bar.push(context_id, &msg);
+            return Ok(());
+        }
+    }
+
+    // The cached entries may contain the deleted data.
+    borrowed_entries.map.clear();
+
+    start_loading_period(&mut borrowed_state, &borrowed_entries)?;
+
+    Ok(())
+}
+
+/// Style the report text view with the font size and the dark (or
+/// light) colors of the settings.
+fn update_report_style(borrowed_state: &GlobalState) -> Result<()> {
+    let gui_settings = &borrowed_state.settings.gui;
+    let css = report_css(gui_settings.font_size, gui_settings.dark_mode);
+    borrowed_state.css_provider.load_from_data(css.as_bytes())?;
+    borrowed_state
+        .bar_graph_tag
+        .set_foreground(Some(bar_graph_color(gui_settings.dark_mode)));
+    Ok(())
+}
+
+/// Write the 'gui' settings to the configuration file, so the font
+/// size and colors are used the next time.
+fn save_view_settings(borrowed_state: &GlobalState) {
+    let status_bar = borrowed_state.status_bar.as_ref().unwrap();
+    let context_id = status_bar.context_id("save_view_settings");
+    let file_path = match find_configuration_file_path_to_write() {
+        Some(value) => value,
+        None => {
+            status_bar.push(
+                context_id,
+                "Configuration file location could not be found.",
+            );
+            return;
+        }
+    };
+
+    let gui_settings = &borrowed_state.settings.gui;
+    let values = [
+        (
+            "gui.font_size",
+            SettingValue::Integer(gui_settings.font_size.into()),
+        ),
+        ("gui.dark_mode", SettingValue::Bool(gui_settings.dark_mode)),
+    ];
+    if let Err(err) = write_configuration_file_values(&file_path, &values) {
+        warn!(
+            "Could not save the view settings to {:?}: {:?}",
+            file_path, err
+        );
+        status_bar.push(
+            context_id,
+            &format!(
+                "Could not save the view settings to {}: {}",
+                file_path.display(),
+                err
+            ),
+        );
+    }
+}
+
+/// When one of the font size menu items is activated, change the font
+/// size of the report text by the number of points, or reset the font
+/// size to the default (when None).
+fn font_size_menu_item_activated(
+    global_state: GlobalStateRcRefCell,
+    change_points: Option<i32>,
+) -> Result<()> {
+    let mut borrowed_state = global_state.borrow_mut();
+    let font_size = match change_points {
+        Some(value) => borrowed_state
+            .settings
+            .gui
+            .font_size
+            .saturating_add_signed(value),
+        None => DEFAULT_GUI_FONT_SIZE,
+    };
+    borrowed_state.settings.gui.font_size = font_size.clamp(
+        constants::REPORT_FONT_SIZE_MIN,
+        constants::REPORT_FONT_SIZE_MAX,
+    );
+
+    update_report_style(&borrowed_state)?;
+    save_view_settings(&borrowed_state);
+    Ok(())
+}
+
+/// When the "Dark Mode" menu item is toggled.
+fn dark_mode_menu_item_toggled(
+    widget: &CheckMenuItem,
+    global_state: GlobalStateRcRefCell,
+) -> Result<()> {
+    let mut borrowed_state = global_state.borrow_mut();
+    borrowed_state.settings.gui.dark_mode = widget.is_active();
+
+    update_report_style(&borrowed_state)?;
+    save_view_settings(&borrowed_state);
+    Ok(())
+}
+
+/// The text displayed in the text view (the generated lines of the
+/// displayed presets).
+fn get_displayed_text(borrowed_state: &GlobalState) -> String {
+    let text_buffer = &borrowed_state.text_buffer;
+    let (start, end) = text_buffer.bounds();
+    text_buffer
+        .text(&start, &end, false)
+        .map(|x| x.to_string())
+        .unwrap_or_default()
+}
+
+/// When the "Copy to Clipboard" button is clicked, copy the displayed
+/// text, such as to paste into an email or timesheet.
+fn copy_button_clicked(global_state: GlobalStateRcRefCell) {
+    let borrowed_state = global_state.borrow();
+    let text = get_displayed_text(&borrowed_state);
+    let clipboard = gtk::Clipboard::get(&gtk::gdk::SELECTION_CLIPBOARD);
+    clipboard.set_text(&text);
+
+    let status_bar = borrowed_state.status_bar.as_ref().unwrap();
+    let context_id = status_bar.context_id("copy_button_clicked");
+    status_bar.push(context_id, "Copied the displayed text to the clipboard.");
+}
+
+/// Ask for the file path to save to, with the file name defaulting to
+/// the displayed period, such as "timetracker_2024-08-05_2024-08-11.csv".
+fn choose_save_file_path(
+    global_state: &GlobalStateRcRefCell,
+    title: &str,
+    file_extension: &str,
+) -> Result<Option<PathBuf>> {
+    // The state is not borrowed while the dialog is running, because
+    // the dialog runs the GTK main loop.
+    let (window, (start_datetime, end_datetime)) = {
+        let borrowed_state = global_state.borrow();
+        (
+            borrowed_state.window.clone(),
+            borrowed_state.period_datetime_pair()?,
+        )
+    };
+    let file_name = format!(
+        "timetracker_{}_{}.{}",
+        start_datetime.format("%Y-%m-%d"),
+        end_datetime.format("%Y-%m-%d"),
+        file_extension
+    );
+
+    let dialog = FileChooserDialog::with_buttons(
+        Some(title),
+        window.as_ref(),
+        FileChooserAction::Save,
+        &[
+            ("_Cancel", ResponseType::Cancel),
+            ("_Save", ResponseType::Accept),
+        ],
+    );
+    dialog.set_do_overwrite_confirmation(true);
+    dialog.set_current_name(&file_name);
+    let response = dialog.run();
+    let file_path = dialog.filename();
+    dialog.close();
+
+    match response {
+        ResponseType::Accept => Ok(file_path),
+        _ => Ok(None),
+    }
+}
+
+/// Write the saved (or exported) file, and report whether it was
+/// written in the status bar.
+fn write_saved_file(borrowed_state: &GlobalState, file_path: &Path, contents: &[u8]) {
+    let status_bar = borrowed_state.status_bar.as_ref().unwrap();
+    let context_id = status_bar.context_id("write_saved_file");
+    match std::fs::write(file_path, contents) {
+        Ok(()) => status_bar.push(context_id, &format!("Saved {}.", file_path.display())),
+        Err(err) => {
+            warn!("Could not save {:?}: {:?}", file_path, err);
+            status_bar.push(
+                context_id,
+                &format!("Could not save {}: {}", file_path.display(), err),
+            )
+        }
+    };
+}
+
+/// When the "Save as Text" button is clicked, save the displayed text
+/// to a file.
+fn save_text_button_clicked(global_state: GlobalStateRcRefCell) -> Result<()> {
+    let file_path = match choose_save_file_path(&global_state, "Save as Text", "txt")? {
+        Some(value) => value,
+        None => return Ok(()),
+    };
+
+    let borrowed_state = global_state.borrow();
+    let mut text = get_displayed_text(&borrowed_state);
+    text.push('\n');
+    write_saved_file(&borrowed_state, &file_path, text.as_bytes());
+    Ok(())
+}
+
+/// When the "Export CSV" button is clicked, write the rows of the
+/// displayed presets (the same as 'timetracker-print --output-format csv')
+/// for the displayed period.
+fn export_csv_button_clicked(
+    global_state: GlobalStateRcRefCell,
+    global_entries: GlobalEntriesRcRefCell,
+) -> Result<()> {
+    let is_loaded = {
+        let borrowed_state = global_state.borrow();
+        let period_datetime_pair = borrowed_state.period_datetime_pair()?;
+        let is_loaded = global_entries
+            .borrow()
+            .map
+            .contains_key(&period_datetime_pair);
+        if !is_loaded {
+            let status_bar = borrowed_state.status_bar.as_ref().unwrap();
+            let context_id = status_bar.context_id("export_csv_button_clicked");
+            status_bar.push(context_id, "The displayed data is still loading.");
+        }
+        is_loaded
+    };
+    if !is_loaded {
+        return Ok(());
+    }
+
+    let file_path = match choose_save_file_path(&global_state, "Export CSV", "csv")? {
+        Some(value) => value,
+        None => return Ok(()),
+    };
+
+    let borrowed_state = global_state.borrow();
+    let borrowed_entries = global_entries.borrow();
+    let period_datetime_pair = borrowed_state.period_datetime_pair()?;
+    let entries = match borrowed_entries.map.get(&period_datetime_pair) {
+        Some(value) => value,
+        // The cached entries were purged while the dialog was open.
+        None => return Ok(()),
+    };
+
+    let settings = &borrowed_state.settings;
+    let (presets, _missing_preset_names) = create_display_presets(settings)?;
+    let rows = generate_presets_rows(
+        &settings.print.display_presets,
+        &presets,
+        entries,
+        period_datetime_pair,
+        settings.core.first_day_of_week,
+    )?;
+    let mut contents = Vec::new();
+    write_rows_csv(&mut contents, &rows)?;
+    write_saved_file(&borrowed_state, &file_path, &contents);
+    Ok(())
+}
+
+/// The database file that is currently recorded into (the per-year
+/// database file of the current year, when enabled).
+fn get_current_database_file_path(settings: &PrintGuiAppSettings) -> Option<PathBuf> {
+    let database_file_path = get_database_file_path(
+        &settings.core.database_dir,
+        &settings.core.database_file_name,
     )?;
+    let now_utc_time_seconds = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    Some(get_database_file_path_for_time(
+        &database_file_path,
+        settings.core.database_per_year,
+        now_utc_time_seconds,
+    ))
+}
 
+/// Open a file or directory in the default application, such as the
+/// file manager or text editor.
+fn open_path_in_default_application(window: Option<&ApplicationWindow>, path: &Path) -> Result<()> {
+    let uri = gtk::glib::filename_to_uri(path, None)?;
+    gtk::show_uri_on_window(window, &uri, gtk::current_event_time())?;
     Ok(())
 }
 
+/// When the "Open Database Folder" or "Open Configuration File"
+/// button is clicked, open the location with the default
+/// application.
+fn open_location_button_clicked(global_state: GlobalStateRcRefCell, open_database_folder: bool) {
+    let borrowed_state = global_state.borrow();
+    let path = match open_database_folder {
+        true => get_current_database_file_path(&borrowed_state.settings)
+            .and_then(|x| x.parent().map(|x| x.to_path_buf())),
+        false => find_existing_configuration_file_path(),
+    };
+
+    let status_bar = borrowed_state.status_bar.as_ref().unwrap();
+    let context_id = status_bar.context_id("open_location_button_clicked");
+    let path = match path {
+        Some(value) => value,
+        None => {
+            status_bar.push(context_id, "Location could not be found.");
+            return;
+        }
+    };
+    match open_path_in_default_application(borrowed_state.window.as_ref(), &path) {
+        Ok(()) => status_bar.push(context_id, &format!("Opened {}.", path.display())),
+        Err(err) => {
+            warn!("Could not open {:?}: {:?}", path, err);
+            status_bar.push(
+                context_id,
+                &format!("Could not open {}: {}", path.display(), err),
+            )
+        }
+    };
+}
+
 /// When one of the preset buttons is toggled.
 fn preset_toggle_clicked(
     _widget: &ToggleButton,
@@ -466,7 +1017,10 @@ fn preset_toggle_clicked(
     global_entries: GlobalEntriesRcRefCell,
 ) -> Result<()> {
     let mut borrowed_state = global_state.borrow_mut();
-    let mut borrowed_entries = global_entries.borrow_mut();
+    let borrowed_entries = global_entries.borrow();
+    if borrowed_state.updating_widgets {
+        return Ok(());
+    }
 
     let toggled_state = match borrowed_state.preset_states.get(&preset_name) {
         Some(PresetState::Enable) => PresetState::Disable,
@@ -477,37 +1031,215 @@ fn preset_toggle_clicked(
         .preset_states
         .insert(preset_name, toggled_state);
 
+    update_display_presets(&mut borrowed_state);
+    start_loading_period(&mut borrowed_state, &borrowed_entries)?;
+
+    Ok(())
+}
+
+/// Set the displayed presets to the enabled presets, in the order of
+/// the preset buttons.
+fn update_display_presets(borrowed_state: &mut GlobalState) {
     borrowed_state.settings.print.display_presets.clear();
     for name in borrowed_state.all_preset_names.clone() {
-        match borrowed_state.preset_states.get(&name) {
-            Some(PresetState::Enable) => borrowed_state.settings.print.display_presets.push(name),
-            _ => (),
+        if let Some(PresetState::Enable) = borrowed_state.preset_states.get(&name) {
+            borrowed_state.settings.print.display_presets.push(name)
         };
     }
+}
+
+/// When a preset button is dragged and dropped onto another preset
+/// button, move the dragged preset to the position of the other
+/// preset.
+fn preset_dropped(
+    dragged_preset_name: &str,
+    target_preset_name: &str,
+    global_state: GlobalStateRcRefCell,
+    global_entries: GlobalEntriesRcRefCell,
+) -> Result<()> {
+    let mut borrowed_state = global_state.borrow_mut();
+    let borrowed_entries = global_entries.borrow();
+
+    let all_preset_names = &borrowed_state.all_preset_names;
+    let dragged_index = all_preset_names
+        .iter()
+        .position(|x| x == dragged_preset_name);
+    let target_index = all_preset_names
+        .iter()
+        .position(|x| x == target_preset_name);
+    let (dragged_index, target_index) = match (dragged_index, target_index) {
+        (Some(dragged_index), Some(target_index)) if dragged_index != target_index => {
+            (dragged_index, target_index)
+        }
+        _ => return Ok(()),
+    };
 
-    let week_datetime_pair = get_absolute_week_start_end(borrowed_state.week_number)?;
+    // The buttons are in the same order as the preset names.
+    let preset_buttons_layout = borrowed_state.preset_buttons_layout.as_ref().unwrap();
+    let preset_buttons = preset_buttons_layout.children();
+    preset_buttons_layout.reorder_child(&preset_buttons[dragged_index], target_index as i32);
 
-    let entries = query_and_cache_entries(
-        borrowed_state.week_number,
-        week_datetime_pair,
-        &borrowed_state.settings.core.database_dir,
-        &borrowed_state.settings.core.database_file_name,
-        &mut borrowed_entries.map,
-    )?;
+    let preset_name = borrowed_state.all_preset_names.remove(dragged_index);
+    borrowed_state
+        .all_preset_names
+        .insert(target_index, preset_name);
+
+    update_display_presets(&mut borrowed_state);
+    start_loading_period(&mut borrowed_state, &borrowed_entries)?;
+
+    Ok(())
+}
 
+/// When the "Save Layout" button is clicked, write the displayed
+/// presets (in the order of the preset buttons) to the configuration
+/// file, so the presets are displayed the next time.
+fn save_layout_button_clicked(global_state: GlobalStateRcRefCell) {
+    let borrowed_state = global_state.borrow();
     let status_bar = borrowed_state.status_bar.as_ref().unwrap();
-    update_text_view(
-        &entries,
-        &status_bar,
-        &borrowed_state.text_buffer,
-        &borrowed_state.settings,
-    )?;
+    let context_id = status_bar.context_id("save_layout_button_clicked");
+
+    let file_path = match find_configuration_file_path_to_write() {
+        Some(value) => value,
+        None => {
+            status_bar.push(
+                context_id,
+                "Configuration file location could not be found.",
+            );
+            return;
+        }
+    };
+    let values = [(
+        "print.display_presets",
+        SettingValue::StringList(borrowed_state.settings.print.display_presets.clone()),
+    )];
+    match write_configuration_file_values(&file_path, &values) {
+        Ok(()) => {
+            status_bar.push(
+                context_id,
+                &format!("Saved the preset layout to {}.", file_path.display()),
+            );
+            // The configuration file may have been created.
+            let open_config_file_button = borrowed_state.open_config_file_button.as_ref().unwrap();
+            open_config_file_button.set_sensitive(true);
+        }
+        Err(err) => {
+            warn!(
+                "Could not save the preset layout to {:?}: {:?}",
+                file_path, err
+            );
+            status_bar.push(
+                context_id,
+                &format!(
+                    "Could not save the preset layout to {}: {}",
+                    file_path.display(),
+                    err
+                ),
+            );
+        }
+    };
+}
+
+/// When the "Preferences" button is clicked, edit the preferences,
+/// then save them to the configuration file and display the period
+/// with the changed preferences.
+fn preferences_button_clicked(
+    global_state: GlobalStateRcRefCell,
+    global_entries: GlobalEntriesRcRefCell,
+) -> Result<()> {
+    // The state is not borrowed while the dialog is running, because
+    // the dialog runs the GTK main loop.
+    let (window, preferences, all_preset_names) = {
+        let borrowed_state = global_state.borrow();
+        (
+            borrowed_state.window.clone(),
+            Preferences::from_settings(&borrowed_state.settings),
+            borrowed_state.all_preset_names.clone(),
+        )
+    };
+    let edited_preferences =
+        match run_preferences_dialog(window.as_ref(), &preferences, &all_preset_names) {
+            Some(value) => value,
+            None => return Ok(()),
+        };
+
+    let preset_buttons = {
+        let mut borrowed_state = global_state.borrow_mut();
+        let status_bar = borrowed_state.status_bar.as_ref().unwrap();
+        let context_id = status_bar.context_id("preferences_button_clicked");
+        match find_configuration_file_path_to_write() {
+            Some(file_path) => match edited_preferences.write_configuration_file(&file_path) {
+                Ok(()) => {
+                    status_bar.push(
+                        context_id,
+                        &format!("Saved the preferences to {}.", file_path.display()),
+                    );
+                    let open_config_file_button =
+                        borrowed_state.open_config_file_button.as_ref().unwrap();
+                    open_config_file_button.set_sensitive(true);
+                }
+                Err(err) => {
+                    warn!(
+                        "Could not save the preferences to {:?}: {:?}",
+                        file_path, err
+                    );
+                    status_bar.push(
+                        context_id,
+                        &format!(
+                            "Could not save the preferences to {}: {}",
+                            file_path.display(),
+                            err
+                        ),
+                    );
+                }
+            },
+            None => {
+                status_bar.push(
+                    context_id,
+                    "Configuration file location could not be found.",
+                );
+            }
+        };
+
+        edited_preferences.apply_to_settings(&mut borrowed_state.settings);
+        for preset_name in borrowed_state.all_preset_names.clone() {
+            let preset_state = match edited_preferences.display_presets.contains(&preset_name) {
+                true => PresetState::Enable,
+                false => PresetState::Disable,
+            };
+            borrowed_state
+                .preset_states
+                .insert(preset_name, preset_state);
+        }
+        borrowed_state.updating_widgets = true;
+        borrowed_state
+            .preset_buttons_layout
+            .as_ref()
+            .unwrap()
+            .children()
+    };
+
+    // The buttons are in the same order as the preset names. The
+    // state is not borrowed while the buttons are changed, because
+    // each change calls 'preset_toggle_clicked'.
+    for (preset_button, preset_name) in preset_buttons.iter().zip(&all_preset_names) {
+        if let Some(toggle_button) = preset_button.downcast_ref::<ToggleButton>() {
+            toggle_button.set_active(edited_preferences.display_presets.contains(preset_name));
+        }
+    }
+
+    let mut borrowed_state = global_state.borrow_mut();
+    let mut borrowed_entries = global_entries.borrow_mut();
+    borrowed_state.updating_widgets = false;
+    if edited_preferences.has_database_changed(&preferences) {
+        borrowed_entries.map.clear();
+    }
+    start_loading_period(&mut borrowed_state, &borrowed_entries)?;
 
     Ok(())
 }
 
 /// Build a button for each preset, so each preset can be toggled
-/// on/off.
+/// on/off, and dragged to reorder the presets.
 fn build_preset_buttons(
     layout_widget: &Box,
     global_state: GlobalStateRcRefCell,
@@ -517,14 +1249,34 @@ fn build_preset_buttons(
 ) {
     for preset_name in preset_names {
         let preset_name = preset_name.clone();
-        let enabled = match preset_states.get(&preset_name) {
-            Some(PresetState::Enable) => true,
-            _ => false,
-        };
+        let enabled = matches!(preset_states.get(&preset_name), Some(PresetState::Enable));
 
         let toggle_button = ToggleButton::with_label(&preset_name);
         toggle_button.set_active(enabled);
 
+        let drag_targets = [TargetEntry::new(
+            constants::PRESET_DRAG_TARGET_NAME,
+            TargetFlags::SAME_APP,
+            0,
+        )];
+        toggle_button.drag_source_set(ModifierType::BUTTON1_MASK, &drag_targets, DragAction::MOVE);
+        toggle_button.drag_dest_set(DestDefaults::ALL, &drag_targets, DragAction::MOVE);
+        toggle_button.connect_drag_data_get(clone!(
+            @strong preset_name => move |_widget, _context, selection_data, _info, _time| {
+                selection_data.set_text(&preset_name);
+        }));
+        toggle_button.connect_drag_data_received(clone!(
+            @strong global_state, @strong global_entries, @strong preset_name =>
+            move |_widget, _context, _x, _y, selection_data, _info, _time| {
+                if let Some(dragged_preset_name) = selection_data.text() {
+                    preset_dropped(
+                        &dragged_preset_name,
+                        &preset_name,
+                        global_state.clone(),
+                        global_entries.clone()).unwrap()
+                }
+        }));
+
         toggle_button.connect_clicked(clone!(
             @strong global_state, @strong global_entries => move |widget| {
                 preset_toggle_clicked(
@@ -557,13 +1309,76 @@ fn construct_window(
     let context_id = status_bar.context_id("build_ui");
     status_bar.push(context_id, "Building UI...");
 
-    borrowed_state.week_number_spin_button = Some(
+    borrowed_state.copy_button = Some(
+        builder
+            .object("copy_button")
+            .expect("Couldn't get 'copy_button'."),
+    );
+    borrowed_state.save_text_button = Some(
+        builder
+            .object("save_text_button")
+            .expect("Couldn't get 'save_text_button'."),
+    );
+    borrowed_state.export_csv_button = Some(
+        builder
+            .object("export_csv_button")
+            .expect("Couldn't get 'export_csv_button'."),
+    );
+    borrowed_state.save_layout_button = Some(
+        builder
+            .object("save_layout_button")
+            .expect("Couldn't get 'save_layout_button'."),
+    );
+    borrowed_state.preferences_button = Some(
+        builder
+            .object("preferences_button")
+            .expect("Couldn't get 'preferences_button'."),
+    );
+
+    borrowed_state.spinner = Some(builder.object("spinner").expect("Couldn't get 'spinner'."));
+
+    borrowed_state.period_number_spin_button = Some(
+        builder
+            .object("period_number_spin_button")
+            .expect("Couldn't get 'period_number_spin_button' widget."),
+    );
+    let period_number_spin_button = borrowed_state.period_number_spin_button.as_ref().unwrap();
+    let period_count = borrowed_state
+        .display_period
+        .count_in_year(borrowed_state.year);
+    period_number_spin_button.set_range(1.0, period_count as f64);
+    period_number_spin_button.set_value(borrowed_state.period_number as f64);
+
+    borrowed_state.period_number_label = Some(
+        builder
+            .object("period_number_label")
+            .expect("Couldn't get 'period_number_label'."),
+    );
+    let period_number_label = borrowed_state.period_number_label.as_ref().unwrap();
+    period_number_label.set_text(borrowed_state.display_period.number_label());
+
+    borrowed_state.year_spin_button = Some(
         builder
-            .object("week_number_spin_button")
-            .expect("Couldn't get 'week_number_spin_button' widget."),
+            .object("year_spin_button")
+            .expect("Couldn't get 'year_spin_button' widget."),
     );
-    let week_number_spin_button = borrowed_state.week_number_spin_button.as_ref().unwrap();
-    week_number_spin_button.set_value(borrowed_state.week_number as f64);
+    let year_spin_button = borrowed_state.year_spin_button.as_ref().unwrap();
+    year_spin_button.set_value(borrowed_state.year as f64);
+
+    borrowed_state.display_period_combo_box = Some(
+        builder
+            .object("display_period_combo_box")
+            .expect("Couldn't get 'display_period_combo_box'."),
+    );
+    let display_period_combo_box = borrowed_state.display_period_combo_box.as_ref().unwrap();
+    display_period_combo_box.append(Some(DISPLAY_PERIOD_WEEK_ID), DISPLAY_PERIOD_WEEK_LABEL);
+    display_period_combo_box.append(Some(DISPLAY_PERIOD_MONTH_ID), DISPLAY_PERIOD_MONTH_LABEL);
+    display_period_combo_box.append(
+        Some(DISPLAY_PERIOD_QUARTER_ID),
+        DISPLAY_PERIOD_QUARTER_LABEL,
+    );
+    let display_period_id = display_period_as_id(borrowed_state.display_period);
+    display_period_combo_box.set_active_id(Some(display_period_id));
 
     borrowed_state.text_view = Some(
         builder
@@ -573,6 +1388,50 @@ fn construct_window(
     let text_view = borrowed_state.text_view.as_ref().unwrap();
     text_view.set_monospace(true);
     text_view.set_buffer(Some(&borrowed_state.text_buffer));
+    let text_view_style_context = text_view.style_context();
+    text_view_style_context.add_class(constants::REPORT_CSS_CLASS);
+    text_view_style_context.add_provider(
+        &borrowed_state.css_provider,
+        gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
+    );
+    update_report_style(&borrowed_state).unwrap();
+
+    borrowed_state.increase_font_size_menu_item = Some(
+        builder
+            .object("increase_font_size_menu_item")
+            .expect("Couldn't get 'increase_font_size_menu_item'."),
+    );
+    borrowed_state.decrease_font_size_menu_item = Some(
+        builder
+            .object("decrease_font_size_menu_item")
+            .expect("Couldn't get 'decrease_font_size_menu_item'."),
+    );
+    borrowed_state.reset_font_size_menu_item = Some(
+        builder
+            .object("reset_font_size_menu_item")
+            .expect("Couldn't get 'reset_font_size_menu_item'."),
+    );
+    borrowed_state.dark_mode_menu_item = Some(
+        builder
+            .object("dark_mode_menu_item")
+            .expect("Couldn't get 'dark_mode_menu_item'."),
+    );
+    let dark_mode_menu_item = borrowed_state.dark_mode_menu_item.as_ref().unwrap();
+    dark_mode_menu_item.set_active(borrowed_state.settings.gui.dark_mode);
+
+    borrowed_state.chart_drawing_area = Some(
+        builder
+            .object("chart_drawing_area")
+            .expect("Couldn't get 'chart_drawing_area'."),
+    );
+    let chart = borrowed_state.chart.clone();
+    let chart_drawing_area = borrowed_state.chart_drawing_area.as_ref().unwrap();
+    chart_drawing_area.connect_draw(move |widget, context| {
+        if let Err(err) = draw_stacked_bar_chart(widget, context, chart.borrow().as_ref()) {
+            warn!("Could not draw chart: {:?}", err);
+        }
+        gtk::glib::Propagation::Stop
+    });
 
     borrowed_state.preset_buttons_layout = Some(
         builder
@@ -581,7 +1440,7 @@ fn construct_window(
     );
     let preset_buttons_layout = borrowed_state.preset_buttons_layout.as_ref().unwrap();
     build_preset_buttons(
-        &preset_buttons_layout,
+        preset_buttons_layout,
         global_state.clone(),
         global_entries.clone(),
         &borrowed_state.all_preset_names,
@@ -594,14 +1453,14 @@ fn construct_window(
             .expect("Couldn't get 'format_date_time_combo_box'."),
     );
     let format_date_time_combo_box = borrowed_state.format_date_time_combo_box.as_ref().unwrap();
-    format_date_time_combo_box.append(Some(DATETIME_FORMAT_ISO_ID), &DATETIME_FORMAT_ISO_LABEL);
+    format_date_time_combo_box.append(Some(DATETIME_FORMAT_ISO_ID), DATETIME_FORMAT_ISO_LABEL);
     format_date_time_combo_box.append(
         Some(DATETIME_FORMAT_USA_MONTH_DAY_YEAR_ID),
         DATETIME_FORMAT_USA_MONTH_DAY_YEAR_LABEL,
     );
     format_date_time_combo_box.append(
         Some(DATETIME_FORMAT_LOCALE_ID),
-        &DATETIME_FORMAT_LOCALE_LABEL,
+        DATETIME_FORMAT_LOCALE_LABEL,
     );
     let datetime_format_id = datetime_format_as_id(borrowed_state.settings.print.format_datetime);
     format_date_time_combo_box.set_active_id(Some(datetime_format_id));
@@ -633,6 +1492,73 @@ fn construct_window(
             .expect("Couldn't get 'date_range_label'."),
     );
 
+    let privacy_notice_label: Label = builder
+        .object("privacy_notice_label")
+        .expect("Couldn't get 'privacy_notice_label'.");
+    privacy_notice_label.set_text(PRIVACY_NOTICE_TEXT);
+
+    borrowed_state.privacy_consent_label = Some(
+        builder
+            .object("privacy_consent_label")
+            .expect("Couldn't get 'privacy_consent_label'."),
+    );
+    let privacy_consent_label = borrowed_state.privacy_consent_label.as_ref().unwrap();
+    let has_consent = has_privacy_consent(&borrowed_state.settings.core).unwrap_or(false);
+    update_privacy_consent_label(privacy_consent_label, has_consent);
+
+    borrowed_state.purge_last_hour_button = Some(
+        builder
+            .object("purge_last_hour_button")
+            .expect("Couldn't get 'purge_last_hour_button'."),
+    );
+    borrowed_state.purge_last_day_button = Some(
+        builder
+            .object("purge_last_day_button")
+            .expect("Couldn't get 'purge_last_day_button'."),
+    );
+    borrowed_state.purge_all_button = Some(
+        builder
+            .object("purge_all_button")
+            .expect("Couldn't get 'purge_all_button'."),
+    );
+
+    let database_path_label: Label = builder
+        .object("database_path_label")
+        .expect("Couldn't get 'database_path_label'.");
+    let database_path_text = match get_current_database_file_path(&borrowed_state.settings) {
+        Some(value) => format!("Database file: {}", value.display()),
+        None => format!(
+            "Database file: not found in {:?}",
+            borrowed_state.settings.core.database_dir
+        ),
+    };
+    database_path_label.set_text(&database_path_text);
+
+    let config_path_label: Label = builder
+        .object("config_path_label")
+        .expect("Couldn't get 'config_path_label'.");
+    let config_file_path = find_existing_configuration_file_path();
+    let config_path_text = match &config_file_path {
+        Some(value) => format!("Configuration file: {}", value.display()),
+        None => {
+            "Configuration file: not found (create one with 'timetracker-configure').".to_string()
+        }
+    };
+    config_path_label.set_text(&config_path_text);
+
+    borrowed_state.open_database_folder_button = Some(
+        builder
+            .object("open_database_folder_button")
+            .expect("Couldn't get 'open_database_folder_button'."),
+    );
+    borrowed_state.open_config_file_button = Some(
+        builder
+            .object("open_config_file_button")
+            .expect("Couldn't get 'open_config_file_button'."),
+    );
+    let open_config_file_button = borrowed_state.open_config_file_button.as_ref().unwrap();
+    open_config_file_button.set_sensitive(config_file_path.is_some());
+
     borrowed_state.window = Some(
         builder
             .object("window")
@@ -652,26 +1578,126 @@ fn construct_window(
 fn setup_signals(global_state: GlobalStateRcRefCell, global_entries: GlobalEntriesRcRefCell) {
     let borrowed_state = global_state.borrow_mut();
 
-    let week_number_spin_button = borrowed_state.week_number_spin_button.as_ref().unwrap();
-    week_number_spin_button.connect_value_changed(clone!(
+    let copy_button = borrowed_state.copy_button.as_ref().unwrap();
+    copy_button.connect_clicked(clone!(
+    @strong global_state =>
+        move |_widget| {
+            copy_button_clicked(global_state.clone())
+        }));
+
+    let save_text_button = borrowed_state.save_text_button.as_ref().unwrap();
+    save_text_button.connect_clicked(clone!(
+    @strong global_state =>
+        move |_widget| {
+            save_text_button_clicked(global_state.clone()).unwrap()
+        }));
+
+    let export_csv_button = borrowed_state.export_csv_button.as_ref().unwrap();
+    export_csv_button.connect_clicked(clone!(
+    @strong global_state, @strong global_entries =>
+        move |_widget| {
+            export_csv_button_clicked(global_state.clone(), global_entries.clone()).unwrap()
+        }));
+
+    let save_layout_button = borrowed_state.save_layout_button.as_ref().unwrap();
+    save_layout_button.connect_clicked(clone!(
+    @strong global_state =>
+        move |_widget| {
+            save_layout_button_clicked(global_state.clone())
+        }));
+
+    let preferences_button = borrowed_state.preferences_button.as_ref().unwrap();
+    preferences_button.connect_clicked(clone!(
+    @strong global_state, @strong global_entries =>
+        move |_widget| {
+            preferences_button_clicked(global_state.clone(), global_entries.clone()).unwrap()
+        }));
+
+    let font_size_menu_items = [
+        (&borrowed_state.increase_font_size_menu_item, Some(1)),
+        (&borrowed_state.decrease_font_size_menu_item, Some(-1)),
+        (&borrowed_state.reset_font_size_menu_item, None),
+    ];
+    for (font_size_menu_item, change_points) in font_size_menu_items {
+        font_size_menu_item
+            .as_ref()
+            .unwrap()
+            .connect_activate(clone!(
+            @strong global_state =>
+                move |_widget| {
+                    font_size_menu_item_activated(global_state.clone(), change_points).unwrap()
+                }));
+    }
+
+    let dark_mode_menu_item = borrowed_state.dark_mode_menu_item.as_ref().unwrap();
+    dark_mode_menu_item.connect_toggled(clone!(
+    @strong global_state =>
+        move |widget| {
+            dark_mode_menu_item_toggled(widget, global_state.clone()).unwrap()
+        }));
+
+    let period_number_spin_button = borrowed_state.period_number_spin_button.as_ref().unwrap();
+    period_number_spin_button.connect_value_changed(clone!(
     @strong global_state, @strong global_entries =>
             move |widget| {
-                week_number_changed(&widget, global_state.clone(), global_entries.clone()).unwrap()
+                period_number_changed(widget, global_state.clone(), global_entries.clone()).unwrap()
             }));
 
+    let year_spin_button = borrowed_state.year_spin_button.as_ref().unwrap();
+    year_spin_button.connect_value_changed(clone!(
+    @strong global_state, @strong global_entries =>
+            move |widget| {
+                year_changed(widget, global_state.clone(), global_entries.clone()).unwrap()
+            }));
+
+    let display_period_combo_box = borrowed_state.display_period_combo_box.as_ref().unwrap();
+    display_period_combo_box.connect_changed(clone!(
+    @strong global_state, @strong global_entries =>
+        move |widget| {
+            display_period_changed(widget, global_state.clone(), global_entries.clone()).unwrap()
+        }));
+
     let format_date_time_combo_box = borrowed_state.format_date_time_combo_box.as_ref().unwrap();
     format_date_time_combo_box.connect_changed(clone!(
     @strong global_state, @strong global_entries =>
         move |widget| {
-            format_date_time_changed(&widget, global_state.clone(), global_entries.clone()).unwrap()
+            format_date_time_changed(widget, global_state.clone(), global_entries.clone()).unwrap()
         }));
 
     let format_duration_combo_box = borrowed_state.format_duration_combo_box.as_ref().unwrap();
     format_duration_combo_box.connect_changed(clone!(
     @strong global_state, @strong global_entries =>
         move |widget| {
-            format_duration_changed(&widget, global_state.clone(), global_entries.clone()).unwrap()
+            format_duration_changed(widget, global_state.clone(), global_entries.clone()).unwrap()
         }));
+
+    let purge_buttons = [
+        (&borrowed_state.purge_last_hour_button, PurgeRange::LastHour),
+        (&borrowed_state.purge_last_day_button, PurgeRange::LastDay),
+        (&borrowed_state.purge_all_button, PurgeRange::All),
+    ];
+    for (purge_button, purge_range) in purge_buttons {
+        purge_button.as_ref().unwrap().connect_clicked(clone!(
+        @strong global_state, @strong global_entries =>
+            move |_widget| {
+                purge_button_clicked(purge_range, global_state.clone(), global_entries.clone()).unwrap()
+            }));
+    }
+
+    let open_location_buttons = [
+        (&borrowed_state.open_database_folder_button, true),
+        (&borrowed_state.open_config_file_button, false),
+    ];
+    for (open_location_button, open_database_folder) in open_location_buttons {
+        open_location_button
+            .as_ref()
+            .unwrap()
+            .connect_clicked(clone!(
+            @strong global_state =>
+                move |_widget| {
+                    open_location_button_clicked(global_state.clone(), open_database_folder)
+                }));
+    }
 }
 
 pub fn build_ui(
@@ -682,7 +1708,25 @@ pub fn build_ui(
     let window = construct_window(global_state.clone(), global_entries.clone());
     window.set_application(Some(app));
 
+    // The weeks are loaded in a worker thread, and the results are
+    // received on the GTK main thread.
+    #[allow(deprecated)]
+    let (load_sender, load_receiver) = glib::MainContext::channel(glib::Priority::DEFAULT);
+    global_state.borrow_mut().load_sender = Some(load_sender);
+    load_receiver.attach(
+        None,
+        clone!(@strong global_state, @strong global_entries => move |message| {
+            period_loaded(message, global_state.clone(), global_entries.clone());
+            glib::ControlFlow::Continue
+        }),
+    );
+
     setup_signals(global_state.clone(), global_entries.clone());
 
+    if !request_privacy_consent(global_state.clone()).unwrap() {
+        app.quit();
+        return;
+    }
+
     window_startup(&window, global_state.clone(), global_entries.clone()).unwrap();
 }
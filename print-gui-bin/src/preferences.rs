@@ -0,0 +1,296 @@
+use crate::constants;
+use crate::constants::TIME_BLOCK_UNIT_FIFTEEN_MINUTES_ID;
+use crate::constants::TIME_BLOCK_UNIT_FIFTEEN_MINUTES_LABEL;
+use crate::constants::TIME_BLOCK_UNIT_FIVE_MINUTES_ID;
+use crate::constants::TIME_BLOCK_UNIT_FIVE_MINUTES_LABEL;
+use crate::constants::TIME_BLOCK_UNIT_SIXTY_MINUTES_ID;
+use crate::constants::TIME_BLOCK_UNIT_SIXTY_MINUTES_LABEL;
+use crate::constants::TIME_BLOCK_UNIT_TEN_MINUTES_ID;
+use crate::constants::TIME_BLOCK_UNIT_TEN_MINUTES_LABEL;
+use crate::constants::TIME_BLOCK_UNIT_THIRTY_MINUTES_ID;
+use crate::constants::TIME_BLOCK_UNIT_THIRTY_MINUTES_LABEL;
+use crate::settings::PrintGuiAppSettings;
+use crate::utils::id_as_time_block_unit;
+use crate::utils::time_block_unit_as_id;
+
+use anyhow::{bail, Result};
+use gtk::prelude::*;
+use gtk::{
+    ApplicationWindow, Box, ButtonsType, CheckButton, ComboBoxText, Dialog, DialogFlags, Entry,
+    Grid, Label, MessageDialog, MessageType, Orientation, ResponseType, ScrolledWindow, SpinButton,
+};
+use std::path::Path;
+
+use timetracker_core::format::TimeBlockUnit;
+use timetracker_core::settings::write_configuration_file_values;
+use timetracker_core::settings::SettingValue;
+use timetracker_core::storage::ENVIRONMENT_VARIABLE_NAMES_MAX_COUNT;
+
+/// The settings edited in the "Preferences" dialog, and saved to the
+/// configuration file.
+#[derive(Debug, Clone)]
+pub struct Preferences {
+    pub environment_variable_names: Vec<String>,
+    pub database_dir: String,
+    pub database_file_name: String,
+    pub time_block_unit: TimeBlockUnit,
+    pub bar_graph_character_num_width: u8,
+    pub display_presets: Vec<String>,
+}
+
+impl Preferences {
+    pub fn from_settings(settings: &PrintGuiAppSettings) -> Preferences {
+        Preferences {
+            environment_variable_names: settings.core.environment_variables.names.clone(),
+            database_dir: settings.core.database_dir.clone(),
+            database_file_name: settings.core.database_file_name.clone(),
+            time_block_unit: settings.print.time_block_unit,
+            bar_graph_character_num_width: settings.print.bar_graph_character_num_width,
+            display_presets: settings.print.display_presets.clone(),
+        }
+    }
+
+    pub fn apply_to_settings(&self, settings: &mut PrintGuiAppSettings) {
+        settings.core.environment_variables.names = self.environment_variable_names.clone();
+        settings.core.database_dir = self.database_dir.clone();
+        settings.core.database_file_name = self.database_file_name.clone();
+        settings.print.time_block_unit = self.time_block_unit;
+        settings.print.bar_graph_character_num_width = self.bar_graph_character_num_width;
+        settings.print.display_presets = self.display_presets.clone();
+    }
+
+    /// Are the database directory or file name different?
+    pub fn has_database_changed(&self, other: &Preferences) -> bool {
+        self.database_dir != other.database_dir
+            || self.database_file_name != other.database_file_name
+    }
+
+    fn validate(&self) -> Result<()> {
+        let envvar_name_count = self.environment_variable_names.len();
+        if envvar_name_count > ENVIRONMENT_VARIABLE_NAMES_MAX_COUNT {
+            bail!(
+                "Timetracker only supports at most {} environment variables, found {}.",
+                ENVIRONMENT_VARIABLE_NAMES_MAX_COUNT,
+                envvar_name_count
+            );
+        }
+        let is_valid_name =
+            |name: &String| name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if let Some(name) = self
+            .environment_variable_names
+            .iter()
+            .find(|x| !is_valid_name(x))
+        {
+            bail!(
+                "Environment variable name {:?} is invalid; use letters, numbers and '_'.",
+                name
+            );
+        }
+        if self.database_dir.trim().is_empty() {
+            bail!("The database directory is empty.");
+        }
+        if self.database_file_name.trim().is_empty() {
+            bail!("The database file name is empty.");
+        }
+        Ok(())
+    }
+
+    /// Write the preferences into the configuration file, keeping the
+    /// other settings of the file.
+    pub fn write_configuration_file(&self, file_path: &Path) -> Result<()> {
+        let values = [
+            (
+                "core.environment_variables.names",
+                SettingValue::StringList(self.environment_variable_names.clone()),
+            ),
+            (
+                "core.database_dir",
+                SettingValue::String(self.database_dir.clone()),
+            ),
+            (
+                "core.database_file_name",
+                SettingValue::String(self.database_file_name.clone()),
+            ),
+            (
+                "print.time_block_unit",
+                SettingValue::String(self.time_block_unit.to_string()),
+            ),
+            (
+                "print.bar_graph_character_num_width",
+                SettingValue::Integer(self.bar_graph_character_num_width.into()),
+            ),
+            (
+                "print.display_presets",
+                SettingValue::StringList(self.display_presets.clone()),
+            ),
+        ];
+        write_configuration_file_values(file_path, &values)?;
+        Ok(())
+    }
+}
+
+/// Split the text into the environment variable names, such as
+/// "PROJECT, SHOT" into "PROJECT" and "SHOT".
+fn parse_environment_variable_names(text: &str) -> Vec<String> {
+    text.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|x| !x.is_empty())
+        .map(|x| x.to_string())
+        .collect()
+}
+
+fn attach_labelled_widget(grid: &Grid, row: i32, label: &str, widget: &impl IsA<gtk::Widget>) {
+    let label_widget = Label::new(Some(label));
+    label_widget.set_halign(gtk::Align::End);
+    grid.attach(&label_widget, 0, row, 1, 1);
+    widget.set_hexpand(true);
+    grid.attach(widget, 1, row, 1, 1);
+}
+
+fn show_invalid_preferences_message(dialog: &Dialog, message: &str) {
+    let message_dialog = MessageDialog::new(
+        Some(dialog),
+        DialogFlags::MODAL,
+        MessageType::Error,
+        ButtonsType::Ok,
+        "Preferences are invalid.",
+    );
+    message_dialog.set_secondary_text(Some(message));
+    message_dialog.run();
+    message_dialog.close();
+}
+
+/// Show the "Preferences" dialog, returning the edited preferences
+/// when "Save" is clicked, or None when cancelled.
+///
+/// The displayed presets can be any of the 'all_preset_names', and
+/// are kept in that order.
+pub fn run_preferences_dialog(
+    window: Option<&ApplicationWindow>,
+    preferences: &Preferences,
+    all_preset_names: &[String],
+) -> Option<Preferences> {
+    let dialog = Dialog::with_buttons(
+        Some(constants::PREFERENCES_DIALOG_TITLE),
+        window,
+        DialogFlags::MODAL | DialogFlags::DESTROY_WITH_PARENT,
+        &[
+            ("_Cancel", ResponseType::Cancel),
+            ("_Save", ResponseType::Accept),
+        ],
+    );
+    dialog.set_default_response(ResponseType::Accept);
+
+    let grid = Grid::new();
+    grid.set_row_spacing(5);
+    grid.set_column_spacing(10);
+    grid.set_border_width(10);
+
+    let environment_variables_entry = Entry::new();
+    environment_variables_entry.set_text(&preferences.environment_variable_names.join(", "));
+    environment_variables_entry.set_tooltip_text(Some(&format!(
+        "The environment variables recorded for each program (such as \"PROJECT, SHOT\"), at most {}. \
+         The recorder must be restarted to record the changed variables.",
+        ENVIRONMENT_VARIABLE_NAMES_MAX_COUNT
+    )));
+    attach_labelled_widget(
+        &grid,
+        0,
+        "Environment Variables",
+        &environment_variables_entry,
+    );
+
+    let database_dir_entry = Entry::new();
+    database_dir_entry.set_text(&preferences.database_dir);
+    database_dir_entry.set_tooltip_text(Some(
+        "The directory of the database file (such as \"~/.config\"). \
+         The recorder must be restarted to record into the changed database.",
+    ));
+    attach_labelled_widget(&grid, 1, "Database Directory", &database_dir_entry);
+
+    let database_file_name_entry = Entry::new();
+    database_file_name_entry.set_text(&preferences.database_file_name);
+    attach_labelled_widget(&grid, 2, "Database File Name", &database_file_name_entry);
+
+    let time_block_unit_combo_box = ComboBoxText::new();
+    let time_block_units = [
+        (
+            TIME_BLOCK_UNIT_FIVE_MINUTES_ID,
+            TIME_BLOCK_UNIT_FIVE_MINUTES_LABEL,
+        ),
+        (
+            TIME_BLOCK_UNIT_TEN_MINUTES_ID,
+            TIME_BLOCK_UNIT_TEN_MINUTES_LABEL,
+        ),
+        (
+            TIME_BLOCK_UNIT_FIFTEEN_MINUTES_ID,
+            TIME_BLOCK_UNIT_FIFTEEN_MINUTES_LABEL,
+        ),
+        (
+            TIME_BLOCK_UNIT_THIRTY_MINUTES_ID,
+            TIME_BLOCK_UNIT_THIRTY_MINUTES_LABEL,
+        ),
+        (
+            TIME_BLOCK_UNIT_SIXTY_MINUTES_ID,
+            TIME_BLOCK_UNIT_SIXTY_MINUTES_LABEL,
+        ),
+    ];
+    for (id, label) in time_block_units {
+        time_block_unit_combo_box.append(Some(id), label);
+    }
+    time_block_unit_combo_box
+        .set_active_id(Some(time_block_unit_as_id(preferences.time_block_unit)));
+    attach_labelled_widget(&grid, 3, "Time Block Unit", &time_block_unit_combo_box);
+
+    let bar_width_spin_button = SpinButton::with_range(1.0, u8::MAX.into(), 1.0);
+    bar_width_spin_button.set_value(preferences.bar_graph_character_num_width.into());
+    bar_width_spin_button.set_tooltip_text(Some(
+        "The width (in characters) of the bar graphs of the presets.",
+    ));
+    attach_labelled_widget(&grid, 4, "Bar Graph Width", &bar_width_spin_button);
+
+    let presets_layout = Box::new(Orientation::Vertical, 2);
+    let mut preset_check_buttons = Vec::new();
+    for preset_name in all_preset_names {
+        let check_button = CheckButton::with_label(preset_name);
+        check_button.set_active(preferences.display_presets.contains(preset_name));
+        presets_layout.add(&check_button);
+        preset_check_buttons.push((preset_name.clone(), check_button));
+    }
+    let presets_scrolled_window = ScrolledWindow::builder()
+        .min_content_height(200)
+        .child(&presets_layout)
+        .build();
+    attach_labelled_widget(&grid, 5, "Displayed Presets", &presets_scrolled_window);
+
+    dialog.content_area().add(&grid);
+    dialog.show_all();
+
+    let edited_preferences = loop {
+        if dialog.run() != ResponseType::Accept {
+            break None;
+        }
+
+        let edited_preferences = Preferences {
+            environment_variable_names: parse_environment_variable_names(
+                &environment_variables_entry.text(),
+            ),
+            database_dir: database_dir_entry.text().trim().to_string(),
+            database_file_name: database_file_name_entry.text().trim().to_string(),
+            time_block_unit: id_as_time_block_unit(time_block_unit_combo_box.active_id().as_ref())
+                .unwrap_or(preferences.time_block_unit),
+            bar_graph_character_num_width: bar_width_spin_button.value_as_int() as u8,
+            display_presets: preset_check_buttons
+                .iter()
+                .filter(|(_preset_name, check_button)| check_button.is_active())
+                .map(|(preset_name, _check_button)| preset_name.clone())
+                .collect(),
+        };
+        match edited_preferences.validate() {
+            Ok(()) => break Some(edited_preferences),
+            Err(err) => show_invalid_preferences_message(&dialog, &err.to_string()),
+        }
+    };
+    dialog.close();
+
+    edited_preferences
+}
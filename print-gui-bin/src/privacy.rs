@@ -0,0 +1,119 @@
+use crate::constants::PRIVACY_NOTICE_VERSION;
+
+use anyhow::{anyhow, Result};
+use log::debug;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use timetracker_core::entries::PrivacyConsent;
+use timetracker_core::filesystem::get_database_file_path;
+use timetracker_core::filesystem::get_database_file_paths_for_range;
+use timetracker_core::multi_storage::MultiStorage;
+use timetracker_core::settings::CoreSettings;
+use timetracker_core::settings::DEFAULT_RECORD_INTERVAL_SECONDS;
+use timetracker_core::storage::Storage;
+
+const ONE_HOUR_SECONDS: u64 = 60 * 60;
+const ONE_DAY_SECONDS: u64 = 24 * ONE_HOUR_SECONDS;
+
+/// How much recorded data should be purged (deleted)?
+#[derive(Debug, Copy, Clone)]
+pub enum PurgeRange {
+    /// The data recorded in the last hour.
+    LastHour,
+    /// The data recorded in the last 24 hours.
+    LastDay,
+    /// All recorded data.
+    All,
+}
+
+impl PurgeRange {
+    pub fn description(&self) -> &'static str {
+        match self {
+            PurgeRange::LastHour => "the last hour",
+            PurgeRange::LastDay => "the last day",
+            PurgeRange::All => "all time",
+        }
+    }
+}
+
+fn now_utc_time_seconds() -> Result<u64> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs())
+}
+
+/// The un-rotated database file, which stores the privacy consent
+/// (even when per-year database files are used).
+fn consent_database_file_path(core_settings: &CoreSettings) -> Result<PathBuf> {
+    get_database_file_path(
+        &core_settings.database_dir,
+        &core_settings.database_file_name,
+    )
+    .ok_or_else(|| {
+        anyhow!(
+            "Database directory {:?} not found.",
+            core_settings.database_dir
+        )
+    })
+}
+
+/// Has the user agreed to the current privacy notice?
+pub fn has_privacy_consent(core_settings: &CoreSettings) -> Result<bool> {
+    let database_file_path = consent_database_file_path(core_settings)?;
+    if !database_file_path.is_file() {
+        return Ok(false);
+    }
+
+    let storage = Storage::open_as_read_only(&database_file_path, DEFAULT_RECORD_INTERVAL_SECONDS)?;
+    let privacy_consent = storage.read_privacy_consent()?;
+    storage.close()?;
+    debug!("Privacy consent: {:?}", privacy_consent);
+
+    Ok(privacy_consent.is_some_and(|x| x.notice_version >= PRIVACY_NOTICE_VERSION))
+}
+
+/// Record that the user agreed to the current privacy notice, now.
+pub fn write_privacy_consent(core_settings: &CoreSettings) -> Result<PrivacyConsent> {
+    let database_file_path = consent_database_file_path(core_settings)?;
+    let privacy_consent = PrivacyConsent::new(now_utc_time_seconds()?, PRIVACY_NOTICE_VERSION);
+
+    let mut storage =
+        Storage::open_as_read_write(&database_file_path, DEFAULT_RECORD_INTERVAL_SECONDS)?;
+    storage.write_privacy_consent(&privacy_consent)?;
+    storage.close()?;
+
+    Ok(privacy_consent)
+}
+
+/// Delete the recorded data in the range, from all database files.
+pub fn purge_data(core_settings: &CoreSettings, purge_range: PurgeRange) -> Result<()> {
+    let database_file_path = consent_database_file_path(core_settings)?;
+    let now_utc_time_seconds = now_utc_time_seconds()?;
+    let start_utc_time_seconds = match purge_range {
+        PurgeRange::LastHour => now_utc_time_seconds.saturating_sub(ONE_HOUR_SECONDS),
+        PurgeRange::LastDay => now_utc_time_seconds.saturating_sub(ONE_DAY_SECONDS),
+        PurgeRange::All => 0,
+    };
+
+    let database_file_paths = get_database_file_paths_for_range(
+        &database_file_path,
+        core_settings.database_per_year,
+        &core_settings.archive_dir,
+        start_utc_time_seconds,
+        now_utc_time_seconds,
+    );
+    let mut storage =
+        MultiStorage::open_as_read_write(&database_file_paths, DEFAULT_RECORD_INTERVAL_SECONDS)?;
+    match purge_range {
+        PurgeRange::All => storage.delete_all_data()?,
+        _ => {
+            // Everything after the start time is deleted.
+            let end_utc_time_seconds = i64::MAX as u64;
+            let count = storage.delete_entries(start_utc_time_seconds, end_utc_time_seconds)?;
+            debug!("Purged {} entries.", count);
+        }
+    }
+    storage.close()?;
+
+    Ok(())
+}
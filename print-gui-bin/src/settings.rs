@@ -9,6 +9,7 @@ use timetracker_core::settings::new_core_settings;
 use timetracker_core::settings::new_print_gui_settings;
 use timetracker_core::settings::validate_core_settings;
 use timetracker_core::settings::CoreSettings;
+use timetracker_core::settings::GuiSettings;
 use timetracker_core::settings::PrintSettings;
 
 // This command arguments are similar to the timetracker-print
@@ -53,11 +54,12 @@ pub struct CommandArguments {
     pub database_file_name: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[allow(unused)]
 pub struct PrintGuiAppSettings {
     pub core: CoreSettings,
     pub print: PrintSettings,
+    pub gui: GuiSettings,
 }
 
 impl PrintGuiAppSettings {
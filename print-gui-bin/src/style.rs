@@ -0,0 +1,79 @@
+use crate::constants::REPORT_CSS_CLASS;
+
+use gtk::prelude::*;
+use gtk::{TextBuffer, TextTag};
+
+/// The characters drawn by the bar graphs of the presets (such as
+/// "Activity" presets).
+const BAR_GRAPH_CHARACTERS: [char; 4] = ['.', '-', 'x', 'X'];
+
+/// The fewest bar graph characters in a row that are a bar graph, so
+/// the characters of words (such as "x" of "firefox") are not
+/// colored.
+const BAR_GRAPH_MIN_CHARACTER_COUNT: usize = 3;
+
+/// The CSS of the report text view, with the font size (in points)
+/// and the dark or light colors.
+pub fn report_css(font_size: u32, dark_mode: bool) -> String {
+    let (background_color, text_color) = match dark_mode {
+        true => ("#1e1e1e", "#d4d4d4"),
+        false => ("#ffffff", "#1e1e1e"),
+    };
+    format!(
+        "textview.{class} {{ font-size: {size}pt; }}\n\
+         textview.{class} text {{ background-color: {background}; color: {text}; }}\n",
+        class = REPORT_CSS_CLASS,
+        size = font_size,
+        background = background_color,
+        text = text_color,
+    )
+}
+
+/// The color of the bar graph characters, readable on the background
+/// of the report text view.
+pub fn bar_graph_color(dark_mode: bool) -> &'static str {
+    match dark_mode {
+        true => "#6cb6ff",
+        false => "#0050a0",
+    }
+}
+
+/// Find the (character) offsets of the start and end of each bar
+/// graph in the line.
+fn find_bar_graph_ranges(line: &str) -> Vec<(i32, i32)> {
+    let mut ranges = Vec::new();
+    let mut run_start = None;
+    // The end of the line ends the last bar graph.
+    let characters = line.chars().chain(std::iter::once('\n'));
+    for (offset, character) in characters.enumerate() {
+        match (BAR_GRAPH_CHARACTERS.contains(&character), run_start) {
+            (true, None) => run_start = Some(offset),
+            (false, Some(start)) => {
+                if offset - start >= BAR_GRAPH_MIN_CHARACTER_COUNT {
+                    ranges.push((start as i32, offset as i32));
+                }
+                run_start = None;
+            }
+            _ => (),
+        }
+    }
+    ranges
+}
+
+/// Apply the tag to the bar graphs of the text in the buffer.
+pub fn apply_bar_graph_tag(text_buffer: &TextBuffer, tag: &TextTag) {
+    let (start, end) = text_buffer.bounds();
+    text_buffer.remove_tag(tag, &start, &end);
+    let text = match text_buffer.text(&start, &end, false) {
+        Some(value) => value,
+        None => return,
+    };
+
+    for (line_index, line) in text.lines().enumerate() {
+        for (start_offset, end_offset) in find_bar_graph_ranges(line) {
+            let start = text_buffer.iter_at_line_offset(line_index as i32, start_offset);
+            let end = text_buffer.iter_at_line_offset(line_index as i32, end_offset);
+            text_buffer.apply_tag(tag, &start, &end);
+        }
+    }
+}
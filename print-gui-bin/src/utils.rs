@@ -1,25 +1,106 @@
 use crate::constants::DATETIME_FORMAT_ISO_ID;
 use crate::constants::DATETIME_FORMAT_LOCALE_ID;
 use crate::constants::DATETIME_FORMAT_USA_MONTH_DAY_YEAR_ID;
+use crate::constants::DISPLAY_PERIOD_MONTH_ID;
+use crate::constants::DISPLAY_PERIOD_QUARTER_ID;
+use crate::constants::DISPLAY_PERIOD_WEEK_ID;
 use crate::constants::DURATION_FORMAT_DECIMAL_HOURS_ID;
 use crate::constants::DURATION_FORMAT_HOURS_MINUTES_ID;
 use crate::constants::DURATION_FORMAT_HOURS_MINUTES_SECONDS_ID;
+use crate::constants::TIME_BLOCK_UNIT_FIFTEEN_MINUTES_ID;
+use crate::constants::TIME_BLOCK_UNIT_FIVE_MINUTES_ID;
+use crate::constants::TIME_BLOCK_UNIT_SIXTY_MINUTES_ID;
+use crate::constants::TIME_BLOCK_UNIT_TEN_MINUTES_ID;
+use crate::constants::TIME_BLOCK_UNIT_THIRTY_MINUTES_ID;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use chrono::Datelike;
 
 use timetracker_core::format::DateTimeFormat;
 use timetracker_core::format::DurationFormat;
+use timetracker_core::format::FirstDayOfWeek;
+use timetracker_core::format::TimeBlockUnit;
+use timetracker_print_lib::datetime::get_datetime_range;
 use timetracker_print_lib::datetime::get_week_datetime_local;
 use timetracker_print_lib::datetime::DateTimeLocalPair;
 
-/// Convert the week number into a start datetime and end datetime.
-///
-/// Assumes the week number is contained in the current year.
-pub fn get_absolute_week_start_end(week_num: u32) -> Result<DateTimeLocalPair> {
-    let today_local_timezone = chrono::Local::now();
-    let today_year = today_local_timezone.year();
-    Ok(get_week_datetime_local(today_year, week_num))
+/// The length of the period of time displayed in the window.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DisplayPeriod {
+    Week,
+    Month,
+    Quarter,
+}
+
+impl DisplayPeriod {
+    /// The label of the period number, such as "Week #".
+    pub fn number_label(self) -> &'static str {
+        match self {
+            DisplayPeriod::Week => "     Week #",
+            DisplayPeriod::Month => "     Month #",
+            DisplayPeriod::Quarter => "     Quarter #",
+        }
+    }
+
+    /// The number of periods in the year, such as the 52 (or 53)
+    /// weeks of the year.
+    pub fn count_in_year(self, year: i32) -> u32 {
+        match self {
+            // The 28th of December is always in the last (ISO) week
+            // of the year.
+            DisplayPeriod::Week => chrono::NaiveDate::from_ymd_opt(year, 12, 28)
+                .map(|date| date.iso_week().week())
+                .unwrap_or(52),
+            DisplayPeriod::Month => 12,
+            DisplayPeriod::Quarter => 4,
+        }
+    }
+}
+
+/// Convert the period number (such as the week number) of the year
+/// into a start datetime and end datetime.
+pub fn get_absolute_period_start_end(
+    period: DisplayPeriod,
+    year: i32,
+    number: u32,
+    first_day_of_week: FirstDayOfWeek,
+) -> Result<DateTimeLocalPair> {
+    let number = number.clamp(1, period.count_in_year(year));
+    let (start_month, month_count) = match period {
+        DisplayPeriod::Week => {
+            return Ok(get_week_datetime_local(year, number, first_day_of_week));
+        }
+        DisplayPeriod::Month => (number, 1),
+        DisplayPeriod::Quarter => ((number - 1) * 3 + 1, 3),
+    };
+
+    let start_date = chrono::NaiveDate::from_ymd_opt(year, start_month, 1)
+        .ok_or_else(|| anyhow!("Invalid year {} and month {}.", year, start_month))?;
+    let end_date = start_date
+        .checked_add_months(chrono::Months::new(month_count))
+        .and_then(|date| date.pred_opt())
+        .ok_or_else(|| anyhow!("Invalid year {} and month {}.", year, start_month))?;
+    Ok(get_datetime_range(start_date, end_date)?)
+}
+
+pub fn display_period_as_id(value: DisplayPeriod) -> &'static str {
+    match value {
+        DisplayPeriod::Week => DISPLAY_PERIOD_WEEK_ID,
+        DisplayPeriod::Month => DISPLAY_PERIOD_MONTH_ID,
+        DisplayPeriod::Quarter => DISPLAY_PERIOD_QUARTER_ID,
+    }
+}
+
+pub fn id_as_display_period(value: Option<&glib::GString>) -> Option<DisplayPeriod> {
+    match value {
+        Some(v) => match v.as_str() {
+            DISPLAY_PERIOD_WEEK_ID => Some(DisplayPeriod::Week),
+            DISPLAY_PERIOD_MONTH_ID => Some(DisplayPeriod::Month),
+            DISPLAY_PERIOD_QUARTER_ID => Some(DisplayPeriod::Quarter),
+            &_ => None,
+        },
+        None => None,
+    }
 }
 
 pub fn datetime_format_as_id(value: DateTimeFormat) -> &'static str {
@@ -61,3 +142,27 @@ pub fn id_as_duration_format(value: Option<&glib::GString>) -> Option<DurationFo
         None => None,
     }
 }
+
+pub fn time_block_unit_as_id(value: TimeBlockUnit) -> &'static str {
+    match value {
+        TimeBlockUnit::FiveMinutes => TIME_BLOCK_UNIT_FIVE_MINUTES_ID,
+        TimeBlockUnit::TenMinutes => TIME_BLOCK_UNIT_TEN_MINUTES_ID,
+        TimeBlockUnit::FifteenMinutes => TIME_BLOCK_UNIT_FIFTEEN_MINUTES_ID,
+        TimeBlockUnit::ThirtyMinutes => TIME_BLOCK_UNIT_THIRTY_MINUTES_ID,
+        TimeBlockUnit::SixtyMinutes => TIME_BLOCK_UNIT_SIXTY_MINUTES_ID,
+    }
+}
+
+pub fn id_as_time_block_unit(value: Option<&glib::GString>) -> Option<TimeBlockUnit> {
+    match value {
+        Some(v) => match v.as_str() {
+            TIME_BLOCK_UNIT_FIVE_MINUTES_ID => Some(TimeBlockUnit::FiveMinutes),
+            TIME_BLOCK_UNIT_TEN_MINUTES_ID => Some(TimeBlockUnit::TenMinutes),
+            TIME_BLOCK_UNIT_FIFTEEN_MINUTES_ID => Some(TimeBlockUnit::FifteenMinutes),
+            TIME_BLOCK_UNIT_THIRTY_MINUTES_ID => Some(TimeBlockUnit::ThirtyMinutes),
+            TIME_BLOCK_UNIT_SIXTY_MINUTES_ID => Some(TimeBlockUnit::SixtyMinutes),
+            &_ => None,
+        },
+        None => None,
+    }
+}
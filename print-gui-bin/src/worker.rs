@@ -0,0 +1,250 @@
+use crate::settings::PrintGuiAppSettings;
+
+use anyhow::Result;
+use gtk::glib;
+use log::warn;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use timetracker_core::filesystem::get_database_file_path;
+use timetracker_core::filesystem::get_database_file_paths_for_range;
+use timetracker_core::multi_storage::MultiStorage;
+use timetracker_core::settings::PrintPresetSettings;
+use timetracker_core::settings::DEFAULT_RECORD_INTERVAL_SECONDS;
+use timetracker_core::storage::Entries;
+use timetracker_print_lib::aggregate::get_map_keys_sorted_strings;
+use timetracker_print_lib::chart::generate_stacked_bar_chart;
+use timetracker_print_lib::chart::StackedBarChart;
+use timetracker_print_lib::datetime::DateTimeLocalPair;
+use timetracker_print_lib::mapping::VariableMappings;
+use timetracker_print_lib::preset::create_presets;
+use timetracker_print_lib::preset::generate_presets;
+use timetracker_print_lib::preset::get_presets_datetime_range;
+use timetracker_print_lib::ramp::ExecutableColors;
+use timetracker_print_lib::variable::Variable;
+
+/// The entries cached for each displayed period (such as a week).
+///
+/// The cached entries are shared (not copied) with the worker thread,
+/// because a week may have hundreds of thousands of entries.
+pub type MapPeriodEntries = HashMap<DateTimeLocalPair, Arc<Entries>>;
+
+/// A displayed period (such as a week) to load (and generate the text
+/// and chart of) in the worker thread.
+pub struct LoadRequest {
+    /// Increases with every request, so the results of the older
+    /// requests (such as when the week is changed quickly) are
+    /// ignored.
+    pub generation: u64,
+    pub period_datetime_pair: DateTimeLocalPair,
+    /// The entries already read for the period, if they cover the
+    /// range needed by the presets.
+    pub cached_entries: Option<Arc<Entries>>,
+    pub settings: PrintGuiAppSettings,
+}
+
+/// The entries read for a displayed period, and the text and chart
+/// generated from the entries.
+pub struct LoadedPeriod {
+    pub entries: Arc<Entries>,
+    pub text: String,
+    pub chart: StackedBarChart,
+    pub duration_seconds: f32,
+}
+
+/// Sent from the worker thread to the GTK main thread, when a
+/// 'LoadRequest' has finished.
+pub struct LoadMessage {
+    pub generation: u64,
+    pub period_datetime_pair: DateTimeLocalPair,
+    pub result: Result<LoadedPeriod>,
+}
+
+pub fn create_display_presets(
+    settings: &PrintGuiAppSettings,
+) -> Result<(Vec<PrintPresetSettings>, Vec<String>)> {
+    let presets = create_presets(
+        settings.print.time_scale,
+        settings.print.format_datetime,
+        settings.print.format_duration,
+        settings.print.time_block_unit,
+        settings.print.bar_graph_character_num_width,
+        settings.print.use_color,
+        settings.print.sort_by,
+        &settings.core.environment_variables.names,
+        &settings.print.display_presets,
+        &settings.print.presets,
+    )?;
+    Ok(presets)
+}
+
+/// Find the cached Storage entries for a given period. This ensures we
+/// never fetch the same data from the database twice (while the GUI
+/// is running).
+///
+/// Currently, to clear the cache, the program must be restarted (or
+/// data purged).
+///
+/// This optimisation assumes that fetching data from the database is
+/// likely the slowest runtime (which it almost always is, unless a
+/// trivial database entry is used).
+pub fn find_cached_entries(
+    period_datetime_pair: DateTimeLocalPair,
+    settings: &PrintGuiAppSettings,
+    entries_cache: &MapPeriodEntries,
+) -> Result<Option<Arc<Entries>>> {
+    // Presets may need entries outside of the period (such as a
+    // whole month), so the cached entries are only used when they cover
+    // the range needed.
+    let (presets, _missing_preset_names) = create_display_presets(settings)?;
+    let (start_datetime, end_datetime) = get_presets_datetime_range(
+        &presets,
+        period_datetime_pair,
+        settings.core.first_day_of_week,
+    )?;
+
+    match entries_cache.get(&period_datetime_pair) {
+        Some(period_entries)
+            if period_entries.start_datetime() <= start_datetime
+                && period_entries.end_datetime() >= end_datetime =>
+        {
+            Ok(Some(Arc::clone(period_entries)))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Read the Storage entries needed by the presets for a given period.
+fn read_entries(
+    period_datetime_pair: DateTimeLocalPair,
+    settings: &PrintGuiAppSettings,
+) -> Result<Entries> {
+    let (presets, _missing_preset_names) = create_display_presets(settings)?;
+    let (start_datetime, end_datetime) = get_presets_datetime_range(
+        &presets,
+        period_datetime_pair,
+        settings.core.first_day_of_week,
+    )?;
+
+    let core_settings = &settings.core;
+    let database_file_path = get_database_file_path(
+        &core_settings.database_dir,
+        &core_settings.database_file_name,
+    );
+    if database_file_path.is_none() {
+        warn!(
+            "Database file {:?} not found in {:?}",
+            core_settings.database_file_name, core_settings.database_dir
+        );
+    }
+
+    let start_of_time = start_datetime.timestamp() as u64;
+    let end_of_time = end_datetime.timestamp() as u64;
+
+    let database_file_paths = get_database_file_paths_for_range(
+        &database_file_path.expect("Database file path should be valid"),
+        core_settings.database_per_year,
+        &core_settings.archive_dir,
+        start_of_time,
+        end_of_time,
+    );
+    let mut storage =
+        MultiStorage::open_as_read_only(&database_file_paths, DEFAULT_RECORD_INTERVAL_SECONDS)?;
+    storage.set_use_recorded_utc_offset(core_settings.use_recorded_utc_offset);
+
+    let period_entries = storage.read_entries(start_of_time, end_of_time)?;
+    storage.close()?;
+    let variable_mappings = VariableMappings::new(&settings.print.mappings)?;
+    Ok(variable_mappings.map_entries(period_entries))
+}
+
+fn generate_text(
+    period_entries: &Entries,
+    period_datetime_pair: DateTimeLocalPair,
+    settings: &PrintGuiAppSettings,
+) -> Result<String> {
+    let (presets, missing_preset_names) = create_display_presets(settings)?;
+
+    let lines = generate_presets(
+        &presets,
+        period_entries,
+        period_datetime_pair,
+        &settings.print.utilization,
+        settings.core.work_hours_per_day,
+        settings.core.first_day_of_week,
+        // The text view cannot display colored text.
+        None,
+        &ExecutableColors::default(),
+    )?;
+    let all_lines_text = lines.join("\n");
+
+    if !missing_preset_names.is_empty() {
+        let all_preset_names = get_map_keys_sorted_strings(&settings.print.presets.keys());
+        warn!(
+            "Preset names {:?} are invalid. possible preset names are: {:?}",
+            missing_preset_names, all_preset_names,
+        );
+    }
+
+    Ok(all_lines_text)
+}
+
+/// Generate the stacked bar chart of the period (with a segment for
+/// each value of the first recorded variable).
+fn generate_chart(
+    entries: &Entries,
+    period_datetime_pair: DateTimeLocalPair,
+    settings: &PrintGuiAppSettings,
+) -> StackedBarChart {
+    let variable = match settings.core.environment_variables.names.first() {
+        Some(variable_name) => Variable::VariableName(variable_name.clone()),
+        None => Variable::Executable,
+    };
+    generate_stacked_bar_chart(
+        entries,
+        period_datetime_pair,
+        variable,
+        settings.print.format_datetime,
+        settings.print.format_duration,
+    )
+}
+
+fn load_period(request: &LoadRequest) -> Result<LoadedPeriod> {
+    let now = SystemTime::now();
+    let entries = match &request.cached_entries {
+        Some(entries) => Arc::clone(entries),
+        None => Arc::new(read_entries(
+            request.period_datetime_pair,
+            &request.settings,
+        )?),
+    };
+    let text = generate_text(&entries, request.period_datetime_pair, &request.settings)?;
+    let chart = generate_chart(&entries, request.period_datetime_pair, &request.settings);
+    let duration_seconds = now.elapsed()?.as_secs_f32();
+
+    Ok(LoadedPeriod {
+        entries,
+        text,
+        chart,
+        duration_seconds,
+    })
+}
+
+/// Read the entries and generate the text and chart of the period in
+/// a new thread, so the GTK main thread is not blocked, then send the
+/// result to the main thread.
+pub fn spawn_load_period(request: LoadRequest, sender: glib::Sender<LoadMessage>) {
+    std::thread::spawn(move || {
+        let result = load_period(&request);
+        let message = LoadMessage {
+            generation: request.generation,
+            period_datetime_pair: request.period_datetime_pair,
+            result,
+        };
+        // The window may have been closed while loading.
+        if sender.send(message).is_err() {
+            warn!("Could not send the loaded period to the window.");
+        }
+    });
+}
@@ -1,5 +1,6 @@
 use crate::datetime::utc_seconds_to_datetime_local;
 use crate::datetime::DateTimeLocalPair;
+use crate::utils::option_string_to_string;
 use crate::variable::combine_variable_values;
 use crate::variable::multi_variable_values;
 use crate::variable::Variable;
@@ -9,7 +10,10 @@ use std::collections::hash_map::Keys;
 use std::collections::HashMap;
 use timetracker_core::entries::Entry;
 use timetracker_core::entries::EntryStatus;
+use timetracker_core::entries::HostBusyPeriod;
+use timetracker_core::format::SortBy;
 use timetracker_core::format::TimeBlockUnit;
+use timetracker_core::settings::PrintPresetSettings;
 
 pub fn sum_entry_duration(entries: &[Entry], only_status: EntryStatus) -> chrono::Duration {
     let mut total_duration_seconds = 0;
@@ -56,6 +60,44 @@ pub fn sum_entry_variables_duration(
     map
 }
 
+/// Sum the durations of the entries for each combination of the
+/// variable values, like 'sum_entry_variables_duration', but with a
+/// value (empty when missing) for each of the 'variables', so the
+/// values can be written as columns.
+///
+/// The key of the entries without any of the variables is empty.
+pub fn sum_entry_variable_columns_duration(
+    entries: &[Entry],
+    variables: &[Variable],
+    only_status: EntryStatus,
+) -> HashMap<String, (Vec<String>, chrono::Duration)> {
+    let mut map = HashMap::<String, (Vec<String>, chrono::Duration)>::new();
+
+    for entry in entries {
+        if entry.status != only_status {
+            continue;
+        }
+
+        let values: Vec<String> = variables
+            .iter()
+            .map(|variable| combine_variable_values(entry, std::slice::from_ref(variable)))
+            .collect();
+        let key = match values.iter().all(|x| x.is_empty()) {
+            true => String::new(),
+            // A separator that cannot be typed, so different values
+            // never have the same key.
+            false => values.join("\u{1f}"),
+        };
+
+        let duration = chrono::Duration::seconds(entry.duration_seconds.try_into().unwrap());
+        map.entry(key)
+            .and_modify(|(_values, total)| *total += duration)
+            .or_insert((values, duration));
+    }
+
+    map
+}
+
 pub fn sum_entry_executable_duration(
     entries: &[Entry],
     only_status: EntryStatus,
@@ -64,6 +106,67 @@ pub fn sum_entry_executable_duration(
     sum_entry_variables_duration(entries, &variables, only_status)
 }
 
+/// Convert the durations summed for each value of a variable (such as
+/// by 'Storage::sum_durations_grouped_by') to the same map as
+/// 'sum_entry_executable_duration' returns for the entries, so the
+/// lines can be generated without reading the entries.
+///
+/// The key of the durations without a value is empty.
+pub fn duration_map_from_grouped_sums(
+    grouped_sums: &[(Option<String>, u64)],
+) -> HashMap<String, (Vec<String>, chrono::Duration)> {
+    let mut map = HashMap::<String, (Vec<String>, chrono::Duration)>::new();
+    for (value, duration_seconds) in grouped_sums {
+        let key = option_string_to_string(value);
+        let vars = match key.is_empty() {
+            true => Vec::new(),
+            false => vec![key.clone()],
+        };
+        let duration = chrono::Duration::seconds((*duration_seconds).try_into().unwrap());
+        map.entry(key)
+            .and_modify(|(_vars, total)| *total += duration)
+            .or_insert((vars, duration));
+    }
+    map
+}
+
+/// Sum the time of the entries (with the given status) that overlaps
+/// a period the machine was busy, for each busy reason.
+pub fn sum_entry_host_busy_duration(
+    entries: &[Entry],
+    host_busy_periods: &[HostBusyPeriod],
+    only_status: EntryStatus,
+) -> HashMap<String, chrono::Duration> {
+    let mut map = HashMap::<String, chrono::Duration>::new();
+    if host_busy_periods.is_empty() {
+        return map;
+    }
+
+    for entry in entries {
+        if entry.status != only_status {
+            continue;
+        }
+
+        let entry_start = entry.utc_time_seconds;
+        let entry_end = entry.utc_time_seconds + entry.duration_seconds;
+        for period in host_busy_periods {
+            let start = std::cmp::max(entry_start, period.utc_time_seconds);
+            let end = std::cmp::min(entry_end, period.end_utc_time_seconds());
+            if end <= start {
+                continue;
+            }
+
+            let duration = chrono::Duration::seconds((end - start).try_into().unwrap());
+            let total = map
+                .entry(period.reason.clone())
+                .or_insert_with(chrono::Duration::zero);
+            *total += duration;
+        }
+    }
+
+    map
+}
+
 fn utc_seconds_rounded(
     utc_time_seconds: u64,
     time_block_unit: TimeBlockUnit,
@@ -204,13 +307,389 @@ pub fn get_map_keys_sorted_strings<T>(map_keys: &Keys<String, T>) -> Vec<String>
     sorted_keys
 }
 
+/// The keys of the durations (from 'sum_entry_variables_duration'),
+/// sorted by name or by the longest duration first. Durations of the
+/// same length are sorted by name.
+///
+/// Ignores 'unknown' tasks; tasks without a valid value.
+pub fn get_duration_map_keys_sorted(
+    duration_map: &HashMap<String, (Vec<String>, chrono::Duration)>,
+    sort_by: SortBy,
+) -> Vec<String> {
+    let mut sorted_keys = get_map_keys_sorted_strings(&duration_map.keys());
+    if sort_by == SortBy::Duration {
+        // The sort is stable, so the keys stay sorted by name for
+        // equal durations.
+        sorted_keys.sort_by_key(|key| std::cmp::Reverse(duration_map[key].1));
+    }
+    sorted_keys
+}
+
+/// How the durations of a preset (such as of each executable) are
+/// listed; the order, and which durations are combined into a single
+/// "other" duration.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct DurationListOptions {
+    pub sort_by: SortBy,
+    /// Only list the N longest durations.
+    pub top_n: Option<usize>,
+    /// Only list the durations at least this long.
+    pub min_duration: Option<chrono::Duration>,
+    /// List the share of the total active time of each duration.
+    pub show_percentage: bool,
+}
+
+impl DurationListOptions {
+    pub fn from_preset(preset: &PrintPresetSettings) -> DurationListOptions {
+        DurationListOptions {
+            sort_by: preset.sort_by.unwrap_or_default(),
+            top_n: preset.top_n.map(|x| x as usize),
+            min_duration: preset
+                .min_duration_minutes
+                .map(|x| chrono::Duration::minutes(x as i64)),
+            show_percentage: preset.show_percentage.unwrap_or(false),
+        }
+    }
+}
+
+/// The keys of the durations listed (by 'get_duration_map_keys_listed'),
+/// with the number and total duration of the keys omitted.
+#[derive(Debug, Clone)]
+pub struct DurationListKeys {
+    pub keys: Vec<String>,
+    pub other_count: usize,
+    pub other_duration: chrono::Duration,
+}
+
+/// The keys of the durations (from 'sum_entry_variables_duration') to
+/// list, sorted, without the keys omitted by the 'top_n' and
+/// 'min_duration' options.
+///
+/// Ignores 'unknown' tasks; tasks without a valid value.
+pub fn get_duration_map_keys_listed(
+    duration_map: &HashMap<String, (Vec<String>, chrono::Duration)>,
+    options: DurationListOptions,
+) -> DurationListKeys {
+    let sorted_keys = get_duration_map_keys_sorted(duration_map, options.sort_by);
+    if options.top_n.is_none() && options.min_duration.is_none() {
+        return DurationListKeys {
+            keys: sorted_keys,
+            other_count: 0,
+            other_duration: chrono::Duration::zero(),
+        };
+    }
+
+    // The longest durations are kept, whatever the printed order.
+    let mut longest_keys = get_duration_map_keys_sorted(duration_map, SortBy::Duration);
+    if let Some(min_duration) = options.min_duration {
+        longest_keys.retain(|key| duration_map[key].1 >= min_duration);
+    }
+    if let Some(top_n) = options.top_n {
+        longest_keys.truncate(top_n);
+    }
+
+    let mut keys = Vec::new();
+    let mut other_count = 0;
+    let mut other_duration = chrono::Duration::zero();
+    for key in sorted_keys {
+        match longest_keys.contains(&key) {
+            true => keys.push(key),
+            false => {
+                other_count += 1;
+                other_duration += duration_map[&key].1;
+            }
+        }
+    }
+    DurationListKeys {
+        keys,
+        other_count,
+        other_duration,
+    }
+}
+
+/// Sum the presence time of the entries; the active time plus each
+/// period of idle time shorter than 'idle_threshold_seconds' (such
+/// as time spent thinking or reading away from the keyboard).
+///
+/// A period of idle time is measured from the start of the first
+/// idle entry to the end of the last, so a gap in the recording
+/// (the recorder was not running) makes the period longer.
+pub fn sum_entry_presence_duration(
+    entries: &[Entry],
+    idle_threshold_seconds: u64,
+) -> chrono::Duration {
+    let mut total_duration_seconds = 0;
+
+    // The idle period start time, end time and the duration of idle
+    // entries in the period.
+    let mut idle_period: Option<(u64, u64, u64)> = None;
+    let mut add_idle_period = |idle_period: Option<(u64, u64, u64)>| {
+        if let Some((start, end, duration_seconds)) = idle_period {
+            if (end - start) <= idle_threshold_seconds {
+                total_duration_seconds += duration_seconds;
+            }
+        }
+    };
+
+    let mut active_duration_seconds = 0;
+    for entry in entries {
+        match entry.status {
+            EntryStatus::Active => {
+                active_duration_seconds += entry.duration_seconds;
+                add_idle_period(idle_period.take());
+            }
+            EntryStatus::Idle => {
+                let entry_end = entry.utc_time_seconds + entry.duration_seconds;
+                idle_period = match idle_period {
+                    Some((start, end, duration_seconds)) => Some((
+                        start,
+                        std::cmp::max(end, entry_end),
+                        duration_seconds + entry.duration_seconds,
+                    )),
+                    None => Some((entry.utc_time_seconds, entry_end, entry.duration_seconds)),
+                };
+            }
+            // A pause (or a locked screen, or a suspended machine) is
+            // not presence, and ends any idle period.
+            EntryStatus::Paused | EntryStatus::Locked | EntryStatus::Suspended => {
+                add_idle_period(idle_period.take())
+            }
+            EntryStatus::Uninitialized => (),
+        }
+    }
+    add_idle_period(idle_period.take());
+
+    total_duration_seconds += active_duration_seconds;
+    chrono::Duration::seconds(total_duration_seconds.try_into().unwrap())
+}
+
+/// The start of the first active entry and the end of the last
+/// active entry (as UTC seconds), or None without active entries.
+pub fn find_entry_active_span(entries: &[Entry]) -> Option<(u64, u64)> {
+    entries
+        .iter()
+        .filter(|x| x.status == EntryStatus::Active)
+        .fold(None, |span, entry| {
+            let entry_start = entry.utc_time_seconds;
+            let entry_end = entry.utc_time_seconds + entry.duration_seconds;
+            match span {
+                Some((start, end)) => Some((
+                    std::cmp::min(start, entry_start),
+                    std::cmp::max(end, entry_end),
+                )),
+                None => Some((entry_start, entry_end)),
+            }
+        })
+}
+
+/// Find the periods of idle time in the entries; each period is the
+/// index range of consecutive idle entries, ending at the next
+/// active (or paused, or locked) entry.
+///
+/// The periods follow the same rules as
+/// 'sum_entry_presence_duration', so a gap in the recording is part
+/// of the idle period.
+pub fn find_entry_idle_periods(entries: &[Entry]) -> Vec<std::ops::Range<usize>> {
+    let mut idle_periods = Vec::new();
+    let mut idle_start_index: Option<usize> = None;
+    let mut idle_end_index = 0;
+    for (i, entry) in entries.iter().enumerate() {
+        match entry.status {
+            EntryStatus::Active
+            | EntryStatus::Paused
+            | EntryStatus::Locked
+            | EntryStatus::Suspended => {
+                if let Some(start_index) = idle_start_index.take() {
+                    idle_periods.push(start_index..idle_end_index);
+                }
+            }
+            EntryStatus::Idle => {
+                idle_start_index.get_or_insert(i);
+                idle_end_index = i + 1;
+            }
+            EntryStatus::Uninitialized => (),
+        }
+    }
+    if let Some(start_index) = idle_start_index {
+        idle_periods.push(start_index..idle_end_index);
+    }
+
+    idle_periods
+}
+
 #[cfg(test)]
+#[allow(clippy::unnecessary_mut_passed)]
 mod tests {
 
     use crate::aggregate::*;
+    use timetracker_core::entries::EntryVariablesList;
     use timetracker_core::format::format_time_no_seconds;
     use timetracker_core::format::DateTimeFormat;
 
+    #[test]
+    fn test_duration_map_from_grouped_sums() {
+        let vars = EntryVariablesList::empty();
+        let mut firefox_vars = vars.clone();
+        firefox_vars.executable = Some("firefox".to_string());
+        let entries = vec![
+            Entry::new(0, 100, EntryStatus::Active, firefox_vars.clone()),
+            Entry::new(100, 50, EntryStatus::Active, vars.clone()),
+            Entry::new(150, 20, EntryStatus::Active, firefox_vars),
+        ];
+        let grouped_sums = vec![(Some("firefox".to_string()), 120), (None, 50)];
+
+        let mut map = duration_map_from_grouped_sums(&grouped_sums);
+        let mut entries_map = sum_entry_executable_duration(&entries, EntryStatus::Active);
+        assert_eq!(map.remove("firefox"), entries_map.remove("firefox"));
+        assert_eq!(map.remove(""), entries_map.remove(""));
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_sum_entry_presence_duration() {
+        let vars = EntryVariablesList::empty();
+        let entries = vec![
+            Entry::new(0, 100, EntryStatus::Active, vars.clone()),
+            Entry::new(100, 150, EntryStatus::Idle, vars.clone()),
+            Entry::new(250, 150, EntryStatus::Idle, vars.clone()),
+            Entry::new(400, 100, EntryStatus::Active, vars.clone()),
+            Entry::new(500, 2000, EntryStatus::Idle, vars.clone()),
+            Entry::new(2500, 100, EntryStatus::Active, vars.clone()),
+            // A gap in the recording makes the idle period too long.
+            Entry::new(2600, 10, EntryStatus::Idle, vars.clone()),
+            Entry::new(5000, 10, EntryStatus::Idle, vars.clone()),
+            Entry::new(5010, 100, EntryStatus::Active, vars),
+        ];
+        assert_eq!(
+            sum_entry_presence_duration(&entries, 900),
+            chrono::Duration::seconds(100 + 300 + 100 + 100 + 100)
+        );
+        assert_eq!(
+            sum_entry_presence_duration(&entries, 0),
+            sum_entry_duration(&entries, EntryStatus::Active)
+        );
+    }
+
+    #[test]
+    fn test_find_entry_active_span() {
+        let vars = EntryVariablesList::empty();
+        let entries = vec![
+            Entry::new(0, 100, EntryStatus::Idle, vars.clone()),
+            Entry::new(100, 100, EntryStatus::Active, vars.clone()),
+            Entry::new(200, 150, EntryStatus::Idle, vars.clone()),
+            Entry::new(350, 50, EntryStatus::Active, vars.clone()),
+            Entry::new(400, 100, EntryStatus::Locked, vars.clone()),
+        ];
+        assert_eq!(find_entry_active_span(&entries), Some((100, 400)));
+        assert_eq!(find_entry_active_span(&entries[..1]), None);
+    }
+
+    #[test]
+    fn test_find_entry_idle_periods() {
+        let vars = EntryVariablesList::empty();
+        let entries = vec![
+            Entry::new(0, 100, EntryStatus::Idle, vars.clone()),
+            Entry::new(100, 100, EntryStatus::Active, vars.clone()),
+            Entry::new(200, 150, EntryStatus::Idle, vars.clone()),
+            Entry::new(350, 10, EntryStatus::Uninitialized, vars.clone()),
+            Entry::new(360, 150, EntryStatus::Idle, vars.clone()),
+            Entry::new(510, 100, EntryStatus::Active, vars.clone()),
+            Entry::new(610, 10, EntryStatus::Idle, vars),
+        ];
+        assert_eq!(find_entry_idle_periods(&entries), vec![0..1, 2..5, 6..7]);
+        assert!(find_entry_idle_periods(&entries[1..2]).is_empty());
+    }
+
+    #[test]
+    fn test_sum_entry_variable_columns_duration() {
+        let new_entry = |project: Option<&str>, shot: Option<&str>| {
+            let mut vars = EntryVariablesList::empty();
+            vars.var1_name = Some("PROJECT".to_string());
+            vars.var1_value = project.map(|x| x.to_string());
+            vars.var2_name = Some("SHOT".to_string());
+            vars.var2_value = shot.map(|x| x.to_string());
+            Entry::new(0, 60, EntryStatus::Active, vars)
+        };
+        let entries = vec![
+            new_entry(Some("acme"), Some("sh010")),
+            new_entry(Some("acme"), None),
+            new_entry(None, Some("acme")),
+            new_entry(None, None),
+            new_entry(Some("acme"), Some("sh010")),
+        ];
+        let variables = vec![
+            Variable::VariableName("PROJECT".to_string()),
+            Variable::VariableName("SHOT".to_string()),
+        ];
+
+        let map = sum_entry_variable_columns_duration(&entries, &variables, EntryStatus::Active);
+        assert_eq!(map.len(), 4);
+        let (values, duration) = &map["acme\u{1f}sh010"];
+        assert_eq!(values, &vec!["acme".to_string(), "sh010".to_string()]);
+        assert_eq!(*duration, chrono::Duration::seconds(120));
+        assert_eq!(
+            map["acme\u{1f}"].0,
+            vec!["acme".to_string(), "".to_string()]
+        );
+        assert_eq!(
+            map["\u{1f}acme"].0,
+            vec!["".to_string(), "acme".to_string()]
+        );
+        assert_eq!(map[""].1, chrono::Duration::seconds(60));
+    }
+
+    #[test]
+    fn test_get_duration_map_keys_sorted() {
+        let mut map = HashMap::new();
+        for (key, seconds) in [("bash", 60), ("firefox", 600), ("emacs", 60), ("", 6000)] {
+            map.insert(
+                key.to_string(),
+                (vec![key.to_string()], chrono::Duration::seconds(seconds)),
+            );
+        }
+
+        let sorted_keys = get_duration_map_keys_sorted(&map, SortBy::Name);
+        assert_eq!(sorted_keys, vec!["bash", "emacs", "firefox"]);
+
+        let sorted_keys = get_duration_map_keys_sorted(&map, SortBy::Duration);
+        assert_eq!(sorted_keys, vec!["firefox", "bash", "emacs"]);
+    }
+
+    #[test]
+    fn test_get_duration_map_keys_listed() {
+        let mut map = HashMap::new();
+        for (key, seconds) in [
+            ("bash", 30),
+            ("emacs", 600),
+            ("firefox", 900),
+            ("gimp", 120),
+        ] {
+            map.insert(
+                key.to_string(),
+                (vec![key.to_string()], chrono::Duration::seconds(seconds)),
+            );
+        }
+
+        let options = DurationListOptions {
+            top_n: Some(2),
+            ..DurationListOptions::default()
+        };
+        let listed = get_duration_map_keys_listed(&map, options);
+        assert_eq!(listed.keys, vec!["emacs", "firefox"]);
+        assert_eq!(listed.other_count, 2);
+        assert_eq!(listed.other_duration, chrono::Duration::seconds(150));
+
+        let options = DurationListOptions {
+            sort_by: SortBy::Duration,
+            min_duration: Some(chrono::Duration::minutes(1)),
+            ..DurationListOptions::default()
+        };
+        let listed = get_duration_map_keys_listed(&map, options);
+        assert_eq!(listed.keys, vec!["firefox", "emacs", "gimp"]);
+        assert_eq!(listed.other_count, 1);
+        assert_eq!(listed.other_duration, chrono::Duration::seconds(30));
+    }
+
     #[test]
     fn test_get_map_keys_sorted_strings() {
         let mut map = std::collections::HashMap::<String, chrono::Duration>::new();
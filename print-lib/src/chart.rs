@@ -0,0 +1,284 @@
+use crate::aggregate::sum_entry_duration;
+use crate::aggregate::sum_entry_variables_duration;
+use crate::datetime::get_weekdays_datetime_local;
+use crate::datetime::DateTimeLocalPair;
+use crate::variable::combine_variable_names;
+use crate::variable::Variable;
+use serde_derive::Serialize;
+use std::collections::HashMap;
+use timetracker_core::entries::EntryStatus;
+use timetracker_core::format::format_date;
+use timetracker_core::format::format_duration;
+use timetracker_core::format::DateTimeFormat;
+use timetracker_core::format::DurationFormat;
+use timetracker_core::storage::Entries;
+
+/// The most categories displayed in a chart; the remaining (least
+/// used) categories are combined into a single category.
+pub const CHART_MAX_CATEGORY_COUNT: usize = 8;
+
+/// The name of the category combining the least used categories.
+pub const CHART_OTHER_CATEGORY_NAME: &str = "(other)";
+
+/// The name of the category of entries without a value.
+pub const CHART_EMPTY_CATEGORY_NAME: &str = "(none)";
+
+// A categorical color palette, with colors that are easy to tell
+// apart.
+const CATEGORY_COLORS: [u32; CHART_MAX_CATEGORY_COUNT] = [
+    0x1f77b4, 0xff7f0e, 0x2ca02c, 0xd62728, 0x9467bd, 0x8c564b, 0xe377c2, 0x17becf,
+];
+const OTHER_CATEGORY_COLOR: u32 = 0x7f7f7f;
+
+/// A category (such as a project) displayed in the chart, with the
+/// total active duration of the category.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChartCategory {
+    pub name: String,
+    /// The color as a 24-bit RGB integer (0xRRGGBB).
+    pub color: u32,
+    /// The color as a hex string, such as "#1f77b4".
+    pub color_hex: String,
+    pub active_seconds: i64,
+    pub duration: String,
+}
+
+/// A segment of a bar; the active duration of one category in a day.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChartSegment {
+    /// The index of the category in 'StackedBarChart::categories'.
+    pub category_index: usize,
+    pub name: String,
+    pub color_hex: String,
+    pub active_seconds: i64,
+    pub duration: String,
+    /// The length of the segment, as a percentage of the longest bar
+    /// in the chart.
+    pub percentage: f64,
+}
+
+/// The bar of a single day, with a segment for each category.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChartBar {
+    pub weekday: String,
+    pub date: String,
+    pub active_seconds: i64,
+    pub duration: String,
+    pub segments: Vec<ChartSegment>,
+}
+
+/// A stacked bar for each day, with the active duration of each
+/// category (the values of a variable) as the segments of the bar.
+#[derive(Debug, Clone, Serialize)]
+pub struct StackedBarChart {
+    /// The name of the variable used for the categories.
+    pub variable_name: String,
+    /// The categories, the most used first.
+    pub categories: Vec<ChartCategory>,
+    pub bars: Vec<ChartBar>,
+    /// The active duration of the longest bar.
+    pub max_active_seconds: i64,
+}
+
+fn format_color_hex(color: u32) -> String {
+    format!("#{:06x}", color & 0xffffff)
+}
+
+fn category_name(value: &str) -> String {
+    match value.is_empty() {
+        true => CHART_EMPTY_CATEGORY_NAME.to_string(),
+        false => value.to_string(),
+    }
+}
+
+/// Pivot the active duration of the entries into the days (in the
+/// date range) and the values of a variable.
+pub fn generate_stacked_bar_chart(
+    entries: &Entries,
+    datetime_pair: DateTimeLocalPair,
+    variable: Variable,
+    datetime_format: DateTimeFormat,
+    duration_format: DurationFormat,
+) -> StackedBarChart {
+    let variables = vec![variable];
+
+    let mut days = Vec::new();
+    let mut category_totals = HashMap::<String, i64>::new();
+    for (weekday, (weekday_start_datetime, weekday_end_datetime)) in
+        get_weekdays_datetime_local(datetime_pair.0, datetime_pair.1)
+    {
+        let weekday_entries =
+            entries.datetime_range_entries(weekday_start_datetime, weekday_end_datetime);
+        let duration = sum_entry_duration(weekday_entries, EntryStatus::Active);
+        if duration.num_seconds() == 0 {
+            continue;
+        }
+
+        let mut day_durations = HashMap::<String, i64>::new();
+        let duration_map =
+            sum_entry_variables_duration(weekday_entries, &variables, EntryStatus::Active);
+        for (key, (_vars, category_duration)) in duration_map {
+            let name = category_name(&key);
+            *day_durations.entry(name.clone()).or_insert(0) += category_duration.num_seconds();
+            *category_totals.entry(name).or_insert(0) += category_duration.num_seconds();
+        }
+        days.push((
+            weekday,
+            weekday_start_datetime,
+            duration.num_seconds(),
+            day_durations,
+        ));
+    }
+
+    // The most used categories are displayed first (and are never
+    // combined into the "other" category).
+    let mut sorted_categories: Vec<(String, i64)> = category_totals.into_iter().collect();
+    sorted_categories.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let named_category_count = match sorted_categories.len() > CHART_MAX_CATEGORY_COUNT {
+        true => CHART_MAX_CATEGORY_COUNT - 1,
+        false => sorted_categories.len(),
+    };
+
+    let mut categories = Vec::new();
+    let mut category_indices = HashMap::<String, usize>::new();
+    for (index, (name, active_seconds)) in sorted_categories.iter().enumerate() {
+        if index < named_category_count {
+            category_indices.insert(name.clone(), index);
+            categories.push((name.clone(), CATEGORY_COLORS[index], *active_seconds));
+        } else {
+            if index == named_category_count {
+                let name = CHART_OTHER_CATEGORY_NAME.to_string();
+                categories.push((name, OTHER_CATEGORY_COLOR, 0));
+            }
+            category_indices.insert(name.clone(), named_category_count);
+            categories[named_category_count].2 += active_seconds;
+        }
+    }
+
+    let max_active_seconds = days.iter().map(|x| x.2).max().unwrap_or(0);
+    let mut bars = Vec::new();
+    for (weekday, weekday_start_datetime, active_seconds, day_durations) in days {
+        let mut category_seconds = vec![0; categories.len()];
+        for (name, seconds) in day_durations {
+            category_seconds[category_indices[&name]] += seconds;
+        }
+
+        let segments = category_seconds
+            .into_iter()
+            .enumerate()
+            .filter(|(_index, seconds)| *seconds > 0)
+            .map(|(index, seconds)| ChartSegment {
+                category_index: index,
+                name: categories[index].0.clone(),
+                color_hex: format_color_hex(categories[index].1),
+                active_seconds: seconds,
+                duration: format_duration(chrono::Duration::seconds(seconds), duration_format),
+                percentage: (seconds as f64 / max_active_seconds as f64) * 100.0,
+            })
+            .collect();
+
+        bars.push(ChartBar {
+            weekday: weekday.to_string(),
+            date: format_date(weekday_start_datetime, datetime_format),
+            active_seconds,
+            duration: format_duration(chrono::Duration::seconds(active_seconds), duration_format),
+            segments,
+        });
+    }
+
+    StackedBarChart {
+        variable_name: combine_variable_names(&variables),
+        categories: categories
+            .into_iter()
+            .map(|(name, color, active_seconds)| ChartCategory {
+                name,
+                color,
+                color_hex: format_color_hex(color),
+                active_seconds,
+                duration: format_duration(
+                    chrono::Duration::seconds(active_seconds),
+                    duration_format,
+                ),
+            })
+            .collect(),
+        bars,
+        max_active_seconds,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use timetracker_core::entries::Entry;
+    use timetracker_core::entries::EntryVariablesList;
+
+    fn new_entry(
+        utc_time_seconds: u64,
+        duration_seconds: u64,
+        status: EntryStatus,
+        project: &str,
+    ) -> Entry {
+        let mut vars = EntryVariablesList::empty();
+        vars.executable = Some("blender".to_string());
+        vars.var1_name = Some("PROJECT".to_string());
+        vars.var1_value = Some(project.to_string());
+        Entry::new(utc_time_seconds, duration_seconds, status, vars)
+    }
+
+    #[test]
+    fn test_generate_stacked_bar_chart_combines_other_categories() {
+        // Monday.
+        let start_datetime = chrono::Local.with_ymd_and_hms(2024, 9, 9, 0, 0, 0).unwrap();
+        let end_datetime = chrono::Local
+            .with_ymd_and_hms(2024, 9, 15, 23, 59, 59)
+            .unwrap();
+        let monday = start_datetime.timestamp() as u64 + (10 * 60 * 60);
+        let tuesday = monday + (24 * 60 * 60);
+
+        // Ten projects, the first is used the most.
+        let mut entries = Vec::new();
+        for index in 0..10 {
+            let duration_seconds = 1000 - (index * 10);
+            entries.push(new_entry(
+                monday + (index * 1000),
+                duration_seconds,
+                EntryStatus::Active,
+                &format!("P{}", index),
+            ));
+        }
+        // Idle entries are not displayed.
+        entries.push(new_entry(monday + 10_000, 100, EntryStatus::Idle, ""));
+        entries.push(new_entry(tuesday, 3000, EntryStatus::Active, "P0"));
+        entries.push(new_entry(tuesday + 3000, 100, EntryStatus::Idle, ""));
+        let entries = Entries::builder()
+            .start_datetime(start_datetime)
+            .end_datetime(end_datetime)
+            .entries(entries)
+            .build();
+
+        let chart = generate_stacked_bar_chart(
+            &entries,
+            (start_datetime, end_datetime),
+            Variable::VariableName("PROJECT".to_string()),
+            DateTimeFormat::Iso,
+            DurationFormat::HoursMinutes,
+        );
+
+        assert_eq!(chart.variable_name, "PROJECT");
+        assert_eq!(chart.categories.len(), CHART_MAX_CATEGORY_COUNT);
+        assert_eq!(chart.categories[0].name, "P0");
+        assert_eq!(chart.categories[0].active_seconds, 4000);
+        let other_category = chart.categories.last().unwrap();
+        assert_eq!(other_category.name, CHART_OTHER_CATEGORY_NAME);
+        assert_eq!(other_category.active_seconds, 930 + 920 + 910);
+
+        assert_eq!(chart.bars.len(), 2);
+        assert_eq!(chart.bars[0].weekday, "Mon");
+        assert_eq!(chart.bars[0].segments.len(), CHART_MAX_CATEGORY_COUNT);
+        assert_eq!(chart.bars[1].segments.len(), 1);
+        assert_eq!(chart.max_active_seconds, chart.bars[0].active_seconds);
+        let percentage = chart.bars[0].segments[0].percentage;
+        assert!((percentage - (1000.0 / 9550.0 * 100.0)).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,142 @@
+use crate::aggregate::sum_entry_variables_duration;
+use crate::variable::Variable;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use timetracker_core::entries::Entry;
+use timetracker_core::entries::EntryStatus;
+
+/// The active duration of a key (the values of the variables, such
+/// as an executable name) in a previous period and a current period.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DurationChange {
+    pub key: String,
+    /// The values of the variables of the key.
+    pub vars: Vec<String>,
+    pub previous_duration: chrono::Duration,
+    pub current_duration: chrono::Duration,
+}
+
+impl DurationChange {
+    /// The current duration minus the previous duration; negative
+    /// when less time was used in the current period.
+    pub fn delta(&self) -> chrono::Duration {
+        self.current_duration - self.previous_duration
+    }
+
+    /// The key was not used in the previous period.
+    pub fn has_appeared(&self) -> bool {
+        self.previous_duration.is_zero() && !self.current_duration.is_zero()
+    }
+
+    /// The key is not used in the current period.
+    pub fn has_disappeared(&self) -> bool {
+        !self.previous_duration.is_zero() && self.current_duration.is_zero()
+    }
+}
+
+/// Compare the durations of each key of two periods (as calculated
+/// by 'sum_entry_variables_duration'). Keys missing from a period
+/// have a zero duration in that period.
+///
+/// The changes are sorted by key.
+pub fn compare_durations(
+    previous_duration_map: &HashMap<String, (Vec<String>, chrono::Duration)>,
+    current_duration_map: &HashMap<String, (Vec<String>, chrono::Duration)>,
+) -> Vec<DurationChange> {
+    let keys: HashSet<&String> = previous_duration_map
+        .keys()
+        .chain(current_duration_map.keys())
+        .collect();
+
+    let mut changes: Vec<DurationChange> = keys
+        .into_iter()
+        .map(|key| {
+            let previous = previous_duration_map.get(key);
+            let current = current_duration_map.get(key);
+            let vars = current
+                .or(previous)
+                .map(|(vars, _duration)| vars.clone())
+                .unwrap_or_default();
+            DurationChange {
+                key: key.clone(),
+                vars,
+                previous_duration: previous
+                    .map(|(_vars, duration)| *duration)
+                    .unwrap_or_else(chrono::Duration::zero),
+                current_duration: current
+                    .map(|(_vars, duration)| *duration)
+                    .unwrap_or_else(chrono::Duration::zero),
+            }
+        })
+        .collect();
+    changes.sort_by(|a, b| a.key.cmp(&b.key));
+    changes
+}
+
+/// Compare the active duration of the values of the variables, from
+/// the previous entries to the current entries.
+pub fn compare_entry_variables_duration(
+    previous_entries: &[Entry],
+    current_entries: &[Entry],
+    variables: &[Variable],
+) -> Vec<DurationChange> {
+    let previous_duration_map =
+        sum_entry_variables_duration(previous_entries, variables, EntryStatus::Active);
+    let current_duration_map =
+        sum_entry_variables_duration(current_entries, variables, EntryStatus::Active);
+    compare_durations(&previous_duration_map, &current_duration_map)
+}
+
+/// Sort the changes by the largest (absolute) delta first, then by
+/// key.
+pub fn sort_by_largest_delta(changes: &mut [DurationChange]) {
+    changes.sort_by(|a, b| {
+        b.delta()
+            .abs()
+            .cmp(&a.delta().abs())
+            .then_with(|| a.key.cmp(&b.key))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use timetracker_core::entries::EntryVariablesList;
+
+    fn new_entry(utc_time_seconds: u64, duration_seconds: u64, executable: &str) -> Entry {
+        let mut vars = EntryVariablesList::empty();
+        vars.executable = Some(executable.to_string());
+        Entry::new(
+            utc_time_seconds,
+            duration_seconds,
+            EntryStatus::Active,
+            vars,
+        )
+    }
+
+    #[test]
+    fn test_compare_entry_variables_duration() {
+        let previous_entries = vec![
+            new_entry(0, 100, "blender"),
+            new_entry(100, 300, "nuke"),
+            new_entry(400, 50, "blender"),
+        ];
+        let current_entries = vec![new_entry(1000, 400, "blender"), new_entry(1400, 60, "maya")];
+        let variables = vec![Variable::Executable];
+
+        let mut changes =
+            compare_entry_variables_duration(&previous_entries, &current_entries, &variables);
+        let keys: Vec<&str> = changes.iter().map(|x| x.key.as_str()).collect();
+        assert_eq!(keys, vec!["blender", "maya", "nuke"]);
+
+        assert_eq!(changes[0].delta(), chrono::Duration::seconds(250));
+        assert!(!changes[0].has_appeared() && !changes[0].has_disappeared());
+        assert!(changes[1].has_appeared());
+        assert!(changes[2].has_disappeared());
+        assert_eq!(changes[2].delta(), chrono::Duration::seconds(-300));
+
+        sort_by_largest_delta(&mut changes);
+        let keys: Vec<&str> = changes.iter().map(|x| x.key.as_str()).collect();
+        assert_eq!(keys, vec!["nuke", "blender", "maya"]);
+    }
+}
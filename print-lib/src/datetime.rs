@@ -1,45 +1,38 @@
+use crate::errors::RenderError;
 use chrono::Datelike;
 use chrono::TimeZone;
+use log::warn;
+use timetracker_core::format::format_date;
+use timetracker_core::format::DateTimeFormat;
+use timetracker_core::format::FirstDayOfWeek;
+use timetracker_core::format::Granularity;
+use timetracker_core::format::TimeScale;
 
 pub type DateTimeLocalPair = (
     chrono::DateTime<chrono::Local>,
     chrono::DateTime<chrono::Local>,
 );
 
-// TODO: This assumes starting the week on Monday morning, until
-// Sunday night. Some People assume Saturday is the last day, others
-// maybe Friday. This needs to be configurable with the
-// "FirstDayOfWeek" enum.
-const WORK_WEEK_WEEKDAYS: &[chrono::Weekday] = &[
-    chrono::Weekday::Mon,
-    chrono::Weekday::Tue,
-    chrono::Weekday::Wed,
-    chrono::Weekday::Thu,
-    chrono::Weekday::Fri,
-    chrono::Weekday::Sat,
-    chrono::Weekday::Sun,
-];
-
 /// Get the pair of datetimes representing the first and last
-/// datetimes of a sub-set of working days in a week.
+/// datetimes of a week (starting on 'first_day_of_week' morning and
+/// ending the night before the next first day of the week).
 ///
 /// `year` is the year of the week datetime to get, such as `2015`, or
 /// `2022`.
 ///
-/// `week` is the week number to get the details for.
-///
-/// `start_weekday` is the first weekday of the week.
-/// `end_weekday` is the first weekday of the week.
-fn get_datetime_local_week_range(
+/// `week` is the (ISO) week number to get the details for. A week
+/// starting on Sunday or Saturday starts the day(s) before the Monday
+/// of the ISO week.
+pub fn get_week_datetime_local(
     year: i32,
     week: u32,
-    start_weekday: chrono::Weekday,
-    end_weekday: chrono::Weekday,
+    first_day_of_week: FirstDayOfWeek,
 ) -> DateTimeLocalPair {
-    let start_date = chrono::NaiveDate::from_isoywd_opt(year, week, start_weekday)
+    let monday_date = chrono::NaiveDate::from_isoywd_opt(year, week, chrono::Weekday::Mon)
         .expect("Start date year/week/day should be valid.");
-    let end_date = chrono::NaiveDate::from_isoywd_opt(year, week, end_weekday)
-        .expect("End date year/week/day should be valid.");
+    let start_date =
+        monday_date - chrono::Duration::days(first_day_of_week.days_before_monday().into());
+    let end_date = start_date + chrono::Duration::days(6);
 
     let start_datetime = start_date
         .and_hms_opt(0, 0, 0)
@@ -54,33 +47,276 @@ fn get_datetime_local_week_range(
     (start_datetime.unwrap(), end_datetime.unwrap())
 }
 
+/// The (ISO) week number of the week starting on 'first_day_of_week'
+/// that the date is in, as used by 'get_week_datetime_local'.
+pub fn get_week_number(date: chrono::NaiveDate, first_day_of_week: FirstDayOfWeek) -> (i32, u32) {
+    let monday_date = first_day_of_week.week_start_date(date)
+        + chrono::Duration::days(first_day_of_week.days_before_monday().into());
+    let iso_week = monday_date.iso_week();
+    (iso_week.year(), iso_week.week())
+}
+
 /// Get the pair of datetimes representing the first and last
-/// datetimes of a working week (starting Monday morning and ending
-/// Sunday night).
-///
-/// `year` is the year of the week datetime to get, such as `2015`, or
-/// `2022`.
+/// datetimes of a (local) date.
+fn get_date_datetime_local(date: chrono::NaiveDate) -> Result<DateTimeLocalPair, RenderError> {
+    let start_datetime = date
+        .and_hms_opt(0, 0, 0)
+        .expect("Start datetime should be valid.");
+    let end_datetime = date
+        .and_hms_opt(23, 59, 59)
+        .expect("End datetime should be valid.");
+
+    let start_datetime = chrono::Local
+        .from_local_datetime(&start_datetime)
+        .earliest()
+        .ok_or_else(|| {
+            RenderError::InvalidDateTime(format!("Start of date {} is invalid.", date))
+        })?;
+    let end_datetime = chrono::Local
+        .from_local_datetime(&end_datetime)
+        .latest()
+        .ok_or_else(|| RenderError::InvalidDateTime(format!("End of date {} is invalid.", date)))?;
+
+    Ok((start_datetime, end_datetime))
+}
+
+/// Get the pair of datetimes representing the first datetime of the
+/// 'start_date' and the last datetime of the 'end_date' (inclusive),
+/// such as a billing period from the 15th to the 14th of the next
+/// month.
+pub fn get_datetime_range(
+    start_date: chrono::NaiveDate,
+    end_date: chrono::NaiveDate,
+) -> Result<DateTimeLocalPair, RenderError> {
+    if end_date < start_date {
+        return Err(RenderError::InvalidDateTime(format!(
+            "Date range end {} must not be before the start {}.",
+            end_date, start_date
+        )));
+    }
+    let (start_datetime, _) = get_date_datetime_local(start_date)?;
+    let (_, end_datetime) = get_date_datetime_local(end_date)?;
+    Ok((start_datetime, end_datetime))
+}
+
+/// Get the pair of datetimes of the first and last day of a month,
+/// relative to the current month. '0' is the current month, '-1' is
+/// the previous month, etc.
+pub fn get_relative_month_start_end(
+    relative_month_index: i32,
+) -> Result<DateTimeLocalPair, RenderError> {
+    let today = chrono::Local::now().date_naive();
+    let month_index =
+        (today.year() as i64 * 12) + today.month0() as i64 + relative_month_index as i64;
+    let invalid_month = || {
+        RenderError::InvalidDateTime(format!("Invalid relative month {}.", relative_month_index))
+    };
+    let year: i32 = month_index
+        .div_euclid(12)
+        .try_into()
+        .map_err(|_| invalid_month())?;
+    let month = month_index.rem_euclid(12) as u32 + 1;
+
+    let start_date = chrono::NaiveDate::from_ymd_opt(year, month, 1).ok_or_else(invalid_month)?;
+    let end_date = start_date
+        .checked_add_months(chrono::Months::new(1))
+        .and_then(|x| x.pred_opt())
+        .ok_or_else(invalid_month)?;
+    get_datetime_range(start_date, end_date)
+}
+
+/// Get the pair of datetimes covered by a time scale, for the
+/// displayed range of datetimes.
 ///
-/// `week` is the week number to get the details for.
-pub fn get_week_datetime_local(year: i32, week: u32) -> DateTimeLocalPair {
-    get_datetime_local_week_range(year, week, chrono::Weekday::Mon, chrono::Weekday::Sun)
+/// A 'TimeScale::Week' or 'TimeScale::Weekday' covers the displayed
+/// range, a 'TimeScale::Fortnight' covers the week (starting on
+/// 'first_day_of_week') of the end of the displayed range and the
+/// previous week, and a
+/// 'TimeScale::Month' covers the calendar months of the displayed
+/// range.
+pub fn get_time_scale_datetime_range(
+    time_scale: TimeScale,
+    datetime_pair: DateTimeLocalPair,
+    first_day_of_week: FirstDayOfWeek,
+) -> Result<DateTimeLocalPair, RenderError> {
+    let (start_datetime, end_datetime) = datetime_pair;
+    match time_scale {
+        TimeScale::Week | TimeScale::Weekday => Ok(datetime_pair),
+        TimeScale::Fortnight => {
+            let week_start_date = first_day_of_week.week_start_date(end_datetime.date_naive());
+            let start_date = week_start_date - chrono::Duration::days(7);
+            let end_date = week_start_date + chrono::Duration::days(6);
+            get_datetime_range(start_date, end_date)
+        }
+        TimeScale::Month => {
+            let start_date = start_datetime
+                .date_naive()
+                .with_day(1)
+                .expect("First day of month should be valid.");
+            let end_date = end_datetime.date_naive();
+            let next_month_date = match end_date.month() {
+                12 => chrono::NaiveDate::from_ymd_opt(end_date.year() + 1, 1, 1),
+                month => chrono::NaiveDate::from_ymd_opt(end_date.year(), month + 1, 1),
+            }
+            .expect("First day of next month should be valid.");
+            let end_date = next_month_date.pred_opt().expect("Date should be valid.");
+            get_datetime_range(start_date, end_date)
+        }
+    }
+}
+
+/// Get the pair of datetimes of the 'week_count' whole weeks (starting
+/// on 'first_day_of_week') ending with the week of the end datetime,
+/// such as the last 8 weeks.
+pub fn get_weeks_ending_datetime_range(
+    datetime_pair: DateTimeLocalPair,
+    week_count: u32,
+    first_day_of_week: FirstDayOfWeek,
+) -> Result<DateTimeLocalPair, RenderError> {
+    let week_start_date = first_day_of_week.week_start_date(datetime_pair.1.date_naive());
+    let start_date = week_start_date - chrono::Duration::weeks(i64::from(week_count.max(1)) - 1);
+    get_datetime_range(start_date, week_start_date + chrono::Duration::days(6))
+}
+
+/// Get the pair of datetimes of the period immediately before the
+/// given period, with the same number of days. For example, the
+/// previous week of a week.
+pub fn get_previous_datetime_range(
+    datetime_pair: DateTimeLocalPair,
+) -> Result<DateTimeLocalPair, RenderError> {
+    let (start_datetime, end_datetime) = datetime_pair;
+    let start_date = start_datetime.date_naive();
+    let end_date = end_datetime.date_naive();
+    let day_count = (end_date - start_date).num_days() + 1;
+    get_datetime_range(
+        start_date - chrono::Duration::days(day_count),
+        start_date - chrono::Duration::days(1),
+    )
+}
+
+/// Get the week number (see 'get_week_number') and pair of datetimes
+/// of each week (starting on 'first_day_of_week') from the start
+/// datetime to the end datetime. The first and last weeks are clamped
+/// to the start and end datetimes.
+pub fn get_weeks_datetime_local(
+    start_datetime: chrono::DateTime<chrono::Local>,
+    end_datetime: chrono::DateTime<chrono::Local>,
+    first_day_of_week: FirstDayOfWeek,
+) -> Vec<(u32, DateTimeLocalPair)> {
+    let mut weeks_datetime_pairs = Vec::<(u32, DateTimeLocalPair)>::new();
+    for (_weekday, (day_start_datetime, day_end_datetime)) in
+        get_weekdays_datetime_local(start_datetime, end_datetime)
+    {
+        let (_year, week) = get_week_number(day_start_datetime.date_naive(), first_day_of_week);
+        match weeks_datetime_pairs.last_mut() {
+            Some((last_week, (_, last_end_datetime))) if *last_week == week => {
+                *last_end_datetime = day_end_datetime;
+            }
+            _ => weeks_datetime_pairs.push((week, (day_start_datetime, day_end_datetime))),
+        }
+    }
+
+    weeks_datetime_pairs
+}
+
+/// Get the pair of datetimes of each calendar month from the start
+/// datetime to the end datetime. The first and last months are
+/// clamped to the start and end datetimes.
+pub fn get_months_datetime_local(
+    start_datetime: chrono::DateTime<chrono::Local>,
+    end_datetime: chrono::DateTime<chrono::Local>,
+) -> Vec<DateTimeLocalPair> {
+    let mut months_datetime_pairs = Vec::<DateTimeLocalPair>::new();
+    let mut last_month = None;
+    for (_weekday, (day_start_datetime, day_end_datetime)) in
+        get_weekdays_datetime_local(start_datetime, end_datetime)
+    {
+        let month = Some((day_start_datetime.year(), day_start_datetime.month()));
+        match months_datetime_pairs.last_mut() {
+            Some((_, last_end_datetime)) if last_month == month => {
+                *last_end_datetime = day_end_datetime;
+            }
+            _ => months_datetime_pairs.push((day_start_datetime, day_end_datetime)),
+        }
+        last_month = month;
+    }
+
+    months_datetime_pairs
 }
 
+/// Split the displayed range of datetimes into each period of the
+/// granularity, with a heading describing each period.
+pub fn get_granularity_datetime_ranges(
+    granularity: Granularity,
+    datetime_pair: DateTimeLocalPair,
+    datetime_format: DateTimeFormat,
+    first_day_of_week: FirstDayOfWeek,
+) -> Vec<(String, DateTimeLocalPair)> {
+    let (start_datetime, end_datetime) = datetime_pair;
+    match granularity {
+        Granularity::Day => get_weekdays_datetime_local(start_datetime, end_datetime)
+            .into_iter()
+            .map(|(weekday, day_datetime_pair)| {
+                let heading = format!(
+                    "{} {}",
+                    weekday,
+                    format_date(day_datetime_pair.0, datetime_format)
+                );
+                (heading, day_datetime_pair)
+            })
+            .collect(),
+        Granularity::Week => {
+            get_weeks_datetime_local(start_datetime, end_datetime, first_day_of_week)
+                .into_iter()
+                .map(|(week, week_datetime_pair)| {
+                    let heading = format!(
+                        "Week {} ({} to {})",
+                        week,
+                        format_date(week_datetime_pair.0, datetime_format),
+                        format_date(week_datetime_pair.1, datetime_format)
+                    );
+                    (heading, week_datetime_pair)
+                })
+                .collect()
+        }
+        Granularity::Month => get_months_datetime_local(start_datetime, end_datetime)
+            .into_iter()
+            .map(|month_datetime_pair| {
+                let heading = month_datetime_pair.0.format("%B %Y").to_string();
+                (heading, month_datetime_pair)
+            })
+            .collect(),
+    }
+}
+
+/// Parse a date in "YYYY-MM-DD" format.
+pub fn parse_date(text: &str) -> Result<chrono::NaiveDate, RenderError> {
+    chrono::NaiveDate::parse_from_str(text, "%Y-%m-%d").map_err(|err| {
+        RenderError::InvalidDateTime(format!(
+            "Invalid date {:?}, expected \"YYYY-MM-DD\"; {}",
+            text, err
+        ))
+    })
+}
+
+/// Get the weekday and pair of datetimes of each day from the start
+/// datetime to the end datetime (inclusive), such as each day of a
+/// week. The days are in order from the start datetime, so the days
+/// of a week from 'get_week_datetime_local' start on the first day of
+/// the week.
 pub fn get_weekdays_datetime_local(
-    week_start_datetime: chrono::DateTime<chrono::Local>,
-    week_end_datetime: chrono::DateTime<chrono::Local>,
+    start_datetime: chrono::DateTime<chrono::Local>,
+    end_datetime: chrono::DateTime<chrono::Local>,
 ) -> Vec<(chrono::Weekday, DateTimeLocalPair)> {
-    let year = week_start_datetime.year();
-    let iso_week = week_start_datetime.iso_week();
-    assert_eq!(year, week_end_datetime.year());
-    assert_eq!(iso_week, week_end_datetime.iso_week());
-    let week: u32 = iso_week.week();
+    let start_date = start_datetime.date_naive();
+    let end_date = end_datetime.date_naive();
 
     let mut weekdays_datetime_pairs = Vec::<(chrono::Weekday, DateTimeLocalPair)>::new();
-
-    for weekday in WORK_WEEK_WEEKDAYS {
-        let weekdays_datetime_pair = get_datetime_local_week_range(year, week, *weekday, *weekday);
-        weekdays_datetime_pairs.push((*weekday, weekdays_datetime_pair));
+    for date in start_date.iter_days().take_while(|x| *x <= end_date) {
+        match get_date_datetime_local(date) {
+            Ok(datetime_pair) => weekdays_datetime_pairs.push((date.weekday(), datetime_pair)),
+            Err(err) => warn!("Skipping date {}; {}", date, err),
+        }
     }
 
     weekdays_datetime_pairs
@@ -93,3 +329,193 @@ pub fn utc_seconds_to_datetime_local(utc_time_seconds: u64) -> chrono::DateTime<
     )
     .with_timezone(&chrono::Local)
 }
+
+/// Parse a block of time within a week, such as "Tue 14:00-15:00",
+/// into a pair of datetimes.
+///
+/// The weekday may be a short ("Tue") or full ("Tuesday") name, and
+/// the times are in 24-hour "HH:MM" format. The weekday is found
+/// within the week given by 'week_datetime_pair'.
+pub fn parse_weekday_time_range(
+    text: &str,
+    week_datetime_pair: DateTimeLocalPair,
+) -> Result<DateTimeLocalPair, RenderError> {
+    let parts: Vec<&str> = text.split_whitespace().collect();
+    if parts.len() != 2 {
+        return Err(RenderError::InvalidDateTime(format!(
+            "Invalid time block {:?}, expected a weekday and time range, such as \"Tue 14:00-15:00\".",
+            text
+        )));
+    }
+
+    let weekday = parts[0].parse::<chrono::Weekday>().map_err(|_| {
+        RenderError::InvalidDateTime(format!(
+            "Invalid weekday {:?} in time block {:?}.",
+            parts[0], text
+        ))
+    })?;
+
+    let times: Vec<&str> = parts[1].splitn(2, '-').collect();
+    if times.len() != 2 {
+        return Err(RenderError::InvalidDateTime(format!(
+            "Invalid time range {:?} in time block {:?}, expected \"HH:MM-HH:MM\".",
+            parts[1], text
+        )));
+    }
+    let start_time = chrono::NaiveTime::parse_from_str(times[0], "%H:%M").map_err(|err| {
+        RenderError::InvalidDateTime(format!(
+            "Invalid time {:?} in time block {:?}; {}",
+            times[0], text, err
+        ))
+    })?;
+    let end_time = chrono::NaiveTime::parse_from_str(times[1], "%H:%M").map_err(|err| {
+        RenderError::InvalidDateTime(format!(
+            "Invalid time {:?} in time block {:?}; {}",
+            times[1], text, err
+        ))
+    })?;
+    if end_time <= start_time {
+        return Err(RenderError::InvalidDateTime(format!(
+            "Time range end {:?} must be after the start {:?}.",
+            times[1], times[0]
+        )));
+    }
+
+    let (week_start_datetime, week_end_datetime) = week_datetime_pair;
+    let weekday_datetime_pairs =
+        get_weekdays_datetime_local(week_start_datetime, week_end_datetime);
+    let (_, (weekday_start_datetime, _)) = weekday_datetime_pairs
+        .into_iter()
+        .find(|(x, _)| *x == weekday)
+        .ok_or_else(|| {
+            RenderError::InvalidDateTime(format!("Weekday {:?} is not in the week.", weekday))
+        })?;
+
+    let date = weekday_start_datetime.date_naive();
+    let start_datetime = chrono::Local
+        .from_local_datetime(&date.and_time(start_time))
+        .earliest()
+        .ok_or_else(|| {
+            RenderError::InvalidDateTime(format!(
+                "Start time {:?} is invalid on {}.",
+                times[0], date
+            ))
+        })?;
+    let end_datetime = chrono::Local
+        .from_local_datetime(&date.and_time(end_time))
+        .latest()
+        .ok_or_else(|| {
+            RenderError::InvalidDateTime(format!("End time {:?} is invalid on {}.", times[1], date))
+        })?;
+
+    Ok((start_datetime, end_datetime))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::datetime::*;
+
+    fn date(year: i32, month: u32, day: u32) -> chrono::NaiveDate {
+        chrono::NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn format_pair(datetime_pair: DateTimeLocalPair) -> (String, String) {
+        let format = "%Y-%m-%d %H:%M:%S";
+        (
+            datetime_pair.0.format(format).to_string(),
+            datetime_pair.1.format(format).to_string(),
+        )
+    }
+
+    fn pair(start: &str, end: &str) -> (String, String) {
+        (start.to_string(), end.to_string())
+    }
+
+    #[test]
+    fn test_get_time_scale_datetime_range_month() {
+        let month_range = |start_date, end_date| {
+            let datetime_pair = get_datetime_range(start_date, end_date).unwrap();
+            let datetime_pair = get_time_scale_datetime_range(
+                TimeScale::Month,
+                datetime_pair,
+                FirstDayOfWeek::Monday,
+            )
+            .unwrap();
+            format_pair(datetime_pair)
+        };
+
+        // December, the next month is in the next year.
+        assert_eq!(
+            month_range(date(2024, 12, 9), date(2024, 12, 15)),
+            pair("2024-12-01 00:00:00", "2024-12-31 23:59:59")
+        );
+        // A leap year.
+        assert_eq!(
+            month_range(date(2024, 2, 12), date(2024, 2, 18)),
+            pair("2024-02-01 00:00:00", "2024-02-29 23:59:59")
+        );
+        assert_eq!(
+            month_range(date(2023, 2, 13), date(2023, 2, 19)),
+            pair("2023-02-01 00:00:00", "2023-02-28 23:59:59")
+        );
+        // A week across two months covers both months.
+        assert_eq!(
+            month_range(date(2024, 1, 29), date(2024, 2, 4)),
+            pair("2024-01-01 00:00:00", "2024-02-29 23:59:59")
+        );
+    }
+
+    #[test]
+    fn test_get_time_scale_datetime_range_fortnight() {
+        let fortnight_range = |start_date, end_date, first_day_of_week| {
+            let datetime_pair = get_datetime_range(start_date, end_date).unwrap();
+            let datetime_pair = get_time_scale_datetime_range(
+                TimeScale::Fortnight,
+                datetime_pair,
+                first_day_of_week,
+            )
+            .unwrap();
+            format_pair(datetime_pair)
+        };
+
+        // The week before the (Monday) week across the year boundary.
+        assert_eq!(
+            fortnight_range(date(2024, 12, 30), date(2025, 1, 5), FirstDayOfWeek::Monday),
+            pair("2024-12-23 00:00:00", "2025-01-05 23:59:59")
+        );
+        // The first week of the year, with the week before it in the
+        // previous year.
+        assert_eq!(
+            fortnight_range(date(2025, 1, 5), date(2025, 1, 11), FirstDayOfWeek::Sunday),
+            pair("2024-12-29 00:00:00", "2025-01-11 23:59:59")
+        );
+    }
+
+    #[test]
+    fn test_get_months_datetime_local() {
+        let months = |start_date, end_date| {
+            let (start_datetime, end_datetime) = get_datetime_range(start_date, end_date).unwrap();
+            get_months_datetime_local(start_datetime, end_datetime)
+                .into_iter()
+                .map(format_pair)
+                .collect::<Vec<(String, String)>>()
+        };
+
+        assert_eq!(
+            months(date(2024, 12, 30), date(2025, 1, 5)),
+            vec![
+                pair("2024-12-30 00:00:00", "2024-12-31 23:59:59"),
+                pair("2025-01-01 00:00:00", "2025-01-05 23:59:59"),
+            ]
+        );
+        assert_eq!(
+            months(date(2024, 1, 29), date(2024, 3, 3)),
+            vec![
+                pair("2024-01-29 00:00:00", "2024-01-31 23:59:59"),
+                pair("2024-02-01 00:00:00", "2024-02-29 23:59:59"),
+                pair("2024-03-01 00:00:00", "2024-03-03 23:59:59"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,31 @@
+use thiserror::Error;
+use timetracker_core::errors::StorageError;
+
+/// The errors returned when generating (rendering) the printed
+/// report.
+#[derive(Debug, Error)]
+pub enum RenderError {
+    /// A date, date range or time block is invalid.
+    #[error("{0}")]
+    InvalidDateTime(String),
+
+    /// A preset setting (such as an activity ramp color) is invalid.
+    #[error("{0}")]
+    InvalidPreset(String),
+
+    /// An entry filter (such as "NAME=VALUE") is invalid.
+    #[error("{0}")]
+    InvalidFilter(String),
+
+    /// A template could not be read or rendered.
+    #[error("{0}")]
+    Template(String),
+
+    /// The rows could not be written as CSV.
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+
+    /// The durations could not be read from the database.
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+}
@@ -0,0 +1,81 @@
+use crate::errors::RenderError;
+use crate::variable::combine_variable_values;
+use crate::variable::Variable;
+use timetracker_core::entries::Entry;
+use timetracker_core::storage::Entries;
+
+/// Filters entries by the executable and the values of variables,
+/// such as only the entries of a single project.
+///
+/// An entry must match one of the executables (if any are given)
+/// and all the variable values.
+#[derive(Debug, Clone, Default)]
+pub struct EntryFilter {
+    executables: Vec<String>,
+    variable_values: Vec<(Variable, String)>,
+}
+
+impl EntryFilter {
+    /// Create a filter from executable names and "NAME=VALUE"
+    /// variable values, as given on the command line.
+    pub fn new(executables: &[String], variable_values: &[String]) -> Result<Self, RenderError> {
+        let mut filter_variable_values = Vec::new();
+        for variable_value in variable_values {
+            match variable_value.split_once('=') {
+                Some((name, value)) if !name.is_empty() => {
+                    filter_variable_values.push((Variable::from_name(name), value.to_string()))
+                }
+                _ => {
+                    return Err(RenderError::InvalidFilter(format!(
+                        "Invalid variable filter {:?}, expected \"NAME=VALUE\".",
+                        variable_value
+                    )))
+                }
+            }
+        }
+
+        Ok(EntryFilter {
+            executables: executables.to_vec(),
+            variable_values: filter_variable_values,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.executables.is_empty() && self.variable_values.is_empty()
+    }
+
+    pub fn matches(&self, entry: &Entry) -> bool {
+        let executable = entry.vars.executable.as_deref().unwrap_or("");
+        let is_executable_match =
+            self.executables.is_empty() || self.executables.iter().any(|x| x == executable);
+        is_executable_match
+            && self.variable_values.iter().all(|(variable, value)| {
+                combine_variable_values(entry, std::slice::from_ref(variable)) == *value
+            })
+    }
+
+    /// Remove the entries not matching the filter. The day notes,
+    /// periods and plans of the entries are kept as-is.
+    pub fn filter_entries(&self, entries: Entries) -> Entries {
+        if self.is_empty() {
+            return entries;
+        }
+
+        Entries::builder()
+            .start_datetime(entries.start_datetime())
+            .end_datetime(entries.end_datetime())
+            .entries(
+                entries
+                    .all_entries()
+                    .iter()
+                    .filter(|entry| self.matches(entry))
+                    .cloned()
+                    .collect(),
+            )
+            .day_notes(entries.day_notes().to_vec())
+            .host_busy_periods(entries.host_busy_periods().to_vec())
+            .window_color_periods(entries.window_color_periods().to_vec())
+            .plans(entries.plans().to_vec())
+            .build()
+    }
+}
@@ -1,6 +1,17 @@
 pub mod aggregate;
+pub mod chart;
+pub mod compare;
 pub mod datetime;
+pub mod errors;
+pub mod filter;
+pub mod mapping;
 pub mod preset;
 pub mod print;
+pub mod ramp;
+pub mod report;
+pub mod rows;
+pub mod template;
+pub mod timeline;
+pub mod timezone;
 pub mod utils;
 pub mod variable;
@@ -0,0 +1,217 @@
+use crate::errors::RenderError;
+use crate::variable::combine_variable_values;
+use crate::variable::Variable;
+use log::debug;
+use regex::Regex;
+use timetracker_core::entries::Entry;
+use timetracker_core::settings::PrintMappingSettings;
+use timetracker_core::storage::Entries;
+
+/// A compiled mapping, creating one derived variable.
+#[derive(Debug)]
+struct VariableMapping {
+    name: String,
+    variable: Variable,
+    pattern: Regex,
+    replace: String,
+}
+
+impl VariableMapping {
+    fn derived_value(&self, entry: &Entry) -> Option<String> {
+        let value = combine_variable_values(entry, std::slice::from_ref(&self.variable));
+        let captures = self.pattern.captures(&value)?;
+        let mut derived_value = String::new();
+        captures.expand(&self.replace, &mut derived_value);
+        match derived_value.is_empty() {
+            true => None,
+            false => Some(derived_value),
+        }
+    }
+}
+
+/// Creates derived variables from the values of recorded variables
+/// (such as a project name from a working directory), so presets can
+/// group entries by the derived variables.
+///
+/// The mappings are applied in order, so a mapping may read the
+/// value of a variable derived by an earlier mapping.
+#[derive(Debug, Default)]
+pub struct VariableMappings {
+    mappings: Vec<VariableMapping>,
+}
+
+impl VariableMappings {
+    pub fn new(settings: &[PrintMappingSettings]) -> Result<VariableMappings, RenderError> {
+        let mut mappings = Vec::new();
+        for mapping_settings in settings {
+            if mapping_settings.name.is_empty() {
+                return Err(RenderError::InvalidPreset(format!(
+                    "Mapping of variable {:?} has no 'name'.",
+                    mapping_settings.variable
+                )));
+            }
+            match Variable::from_name(&mapping_settings.name) {
+                Variable::VariableName(_) => (),
+                _ => {
+                    return Err(RenderError::InvalidPreset(format!(
+                        "Mapping name {:?} is a recorded field; use an environment variable name.",
+                        mapping_settings.name
+                    )))
+                }
+            }
+            let pattern = Regex::new(&mapping_settings.pattern).map_err(|err| {
+                RenderError::InvalidPreset(format!(
+                    "Invalid regular expression {:?} in mapping {:?}: {}",
+                    mapping_settings.pattern, mapping_settings.name, err
+                ))
+            })?;
+            mappings.push(VariableMapping {
+                name: mapping_settings.name.clone(),
+                variable: Variable::from_name(&mapping_settings.variable),
+                pattern,
+                replace: mapping_settings.replace.clone(),
+            });
+        }
+        Ok(VariableMappings { mappings })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mappings.is_empty()
+    }
+
+    /// The names of the variables recorded (in 'recorded_names'), and
+    /// the derived variable names.
+    ///
+    /// Each entry has a limited number of variables, so an error is
+    /// returned when there are too many derived variables.
+    pub fn variable_names(&self, recorded_names: &[String]) -> Result<Vec<String>, RenderError> {
+        let mut names = recorded_names.to_vec();
+        for mapping in &self.mappings {
+            if !names.contains(&mapping.name) {
+                names.push(mapping.name.clone());
+            }
+        }
+        let max_count = timetracker_core::storage::ENVIRONMENT_VARIABLE_NAMES_MAX_COUNT;
+        if names.len() > max_count {
+            return Err(RenderError::InvalidPreset(format!(
+                "Too many variables; {} variables are recorded and derived by mappings, but only {} are allowed: {:?}",
+                names.len(),
+                max_count,
+                names
+            )));
+        }
+        Ok(names)
+    }
+
+    /// Set the derived variables of the entry. A mapping whose
+    /// pattern does not match leaves the derived variable unchanged.
+    pub fn apply(&self, entry: &mut Entry) {
+        for mapping in &self.mappings {
+            if let Some(value) = mapping.derived_value(entry) {
+                if !entry.vars.set_variable_value(&mapping.name, &value) {
+                    debug!(
+                        "No unused variable for mapping {:?} in entry {:?}.",
+                        mapping.name, entry.vars
+                    );
+                }
+            }
+        }
+    }
+
+    /// Set the derived variables of all entries. The day notes,
+    /// periods and plans of the entries are kept as-is.
+    pub fn map_entries(&self, entries: Entries) -> Entries {
+        if self.is_empty() {
+            return entries;
+        }
+
+        let mut mapped_entries = entries.all_entries().to_vec();
+        for entry in &mut mapped_entries {
+            self.apply(entry);
+        }
+
+        Entries::builder()
+            .start_datetime(entries.start_datetime())
+            .end_datetime(entries.end_datetime())
+            .entries(mapped_entries)
+            .day_notes(entries.day_notes().to_vec())
+            .host_busy_periods(entries.host_busy_periods().to_vec())
+            .window_color_periods(entries.window_color_periods().to_vec())
+            .plans(entries.plans().to_vec())
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use timetracker_core::entries::EntryStatus;
+    use timetracker_core::entries::EntryVariablesList;
+
+    fn new_mapping(
+        name: &str,
+        variable: &str,
+        pattern: &str,
+        replace: &str,
+    ) -> PrintMappingSettings {
+        PrintMappingSettings {
+            name: name.to_string(),
+            variable: variable.to_string(),
+            pattern: pattern.to_string(),
+            replace: replace.to_string(),
+        }
+    }
+
+    fn new_entry(pwd: &str) -> Entry {
+        let mut vars = EntryVariablesList::empty();
+        vars.var1_name = Some("PWD".to_string());
+        vars.var1_value = Some(pwd.to_string());
+        Entry::new(0, 1, EntryStatus::Active, vars)
+    }
+
+    #[test]
+    fn test_apply() {
+        let mappings = VariableMappings::new(&[
+            new_mapping("SHOW", "PWD", "^/projects/(?P<show>[^/]+)/", "$show"),
+            new_mapping("CLIENT", "SHOW", "^(acme|globex)$", "client-$1"),
+        ])
+        .unwrap();
+
+        let mut entry = new_entry("/projects/acme/shots/sh010");
+        mappings.apply(&mut entry);
+        assert_eq!(entry.vars.var2_name, Some("SHOW".to_string()));
+        assert_eq!(entry.vars.var2_value, Some("acme".to_string()));
+        assert_eq!(entry.vars.var3_name, Some("CLIENT".to_string()));
+        assert_eq!(entry.vars.var3_value, Some("client-acme".to_string()));
+
+        // The pattern does not match, so nothing is derived.
+        let mut entry = new_entry("/home/user");
+        mappings.apply(&mut entry);
+        assert_eq!(entry.vars.var2_name, None);
+    }
+
+    #[test]
+    fn test_new_invalid() {
+        assert!(VariableMappings::new(&[new_mapping("SHOW", "PWD", "(", "$1")]).is_err());
+        assert!(VariableMappings::new(&[new_mapping("executable", "PWD", ".*", "x")]).is_err());
+        assert!(VariableMappings::new(&[new_mapping("", "PWD", ".*", "x")]).is_err());
+    }
+
+    #[test]
+    fn test_variable_names() {
+        let mappings =
+            VariableMappings::new(&[new_mapping("SHOW", "PWD", "^/projects/([^/]+)/", "$1")])
+                .unwrap();
+        let recorded_names = vec!["PWD".to_string(), "SHOW".to_string()];
+        assert_eq!(
+            mappings.variable_names(&recorded_names).unwrap(),
+            recorded_names
+        );
+
+        let recorded_names: Vec<String> = ["A", "B", "C", "D", "E"]
+            .iter()
+            .map(|x| x.to_string())
+            .collect();
+        assert!(mappings.variable_names(&recorded_names).is_err());
+    }
+}
@@ -1,15 +1,38 @@
+use crate::aggregate::duration_map_from_grouped_sums;
+use crate::aggregate::DurationListOptions;
+use crate::datetime::get_granularity_datetime_ranges;
+use crate::datetime::get_previous_datetime_range;
+use crate::datetime::get_time_scale_datetime_range;
+use crate::datetime::get_weeks_ending_datetime_range;
 use crate::datetime::DateTimeLocalPair;
+use crate::errors::RenderError;
+use crate::print::can_generate_preset_lines_from_durations;
+use crate::print::generate_comparison;
 use crate::print::generate_preset_lines;
+use crate::print::generate_preset_lines_from_durations;
+use crate::ramp::parse_color;
+use crate::ramp::DensityRamp;
+use crate::ramp::ExecutableColors;
+use crate::timezone::parse_timezone;
+use crate::timezone::shift_entries_to_timezone;
+use crate::timezone::MAX_TIMEZONE_DIFFERENCE_SECONDS;
+use crate::variable::combine_variable_names;
 use crate::variable::Variable;
-use anyhow::Result;
+use colored::Colorize;
 use log::warn;
 use std::collections::HashMap;
+use timetracker_core::entries::EntryStatus;
 use timetracker_core::format::DateTimeFormat;
 use timetracker_core::format::DurationFormat;
+use timetracker_core::format::FirstDayOfWeek;
+use timetracker_core::format::Granularity;
 use timetracker_core::format::PrintType;
+use timetracker_core::format::SortBy;
 use timetracker_core::format::TimeBlockUnit;
 use timetracker_core::format::TimeScale;
+use timetracker_core::multi_storage::MultiStorage;
 use timetracker_core::settings::PrintPresetSettings;
+use timetracker_core::settings::PrintUtilizationSettings;
 use timetracker_core::storage::Entries;
 
 pub fn override_preset_value<T>(new_value: Option<T>, old_value: Option<T>) -> Option<T> {
@@ -19,6 +42,7 @@ pub fn override_preset_value<T>(new_value: Option<T>, old_value: Option<T>) -> O
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn create_presets(
     default_time_scale: TimeScale,
     default_format_datetime: DateTimeFormat,
@@ -26,24 +50,28 @@ pub fn create_presets(
     default_time_block_unit: TimeBlockUnit,
     default_bar_graph_character_num_width: u8,
     default_use_color: bool,
+    default_sort_by: SortBy,
     environment_variables_names: &[String],
     display_presets: &[String],
     print_presets: &HashMap<String, PrintPresetSettings>,
-) -> Result<(Vec<PrintPresetSettings>, Vec<String>)> {
-    let core_preset = PrintPresetSettings::new(
-        // The 'print_type' must be valid for the preset to be used,
-        // but the core settings (intentionally) do not define any
-        // default value - it must be defined by the user-created
-        // preset.
-        None,
-        Some(default_time_scale),
-        Some(default_format_datetime),
-        Some(default_format_duration),
-        Some(default_time_block_unit),
-        Some(default_bar_graph_character_num_width),
-        Some(default_use_color),
-        Some(environment_variables_names.to_vec()),
-    );
+) -> Result<(Vec<PrintPresetSettings>, Vec<String>), RenderError> {
+    let core_preset = PrintPresetSettings {
+        sort_by: Some(default_sort_by),
+        ..PrintPresetSettings::new(
+            // The 'print_type' must be valid for the preset to be
+            // used, but the core settings (intentionally) do not
+            // define any default value - it must be defined by the
+            // user-created preset.
+            None,
+            Some(default_time_scale),
+            Some(default_format_datetime),
+            Some(default_format_duration),
+            Some(default_time_block_unit),
+            Some(default_bar_graph_character_num_width),
+            Some(default_use_color),
+            Some(environment_variables_names.to_vec()),
+        )
+    };
 
     let mut missing_preset_names = Vec::new();
     let mut presets = Vec::new();
@@ -64,17 +92,31 @@ pub fn create_presets(
                 );
                 let use_color = override_preset_value(value.use_color, core_preset.use_color);
                 let variable_names = value.variable_names.clone();
+                let sort_by = override_preset_value(value.sort_by, core_preset.sort_by);
 
-                PrintPresetSettings::new(
-                    print_type,
-                    time_scale,
-                    format_datetime,
-                    format_duration,
-                    time_block_unit,
-                    bar_graph_character_num_width,
-                    use_color,
-                    variable_names,
-                )
+                PrintPresetSettings {
+                    activity_use_unicode_blocks: value.activity_use_unicode_blocks,
+                    activity_ramp_characters: value.activity_ramp_characters.clone(),
+                    activity_ramp_thresholds: value.activity_ramp_thresholds.clone(),
+                    activity_ramp_colors: value.activity_ramp_colors.clone(),
+                    timezone: value.timezone.clone(),
+                    sort_by,
+                    top_n: value.top_n,
+                    min_duration_minutes: value.min_duration_minutes,
+                    show_percentage: value.show_percentage,
+                    color: value.color.clone(),
+                    statistics_weeks: value.statistics_weeks,
+                    ..PrintPresetSettings::new(
+                        print_type,
+                        time_scale,
+                        format_datetime,
+                        format_duration,
+                        time_block_unit,
+                        bar_graph_character_num_width,
+                        use_color,
+                        variable_names,
+                    )
+                }
             }
             None => {
                 warn!("Preset name {:?} is unavailable.", preset_name);
@@ -89,15 +131,188 @@ pub fn create_presets(
     Ok((presets, missing_preset_names))
 }
 
-// When color is used, use this.
-const DEFAULT_COLOR: colored::Color = colored::Color::Green;
+/// Find the problems of the presets (created by 'create_presets')
+/// that are otherwise skipped with a warning (or print nothing); an
+/// unknown preset name, a preset without a 'print_type', an unknown
+/// 'timezone', or a 'Variables' preset with a variable that is not
+/// recorded.
+///
+/// The recorded variables are the built-in variables (such as
+/// "executable") and the 'core.environment_variables.names'.
+pub fn find_preset_problems(
+    display_presets: &[String],
+    presets: &[PrintPresetSettings],
+    missing_preset_names: &[String],
+    environment_variables_names: &[String],
+) -> Vec<String> {
+    let mut problems = Vec::new();
+    for (preset_name, preset) in display_presets.iter().zip(presets) {
+        if missing_preset_names.contains(preset_name) {
+            problems.push(format!("Preset name {:?} is unavailable.", preset_name));
+            continue;
+        }
+
+        let print_type = match preset.print_type {
+            Some(value) => value,
+            None => {
+                problems.push(format!("Preset {:?} has no 'print_type'.", preset_name));
+                continue;
+            }
+        };
+        if let Some(timezone) = &preset.timezone {
+            if parse_timezone(timezone).is_err() {
+                problems.push(format!(
+                    "Preset {:?} timezone {:?} is unknown.",
+                    preset_name, timezone
+                ));
+            }
+        }
+        if !matches!(print_type, PrintType::Variables | PrintType::Churn) {
+            continue;
+        }
+
+        let variable_names = preset.variable_names.clone().unwrap_or_default();
+        if matches!(print_type, PrintType::Variables) && variable_names.is_empty() {
+            problems.push(format!(
+                "Preset {:?} has no 'variable_names' to print.",
+                preset_name
+            ));
+        }
+        for variable_name in &variable_names {
+            let is_recorded = match Variable::from_name(variable_name) {
+                Variable::VariableName(name) => environment_variables_names.contains(&name),
+                _ => true,
+            };
+            if !is_recorded {
+                problems.push(format!(
+                    "Preset {:?} variable {:?} is not recorded; it is not in 'core.environment_variables.names' (or derived by 'print.mappings') {:?}.",
+                    preset_name, variable_name, environment_variables_names
+                ));
+            }
+        }
+    }
+    problems
+}
+
+/// The color of the text of presets, when color is used.
+pub const DEFAULT_COLOR: colored::Color = colored::Color::Green;
+
+/// The number of weeks of the "Statistics" print type, when the
+/// preset has no 'statistics_weeks'.
+const DEFAULT_STATISTICS_WEEKS: u32 = 8;
+
+/// The range of datetimes printed by the preset, for the displayed
+/// range of datetimes, such as the whole month of a 'TimeScale::Month'.
+fn get_preset_datetime_range(
+    preset: &PrintPresetSettings,
+    time_scale: TimeScale,
+    datetime_pair: DateTimeLocalPair,
+    first_day_of_week: FirstDayOfWeek,
+) -> Result<DateTimeLocalPair, RenderError> {
+    match preset.print_type {
+        Some(PrintType::Statistics) => get_weeks_ending_datetime_range(
+            datetime_pair,
+            preset.statistics_weeks.unwrap_or(DEFAULT_STATISTICS_WEEKS),
+            first_day_of_week,
+        ),
+        _ => get_time_scale_datetime_range(time_scale, datetime_pair, first_day_of_week),
+    }
+}
+
+/// Get the range of datetimes needed to generate all the presets, for
+/// the displayed range of datetimes. For example, a preset with a
+/// 'TimeScale::Month' needs the entries of a whole month, and a
+/// preset with a 'timezone' needs the entries shifted into the range
+/// from the timezone.
+pub fn get_presets_datetime_range(
+    presets: &[PrintPresetSettings],
+    datetime_pair: DateTimeLocalPair,
+    first_day_of_week: FirstDayOfWeek,
+) -> Result<DateTimeLocalPair, RenderError> {
+    let (mut start_datetime, mut end_datetime) = datetime_pair;
+    for preset in presets {
+        if preset.print_type.is_none() {
+            continue;
+        }
+        if let Some(time_scale) = preset.time_scale {
+            let (mut preset_start_datetime, preset_end_datetime) =
+                get_preset_datetime_range(preset, time_scale, datetime_pair, first_day_of_week)?;
+            if matches!(preset.print_type, Some(PrintType::Churn)) {
+                // Churn is compared to the previous period.
+                (preset_start_datetime, _) =
+                    get_previous_datetime_range((preset_start_datetime, preset_end_datetime))?;
+            }
+            let (preset_start_datetime, preset_end_datetime) = match preset.timezone {
+                Some(_) => {
+                    let difference = chrono::Duration::seconds(MAX_TIMEZONE_DIFFERENCE_SECONDS);
+                    (
+                        preset_start_datetime - difference,
+                        preset_end_datetime + difference,
+                    )
+                }
+                None => (preset_start_datetime, preset_end_datetime),
+            };
+            start_datetime = std::cmp::min(start_datetime, preset_start_datetime);
+            end_datetime = std::cmp::max(end_datetime, preset_end_datetime);
+        }
+    }
+    Ok((start_datetime, end_datetime))
+}
+
+/// The variables a preset groups the entries by, such as the
+/// executable for the "Software" print type.
+pub(crate) fn get_preset_variables(
+    preset: &PrintPresetSettings,
+    print_type: PrintType,
+) -> Vec<Variable> {
+    match print_type {
+        PrintType::Software => vec![Variable::Executable; 1],
+        PrintType::WindowTitle => vec![Variable::WindowTitle; 1],
+        PrintType::WorkingDirectory => vec![Variable::WorkingDirectory; 1],
+        PrintType::DocumentPath => vec![Variable::DocumentPath; 1],
+        // Churn compares executables, unless variables (such as
+        // a project) are given.
+        PrintType::Churn => match &preset.variable_names {
+            Some(variable_names) if !variable_names.is_empty() => variable_names
+                .iter()
+                .map(|name| Variable::from_name(name))
+                .collect(),
+            _ => vec![Variable::Executable; 1],
+        },
+        PrintType::Variables => {
+            let mut variables = Vec::new();
+            if let Some(variable_names) = &preset.variable_names {
+                for name in variable_names {
+                    let variable = Variable::from_name(name);
+                    variables.push(variable);
+                }
+            }
+            variables
+        }
+        _ => Vec::new(),
+    }
+}
 
+/// Generate the lines of each preset, for the displayed range of
+/// datetimes. The 'entries' must cover the range given by
+/// 'get_presets_datetime_range'.
+///
+/// The text is drawn with the 'color' (or the 'color' of the preset)
+/// of the presets using color, and the 'executable_colors' of the
+/// "Software" presets, so a 'color' of None (such as when the output
+/// is not a terminal) prints every preset without color.
+#[allow(clippy::too_many_arguments)]
 pub fn generate_presets(
     presets: &Vec<PrintPresetSettings>,
     entries: &Entries,
-) -> Result<Vec<String>> {
-    let week_datetime_pair: DateTimeLocalPair = (entries.start_datetime(), entries.end_datetime());
-
+    datetime_pair: DateTimeLocalPair,
+    utilization_settings: &PrintUtilizationSettings,
+    work_hours_per_day: f64,
+    first_day_of_week: FirstDayOfWeek,
+    color: Option<colored::Color>,
+    executable_colors: &ExecutableColors,
+) -> Result<Vec<String>, RenderError> {
+    let no_executable_colors = ExecutableColors::default();
     let mut lines = Vec::new();
     for preset in presets {
         if preset.print_type.is_none() {
@@ -105,39 +320,273 @@ pub fn generate_presets(
         }
         let print_type = preset.print_type.unwrap();
 
-        let preset_variables = match print_type {
-            PrintType::Software => vec![Variable::Executable; 1],
-            PrintType::Variables => {
-                let mut variables = Vec::new();
-                if let Some(variable_names) = &preset.variable_names {
-                    for name in variable_names {
-                        let variable = Variable::VariableName(name.clone());
-                        variables.push(variable);
-                    }
-                }
-                variables
-            }
-            _ => Vec::new(),
+        let preset_variables = get_preset_variables(preset, print_type);
+
+        let preset_color = get_preset_color(preset, color)?;
+        let preset_executable_colors = match preset_color {
+            Some(_) => executable_colors,
+            None => &no_executable_colors,
         };
 
-        let color = match preset.use_color.unwrap() {
-            true => Some(DEFAULT_COLOR),
-            false => None,
+        let density_ramp = DensityRamp::from_preset(preset)?;
+        let time_scale = preset.time_scale.unwrap();
+        let preset_datetime_pair =
+            get_preset_datetime_range(preset, time_scale, datetime_pair, first_day_of_week)?;
+
+        // The days of a preset with a timezone are divided in the
+        // timezone, by shifting the entries so the local timezone
+        // displays the times of the timezone.
+        let timezone_entries = match &preset.timezone {
+            Some(name) => {
+                let timezone = parse_timezone(name)?;
+                lines.push(format!("Timezone: {}", timezone.name()));
+                Some(shift_entries_to_timezone(entries, timezone))
+            }
+            None => None,
         };
 
+        let mut preset_lines = Vec::new();
         generate_preset_lines(
-            entries,
-            &mut lines,
-            week_datetime_pair,
+            timezone_entries.as_ref().unwrap_or(entries),
+            &mut preset_lines,
+            preset_datetime_pair,
             print_type,
             &preset_variables,
-            preset.time_scale.unwrap(),
+            time_scale,
             preset.format_datetime.unwrap(),
             preset.format_duration.unwrap(),
             preset.time_block_unit.unwrap(),
             preset.bar_graph_character_num_width.unwrap(),
-            color,
+            preset_color,
+            &density_ramp,
+            utilization_settings,
+            work_hours_per_day,
+            DurationListOptions::from_preset(preset),
+            preset_executable_colors,
+            first_day_of_week,
+        )?;
+
+        append_preset_lines(&mut lines, preset_lines, preset, preset_color, print_type);
+    }
+
+    Ok(lines)
+}
+
+/// The color the preset is drawn with; the 'color' of the preset, or
+/// the default 'color', if the preset uses color.
+fn get_preset_color(
+    preset: &PrintPresetSettings,
+    color: Option<colored::Color>,
+) -> Result<Option<colored::Color>, RenderError> {
+    match (preset.use_color.unwrap(), color, &preset.color) {
+        (true, Some(_), Some(color_name)) => Ok(Some(parse_color(color_name)?)),
+        (true, Some(default_color), None) => Ok(Some(default_color)),
+        _ => Ok(None),
+    }
+}
+
+fn append_preset_lines(
+    lines: &mut Vec<String>,
+    mut preset_lines: Vec<String>,
+    preset: &PrintPresetSettings,
+    preset_color: Option<colored::Color>,
+    print_type: PrintType,
+) {
+    // The "Activity" presets draw the bar graphs with the color,
+    // and the other presets with a 'color' have all lines colored.
+    match (preset_color, &preset.color, print_type) {
+        (Some(c), Some(_), print_type) if !matches!(print_type, PrintType::Activity) => lines
+            .extend(preset_lines.into_iter().map(|line| match line.is_empty() {
+                true => line,
+                false => line.color(c).to_string(),
+            })),
+        _ => lines.append(&mut preset_lines),
+    }
+}
+
+/// Can all the presets be generated by 'generate_presets_from_durations',
+/// from the durations summed by SQLite, without reading the entries?
+///
+/// Only the "Summary" of a week and the "Software" of a week,
+/// fortnight or month (without a 'timezone') are supported.
+pub fn can_generate_presets_from_durations(presets: &[PrintPresetSettings]) -> bool {
+    presets.iter().all(
+        |preset| match (preset.print_type, preset.time_scale, &preset.timezone) {
+            (None, _, _) => true,
+            (Some(print_type), Some(time_scale), None) => {
+                can_generate_preset_lines_from_durations(print_type, time_scale)
+            }
+            _ => false,
+        },
+    )
+}
+
+/// Generate the same lines as 'generate_presets', but with the
+/// durations of each executable summed by SQLite, rather than
+/// reading the entries, so large ranges of time are printed quickly.
+///
+/// The presets must be supported, as given by
+/// 'can_generate_presets_from_durations'.
+pub fn generate_presets_from_durations(
+    presets: &Vec<PrintPresetSettings>,
+    storage: &MultiStorage,
+    datetime_pair: DateTimeLocalPair,
+    first_day_of_week: FirstDayOfWeek,
+    color: Option<colored::Color>,
+    executable_colors: &ExecutableColors,
+) -> Result<Vec<String>, RenderError> {
+    let no_executable_colors = ExecutableColors::default();
+    let mut lines = Vec::new();
+    for preset in presets {
+        if preset.print_type.is_none() {
+            continue;
+        }
+        let print_type = preset.print_type.unwrap();
+
+        let preset_variables = get_preset_variables(preset, print_type);
+        let preset_color = get_preset_color(preset, color)?;
+        let preset_executable_colors = match preset_color {
+            Some(_) => executable_colors,
+            None => &no_executable_colors,
+        };
+
+        let time_scale = preset.time_scale.unwrap();
+        let preset_datetime_pair =
+            get_preset_datetime_range(preset, time_scale, datetime_pair, first_day_of_week)?;
+        let grouped_sums = storage.sum_durations_grouped_by(
+            "executable",
+            preset_datetime_pair.0.timestamp() as u64,
+            preset_datetime_pair.1.timestamp() as u64,
+            EntryStatus::Active,
+        )?;
+        let executable_duration_map = duration_map_from_grouped_sums(&grouped_sums);
+        let total_duration = executable_duration_map
+            .values()
+            .map(|(_vars, duration)| *duration)
+            .sum();
+
+        let mut preset_lines = Vec::new();
+        generate_preset_lines_from_durations(
+            &executable_duration_map,
+            total_duration,
+            &mut preset_lines,
+            preset_datetime_pair,
+            print_type,
+            &preset_variables,
+            time_scale,
+            preset.format_datetime.unwrap(),
+            preset.format_duration.unwrap(),
+            DurationListOptions::from_preset(preset),
+            preset_executable_colors,
         )?;
+        append_preset_lines(&mut lines, preset_lines, preset, preset_color, print_type);
+    }
+
+    Ok(lines)
+}
+
+/// Generate the lines of each preset comparing the displayed range of
+/// datetimes to the 'against_datetime_pair' (such as the week before),
+/// with the durations of both periods side by side.
+///
+/// Only the print types aggregating the durations of variables (such
+/// as "Software" and "Variables") are compared; the other presets
+/// are skipped with a warning. The 'entries' must cover both ranges.
+pub fn generate_presets_comparison(
+    presets: &Vec<PrintPresetSettings>,
+    entries: &Entries,
+    datetime_pair: DateTimeLocalPair,
+    against_datetime_pair: DateTimeLocalPair,
+) -> Result<Vec<String>, RenderError> {
+    let line_indent = " ";
+    let mut lines = Vec::new();
+    for preset in presets {
+        let print_type = match preset.print_type {
+            Some(value) => value,
+            None => continue,
+        };
+        let preset_variables = get_preset_variables(preset, print_type);
+        let heading_text = match print_type {
+            PrintType::Software | PrintType::Variables => format!(
+                "{} ({})",
+                print_type,
+                combine_variable_names(&preset_variables)
+            ),
+            PrintType::WindowTitle => "Window Titles".to_string(),
+            PrintType::WorkingDirectory => "Working Directories".to_string(),
+            PrintType::DocumentPath => "Document Paths".to_string(),
+            _ => {
+                warn!(
+                    "Print type {:?} cannot be compared, preset skipped.",
+                    print_type
+                );
+                continue;
+            }
+        };
+
+        let timezone_entries = match &preset.timezone {
+            Some(name) => {
+                let timezone = parse_timezone(name)?;
+                lines.push(format!("Timezone: {}", timezone.name()));
+                Some(shift_entries_to_timezone(entries, timezone))
+            }
+            None => None,
+        };
+
+        generate_comparison(
+            timezone_entries.as_ref().unwrap_or(entries),
+            &mut lines,
+            line_indent,
+            &heading_text,
+            datetime_pair,
+            against_datetime_pair,
+            preset.format_datetime.unwrap(),
+            preset.format_duration.unwrap(),
+            &preset_variables,
+            preset.sort_by.unwrap_or_default(),
+        );
+        lines.push("".to_string());
+    }
+
+    Ok(lines)
+}
+
+/// Generate the lines of each preset once for each period of the
+/// granularity (such as each day of a week), with a heading before
+/// each period. The 'entries' must cover the range given by
+/// 'get_presets_datetime_range' for every period.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_presets_per_period(
+    presets: &Vec<PrintPresetSettings>,
+    entries: &Entries,
+    datetime_pair: DateTimeLocalPair,
+    granularity: Granularity,
+    datetime_format: DateTimeFormat,
+    utilization_settings: &PrintUtilizationSettings,
+    work_hours_per_day: f64,
+    first_day_of_week: FirstDayOfWeek,
+    color: Option<colored::Color>,
+    executable_colors: &ExecutableColors,
+) -> Result<Vec<String>, RenderError> {
+    let mut lines = Vec::new();
+    for (heading, period_datetime_pair) in get_granularity_datetime_ranges(
+        granularity,
+        datetime_pair,
+        datetime_format,
+        first_day_of_week,
+    ) {
+        lines.push(format!("=== {} ===", heading));
+        lines.push("".to_string());
+        lines.extend(generate_presets(
+            presets,
+            entries,
+            period_datetime_pair,
+            utilization_settings,
+            work_hours_per_day,
+            first_day_of_week,
+            color,
+            executable_colors,
+        )?);
     }
 
     Ok(lines)
@@ -1,33 +1,63 @@
+use crate::aggregate::find_entry_active_span;
+use crate::aggregate::find_entry_idle_periods;
+use crate::aggregate::get_duration_map_keys_listed;
 use crate::aggregate::get_map_keys_sorted_general;
 use crate::aggregate::get_map_keys_sorted_strings;
 use crate::aggregate::sum_entry_activity_duration;
 use crate::aggregate::sum_entry_duration;
 use crate::aggregate::sum_entry_executable_duration;
+use crate::aggregate::sum_entry_host_busy_duration;
+use crate::aggregate::sum_entry_presence_duration;
 use crate::aggregate::sum_entry_variables_duration;
+use crate::aggregate::DurationListKeys;
+use crate::aggregate::DurationListOptions;
+use crate::compare::compare_entry_variables_duration;
+use crate::compare::sort_by_largest_delta;
+use crate::compare::DurationChange;
+use crate::datetime::get_previous_datetime_range;
 use crate::datetime::get_week_datetime_local;
+use crate::datetime::get_week_number;
 use crate::datetime::get_weekdays_datetime_local;
+use crate::datetime::get_weeks_datetime_local;
+use crate::datetime::utc_seconds_to_datetime_local;
 use crate::datetime::DateTimeLocalPair;
+use crate::ramp::format_sparkline;
+use crate::ramp::DensityRamp;
+use crate::ramp::ExecutableColors;
+use crate::utils::option_string_to_string;
 use crate::variable::combine_variable_names;
+use crate::variable::combine_variable_values;
 use crate::variable::Variable;
 
-use anyhow::Result;
+use crate::errors::RenderError;
 use chrono::Datelike;
 use colored::Colorize;
 use log::debug;
+use std::collections::HashMap;
 use timetracker_core::entries::Entry;
 use timetracker_core::entries::EntryStatus;
+use timetracker_core::entries::HostBusyPeriod;
 use timetracker_core::format::format_date;
 use timetracker_core::format::format_duration;
 use timetracker_core::format::format_naive_time_no_seconds;
+use timetracker_core::format::format_time;
+use timetracker_core::format::format_time_no_seconds;
 use timetracker_core::format::DateTimeFormat;
 use timetracker_core::format::DurationFormat;
+use timetracker_core::format::FirstDayOfWeek;
 use timetracker_core::format::PrintType;
+use timetracker_core::format::SortBy;
 use timetracker_core::format::TimeBlockUnit;
 use timetracker_core::format::TimeScale;
+use timetracker_core::settings::PrintUtilizationSettings;
 use timetracker_core::storage::Entries;
 
-const HEADING_TOTAL_TEXT_START: &str = "[total ";
-const HEADING_TOTAL_TEXT_END: &str = "]";
+pub(crate) const HEADING_TOTAL_TEXT_START: &str = "[total ";
+pub(crate) const HEADING_TOTAL_TEXT_END: &str = "]";
+
+/// The number of changes listed as the biggest movers of a churn
+/// report.
+const CHURN_MOVER_COUNT: usize = 5;
 
 fn combine_start_end_lines(
     lines: &mut Vec<String>,
@@ -60,6 +90,7 @@ fn get_longest_string(values: &[String]) -> usize {
 }
 
 // TODO: Eliminate the generated spaces when a line_mid* value is empty.
+#[allow(clippy::too_many_arguments)]
 fn combine_start_mid_end_lines(
     lines: &mut Vec<String>,
     lines_start: &[String],
@@ -133,6 +164,776 @@ fn combine_start_mid_end_lines(
     }
 }
 
+/// Format the utilization (the active duration divided by the
+/// presence duration) as a percentage.
+fn format_utilization(
+    active_duration: chrono::Duration,
+    presence_duration: chrono::Duration,
+    duration_format: DurationFormat,
+) -> String {
+    let utilization = match presence_duration.num_seconds() {
+        0 => 0.0,
+        presence_seconds => {
+            (active_duration.num_seconds() as f64 / presence_seconds as f64) * 100.0
+        }
+    };
+    format!(
+        "{:.1}% (active {} of present {})",
+        utilization,
+        format_duration(active_duration, duration_format),
+        format_duration(presence_duration, duration_format),
+    )
+}
+
+fn generate_utilization_week(
+    entries: &Entries,
+    lines: &mut Vec<String>,
+    line_prefix: &str,
+    week_datetime_pair: DateTimeLocalPair,
+    datetime_format: DateTimeFormat,
+    duration_format: DurationFormat,
+    utilization_settings: &PrintUtilizationSettings,
+) -> Result<(), RenderError> {
+    let (week_start_datetime, week_end_datetime) = week_datetime_pair;
+    let week_entries = entries.datetime_range_entries(week_start_datetime, week_end_datetime);
+
+    let active_duration = sum_entry_duration(week_entries, EntryStatus::Active);
+    let presence_duration =
+        sum_entry_presence_duration(week_entries, utilization_settings.idle_threshold_seconds);
+    let week_start_date_text = format_date(week_start_datetime, datetime_format);
+    let week_end_date_text = format_date(week_end_datetime, datetime_format);
+
+    let line = format!(
+        "{}{} to {} | utilization {}",
+        line_prefix,
+        week_start_date_text,
+        week_end_date_text,
+        format_utilization(active_duration, presence_duration, duration_format)
+    );
+    lines.push(line);
+    Ok(())
+}
+
+/// The utilization of a month (or fortnight), and of each week.
+#[allow(clippy::too_many_arguments)]
+fn generate_utilization_month(
+    entries: &Entries,
+    lines: &mut Vec<String>,
+    line_prefix: &str,
+    month_datetime_pair: DateTimeLocalPair,
+    datetime_format: DateTimeFormat,
+    duration_format: DurationFormat,
+    utilization_settings: &PrintUtilizationSettings,
+    first_day_of_week: FirstDayOfWeek,
+) -> Result<(), RenderError> {
+    generate_utilization_week(
+        entries,
+        lines,
+        line_prefix,
+        month_datetime_pair,
+        datetime_format,
+        duration_format,
+        utilization_settings,
+    )?;
+
+    let (month_start_datetime, month_end_datetime) = month_datetime_pair;
+    let mut lines_start = Vec::new();
+    let mut lines_end = Vec::new();
+    for (week, (week_start_datetime, week_end_datetime)) in
+        get_weeks_datetime_local(month_start_datetime, month_end_datetime, first_day_of_week)
+    {
+        let week_entries = entries.datetime_range_entries(week_start_datetime, week_end_datetime);
+        if week_entries.is_empty() {
+            continue;
+        }
+
+        let active_duration = sum_entry_duration(week_entries, EntryStatus::Active);
+        let presence_duration =
+            sum_entry_presence_duration(week_entries, utilization_settings.idle_threshold_seconds);
+        lines_start.push(format!("{}- Week {}", line_prefix, week));
+        lines_end.push(format!(
+            "utilization {}",
+            format_utilization(active_duration, presence_duration, duration_format)
+        ));
+    }
+
+    let middle_string = " | ".to_string();
+    combine_start_end_lines(lines, &lines_start, &lines_end, &middle_string);
+    Ok(())
+}
+
+fn generate_utilization_weekday(
+    entries: &Entries,
+    lines: &mut Vec<String>,
+    line_prefix: &str,
+    week_datetime_pair: DateTimeLocalPair,
+    datetime_format: DateTimeFormat,
+    duration_format: DurationFormat,
+    utilization_settings: &PrintUtilizationSettings,
+) -> Result<(), RenderError> {
+    let (week_start_datetime, week_end_datetime) = week_datetime_pair;
+
+    let mut lines_start = Vec::new();
+    let mut lines_end = Vec::new();
+
+    let weekdays_datetime_pairs =
+        get_weekdays_datetime_local(week_start_datetime, week_end_datetime);
+    for (weekday, weekdays_datetime_pair) in weekdays_datetime_pairs {
+        let (weekday_start_datetime, weekday_end_datetime) = weekdays_datetime_pair;
+        let weekday_entries =
+            entries.datetime_range_entries(weekday_start_datetime, weekday_end_datetime);
+
+        if weekday_entries.is_empty() {
+            continue;
+        }
+
+        let active_duration = sum_entry_duration(weekday_entries, EntryStatus::Active);
+        let presence_duration = sum_entry_presence_duration(
+            weekday_entries,
+            utilization_settings.idle_threshold_seconds,
+        );
+
+        let line_start = format!(
+            "{}{} {}",
+            line_prefix,
+            weekday,
+            format_date(weekday_start_datetime, datetime_format),
+        );
+        let line_end = format!(
+            "utilization {}",
+            format_utilization(active_duration, presence_duration, duration_format)
+        );
+        lines_start.push(line_start);
+        lines_end.push(line_end);
+    }
+
+    let middle_string = " | ".to_string();
+    combine_start_end_lines(lines, &lines_start, &lines_end, &middle_string);
+    Ok(())
+}
+
+/// The active time expected on a day; 'work_hours_per_day' on Monday
+/// to Friday, and none on the weekend.
+fn get_overtime_target_duration(
+    weekday: chrono::Weekday,
+    work_hours_per_day: f64,
+) -> chrono::Duration {
+    match weekday {
+        chrono::Weekday::Sat | chrono::Weekday::Sun => chrono::Duration::zero(),
+        _ => chrono::Duration::seconds((work_hours_per_day * 60.0 * 60.0).round() as i64),
+    }
+}
+
+/// Format a duration with a "+" or "-" sign, such as "+01h 30m".
+fn format_signed_duration(duration: chrono::Duration, duration_format: DurationFormat) -> String {
+    let sign = match duration < chrono::Duration::zero() {
+        true => "-",
+        false => "+",
+    };
+    format!(
+        "{}{}",
+        sign,
+        format_duration(duration.abs(), duration_format)
+    )
+}
+
+/// Sum the active time and the target time of each day in the
+/// datetime range. Days without any entries (such as holidays) are
+/// skipped.
+fn sum_overtime_durations(
+    entries: &Entries,
+    datetime_pair: DateTimeLocalPair,
+    work_hours_per_day: f64,
+) -> (chrono::Duration, chrono::Duration) {
+    let (start_datetime, end_datetime) = datetime_pair;
+    let mut active_duration = chrono::Duration::zero();
+    let mut target_duration = chrono::Duration::zero();
+    for (weekday, (day_start_datetime, day_end_datetime)) in
+        get_weekdays_datetime_local(start_datetime, end_datetime)
+    {
+        let day_entries = entries.datetime_range_entries(day_start_datetime, day_end_datetime);
+        if day_entries.is_empty() {
+            continue;
+        }
+        active_duration += sum_entry_duration(day_entries, EntryStatus::Active);
+        target_duration += get_overtime_target_duration(weekday, work_hours_per_day);
+    }
+    (active_duration, target_duration)
+}
+
+fn format_overtime(
+    active_duration: chrono::Duration,
+    target_duration: chrono::Duration,
+    duration_format: DurationFormat,
+) -> String {
+    format!(
+        "active {} | target {} | {}",
+        format_duration(active_duration, duration_format),
+        format_duration(target_duration, duration_format),
+        format_signed_duration(active_duration - target_duration, duration_format),
+    )
+}
+
+fn generate_overtime_week(
+    entries: &Entries,
+    lines: &mut Vec<String>,
+    line_prefix: &str,
+    week_datetime_pair: DateTimeLocalPair,
+    datetime_format: DateTimeFormat,
+    duration_format: DurationFormat,
+    work_hours_per_day: f64,
+) -> Result<(), RenderError> {
+    let (week_start_datetime, week_end_datetime) = week_datetime_pair;
+    let (active_duration, target_duration) =
+        sum_overtime_durations(entries, week_datetime_pair, work_hours_per_day);
+
+    let line = format!(
+        "{}{} to {} | {}",
+        line_prefix,
+        format_date(week_start_datetime, datetime_format),
+        format_date(week_end_datetime, datetime_format),
+        format_overtime(active_duration, target_duration, duration_format)
+    );
+    lines.push(line);
+    Ok(())
+}
+
+/// The overtime of a month (or fortnight), and of each week with the
+/// running balance.
+#[allow(clippy::too_many_arguments)]
+fn generate_overtime_month(
+    entries: &Entries,
+    lines: &mut Vec<String>,
+    line_prefix: &str,
+    month_datetime_pair: DateTimeLocalPair,
+    datetime_format: DateTimeFormat,
+    duration_format: DurationFormat,
+    work_hours_per_day: f64,
+    first_day_of_week: FirstDayOfWeek,
+) -> Result<(), RenderError> {
+    generate_overtime_week(
+        entries,
+        lines,
+        line_prefix,
+        month_datetime_pair,
+        datetime_format,
+        duration_format,
+        work_hours_per_day,
+    )?;
+
+    let (month_start_datetime, month_end_datetime) = month_datetime_pair;
+    let mut balance_duration = chrono::Duration::zero();
+    let mut lines_start = Vec::new();
+    let mut lines_end = Vec::new();
+    for (week, week_datetime_pair) in
+        get_weeks_datetime_local(month_start_datetime, month_end_datetime, first_day_of_week)
+    {
+        let (active_duration, target_duration) =
+            sum_overtime_durations(entries, week_datetime_pair, work_hours_per_day);
+        if active_duration.is_zero() && target_duration.is_zero() {
+            continue;
+        }
+
+        balance_duration += active_duration - target_duration;
+        lines_start.push(format!("{}- Week {}", line_prefix, week));
+        lines_end.push(format!(
+            "{} | balance {}",
+            format_overtime(active_duration, target_duration, duration_format),
+            format_signed_duration(balance_duration, duration_format)
+        ));
+    }
+
+    let middle_string = " | ".to_string();
+    combine_start_end_lines(lines, &lines_start, &lines_end, &middle_string);
+    Ok(())
+}
+
+/// The overtime of each day in the week, with the running balance of
+/// the week.
+fn generate_overtime_weekday(
+    entries: &Entries,
+    lines: &mut Vec<String>,
+    line_prefix: &str,
+    week_datetime_pair: DateTimeLocalPair,
+    datetime_format: DateTimeFormat,
+    duration_format: DurationFormat,
+    work_hours_per_day: f64,
+) -> Result<(), RenderError> {
+    let (week_start_datetime, week_end_datetime) = week_datetime_pair;
+
+    let mut balance_duration = chrono::Duration::zero();
+    let mut lines_start = Vec::new();
+    let mut lines_end = Vec::new();
+    for (weekday, weekday_datetime_pair) in
+        get_weekdays_datetime_local(week_start_datetime, week_end_datetime)
+    {
+        let (weekday_start_datetime, weekday_end_datetime) = weekday_datetime_pair;
+        let weekday_entries =
+            entries.datetime_range_entries(weekday_start_datetime, weekday_end_datetime);
+        if weekday_entries.is_empty() {
+            continue;
+        }
+
+        let active_duration = sum_entry_duration(weekday_entries, EntryStatus::Active);
+        let target_duration = get_overtime_target_duration(weekday, work_hours_per_day);
+        balance_duration += active_duration - target_duration;
+
+        lines_start.push(format!(
+            "{}{} {}",
+            line_prefix,
+            weekday,
+            format_date(weekday_start_datetime, datetime_format),
+        ));
+        lines_end.push(format!(
+            "{} | balance {}",
+            format_overtime(active_duration, target_duration, duration_format),
+            format_signed_duration(balance_duration, duration_format)
+        ));
+    }
+
+    let middle_string = " | ".to_string();
+    combine_start_end_lines(lines, &lines_start, &lines_end, &middle_string);
+    Ok(())
+}
+
+/// Format the active duration of a plan, with the difference from
+/// the planned duration and the percentage of the plan used.
+fn format_plan_variance(
+    planned_duration: chrono::Duration,
+    actual_duration: chrono::Duration,
+    duration_format: DurationFormat,
+) -> String {
+    let percentage = match planned_duration.is_zero() {
+        true => "".to_string(),
+        false => format!(
+            " ({:.0}%)",
+            (actual_duration.num_seconds() as f64 / planned_duration.num_seconds() as f64) * 100.0
+        ),
+    };
+    format!(
+        "planned {} | actual {} | {}{}",
+        format_duration(planned_duration, duration_format),
+        format_duration(actual_duration, duration_format),
+        format_signed_duration(actual_duration - planned_duration, duration_format),
+        percentage
+    )
+}
+
+/// Compare the planned duration of each value (such as a project)
+/// with the active duration recorded, for each week in the datetime
+/// range.
+fn generate_plan(
+    entries: &Entries,
+    lines: &mut Vec<String>,
+    line_prefix: &str,
+    datetime_pair: DateTimeLocalPair,
+    datetime_format: DateTimeFormat,
+    duration_format: DurationFormat,
+    first_day_of_week: FirstDayOfWeek,
+) -> Result<(), RenderError> {
+    let (start_datetime, end_datetime) = datetime_pair;
+    let middle_string = " | ".to_string();
+    for (_week, (week_start_datetime, week_end_datetime)) in
+        get_weeks_datetime_local(start_datetime, end_datetime, first_day_of_week)
+    {
+        let week_start_date = first_day_of_week.week_start_date(week_start_datetime.date_naive());
+        lines.push(format!(
+            "{}{} to {}:",
+            line_prefix,
+            format_date(week_start_datetime, datetime_format),
+            format_date(week_end_datetime, datetime_format),
+        ));
+
+        let week_plans = entries.week_plans(week_start_date);
+        if week_plans.is_empty() {
+            lines.push(format!("{} No plan for this week.", line_prefix));
+            continue;
+        }
+
+        let week_entries = entries.datetime_range_entries(week_start_datetime, week_end_datetime);
+        let mut total_planned_duration = chrono::Duration::zero();
+        let mut total_actual_duration = chrono::Duration::zero();
+        let mut lines_start = Vec::new();
+        let mut lines_end = Vec::new();
+        for plan in week_plans {
+            let variable = Variable::from_name(&plan.variable_name);
+            let actual_duration = week_entries
+                .iter()
+                .filter(|entry| entry.status == EntryStatus::Active)
+                .filter(|entry| {
+                    combine_variable_values(entry, std::slice::from_ref(&variable))
+                        == plan.variable_value
+                })
+                .fold(chrono::Duration::zero(), |duration, entry| {
+                    duration + chrono::Duration::seconds(entry.duration_seconds as i64)
+                });
+            let planned_duration = chrono::Duration::seconds(plan.planned_seconds as i64);
+            total_planned_duration += planned_duration;
+            total_actual_duration += actual_duration;
+
+            lines_start.push(format!(
+                "{} - {}={}",
+                line_prefix, plan.variable_name, plan.variable_value
+            ));
+            lines_end.push(format_plan_variance(
+                planned_duration,
+                actual_duration,
+                duration_format,
+            ));
+        }
+        lines_start.push(format!("{} - total", line_prefix));
+        lines_end.push(format_plan_variance(
+            total_planned_duration,
+            total_actual_duration,
+            duration_format,
+        ));
+        combine_start_end_lines(lines, &lines_start, &lines_end, &middle_string);
+    }
+    Ok(())
+}
+
+fn churn_change_name(change: &DurationChange) -> String {
+    match change.key.is_empty() {
+        true => "other".to_string(),
+        false => change.key.clone(),
+    }
+}
+
+/// List the values of the variables (such as executables or
+/// projects) that appeared or disappeared compared to the previous
+/// period, and the values with the biggest change of active time.
+#[allow(clippy::too_many_arguments)]
+fn generate_churn(
+    entries: &Entries,
+    lines: &mut Vec<String>,
+    line_prefix: &str,
+    line_heading: &str,
+    datetime_pair: DateTimeLocalPair,
+    datetime_format: DateTimeFormat,
+    duration_format: DurationFormat,
+    variables: &[Variable],
+) -> Result<(), RenderError> {
+    let (start_datetime, end_datetime) = datetime_pair;
+    let (previous_start_datetime, previous_end_datetime) =
+        get_previous_datetime_range(datetime_pair)?;
+    let current_entries = entries.datetime_range_entries(start_datetime, end_datetime);
+    let previous_entries =
+        entries.datetime_range_entries(previous_start_datetime, previous_end_datetime);
+
+    let mut changes =
+        compare_entry_variables_duration(previous_entries, current_entries, variables);
+    lines.push(format!(
+        "{} {} to {} vs. {} to {}:",
+        line_heading,
+        format_date(start_datetime, datetime_format),
+        format_date(end_datetime, datetime_format),
+        format_date(previous_start_datetime, datetime_format),
+        format_date(previous_end_datetime, datetime_format),
+    ));
+
+    let line_indent2 = format!("{} ", line_prefix);
+    let middle_string = " | ".to_string();
+
+    let mut lines_start = Vec::new();
+    let mut lines_end = Vec::new();
+    for change in changes.iter().filter(|x| x.has_appeared()) {
+        lines_start.push(format!("{}- {}", line_indent2, churn_change_name(change)));
+        lines_end.push(format_duration(change.current_duration, duration_format));
+    }
+    lines.push(format!("{}Appeared ({}):", line_prefix, lines_start.len()));
+    combine_start_end_lines(lines, &lines_start, &lines_end, &middle_string);
+
+    let mut lines_start = Vec::new();
+    let mut lines_end = Vec::new();
+    for change in changes.iter().filter(|x| x.has_disappeared()) {
+        lines_start.push(format!("{}- {}", line_indent2, churn_change_name(change)));
+        lines_end.push(format_duration(change.previous_duration, duration_format));
+    }
+    lines.push(format!(
+        "{}Disappeared ({}):",
+        line_prefix,
+        lines_start.len()
+    ));
+    combine_start_end_lines(lines, &lines_start, &lines_end, &middle_string);
+
+    sort_by_largest_delta(&mut changes);
+    let mut lines_start = Vec::new();
+    let mut lines_end = Vec::new();
+    for change in changes
+        .iter()
+        .filter(|x| !x.delta().is_zero())
+        .take(CHURN_MOVER_COUNT)
+    {
+        lines_start.push(format!("{}- {}", line_indent2, churn_change_name(change)));
+        lines_end.push(format!(
+            "{} -> {} | {}",
+            format_duration(change.previous_duration, duration_format),
+            format_duration(change.current_duration, duration_format),
+            format_signed_duration(change.delta(), duration_format),
+        ));
+    }
+    lines.push(format!("{}Biggest movers:", line_prefix));
+    combine_start_end_lines(lines, &lines_start, &lines_end, &middle_string);
+
+    Ok(())
+}
+
+/// Format the duration, followed by the share of the 'total_duration'
+/// as a percentage, such as "04h 32m (34%)".
+fn format_duration_share(
+    duration: chrono::Duration,
+    total_duration: chrono::Duration,
+    duration_format: DurationFormat,
+) -> String {
+    let percentage = match total_duration.num_seconds() {
+        0 => 0.0,
+        total_seconds => (duration.num_seconds() as f64 / total_seconds as f64) * 100.0,
+    };
+    format!(
+        "{} ({:>3.0}%)",
+        format_duration(duration, duration_format),
+        percentage
+    )
+}
+
+/// Print the durations of the values of the variables (such as
+/// executables or projects) of two periods side by side, with the
+/// change of each duration, such as "firefox | 03h 10m ( 28%) ->
+/// 04h 32m ( 34%) | +01h 22m".
+///
+/// The 'entries' must cover both the 'datetime_pair' and the
+/// 'against_datetime_pair'.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_comparison(
+    entries: &Entries,
+    lines: &mut Vec<String>,
+    line_prefix: &str,
+    line_heading: &str,
+    datetime_pair: DateTimeLocalPair,
+    against_datetime_pair: DateTimeLocalPair,
+    datetime_format: DateTimeFormat,
+    duration_format: DurationFormat,
+    variables: &[Variable],
+    sort_by: SortBy,
+) {
+    let current_entries = entries.datetime_range_entries(datetime_pair.0, datetime_pair.1);
+    let against_entries =
+        entries.datetime_range_entries(against_datetime_pair.0, against_datetime_pair.1);
+    let current_total_duration = sum_entry_duration(current_entries, EntryStatus::Active);
+    let against_total_duration = sum_entry_duration(against_entries, EntryStatus::Active);
+
+    let mut changes = compare_entry_variables_duration(against_entries, current_entries, variables);
+    if sort_by == SortBy::Duration {
+        // A stable sort, so equal durations stay sorted by key.
+        changes.sort_by_key(|x| std::cmp::Reverse(x.current_duration));
+    }
+
+    lines.push(format!(
+        "{} {} to {} vs. {} to {}:",
+        line_heading,
+        format_date(datetime_pair.0, datetime_format),
+        format_date(datetime_pair.1, datetime_format),
+        format_date(against_datetime_pair.0, datetime_format),
+        format_date(against_datetime_pair.1, datetime_format),
+    ));
+
+    let mut lines_start = Vec::new();
+    let mut lines_end = Vec::new();
+    let change_lines = changes.iter().map(|change| {
+        (
+            churn_change_name(change),
+            change.previous_duration,
+            change.current_duration,
+        )
+    });
+    let total_line = (
+        "total".to_string(),
+        against_total_duration,
+        current_total_duration,
+    );
+    for (name, against_duration, current_duration) in change_lines.chain([total_line]) {
+        lines_start.push(format!("{}- {}", line_prefix, name));
+        lines_end.push(format!(
+            "{} -> {} | {}",
+            format_duration_share(against_duration, against_total_duration, duration_format),
+            format_duration_share(current_duration, current_total_duration, duration_format),
+            format_signed_duration(current_duration - against_duration, duration_format),
+        ));
+    }
+
+    let middle_string = " | ".to_string();
+    combine_start_end_lines(lines, &lines_start, &lines_end, &middle_string);
+}
+
+/// Print statistics of the active days of many weeks; the average
+/// active time of a day and a week, the busiest weekday, the average
+/// start and end times of a day, and a sparkline of the totals of
+/// each week.
+fn generate_statistics(
+    entries: &Entries,
+    lines: &mut Vec<String>,
+    line_prefix: &str,
+    weeks_datetime_pair: DateTimeLocalPair,
+    datetime_format: DateTimeFormat,
+    duration_format: DurationFormat,
+    first_day_of_week: FirstDayOfWeek,
+) -> Result<(), RenderError> {
+    let (weeks_start_datetime, weeks_end_datetime) = weeks_datetime_pair;
+    let week_totals: Vec<chrono::Duration> =
+        get_weeks_datetime_local(weeks_start_datetime, weeks_end_datetime, first_day_of_week)
+            .into_iter()
+            .map(|(_week, (week_start_datetime, week_end_datetime))| {
+                let week_entries =
+                    entries.datetime_range_entries(week_start_datetime, week_end_datetime);
+                sum_entry_duration(week_entries, EntryStatus::Active)
+            })
+            .collect();
+    lines.push(format!(
+        "Statistics ({} weeks) {} to {}:",
+        week_totals.len(),
+        format_date(weeks_start_datetime, datetime_format),
+        format_date(weeks_end_datetime, datetime_format),
+    ));
+
+    let mut day_count = 0;
+    let mut total_duration = chrono::Duration::zero();
+    let mut start_seconds_sum = 0;
+    let mut end_seconds_sum = 0;
+    let mut weekday_durations = HashMap::<chrono::Weekday, (i32, chrono::Duration)>::new();
+    for (weekday, (day_start_datetime, day_end_datetime)) in
+        get_weekdays_datetime_local(weeks_start_datetime, weeks_end_datetime)
+    {
+        let day_entries = entries.datetime_range_entries(day_start_datetime, day_end_datetime);
+        let (start_seconds, end_seconds) = match find_entry_active_span(day_entries) {
+            Some(value) => value,
+            None => continue,
+        };
+        let day_duration = sum_entry_duration(day_entries, EntryStatus::Active);
+        day_count += 1;
+        total_duration += day_duration;
+        // The times of day are measured from the start of the day,
+        // so the last active time may be after midnight.
+        let day_start_seconds = day_start_datetime.timestamp() as u64;
+        start_seconds_sum += start_seconds.saturating_sub(day_start_seconds);
+        end_seconds_sum += end_seconds.saturating_sub(day_start_seconds);
+        let weekday_duration = weekday_durations
+            .entry(weekday)
+            .or_insert((0, chrono::Duration::zero()));
+        weekday_duration.0 += 1;
+        weekday_duration.1 += day_duration;
+    }
+    if day_count == 0 {
+        lines.push(format!("{}No active days.", line_prefix));
+        return Ok(());
+    }
+
+    let format_average_time = |seconds_sum: u64| {
+        let seconds = (seconds_sum / day_count as u64) % (24 * 60 * 60);
+        let time = chrono::NaiveTime::from_num_seconds_from_midnight_opt(seconds as u32, 0)
+            .expect("Seconds of a day should be a valid time.");
+        format_naive_time_no_seconds(time, datetime_format)
+    };
+    let busiest_weekday = weekday_durations
+        .iter()
+        .map(|(weekday, (count, duration))| (*weekday, *duration / *count))
+        .max_by_key(|(weekday, average_duration)| {
+            (
+                *average_duration,
+                std::cmp::Reverse(first_day_of_week.num_days_from_first_day(*weekday)),
+            )
+        });
+    let week_total_seconds: Vec<f64> = week_totals.iter().map(|x| x.num_seconds() as f64).collect();
+    let min_week_total = week_totals.iter().min().cloned().unwrap_or_default();
+    let max_week_total = week_totals.iter().max().cloned().unwrap_or_default();
+
+    let mut lines_start = Vec::new();
+    let mut lines_end = Vec::new();
+    lines_start.push(format!("{}- Active days", line_prefix));
+    lines_end.push(day_count.to_string());
+    lines_start.push(format!("{}- Average day", line_prefix));
+    lines_end.push(format_duration(total_duration / day_count, duration_format));
+    lines_start.push(format!("{}- Average week", line_prefix));
+    lines_end.push(format_duration(
+        total_duration / week_totals.len().max(1) as i32,
+        duration_format,
+    ));
+    if let Some((weekday, average_duration)) = busiest_weekday {
+        lines_start.push(format!("{}- Busiest weekday", line_prefix));
+        lines_end.push(format!(
+            "{} (average {})",
+            weekday,
+            format_duration(average_duration, duration_format)
+        ));
+    }
+    lines_start.push(format!("{}- Average start", line_prefix));
+    lines_end.push(format_average_time(start_seconds_sum));
+    lines_start.push(format!("{}- Average end", line_prefix));
+    lines_end.push(format_average_time(end_seconds_sum));
+    lines_start.push(format!("{}- Weekly totals", line_prefix));
+    lines_end.push(format!(
+        "{} ({} to {})",
+        format_sparkline(&week_total_seconds),
+        format_duration(min_week_total, duration_format),
+        format_duration(max_week_total, duration_format),
+    ));
+
+    let middle_string = " | ".to_string();
+    combine_start_end_lines(lines, &lines_start, &lines_end, &middle_string);
+    Ok(())
+}
+
+/// Summarize a month (or fortnight) with the total of each week, so
+/// weekly reports do not need to be added up manually.
+#[allow(clippy::too_many_arguments)]
+fn generate_summary_month(
+    entries: &Entries,
+    lines: &mut Vec<String>,
+    line_prefix: &str,
+    line_heading: &str,
+    month_datetime_pair: DateTimeLocalPair,
+    datetime_format: DateTimeFormat,
+    duration_format: DurationFormat,
+    first_day_of_week: FirstDayOfWeek,
+) -> Result<(), RenderError> {
+    let (month_start_datetime, month_end_datetime) = month_datetime_pair;
+    let month_entries = entries.datetime_range_entries(month_start_datetime, month_end_datetime);
+
+    let month_total_duration = sum_entry_duration(month_entries, EntryStatus::Active);
+    let month_total_duration_text = format_duration(month_total_duration, duration_format);
+    lines.push(format!(
+        "{} {}{}{}:",
+        line_heading, HEADING_TOTAL_TEXT_START, month_total_duration_text, HEADING_TOTAL_TEXT_END
+    ));
+
+    let mut lines_start = Vec::new();
+    let mut lines_end = Vec::new();
+    for (week, (week_start_datetime, week_end_datetime)) in
+        get_weeks_datetime_local(month_start_datetime, month_end_datetime, first_day_of_week)
+    {
+        let week_entries = entries.datetime_range_entries(week_start_datetime, week_end_datetime);
+        if week_entries.is_empty() {
+            continue;
+        }
+
+        let week_total_duration = sum_entry_duration(week_entries, EntryStatus::Active);
+        lines_start.push(format!(
+            "{}Week {} {} to {}",
+            line_prefix,
+            week,
+            format_date(week_start_datetime, datetime_format),
+            format_date(week_end_datetime, datetime_format),
+        ));
+        lines_end.push(format!(
+            "total {}",
+            format_duration(week_total_duration, duration_format)
+        ));
+    }
+
+    let middle_string = " | ".to_string();
+    combine_start_end_lines(lines, &lines_start, &lines_end, &middle_string);
+    Ok(())
+}
+
 fn generate_summary_week(
     entries: &Entries,
     lines: &mut Vec<String>,
@@ -140,11 +941,31 @@ fn generate_summary_week(
     week_datetime_pair: DateTimeLocalPair,
     datetime_format: DateTimeFormat,
     duration_format: DurationFormat,
-) -> Result<()> {
+) -> Result<(), RenderError> {
     let (week_start_datetime, week_end_datetime) = week_datetime_pair;
     let week_entries = entries.datetime_range_entries(week_start_datetime, week_end_datetime);
 
-    let week_total_duration = sum_entry_duration(&week_entries, EntryStatus::Active);
+    let week_total_duration = sum_entry_duration(week_entries, EntryStatus::Active);
+    generate_summary_week_total(
+        week_total_duration,
+        lines,
+        line_prefix,
+        week_datetime_pair,
+        datetime_format,
+        duration_format,
+    );
+    Ok(())
+}
+
+fn generate_summary_week_total(
+    week_total_duration: chrono::Duration,
+    lines: &mut Vec<String>,
+    line_prefix: &str,
+    week_datetime_pair: DateTimeLocalPair,
+    datetime_format: DateTimeFormat,
+    duration_format: DurationFormat,
+) {
+    let (week_start_datetime, week_end_datetime) = week_datetime_pair;
     let week_start_date_text = format_date(week_start_datetime, datetime_format);
     let week_end_date_text = format_date(week_end_datetime, datetime_format);
     let week_total_duration_text = format_duration(week_total_duration, duration_format);
@@ -154,7 +975,6 @@ fn generate_summary_week(
         line_prefix, week_start_date_text, week_end_date_text, week_total_duration_text
     );
     lines.push(line);
-    Ok(())
 }
 
 fn generate_summary_weekday(
@@ -165,11 +985,14 @@ fn generate_summary_weekday(
     week_datetime_pair: DateTimeLocalPair,
     datetime_format: DateTimeFormat,
     duration_format: DurationFormat,
-) -> Result<()> {
+) -> Result<(), RenderError> {
     let (week_start_datetime, week_end_datetime) = week_datetime_pair;
 
     let mut lines_start = Vec::new();
     let mut lines_end = Vec::new();
+    let mut lines_note = Vec::new();
+    let mut lines_host_busy = Vec::new();
+    let mut lines_locked = Vec::new();
 
     let mut week_total_duration = chrono::Duration::zero();
 
@@ -180,12 +1003,15 @@ fn generate_summary_weekday(
         let weekday_entries =
             entries.datetime_range_entries(weekday_start_datetime, weekday_end_datetime);
 
-        if weekday_entries.is_empty() {
+        // A day with a note is displayed, even without entries (such
+        // as a day off).
+        let day_note = entries.day_note(weekday_start_datetime.date_naive());
+        if weekday_entries.is_empty() && day_note.is_none() {
             continue;
         }
 
-        let total_duration = sum_entry_duration(&weekday_entries, EntryStatus::Active);
-        week_total_duration = week_total_duration + total_duration;
+        let total_duration = sum_entry_duration(weekday_entries, EntryStatus::Active);
+        week_total_duration += total_duration;
 
         let total_duration_text = format_duration(total_duration, duration_format);
         let line_start = format!(
@@ -196,9 +1022,31 @@ fn generate_summary_weekday(
         )
         .to_string();
         let line_end = format!("total {}", total_duration_text).to_string();
+        let line_note = day_note.map(|x| format!("{}  Note: {}", line_prefix, x.note));
+
+        let line_host_busy = generate_host_busy_text(
+            weekday_entries,
+            entries.host_busy_periods(),
+            duration_format,
+        )
+        .map(|x| format!("{}  Idle while machine was busy: {}", line_prefix, x));
+
+        // Time away with a locked screen is told apart from idle
+        // time at the computer (such as reading).
+        let locked_duration = sum_entry_duration(weekday_entries, EntryStatus::Locked);
+        let line_locked = (locked_duration > chrono::Duration::zero()).then(|| {
+            format!(
+                "{}  Screen locked: {}",
+                line_prefix,
+                format_duration(locked_duration, duration_format)
+            )
+        });
 
         lines_start.push(line_start);
         lines_end.push(line_end);
+        lines_note.push(line_note);
+        lines_host_busy.push(line_host_busy);
+        lines_locked.push(line_locked);
     }
 
     let week_total_duration_text = format_duration(week_total_duration, duration_format);
@@ -207,11 +1055,102 @@ fn generate_summary_weekday(
         line_heading, HEADING_TOTAL_TEXT_START, week_total_duration_text, HEADING_TOTAL_TEXT_END
     ));
 
+    let mut weekday_lines = Vec::new();
     let middle_string = " | ".to_string();
-    combine_start_end_lines(lines, &lines_start, &lines_end, &middle_string);
+    combine_start_end_lines(&mut weekday_lines, &lines_start, &lines_end, &middle_string);
+
+    // Notes are displayed under the weekday they are written for.
+    for (((line, line_note), line_host_busy), line_locked) in weekday_lines
+        .into_iter()
+        .zip(lines_note)
+        .zip(lines_host_busy)
+        .zip(lines_locked)
+    {
+        lines.push(line);
+        if let Some(line_note) = line_note {
+            lines.push(line_note);
+        }
+        if let Some(line_host_busy) = line_host_busy {
+            lines.push(line_host_busy);
+        }
+        if let Some(line_locked) = line_locked {
+            lines.push(line_locked);
+        }
+    }
     Ok(())
 }
 
+/// Describe how long the user was idle while the machine was busy,
+/// such as "01h 20m (blender), 00h 05m (load)", or None if the user
+/// was never idle while the machine was busy.
+fn generate_host_busy_text(
+    entries: &[Entry],
+    host_busy_periods: &[HostBusyPeriod],
+    duration_format: DurationFormat,
+) -> Option<String> {
+    let duration_map = sum_entry_host_busy_duration(entries, host_busy_periods, EntryStatus::Idle);
+    if duration_map.is_empty() {
+        return None;
+    }
+
+    let mut reasons: Vec<(&String, &chrono::Duration)> = duration_map.iter().collect();
+    reasons.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    let texts: Vec<String> = reasons
+        .into_iter()
+        .map(|(reason, duration)| {
+            format!(
+                "{} ({})",
+                format_duration(*duration, duration_format),
+                reason
+            )
+        })
+        .collect();
+    Some(texts.join(", "))
+}
+
+/// Format the duration, followed by the share of the 'total_duration'
+/// as a percentage (such as "12h 30m | 41%") when 'show_percentage'
+/// is true.
+fn format_listed_duration(
+    duration: chrono::Duration,
+    total_duration: chrono::Duration,
+    duration_format: DurationFormat,
+    show_percentage: bool,
+) -> String {
+    let duration_text = format_duration(duration, duration_format);
+    if !show_percentage {
+        return duration_text;
+    }
+    let percentage = match total_duration.num_seconds() {
+        0 => 0.0,
+        total_seconds => (duration.num_seconds() as f64 / total_seconds as f64) * 100.0,
+    };
+    format!("{} | {:>3.0}%", duration_text, percentage)
+}
+
+/// The name and duration of the "other" line; the unknown duration
+/// (of entries without the variables) and the durations omitted from
+/// the 'listed_keys', such as "other (42 items)".
+fn get_other_name_duration(
+    duration_map: &HashMap<String, (Vec<String>, chrono::Duration)>,
+    listed_keys: &DurationListKeys,
+) -> Option<(String, chrono::Duration)> {
+    let unknown_duration = duration_map.get("").map(|(_vars, duration)| *duration);
+    match (unknown_duration, listed_keys.other_count) {
+        (None, 0) => None,
+        (Some(duration), 0) => Some(("other".to_string(), duration)),
+        (_, other_count) => {
+            let name = match other_count {
+                1 => "other (1 item)".to_string(),
+                _ => format!("other ({} items)", other_count),
+            };
+            let duration = listed_keys.other_duration + unknown_duration.unwrap_or_default();
+            Some((name, duration))
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn generate_entry_variables_lines(
     entries: &[Entry],
     lines_start: &mut Vec<String>,
@@ -225,15 +1164,21 @@ fn generate_entry_variables_lines(
     _datetime_format: DateTimeFormat,
     duration_format: DurationFormat,
     variables: &[Variable],
+    list_options: DurationListOptions,
 ) {
     let duration_map = sum_entry_variables_duration(entries, variables, EntryStatus::Active);
-    let keys = duration_map.keys();
-    let sorted_keys = get_map_keys_sorted_strings(&keys);
+    let listed_keys = get_duration_map_keys_listed(&duration_map, list_options);
+    let total_duration = sum_entry_duration(entries, EntryStatus::Active);
 
-    for key in sorted_keys {
-        if let Some(value) = duration_map.get(&key) {
+    for key in &listed_keys.keys {
+        if let Some(value) = duration_map.get(key) {
             let (vars, duration) = value;
-            let duration_text = format_duration(*duration, duration_format);
+            let duration_text = format_listed_duration(
+                *duration,
+                total_duration,
+                duration_format,
+                list_options.show_percentage,
+            );
             let line_start = format!("{}-", line_prefix).to_string();
 
             let line_mid1 = if !vars.is_empty() {
@@ -279,45 +1224,21 @@ fn generate_entry_variables_lines(
     }
 
     // Print unknown "other" durations, when the variables could
-    // not be found.
-    let empty_key = String::new();
-
-    if let Some(value) = duration_map.get(&empty_key) {
-        let (vars, duration) = value;
-        let duration_text = format_duration(*duration, duration_format);
+    // not be found, and the durations omitted from the list.
+    if let Some((other_name, duration)) = get_other_name_duration(&duration_map, &listed_keys) {
+        let duration_text = format_listed_duration(
+            duration,
+            total_duration,
+            duration_format,
+            list_options.show_percentage,
+        );
 
         let line_start = format!("{}-", line_prefix);
-
-        let line_mid1 = if !vars.is_empty() {
-            vars[0].to_string()
-        } else {
-            "other".to_string()
-        };
-
-        let line_mid2 = if vars.len() > 1 {
-            vars[1].to_string()
-        } else {
-            "".to_string()
-        };
-
-        let line_mid3 = if vars.len() > 2 {
-            vars[2].to_string()
-        } else {
-            "".to_string()
-        };
-
-        let line_mid4 = if vars.len() > 3 {
-            vars[3].to_string()
-        } else {
-            "".to_string()
-        };
-
-        let line_mid5 = if vars.len() > 4 {
-            vars[4].to_string()
-        } else {
-            "".to_string()
-        };
-
+        let line_mid1 = other_name;
+        let line_mid2 = "".to_string();
+        let line_mid3 = "".to_string();
+        let line_mid4 = "".to_string();
+        let line_mid5 = "".to_string();
         let line_end = duration_text;
 
         lines_start.push(line_start);
@@ -330,6 +1251,7 @@ fn generate_entry_variables_lines(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn generate_variables_week(
     entries: &Entries,
     lines: &mut Vec<String>,
@@ -339,10 +1261,11 @@ fn generate_variables_week(
     datetime_format: DateTimeFormat,
     duration_format: DurationFormat,
     variables: &[Variable],
-) -> Result<()> {
+    list_options: DurationListOptions,
+) -> Result<(), RenderError> {
     let (week_start_datetime, week_end_datetime) = week_datetime_pair;
     let week_entries = entries.datetime_range_entries(week_start_datetime, week_end_datetime);
-    let week_total_duration = sum_entry_duration(&week_entries, EntryStatus::Active);
+    let week_total_duration = sum_entry_duration(week_entries, EntryStatus::Active);
 
     let mut lines_start = Vec::new();
     let mut lines_mid1 = Vec::new();
@@ -354,7 +1277,7 @@ fn generate_variables_week(
 
     // Group entries by variable name and print details.
     generate_entry_variables_lines(
-        &week_entries,
+        week_entries,
         &mut lines_start,
         &mut lines_mid1,
         &mut lines_mid2,
@@ -366,6 +1289,7 @@ fn generate_variables_week(
         datetime_format,
         duration_format,
         variables,
+        list_options,
     );
 
     let week_total_duration_text = format_duration(week_total_duration, duration_format);
@@ -390,6 +1314,7 @@ fn generate_variables_week(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn generate_variables_weekday(
     entries: &Entries,
     lines: &mut Vec<String>,
@@ -398,7 +1323,8 @@ fn generate_variables_weekday(
     datetime_format: DateTimeFormat,
     duration_format: DurationFormat,
     variables: &[Variable],
-) -> Result<()> {
+    list_options: DurationListOptions,
+) -> Result<(), RenderError> {
     let (week_start_datetime, week_end_datetime) = week_datetime_pair;
 
     let weekdays_datetime_pairs =
@@ -412,7 +1338,7 @@ fn generate_variables_weekday(
             continue;
         }
 
-        let total_duration = sum_entry_duration(&weekday_entries, EntryStatus::Active);
+        let total_duration = sum_entry_duration(weekday_entries, EntryStatus::Active);
         let total_duration_text = format_duration(total_duration, duration_format);
         let line = format!(
             "{}{} {} {}{}{}",
@@ -436,7 +1362,7 @@ fn generate_variables_weekday(
 
         let line_indent2 = format!("{} ", line_prefix);
         generate_entry_variables_lines(
-            &weekday_entries,
+            weekday_entries,
             &mut lines_start,
             &mut lines_mid1,
             &mut lines_mid2,
@@ -448,6 +1374,7 @@ fn generate_variables_weekday(
             datetime_format,
             duration_format,
             variables,
+            list_options,
         );
 
         let middle_string = " ".to_string();
@@ -474,47 +1401,94 @@ fn generate_entry_software_lines(
     line_prefix: &str,
     _datetime_format: DateTimeFormat,
     duration_format: DurationFormat,
+    list_options: DurationListOptions,
+    executable_colors: &ExecutableColors,
 ) {
     let executable_duration_map = sum_entry_executable_duration(entries, EntryStatus::Active);
-    let keys = executable_duration_map.keys();
-    // TODO: Allow sorting by value, so we can show how much the
-    // software was used, starting at the top of the print out (rather
-    // than alphabetical).
-    let sorted_keys = get_map_keys_sorted_strings(&keys);
+    let total_duration = sum_entry_duration(entries, EntryStatus::Active);
+    generate_software_lines(
+        &executable_duration_map,
+        total_duration,
+        lines,
+        line_prefix,
+        duration_format,
+        list_options,
+        executable_colors,
+    );
+}
+
+fn generate_software_lines(
+    executable_duration_map: &HashMap<String, (Vec<String>, chrono::Duration)>,
+    total_duration: chrono::Duration,
+    lines: &mut Vec<String>,
+    line_prefix: &str,
+    duration_format: DurationFormat,
+    list_options: DurationListOptions,
+    executable_colors: &ExecutableColors,
+) {
+    let listed_keys = get_duration_map_keys_listed(executable_duration_map, list_options);
 
     let mut lines_start = Vec::new();
     let mut lines_end = Vec::new();
+    let mut lines_color = Vec::new();
 
-    for key in &sorted_keys {
+    for key in &listed_keys.keys {
         if let Some(value) = executable_duration_map.get(key) {
             let (_vars, duration) = value;
-            let duration_text = format_duration(*duration, duration_format);
+            let duration_text = format_listed_duration(
+                *duration,
+                total_duration,
+                duration_format,
+                list_options.show_percentage,
+            );
 
             let line_start = format!("{}- {}", line_prefix, key);
             let line_end = format!("| {}", duration_text);
 
             lines_start.push(line_start);
             lines_end.push(line_end);
+            lines_color.push(executable_colors.get(key));
         }
     }
 
     // Print unknown "other" durations, when the variables
-    // could not be found.
-    let empty_key = String::new();
-    if let Some(value) = executable_duration_map.get(&empty_key) {
-        let (_vars, duration) = value;
-        let duration_text = format_duration(*duration, duration_format);
-        let line_start = format!("{}- other", line_prefix);
+    // could not be found, and the durations omitted from the list.
+    if let Some((other_name, duration)) =
+        get_other_name_duration(executable_duration_map, &listed_keys)
+    {
+        let duration_text = format_listed_duration(
+            duration,
+            total_duration,
+            duration_format,
+            list_options.show_percentage,
+        );
+        let line_start = format!("{}- {}", line_prefix, other_name);
         let line_end = format!("| {}", duration_text);
 
         lines_start.push(line_start);
         lines_end.push(line_end);
+        lines_color.push(None);
     }
 
+    // The lines are colored after being aligned, because the color
+    // codes have no width in the terminal.
+    let mut software_lines = Vec::new();
     let middle_string = " ".to_string();
-    combine_start_end_lines(lines, &lines_start, &lines_end, &middle_string);
+    combine_start_end_lines(
+        &mut software_lines,
+        &lines_start,
+        &lines_end,
+        &middle_string,
+    );
+    for (line, color) in software_lines.into_iter().zip(lines_color) {
+        match color {
+            Some(c) => lines.push(line.color(c).to_string()),
+            None => lines.push(line),
+        }
+    }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn generate_software_week(
     entries: &Entries,
     lines: &mut Vec<String>,
@@ -523,11 +1497,13 @@ fn generate_software_week(
     week_datetime_pair: DateTimeLocalPair,
     datetime_format: DateTimeFormat,
     duration_format: DurationFormat,
-) -> Result<()> {
+    list_options: DurationListOptions,
+    executable_colors: &ExecutableColors,
+) -> Result<(), RenderError> {
     let (week_start_datetime, week_end_datetime) = week_datetime_pair;
     let week_entries = entries.datetime_range_entries(week_start_datetime, week_end_datetime);
 
-    let week_total_duration = sum_entry_duration(&week_entries, EntryStatus::Active);
+    let week_total_duration = sum_entry_duration(week_entries, EntryStatus::Active);
     let week_total_duration_text = format_duration(week_total_duration, duration_format);
     lines.push(format!(
         "{} {}{}{}:",
@@ -536,16 +1512,19 @@ fn generate_software_week(
 
     // Group entries by name and print details.
     generate_entry_software_lines(
-        &week_entries,
+        week_entries,
         lines,
         line_prefix,
         datetime_format,
         duration_format,
+        list_options,
+        executable_colors,
     );
 
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn generate_software_weekday(
     entries: &Entries,
     lines: &mut Vec<String>,
@@ -553,7 +1532,9 @@ fn generate_software_weekday(
     week_datetime_pair: DateTimeLocalPair,
     datetime_format: DateTimeFormat,
     duration_format: DurationFormat,
-) -> Result<()> {
+    list_options: DurationListOptions,
+    executable_colors: &ExecutableColors,
+) -> Result<(), RenderError> {
     let (week_start_datetime, week_end_datetime) = week_datetime_pair;
 
     let weekday_datetime_pairs =
@@ -570,7 +1551,7 @@ fn generate_software_weekday(
 
         let date_string = format_date(week_start_datetime, datetime_format);
 
-        let weekday_total_duration = sum_entry_duration(&weekday_entries, EntryStatus::Active);
+        let weekday_total_duration = sum_entry_duration(weekday_entries, EntryStatus::Active);
         let weekday_total_duration_text = format_duration(weekday_total_duration, duration_format);
         lines.push(format!(
             "{} {} {}{}{}:",
@@ -583,17 +1564,20 @@ fn generate_software_weekday(
 
         // Group entries by name and print details.
         generate_entry_software_lines(
-            &weekday_entries,
+            weekday_entries,
             lines,
             line_prefix,
             datetime_format,
             duration_format,
+            list_options,
+            executable_colors,
         );
     }
 
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn generate_entry_activity_lines(
     entries: &[Entry],
     lines: &mut Vec<String>,
@@ -663,6 +1647,7 @@ fn generate_entry_activity_lines(
     combine_start_end_lines(lines, &lines_start, &lines_end, &middle_string);
 }
 
+#[allow(clippy::too_many_arguments)]
 fn generate_activity_weekday(
     entries: &Entries,
     lines: &mut Vec<String>,
@@ -673,7 +1658,7 @@ fn generate_activity_weekday(
     time_block_unit: TimeBlockUnit,
     bar_graph_character_num_width: u8,
     color: Option<colored::Color>,
-) -> Result<()> {
+) -> Result<(), RenderError> {
     let (week_start_datetime, week_end_datetime) = week_datetime_pair;
 
     let weekday_datetime_pairs =
@@ -690,7 +1675,7 @@ fn generate_activity_weekday(
 
         let date_string = format_date(week_start_datetime, datetime_format);
 
-        let weekday_total_duration = sum_entry_duration(&weekday_entries, EntryStatus::Active);
+        let weekday_total_duration = sum_entry_duration(weekday_entries, EntryStatus::Active);
         let weekday_total_duration_text = format_duration(weekday_total_duration, duration_format);
         lines.push(format!(
             "{} {} {}{}{}",
@@ -703,7 +1688,7 @@ fn generate_activity_weekday(
 
         // Group entries by name and print details.
         generate_entry_activity_lines(
-            &weekday_entries,
+            weekday_entries,
             lines,
             line_prefix,
             datetime_format,
@@ -720,48 +1705,14 @@ fn generate_activity_weekday(
 
 fn generate_duration_bins_text(
     duration_bins_normalized: &Vec<f32>,
-    use_unicode_blocks: bool,
+    density_ramp: &DensityRamp,
     color: Option<colored::Color>,
 ) -> String {
     let mut duration_text = String::new();
     duration_text.push('[');
 
     for duration_ratio in duration_bins_normalized {
-        let duration_ratio = *duration_ratio;
-        let text;
-        if duration_ratio < 0.05 {
-            text = " ".to_string();
-        } else if duration_ratio <= 0.2 {
-            if !use_unicode_blocks {
-                text = ".".to_string();
-            } else {
-                text = "\u{2591}".to_string();
-            }
-        } else if duration_ratio <= 0.5 {
-            if !use_unicode_blocks {
-                text = "-".to_string();
-            } else {
-                text = "\u{2592}".to_string();
-            }
-        } else if duration_ratio <= 0.8 {
-            if !use_unicode_blocks {
-                text = "x".to_string();
-            } else {
-                text = "\u{2593}".to_string();
-            }
-        } else {
-            if !use_unicode_blocks {
-                text = "X".to_string();
-            } else {
-                text = "\u{2588}".to_string();
-            }
-        }
-
-        let text = match color {
-            Some(c) => text.color(c).to_string(),
-            None => text.into(),
-        };
-
+        let text = density_ramp.text(*duration_ratio, color);
         duration_text.push_str(&text)
     }
 
@@ -770,6 +1721,7 @@ fn generate_duration_bins_text(
     duration_text
 }
 
+#[allow(clippy::too_many_arguments)]
 fn generate_entry_day_activity_lines(
     entries: &[Entry],
     lines: &mut Vec<String>,
@@ -778,6 +1730,7 @@ fn generate_entry_day_activity_lines(
     duration_format: DurationFormat,
     bar_graph_character_num_width: u8,
     color: Option<colored::Color>,
+    density_ramp: &DensityRamp,
     weekday: chrono::Weekday,
     weekday_datetime_pair: DateTimeLocalPair,
     time_block_unit: TimeBlockUnit,
@@ -799,8 +1752,7 @@ fn generate_entry_day_activity_lines(
         return;
     }
 
-    let mut duration_bins: Vec<u64> = Vec::with_capacity(bar_graph_character_num_width as usize);
-    duration_bins.resize(bar_graph_character_num_width as usize, 0);
+    let mut duration_bins: Vec<u64> = vec![0; bar_graph_character_num_width as usize];
 
     let mut max_duration_bin_value = 0;
     let sorted_keys_length = sorted_keys.len() as f32;
@@ -847,9 +1799,8 @@ fn generate_entry_day_activity_lines(
     let key_first_string = format_naive_time_no_seconds(*key_first, datetime_format);
     let key_last_string = format_naive_time_no_seconds(*key_last, datetime_format);
 
-    let use_unicode_blocks = false;
     let mut duration_text =
-        generate_duration_bins_text(&duration_bins_normalized, use_unicode_blocks, color);
+        generate_duration_bins_text(&duration_bins_normalized, density_ramp, color);
     duration_text.push(' ');
     duration_text.push_str(&key_last_string);
 
@@ -863,7 +1814,7 @@ fn generate_entry_day_activity_lines(
         line_prefix, weekday, date_string, key_first_string
     );
 
-    let total_duration = sum_entry_duration(&entries, EntryStatus::Active);
+    let total_duration = sum_entry_duration(entries, EntryStatus::Active);
     let total_duration_text = format_duration(total_duration, duration_format);
     let line_end = format!(
         "{} {}{}{}",
@@ -877,6 +1828,7 @@ fn generate_entry_day_activity_lines(
     combine_start_end_lines(lines, &lines_start, &lines_end, &middle_string);
 }
 
+#[allow(clippy::too_many_arguments)]
 fn generate_activity_week(
     entries: &Entries,
     lines: &mut Vec<String>,
@@ -888,7 +1840,8 @@ fn generate_activity_week(
     time_block_unit: TimeBlockUnit,
     bar_graph_character_num_width: u8,
     color: Option<colored::Color>,
-) -> Result<()> {
+    density_ramp: &DensityRamp,
+) -> Result<(), RenderError> {
     let (week_start_datetime, week_end_datetime) = week_datetime_pair;
 
     let mut weekday_lines = Vec::<String>::new();
@@ -906,18 +1859,19 @@ fn generate_activity_week(
             continue;
         }
 
-        let weekday_total_duration = sum_entry_duration(&weekday_entries, EntryStatus::Active);
-        week_total_duration = week_total_duration + weekday_total_duration;
+        let weekday_total_duration = sum_entry_duration(weekday_entries, EntryStatus::Active);
+        week_total_duration += weekday_total_duration;
 
         // Group entries by name and print details.
         generate_entry_day_activity_lines(
-            &weekday_entries,
+            weekday_entries,
             &mut weekday_lines,
             line_prefix,
             datetime_format,
             duration_format,
             bar_graph_character_num_width,
             color,
+            density_ramp,
             weekday,
             weekday_datetime_pair,
             time_block_unit,
@@ -939,18 +1893,115 @@ fn generate_activity_week(
 /// the user into account.
 //
 // TODO: Write function to get relative fortnight and month.
-pub fn get_relative_week_start_end(relative_week_index: i32) -> Result<DateTimeLocalPair> {
+pub fn get_relative_week_start_end(
+    relative_week_index: i32,
+    first_day_of_week: FirstDayOfWeek,
+) -> Result<DateTimeLocalPair, RenderError> {
     let today_local_timezone = chrono::Local::now();
-    let today_iso_week = today_local_timezone.iso_week();
-    let today_week_num: u32 = (today_iso_week.week() as i64 + relative_week_index as i64)
+    let (_today_year, today_week) =
+        get_week_number(today_local_timezone.date_naive(), first_day_of_week);
+    let today_week_num: u32 = (today_week as i64 + relative_week_index as i64)
         .clamp(u32::MIN.into(), u32::MAX.into())
-        .try_into()?;
+        .try_into()
+        .map_err(|_| {
+            RenderError::InvalidDateTime(format!("Invalid relative week {}.", relative_week_index))
+        })?;
     let today_year = today_local_timezone.year();
 
-    Ok(get_week_datetime_local(today_year, today_week_num))
+    Ok(get_week_datetime_local(
+        today_year,
+        today_week_num,
+        first_day_of_week,
+    ))
+}
+
+/// Can the lines of the print type and time scale be generated from
+/// the summed durations of each executable (with
+/// 'generate_preset_lines_from_durations'), without the entries?
+pub fn can_generate_preset_lines_from_durations(
+    print_type: PrintType,
+    time_scale: TimeScale,
+) -> bool {
+    matches!(
+        (print_type, time_scale),
+        (PrintType::Summary, TimeScale::Week)
+            | (
+                PrintType::Software,
+                TimeScale::Week | TimeScale::Fortnight | TimeScale::Month
+            )
+    )
+}
+
+/// Prints the same lines as 'generate_preset_lines', from the
+/// 'executable_duration_map' and 'total_duration' of the active
+/// entries in the 'start_end_datetime_pair', such as summed by
+/// SQLite.
+///
+/// Only the print types and time scales accepted by
+/// 'can_generate_preset_lines_from_durations' are supported.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_preset_lines_from_durations(
+    executable_duration_map: &HashMap<String, (Vec<String>, chrono::Duration)>,
+    total_duration: chrono::Duration,
+    output_lines: &mut Vec<String>,
+    start_end_datetime_pair: DateTimeLocalPair,
+    print_type: PrintType,
+    variables: &[Variable],
+    time_scale: TimeScale,
+    datetime_format: DateTimeFormat,
+    duration_format: DurationFormat,
+    list_options: DurationListOptions,
+    executable_colors: &ExecutableColors,
+) -> Result<(), RenderError> {
+    let line_indent = " ";
+
+    match (print_type, time_scale) {
+        (PrintType::Summary, TimeScale::Week) => {
+            output_lines.push("Week Summary:".to_string());
+            generate_summary_week_total(
+                total_duration,
+                output_lines,
+                line_indent,
+                start_end_datetime_pair,
+                datetime_format,
+                duration_format,
+            );
+        }
+        (PrintType::Software, TimeScale::Week | TimeScale::Fortnight | TimeScale::Month) => {
+            let names = combine_variable_names(variables);
+            let total_duration_text = format_duration(total_duration, duration_format);
+            output_lines.push(format!(
+                "{} Software ({}) {}{}{}:",
+                time_scale,
+                names,
+                HEADING_TOTAL_TEXT_START,
+                total_duration_text,
+                HEADING_TOTAL_TEXT_END
+            ));
+            generate_software_lines(
+                executable_duration_map,
+                total_duration,
+                output_lines,
+                line_indent,
+                duration_format,
+                list_options,
+                executable_colors,
+            );
+        }
+        _ => {
+            return Err(RenderError::InvalidPreset(format!(
+                "{} {} lines cannot be generated from durations.",
+                time_scale, print_type
+            )))
+        }
+    }
+    output_lines.push("".to_string());
+
+    Ok(())
 }
 
 /// Prints the time entries with the various settings given.
+#[allow(clippy::too_many_arguments)]
 pub fn generate_preset_lines(
     entries: &Entries,
     output_lines: &mut Vec<String>,
@@ -963,7 +2014,13 @@ pub fn generate_preset_lines(
     time_block_unit: TimeBlockUnit,
     bar_graph_character_num_width: u8,
     color: Option<colored::Color>,
-) -> Result<()> {
+    density_ramp: &DensityRamp,
+    utilization_settings: &PrintUtilizationSettings,
+    work_hours_per_day: f64,
+    list_options: DurationListOptions,
+    executable_colors: &ExecutableColors,
+    first_day_of_week: FirstDayOfWeek,
+) -> Result<(), RenderError> {
     let line_indent = " ";
 
     match print_type {
@@ -993,6 +2050,20 @@ pub fn generate_preset_lines(
                 )?;
                 output_lines.push("".to_string());
             }
+            TimeScale::Fortnight | TimeScale::Month => {
+                let heading_text = format!("{} Summary", time_scale);
+                generate_summary_month(
+                    entries,
+                    output_lines,
+                    line_indent,
+                    &heading_text,
+                    start_end_datetime_pair,
+                    datetime_format,
+                    duration_format,
+                    first_day_of_week,
+                )?;
+                output_lines.push("".to_string());
+            }
         },
 
         PrintType::Activity => {
@@ -1004,13 +2075,14 @@ pub fn generate_preset_lines(
                         entries,
                         output_lines,
                         line_indent,
-                        &heading_text,
+                        heading_text,
                         start_end_datetime_pair,
                         datetime_format,
                         duration_format,
                         TimeBlockUnit::FiveMinutes,
                         bar_graph_character_num_width,
                         color,
+                        density_ramp,
                     )?;
                     output_lines.push("".to_string());
                 }
@@ -1030,6 +2102,24 @@ pub fn generate_preset_lines(
                     )?;
                     output_lines.push("".to_string());
                 }
+
+                TimeScale::Fortnight | TimeScale::Month => {
+                    let heading_text = format!("{} Activity", time_scale);
+                    generate_activity_week(
+                        entries,
+                        output_lines,
+                        line_indent,
+                        &heading_text,
+                        start_end_datetime_pair,
+                        datetime_format,
+                        duration_format,
+                        TimeBlockUnit::FiveMinutes,
+                        bar_graph_character_num_width,
+                        color,
+                        density_ramp,
+                    )?;
+                    output_lines.push("".to_string());
+                }
             }
         }
 
@@ -1047,6 +2137,7 @@ pub fn generate_preset_lines(
                     datetime_format,
                     duration_format,
                     variables,
+                    list_options,
                 )?;
                 output_lines.push("".to_string());
             }
@@ -1062,6 +2153,24 @@ pub fn generate_preset_lines(
                     datetime_format,
                     duration_format,
                     variables,
+                    list_options,
+                )?;
+                output_lines.push("".to_string());
+            }
+            TimeScale::Fortnight | TimeScale::Month => {
+                let names = combine_variable_names(variables);
+                let heading_text = format!("{} Variables ({})", time_scale, names).to_string();
+
+                generate_variables_week(
+                    entries,
+                    output_lines,
+                    line_indent,
+                    &heading_text,
+                    start_end_datetime_pair,
+                    datetime_format,
+                    duration_format,
+                    variables,
+                    list_options,
                 )?;
                 output_lines.push("".to_string());
             }
@@ -1080,6 +2189,8 @@ pub fn generate_preset_lines(
                     start_end_datetime_pair,
                     datetime_format,
                     duration_format,
+                    list_options,
+                    executable_colors,
                 )?;
                 output_lines.push("".to_string());
             }
@@ -1094,11 +2205,416 @@ pub fn generate_preset_lines(
                     start_end_datetime_pair,
                     datetime_format,
                     duration_format,
+                    list_options,
+                    executable_colors,
+                )?;
+                output_lines.push("".to_string());
+            }
+            TimeScale::Fortnight | TimeScale::Month => {
+                let names = combine_variable_names(variables);
+                let heading_text = format!("{} Software ({})", time_scale, names).to_string();
+
+                generate_software_week(
+                    entries,
+                    output_lines,
+                    line_indent,
+                    &heading_text,
+                    start_end_datetime_pair,
+                    datetime_format,
+                    duration_format,
+                    list_options,
+                    executable_colors,
+                )?;
+                output_lines.push("".to_string());
+            }
+        },
+
+        PrintType::Utilization => match time_scale {
+            TimeScale::Week => {
+                output_lines.push("Week Utilization:".to_string());
+                generate_utilization_week(
+                    entries,
+                    output_lines,
+                    line_indent,
+                    start_end_datetime_pair,
+                    datetime_format,
+                    duration_format,
+                    utilization_settings,
+                )?;
+                output_lines.push("".to_string());
+            }
+            TimeScale::Weekday => {
+                output_lines.push("Weekdays Utilization:".to_string());
+                generate_utilization_weekday(
+                    entries,
+                    output_lines,
+                    line_indent,
+                    start_end_datetime_pair,
+                    datetime_format,
+                    duration_format,
+                    utilization_settings,
+                )?;
+                output_lines.push("".to_string());
+            }
+            TimeScale::Fortnight | TimeScale::Month => {
+                output_lines.push(format!("{} Utilization:", time_scale));
+                generate_utilization_month(
+                    entries,
+                    output_lines,
+                    line_indent,
+                    start_end_datetime_pair,
+                    datetime_format,
+                    duration_format,
+                    utilization_settings,
+                    first_day_of_week,
                 )?;
                 output_lines.push("".to_string());
             }
         },
+
+        PrintType::Overtime => match time_scale {
+            TimeScale::Week => {
+                output_lines.push("Week Overtime:".to_string());
+                generate_overtime_week(
+                    entries,
+                    output_lines,
+                    line_indent,
+                    start_end_datetime_pair,
+                    datetime_format,
+                    duration_format,
+                    work_hours_per_day,
+                )?;
+                output_lines.push("".to_string());
+            }
+            TimeScale::Weekday => {
+                output_lines.push("Weekdays Overtime:".to_string());
+                generate_overtime_weekday(
+                    entries,
+                    output_lines,
+                    line_indent,
+                    start_end_datetime_pair,
+                    datetime_format,
+                    duration_format,
+                    work_hours_per_day,
+                )?;
+                output_lines.push("".to_string());
+            }
+            TimeScale::Fortnight | TimeScale::Month => {
+                output_lines.push(format!("{} Overtime:", time_scale));
+                generate_overtime_month(
+                    entries,
+                    output_lines,
+                    line_indent,
+                    start_end_datetime_pair,
+                    datetime_format,
+                    duration_format,
+                    work_hours_per_day,
+                    first_day_of_week,
+                )?;
+                output_lines.push("".to_string());
+            }
+        },
+
+        PrintType::WindowTitle | PrintType::WorkingDirectory | PrintType::DocumentPath => {
+            let heading_name = match print_type {
+                PrintType::WindowTitle => "Window Titles",
+                PrintType::DocumentPath => "Document Paths",
+                _ => "Working Directories",
+            };
+            match time_scale {
+                TimeScale::Week | TimeScale::Fortnight | TimeScale::Month => {
+                    let heading_text = format!("{} {}", time_scale, heading_name).to_string();
+
+                    generate_variables_week(
+                        entries,
+                        output_lines,
+                        line_indent,
+                        &heading_text,
+                        start_end_datetime_pair,
+                        datetime_format,
+                        duration_format,
+                        variables,
+                        list_options,
+                    )?;
+                    output_lines.push("".to_string());
+                }
+                TimeScale::Weekday => {
+                    output_lines.push(format!("Weekday {}:", heading_name));
+
+                    generate_variables_weekday(
+                        entries,
+                        output_lines,
+                        line_indent,
+                        start_end_datetime_pair,
+                        datetime_format,
+                        duration_format,
+                        variables,
+                        list_options,
+                    )?;
+                    output_lines.push("".to_string());
+                }
+            }
+        }
+
+        PrintType::Plan => {
+            output_lines.push(format!("{} Plan:", time_scale));
+            generate_plan(
+                entries,
+                output_lines,
+                line_indent,
+                start_end_datetime_pair,
+                datetime_format,
+                duration_format,
+                first_day_of_week,
+            )?;
+            output_lines.push("".to_string());
+        }
+
+        PrintType::Statistics => {
+            generate_statistics(
+                entries,
+                output_lines,
+                line_indent,
+                start_end_datetime_pair,
+                datetime_format,
+                duration_format,
+                first_day_of_week,
+            )?;
+            output_lines.push("".to_string());
+        }
+
+        PrintType::Churn => {
+            let names = combine_variable_names(variables);
+            let heading_text = format!("{} Churn ({})", time_scale, names).to_string();
+
+            generate_churn(
+                entries,
+                output_lines,
+                line_indent,
+                &heading_text,
+                start_end_datetime_pair,
+                datetime_format,
+                duration_format,
+                variables,
+            )?;
+            output_lines.push("".to_string());
+        }
     }
 
     Ok(())
 }
+
+fn entry_variables_text(entry: &Entry) -> String {
+    let vars = &entry.vars;
+    let name_values = [
+        (&vars.var1_name, &vars.var1_value),
+        (&vars.var2_name, &vars.var2_value),
+        (&vars.var3_name, &vars.var3_value),
+        (&vars.var4_name, &vars.var4_value),
+        (&vars.var5_name, &vars.var5_value),
+    ];
+
+    let mut texts = Vec::new();
+    for (name, value) in name_values {
+        if let Some(name) = name {
+            let value = option_string_to_string(value);
+            texts.push(format!("{}={}", name, value));
+        }
+    }
+    texts.join(" ")
+}
+
+/// Lists the raw entries inside a block of time, one line per entry,
+/// so the details of a block of activity can be inspected.
+pub fn generate_inspect_lines(
+    entries: &Entries,
+    lines: &mut Vec<String>,
+    block_datetime_pair: DateTimeLocalPair,
+    datetime_format: DateTimeFormat,
+    duration_format: DurationFormat,
+) -> Result<(), RenderError> {
+    let line_prefix = " ";
+    let (block_start_datetime, block_end_datetime) = block_datetime_pair;
+    let block_entries = entries.datetime_range_entries(block_start_datetime, block_end_datetime);
+
+    let block_total_duration = sum_entry_duration(block_entries, EntryStatus::Active);
+    let block_total_duration_text = format_duration(block_total_duration, duration_format);
+    lines.push(format!(
+        "Inspect {} {} to {} {}{}{}:",
+        format_date(block_start_datetime, datetime_format),
+        format_time_no_seconds(block_start_datetime, datetime_format),
+        format_time_no_seconds(block_end_datetime, datetime_format),
+        HEADING_TOTAL_TEXT_START,
+        block_total_duration_text,
+        HEADING_TOTAL_TEXT_END
+    ));
+
+    let mut lines_start = Vec::new();
+    let mut lines_end = Vec::new();
+    for entry in block_entries {
+        let datetime = utc_seconds_to_datetime_local(entry.utc_time_seconds);
+        let duration = chrono::Duration::seconds(entry.duration_seconds.try_into().unwrap());
+        let executable = option_string_to_string(&entry.vars.executable);
+
+        let line_start = format!(
+            "{}- {}",
+            line_prefix,
+            format_time(datetime, datetime_format)
+        );
+        let mut line_end = format!(
+            "{} {:<6} {} {}",
+            // Raw entries are usually only seconds long.
+            format_duration(duration, DurationFormat::HoursMinutesSeconds),
+            format!("{:?}", entry.status),
+            executable,
+            entry_variables_text(entry)
+        );
+        if entry.status == EntryStatus::Idle {
+            let duration_map = sum_entry_host_busy_duration(
+                std::slice::from_ref(entry),
+                entries.host_busy_periods(),
+                EntryStatus::Idle,
+            );
+            if !duration_map.is_empty() {
+                let reasons = get_map_keys_sorted_strings(&duration_map.keys());
+                line_end = format!(
+                    "{} (machine was busy: {})",
+                    line_end.trim_end(),
+                    reasons.join(", ")
+                );
+            }
+        }
+        lines_start.push(line_start);
+        lines_end.push(line_end.trim_end().to_string());
+    }
+
+    let middle_string = " | ".to_string();
+    combine_start_end_lines(lines, &lines_start, &lines_end, &middle_string);
+    lines.push("".to_string());
+
+    Ok(())
+}
+
+/// Lists the periods of idle time (at least
+/// 'idle_threshold_seconds' long) in the datetime range, one line
+/// per period, so time away from the computer (such as meetings) can
+/// be found and annotated.
+pub fn generate_idle_lines(
+    entries: &Entries,
+    lines: &mut Vec<String>,
+    datetime_pair: DateTimeLocalPair,
+    datetime_format: DateTimeFormat,
+    duration_format: DurationFormat,
+    idle_threshold_seconds: u64,
+) -> Result<(), RenderError> {
+    let line_prefix = " ";
+    let (start_datetime, end_datetime) = datetime_pair;
+    let range_entries = entries.datetime_range_entries(start_datetime, end_datetime);
+
+    let mut total_duration = chrono::Duration::zero();
+    let mut lines_start = Vec::new();
+    let mut lines_end = Vec::new();
+    for idle_period in find_entry_idle_periods(range_entries) {
+        let period_entries = &range_entries[idle_period];
+        let (first_entry, last_entry) = match (period_entries.first(), period_entries.last()) {
+            (Some(first_entry), Some(last_entry)) => (first_entry, last_entry),
+            _ => continue,
+        };
+        let period_start = first_entry.utc_time_seconds;
+        let period_end = last_entry.utc_time_seconds + last_entry.duration_seconds;
+        let duration_seconds = period_end - period_start;
+        if duration_seconds < idle_threshold_seconds {
+            continue;
+        }
+        let duration = chrono::Duration::seconds(duration_seconds.try_into().unwrap());
+        total_duration += duration;
+
+        let period_start_datetime = utc_seconds_to_datetime_local(period_start);
+        let period_end_datetime = utc_seconds_to_datetime_local(period_end);
+        let line_start = format!(
+            "{}- {} {} to {}",
+            line_prefix,
+            format_date(period_start_datetime, datetime_format),
+            format_time_no_seconds(period_start_datetime, datetime_format),
+            format_time_no_seconds(period_end_datetime, datetime_format),
+        );
+        let mut line_end = format_duration(duration, duration_format);
+        let duration_map = sum_entry_host_busy_duration(
+            period_entries,
+            entries.host_busy_periods(),
+            EntryStatus::Idle,
+        );
+        if !duration_map.is_empty() {
+            let reasons = get_map_keys_sorted_strings(&duration_map.keys());
+            line_end = format!("{} (machine was busy: {})", line_end, reasons.join(", "));
+        }
+        lines_start.push(line_start);
+        lines_end.push(line_end);
+    }
+
+    lines.push(format!(
+        "Idle {} to {} {}{}{}:",
+        format_date(start_datetime, datetime_format),
+        format_date(end_datetime, datetime_format),
+        HEADING_TOTAL_TEXT_START,
+        format_duration(total_duration, duration_format),
+        HEADING_TOTAL_TEXT_END
+    ));
+    let middle_string = " | ".to_string();
+    combine_start_end_lines(lines, &lines_start, &lines_end, &middle_string);
+    lines.push("".to_string());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::print::*;
+    use chrono::TimeZone;
+    use timetracker_core::entries::DayNote;
+    use timetracker_core::entries::EntryVariablesList;
+
+    #[test]
+    fn test_generate_summary_weekday_day_note_without_entries() {
+        // Monday.
+        let start_datetime = chrono::Local.with_ymd_and_hms(2024, 9, 9, 0, 0, 0).unwrap();
+        let end_datetime = chrono::Local
+            .with_ymd_and_hms(2024, 9, 15, 23, 59, 59)
+            .unwrap();
+        let nine_o_clock = start_datetime.timestamp() as u64 + (9 * 60 * 60);
+        let tuesday_date = chrono::NaiveDate::from_ymd_opt(2024, 9, 10).unwrap();
+        let entries = Entries::builder()
+            .start_datetime(start_datetime)
+            .end_datetime(end_datetime)
+            .entries(vec![Entry::new(
+                nine_o_clock,
+                60 * 60,
+                EntryStatus::Active,
+                EntryVariablesList::empty(),
+            )])
+            .day_notes(vec![DayNote::new(tuesday_date, "Day off".to_string())])
+            .build();
+
+        let mut lines = Vec::new();
+        generate_summary_weekday(
+            &entries,
+            &mut lines,
+            "",
+            "Weekdays Summary",
+            (start_datetime, end_datetime),
+            DateTimeFormat::Iso,
+            DurationFormat::HoursMinutes,
+        )
+        .unwrap();
+        assert_eq!(
+            lines,
+            vec![
+                "Weekdays Summary [total 01h 00m]:",
+                "Mon 2024-09-09 | total 01h 00m",
+                "Tue 2024-09-10 | total 00h 00m",
+                "  Note: Day off",
+            ]
+        );
+    }
+}
@@ -0,0 +1,283 @@
+use crate::errors::RenderError;
+use colored::Colorize;
+use std::collections::HashMap;
+use timetracker_core::format_short_executable_name;
+use timetracker_core::settings::PrintPresetSettings;
+
+const DEFAULT_THRESHOLDS: [f32; 4] = [0.05, 0.2, 0.5, 0.8];
+const DEFAULT_ASCII_CHARACTERS: [&str; 5] = [" ", ".", "-", "x", "X"];
+const DEFAULT_UNICODE_CHARACTERS: [&str; 5] = [" ", "\u{2591}", "\u{2592}", "\u{2593}", "\u{2588}"];
+
+const COLOR_NAMES: [&str; 17] = [
+    "black",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "magenta",
+    "purple",
+    "cyan",
+    "white",
+    "bright black",
+    "bright red",
+    "bright green",
+    "bright yellow",
+    "bright blue",
+    "bright magenta",
+    "bright cyan",
+    "bright white",
+];
+
+/// Parse a color name (such as "green" or "bright blue"), or a
+/// "#RRGGBB" hexadecimal color.
+pub fn parse_color(value: &str) -> Result<colored::Color, RenderError> {
+    let value = value.trim().to_lowercase();
+    if let Some(hex) = value.strip_prefix('#') {
+        if hex.len() == 6 && hex.is_ascii() {
+            let r = u8::from_str_radix(&hex[0..2], 16);
+            let g = u8::from_str_radix(&hex[2..4], 16);
+            let b = u8::from_str_radix(&hex[4..6], 16);
+            if let (Ok(r), Ok(g), Ok(b)) = (r, g, b) {
+                return Ok(colored::Color::TrueColor { r, g, b });
+            }
+        }
+        return Err(RenderError::InvalidPreset(format!(
+            "Invalid hexadecimal color {:?}, expected \"#RRGGBB\".",
+            value
+        )));
+    }
+
+    if !COLOR_NAMES.contains(&value.as_str()) {
+        return Err(RenderError::InvalidPreset(format!(
+            "Invalid color name {:?}, expected one of {:?}.",
+            value, COLOR_NAMES
+        )));
+    }
+    Ok(colored::Color::from(value.as_str()))
+}
+
+/// The characters (and colors) used to display the density of
+/// activity, from least to most dense.
+///
+/// A ratio below the first threshold uses the first character, a
+/// ratio up to (and including) the second threshold uses the second
+/// character, and so on. A ratio above the last threshold uses the
+/// last character.
+#[derive(Debug, Clone)]
+pub struct DensityRamp {
+    characters: Vec<String>,
+    thresholds: Vec<f32>,
+    colors: Vec<colored::Color>,
+}
+
+impl DensityRamp {
+    pub fn new(
+        characters: Vec<String>,
+        thresholds: Vec<f32>,
+        colors: Vec<colored::Color>,
+    ) -> Result<DensityRamp, RenderError> {
+        if characters.len() != thresholds.len() + 1 {
+            return Err(RenderError::InvalidPreset(format!(
+                "Activity ramp must have one more character than thresholds; found {} characters and {} thresholds.",
+                characters.len(),
+                thresholds.len()
+            )));
+        }
+        if thresholds.windows(2).any(|x| x[0] > x[1]) {
+            return Err(RenderError::InvalidPreset(format!(
+                "Activity ramp thresholds must be in ascending order; {:?}.",
+                thresholds
+            )));
+        }
+        if !colors.is_empty() && colors.len() != characters.len() {
+            return Err(RenderError::InvalidPreset(format!(
+                "Activity ramp must have a color for each character; found {} colors and {} characters.",
+                colors.len(),
+                characters.len()
+            )));
+        }
+
+        Ok(DensityRamp {
+            characters,
+            thresholds,
+            colors,
+        })
+    }
+
+    /// Create the ramp from a preset, using the default ramp for
+    /// any values not given.
+    pub fn from_preset(preset: &PrintPresetSettings) -> Result<DensityRamp, RenderError> {
+        let use_unicode_blocks = preset.activity_use_unicode_blocks.unwrap_or(false);
+        let characters = match &preset.activity_ramp_characters {
+            Some(value) => value.clone(),
+            None => match use_unicode_blocks {
+                true => DEFAULT_UNICODE_CHARACTERS,
+                false => DEFAULT_ASCII_CHARACTERS,
+            }
+            .iter()
+            .map(|x| x.to_string())
+            .collect(),
+        };
+        let thresholds = match &preset.activity_ramp_thresholds {
+            Some(value) => value.clone(),
+            None => DEFAULT_THRESHOLDS.to_vec(),
+        };
+        let colors = match &preset.activity_ramp_colors {
+            Some(value) => value
+                .iter()
+                .map(|x| parse_color(x))
+                .collect::<Result<Vec<_>, RenderError>>()?,
+            None => Vec::new(),
+        };
+
+        DensityRamp::new(characters, thresholds, colors)
+    }
+
+    fn level(&self, ratio: f32) -> usize {
+        match self.thresholds.first() {
+            Some(first) if ratio < *first => 0,
+            _ => self
+                .thresholds
+                .iter()
+                .skip(1)
+                .position(|x| ratio <= *x)
+                .map_or(self.thresholds.len(), |x| x + 1),
+        }
+    }
+
+    /// Get the (possibly colored) text for the density ratio.
+    ///
+    /// When the ramp has a color gradient it is used for each
+    /// level, otherwise the given 'color' is used for all levels.
+    pub fn text(&self, ratio: f32, color: Option<colored::Color>) -> String {
+        let level = self.level(ratio);
+        let text = &self.characters[level];
+        match color {
+            Some(c) => match self.colors.get(level) {
+                Some(gradient_color) => text.color(*gradient_color).to_string(),
+                None => text.color(c).to_string(),
+            },
+            None => text.to_string(),
+        }
+    }
+}
+
+const SPARKLINE_CHARACTERS: [&str; 8] = [
+    "\u{2581}", "\u{2582}", "\u{2583}", "\u{2584}", "\u{2585}", "\u{2586}", "\u{2587}", "\u{2588}",
+];
+
+/// A line of unicode block characters, with the height of each
+/// character relative to the largest value, such as "\u{2581}\u{2584}\u{2588}".
+pub fn format_sparkline(values: &[f64]) -> String {
+    let max_value = values.iter().cloned().fold(0.0, f64::max);
+    let last_index = (SPARKLINE_CHARACTERS.len() - 1) as f64;
+    values
+        .iter()
+        .map(|value| {
+            let ratio = match max_value > 0.0 {
+                true => (value / max_value).clamp(0.0, 1.0),
+                false => 0.0,
+            };
+            SPARKLINE_CHARACTERS[(ratio * last_index).round() as usize]
+        })
+        .collect()
+}
+
+/// The colors of executables (from the 'print.colors' settings),
+/// looked up by the full or short executable name.
+///
+/// The keys of configuration file tables are lowercased when read,
+/// so executable names are compared case-insensitively.
+#[derive(Debug, Default)]
+pub struct ExecutableColors {
+    colors: HashMap<String, colored::Color>,
+}
+
+impl ExecutableColors {
+    pub fn new(colors: &HashMap<String, String>) -> Result<ExecutableColors, RenderError> {
+        let mut parsed_colors = HashMap::new();
+        for (executable_name, color_name) in colors {
+            parsed_colors.insert(executable_name.to_lowercase(), parse_color(color_name)?);
+        }
+        Ok(ExecutableColors {
+            colors: parsed_colors,
+        })
+    }
+
+    /// The color of the executable, such as "/usr/bin/firefox" is
+    /// found with the names "/usr/bin/firefox" or "firefox".
+    pub fn get(&self, executable_name: &str) -> Option<colored::Color> {
+        if self.colors.is_empty() {
+            return None;
+        }
+        let name = executable_name.to_lowercase();
+        self.colors
+            .get(&name)
+            .or_else(|| self.colors.get(format_short_executable_name(&name)))
+            .copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::ramp::*;
+
+    #[test]
+    fn test_density_ramp_default_levels() {
+        let characters = DEFAULT_ASCII_CHARACTERS
+            .iter()
+            .map(|x| x.to_string())
+            .collect();
+        let ramp = DensityRamp::new(characters, DEFAULT_THRESHOLDS.to_vec(), Vec::new()).unwrap();
+        assert_eq!(ramp.text(0.0, None), " ");
+        assert_eq!(ramp.text(0.05, None), ".");
+        assert_eq!(ramp.text(0.2, None), ".");
+        assert_eq!(ramp.text(0.21, None), "-");
+        assert_eq!(ramp.text(0.8, None), "x");
+        assert_eq!(ramp.text(1.0, None), "X");
+    }
+
+    #[test]
+    fn test_density_ramp_invalid() {
+        let characters = vec![" ".to_string(), "#".to_string()];
+        assert!(DensityRamp::new(characters.clone(), vec![0.1, 0.5], Vec::new()).is_err());
+        assert!(
+            DensityRamp::new(characters.clone(), vec![0.5], vec![colored::Color::Red]).is_err()
+        );
+        assert!(DensityRamp::new(characters, vec![0.5], Vec::new()).is_ok());
+        assert!(parse_color("bright blue").is_ok());
+        assert!(parse_color("#00ff00").is_ok());
+        assert!(parse_color("not-a-color").is_err());
+    }
+
+    #[test]
+    fn test_format_sparkline() {
+        assert_eq!(
+            format_sparkline(&[0.0, 50.0, 100.0]),
+            "\u{2581}\u{2585}\u{2588}"
+        );
+        assert_eq!(format_sparkline(&[0.0, 0.0]), "\u{2581}\u{2581}");
+        assert_eq!(format_sparkline(&[]), "");
+    }
+
+    #[test]
+    fn test_executable_colors() {
+        let mut colors = HashMap::new();
+        colors.insert("firefox".to_string(), "bright blue".to_string());
+        colors.insert("/opt/maya/bin/maya".to_string(), "red".to_string());
+        let executable_colors = ExecutableColors::new(&colors).unwrap();
+        assert_eq!(
+            executable_colors.get("/usr/bin/Firefox"),
+            Some(colored::Color::BrightBlue)
+        );
+        assert_eq!(
+            executable_colors.get("/opt/maya/bin/maya"),
+            Some(colored::Color::Red)
+        );
+        assert_eq!(executable_colors.get("/usr/bin/maya"), None);
+
+        colors.insert("emacs".to_string(), "not-a-color".to_string());
+        assert!(ExecutableColors::new(&colors).is_err());
+    }
+}
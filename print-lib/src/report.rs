@@ -0,0 +1,355 @@
+use crate::aggregate::get_map_keys_sorted_strings;
+use crate::aggregate::sum_entry_duration;
+use crate::aggregate::sum_entry_executable_duration;
+use crate::aggregate::sum_entry_variables_duration;
+use crate::chart::generate_stacked_bar_chart;
+use crate::chart::StackedBarChart;
+use crate::datetime::get_weekdays_datetime_local;
+use crate::datetime::utc_seconds_to_datetime_local;
+use crate::datetime::DateTimeLocalPair;
+use crate::timeline::generate_timeline_blocks;
+use crate::utils::option_string_to_string;
+use crate::variable::Variable;
+use serde_derive::Serialize;
+use timetracker_core::entries::Entry;
+use timetracker_core::entries::EntryStatus;
+use timetracker_core::format::format_date;
+use timetracker_core::format::format_duration;
+use timetracker_core::format::format_time;
+use timetracker_core::format::format_time_no_seconds;
+use timetracker_core::format::DateTimeFormat;
+use timetracker_core::format::DurationFormat;
+use timetracker_core::format::TimeBlockUnit;
+use timetracker_core::storage::Entries;
+
+/// The active duration of a named item, such as an executable or a
+/// variable value.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportItem {
+    pub name: String,
+    pub active_seconds: i64,
+    pub duration: String,
+}
+
+/// The active durations of each value of a variable.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportVariable {
+    pub name: String,
+    pub items: Vec<ReportItem>,
+}
+
+/// A recorded entry, listed in the block of time it starts in.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportEntry {
+    pub time: String,
+    pub duration_seconds: u64,
+    pub duration: String,
+    pub status: String,
+    pub executable: String,
+    pub window_title: String,
+}
+
+/// A block of time in a day (of 'print.time_block_unit'), with the
+/// entries starting in the block.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportBlock {
+    /// The day and start of the block, as "YYYY-MM-DD-HHMM",
+    /// regardless of the date format. Used to link to the block.
+    pub id: String,
+    pub start_time: String,
+    pub end_time: String,
+    pub active_seconds: i64,
+    pub duration: String,
+    pub executables: Vec<ReportItem>,
+    pub variables: Vec<ReportVariable>,
+    pub entries: Vec<ReportEntry>,
+}
+
+/// The activity of a single day.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportDay {
+    /// The day, as "YYYY-MM-DD", regardless of the date format. Used
+    /// to link to the day.
+    pub id: String,
+    pub weekday: String,
+    pub date: String,
+    pub active_seconds: i64,
+    pub duration: String,
+    pub note: Option<String>,
+    pub executables: Vec<ReportItem>,
+    pub variables: Vec<ReportVariable>,
+    /// The blocks of the day with entries.
+    pub blocks: Vec<ReportBlock>,
+}
+
+/// A structured report of the recorded entries, used to feed
+/// templates and structured output formats.
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub start_datetime: String,
+    pub end_datetime: String,
+    pub start_date: String,
+    pub end_date: String,
+    pub active_seconds: i64,
+    pub duration: String,
+    pub days: Vec<ReportDay>,
+    pub executables: Vec<ReportItem>,
+    pub variables: Vec<ReportVariable>,
+    /// A stacked bar for each day, with a segment for each value of
+    /// the first variable (or each executable, without variables).
+    pub chart: StackedBarChart,
+}
+
+fn new_report_item(
+    name: String,
+    duration: chrono::Duration,
+    duration_format: DurationFormat,
+) -> ReportItem {
+    ReportItem {
+        name,
+        active_seconds: duration.num_seconds(),
+        duration: format_duration(duration, duration_format),
+    }
+}
+
+pub(crate) fn generate_report_executables(
+    entries: &[Entry],
+    duration_format: DurationFormat,
+) -> Vec<ReportItem> {
+    let duration_map = sum_entry_executable_duration(entries, EntryStatus::Active);
+    let sorted_keys = get_map_keys_sorted_strings(&duration_map.keys());
+    sorted_keys
+        .into_iter()
+        .filter_map(|key| {
+            let (_vars, duration) = duration_map.get(&key)?;
+            Some(new_report_item(key.clone(), *duration, duration_format))
+        })
+        .collect()
+}
+
+pub(crate) fn generate_report_variables(
+    entries: &[Entry],
+    variable_names: &[String],
+    duration_format: DurationFormat,
+) -> Vec<ReportVariable> {
+    let mut report_variables = Vec::new();
+    for variable_name in variable_names {
+        let variables = vec![Variable::VariableName(variable_name.clone())];
+        let duration_map = sum_entry_variables_duration(entries, &variables, EntryStatus::Active);
+        let sorted_keys = get_map_keys_sorted_strings(&duration_map.keys());
+        let items = sorted_keys
+            .into_iter()
+            .filter_map(|key| {
+                let (_vars, duration) = duration_map.get(&key)?;
+                Some(new_report_item(key.clone(), *duration, duration_format))
+            })
+            .collect();
+
+        report_variables.push(ReportVariable {
+            name: variable_name.clone(),
+            items,
+        });
+    }
+    report_variables
+}
+
+fn new_report_entry(
+    entry: &Entry,
+    datetime_format: DateTimeFormat,
+    duration_format: DurationFormat,
+) -> ReportEntry {
+    let duration = chrono::Duration::seconds(entry.duration_seconds as i64);
+    ReportEntry {
+        time: format_time(
+            utc_seconds_to_datetime_local(entry.utc_time_seconds),
+            datetime_format,
+        ),
+        duration_seconds: entry.duration_seconds,
+        duration: format_duration(duration, duration_format),
+        status: format!("{:?}", entry.status),
+        executable: option_string_to_string(&entry.vars.executable),
+        window_title: option_string_to_string(&entry.vars.window_title),
+    }
+}
+
+/// The blocks of time of the day with entries, and the entries
+/// starting in each block.
+fn generate_report_blocks(
+    entries: &Entries,
+    day_datetime_pair: DateTimeLocalPair,
+    time_block_unit: TimeBlockUnit,
+    variable_names: &[String],
+    datetime_format: DateTimeFormat,
+    duration_format: DurationFormat,
+) -> Vec<ReportBlock> {
+    let blocks = generate_timeline_blocks(
+        entries,
+        day_datetime_pair,
+        time_block_unit,
+        variable_names,
+        duration_format,
+    );
+
+    let mut report_blocks = Vec::new();
+    for block in blocks {
+        // An entry starting at the end of the block is in the next
+        // block.
+        let end_of_time = block.end_datetime.timestamp() as u64;
+        let block_entries: Vec<ReportEntry> = entries
+            .datetime_range_entries(block.start_datetime, block.end_datetime)
+            .iter()
+            .filter(|x| x.utc_time_seconds < end_of_time)
+            .map(|x| new_report_entry(x, datetime_format, duration_format))
+            .collect();
+        if block_entries.is_empty() {
+            continue;
+        }
+
+        report_blocks.push(ReportBlock {
+            id: block.start_datetime.format("%Y-%m-%d-%H%M").to_string(),
+            start_time: format_time_no_seconds(block.start_datetime, datetime_format),
+            end_time: format_time_no_seconds(block.end_datetime, datetime_format),
+            active_seconds: block.duration.num_seconds(),
+            duration: format_duration(block.duration, duration_format),
+            executables: block.executables,
+            variables: block.variables,
+            entries: block_entries,
+        });
+    }
+    report_blocks
+}
+
+/// Create a report of the entries (in the week of the entries), with
+/// the active duration of each day, executable and variable value,
+/// and the entries of each block of time ('time_block_unit') of the
+/// days.
+pub fn generate_report(
+    entries: &Entries,
+    variable_names: &[String],
+    datetime_format: DateTimeFormat,
+    duration_format: DurationFormat,
+    time_block_unit: TimeBlockUnit,
+) -> Report {
+    let start_datetime = entries.start_datetime();
+    let end_datetime = entries.end_datetime();
+    let all_entries = entries.all_entries();
+
+    let mut days = Vec::new();
+    for (weekday, (weekday_start_datetime, weekday_end_datetime)) in
+        get_weekdays_datetime_local(start_datetime, end_datetime)
+    {
+        let weekday_entries =
+            entries.datetime_range_entries(weekday_start_datetime, weekday_end_datetime);
+        // A day with a note is reported, even without entries.
+        let note = entries
+            .day_note(weekday_start_datetime.date_naive())
+            .map(|x| x.note.clone());
+        if weekday_entries.is_empty() && note.is_none() {
+            continue;
+        }
+
+        let duration = sum_entry_duration(weekday_entries, EntryStatus::Active);
+        days.push(ReportDay {
+            id: weekday_start_datetime.format("%Y-%m-%d").to_string(),
+            weekday: weekday.to_string(),
+            date: format_date(weekday_start_datetime, datetime_format),
+            active_seconds: duration.num_seconds(),
+            duration: format_duration(duration, duration_format),
+            note,
+            executables: generate_report_executables(weekday_entries, duration_format),
+            variables: generate_report_variables(weekday_entries, variable_names, duration_format),
+            blocks: generate_report_blocks(
+                entries,
+                (weekday_start_datetime, weekday_end_datetime),
+                time_block_unit,
+                variable_names,
+                datetime_format,
+                duration_format,
+            ),
+        });
+    }
+
+    let chart_variable = match variable_names.first() {
+        Some(variable_name) => Variable::VariableName(variable_name.clone()),
+        None => Variable::Executable,
+    };
+    let chart = generate_stacked_bar_chart(
+        entries,
+        (start_datetime, end_datetime),
+        chart_variable,
+        datetime_format,
+        duration_format,
+    );
+
+    let duration = sum_entry_duration(all_entries, EntryStatus::Active);
+    Report {
+        start_datetime: start_datetime.to_rfc3339(),
+        end_datetime: end_datetime.to_rfc3339(),
+        start_date: format_date(start_datetime, datetime_format),
+        end_date: format_date(end_datetime, datetime_format),
+        active_seconds: duration.num_seconds(),
+        duration: format_duration(duration, duration_format),
+        days,
+        executables: generate_report_executables(all_entries, duration_format),
+        variables: generate_report_variables(all_entries, variable_names, duration_format),
+        chart,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::report::*;
+    use chrono::TimeZone;
+    use timetracker_core::entries::DayNote;
+    use timetracker_core::entries::EntryVariablesList;
+
+    #[test]
+    fn test_generate_report_day_note_without_entries() {
+        // Monday.
+        let start_datetime = chrono::Local.with_ymd_and_hms(2024, 9, 9, 0, 0, 0).unwrap();
+        let end_datetime = chrono::Local
+            .with_ymd_and_hms(2024, 9, 15, 23, 59, 59)
+            .unwrap();
+        let nine_o_clock = start_datetime.timestamp() as u64 + (9 * 60 * 60);
+        let tuesday_date = chrono::NaiveDate::from_ymd_opt(2024, 9, 10).unwrap();
+        let entries = Entries::builder()
+            .start_datetime(start_datetime)
+            .end_datetime(end_datetime)
+            .entries(vec![Entry::new(
+                nine_o_clock,
+                60 * 60,
+                EntryStatus::Active,
+                EntryVariablesList::empty(),
+            )])
+            .day_notes(vec![DayNote::new(tuesday_date, "Day off".to_string())])
+            .build();
+
+        let report = generate_report(
+            &entries,
+            &[],
+            DateTimeFormat::Iso,
+            DurationFormat::HoursMinutes,
+            TimeBlockUnit::SixtyMinutes,
+        );
+        let days: Vec<(&str, i64, Option<&str>, usize)> = report
+            .days
+            .iter()
+            .map(|day| {
+                (
+                    day.id.as_str(),
+                    day.active_seconds,
+                    day.note.as_deref(),
+                    day.blocks.len(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            days,
+            vec![
+                ("2024-09-09", 60 * 60, None, 1),
+                ("2024-09-10", 0, Some("Day off"), 0),
+            ]
+        );
+    }
+}
@@ -0,0 +1,425 @@
+use crate::aggregate::get_duration_map_keys_listed;
+use crate::aggregate::sum_entry_activity_duration;
+use crate::aggregate::sum_entry_duration;
+use crate::aggregate::sum_entry_variable_columns_duration;
+use crate::aggregate::DurationListOptions;
+use crate::datetime::get_time_scale_datetime_range;
+use crate::datetime::get_weekdays_datetime_local;
+use crate::datetime::DateTimeLocalPair;
+use crate::errors::RenderError;
+use crate::preset::get_preset_variables;
+use crate::timezone::parse_timezone;
+use crate::timezone::shift_entries_to_timezone;
+use crate::variable::combine_variable_names;
+use crate::variable::Variable;
+use log::warn;
+use serde_derive::Serialize;
+use std::io::Write;
+use timetracker_core::entries::EntryStatus;
+use timetracker_core::format::format_duration;
+use timetracker_core::format::format_naive_time_no_seconds;
+use timetracker_core::format::DateTimeFormat;
+use timetracker_core::format::FirstDayOfWeek;
+use timetracker_core::format::PrintType;
+use timetracker_core::format::TimeScale;
+use timetracker_core::settings::PrintPresetSettings;
+use timetracker_core::storage::Entries;
+
+/// The name of the row combining the unknown durations (of entries
+/// without the variables) and the durations omitted by the 'top_n'
+/// and 'min_duration_minutes' preset settings.
+pub const OTHER_ROW_NAME: &str = "other";
+
+/// The active duration of a value (or values) of a preset, in a
+/// period, such as the duration of a project in a week.
+#[derive(Debug, Clone, Serialize)]
+pub struct PresetRow {
+    pub preset: String,
+    pub print_type: String,
+    /// The first and last date of the period, as "YYYY-MM-DD".
+    pub start_date: String,
+    pub end_date: String,
+    /// The variable names (such as "PROJECT") and the value of each
+    /// variable, in the same order. The values of the "other" row
+    /// are all "other".
+    pub names: Vec<String>,
+    pub values: Vec<String>,
+    pub active_seconds: i64,
+    pub duration: String,
+    /// The share of the total active time of the period.
+    pub percentage: f64,
+}
+
+/// The active duration of a block of time (such as 15 minutes) of a
+/// day, of an "Activity" preset.
+#[derive(Debug, Clone, Serialize)]
+pub struct ActivityBlockRow {
+    pub preset: String,
+    /// The day, as "YYYY-MM-DD".
+    pub date: String,
+    /// The start of the block, as "HH:MM".
+    pub time: String,
+    pub active_seconds: i64,
+    pub duration: String,
+    /// The active share of the block, from 0.0 to 1.0.
+    pub ratio: f64,
+}
+
+/// The print types producing rows; the types aggregating the active
+/// duration of each value of variables.
+fn has_rows(print_type: PrintType) -> bool {
+    matches!(
+        print_type,
+        PrintType::Software
+            | PrintType::Variables
+            | PrintType::WindowTitle
+            | PrintType::WorkingDirectory
+            | PrintType::DocumentPath
+    )
+}
+
+fn format_row_date(datetime: chrono::DateTime<chrono::Local>) -> String {
+    datetime.format("%Y-%m-%d").to_string()
+}
+
+fn generate_period_rows(
+    rows: &mut Vec<PresetRow>,
+    preset_name: &str,
+    preset: &PrintPresetSettings,
+    print_type: PrintType,
+    variables: &[Variable],
+    entries: &Entries,
+    period_datetime_pair: DateTimeLocalPair,
+) {
+    let (start_datetime, end_datetime) = period_datetime_pair;
+    let period_entries = entries.datetime_range_entries(start_datetime, end_datetime);
+    if period_entries.is_empty() {
+        return;
+    }
+
+    let duration_format = preset.format_duration.unwrap();
+    let total_duration = sum_entry_duration(period_entries, EntryStatus::Active);
+    let names: Vec<String> = variables
+        .iter()
+        .map(|variable| combine_variable_names(std::slice::from_ref(variable)))
+        .collect();
+    let mut push_row = |values: Vec<String>, duration: chrono::Duration| {
+        let percentage = match total_duration.num_seconds() {
+            0 => 0.0,
+            total_seconds => (duration.num_seconds() as f64 / total_seconds as f64) * 100.0,
+        };
+        rows.push(PresetRow {
+            preset: preset_name.to_string(),
+            print_type: print_type.to_string(),
+            start_date: format_row_date(start_datetime),
+            end_date: format_row_date(end_datetime),
+            names: names.clone(),
+            values,
+            active_seconds: duration.num_seconds(),
+            duration: format_duration(duration, duration_format),
+            percentage,
+        });
+    };
+
+    let duration_map =
+        sum_entry_variable_columns_duration(period_entries, variables, EntryStatus::Active);
+    let listed_keys =
+        get_duration_map_keys_listed(&duration_map, DurationListOptions::from_preset(preset));
+    for key in &listed_keys.keys {
+        let (values, duration) = &duration_map[key];
+        push_row(values.clone(), *duration);
+    }
+
+    let unknown_duration = duration_map.get("").map(|(_values, duration)| *duration);
+    if unknown_duration.is_some() || listed_keys.other_count > 0 {
+        let duration = listed_keys.other_duration + unknown_duration.unwrap_or_default();
+        push_row(vec![OTHER_ROW_NAME.to_string(); names.len()], duration);
+    }
+}
+
+/// The datetime pairs of the periods of the preset, with the timezone
+/// of the preset applied to the entries.
+fn get_preset_periods(
+    preset: &PrintPresetSettings,
+    entries: &Entries,
+    datetime_pair: DateTimeLocalPair,
+    first_day_of_week: FirstDayOfWeek,
+    every_day: bool,
+) -> Result<(Option<Entries>, Vec<DateTimeLocalPair>), RenderError> {
+    let time_scale = preset.time_scale.unwrap();
+    let preset_datetime_pair =
+        get_time_scale_datetime_range(time_scale, datetime_pair, first_day_of_week)?;
+    let timezone_entries = match &preset.timezone {
+        Some(name) => Some(shift_entries_to_timezone(entries, parse_timezone(name)?)),
+        None => None,
+    };
+
+    let period_datetime_pairs = match (time_scale, every_day) {
+        (TimeScale::Weekday, _) | (_, true) => {
+            get_weekdays_datetime_local(preset_datetime_pair.0, preset_datetime_pair.1)
+                .into_iter()
+                .map(|(_weekday, day_datetime_pair)| day_datetime_pair)
+                .collect()
+        }
+        (TimeScale::Week | TimeScale::Fortnight | TimeScale::Month, false) => {
+            vec![preset_datetime_pair]
+        }
+    };
+    Ok((timezone_entries, period_datetime_pairs))
+}
+
+/// Generate the rows of each preset (created by 'create_presets' from
+/// the 'preset_names'), for the displayed range of datetimes, such as
+/// to write as CSV or JSON.
+///
+/// Only the print types aggregating the durations of variables (such
+/// as "Software" and "Variables") have rows; the other presets are
+/// skipped with a warning. The 'entries' must cover the range given
+/// by 'get_presets_datetime_range'.
+pub fn generate_presets_rows(
+    preset_names: &[String],
+    presets: &[PrintPresetSettings],
+    entries: &Entries,
+    datetime_pair: DateTimeLocalPair,
+    first_day_of_week: FirstDayOfWeek,
+) -> Result<Vec<PresetRow>, RenderError> {
+    let mut rows = Vec::new();
+    for (preset_name, preset) in preset_names.iter().zip(presets) {
+        let print_type = match preset.print_type {
+            Some(value) => value,
+            None => continue,
+        };
+        if !has_rows(print_type) {
+            warn!(
+                "Preset {:?} print type {:?} has no rows, skipped.",
+                preset_name, print_type
+            );
+            continue;
+        }
+
+        let variables = get_preset_variables(preset, print_type);
+        let (timezone_entries, period_datetime_pairs) =
+            get_preset_periods(preset, entries, datetime_pair, first_day_of_week, false)?;
+        let preset_entries = timezone_entries.as_ref().unwrap_or(entries);
+        for period_datetime_pair in period_datetime_pairs {
+            generate_period_rows(
+                &mut rows,
+                preset_name,
+                preset,
+                print_type,
+                &variables,
+                preset_entries,
+                period_datetime_pair,
+            );
+        }
+    }
+    Ok(rows)
+}
+
+/// Generate the rows of the blocks of time of each day, of each
+/// "Activity" preset (created by 'create_presets' from the
+/// 'preset_names'), such as to draw as bar charts. The other presets
+/// are ignored.
+///
+/// The blocks between the first and last active block of a day are
+/// all given, so the gaps of a day are rows with no active time.
+pub fn generate_presets_activity_rows(
+    preset_names: &[String],
+    presets: &[PrintPresetSettings],
+    entries: &Entries,
+    datetime_pair: DateTimeLocalPair,
+    first_day_of_week: FirstDayOfWeek,
+) -> Result<Vec<ActivityBlockRow>, RenderError> {
+    let mut rows = Vec::new();
+    for (preset_name, preset) in preset_names.iter().zip(presets) {
+        if !matches!(preset.print_type, Some(PrintType::Activity)) {
+            continue;
+        }
+
+        let time_block_unit = preset.time_block_unit.unwrap();
+        let duration_format = preset.format_duration.unwrap();
+        let block_seconds = time_block_unit.as_seconds() as f64;
+        let (timezone_entries, day_datetime_pairs) =
+            get_preset_periods(preset, entries, datetime_pair, first_day_of_week, true)?;
+        let preset_entries = timezone_entries.as_ref().unwrap_or(entries);
+
+        for day_datetime_pair in day_datetime_pairs {
+            let day_entries =
+                preset_entries.datetime_range_entries(day_datetime_pair.0, day_datetime_pair.1);
+            if day_entries.is_empty() {
+                continue;
+            }
+
+            let add_fringe_datetimes = false;
+            let fill_datetimes_gaps = true;
+            let duration_map = sum_entry_activity_duration(
+                day_entries,
+                day_datetime_pair,
+                add_fringe_datetimes,
+                fill_datetimes_gaps,
+                time_block_unit,
+                EntryStatus::Active,
+            );
+            let mut times: Vec<&chrono::NaiveTime> = duration_map.keys().collect();
+            times.sort();
+            for time in times {
+                let duration = duration_map[time];
+                rows.push(ActivityBlockRow {
+                    preset: preset_name.to_string(),
+                    date: format_row_date(day_datetime_pair.0),
+                    time: format_naive_time_no_seconds(*time, DateTimeFormat::Iso),
+                    active_seconds: duration.num_seconds(),
+                    duration: format_duration(duration, duration_format),
+                    ratio: (duration.num_seconds() as f64 / block_seconds).min(1.0),
+                });
+            }
+        }
+    }
+    Ok(rows)
+}
+
+/// The most variables of any row, so every CSV record has the same
+/// number of fields.
+fn max_variable_count(rows: &[PresetRow]) -> usize {
+    rows.iter().map(|row| row.names.len()).max().unwrap_or(0)
+}
+
+/// Format the percentage of a row, such as "41.7".
+pub fn format_row_percentage(percentage: f64) -> String {
+    format!("{:.1}", percentage)
+}
+
+// The CSV File Format is described here:
+// https://www.rfc-editor.org/rfc/rfc4180#section-2
+//
+// Each row has a name and value field for each variable (such as
+// "var1_name" and "var1_value"), empty when the preset of the row
+// has fewer variables.
+pub fn write_rows_csv(writer: impl Write, rows: &[PresetRow]) -> Result<(), RenderError> {
+    let variable_count = max_variable_count(rows);
+
+    let mut headers = vec![
+        "preset".to_string(),
+        "print_type".to_string(),
+        "start_date".to_string(),
+        "end_date".to_string(),
+    ];
+    for number in 1..=variable_count {
+        headers.push(format!("var{}_name", number));
+        headers.push(format!("var{}_value", number));
+    }
+    headers.push("active_seconds".to_string());
+    headers.push("duration".to_string());
+    headers.push("percentage".to_string());
+
+    let mut csv_writer = csv::WriterBuilder::new()
+        .terminator(csv::Terminator::CRLF)
+        .from_writer(writer);
+    csv_writer.write_record(&headers)?;
+    for row in rows {
+        let mut record = vec![
+            row.preset.clone(),
+            row.print_type.clone(),
+            row.start_date.clone(),
+            row.end_date.clone(),
+        ];
+        for index in 0..variable_count {
+            record.push(row.names.get(index).cloned().unwrap_or_default());
+            record.push(row.values.get(index).cloned().unwrap_or_default());
+        }
+        record.push(row.active_seconds.to_string());
+        record.push(row.duration.clone());
+        record.push(format_row_percentage(row.percentage));
+        csv_writer.write_record(&record)?;
+    }
+    csv_writer.flush().map_err(csv::Error::from)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::rows::*;
+    use chrono::TimeZone;
+    use timetracker_core::entries::Entry;
+    use timetracker_core::entries::EntryVariablesList;
+    use timetracker_core::format::DurationFormat;
+    use timetracker_core::format::TimeBlockUnit;
+
+    #[test]
+    fn test_generate_presets_activity_rows() {
+        // Monday.
+        let start_datetime = chrono::Local.with_ymd_and_hms(2024, 9, 9, 0, 0, 0).unwrap();
+        let end_datetime = chrono::Local
+            .with_ymd_and_hms(2024, 9, 15, 23, 59, 59)
+            .unwrap();
+        let nine_o_clock = start_datetime.timestamp() as u64 + (9 * 60 * 60);
+        let vars = EntryVariablesList::empty();
+        let entries = Entries::builder()
+            .start_datetime(start_datetime)
+            .end_datetime(end_datetime)
+            .entries(vec![
+                Entry::new(
+                    nine_o_clock + (10 * 60),
+                    30 * 60,
+                    EntryStatus::Active,
+                    vars.clone(),
+                ),
+                Entry::new(
+                    nine_o_clock + (2 * 60 * 60),
+                    60 * 60,
+                    EntryStatus::Active,
+                    vars,
+                ),
+            ])
+            .build();
+        let new_preset = |print_type| {
+            PrintPresetSettings::new(
+                Some(print_type),
+                Some(TimeScale::Week),
+                Some(DateTimeFormat::Iso),
+                Some(DurationFormat::HoursMinutes),
+                Some(TimeBlockUnit::SixtyMinutes),
+                Some(50),
+                Some(false),
+                Some(vec![]),
+            )
+        };
+        let preset_names = vec!["software".to_string(), "activity".to_string()];
+        let presets = vec![
+            new_preset(PrintType::Software),
+            new_preset(PrintType::Activity),
+        ];
+
+        let rows = generate_presets_activity_rows(
+            &preset_names,
+            &presets,
+            &entries,
+            (start_datetime, end_datetime),
+            FirstDayOfWeek::Monday,
+        )
+        .unwrap();
+        let rows: Vec<(&str, &str, &str, i64, &str)> = rows
+            .iter()
+            .map(|row| {
+                (
+                    row.preset.as_str(),
+                    row.date.as_str(),
+                    row.time.as_str(),
+                    row.active_seconds,
+                    row.duration.as_str(),
+                )
+            })
+            .collect();
+        // The software preset has no activity rows, and the hour
+        // without entries between the two entries is filled.
+        assert_eq!(
+            rows,
+            vec![
+                ("activity", "2024-09-09", "09:00", 30 * 60, "00h 30m"),
+                ("activity", "2024-09-09", "10:00", 0, "00h 00m"),
+                ("activity", "2024-09-09", "11:00", 60 * 60, "01h 00m"),
+            ]
+        );
+    }
+}
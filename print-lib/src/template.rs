@@ -0,0 +1,211 @@
+use crate::errors::RenderError;
+use crate::report::Report;
+use std::path::Path;
+
+/// Template file extensions that are HTML-escaped automatically,
+/// the same as Tera's own defaults.
+const AUTOESCAPE_EXTENSIONS: [&str; 3] = ["html", "htm", "xml"];
+
+/// Render a report with a user-supplied Tera template.
+///
+/// The fields of the 'Report' structure are available as variables
+/// in the template. See https://keats.github.io/tera/docs/ for the
+/// template syntax. With 'autoescape', every value written by the
+/// template is HTML-escaped, unless the 'safe' filter is used.
+pub fn render_report_template(
+    template_text: &str,
+    report: &Report,
+    autoescape: bool,
+) -> Result<String, RenderError> {
+    let context = tera::Context::from_serialize(report).map_err(|err| {
+        RenderError::Template(format!("Could not create template context: {}", err))
+    })?;
+    tera::Tera::one_off(template_text, &context, autoescape).map_err(|err| {
+        // Tera errors hide the interesting details in the source
+        // errors.
+        let mut message = err.to_string();
+        let mut source = std::error::Error::source(&err);
+        while let Some(value) = source {
+            message = format!("{}; {}", message, value);
+            source = value.source();
+        }
+        RenderError::Template(format!("Could not render template: {}", message))
+    })
+}
+
+/// Should the template file be HTML-escaped automatically?
+///
+/// Following Tera, templates ending in ".html", ".htm" or ".xml" are
+/// escaped. A trailing ".tera" extension is ignored, so
+/// "weekly_chart.html.tera" is escaped but "timesheet.csv.tera" is
+/// not.
+pub fn template_file_autoescape(template_file_path: &Path) -> bool {
+    let mut path = template_file_path.to_path_buf();
+    if path.extension().is_some_and(|value| value == "tera") {
+        path.set_extension("");
+    }
+    match path.extension().and_then(|value| value.to_str()) {
+        Some(extension) => AUTOESCAPE_EXTENSIONS
+            .iter()
+            .any(|value| value.eq_ignore_ascii_case(extension)),
+        None => false,
+    }
+}
+
+/// Read a template file and render the report with it.
+///
+/// Autoescaping is chosen from the file extension, see
+/// 'template_file_autoescape'.
+pub fn render_report_template_file(
+    template_file_path: &Path,
+    report: &Report,
+) -> Result<String, RenderError> {
+    let template_text = std::fs::read_to_string(template_file_path).map_err(|err| {
+        RenderError::Template(format!(
+            "Could not read template file {:?}: {}",
+            template_file_path, err
+        ))
+    })?;
+    let autoescape = template_file_autoescape(template_file_path);
+    render_report_template(&template_text, report, autoescape)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::report::generate_report;
+    use crate::template::*;
+    use chrono::TimeZone;
+    use timetracker_core::entries::DayNote;
+    use timetracker_core::entries::Entry;
+    use timetracker_core::entries::EntryStatus;
+    use timetracker_core::entries::EntryVariablesList;
+    use timetracker_core::format::DateTimeFormat;
+    use timetracker_core::format::DurationFormat;
+    use timetracker_core::format::TimeBlockUnit;
+    use timetracker_core::storage::Entries;
+
+    const TIMESHEET_TEMPLATE: &str = include_str!("../../share/templates/timesheet.csv.tera");
+    const WEEKLY_SUMMARY_TEMPLATE: &str = include_str!("../../share/templates/weekly_summary.tera");
+
+    fn new_report() -> Report {
+        new_report_with_project("rocket")
+    }
+
+    fn new_report_with_project(project: &str) -> Report {
+        // Monday.
+        let start_datetime = chrono::Local.with_ymd_and_hms(2024, 9, 9, 0, 0, 0).unwrap();
+        let end_datetime = chrono::Local
+            .with_ymd_and_hms(2024, 9, 15, 23, 59, 59)
+            .unwrap();
+        let nine_o_clock = start_datetime.timestamp() as u64 + (9 * 60 * 60);
+        let new_entry = |utc_time_seconds: u64, minutes: u64, executable: &str, project: &str| {
+            let mut vars = EntryVariablesList::empty();
+            vars.executable = Some(executable.to_string());
+            vars.var1_name = Some("PROJECT".to_string());
+            vars.var1_value = Some(project.to_string());
+            Entry::new(utc_time_seconds, minutes * 60, EntryStatus::Active, vars)
+        };
+        let entries = Entries::builder()
+            .start_datetime(start_datetime)
+            .end_datetime(end_datetime)
+            .entries(vec![
+                new_entry(nine_o_clock, 90, "blender", project),
+                new_entry(nine_o_clock + (2 * 60 * 60), 50, "firefox", project),
+            ])
+            .day_notes(vec![
+                DayNote::new(
+                    start_datetime.date_naive(),
+                    "Review \"shot_010\", then notes".to_string(),
+                ),
+                DayNote::new(
+                    chrono::NaiveDate::from_ymd_opt(2024, 9, 10).unwrap(),
+                    "Holiday".to_string(),
+                ),
+            ])
+            .build();
+        generate_report(
+            &entries,
+            &["PROJECT".to_string()],
+            DateTimeFormat::Iso,
+            DurationFormat::HoursMinutes,
+            TimeBlockUnit::SixtyMinutes,
+        )
+    }
+
+    #[test]
+    fn test_render_timesheet_template() {
+        let text = render_report_template(TIMESHEET_TEMPLATE, &new_report(), false).unwrap();
+        // Quotes in the notes are escaped by doubling them.
+        assert_eq!(
+            text,
+            concat!(
+                "date,weekday,hours,note\n",
+                "2024-09-09,Mon,2.33,\"Review \"\"shot_010\"\", then notes\"\n",
+                "2024-09-10,Tue,0,\"Holiday\"\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_render_weekly_summary_template() {
+        let text = render_report_template(WEEKLY_SUMMARY_TEMPLATE, &new_report(), false).unwrap();
+        assert_eq!(
+            text,
+            concat!(
+                "Week: 2024-09-09 to 2024-09-15\n",
+                "Total: 02h 20m\n",
+                "\n",
+                "Days:\n",
+                "  Mon 2024-09-09: 02h 20m - Review \"shot_010\", then notes\n",
+                "  Tue 2024-09-10: 00h 00m - Holiday\n",
+                "\n",
+                "Executables:\n",
+                "  blender: 01h 30m\n",
+                "  firefox: 00h 50m\n",
+                "\n",
+                "PROJECT:\n",
+                "  rocket: 02h 20m\n",
+                "\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_render_weekly_chart_template_escapes_names() {
+        let report = new_report_with_project("R&D <\"rocket\">");
+        let template_file_path =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("../share/templates/weekly_chart.html.tera");
+        let text = render_report_template_file(&template_file_path, &report).unwrap();
+        assert!(text.contains("title=\"R&amp;D &lt;&quot;rocket&quot;&gt;: 02h 20m\""));
+        assert!(text.contains("</span>R&amp;D &lt;&quot;rocket&quot;&gt; (02h 20m)</span>"));
+        assert!(!text.contains("<\"rocket\">"));
+    }
+
+    #[test]
+    fn test_render_weekly_report_template_links_days_and_blocks() {
+        let template_file_path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../share/templates/weekly_report.html.tera");
+        let text = render_report_template_file(&template_file_path, &new_report()).unwrap();
+        assert!(text.contains("<a href=\"#day-2024-09-09\">Mon 2024-09-09</a>"));
+        assert!(text.contains("<section id=\"day-2024-09-09\">"));
+        assert!(text.contains("<a href=\"#block-2024-09-09-0900\">09:00 to 10:00</a>"));
+        assert!(text.contains("<section id=\"block-2024-09-09-0900\">"));
+        assert!(text.contains("<a href=\"#day-2024-09-09\">Back to Mon 2024-09-09</a>"));
+        assert!(text.contains("<td>blender</td>"));
+        assert!(text.contains("Review &quot;shot_010&quot;, then notes"));
+    }
+
+    #[test]
+    fn test_template_file_autoescape() {
+        assert!(template_file_autoescape(Path::new(
+            "weekly_chart.html.tera"
+        )));
+        assert!(template_file_autoescape(Path::new("/tmp/report.HTML")));
+        assert!(template_file_autoescape(Path::new("report.htm")));
+        assert!(template_file_autoescape(Path::new("report.xml.tera")));
+        assert!(!template_file_autoescape(Path::new("timesheet.csv.tera")));
+        assert!(!template_file_autoescape(Path::new("weekly_summary.tera")));
+        assert!(!template_file_autoescape(Path::new("report")));
+    }
+}
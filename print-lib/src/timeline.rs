@@ -0,0 +1,111 @@
+use crate::aggregate::sum_entry_duration;
+use crate::datetime::DateTimeLocalPair;
+use crate::print::HEADING_TOTAL_TEXT_END;
+use crate::print::HEADING_TOTAL_TEXT_START;
+use crate::report::generate_report_executables;
+use crate::report::generate_report_variables;
+use crate::report::ReportItem;
+use crate::report::ReportVariable;
+use timetracker_core::entries::EntryStatus;
+use timetracker_core::format::format_duration;
+use timetracker_core::format::format_time_no_seconds;
+use timetracker_core::format::DateTimeFormat;
+use timetracker_core::format::DurationFormat;
+use timetracker_core::format::TimeBlockUnit;
+use timetracker_core::storage::Entries;
+
+/// A block of time in a day, with the executables and variable
+/// values active inside the block.
+#[derive(Debug, Clone)]
+pub struct TimelineBlock {
+    pub start_datetime: chrono::DateTime<chrono::Local>,
+    pub end_datetime: chrono::DateTime<chrono::Local>,
+    pub duration: chrono::Duration,
+    pub executables: Vec<ReportItem>,
+    pub variables: Vec<ReportVariable>,
+}
+
+impl TimelineBlock {
+    /// The ratio of the block's time spent active, between 0.0 and
+    /// 1.0.
+    pub fn active_ratio(&self) -> f32 {
+        let block_seconds = (self.end_datetime - self.start_datetime).num_seconds();
+        if block_seconds <= 0 {
+            return 0.0;
+        }
+        let ratio = self.duration.num_seconds() as f32 / block_seconds as f32;
+        ratio.clamp(0.0, 1.0)
+    }
+}
+
+/// Split the day into blocks of 'time_block_unit' and gather the
+/// activity of each block.
+pub fn generate_timeline_blocks(
+    entries: &Entries,
+    day_datetime_pair: DateTimeLocalPair,
+    time_block_unit: TimeBlockUnit,
+    variable_names: &[String],
+    duration_format: DurationFormat,
+) -> Vec<TimelineBlock> {
+    let (day_start_datetime, day_end_datetime) = day_datetime_pair;
+    let block_duration = chrono::Duration::seconds(time_block_unit.as_seconds() as i64);
+
+    let mut blocks = Vec::new();
+    let mut block_start_datetime = day_start_datetime;
+    while block_start_datetime < day_end_datetime {
+        let block_end_datetime =
+            std::cmp::min(block_start_datetime + block_duration, day_end_datetime);
+        let block_entries =
+            entries.datetime_range_entries(block_start_datetime, block_end_datetime);
+
+        blocks.push(TimelineBlock {
+            start_datetime: block_start_datetime,
+            end_datetime: block_end_datetime,
+            duration: sum_entry_duration(block_entries, EntryStatus::Active),
+            executables: generate_report_executables(block_entries, duration_format),
+            variables: generate_report_variables(block_entries, variable_names, duration_format),
+        });
+        block_start_datetime = block_end_datetime;
+    }
+
+    blocks
+}
+
+/// The lines describing a block's executables and variable values,
+/// sorted from the longest duration.
+pub fn generate_timeline_block_lines(
+    block: &TimelineBlock,
+    datetime_format: DateTimeFormat,
+    duration_format: DurationFormat,
+) -> Vec<String> {
+    let mut lines = Vec::new();
+    lines.push(format!(
+        "{} to {} {}{}{}",
+        format_time_no_seconds(block.start_datetime, datetime_format),
+        format_time_no_seconds(block.end_datetime, datetime_format),
+        HEADING_TOTAL_TEXT_START,
+        format_duration(block.duration, duration_format),
+        HEADING_TOTAL_TEXT_END
+    ));
+
+    let push_items = |lines: &mut Vec<String>, heading: &str, items: &[ReportItem]| {
+        if items.is_empty() {
+            return;
+        }
+        let mut items = items.to_vec();
+        items.sort_by_key(|x| std::cmp::Reverse(x.active_seconds));
+
+        lines.push("".to_string());
+        lines.push(format!("{}:", heading));
+        for item in items {
+            lines.push(format!(" - {} {}", item.duration, item.name));
+        }
+    };
+
+    push_items(&mut lines, "Executables", &block.executables);
+    for variable in &block.variables {
+        push_items(&mut lines, &variable.name, &variable.items);
+    }
+
+    lines
+}
@@ -0,0 +1,98 @@
+use crate::errors::RenderError;
+use chrono::Offset;
+use chrono::TimeZone;
+use timetracker_core::entries::shift_utc_time_to_recorded_offset;
+use timetracker_core::storage::Entries;
+
+/// The most a timezone may differ from the local timezone, for
+/// example UTC-12:00 compared to UTC+14:00.
+pub const MAX_TIMEZONE_DIFFERENCE_SECONDS: i64 = 26 * 60 * 60;
+
+/// Parse an IANA timezone name, such as "America/Los_Angeles".
+pub fn parse_timezone(name: &str) -> Result<chrono_tz::Tz, RenderError> {
+    name.parse::<chrono_tz::Tz>().map_err(|_| {
+        RenderError::InvalidPreset(format!(
+            "Timezone {:?} is unknown; expected an IANA timezone name such as \"America/Los_Angeles\".",
+            name
+        ))
+    })
+}
+
+/// Shift a UTC time so the local timezone displays the (wall-clock)
+/// time of the timezone at that time.
+fn shift_utc_time_to_timezone(utc_time_seconds: u64, timezone: chrono_tz::Tz) -> u64 {
+    let timezone_offset_seconds = timezone
+        .timestamp_opt(utc_time_seconds as i64, 0)
+        .single()
+        .map_or(0, |x| x.offset().fix().local_minus_utc());
+    let local_offset_seconds = chrono::Local
+        .timestamp_opt(utc_time_seconds as i64, 0)
+        .single()
+        .map_or(0, |x| x.offset().local_minus_utc());
+    shift_utc_time_to_recorded_offset(
+        utc_time_seconds,
+        timezone_offset_seconds,
+        local_offset_seconds,
+    )
+}
+
+/// Shift the entries (and periods) so they are divided into the days
+/// of the timezone, rather than the days of the local timezone.
+///
+/// The printed times are the (wall-clock) times of the timezone. The
+/// 'entries' should cover 'MAX_TIMEZONE_DIFFERENCE_SECONDS' before and
+/// after the range printed, because the entries are shifted by up to
+/// the difference between the timezones.
+pub fn shift_entries_to_timezone(entries: &Entries, timezone: chrono_tz::Tz) -> Entries {
+    let mut shifted_entries = entries.all_entries().to_vec();
+    for entry in &mut shifted_entries {
+        entry.utc_time_seconds = shift_utc_time_to_timezone(entry.utc_time_seconds, timezone);
+    }
+
+    let mut host_busy_periods = entries.host_busy_periods().to_vec();
+    for period in &mut host_busy_periods {
+        period.utc_time_seconds = shift_utc_time_to_timezone(period.utc_time_seconds, timezone);
+    }
+
+    let mut window_color_periods = entries.window_color_periods().to_vec();
+    for period in &mut window_color_periods {
+        period.utc_time_seconds = shift_utc_time_to_timezone(period.utc_time_seconds, timezone);
+    }
+
+    Entries::builder()
+        .start_datetime(entries.start_datetime())
+        .end_datetime(entries.end_datetime())
+        .entries(shifted_entries)
+        .day_notes(entries.day_notes().to_vec())
+        .host_busy_periods(host_busy_periods)
+        .window_color_periods(window_color_periods)
+        .plans(entries.plans().to_vec())
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timezone() {
+        assert!(parse_timezone("America/Los_Angeles").is_ok());
+        assert!(parse_timezone("UTC").is_ok());
+        assert!(parse_timezone("Not/A_Timezone").is_err());
+    }
+
+    #[test]
+    fn test_shift_utc_time_to_timezone() {
+        // 2024-01-15 12:00:00 UTC is 04:00:00 in Los Angeles (UTC-8).
+        let utc_time_seconds = 1705320000;
+        let shifted = shift_utc_time_to_timezone(utc_time_seconds, chrono_tz::America::Los_Angeles);
+        let local_datetime = chrono::Local.timestamp_opt(shifted as i64, 0).unwrap();
+        assert_eq!(
+            local_datetime.naive_local(),
+            chrono::NaiveDate::from_ymd_opt(2024, 1, 15)
+                .unwrap()
+                .and_hms_opt(4, 0, 0)
+                .unwrap()
+        );
+    }
+}
@@ -4,14 +4,45 @@ use timetracker_core::entries::Entry;
 #[derive(Clone, Debug)]
 pub enum Variable {
     Executable,
+    WindowTitle,
+    WorkingDirectory,
+    DocumentPath,
+    Hostname,
+    Username,
+    Display,
     VariableName(String),
 }
 
+impl Variable {
+    /// The variable of a name, as used in presets and filters. The
+    /// recorded fields of an entry (such as "executable" or
+    /// "hostname") are lowercase, any other name is an environment
+    /// variable name.
+    pub fn from_name(name: &str) -> Variable {
+        match name {
+            "executable" => Variable::Executable,
+            "window_title" => Variable::WindowTitle,
+            "working_directory" => Variable::WorkingDirectory,
+            "document_path" => Variable::DocumentPath,
+            "hostname" => Variable::Hostname,
+            "username" => Variable::Username,
+            "display" => Variable::Display,
+            _ => Variable::VariableName(name.to_string()),
+        }
+    }
+}
+
 pub fn combine_variable_names(variables: &[Variable]) -> String {
     let mut key = String::new();
     for (num, variable) in variables.iter().enumerate() {
         let var_name = match variable {
             Variable::Executable => "Executable".to_string(),
+            Variable::WindowTitle => "Window Title".to_string(),
+            Variable::WorkingDirectory => "Working Directory".to_string(),
+            Variable::DocumentPath => "Document Path".to_string(),
+            Variable::Hostname => "Hostname".to_string(),
+            Variable::Username => "Username".to_string(),
+            Variable::Display => "Display".to_string(),
             Variable::VariableName(var_name) => var_name.to_string(),
         };
 
@@ -46,6 +77,12 @@ pub fn combine_variable_values(entry: &Entry, variables: &[Variable]) -> String
     for (num, variable) in variables.iter().enumerate() {
         let var_value = match variable {
             Variable::Executable => option_string_to_string(&entry.vars.executable),
+            Variable::WindowTitle => option_string_to_string(&entry.vars.window_title),
+            Variable::WorkingDirectory => option_string_to_string(&entry.vars.working_directory),
+            Variable::DocumentPath => option_string_to_string(&entry.vars.document_path),
+            Variable::Hostname => option_string_to_string(&entry.vars.hostname),
+            Variable::Username => option_string_to_string(&entry.vars.username),
+            Variable::Display => option_string_to_string(&entry.vars.display),
             Variable::VariableName(var_name) => {
                 let var1_name = option_string_to_string(&entry.vars.var1_name);
                 let var2_name = option_string_to_string(&entry.vars.var2_name);
@@ -90,6 +127,12 @@ pub fn multi_variable_values(entry: &Entry, variables: &[Variable]) -> Vec<Strin
     for variable in variables.iter() {
         let var_value = match variable {
             Variable::Executable => option_string_to_string(&entry.vars.executable),
+            Variable::WindowTitle => option_string_to_string(&entry.vars.window_title),
+            Variable::WorkingDirectory => option_string_to_string(&entry.vars.working_directory),
+            Variable::DocumentPath => option_string_to_string(&entry.vars.document_path),
+            Variable::Hostname => option_string_to_string(&entry.vars.hostname),
+            Variable::Username => option_string_to_string(&entry.vars.username),
+            Variable::Display => option_string_to_string(&entry.vars.display),
             Variable::VariableName(var_name) => {
                 let var1_name = option_string_to_string(&entry.vars.var1_name);
                 let var2_name = option_string_to_string(&entry.vars.var2_name);
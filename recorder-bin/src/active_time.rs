@@ -0,0 +1,56 @@
+use crate::recorder_state::RecorderState;
+use chrono::TimeZone;
+use log::warn;
+use std::path::Path;
+use timetracker_core::entries::EntryStatus;
+use timetracker_core::filesystem::get_database_file_path_for_time;
+use timetracker_core::settings::DEFAULT_RECORD_INTERVAL_SECONDS;
+use timetracker_core::storage::Storage;
+
+/// The UTC time of the start of today, in the local timezone.
+fn get_today_start_utc_time_seconds() -> u64 {
+    let today = chrono::Local::now().date_naive();
+    chrono::Local
+        .from_local_datetime(&today.and_time(chrono::NaiveTime::MIN))
+        .earliest()
+        .map(|datetime| datetime.timestamp() as u64)
+        .unwrap_or_default()
+}
+
+/// The active time of today; the entries written to the database,
+/// and the entries still buffered in memory.
+pub fn read_today_active_seconds(
+    state: &RecorderState,
+    database_file_path: &Path,
+    database_per_year: bool,
+) -> u64 {
+    let start_utc_time_seconds = get_today_start_utc_time_seconds();
+    let now_seconds = chrono::Utc::now().timestamp() as u64;
+    let current_database_file_path =
+        get_database_file_path_for_time(database_file_path, database_per_year, now_seconds);
+
+    // The database may not exist yet (such as on the first day of a
+    // year), so only the buffered entries are counted.
+    let written_seconds =
+        Storage::open_as_read_only(&current_database_file_path, DEFAULT_RECORD_INTERVAL_SECONDS)
+            .and_then(|storage| {
+                storage.sum_entries_duration_seconds(
+                    start_utc_time_seconds,
+                    now_seconds,
+                    EntryStatus::Active,
+                )
+            })
+            .unwrap_or_else(|err| {
+                warn!(
+                    "Could not read today's active time from {:?}. {:?}",
+                    current_database_file_path, err
+                );
+                0
+            });
+    let buffered_seconds = state
+        .entry_buffer
+        .lock()
+        .unwrap()
+        .sum_duration_seconds(start_utc_time_seconds, EntryStatus::Active);
+    written_seconds + buffered_seconds
+}
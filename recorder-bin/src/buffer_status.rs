@@ -0,0 +1,51 @@
+use anyhow::Result;
+use serde_derive::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use timetracker_core::entries::EntryStatus;
+
+/// The state of the recorder's entry buffer, written by the running
+/// recorder after each write to storage, and printed by the
+/// 'timetracker-recorder status' command.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BufferStatus {
+    /// When the status was written.
+    pub utc_time_seconds: u64,
+    /// The number of entries in memory, waiting to be written.
+    pub buffered_entry_count: usize,
+    /// The number of entries in the journal file, waiting to be
+    /// written.
+    pub journal_entry_count: usize,
+    /// The number of entries dropped since the recorder started.
+    pub dropped_entry_count: u64,
+    /// The number of entries spilled to the journal file since the
+    /// recorder started.
+    pub spilled_entry_count: u64,
+    /// The number of writes to storage that failed in a row.
+    pub failed_write_count: u64,
+    /// When storage was last written to successfully.
+    pub last_write_utc_time_seconds: Option<u64>,
+    /// Is the user active or idle, when the status was written?
+    #[serde(default)]
+    pub entry_status: Option<EntryStatus>,
+}
+
+/// The status file is written next to the journal file.
+pub fn get_status_file_path(journal_file_path: &Path) -> PathBuf {
+    journal_file_path.with_extension("status")
+}
+
+pub fn write_buffer_status(status_file_path: &Path, status: &BufferStatus) -> Result<()> {
+    let text = serde_json::to_string_pretty(status)?;
+    fs::write(status_file_path, text)?;
+    Ok(())
+}
+
+pub fn read_buffer_status(status_file_path: &Path) -> Result<Option<BufferStatus>> {
+    if !status_file_path.is_file() {
+        return Ok(None);
+    }
+    let text = fs::read_to_string(status_file_path)?;
+    Ok(Some(serde_json::from_str(&text)?))
+}
@@ -0,0 +1,242 @@
+use crate::recorder_state::RecorderState;
+use anyhow::{bail, Context, Result};
+use log::{debug, info, warn};
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::UnixListener;
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+use std::time;
+
+const CONTROL_SOCKET_FILE_NAME: &str = "timetracker-recorder.socket";
+
+/// The reply to a command that succeeded.
+const REPLY_OK: &str = "ok";
+
+/// The reply to a command that failed is this prefix, followed by a
+/// description of the error.
+const REPLY_ERROR_PREFIX: &str = "error: ";
+
+/// How long to wait for a command (or reply) to be sent, so a client
+/// that connects without sending a command cannot block the control
+/// listener.
+const CONNECTION_TIMEOUT_SECONDS: u64 = 5;
+
+/// The longest command line that is read.
+const COMMAND_MAX_BYTE_COUNT: u64 = 1024;
+
+/// A command sent to the running recorder.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ControlCommand {
+    /// Stop recording activity, until the UTC time (if given) or
+    /// until resumed.
+    Pause { end_utc_time_seconds: Option<u64> },
+    /// Start recording activity again.
+    Resume,
+    /// Reload the configuration file.
+    Reload,
+}
+
+impl ControlCommand {
+    /// The command as a line of text, such as "pause 1726149600".
+    fn to_line(self) -> String {
+        match self {
+            ControlCommand::Pause {
+                end_utc_time_seconds: Some(value),
+            } => format!("pause {}", value),
+            ControlCommand::Pause {
+                end_utc_time_seconds: None,
+            } => "pause".to_string(),
+            ControlCommand::Resume => "resume".to_string(),
+            ControlCommand::Reload => "reload".to_string(),
+        }
+    }
+
+    fn from_line(line: &str) -> Option<ControlCommand> {
+        let mut words = line.split_whitespace();
+        let command = match (words.next()?, words.next()) {
+            ("pause", Some(value)) => ControlCommand::Pause {
+                end_utc_time_seconds: Some(value.parse().ok()?),
+            },
+            ("pause", None) => ControlCommand::Pause {
+                end_utc_time_seconds: None,
+            },
+            ("resume", None) => ControlCommand::Resume,
+            ("reload", None) => ControlCommand::Reload,
+            _ => return None,
+        };
+        match words.next() {
+            Some(_) => None,
+            None => Some(command),
+        }
+    }
+}
+
+/// The unix domain socket the running recorder is controlled with.
+///
+/// The socket is created in "$XDG_RUNTIME_DIR" (which is private to
+/// the user, and never on a network file system), or next to the
+/// journal file when "XDG_RUNTIME_DIR" is not set.
+pub fn get_control_socket_path(journal_file_path: &Path) -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(value) if !value.is_empty() => PathBuf::from(value).join(CONTROL_SOCKET_FILE_NAME),
+        _ => journal_file_path.with_extension("socket"),
+    }
+}
+
+fn apply_control_command(state: &RecorderState, command: ControlCommand) {
+    match command {
+        ControlCommand::Pause {
+            end_utc_time_seconds,
+        } => {
+            info!("Recording paused (until {:?}).", end_utc_time_seconds);
+            state.pause(end_utc_time_seconds);
+        }
+        ControlCommand::Resume => {
+            info!("Recording resumed.");
+            state.resume();
+        }
+        ControlCommand::Reload => {
+            info!("Reload of the settings requested.");
+            state.request_reload();
+        }
+    }
+}
+
+fn handle_connection(state: &RecorderState, stream: UnixStream) -> Result<()> {
+    let timeout = Some(time::Duration::from_secs(CONNECTION_TIMEOUT_SECONDS));
+    stream.set_read_timeout(timeout)?;
+    stream.set_write_timeout(timeout)?;
+
+    let mut line = String::new();
+    BufReader::new(&stream)
+        .take(COMMAND_MAX_BYTE_COUNT)
+        .read_line(&mut line)?;
+    debug!("Control command: {:?}", line);
+
+    let reply = match ControlCommand::from_line(&line) {
+        Some(command) => {
+            apply_control_command(state, command);
+            REPLY_OK.to_string()
+        }
+        None => format!("{}unknown command {:?}", REPLY_ERROR_PREFIX, line.trim()),
+    };
+    let mut stream = stream;
+    writeln!(stream, "{}", reply)?;
+    Ok(())
+}
+
+/// Listen for control commands (such as pause and reload) on the
+/// socket, in a new thread, applying the commands to the recorder
+/// 'state'.
+///
+/// Only the user can connect to the socket.
+pub fn start_control_listener(socket_path: &Path, state: Arc<RecorderState>) -> Result<()> {
+    if socket_path.exists() {
+        if UnixStream::connect(socket_path).is_ok() {
+            bail!(
+                "Control socket {:?} is used by another recorder.",
+                socket_path
+            );
+        }
+        // Left behind by a recorder that did not exit cleanly.
+        std::fs::remove_file(socket_path)?;
+    }
+
+    let listener = UnixListener::bind(socket_path)
+        .with_context(|| format!("Could not create control socket {:?}.", socket_path))?;
+    std::fs::set_permissions(socket_path, std::fs::Permissions::from_mode(0o600))?;
+    println!("Control socket: {:?}", socket_path);
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let result = stream
+                .map_err(anyhow::Error::from)
+                .and_then(|stream| handle_connection(&state, stream));
+            if let Err(err) = result {
+                warn!("Could not read control command. {:?}", err);
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Remove the control socket, when the recorder exits.
+pub fn remove_control_socket(socket_path: &Path) {
+    if let Err(err) = std::fs::remove_file(socket_path) {
+        warn!(
+            "Could not remove control socket {:?}. {:?}",
+            socket_path, err
+        );
+    }
+}
+
+/// Send a command to the running recorder.
+pub fn send_control_command(socket_path: &Path, command: ControlCommand) -> Result<()> {
+    let mut stream = UnixStream::connect(socket_path).with_context(|| {
+        format!(
+            "Could not connect to the recorder control socket {:?}; is the recorder running?",
+            socket_path
+        )
+    })?;
+    let timeout = Some(time::Duration::from_secs(CONNECTION_TIMEOUT_SECONDS));
+    stream.set_read_timeout(timeout)?;
+    stream.set_write_timeout(timeout)?;
+    writeln!(stream, "{}", command.to_line())?;
+
+    let mut reply = String::new();
+    BufReader::new(&stream).read_line(&mut reply)?;
+    let reply = reply.trim();
+    match reply.strip_prefix(REPLY_ERROR_PREFIX) {
+        Some(message) => bail!("The recorder could not run the command: {}", message),
+        None if reply == REPLY_OK => Ok(()),
+        None => bail!("Unexpected reply from the recorder: {:?}", reply),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_control_command_line_round_trip() {
+        let commands = [
+            ControlCommand::Pause {
+                end_utc_time_seconds: Some(1726149600),
+            },
+            ControlCommand::Pause {
+                end_utc_time_seconds: None,
+            },
+            ControlCommand::Resume,
+            ControlCommand::Reload,
+        ];
+        for command in commands {
+            assert_eq!(ControlCommand::from_line(&command.to_line()), Some(command));
+        }
+        // The line read from the socket ends with a new line.
+        assert_eq!(
+            ControlCommand::from_line("resume\n"),
+            Some(ControlCommand::Resume)
+        );
+    }
+
+    #[test]
+    fn test_control_command_from_invalid_line() {
+        for line in [
+            "",
+            "stop",
+            "pause soon",
+            "pause -1",
+            "pause 1726149600 now",
+            "resume now",
+            "reload settings",
+        ] {
+            assert_eq!(ControlCommand::from_line(line), None, "line: {:?}", line);
+        }
+    }
+}
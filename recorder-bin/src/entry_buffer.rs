@@ -0,0 +1,348 @@
+use crate::journal::append_entries_to_journal;
+use crate::journal::replace_journal_entries;
+use log::{error, warn};
+use std::path::PathBuf;
+use timetracker_core::entries::Entry;
+use timetracker_core::entries::EntryStatus;
+use timetracker_core::format::BufferOverflowPolicy;
+use timetracker_core::settings::DEFAULT_RECORDER_BUFFER_MAX_ENTRIES;
+
+/// The entries stored in memory, waiting to be written to storage.
+///
+/// The buffer is bounded; when the database cannot be written to
+/// (such as an unavailable NFS home directory) the oldest entries
+/// overflow, and are dropped or spilled to the journal file.
+///
+/// When a buffer file is given, each entry is appended to the buffer
+/// file before it is buffered, so no entries are lost if the process
+/// is killed or the machine loses power. The buffer file is only
+/// compacted (rewritten with the entries still buffered) after the
+/// entries are written to storage; until then it also holds the
+/// entries being written and the entries that overflowed the buffer.
+#[derive(Debug)]
+pub struct EntryBuffer {
+    entries: Vec<Entry>,
+    max_entries: usize,
+    overflow_policy: BufferOverflowPolicy,
+    journal_file_path: PathBuf,
+    buffer_file_path: Option<PathBuf>,
+    dropped_entry_count: u64,
+    spilled_entry_count: u64,
+}
+
+impl EntryBuffer {
+    pub fn new() -> EntryBuffer {
+        EntryBuffer {
+            entries: Vec::new(),
+            max_entries: DEFAULT_RECORDER_BUFFER_MAX_ENTRIES,
+            overflow_policy: BufferOverflowPolicy::DropOldest,
+            journal_file_path: PathBuf::new(),
+            buffer_file_path: None,
+            dropped_entry_count: 0,
+            spilled_entry_count: 0,
+        }
+    }
+
+    pub fn configure(
+        &mut self,
+        max_entries: usize,
+        overflow_policy: BufferOverflowPolicy,
+        journal_file_path: PathBuf,
+        buffer_file_path: Option<PathBuf>,
+    ) {
+        self.max_entries = max_entries.max(1);
+        self.overflow_policy = overflow_policy;
+        self.journal_file_path = journal_file_path;
+        self.buffer_file_path = buffer_file_path;
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn journal_file_path(&self) -> &PathBuf {
+        &self.journal_file_path
+    }
+
+    /// Are the buffered entries also kept in the buffer file?
+    pub fn has_buffer_file(&self) -> bool {
+        self.buffer_file_path.is_some()
+    }
+
+    /// The number of entries dropped because the buffer was full.
+    pub fn dropped_entry_count(&self) -> u64 {
+        self.dropped_entry_count
+    }
+
+    /// The number of entries spilled to the journal file because the
+    /// buffer was full.
+    pub fn spilled_entry_count(&self) -> u64 {
+        self.spilled_entry_count
+    }
+
+    /// The total duration of the buffered entries with the status,
+    /// from the UTC time onwards.
+    pub fn sum_duration_seconds(&self, start_utc_time_seconds: u64, status: EntryStatus) -> u64 {
+        self.entries
+            .iter()
+            .filter(|entry| entry.status == status)
+            .map(|entry| {
+                let end_utc_time_seconds = entry.utc_time_seconds + entry.duration_seconds;
+                end_utc_time_seconds
+                    .saturating_sub(entry.utc_time_seconds.max(start_utc_time_seconds))
+            })
+            .sum()
+    }
+
+    pub fn push(&mut self, entry: Entry) {
+        if let Some(buffer_file_path) = &self.buffer_file_path {
+            if let Err(err) =
+                append_entries_to_journal(buffer_file_path, std::slice::from_ref(&entry))
+            {
+                error!(
+                    "Could not write entry to buffer file {:?}. {:?}",
+                    buffer_file_path, err
+                );
+            }
+        }
+        self.entries.push(entry);
+        self.enforce_max_entries();
+    }
+
+    /// Take all the entries, to be written to storage. The entries
+    /// stay in the buffer file until 'finish_taken_entries'.
+    pub fn take_entries(&mut self) -> Vec<Entry> {
+        std::mem::take(&mut self.entries)
+    }
+
+    /// Remove the entries (taken with 'take_entries') that have been
+    /// written to storage from the buffer file, by compacting the
+    /// buffer file.
+    pub fn finish_taken_entries(&mut self) {
+        self.compact_buffer_file();
+    }
+
+    /// Put back entries (taken with 'take_entries') that could not be
+    /// written to storage, before any entries pushed since.
+    pub fn restore_entries(&mut self, mut entries: Vec<Entry>) {
+        // The buffer file already has the entries, in the same order.
+        entries.append(&mut self.entries);
+        self.entries = entries;
+        self.enforce_max_entries();
+    }
+
+    /// Remove all the entries following the overflow policy, such as
+    /// when the process exits without being able to write to storage.
+    pub fn overflow_all_entries(&mut self) {
+        let count = self.entries.len();
+        self.overflow(count);
+    }
+
+    fn enforce_max_entries(&mut self) {
+        if self.entries.len() > self.max_entries {
+            let count = self.entries.len() - self.max_entries;
+            self.overflow(count);
+        }
+    }
+
+    /// Remove the oldest 'count' entries, following the overflow
+    /// policy.
+    fn overflow(&mut self, count: usize) {
+        if count == 0 {
+            return;
+        }
+        let entries: Vec<Entry> = self.entries.drain(..count).collect();
+
+        let mut is_spilled = false;
+        if self.overflow_policy == BufferOverflowPolicy::SpillToJournal {
+            match append_entries_to_journal(&self.journal_file_path, &entries) {
+                Ok(()) => {
+                    self.spilled_entry_count += count as u64;
+                    is_spilled = true;
+                }
+                Err(err) => error!(
+                    "Could not spill entries to journal file {:?}. {:?}",
+                    self.journal_file_path, err
+                ),
+            }
+        }
+        if !is_spilled {
+            warn!("Entry buffer is full, dropping {} oldest entries.", count);
+            self.dropped_entry_count += count as u64;
+        }
+        // The overflowed entries stay in the (append-only) buffer
+        // file until it is compacted; spilled entries found in the
+        // journal file are skipped when the buffer file is recovered.
+    }
+
+    /// Replace the buffer file with the entries still buffered.
+    fn compact_buffer_file(&self) {
+        if let Some(buffer_file_path) = &self.buffer_file_path {
+            if let Err(err) = replace_journal_entries(buffer_file_path, &self.entries) {
+                error!(
+                    "Could not write buffer file {:?}. {:?}",
+                    buffer_file_path, err
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::journal::read_journal_entries;
+    use std::path::Path;
+    use timetracker_core::entries::EntryVariablesList;
+
+    fn temp_file_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "timetracker-entry-buffer-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn new_entry(utc_time_seconds: u64) -> Entry {
+        Entry::new(
+            utc_time_seconds,
+            10,
+            EntryStatus::Active,
+            EntryVariablesList::empty(),
+        )
+    }
+
+    fn read_times(file_path: &Path) -> Vec<u64> {
+        let (entries, _byte_count) = read_journal_entries(file_path).unwrap();
+        entries.iter().map(|x| x.utc_time_seconds).collect()
+    }
+
+    fn times(entries: &[Entry]) -> Vec<u64> {
+        entries.iter().map(|x| x.utc_time_seconds).collect()
+    }
+
+    #[test]
+    fn test_entry_buffer_buffer_file_round_trip() {
+        let journal_file_path = temp_file_path("round-trip.journal");
+        let buffer_file_path = temp_file_path("round-trip.buffer");
+        let mut buffer = EntryBuffer::new();
+        buffer.configure(
+            10,
+            BufferOverflowPolicy::DropOldest,
+            journal_file_path,
+            Some(buffer_file_path.clone()),
+        );
+
+        for utc_time_seconds in [10, 20, 30] {
+            buffer.push(new_entry(utc_time_seconds));
+        }
+        assert_eq!(read_times(&buffer_file_path), vec![10, 20, 30]);
+
+        // The taken entries stay in the buffer file until they are
+        // written to storage.
+        let entries = buffer.take_entries();
+        assert_eq!(times(&entries), vec![10, 20, 30]);
+        buffer.push(new_entry(40));
+        assert_eq!(read_times(&buffer_file_path), vec![10, 20, 30, 40]);
+
+        buffer.finish_taken_entries();
+        assert_eq!(read_times(&buffer_file_path), vec![40]);
+        buffer.take_entries();
+        buffer.finish_taken_entries();
+        assert!(!buffer_file_path.exists());
+    }
+
+    #[test]
+    fn test_entry_buffer_take_restore() {
+        let journal_file_path = temp_file_path("take-restore.journal");
+        let buffer_file_path = temp_file_path("take-restore.buffer");
+        let mut buffer = EntryBuffer::new();
+        buffer.configure(
+            10,
+            BufferOverflowPolicy::DropOldest,
+            journal_file_path,
+            Some(buffer_file_path.clone()),
+        );
+
+        buffer.push(new_entry(10));
+        buffer.push(new_entry(20));
+        let entries = buffer.take_entries();
+        assert_eq!(buffer.len(), 0);
+        buffer.push(new_entry(30));
+
+        // The write failed, so the entries are put back before the
+        // entries pushed since.
+        buffer.restore_entries(entries);
+        assert_eq!(times(&buffer.take_entries()), vec![10, 20, 30]);
+        assert_eq!(read_times(&buffer_file_path), vec![10, 20, 30]);
+
+        std::fs::remove_file(buffer_file_path).unwrap();
+    }
+
+    #[test]
+    fn test_entry_buffer_drop_oldest() {
+        let mut buffer = EntryBuffer::new();
+        buffer.configure(
+            2,
+            BufferOverflowPolicy::DropOldest,
+            temp_file_path("drop-oldest.journal"),
+            None,
+        );
+
+        for utc_time_seconds in [10, 20, 30] {
+            buffer.push(new_entry(utc_time_seconds));
+        }
+        assert_eq!(buffer.dropped_entry_count(), 1);
+        assert_eq!(buffer.spilled_entry_count(), 0);
+        assert_eq!(times(&buffer.take_entries()), vec![20, 30]);
+    }
+
+    #[test]
+    fn test_entry_buffer_spill_to_journal() {
+        let journal_file_path = temp_file_path("spill.journal");
+        let mut buffer = EntryBuffer::new();
+        buffer.configure(
+            2,
+            BufferOverflowPolicy::SpillToJournal,
+            journal_file_path.clone(),
+            None,
+        );
+
+        for utc_time_seconds in [10, 20, 30, 40] {
+            buffer.push(new_entry(utc_time_seconds));
+        }
+        assert_eq!(buffer.spilled_entry_count(), 2);
+        assert_eq!(buffer.dropped_entry_count(), 0);
+        assert_eq!(read_times(&journal_file_path), vec![10, 20]);
+
+        // The remaining entries are spilled when exiting without
+        // writing to storage.
+        buffer.overflow_all_entries();
+        assert_eq!(buffer.len(), 0);
+        assert_eq!(buffer.spilled_entry_count(), 4);
+        assert_eq!(read_times(&journal_file_path), vec![10, 20, 30, 40]);
+        std::fs::remove_file(journal_file_path).unwrap();
+    }
+
+    #[test]
+    fn test_entry_buffer_spill_to_journal_failed() {
+        // The journal file cannot be written, so the entries are
+        // dropped instead.
+        let journal_file_path = temp_file_path("missing-directory").join("spill.journal");
+        let mut buffer = EntryBuffer::new();
+        buffer.configure(
+            1,
+            BufferOverflowPolicy::SpillToJournal,
+            journal_file_path.clone(),
+            None,
+        );
+
+        buffer.push(new_entry(10));
+        buffer.push(new_entry(20));
+        assert_eq!(buffer.spilled_entry_count(), 0);
+        assert_eq!(buffer.dropped_entry_count(), 1);
+        assert!(!journal_file_path.exists());
+    }
+}
@@ -0,0 +1,191 @@
+use anyhow::Result;
+use log::debug;
+use std::collections::HashMap;
+use std::io;
+use std::io::Read;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Stdio;
+use std::sync::mpsc;
+use std::thread;
+use std::time;
+use timetracker_core::entries::ExecutableVersion;
+use timetracker_core::format_short_executable_name;
+
+/// How long to wait for an executable to print the version, before
+/// the probing process is killed.
+const VERSION_PROBE_TIMEOUT_MILLISECONDS: u64 = 2000;
+
+/// The maximum number of bytes of output kept from an executable
+/// printing the version.
+const VERSION_PROBE_MAX_OUTPUT_BYTES: u64 = 4096;
+
+/// The maximum number of characters stored for a version string.
+const VERSION_MAX_CHARACTER_COUNT: usize = 128;
+
+/// Caches the detected versions of executable files, keyed by the
+/// file path and modification time, so each installed executable is
+/// only probed once.
+pub struct ExecutableVersionCache {
+    versions: HashMap<(PathBuf, u64), Option<String>>,
+}
+
+impl ExecutableVersionCache {
+    pub fn new() -> ExecutableVersionCache {
+        ExecutableVersionCache {
+            versions: HashMap::new(),
+        }
+    }
+
+    /// Get the version of the executable file, probing for the
+    /// version only if the file has not been seen before (or has
+    /// changed on disk).
+    pub fn get_executable_version(
+        &mut self,
+        executable_path: &Path,
+        utc_time_seconds: u64,
+    ) -> Result<ExecutableVersion> {
+        let modified_time_seconds = file_modified_time_seconds(executable_path)?;
+
+        let key = (executable_path.to_path_buf(), modified_time_seconds);
+        let version = match self.versions.get(&key) {
+            Some(version) => version.clone(),
+            None => {
+                let version = probe_executable_version(executable_path);
+                debug!(
+                    "Probed executable version: path={:?} version={:?}",
+                    executable_path, version
+                );
+                self.versions.insert(key, version.clone());
+                version
+            }
+        };
+
+        let executable_path_str = executable_path.to_string_lossy().to_string();
+        let executable = format_short_executable_name(&executable_path_str).to_string();
+        Ok(ExecutableVersion::new(
+            executable,
+            executable_path_str,
+            modified_time_seconds,
+            version,
+            utc_time_seconds,
+        ))
+    }
+}
+
+fn file_modified_time_seconds(file_path: &Path) -> Result<u64> {
+    let modified_time = std::fs::metadata(file_path)?.modified()?;
+    let duration = modified_time.duration_since(time::UNIX_EPOCH)?;
+    Ok(duration.as_secs())
+}
+
+fn clean_version_text(text: &str) -> Option<String> {
+    let line = text.lines().map(|x| x.trim()).find(|x| !x.is_empty())?;
+    Some(line.chars().take(VERSION_MAX_CHARACTER_COUNT).collect())
+}
+
+/// Run a command and return the standard output, if the command was
+/// successful.
+fn run_command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Ask the RPM package manager for the version of the package that
+/// owns the executable file.
+fn query_rpm_package_version(executable_path: &Path) -> Option<String> {
+    let path = executable_path.to_str()?;
+    let output = run_command_output("rpm", &["-qf", "--queryformat", "%{VERSION}", path])?;
+    clean_version_text(&output)
+}
+
+/// Ask the Debian package manager for the version of the package
+/// that owns the executable file.
+fn query_dpkg_package_version(executable_path: &Path) -> Option<String> {
+    let path = executable_path.to_str()?;
+
+    // Output looks like "package-name: /path/to/executable".
+    let output = run_command_output("dpkg-query", &["-S", path])?;
+    let package_name = output.lines().next()?.split(':').next()?.trim().to_string();
+    if package_name.is_empty() {
+        return None;
+    }
+
+    let output = run_command_output("dpkg-query", &["-W", "-f=${Version}", &package_name])?;
+    clean_version_text(&output)
+}
+
+/// Run the executable with a "--version" flag, and use the first
+/// line printed as the version.
+///
+/// The executable is killed if it does not exit quickly, because
+/// not all software understands the flag. The output is read on
+/// another thread, so an executable printing more than the pipe can
+/// hold is not blocked, and only the start of the output is kept.
+fn probe_version_argument(executable_path: &Path) -> Option<String> {
+    let mut child = Command::new(executable_path)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+
+    let mut stdout = child.stdout.take()?;
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut output = Vec::new();
+        let result = stdout
+            .by_ref()
+            .take(VERSION_PROBE_MAX_OUTPUT_BYTES)
+            .read_to_end(&mut output)
+            // Discard the rest, so the executable can exit.
+            .and_then(|_| io::copy(&mut stdout, &mut io::sink()));
+        let _ = sender.send(result.map(|_| output));
+    });
+
+    let now = time::Instant::now();
+    let timeout = time::Duration::from_millis(VERSION_PROBE_TIMEOUT_MILLISECONDS);
+    let exit_status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) => {
+                if now.elapsed() > timeout {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return None;
+                }
+                thread::sleep(time::Duration::from_millis(50));
+            }
+            Err(_) => return None,
+        }
+    };
+    if !exit_status.success() {
+        return None;
+    }
+
+    // Another process started by the executable may keep the output
+    // open, so do not wait longer than the timeout.
+    let output = receiver
+        .recv_timeout(timeout.saturating_sub(now.elapsed()))
+        .ok()?
+        .ok()?;
+    clean_version_text(&String::from_utf8_lossy(&output))
+}
+
+/// Detect the version of an executable file, preferring the package
+/// manager (which does not run the executable).
+pub fn probe_executable_version(executable_path: &Path) -> Option<String> {
+    query_rpm_package_version(executable_path)
+        .or_else(|| query_dpkg_package_version(executable_path))
+        .or_else(|| probe_version_argument(executable_path))
+}
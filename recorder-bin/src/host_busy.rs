@@ -0,0 +1,86 @@
+use crate::linux_process::find_running_executable_names;
+use anyhow::{anyhow, Result};
+use log::warn;
+use std::fs::read_to_string;
+use std::time::Duration;
+use std::time::Instant;
+
+/// The reason given when the machine is busy because of the load
+/// average.
+pub const HOST_BUSY_REASON_LOAD: &str = "load";
+
+/// Scanning all processes is slower than reading the load average,
+/// so the running processes are only scanned this often.
+const PROCESS_SCAN_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Read the 1-minute load average from "/proc/loadavg".
+pub fn read_load_average() -> Result<f64> {
+    // The file content looks like "0.52 0.58 0.59 1/467 12345".
+    let file_content = read_to_string("/proc/loadavg")?;
+    let value = file_content
+        .split_whitespace()
+        .next()
+        .ok_or_else(|| anyhow!("Could not parse /proc/loadavg: {:?}", file_content))?;
+    Ok(value.parse::<f64>()?)
+}
+
+/// Detects when the machine is busy, from the load average or from
+/// configured background processes (such as a local render).
+pub struct HostBusyDetector {
+    load_average_threshold: f64,
+    executable_names: Vec<String>,
+    running_executable_name: Option<String>,
+    last_process_scan: Option<Instant>,
+}
+
+impl HostBusyDetector {
+    pub fn new(load_average_threshold: f64, executable_names: Vec<String>) -> HostBusyDetector {
+        HostBusyDetector {
+            load_average_threshold,
+            executable_names,
+            running_executable_name: None,
+            last_process_scan: None,
+        }
+    }
+
+    fn scan_running_executable_name(&mut self) {
+        if self.executable_names.is_empty() {
+            return;
+        }
+        let scan_is_due = self
+            .last_process_scan
+            .is_none_or(|x| x.elapsed() >= PROCESS_SCAN_INTERVAL);
+        if !scan_is_due {
+            return;
+        }
+        self.last_process_scan = Some(Instant::now());
+
+        match find_running_executable_names(&self.executable_names) {
+            Ok(names) => self.running_executable_name = names.into_iter().next(),
+            Err(err) => warn!("Could not find running processes: err={:?}", err),
+        }
+    }
+
+    /// The reason the machine is busy, or None if the machine is not
+    /// busy.
+    ///
+    /// A running background process is given as the reason before
+    /// the load average, because it is more descriptive.
+    pub fn busy_reason(&mut self) -> Option<String> {
+        self.scan_running_executable_name();
+        if let Some(executable_name) = &self.running_executable_name {
+            return Some(executable_name.clone());
+        }
+
+        match read_load_average() {
+            Ok(load_average) if load_average >= self.load_average_threshold => {
+                Some(HOST_BUSY_REASON_LOAD.to_string())
+            }
+            Ok(_) => None,
+            Err(err) => {
+                warn!("Could not read load average: err={:?}", err);
+                None
+            }
+        }
+    }
+}
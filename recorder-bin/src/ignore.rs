@@ -0,0 +1,74 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::collections::HashMap;
+use timetracker_core::format::IgnoreAction;
+use timetracker_core::settings::RecorderIgnoreSettings;
+
+/// Convert a glob pattern ("*" matches any characters, "?" matches
+/// one character) to a regular expression matching the whole value.
+fn compile_glob(pattern: &str) -> Result<Regex> {
+    let mut expression = String::from("^");
+    for character in pattern.chars() {
+        match character {
+            '*' => expression.push_str(".*"),
+            '?' => expression.push('.'),
+            _ => expression.push_str(&regex::escape(&character.to_string())),
+        }
+    }
+    expression.push('$');
+    Regex::new(&expression)
+        .with_context(|| format!("Invalid glob pattern {:?} in recorder ignore.", pattern))
+}
+
+fn compile_globs(patterns: &[String]) -> Result<Vec<Regex>> {
+    patterns.iter().map(|x| compile_glob(x)).collect()
+}
+
+/// Decides which activity is ignored by the recorder, such as a
+/// password manager, or any process working in a private directory.
+///
+/// The keys of configuration file tables are lowercased when read,
+/// so variable names are compared case-insensitively.
+#[derive(Debug)]
+pub struct IgnoreRules {
+    executables: Vec<Regex>,
+    variables: HashMap<String, Vec<Regex>>,
+    pub action: IgnoreAction,
+}
+
+impl IgnoreRules {
+    pub fn new(settings: &RecorderIgnoreSettings) -> Result<IgnoreRules> {
+        let mut variables = HashMap::new();
+        for (variable_name, patterns) in &settings.variables {
+            variables.insert(variable_name.to_lowercase(), compile_globs(patterns)?);
+        }
+        Ok(IgnoreRules {
+            executables: compile_globs(&settings.executables)?,
+            variables,
+            action: settings.action,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.executables.is_empty() && self.variables.is_empty()
+    }
+
+    /// Is the activity of the executable, with the (full) process
+    /// environment variables, ignored?
+    pub fn is_ignored(
+        &self,
+        executable_name: Option<&str>,
+        environ_vars: &HashMap<String, String>,
+    ) -> bool {
+        if let Some(executable_name) = executable_name {
+            if self.executables.iter().any(|x| x.is_match(executable_name)) {
+                return true;
+            }
+        }
+        environ_vars.iter().any(|(name, value)| {
+            self.variables
+                .get(&name.to_lowercase())
+                .is_some_and(|patterns| patterns.iter().any(|x| x.is_match(value)))
+        })
+    }
+}
@@ -0,0 +1,176 @@
+use crate::linux_process::get_user_id_running_process_id;
+use anyhow::{bail, Context, Result};
+use log::debug;
+use std::fs::File;
+use std::io::prelude::*;
+use std::os::unix::fs::FileExt;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::path::PathBuf;
+use std::thread;
+use std::time;
+
+const LOCK_FILE_NAME: &str = "timetracker-recorder.lock";
+
+/// How many times the lock file is read, while the recorder that has
+/// just taken the lock has not yet written the process id.
+const READ_PROCESS_ID_ATTEMPT_COUNT: u32 = 10;
+const READ_PROCESS_ID_RETRY_MILLISECONDS: u64 = 20;
+
+/// The lock file held by the running recorder, containing the
+/// process id of the recorder.
+///
+/// Like the control socket, the lock file is created in
+/// "$XDG_RUNTIME_DIR" (which is private to the user, and never on a
+/// network file system, so recorders on other machines sharing the
+/// database are not locked out), or next to the journal file when
+/// "XDG_RUNTIME_DIR" is not set.
+pub fn get_lock_file_path(journal_file_path: &Path) -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(value) if !value.is_empty() => PathBuf::from(value).join(LOCK_FILE_NAME),
+        _ => journal_file_path.with_extension("lock"),
+    }
+}
+
+/// Try to take the (advisory) lock of the file, without waiting,
+/// returning false if another process holds the lock.
+fn try_lock_file(file: &File, operation: libc::c_int) -> Result<bool> {
+    let result = unsafe { libc::flock(file.as_raw_fd(), operation | libc::LOCK_NB) };
+    if result == 0 {
+        return Ok(true);
+    }
+    let err = std::io::Error::last_os_error();
+    match err.raw_os_error() {
+        Some(libc::EWOULDBLOCK) => Ok(false),
+        _ => Err(err.into()),
+    }
+}
+
+/// Ensures only one recorder runs for the user, by holding an
+/// exclusive lock ('flock') on the lock file while the recorder
+/// runs.
+///
+/// The lock is released by the operating system when the process
+/// exits (even when it crashes), so a stale lock file never stops the
+/// recorder from starting.
+pub struct InstanceLock {
+    // The lock is held while the file is open.
+    _file: File,
+}
+
+impl InstanceLock {
+    /// Take the lock and write this process id to the lock file, or
+    /// return None if another recorder holds the lock.
+    pub fn acquire(lock_file_path: &Path) -> Result<Option<InstanceLock>> {
+        let mut file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .mode(0o600)
+            .open(lock_file_path)
+            .with_context(|| format!("Could not open lock file {:?}.", lock_file_path))?;
+        if !try_lock_file(&file, libc::LOCK_EX)? {
+            return Ok(None);
+        }
+
+        // The file is only changed once the lock is held, so the
+        // running recorder's process id is never overwritten. The
+        // process id is written over the start of the file before the
+        // file is truncated, so the file is never seen empty.
+        let text = format!("{}\n", std::process::id());
+        file.write_all_at(text.as_bytes(), 0)?;
+        file.set_len(text.len() as u64)?;
+        file.flush()?;
+        debug!("Acquired lock file {:?}.", lock_file_path);
+        Ok(Some(InstanceLock { _file: file }))
+    }
+}
+
+/// The process id of the recorder holding the lock of the lock file,
+/// or None if no recorder is running.
+///
+/// The process must be owned by the current user, so the process of
+/// another user (that can read the lock file) is never terminated.
+pub fn read_locked_process_id(lock_file_path: &Path) -> Result<Option<u32>> {
+    let mut file = match File::open(lock_file_path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    // The shared lock can only be taken when no recorder holds the
+    // exclusive lock; it is released when the file is closed.
+    if try_lock_file(&file, libc::LOCK_SH)? {
+        return Ok(None);
+    }
+
+    // A recorder that has just created the lock file may not have
+    // written the process id yet.
+    let mut attempt = 1;
+    let process_id = loop {
+        let mut text = String::new();
+        file.rewind()?;
+        file.read_to_string(&mut text)?;
+        let first_line = text.lines().next().unwrap_or("").trim();
+        match first_line.parse::<u32>() {
+            Ok(process_id) => break process_id,
+            Err(_) if first_line.is_empty() && attempt < READ_PROCESS_ID_ATTEMPT_COUNT => {
+                attempt += 1;
+                thread::sleep(time::Duration::from_millis(
+                    READ_PROCESS_ID_RETRY_MILLISECONDS,
+                ));
+            }
+            Err(err) => {
+                return Err(err).with_context(|| {
+                    format!("Invalid process id in lock file {:?}.", lock_file_path)
+                })
+            }
+        }
+    };
+    let user_id = unsafe { libc::getuid() };
+    if get_user_id_running_process_id(process_id)? != user_id {
+        bail!(
+            "Process {} of lock file {:?} is not owned by the current user.",
+            process_id,
+            lock_file_path
+        );
+    }
+    Ok(Some(process_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instance_lock_read_locked_process_id() -> Result<()> {
+        let lock_file_path = std::env::temp_dir().join(format!(
+            "timetracker-instance-lock-{}.lock",
+            std::process::id()
+        ));
+        // A longer process id left by an earlier recorder is
+        // replaced.
+        std::fs::write(&lock_file_path, "4294967295\n")?;
+        assert_eq!(read_locked_process_id(&lock_file_path)?, None);
+
+        let lock = InstanceLock::acquire(&lock_file_path)?;
+        assert!(lock.is_some());
+        assert_eq!(
+            std::fs::read_to_string(&lock_file_path)?,
+            format!("{}\n", std::process::id())
+        );
+        // The lock is held by the file (not the process), so the lock
+        // is read with a second file handle.
+        assert_eq!(
+            read_locked_process_id(&lock_file_path)?,
+            Some(std::process::id())
+        );
+        assert!(InstanceLock::acquire(&lock_file_path)?.is_none());
+
+        drop(lock);
+        assert_eq!(read_locked_process_id(&lock_file_path)?, None);
+        std::fs::remove_file(lock_file_path)?;
+        Ok(())
+    }
+}
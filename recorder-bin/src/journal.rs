@@ -0,0 +1,286 @@
+use anyhow::Result;
+use log::warn;
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use timetracker_core::entries::Entry;
+
+/// The journal file name, used when the journal file path is not
+/// configured.
+const JOURNAL_FILE_NAME: &str = ".timetracker-recorder.journal";
+
+/// The buffer file, next to the journal file, that each entry is
+/// written to before it is buffered in memory.
+pub fn get_buffer_file_path(journal_file_path: &Path) -> PathBuf {
+    journal_file_path.with_extension("buffer")
+}
+
+/// Get the journal file path; the configured path, or a file next to
+/// the database file.
+pub fn get_journal_file_path(journal_file_path: &str, database_file_path: &Path) -> PathBuf {
+    if !journal_file_path.is_empty() {
+        return PathBuf::from(journal_file_path);
+    }
+    match database_file_path.parent() {
+        Some(directory) => directory.join(JOURNAL_FILE_NAME),
+        None => PathBuf::from(JOURNAL_FILE_NAME),
+    }
+}
+
+/// Append the entries to the journal file, one entry per line, and
+/// flush the file to disk.
+pub fn append_entries_to_journal(journal_file_path: &Path, entries: &[Entry]) -> Result<()> {
+    let mut text = String::new();
+    for entry in entries {
+        text.push_str(&serde_json::to_string(entry)?);
+        text.push('\n');
+    }
+
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(journal_file_path)?;
+    file.write_all(text.as_bytes())?;
+    file.sync_data()?;
+    Ok(())
+}
+
+/// Flush the directory of the file to disk, so a renamed (or
+/// removed) file is not reverted when the machine loses power.
+fn sync_parent_directory(file_path: &Path) -> Result<()> {
+    let directory = match file_path.parent() {
+        Some(directory) if !directory.as_os_str().is_empty() => directory,
+        _ => Path::new("."),
+    };
+    fs::File::open(directory)?.sync_all()?;
+    Ok(())
+}
+
+/// Replace the entries in the journal file, removing the file when
+/// there are no entries.
+///
+/// The entries are written to a temporary file that replaces the
+/// journal file, so the journal file is never partially written.
+pub fn replace_journal_entries(journal_file_path: &Path, entries: &[Entry]) -> Result<()> {
+    if entries.is_empty() {
+        match fs::remove_file(journal_file_path) {
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err.into()),
+            Ok(()) => return sync_parent_directory(journal_file_path),
+        }
+    }
+
+    let temp_file_path = journal_file_path.with_extension("tmp");
+    if temp_file_path.exists() {
+        fs::remove_file(&temp_file_path)?;
+    }
+    append_entries_to_journal(&temp_file_path, entries)?;
+    fs::rename(&temp_file_path, journal_file_path)?;
+    sync_parent_directory(journal_file_path)?;
+    Ok(())
+}
+
+/// Move the entries of the buffer file (left when the recorder was
+/// killed, or could not write to storage before exiting) to the
+/// journal file, to be written to storage. Returns the number of
+/// entries moved.
+///
+/// Entries already in the journal file (spilled from the buffer,
+/// but not yet removed from the buffer file) are not moved again.
+pub fn move_buffer_file_to_journal(
+    buffer_file_path: &Path,
+    journal_file_path: &Path,
+) -> Result<usize> {
+    let (entries, _byte_count) = read_journal_entries(buffer_file_path)?;
+    let (journal_entries, _byte_count) = read_journal_entries(journal_file_path)?;
+    let journal_lines = journal_entries
+        .iter()
+        .map(serde_json::to_string)
+        .collect::<Result<HashSet<String>, _>>()?;
+    let mut entries_to_move = Vec::with_capacity(entries.len());
+    for entry in entries {
+        if !journal_lines.contains(&serde_json::to_string(&entry)?) {
+            entries_to_move.push(entry);
+        }
+    }
+    let entries = entries_to_move;
+    if !entries.is_empty() {
+        append_entries_to_journal(journal_file_path, &entries)?;
+    }
+    if buffer_file_path.exists() {
+        fs::remove_file(buffer_file_path)?;
+    }
+    Ok(entries.len())
+}
+
+/// Read all the entries in the journal file, and the number of bytes
+/// read (so only the read entries are removed from the journal
+/// file).
+///
+/// Lines that cannot be parsed (such as a line partially written
+/// when the process was killed) are skipped.
+pub fn read_journal_entries(journal_file_path: &Path) -> Result<(Vec<Entry>, usize)> {
+    if !journal_file_path.is_file() {
+        return Ok((Vec::new(), 0));
+    }
+
+    let text = fs::read_to_string(journal_file_path)?;
+    let mut entries = Vec::new();
+    let mut byte_count = 0;
+    for line in text.split_inclusive('\n') {
+        if !line.ends_with('\n') {
+            // The line is still being written.
+            break;
+        }
+        byte_count += line.len();
+        match serde_json::from_str::<Entry>(line.trim_end()) {
+            Ok(entry) => entries.push(entry),
+            Err(err) => warn!(
+                "Skipping invalid journal line in {:?}: {:?}",
+                journal_file_path, err
+            ),
+        }
+    }
+    Ok((entries, byte_count))
+}
+
+/// Remove the first 'byte_count' bytes (the entries written to the
+/// database) from the journal file, removing the file when it is
+/// empty.
+pub fn remove_journal_entries(journal_file_path: &Path, byte_count: usize) -> Result<()> {
+    if byte_count == 0 {
+        return Ok(());
+    }
+
+    let data = fs::read(journal_file_path)?;
+    if data.len() <= byte_count {
+        fs::remove_file(journal_file_path)?;
+    } else {
+        fs::write(journal_file_path, &data[byte_count..])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use timetracker_core::entries::EntryStatus;
+    use timetracker_core::entries::EntryVariablesList;
+
+    fn temp_file_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "timetracker-journal-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn new_entry(utc_time_seconds: u64) -> Entry {
+        Entry::new(
+            utc_time_seconds,
+            10,
+            EntryStatus::Active,
+            EntryVariablesList::empty(),
+        )
+    }
+
+    fn read_times(file_path: &Path) -> Vec<u64> {
+        let (entries, _byte_count) = read_journal_entries(file_path).unwrap();
+        entries.iter().map(|x| x.utc_time_seconds).collect()
+    }
+
+    #[test]
+    fn test_journal_round_trip() -> Result<()> {
+        let journal_file_path = temp_file_path("round-trip");
+        assert_eq!(read_journal_entries(&journal_file_path)?.0.len(), 0);
+
+        append_entries_to_journal(&journal_file_path, &[new_entry(10), new_entry(20)])?;
+        append_entries_to_journal(&journal_file_path, &[new_entry(30)])?;
+        assert_eq!(read_times(&journal_file_path), vec![10, 20, 30]);
+
+        replace_journal_entries(&journal_file_path, &[new_entry(40)])?;
+        assert_eq!(read_times(&journal_file_path), vec![40]);
+        assert!(!journal_file_path.with_extension("tmp").exists());
+
+        replace_journal_entries(&journal_file_path, &[])?;
+        assert!(!journal_file_path.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_move_buffer_file_to_journal() -> Result<()> {
+        let journal_file_path = temp_file_path("recover.journal");
+        let buffer_file_path = temp_file_path("recover.buffer");
+
+        // The first entry was spilled to the journal file, but is
+        // still in the buffer file.
+        append_entries_to_journal(&journal_file_path, &[new_entry(10)])?;
+        append_entries_to_journal(&buffer_file_path, &[new_entry(10), new_entry(20)])?;
+
+        assert_eq!(
+            move_buffer_file_to_journal(&buffer_file_path, &journal_file_path)?,
+            1
+        );
+        assert_eq!(read_times(&journal_file_path), vec![10, 20]);
+        assert!(!buffer_file_path.exists());
+
+        // Without a buffer file there is nothing to recover.
+        assert_eq!(
+            move_buffer_file_to_journal(&buffer_file_path, &journal_file_path)?,
+            0
+        );
+        fs::remove_file(journal_file_path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_journal_entries() -> Result<()> {
+        let journal_file_path = temp_file_path("read");
+        let first_line = format!("{}\n", serde_json::to_string(&new_entry(10))?);
+        let second_line = format!("{}\n", serde_json::to_string(&new_entry(20))?);
+        let partial_line = serde_json::to_string(&new_entry(30))?;
+        let text = format!(
+            "{}not an entry\n{}{}",
+            first_line,
+            second_line,
+            &partial_line[..partial_line.len() / 2]
+        );
+        fs::write(&journal_file_path, &text)?;
+
+        // The invalid line is skipped, and the partially written line
+        // is not read (or counted).
+        let (entries, byte_count) = read_journal_entries(&journal_file_path)?;
+        let times: Vec<u64> = entries.iter().map(|x| x.utc_time_seconds).collect();
+        assert_eq!(times, vec![10, 20]);
+        assert_eq!(
+            byte_count,
+            first_line.len() + "not an entry\n".len() + second_line.len()
+        );
+
+        fs::remove_file(journal_file_path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_journal_entries() -> Result<()> {
+        let journal_file_path = temp_file_path("remove");
+        append_entries_to_journal(&journal_file_path, &[new_entry(10), new_entry(20)])?;
+        let (_entries, byte_count) = read_journal_entries(&journal_file_path)?;
+
+        // Entries appended after reading the journal are kept.
+        append_entries_to_journal(&journal_file_path, &[new_entry(30)])?;
+        remove_journal_entries(&journal_file_path, 0)?;
+        assert_eq!(read_times(&journal_file_path), vec![10, 20, 30]);
+        remove_journal_entries(&journal_file_path, byte_count)?;
+        assert_eq!(read_times(&journal_file_path), vec![30]);
+
+        let (_entries, byte_count) = read_journal_entries(&journal_file_path)?;
+        remove_journal_entries(&journal_file_path, byte_count)?;
+        assert!(!journal_file_path.exists());
+        Ok(())
+    }
+}
@@ -5,7 +5,6 @@ use std::collections::HashMap;
 use std::fs::read_to_string;
 use std::path::PathBuf;
 use std::process::Command;
-use timetracker_core::format_short_executable_name;
 
 type UserID = u32;
 
@@ -106,80 +105,74 @@ pub fn get_user_id_running_process_id(process_id: ProcessID) -> Result<UserID> {
     Ok(user_id)
 }
 
-/// Gets all processes (as 'pid's) that not this current process, and
-/// are owned by 'user_id_owner', and are named 'executable_name'.
-///
-/// 'user_id_owner' is used to make sure only the process ids that are
-/// owned by the current user are returned. On Linux multiple users
-/// may be logged into the same machine and running
-/// 'timetracker-recorder' at the same time on the same machine.
-#[cfg(target_os = "linux")]
-pub fn find_process_ids_by_user_and_executable_name(
-    executable_name: &str,
-    user_id_owner: UserID,
-    this_process_id: ProcessID,
-) -> Result<Vec<ProcessID>> {
-    let mut path = PathBuf::new();
-    path.push("/");
-    path.push("proc");
+/// Get the name of this machine.
+#[cfg(target_os = "linux")]
+pub fn get_hostname() -> Result<String> {
+    let hostname = read_to_string("/proc/sys/kernel/hostname")?;
+    Ok(hostname.trim().to_string())
+}
 
-    let read_directory = std::fs::read_dir(path)?;
-    let valid_directories: Vec<_> = read_directory
-        .filter_map(|entry| {
-            let entry = entry.ok()?.path();
+/// Get the name of the user running this process, from the user
+/// database ("/etc/passwd", or the network directory).
+#[cfg(target_os = "linux")]
+pub fn get_username() -> Result<String> {
+    let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut buffer = vec![0 as libc::c_char; 4096];
+    let mut result: *mut libc::passwd = std::ptr::null_mut();
+    let status = unsafe {
+        libc::getpwuid_r(
+            libc::getuid(),
+            &mut passwd,
+            buffer.as_mut_ptr(),
+            buffer.len(),
+            &mut result,
+        )
+    };
+    if status != 0 || result.is_null() {
+        return Err(anyhow!("Could not find the user name: status={}", status));
+    }
 
-            if entry.is_dir() {
-                Some(entry)
-            } else {
-                None
-            }
-        })
-        .collect();
+    let username = unsafe { std::ffi::CStr::from_ptr(passwd.pw_name) };
+    Ok(username.to_string_lossy().to_string())
+}
 
-    let process_ids: Vec<ProcessID> = valid_directories
-        .iter()
-        .filter_map(|p| {
-            let process_id_str = p.file_name();
+/// Get the number of seconds since the process was started.
+#[cfg(target_os = "linux")]
+pub fn get_process_id_uptime_seconds(process_id: ProcessID) -> Result<u64> {
+    let mut path = PathBuf::new();
+    path.push("/");
+    path.push("proc");
+    path.push(format!("{}", process_id));
+    path.push("stat");
 
-            let mut cmdline_path = p.to_path_buf();
-            cmdline_path.push("cmdline");
+    // The start time is the 22nd field of the file content, in clock
+    // ticks since the machine was booted. The fields are counted
+    // after the 'comm' field, which may contain spaces.
+    let file_content = read_to_string(&path)?;
+    let comm_end = file_content
+        .rfind(')')
+        .ok_or_else(|| anyhow!("Invalid {:?} file content.", path))?;
+    let start_time_ticks = file_content[(comm_end + 1)..]
+        .split_whitespace()
+        .nth(19)
+        .ok_or_else(|| anyhow!("Invalid {:?} file content.", path))?
+        .parse::<u64>()?;
 
-            let file_metadata = std::fs::metadata(&cmdline_path).ok()?;
-            if user_id_owner != file_metadata.st_uid() {
-                return None;
-            }
+    // The first value is the number of seconds since the machine
+    // was booted.
+    let uptime_content = read_to_string("/proc/uptime")?;
+    let machine_uptime_seconds = uptime_content
+        .split_whitespace()
+        .next()
+        .ok_or_else(|| anyhow!("Invalid /proc/uptime file content."))?
+        .parse::<f64>()?;
 
-            let file_content = read_to_string(&cmdline_path).ok()?;
-
-            let executable =
-                timetracker_core::strip_executable_name(&file_content.replace('\0', " "))
-                    .to_string();
-            let executable_short = format_short_executable_name(&executable);
-
-            if executable_name == executable_short {
-                match process_id_str {
-                    Some(value) => {
-                        let process_id = value
-                            .to_os_string()
-                            .into_string()
-                            .ok()?
-                            .parse::<ProcessID>()
-                            .ok()?;
-                        if this_process_id != process_id {
-                            Some(process_id)
-                        } else {
-                            None
-                        }
-                    }
-                    None => None,
-                }
-            } else {
-                None
-            }
-        })
-        .collect();
-
-    Ok(process_ids)
+    let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if ticks_per_second <= 0 {
+        return Err(anyhow!("Could not find the clock ticks per second."));
+    }
+    let start_time_seconds = start_time_ticks / (ticks_per_second as u64);
+    Ok((machine_uptime_seconds as u64).saturating_sub(start_time_seconds))
 }
 
 #[cfg(target_os = "linux")]
@@ -208,3 +201,153 @@ pub fn get_process_id_executable_name(process_id: ProcessID) -> Result<String> {
 
     Ok(executable)
 }
+
+/// Get the full path to the executable file run by the given process
+/// (pid).
+#[cfg(target_os = "linux")]
+pub fn get_process_id_executable_path(process_id: ProcessID) -> Result<PathBuf> {
+    let mut path = PathBuf::new();
+    let process_id_str: String = format!("{}", process_id);
+    path.push("/");
+    path.push("proc");
+    path.push(process_id_str);
+    path.push("exe");
+
+    let executable_path = std::fs::read_link(&path)?;
+    Ok(executable_path)
+}
+
+/// Get the current working directory of the given process (pid).
+///
+/// Unlike the "PWD" environment variable (which is only set by
+/// shells, and is stale for applications started from a desktop
+/// launcher), this is the directory the process is using now.
+#[cfg(target_os = "linux")]
+pub fn get_process_id_working_directory(process_id: ProcessID) -> Result<PathBuf> {
+    let mut path = PathBuf::new();
+    let process_id_str: String = format!("{}", process_id);
+    path.push("/");
+    path.push("proc");
+    path.push(process_id_str);
+    path.push("cwd");
+
+    let working_directory = std::fs::read_link(&path)?;
+    Ok(working_directory)
+}
+
+/// Get the parent process id (ppid) and command name of a process.
+#[cfg(target_os = "linux")]
+fn read_process_id_stat(process_id: ProcessID) -> Option<(ProcessID, String)> {
+    let mut path = PathBuf::new();
+    path.push("/");
+    path.push("proc");
+    path.push(format!("{}", process_id));
+    path.push("stat");
+
+    // The file content looks like "pid (comm) state ppid ...", where
+    // 'comm' may contain spaces and parentheses.
+    let file_content = read_to_string(&path).ok()?;
+    let comm_start = file_content.find('(')?;
+    let comm_end = file_content.rfind(')')?;
+    let command_name = file_content.get((comm_start + 1)..comm_end)?.to_string();
+    let fields: Vec<&str> = file_content
+        .get((comm_end + 1)..)?
+        .split_whitespace()
+        .collect();
+    let parent_process_id = fields.get(1)?.parse::<ProcessID>().ok()?;
+    Some((parent_process_id, command_name))
+}
+
+/// Find all the processes that are descendants (children,
+/// grandchildren, etc) of the given process, with the command name
+/// of each process.
+#[cfg(target_os = "linux")]
+pub fn find_descendant_process_ids(process_id: ProcessID) -> Result<Vec<(ProcessID, String)>> {
+    let mut path = PathBuf::new();
+    path.push("/");
+    path.push("proc");
+
+    let mut children = HashMap::<ProcessID, Vec<(ProcessID, String)>>::new();
+    for entry in std::fs::read_dir(path)? {
+        let entry = match entry {
+            Ok(value) => value,
+            Err(_) => continue,
+        };
+        let child_process_id = match entry.file_name().to_str().map(|x| x.parse::<ProcessID>()) {
+            Some(Ok(value)) => value,
+            _ => continue,
+        };
+        if let Some((parent_process_id, command_name)) = read_process_id_stat(child_process_id) {
+            children
+                .entry(parent_process_id)
+                .or_default()
+                .push((child_process_id, command_name));
+        }
+    }
+
+    let mut descendants = Vec::new();
+    let mut pending = vec![process_id];
+    while let Some(parent_process_id) = pending.pop() {
+        if let Some(values) = children.get(&parent_process_id) {
+            for (child_process_id, command_name) in values {
+                descendants.push((*child_process_id, command_name.clone()));
+                pending.push(*child_process_id);
+            }
+        }
+    }
+
+    Ok(descendants)
+}
+
+/// The Linux kernel truncates the command name of a process to 15
+/// characters.
+const COMMAND_NAME_MAX_LENGTH: usize = 15;
+
+/// Find which of the given executable names are running (as any
+/// user), returned in the order given.
+#[cfg(target_os = "linux")]
+pub fn find_running_executable_names(executable_names: &[String]) -> Result<Vec<String>> {
+    let mut path = PathBuf::new();
+    path.push("/");
+    path.push("proc");
+
+    let mut running_command_names = std::collections::HashSet::<String>::new();
+    for entry in std::fs::read_dir(path)? {
+        let entry = match entry {
+            Ok(value) => value,
+            Err(_) => continue,
+        };
+        let process_id = match entry.file_name().to_str().map(|x| x.parse::<ProcessID>()) {
+            Some(Ok(value)) => value,
+            _ => continue,
+        };
+        if let Some((_parent_process_id, command_name)) = read_process_id_stat(process_id) {
+            running_command_names.insert(command_name);
+        }
+    }
+
+    let running_executable_names = executable_names
+        .iter()
+        .filter(|name| {
+            let command_name: String = name.chars().take(COMMAND_NAME_MAX_LENGTH).collect();
+            running_command_names.contains(&command_name)
+        })
+        .cloned()
+        .collect();
+    Ok(running_executable_names)
+}
+
+/// Get the terminal device (such as "/dev/pts/3") connected to the
+/// standard input of the process.
+#[cfg(target_os = "linux")]
+pub fn get_process_id_terminal_path(process_id: ProcessID) -> Result<PathBuf> {
+    let mut path = PathBuf::new();
+    path.push("/");
+    path.push("proc");
+    path.push(format!("{}", process_id));
+    path.push("fd");
+    path.push("0");
+
+    let terminal_path = std::fs::read_link(&path)?;
+    Ok(terminal_path)
+}
@@ -1,6 +1,5 @@
 use anyhow::Result;
 use log::{debug, warn};
-use std::ffi::CStr;
 use std::os::raw::c_char;
 use std::os::raw::c_int;
 use std::os::raw::c_long;
@@ -41,6 +40,24 @@ extern "C" fn handle_error_callback(
 /// The global error status of X11.
 static mut X11_ERROR: XError = XError::Success;
 
+/// Open an X11 display by name (such as ":1", or ":0.1" for a
+/// screen), or the default display (named by the "DISPLAY"
+/// variable) when the name is None.
+///
+/// Returns a null pointer when the display cannot be opened.
+fn open_display(display_name: Option<&str>) -> *mut x11::xlib::Display {
+    match display_name {
+        Some(display_name) => match std::ffi::CString::new(display_name) {
+            Ok(display_name) => unsafe { x11::xlib::XOpenDisplay(display_name.as_ptr()) },
+            Err(_) => std::ptr::null_mut(),
+        },
+        None => {
+            let display_num = 0 as c_char;
+            unsafe { x11::xlib::XOpenDisplay(&display_num) }
+        }
+    }
+}
+
 fn get_window_id_with_focus(display_ptr: *mut x11::xlib::Display) -> c_ulong {
     let mut window_id = 0 as c_ulong;
     let mut revert_to = 0 as c_int;
@@ -48,7 +65,6 @@ fn get_window_id_with_focus(display_ptr: *mut x11::xlib::Display) -> c_ulong {
     window_id
 }
 
-#[allow(dead_code)]
 fn get_top_window_id(display_ptr: *mut x11::xlib::Display, start_window_id: c_ulong) -> c_ulong {
     let mut window_id = start_window_id;
     let mut parent_window_id = start_window_id;
@@ -98,7 +114,7 @@ fn list_window_properties(display_ptr: *mut x11::xlib::Display, window_id: c_ulo
 
 fn get_process_id_property_id(display_ptr: *mut x11::xlib::Display) -> Result<x11::xlib::Atom> {
     // https://tronche.com/gui/x/xlib/window-information/XInternAtom.html
-    let atom_name = CStr::from_bytes_with_nul(b"_NET_WM_PID\0")?;
+    let atom_name = c"_NET_WM_PID";
     let atom_name_ptr = atom_name.as_ptr();
     let only_if_exists = 1 as c_int;
     let property_id: x11::xlib::Atom =
@@ -216,10 +232,12 @@ fn get_process_id_from_window_tree(
     process_id
 }
 
-pub fn get_active_window_process_id_from_x11() -> Result<ProcessID> {
+pub fn get_active_window_process_id_from_x11(display_name: Option<&str>) -> Result<ProcessID> {
     // Get X11 Display.
-    let display_num = 0 as c_char;
-    let display_ptr = unsafe { x11::xlib::XOpenDisplay(&display_num) };
+    let display_ptr = open_display(display_name);
+    if display_ptr.is_null() {
+        return Ok(0);
+    }
 
     let window_id = get_window_id_with_focus(display_ptr);
     let property_id = get_process_id_property_id(display_ptr)?;
@@ -231,13 +249,134 @@ pub fn get_active_window_process_id_from_x11() -> Result<ProcessID> {
     Ok(process_id)
 }
 
-pub fn get_user_idle_time_from_x11() -> c_ulong {
-    let mut idle_time_sec = 0;
+/// Get the top-level window (the child of the root window) that
+/// contains the window with focus, or zero if no window has focus.
+pub fn get_active_top_window_id_from_x11() -> c_ulong {
+    // Get X11 Display.
+    let display_num = 0 as c_char;
+    let display_ptr = unsafe { x11::xlib::XOpenDisplay(&display_num) };
 
+    let root_window_id = unsafe { x11::xlib::XDefaultRootWindow(display_ptr) };
+    let focus_window_id = get_window_id_with_focus(display_ptr);
+    // 'PointerRoot' (1) and 'None' (0) are not real windows.
+    let window_id = match focus_window_id {
+        0 | 1 => 0,
+        id if id == root_window_id => 0,
+        id => {
+            unsafe {
+                x11::xlib::XSetErrorHandler(Some(handle_error_callback));
+            }
+            let top_window_id = get_top_window_id(display_ptr, id);
+            unsafe {
+                x11::xlib::XSetErrorHandler(None);
+                if X11_ERROR == XError::Failure {
+                    X11_ERROR = XError::Success;
+                    0
+                } else {
+                    top_window_id
+                }
+            }
+        }
+    };
+
+    // Close the X11 display.
+    unsafe { x11::xlib::XCloseDisplay(display_ptr) };
+
+    window_id
+}
+
+/// Convert the bits of a pixel selected by 'mask' into an 8-bit
+/// color channel value.
+fn pixel_channel_value(pixel: c_ulong, mask: c_ulong) -> u8 {
+    if mask == 0 {
+        return 0;
+    }
+    let shift = mask.trailing_zeros();
+    let max_value = mask >> shift;
+    (((pixel & mask) >> shift) * 255 / max_value) as u8
+}
+
+/// Read the colors of a sparse grid of pixels ('grid_size' *
+/// 'grid_size') across the window, one pixel at a time, so that an
+/// image of the whole window is never copied.
+///
+/// Returns an empty list if the window is not visible.
+pub fn get_window_pixel_colors_from_x11(window_id: c_ulong, grid_size: u32) -> Vec<(u8, u8, u8)> {
     // Get X11 Display.
     let display_num = 0 as c_char;
     let display_ptr = unsafe { x11::xlib::XOpenDisplay(&display_num) };
 
+    unsafe {
+        x11::xlib::XSetErrorHandler(Some(handle_error_callback));
+    }
+
+    let mut colors = Vec::new();
+    let mut attributes: x11::xlib::XWindowAttributes = unsafe { std::mem::zeroed() };
+    // https://tronche.com/gui/x/xlib/window-information/XGetWindowAttributes.html
+    let status =
+        unsafe { x11::xlib::XGetWindowAttributes(display_ptr, window_id, &mut attributes) };
+    let is_viewable = status != 0 && attributes.map_state == x11::xlib::IsViewable;
+    if is_viewable && attributes.width > 0 && attributes.height > 0 {
+        let plane_mask = unsafe { x11::xlib::XAllPlanes() };
+        for row in 0..grid_size {
+            for column in 0..grid_size {
+                // The center of each grid cell.
+                let x = ((2 * column + 1) * attributes.width as u32) / (2 * grid_size);
+                let y = ((2 * row + 1) * attributes.height as u32) / (2 * grid_size);
+
+                // https://tronche.com/gui/x/xlib/graphics/XGetImage.html
+                let image_ptr = unsafe {
+                    x11::xlib::XGetImage(
+                        display_ptr,
+                        window_id,
+                        x as c_int,
+                        y as c_int,
+                        1,
+                        1,
+                        plane_mask,
+                        x11::xlib::ZPixmap,
+                    )
+                };
+                if image_ptr.is_null() {
+                    continue;
+                }
+
+                let (pixel, red_mask, green_mask, blue_mask) = unsafe {
+                    let image = &*image_ptr;
+                    (
+                        x11::xlib::XGetPixel(image_ptr, 0, 0),
+                        image.red_mask,
+                        image.green_mask,
+                        image.blue_mask,
+                    )
+                };
+                colors.push((
+                    pixel_channel_value(pixel, red_mask),
+                    pixel_channel_value(pixel, green_mask),
+                    pixel_channel_value(pixel, blue_mask),
+                ));
+                unsafe { x11::xlib::XDestroyImage(image_ptr) };
+            }
+        }
+    }
+
+    unsafe {
+        x11::xlib::XSetErrorHandler(None);
+        X11_ERROR = XError::Success;
+    }
+
+    // Close the X11 display.
+    unsafe { x11::xlib::XCloseDisplay(display_ptr) };
+
+    colors
+}
+
+/// The time (in seconds) since the last keyboard or mouse input on
+/// the display, or None if the screen saver extension cannot be
+/// queried.
+fn get_display_idle_time(display_ptr: *mut x11::xlib::Display) -> Option<c_ulong> {
+    let mut idle_time_sec = None;
+
     let info_ptr = unsafe { x11::xss::XScreenSaverAllocInfo() };
     if !info_ptr.is_null() {
         let status = unsafe {
@@ -250,13 +389,25 @@ pub fn get_user_idle_time_from_x11() -> c_ulong {
 
         if status != 0 {
             let idle_time_ms = unsafe { (*info_ptr).idle }; // milliseconds
-            idle_time_sec = idle_time_ms / 1000;
+            idle_time_sec = Some(idle_time_ms / 1000);
             unsafe {
                 x11::xlib::XFree(info_ptr as *mut c_void);
             }
         }
     }
 
+    idle_time_sec
+}
+
+pub fn get_user_idle_time_from_x11(display_name: Option<&str>) -> c_ulong {
+    // Get X11 Display.
+    let display_ptr = open_display(display_name);
+    if display_ptr.is_null() {
+        return 0;
+    }
+
+    let idle_time_sec = get_display_idle_time(display_ptr).unwrap_or(0);
+
     // Close the X11 display.
     unsafe {
         x11::xlib::XCloseDisplay(display_ptr);
@@ -264,3 +415,300 @@ pub fn get_user_idle_time_from_x11() -> c_ulong {
 
     idle_time_sec
 }
+
+/// Find the display the user is using, of the displays (or seats)
+/// given; the display with the most recent keyboard or mouse input,
+/// and the idle time of the display.
+///
+/// 'XGetInputFocus' only reads the focus of one display, so each
+/// display is queried. Displays that cannot be opened are skipped,
+/// returning None when no display can be opened.
+pub fn find_active_display_from_x11(display_names: &[String]) -> Option<(String, c_ulong)> {
+    let mut active_display = None;
+    for display_name in display_names {
+        let display_ptr = open_display(Some(display_name));
+        if display_ptr.is_null() {
+            debug!("Could not open X11 display {:?}.", display_name);
+            continue;
+        }
+
+        let idle_time_sec = get_display_idle_time(display_ptr);
+
+        // Close the X11 display.
+        unsafe {
+            x11::xlib::XCloseDisplay(display_ptr);
+        }
+
+        if let Some(idle_time_sec) = idle_time_sec {
+            let is_more_recent = active_display
+                .as_ref()
+                .is_none_or(|(_, active_idle_time_sec)| idle_time_sec < *active_idle_time_sec);
+            if is_more_recent {
+                active_display = Some((display_name.clone(), idle_time_sec));
+            }
+        }
+    }
+    active_display
+}
+
+/// Is the X11 screen saver active (such as when the screen is
+/// blanked or locked by a screen saver using the 'XScreenSaver'
+/// extension)?
+pub fn is_screen_saver_active_from_x11() -> bool {
+    let mut is_active = false;
+
+    // Get X11 Display.
+    let display_num = 0 as c_char;
+    let display_ptr = unsafe { x11::xlib::XOpenDisplay(&display_num) };
+
+    let info_ptr = unsafe { x11::xss::XScreenSaverAllocInfo() };
+    if !info_ptr.is_null() {
+        let status = unsafe {
+            x11::xss::XScreenSaverQueryInfo(
+                display_ptr,
+                x11::xlib::XDefaultRootWindow(display_ptr),
+                info_ptr,
+            )
+        };
+
+        if status != 0 {
+            let state = unsafe { (*info_ptr).state };
+            is_active = state == x11::xss::ScreenSaverOn;
+            unsafe {
+                x11::xlib::XFree(info_ptr as *mut c_void);
+            }
+        }
+    }
+
+    // Close the X11 display.
+    unsafe {
+        x11::xlib::XCloseDisplay(display_ptr);
+    }
+
+    is_active
+}
+
+fn get_atom_id(
+    display_ptr: *mut x11::xlib::Display,
+    atom_name: &std::ffi::CStr,
+) -> x11::xlib::Atom {
+    let only_if_exists = 1 as c_int;
+    unsafe { x11::xlib::XInternAtom(display_ptr, atom_name.as_ptr(), only_if_exists) }
+}
+
+/// Get the raw bytes of a window property, or None if the window does
+/// not have the property.
+fn get_window_property_bytes(
+    display_ptr: *mut x11::xlib::Display,
+    window_id: c_ulong,
+    property_id: x11::xlib::Atom,
+    req_type: x11::xlib::Atom,
+) -> Option<(c_int, Vec<u8>)> {
+    let long_offset = 0 as c_long;
+    // Read (up to) 4096 * 4 bytes.
+    let long_length = 4096 as c_long;
+    let delete = x11::xlib::False as c_int;
+
+    let mut actual_type_return = 0 as c_ulong;
+    let mut actual_format_return = 0 as c_int;
+    let mut nitems_return = 0 as c_ulong;
+    let mut bytes_after_return = 0 as c_ulong;
+    let mut prop_return_ptr: *mut c_uchar = std::ptr::null_mut();
+
+    // https://tronche.com/gui/x/xlib/window-information/XGetWindowProperty.html
+    let status: c_int = unsafe {
+        x11::xlib::XGetWindowProperty(
+            display_ptr,
+            window_id,
+            property_id,
+            long_offset,
+            long_length,
+            delete,
+            req_type,
+            &mut actual_type_return,
+            &mut actual_format_return,
+            &mut nitems_return,
+            &mut bytes_after_return,
+            &mut prop_return_ptr,
+        )
+    };
+    if status != (x11::xlib::Success as i32) || prop_return_ptr.is_null() {
+        return None;
+    }
+
+    let mut bytes = Vec::new();
+    if actual_type_return == req_type {
+        // Format 32 properties are returned as an array of 'long'
+        // values, regardless of the size of 'long'.
+        let item_size = match actual_format_return {
+            8 => 1,
+            16 => std::mem::size_of::<std::os::raw::c_short>(),
+            32 => std::mem::size_of::<c_long>(),
+            _ => 0,
+        };
+        let byte_count = (nitems_return as usize) * item_size;
+        bytes = unsafe { std::slice::from_raw_parts(prop_return_ptr, byte_count) }.to_vec();
+    }
+    unsafe { x11::xlib::XFree(prop_return_ptr as *mut c_void) };
+
+    match bytes.is_empty() {
+        true => None,
+        false => Some((actual_format_return, bytes)),
+    }
+}
+
+/// Get the name of the current workspace (virtual desktop), as set
+/// by the window manager.
+///
+/// When the window manager does not name the workspaces, the
+/// workspace number (starting at 1) is returned as the name.
+pub fn get_current_workspace_name_from_x11(display_name: Option<&str>) -> Result<Option<String>> {
+    // Get X11 Display.
+    let display_ptr = open_display(display_name);
+    if display_ptr.is_null() {
+        return Ok(None);
+    }
+    let root_window_id = unsafe { x11::xlib::XDefaultRootWindow(display_ptr) };
+
+    let current_desktop_id = get_atom_id(display_ptr, c"_NET_CURRENT_DESKTOP");
+    let desktop_names_id = get_atom_id(display_ptr, c"_NET_DESKTOP_NAMES");
+    let utf8_string_id = get_atom_id(display_ptr, c"UTF8_STRING");
+
+    let mut workspace_name = None;
+    if current_desktop_id != 0 {
+        let current_desktop = get_window_property_bytes(
+            display_ptr,
+            root_window_id,
+            current_desktop_id,
+            x11::xlib::XA_CARDINAL,
+        );
+        if let Some((32, bytes)) = current_desktop {
+            let desktop_index =
+                c_long::from_ne_bytes(bytes[..std::mem::size_of::<c_long>()].try_into()?) as usize;
+
+            let mut desktop_names = Vec::new();
+            if desktop_names_id != 0 && utf8_string_id != 0 {
+                if let Some((8, bytes)) = get_window_property_bytes(
+                    display_ptr,
+                    root_window_id,
+                    desktop_names_id,
+                    utf8_string_id,
+                ) {
+                    desktop_names = bytes
+                        .split(|x| *x == 0)
+                        .map(|x| String::from_utf8_lossy(x).to_string())
+                        .collect();
+                }
+            }
+
+            workspace_name = match desktop_names.get(desktop_index) {
+                Some(name) if !name.is_empty() => Some(name.clone()),
+                _ => Some(format!("{}", desktop_index + 1)),
+            };
+        }
+    }
+
+    // Close the X11 display.
+    unsafe { x11::xlib::XCloseDisplay(display_ptr) };
+
+    Ok(workspace_name)
+}
+
+/// Get the title of a window; the "_NET_WM_NAME" (UTF-8) property,
+/// falling back to the older "WM_NAME" property.
+fn get_window_title(
+    display_ptr: *mut x11::xlib::Display,
+    window_id: c_ulong,
+    net_wm_name_id: x11::xlib::Atom,
+    utf8_string_id: x11::xlib::Atom,
+) -> Option<String> {
+    let title_bytes = match (net_wm_name_id, utf8_string_id) {
+        (0, _) | (_, 0) => None,
+        _ => get_window_property_bytes(display_ptr, window_id, net_wm_name_id, utf8_string_id),
+    }
+    .or_else(|| {
+        get_window_property_bytes(
+            display_ptr,
+            window_id,
+            x11::xlib::XA_WM_NAME,
+            x11::xlib::XA_STRING,
+        )
+    });
+
+    match title_bytes {
+        Some((8, bytes)) => {
+            let title = String::from_utf8_lossy(&bytes).trim().to_string();
+            match title.is_empty() {
+                true => None,
+                false => Some(title),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Get the title of the window with focus, such as the name of the
+/// document or web page.
+///
+/// The focus may be given to a child of the window with the title,
+/// and window managers may re-parent windows into a frame, so the
+/// parent windows are searched until a title is found.
+pub fn get_active_window_title_from_x11(display_name: Option<&str>) -> Result<Option<String>> {
+    // Get X11 Display.
+    let display_ptr = open_display(display_name);
+    if display_ptr.is_null() {
+        return Ok(None);
+    }
+
+    let net_wm_name_id = get_atom_id(display_ptr, c"_NET_WM_NAME");
+    let utf8_string_id = get_atom_id(display_ptr, c"UTF8_STRING");
+
+    unsafe {
+        x11::xlib::XSetErrorHandler(Some(handle_error_callback));
+    }
+
+    let mut window_title = None;
+    let mut window_id = get_window_id_with_focus(display_ptr);
+    let mut root_window_id = unsafe { x11::xlib::XDefaultRootWindow(display_ptr) };
+    // 'PointerRoot' (1) and 'None' (0) are not real windows.
+    while window_id > 1 && window_id != root_window_id {
+        window_title = get_window_title(display_ptr, window_id, net_wm_name_id, utf8_string_id);
+        if window_title.is_some() || unsafe { X11_ERROR == XError::Failure } {
+            break;
+        }
+
+        let mut parent_window_id = 0 as c_ulong;
+        let mut child_window_ids = std::ptr::null_mut::<c_ulong>();
+        let mut child_count = 0 as c_uint;
+        let status: i32 = unsafe {
+            x11::xlib::XQueryTree(
+                display_ptr,
+                window_id,
+                &mut root_window_id,
+                &mut parent_window_id,
+                &mut child_window_ids,
+                &mut child_count,
+            )
+        };
+        if status == 0 {
+            break;
+        }
+        unsafe {
+            x11::xlib::XFree(child_window_ids as *mut c_void);
+        };
+        window_id = parent_window_id;
+    }
+
+    unsafe {
+        x11::xlib::XSetErrorHandler(None);
+        if X11_ERROR == XError::Failure {
+            X11_ERROR = XError::Success;
+            window_title = None;
+        }
+    }
+
+    // Close the X11 display.
+    unsafe { x11::xlib::XCloseDisplay(display_ptr) };
+
+    Ok(window_title)
+}
@@ -1,116 +1,365 @@
-use crate::linux_process::find_process_ids_by_user_and_executable_name;
+use crate::active_time::read_today_active_seconds;
+use crate::buffer_status::get_status_file_path;
+use crate::buffer_status::read_buffer_status;
+use crate::buffer_status::write_buffer_status;
+use crate::buffer_status::BufferStatus;
+use crate::control::get_control_socket_path;
+use crate::control::remove_control_socket;
+use crate::control::send_control_command;
+use crate::control::start_control_listener;
+use crate::control::ControlCommand;
+use crate::entry_buffer::EntryBuffer;
+use crate::executable_version::ExecutableVersionCache;
+use crate::host_busy::HostBusyDetector;
+use crate::ignore::IgnoreRules;
+use crate::instance_lock::get_lock_file_path;
+use crate::instance_lock::read_locked_process_id;
+use crate::instance_lock::InstanceLock;
+use crate::journal::get_buffer_file_path;
+use crate::journal::get_journal_file_path;
+use crate::journal::move_buffer_file_to_journal;
+use crate::journal::read_journal_entries;
+use crate::journal::remove_journal_entries;
+use crate::linux_process::get_hostname;
 use crate::linux_process::get_process_id_executable_name;
-use crate::linux_process::get_user_id_running_process_id;
+use crate::linux_process::get_process_id_executable_path;
+use crate::linux_process::get_process_id_uptime_seconds;
+use crate::linux_process::get_process_id_working_directory;
+use crate::linux_process::get_username;
 use crate::linux_process::read_process_environment_variables;
 use crate::linux_process::terminate_processes;
 use crate::linux_signal::install_signal_handler;
+use crate::notifier::notify_storage_write_status;
+use crate::notifier::Notifier;
+use crate::power::PowerMonitor;
+use crate::power::SuspendDetector;
+use crate::recently_used::RecentlyUsedDocuments;
+use crate::recorder_state::RecorderState;
+use crate::sample_clock::SampleClock;
+use crate::screen_lock::ScreenLockDetector;
 use crate::settings::CommandArguments;
 use crate::settings::CommandModes;
 use crate::settings::RecorderAppSettings;
-use anyhow::{bail, Result};
+use crate::supervisor::supervise_worker;
+use crate::terminal_multiplexer::TerminalMultiplexerCache;
+use crate::tray::start_tray_icon;
+use crate::variable_filter::VariableFilters;
+use crate::window_color::WindowColorSampler;
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::TimeZone;
 use clap::Parser;
 use log::{debug, error, info, warn};
-use once_cell::sync::Lazy;
+use std::collections::HashMap;
 use std::path::Path;
 use std::path::PathBuf;
 use std::sync;
-use std::sync::Mutex;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicI32;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
 use std::thread;
 use std::time;
+use timetracker_core::entries::find_recording_gap;
 use timetracker_core::entries::Entry;
 use timetracker_core::entries::EntryStatus;
 use timetracker_core::entries::EntryVariablesList;
+use timetracker_core::entries::HostBusyPeriod;
+use timetracker_core::entries::WindowColorPeriod;
+use timetracker_core::entries::IGNORED_EXECUTABLE_NAME;
+use timetracker_core::entries::RECORDING_GAP_INTERVAL_COUNT;
 use timetracker_core::filesystem::get_database_file_path;
-use timetracker_core::settings::RECORD_INTERVAL_SECONDS;
-use timetracker_core::settings::USER_IS_IDLE_LIMIT_SECONDS;
+use timetracker_core::filesystem::get_database_file_path_for_time;
+use timetracker_core::format::format_duration;
+use timetracker_core::format::parse_duration;
+use timetracker_core::format::parse_local_datetime;
+use timetracker_core::format::DurationFormat;
+use timetracker_core::format::IgnoreAction;
+use timetracker_core::http_storage::HttpStorage;
+use timetracker_core::settings::DEFAULT_RECORD_INTERVAL_SECONDS;
 use timetracker_core::storage::Storage;
+use timetracker_core::storage::StorageBackend;
 
+mod active_time;
+mod buffer_status;
+#[cfg(target_os = "linux")]
+mod executable_version;
+#[cfg(target_os = "linux")]
+mod host_busy;
 #[cfg(target_os = "linux")]
 mod linux_process;
 #[cfg(target_os = "linux")]
 mod linux_signal;
 #[cfg(target_os = "linux")]
 mod linux_x11;
+#[cfg(target_os = "linux")]
+mod power;
+#[cfg(target_os = "linux")]
+mod screen_lock;
+#[cfg(target_os = "linux")]
+mod supervisor;
+#[cfg(target_os = "linux")]
+mod terminal_multiplexer;
+#[cfg(target_os = "linux")]
+mod window_color;
 
+mod control;
+mod entry_buffer;
+mod ignore;
+mod instance_lock;
+mod journal;
+mod notifier;
+mod recently_used;
+mod recorder_state;
+mod sample_clock;
 mod settings;
+mod tray;
+mod variable_filter;
 
 /// How many enties are stored in memory before being saved to the
 /// storage.
 const ENTRY_BUFFER_MAX_COUNT: usize = 10;
 
-/// The global buffer of entries stored in memory, waiting to be
-/// written to storage.
-static mut ENTRY_BUFFER: Lazy<Mutex<Vec<Entry>>> = Lazy::new(|| Mutex::new(vec![]));
+/// How long to wait before writing to storage again, after a write
+/// has failed.
+const FAILED_WRITE_RETRY_DELAY_SECONDS: u64 = 30;
+
+/// How long to wait for a write to storage (by the storage thread)
+/// to finish, before the buffered entries are written when exiting.
+const EXIT_WRITE_WAIT_SECONDS: u64 = 10;
 
-/// The global status of the user; Is the user active or idle?
-static mut ENTRY_STATUS: EntryStatus = EntryStatus::Uninitialized;
+/// The number of the signal (such as 'SIGTERM') the process was given
+/// to terminate, or 0 when no signal has been given. Set by the
+/// signal handler (named "handle_signal") and checked by the
+/// recording loop, which writes the buffered entries and exits.
+static EXIT_SIGNAL_NUMBER: AtomicI32 = AtomicI32::new(0);
 
-/// The database file path is stored so the signal handler clean up
-/// function (named "handle_signal") can use it to write data to to
-/// the database when exiting the process.
-static mut CLEANUP_DATABASE_FILE_PATH: Lazy<Mutex<PathBuf>> =
-    Lazy::new(|| Mutex::new(PathBuf::new()));
+/// Set when the process is given 'SIGHUP', by the signal handler
+/// (named "handle_reload_signal"), and checked by the recording loop,
+/// which reloads the settings.
+static RELOAD_SIGNAL_RECEIVED: AtomicBool = AtomicBool::new(false);
 
 /// The name of this executable file name.
 const THIS_EXECUTABLE_NAME: &str = "timetracker-recorder";
 
-/// Writes data to the database, and retries multiple times until
+/// Get the database file path to write to now, which changes at the
+/// year boundary when 'database_per_year' is enabled.
+fn get_current_database_file_path(database_file_path: &Path, database_per_year: bool) -> PathBuf {
+    let utc_time_seconds = chrono::Utc::now().timestamp() as u64;
+    get_database_file_path_for_time(database_file_path, database_per_year, utc_time_seconds)
+}
+
+/// Create a variables list with the (first five) variable names to
+/// record, without any values.
+fn new_variables_list(names: &[String]) -> EntryVariablesList {
+    let mut vars = EntryVariablesList::empty();
+    let name_count = names.len();
+    if name_count > 0 {
+        vars.var1_name = Some(names[0].clone());
+    }
+    if name_count > 1 {
+        vars.var2_name = Some(names[1].clone());
+    }
+    if name_count > 2 {
+        vars.var3_name = Some(names[2].clone());
+    }
+    if name_count > 3 {
+        vars.var4_name = Some(names[3].clone());
+    }
+    if name_count > 4 {
+        vars.var5_name = Some(names[4].clone());
+    }
+    vars
+}
+
+/// Writes the buffered entries (after any entries in the journal
+/// file) to the database.
+///
+/// When the write fails the entries are put back into the buffer, to
+/// be written later. Returns the written entries.
+fn write_data_to_storage(
+    state: &RecorderState,
+    database_file_path: &Path,
+    database_per_year: bool,
+    max_merged_entry_seconds: u64,
+) -> Result<Vec<Entry>> {
+    // The journal is read while the buffer is locked, so no entries
+    // are spilled between reading the journal and taking the
+    // entries, and the entries are written in order.
+    let (journal_entries, journal_byte_count, entries) = {
+        let mut data = state.entry_buffer.lock().unwrap();
+        let (journal_entries, journal_byte_count) =
+            match read_journal_entries(data.journal_file_path()) {
+                Ok(value) => value,
+                Err(err) => {
+                    error!(
+                        "Could not read journal file {:?}. {:?}",
+                        data.journal_file_path(),
+                        err
+                    );
+                    (Vec::new(), 0)
+                }
+            };
+        (journal_entries, journal_byte_count, data.take_entries())
+    };
+
+    let result = write_entries_to_storage(
+        database_file_path,
+        database_per_year,
+        max_merged_entry_seconds,
+        state.record_interval_seconds(),
+        &journal_entries,
+        &entries,
+        Some(state),
+    );
+
+    let mut data = state.entry_buffer.lock().unwrap();
+    match result {
+        Ok(()) => {
+            if !journal_entries.is_empty() {
+                info!(
+                    "Written {} entries from the journal file to storage.",
+                    journal_entries.len()
+                );
+            }
+            if let Err(err) = remove_journal_entries(data.journal_file_path(), journal_byte_count) {
+                error!(
+                    "Could not remove written entries from journal file {:?}. {:?}",
+                    data.journal_file_path(),
+                    err
+                );
+            }
+            data.finish_taken_entries();
+            let mut written_entries = journal_entries;
+            written_entries.extend(entries);
+            Ok(written_entries)
+        }
+        Err(err) => {
+            data.restore_entries(entries);
+            Err(err)
+        }
+    }
+}
+
+/// Send the written entries to the sync server. Entries that could
+/// not be sent are kept, and sent with the next entries.
+fn sync_entries_to_server(
+    sync_storage: &mut HttpStorage,
+    entries: &[Entry],
+    max_pending_entry_count: usize,
+) {
+    sync_storage.insert_entries(entries);
+    if let Err(err) = sync_storage.write_entries() {
+        warn!(
+            "Could not send {} entries to the sync server, will retry later. {:?}",
+            sync_storage.pending_entry_count(),
+            err
+        );
+        let dropped_entry_count = sync_storage.limit_pending_entries(max_pending_entry_count);
+        if dropped_entry_count > 0 {
+            warn!(
+                "Dropped {} entries not sent to the sync server.",
+                dropped_entry_count
+            );
+        }
+    }
+}
+
+/// Writes entries to the database, and retries multiple times until
 /// success can be made, or a timer runs out.
-fn write_data_to_storage(database_file_path: &Path) -> Result<()> {
+///
+/// The periods buffered in the recorder 'state' (if given) are
+/// written with the entries.
+fn write_entries_to_storage(
+    database_file_path: &Path,
+    database_per_year: bool,
+    max_merged_entry_seconds: u64,
+    record_interval_seconds: u64,
+    journal_entries: &[Entry],
+    entries: &[Entry],
+    state: Option<&RecorderState>,
+) -> Result<()> {
     let now = time::SystemTime::now();
+    let database_file_path = get_current_database_file_path(database_file_path, database_per_year);
 
     let mut wait_duration = time::Duration::from_millis(1);
     // 8 seconds is chosen to stop the storage attempts before the
     // next round of storage read/write attempts are made.
     let total_allowed_wait_seconds =
-        ((RECORD_INTERVAL_SECONDS as f32 * ENTRY_BUFFER_MAX_COUNT as f32) * 0.8) as u64;
+        ((record_interval_seconds as f32 * ENTRY_BUFFER_MAX_COUNT as f32) * 0.8) as u64;
     let total_allowed_wait_duration = time::Duration::from_secs(total_allowed_wait_seconds);
     let total_allowed_attempts = 10;
     for attempt_number in 0..=(total_allowed_attempts + 1) {
         if attempt_number > 0 {
             error!("Attempt #{}.", attempt_number);
 
-            let mut do_exit = false;
             if attempt_number >= total_allowed_attempts {
-                error!("All {} attempts failed. Exiting.", attempt_number);
-                do_exit = true;
+                bail!("All {} attempts failed.", attempt_number);
             }
             let has_waited = now.elapsed()?;
             if has_waited > total_allowed_wait_duration {
-                error!(
-                    "Running {} attempts has taken longer than {:?}. Exiting...",
-                    attempt_number, total_allowed_wait_duration
+                bail!(
+                    "Running {} attempts has taken longer than {:?}.",
+                    attempt_number,
+                    total_allowed_wait_duration
                 );
-                do_exit = true;
-            }
-            if do_exit {
-                // This will stop the full program, along with all
-                // threads (including the main thread).
-                std::process::abort();
             }
 
             thread::sleep(wait_duration);
             wait_duration += wait_duration * 2;
         }
 
-        let storage = Storage::open_as_read_write(database_file_path, RECORD_INTERVAL_SECONDS);
+        let storage = Storage::open_as_read_write(&database_file_path, record_interval_seconds);
         if let Err(err) = storage {
             error!("Could not open storage. {:?}", err);
             continue;
         }
         let mut storage = storage?;
+        storage.set_max_merged_entry_seconds(max_merged_entry_seconds);
+        storage.set_optimize_on_close(true);
 
-        unsafe {
-            let mut data = ENTRY_BUFFER.lock().unwrap();
-            storage.insert_entries(&data);
-            let _ = &data.clear();
-        }
+        storage.insert_entries(journal_entries);
+        storage.insert_entries(entries);
         let write_result = storage.write_entries();
         if let Err(err) = write_result {
             error!("Could not write to storage. {:#?}", err);
+            if let Err(err) = storage.close() {
+                error!("Could not close storage. {:#?}", err);
+            }
             continue;
         }
-        storage.close();
+
+        if let Some(state) = state {
+            let host_busy_periods: Vec<HostBusyPeriod> = {
+                let mut data = state.host_busy_periods.lock().unwrap();
+                data.drain(..).collect()
+            };
+            if !host_busy_periods.is_empty() {
+                if let Err(err) = storage.write_host_busy_periods(&host_busy_periods) {
+                    error!("Could not write host busy periods to storage. {:#?}", err);
+                    // Put the periods back (before any newer periods),
+                    // to be written with the next entries.
+                    let mut data = state.host_busy_periods.lock().unwrap();
+                    data.splice(0..0, host_busy_periods);
+                }
+            }
+
+            let window_color_periods: Vec<WindowColorPeriod> = {
+                let mut data = state.window_color_periods.lock().unwrap();
+                data.drain(..).collect()
+            };
+            if !window_color_periods.is_empty() {
+                if let Err(err) = storage.write_window_color_periods(&window_color_periods) {
+                    error!("Could not write window colors to storage. {:#?}", err);
+                    let mut data = state.window_color_periods.lock().unwrap();
+                    data.splice(0..0, window_color_periods);
+                }
+            }
+        }
+        // The entries have already been written, so closing is not
+        // retried; that would write the entries again.
+        if let Err(err) = storage.close() {
+            error!("Could not close storage after writing. {:#?}", err);
+        }
 
         if attempt_number == 0 {
             debug!("Successfully written to storage.");
@@ -126,26 +375,165 @@ fn write_data_to_storage(database_file_path: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Detects the versions of the executables seen since the last call,
+/// and writes them to the database.
+///
+/// Version detection may be slow (it can run other processes), so
+/// this must not be called from the thread recording activity.
+fn write_executable_versions_to_storage(
+    state: &RecorderState,
+    database_file_path: &Path,
+    database_per_year: bool,
+    cache: &mut ExecutableVersionCache,
+) -> Result<()> {
+    let executable_paths: Vec<(PathBuf, u64)> = {
+        let mut data = state.pending_executable_paths.lock().unwrap();
+        data.drain().collect()
+    };
+    if executable_paths.is_empty() {
+        return Ok(());
+    }
+
+    let mut executable_versions = Vec::with_capacity(executable_paths.len());
+    for (executable_path, utc_time_seconds) in executable_paths {
+        match cache.get_executable_version(&executable_path, utc_time_seconds) {
+            Ok(executable_version) => executable_versions.push(executable_version),
+            Err(err) => warn!(
+                "Could not get executable version: path={:?} err={:?}",
+                executable_path, err
+            ),
+        }
+    }
+
+    let database_file_path = get_current_database_file_path(database_file_path, database_per_year);
+    let mut storage =
+        Storage::open_as_read_write(&database_file_path, DEFAULT_RECORD_INTERVAL_SECONDS)?;
+    for executable_version in &executable_versions {
+        storage.write_executable_version(executable_version)?;
+    }
+    storage.close()?;
+
+    Ok(())
+}
+
 /// Function that gets called when this process is given a signal
 /// (such as 'SIGINT' number 2 or 'SIGTERM' number 15) and told to
 /// terminate.
+///
+/// Only async-signal-safe work can be done in a signal handler (no
+/// locks, memory allocation or logging), so the handler only stores
+/// the signal number, and the recording loop exits.
 extern "C" fn handle_signal(signal_number: libc::c_int) {
-    warn!("Received signal {}, exiting gracefully...", signal_number);
+    EXIT_SIGNAL_NUMBER.store(signal_number, Ordering::SeqCst);
+}
 
-    let database_file_path = unsafe { &CLEANUP_DATABASE_FILE_PATH.lock().unwrap() };
-    write_data_to_storage(database_file_path).unwrap();
+/// Function that gets called when this process is given 'SIGHUP',
+/// asking the recorder to reload the configuration file.
+extern "C" fn handle_reload_signal(_signal_number: libc::c_int) {
+    RELOAD_SIGNAL_RECEIVED.store(true, Ordering::SeqCst);
+}
 
-    // This will stop the full program, along with all threads
-    // (including the main thread).
-    std::process::abort();
+/// Read the settings again (from the configuration file and the
+/// environment), for the recording loop to use without restarting.
+///
+/// The settings are only used if all of them are valid, so a mistake
+/// in the configuration file never leaves the recorder with half of
+/// the new settings.
+fn reload_settings(
+    args: &CommandArguments,
+) -> Result<(RecorderAppSettings, VariableFilters, IgnoreRules)> {
+    let settings = RecorderAppSettings::new(args)?;
+    let variable_filters = VariableFilters::new(&settings.variable_filters)?;
+    let ignore_rules = IgnoreRules::new(&settings.recorder.ignore)?;
+    Ok((settings, variable_filters, ignore_rules))
 }
 
-/// Run to start recording activity.
-fn start_recording(
-    _args: &CommandArguments,
-    settings: RecorderAppSettings,
+/// Write the buffered entries before the process exits, after any
+/// write in progress by the storage thread has finished.
+fn write_data_before_exit(
+    state: &RecorderState,
+    database_file_path: &Path,
+    database_per_year: bool,
+    max_merged_entry_seconds: u64,
+) {
+    let wait_start = time::Instant::now();
+    while state.write_pending.load(Ordering::SeqCst)
+        && wait_start.elapsed() < time::Duration::from_secs(EXIT_WRITE_WAIT_SECONDS)
+    {
+        thread::sleep(time::Duration::from_millis(50));
+    }
+
+    if let Err(err) = write_data_to_storage(
+        state,
+        database_file_path,
+        database_per_year,
+        max_merged_entry_seconds,
+    ) {
+        error!("Could not write to storage before exiting. {:?}", err);
+        let mut data = state.entry_buffer.lock().unwrap();
+        match data.has_buffer_file() {
+            true => warn!(
+                "Keeping {} entries in the buffer file, to be written when the recorder next starts.",
+                data.len()
+            ),
+            false => data.overflow_all_entries(),
+        }
+    }
+}
+
+/// How long to wait for a running recorder to stop, when it is
+/// terminated by a new recorder.
+const TERMINATE_WAIT_SECONDS: u64 = 30;
+
+/// Take the lock of the running recorder, terminating the recorder
+/// holding the lock if 'terminate_existing_processes' is true.
+///
+/// Returns None when another recorder is running (and was not
+/// terminated), so a new recorder must not be started. The lock must
+/// be held while recording.
+fn acquire_instance_lock(
+    lock_file_path: &Path,
     terminate_existing_processes: bool,
-) -> Result<()> {
+) -> Result<Option<InstanceLock>> {
+    if let Some(instance_lock) = InstanceLock::acquire(lock_file_path)? {
+        return Ok(Some(instance_lock));
+    }
+    let process_id = read_locked_process_id(lock_file_path)?;
+    if !terminate_existing_processes {
+        error!(
+            "{} is already running, with process id {:?} (lock file {:?}).",
+            THIS_EXECUTABLE_NAME, process_id, lock_file_path
+        );
+        error!("Rerun with --terminate-existing-processes flag to stop the running recorder.");
+        return Ok(None);
+    }
+
+    if let Some(process_id) = process_id {
+        terminate_processes(&vec![process_id])?;
+    }
+    // The running recorder writes the buffered entries before
+    // exiting, which releases the lock.
+    let end_time = time::Instant::now() + time::Duration::from_secs(TERMINATE_WAIT_SECONDS);
+    while time::Instant::now() < end_time {
+        thread::sleep(time::Duration::from_millis(100));
+        if let Some(instance_lock) = InstanceLock::acquire(lock_file_path)? {
+            return Ok(Some(instance_lock));
+        }
+    }
+    bail!(
+        "The running {} (process id {:?}) did not stop after {} seconds.",
+        THIS_EXECUTABLE_NAME,
+        process_id,
+        TERMINATE_WAIT_SECONDS
+    )
+}
+
+/// Run to start recording activity.
+///
+/// The lock of the running recorder must be held (with
+/// 'acquire_instance_lock') before this function, by this process or
+/// the supervisor process.
+fn start_recording(args: &CommandArguments, mut settings: RecorderAppSettings) -> Result<()> {
     println!("Starting Time Tracker Recorder...");
 
     let database_file_path = get_database_file_path(
@@ -155,48 +543,86 @@ fn start_recording(
     .expect("Database file path should be valid");
     println!("Database file: {:?}", database_file_path);
 
-    // Store a copy of the database file path in static memory, so the
-    // "handle_signal" function can use it.
-    unsafe {
-        let mut cleanup_database_file_path = CLEANUP_DATABASE_FILE_PATH.lock().unwrap();
-        *cleanup_database_file_path = database_file_path.clone();
-    };
-
-    // Signal handlers allow us to clean up and write data to the
-    // database before the process shuts down.
-    install_signal_handler(libc::SIGINT, handle_signal as usize);
-    install_signal_handler(libc::SIGTERM, handle_signal as usize);
+    let max_merged_entry_seconds = settings.storage.max_merged_entry_seconds;
+    let record_interval_seconds = settings.recorder.record_interval_seconds;
+    let database_per_year = settings.core.database_per_year;
+    if database_per_year {
+        println!(
+            "Database file for this year: {:?}",
+            get_current_database_file_path(&database_file_path, database_per_year)
+        );
+    }
 
-    let this_process_id = std::process::id();
-    let this_user_id = get_user_id_running_process_id(this_process_id)?;
-    let running_process_ids = find_process_ids_by_user_and_executable_name(
-        THIS_EXECUTABLE_NAME,
-        this_user_id,
-        this_process_id,
-    )?;
-    if !running_process_ids.is_empty() {
-        if terminate_existing_processes {
-            terminate_processes(&running_process_ids)?;
-        } else {
-            error!(
-                "{} is already running, found running process ids {:?}.",
-                THIS_EXECUTABLE_NAME, running_process_ids
+    let journal_file_path =
+        get_journal_file_path(&settings.recorder.journal_file_path, &database_file_path);
+    println!("Journal file: {:?}", journal_file_path);
+    let buffer_file_path = get_buffer_file_path(&journal_file_path);
+    // The entries left in the buffer file by a recorder that did not
+    // exit cleanly are written to storage (with the journal file)
+    // before recording starts.
+    let recovered_entry_count = move_buffer_file_to_journal(&buffer_file_path, &journal_file_path)
+        .with_context(|| format!("Could not recover buffer file {:?}.", buffer_file_path))?;
+    let status_file_path = get_status_file_path(&journal_file_path);
+    let control_socket_path = get_control_socket_path(&journal_file_path);
+    let mut entry_buffer = EntryBuffer::new();
+    entry_buffer.configure(
+        settings
+            .recorder
+            .buffer_max_entries
+            .max(ENTRY_BUFFER_MAX_COUNT)
+            .max(settings.recorder.power.battery_write_entry_count),
+        settings.recorder.buffer_overflow_policy,
+        journal_file_path,
+        match settings.recorder.crash_safe_buffer {
+            true => Some(buffer_file_path),
+            false => None,
+        },
+    );
+    let state = Arc::new(RecorderState::new(entry_buffer, record_interval_seconds));
+    if recovered_entry_count > 0 {
+        println!(
+            "Recovered {} entries not written by the last recorder.",
+            recovered_entry_count
+        );
+        if let Err(err) = write_data_to_storage(
+            &state,
+            &database_file_path,
+            database_per_year,
+            max_merged_entry_seconds,
+        ) {
+            warn!(
+                "Could not write the recovered entries to storage, will retry later. {:?}",
+                err
             );
-            error!("Rerun with --terminate-existing-processes flag to kill the running processes.");
-            return Ok(());
         }
     }
 
-    // TODO: When this this function is meant to go into a loop and
-    // query X11, instead we should make a child process that queries
-    // the X11 stuff, because it can be a little unstable in weird
-    // edge cases (that can happen on KDE). Therefore we should start
-    // a new child process that does the real work, and this
-    // ("parent") process will wait for the child-process to exit then
-    // re-run the same command when it errors. This will mean that no
-    // matter what happens the recorder will always be restarted if a
-    // panic happens.
+    // Recording still works without the control socket, only pausing
+    // is unavailable.
+    let has_control_socket = match start_control_listener(&control_socket_path, state.clone()) {
+        Ok(()) => true,
+        Err(err) => {
+            warn!("Could not listen for control commands. {:?}", err);
+            false
+        }
+    };
+
+    // Invalid filters are an error, rather than ignored, so secrets
+    // are never recorded by mistake.
+    let mut variable_filters = VariableFilters::new(&settings.variable_filters)?;
+    let mut ignore_rules = IgnoreRules::new(&settings.recorder.ignore)?;
+
+    // Signal handlers allow us to clean up and write data to the
+    // database before the process shuts down.
+    install_signal_handler(libc::SIGINT, handle_signal as *const () as usize);
+    install_signal_handler(libc::SIGTERM, handle_signal as *const () as usize);
+    install_signal_handler(libc::SIGHUP, handle_reload_signal as *const () as usize);
 
+    // NOTE: Querying X11 can be a little unstable in weird edge
+    // cases (that can happen on KDE), so by default this function
+    // runs in a worker (child) process, restarted by the supervisor
+    // ("parent") process when it exits abnormally. See
+    // 'supervise_worker'.
     gtk::init()?;
 
     let (tx, rx) = sync::mpsc::channel();
@@ -204,53 +630,265 @@ fn start_recording(
     // A second thread is used to avoid a congested/slow storage
     // read/write from slowing down or messing up the recording of
     // user activity, and causing instability or a panic.
-    thread::spawn(move || loop {
-        rx.recv()
-            .expect("Should have recieved a value from the main thread.");
-        write_data_to_storage(&database_file_path).unwrap();
-    });
+    let record_executable_versions = settings.recorder.record_executable_versions;
+    let mut sync_storage = match settings.sync.enabled {
+        true => {
+            println!("Sync server: {}", settings.sync.host);
+            Some(HttpStorage::new(&settings.sync.host, &settings.sync.token))
+        }
+        false => None,
+    };
+    let max_sync_pending_entry_count = settings.recorder.buffer_max_entries;
+    let storage_state = state.clone();
+    let storage_database_file_path = database_file_path.clone();
+    let storage_notifications_settings = settings.notifications.clone();
+    thread::spawn(move || {
+        let state = storage_state;
+        let database_file_path = storage_database_file_path;
+        let mut executable_version_cache = ExecutableVersionCache::new();
+        let mut buffer_status = BufferStatus::default();
+        loop {
+            rx.recv()
+                .expect("Should have recieved a value from the main thread.");
+            let result = write_data_to_storage(
+                &state,
+                &database_file_path,
+                database_per_year,
+                max_merged_entry_seconds,
+            );
+            let now_seconds = chrono::Utc::now().timestamp() as u64;
+            let previous_failed_write_count = buffer_status.failed_write_count;
+            match result {
+                Ok(written_entries) => {
+                    buffer_status.failed_write_count = 0;
+                    buffer_status.last_write_utc_time_seconds = Some(now_seconds);
+                    if let Some(sync_storage) = sync_storage.as_mut() {
+                        sync_entries_to_server(
+                            sync_storage,
+                            &written_entries,
+                            max_sync_pending_entry_count,
+                        );
+                    }
+                }
+                Err(err) => {
+                    buffer_status.failed_write_count += 1;
+                    error!(
+                        "Could not write to storage ({} failures in a row), entries are kept in memory. {:?}",
+                        buffer_status.failed_write_count, err
+                    );
+                }
+            }
 
-    let record_interval_seconds = RECORD_INTERVAL_SECONDS;
-    let user_is_idle_limit_seconds = USER_IS_IDLE_LIMIT_SECONDS;
-    let interval_seconds = record_interval_seconds.try_into()?;
-    let _source_id = glib::source::timeout_add_seconds_local(interval_seconds, move || {
-        let idle_time_sec = linux_x11::get_user_idle_time_from_x11();
-        if idle_time_sec > user_is_idle_limit_seconds {
-            unsafe {
-                ENTRY_STATUS = EntryStatus::Idle;
+            notify_storage_write_status(
+                &storage_notifications_settings,
+                previous_failed_write_count,
+                buffer_status.failed_write_count,
+            );
+
+            buffer_status.utc_time_seconds = now_seconds;
+            buffer_status.entry_status = Some(state.entry_status());
+            {
+                let data = state.entry_buffer.lock().unwrap();
+                buffer_status.buffered_entry_count = data.len();
+                buffer_status.dropped_entry_count = data.dropped_entry_count();
+                buffer_status.spilled_entry_count = data.spilled_entry_count();
+                buffer_status.journal_entry_count = read_journal_entries(data.journal_file_path())
+                    .map(|(entries, _)| entries.len())
+                    .unwrap_or(0);
             }
-        } else {
-            unsafe {
-                ENTRY_STATUS = EntryStatus::Active;
+            if let Err(err) = write_buffer_status(&status_file_path, &buffer_status) {
+                warn!(
+                    "Could not write status file {:?}. {:?}",
+                    status_file_path, err
+                );
+            }
+
+            if buffer_status.failed_write_count > 0 {
+                thread::sleep(time::Duration::from_secs(FAILED_WRITE_RETRY_DELAY_SECONDS));
+            }
+            state.write_pending.store(false, Ordering::SeqCst);
+
+            if record_executable_versions {
+                let result = write_executable_versions_to_storage(
+                    &state,
+                    &database_file_path,
+                    database_per_year,
+                    &mut executable_version_cache,
+                );
+                if let Err(err) = result {
+                    error!("Could not write executable versions to storage. {:?}", err);
+                }
             }
         }
+    });
 
-        let mut env_var_list = EntryVariablesList::empty();
-        let name_count = settings.core.environment_variables.names.len();
-        if name_count > 0 {
-            env_var_list.var1_name = Some(settings.core.environment_variables.names[0].clone());
+    let mut user_is_idle_limit_seconds = settings.recorder.user_is_idle_limit_seconds;
+    let interval_seconds = record_interval_seconds.try_into()?;
+    let mut sample_clock = SampleClock::new(
+        record_interval_seconds,
+        settings.recorder.compensate_sampling_jitter,
+    );
+    let mut last_utc_offset_seconds: Option<i32> = None;
+    let hostname = match settings.recorder.record_hostname {
+        true => get_hostname()
+            .map_err(|err| warn!("Could not get the hostname: err={:?}", err))
+            .ok(),
+        false => None,
+    };
+    let username = match settings.recorder.record_username {
+        true => get_username()
+            .map_err(|err| warn!("Could not get the username: err={:?}", err))
+            .ok(),
+        false => None,
+    };
+    let mut host_busy_detector = match settings.recorder.record_host_busy {
+        true => Some(HostBusyDetector::new(
+            settings.recorder.host_busy_load_average,
+            settings.recorder.host_busy_executable_names.clone(),
+        )),
+        false => None,
+    };
+    let mut screen_lock_detector = match settings.recorder.record_screen_lock {
+        true => Some(ScreenLockDetector::new()),
+        false => None,
+    };
+    let mut window_color_sampler = match settings.recorder.record_window_color {
+        true => Some(WindowColorSampler::new()),
+        false => None,
+    };
+    let mut recently_used_documents = match settings.recorder.record_document_path {
+        true => Some(RecentlyUsedDocuments::new()),
+        false => None,
+    };
+    let mut notifier = Notifier::new(settings.notifications.clone());
+    let mut power_monitor = PowerMonitor::new();
+    let mut suspend_detector = SuspendDetector::new();
+    let mut terminal_multiplexer_cache = TerminalMultiplexerCache::new();
+    let mut last_sample_instant: Option<time::Instant> = None;
+    let mut last_sample_end_seconds: Option<u64> = None;
+    let tray_icon_enabled = settings.recorder.tray_icon;
+    let timer_args = args.clone();
+    let timer_database_file_path = database_file_path.clone();
+    let timer_state = state.clone();
+    let _source_id = glib::source::timeout_add_seconds_local(interval_seconds, move || {
+        let state = &timer_state;
+        if EXIT_SIGNAL_NUMBER.load(Ordering::SeqCst) != 0 {
+            gtk::main_quit();
+            return glib::ControlFlow::Break;
         }
-        if name_count > 1 {
-            env_var_list.var2_name = Some(settings.core.environment_variables.names[1].clone());
+
+        // Only the settings read by each sample are reloaded; the
+        // database, interval and detector settings (and so on) are
+        // used when the recorder starts.
+        if RELOAD_SIGNAL_RECEIVED.swap(false, Ordering::SeqCst) || state.take_reload_request() {
+            match reload_settings(&timer_args) {
+                Ok((new_settings, new_variable_filters, new_ignore_rules)) => {
+                    settings = new_settings;
+                    variable_filters = new_variable_filters;
+                    ignore_rules = new_ignore_rules;
+                    user_is_idle_limit_seconds = settings.recorder.user_is_idle_limit_seconds;
+                    info!(
+                        "Reloaded settings; recording environment variables: {:?}",
+                        settings.core.environment_variables.names
+                    );
+                }
+                Err(err) => error!(
+                    "Could not reload settings, using the current settings. {:?}",
+                    err
+                ),
+            }
         }
-        if name_count > 2 {
-            env_var_list.var3_name = Some(settings.core.environment_variables.names[2].clone());
+
+        // On battery the system is sampled (and written to storage)
+        // less often, by skipping the timer callbacks.
+        let is_on_battery = settings.recorder.power.enabled && power_monitor.is_on_battery();
+        let current_record_interval_seconds = match is_on_battery {
+            true => settings
+                .recorder
+                .power
+                .battery_record_interval_seconds
+                .max(record_interval_seconds),
+            false => record_interval_seconds,
+        };
+        if current_record_interval_seconds != state.record_interval_seconds() {
+            state.set_record_interval_seconds(current_record_interval_seconds);
+            sample_clock.set_record_interval_seconds(current_record_interval_seconds);
         }
-        if name_count > 3 {
-            env_var_list.var4_name = Some(settings.core.environment_variables.names[3].clone());
+        let write_entry_count = match is_on_battery {
+            true => settings.recorder.power.battery_write_entry_count.max(1),
+            false => ENTRY_BUFFER_MAX_COUNT,
+        };
+        // Half of the timer interval is allowed for the timer being
+        // called a little early.
+        let is_sample_due = last_sample_instant.is_none_or(|x| {
+            x.elapsed().as_secs_f64() + (record_interval_seconds as f64 / 2.0)
+                >= current_record_interval_seconds as f64
+        });
+        if !is_sample_due {
+            return glib::ControlFlow::Continue;
         }
-        if name_count > 4 {
-            env_var_list.var5_name = Some(settings.core.environment_variables.names[4].clone());
+        last_sample_instant = Some(time::Instant::now());
+
+        // Nothing about the user's activity is read while paused.
+        let is_paused = state.is_paused(chrono::Utc::now().timestamp() as u64);
+        // With multiple displays (or seats), the active window is
+        // read from the display the user is using.
+        let (active_display, idle_time_sec) =
+            match linux_x11::find_active_display_from_x11(&settings.recorder.displays) {
+                Some((display, idle_time_sec)) => (Some(display), idle_time_sec),
+                None => (None, linux_x11::get_user_idle_time_from_x11(None)),
+            };
+        let is_idle = idle_time_sec > user_is_idle_limit_seconds;
+        // The screen can only be locked once the user is idle, so the
+        // lock is not checked while the user is active.
+        let is_locked = match (&mut screen_lock_detector, is_idle) {
+            (Some(detector), true) => detector.is_screen_locked(),
+            (Some(detector), false) => {
+                detector.reset();
+                false
+            }
+            (None, _) => false,
+        };
+        if is_paused {
+            state.set_entry_status(EntryStatus::Paused);
+        } else if is_locked {
+            state.set_entry_status(EntryStatus::Locked);
+        } else if is_idle {
+            state.set_entry_status(EntryStatus::Idle);
+        } else {
+            state.set_entry_status(EntryStatus::Active);
         }
 
-        let process_id = linux_x11::get_active_window_process_id_from_x11().unwrap();
+        let mut env_var_list = new_variables_list(&settings.core.environment_variables.names);
+        env_var_list.hostname = hostname.clone();
+        env_var_list.username = username.clone();
+        env_var_list.display = active_display.clone();
+
+        let process_id = match is_paused {
+            true => 0,
+            false => {
+                linux_x11::get_active_window_process_id_from_x11(active_display.as_deref()).unwrap()
+            }
+        };
         debug!("Process ID: {:?}", process_id);
+        let mut is_ignored = false;
         match process_id {
             0 => (),
             _ => {
                 let environ_vars = read_process_environment_variables(process_id);
                 match environ_vars {
-                    Ok(env_vars) => {
+                    Ok(mut env_vars) => {
+                        if settings.recorder.record_terminal_multiplexer {
+                            match terminal_multiplexer_cache
+                                .get_variables(process_id, time::Instant::now())
+                            {
+                                Ok(multiplexer_vars) => env_vars.extend(multiplexer_vars),
+                                Err(err) => warn!(
+                                    "Could not read terminal multiplexer variables: pid={:?} err={:?}",
+                                    process_id, err
+                                ),
+                            }
+                        }
                         env_var_list.replace_with_environ_vars(&env_vars);
                         let exec_name = get_process_id_executable_name(process_id);
                         match exec_name {
@@ -263,92 +901,679 @@ fn start_recording(
                                 env_var_list.executable = None;
                             }
                         }
+                        is_ignored = !ignore_rules.is_empty()
+                            && ignore_rules
+                                .is_ignored(env_var_list.executable.as_deref(), &env_vars);
                     }
                     Err(err) => warn!(
                         "Could not read process environment variables: pid={:?} err={:?}",
                         process_id, err
                     ),
                 }
+
+                if settings.recorder.track_process_cwd {
+                    match get_process_id_working_directory(process_id) {
+                        Ok(working_directory) => {
+                            env_var_list.working_directory =
+                                Some(working_directory.to_string_lossy().to_string())
+                        }
+                        Err(err) => warn!(
+                            "Could not get process id working directory: pid={:?} err={:?}",
+                            process_id, err
+                        ),
+                    }
+                }
+
+                if record_executable_versions && !is_ignored {
+                    match get_process_id_executable_path(process_id) {
+                        Ok(executable_path) => {
+                            let now_seconds = chrono::Utc::now().timestamp() as u64;
+                            let mut data = state.pending_executable_paths.lock().unwrap();
+                            data.insert(executable_path, now_seconds);
+                        }
+                        Err(err) => warn!(
+                            "Could not get process id executable path: pid={:?} err={:?}",
+                            process_id, err
+                        ),
+                    }
+                }
             }
         };
 
-        let now_seconds = chrono::Utc::now().timestamp() as u64;
-        debug!("Time: {:?}", now_seconds);
+        // Nothing about ignored activity is recorded, except (with
+        // the "Record" action) that the user was using the machine.
+        if is_ignored {
+            debug!("Activity is ignored: pid={:?}", process_id);
+            if ignore_rules.action == IgnoreAction::Skip {
+                if let Some((seconds, duration_seconds)) = sample_clock.sample() {
+                    last_sample_end_seconds = Some(seconds + duration_seconds);
+                }
+                return glib::ControlFlow::Continue;
+            }
+            env_var_list = new_variables_list(&settings.core.environment_variables.names);
+            env_var_list.hostname = hostname.clone();
+            env_var_list.username = username.clone();
+            env_var_list.display = active_display.clone();
+            env_var_list.executable = Some(IGNORED_EXECUTABLE_NAME.to_string());
+        }
 
-        let status = unsafe { ENTRY_STATUS };
+        if settings.recorder.record_window_title && process_id != 0 && !is_ignored {
+            match linux_x11::get_active_window_title_from_x11(active_display.as_deref()) {
+                Ok(window_title) => env_var_list.window_title = window_title,
+                Err(err) => warn!("Could not get active window title: err={:?}", err),
+            }
+        }
+
+        if let (Some(documents), Some(executable), false) = (
+            &mut recently_used_documents,
+            &env_var_list.executable,
+            is_ignored,
+        ) {
+            env_var_list.document_path = documents.document_path(executable);
+        }
 
-        let entry = Entry::new(now_seconds, record_interval_seconds, status, env_var_list);
+        // Attribute time to a project from the current workspace,
+        // when the project is not set in the environment.
+        if !is_paused && !settings.workspace_projects.is_empty() {
+            match linux_x11::get_current_workspace_name_from_x11(active_display.as_deref()) {
+                Ok(Some(workspace_name)) => {
+                    debug!("Workspace name: {:?}", workspace_name);
+                    if let Some(project) = settings.workspace_projects.get(&workspace_name) {
+                        env_var_list.set_missing_variable_value(
+                            &settings.recorder.workspace_project_variable,
+                            project,
+                        );
+                    }
+                }
+                Ok(None) => (),
+                Err(err) => warn!("Could not get current workspace name: err={:?}", err),
+            }
+        }
 
-        let entry_buffer_length = unsafe {
-            let mut data = ENTRY_BUFFER.lock().unwrap();
-            let _ = &data.push(entry);
+        if !variable_filters.is_empty() {
+            variable_filters.apply(&mut env_var_list);
+        }
+
+        let (mut sample_seconds, mut sample_duration_seconds) = match sample_clock.sample() {
+            Some(value) => value,
+            None => return glib::ControlFlow::Continue,
+        };
+        let sample_end_seconds = sample_seconds + sample_duration_seconds;
+
+        // When the wall clock jumps forward (such as when the machine
+        // was suspended), the previous entry ends where the last
+        // sample ended, and a new entry starts on resume. A known
+        // suspend is a gap however short it is.
+        let suspended_seconds = suspend_detector.take_suspended_seconds();
+        let gap_limit_seconds = match suspended_seconds {
+            Some(_) => 0,
+            None => RECORDING_GAP_INTERVAL_COUNT * current_record_interval_seconds,
+        };
+        let gap = last_sample_end_seconds.and_then(|previous_sample_end_seconds| {
+            find_recording_gap(
+                previous_sample_end_seconds,
+                sample_end_seconds,
+                current_record_interval_seconds,
+                gap_limit_seconds,
+            )
+        });
+        last_sample_end_seconds = Some(sample_end_seconds);
+        if let Some((gap_start_seconds, gap_end_seconds)) = gap {
+            sample_seconds = sample_seconds.max(gap_end_seconds);
+            sample_duration_seconds = sample_end_seconds - sample_seconds;
+            match suspended_seconds {
+                Some(suspended_seconds) => {
+                    info!("Machine was suspended for {} seconds.", suspended_seconds)
+                }
+                None => warn!(
+                    "Wall clock jumped forward {} seconds; starting a new entry.",
+                    gap_end_seconds - gap_start_seconds
+                ),
+            }
+
+            // The time the machine was suspended is recorded, rather
+            // than being a gap in the recording.
+            if suspended_seconds.is_some() && settings.recorder.record_suspend {
+                let mut suspend_env_var_list =
+                    new_variables_list(&settings.core.environment_variables.names);
+                suspend_env_var_list.hostname = hostname.clone();
+                suspend_env_var_list.username = username.clone();
+                let suspend_entry = Entry::new(
+                    gap_start_seconds,
+                    gap_end_seconds - gap_start_seconds,
+                    EntryStatus::Suspended,
+                    suspend_env_var_list,
+                )
+                .with_utc_offset_seconds(
+                    chrono::Local
+                        .timestamp_opt(gap_start_seconds as i64, 0)
+                        .earliest()
+                        .map(|x| x.offset().local_minus_utc()),
+                );
+                state.entry_buffer.lock().unwrap().push(suspend_entry);
+            }
+        }
+        debug!(
+            "Time: {:?} Duration: {:?}",
+            sample_seconds, sample_duration_seconds
+        );
+
+        if let (false, Some(detector)) = (is_paused, &mut host_busy_detector) {
+            if let Some(reason) = detector.busy_reason() {
+                debug!("Host is busy: {:?}", reason);
+                let period = HostBusyPeriod::new(sample_seconds, sample_duration_seconds, reason);
+                let mut data = state.host_busy_periods.lock().unwrap();
+                let is_extended = data
+                    .last_mut()
+                    .is_some_and(|last| last.try_extend(&period, current_record_interval_seconds));
+                if !is_extended {
+                    data.push(period);
+                }
+            }
+        }
+
+        if let (Some(sampler), Some(executable), false) = (
+            &mut window_color_sampler,
+            &env_var_list.executable,
+            is_ignored,
+        ) {
+            if let Some(color) = sampler.sample() {
+                let period = WindowColorPeriod::new(
+                    sample_seconds,
+                    sample_duration_seconds,
+                    executable.clone(),
+                    color,
+                );
+                let mut data = state.window_color_periods.lock().unwrap();
+                let is_extended = data
+                    .last_mut()
+                    .is_some_and(|last| last.try_extend(&period, current_record_interval_seconds));
+                if !is_extended {
+                    data.push(period);
+                }
+            }
+        }
+
+        // The local offset is stored with each entry, so reports can
+        // reconstruct the local day the entry was recorded in, even
+        // after the timezone changes.
+        let utc_offset_seconds = chrono::Local
+            .timestamp_opt(sample_seconds as i64, 0)
+            .earliest()
+            .map(|x| x.offset().local_minus_utc());
+        if last_utc_offset_seconds.is_some() && utc_offset_seconds != last_utc_offset_seconds {
+            warn!(
+                "Local offset from UTC changed from {:?} to {:?} seconds.",
+                last_utc_offset_seconds, utc_offset_seconds
+            );
+        }
+        last_utc_offset_seconds = utc_offset_seconds;
+
+        let status = state.entry_status();
+        notifier.add_sample(sample_seconds, status);
+        notifier.check_daily_target(sample_seconds, || {
+            read_today_active_seconds(state, &timer_database_file_path, database_per_year)
+        });
+
+        let entry = Entry::new(
+            sample_seconds,
+            sample_duration_seconds,
+            status,
+            env_var_list,
+        )
+        .with_utc_offset_seconds(utc_offset_seconds);
+
+        let entry_buffer_length = {
+            let mut data = state.entry_buffer.lock().unwrap();
+            data.push(entry);
             data.len()
         };
 
-        if entry_buffer_length == ENTRY_BUFFER_MAX_COUNT {
+        // Only one write is requested at a time, so a slow (or
+        // failing) storage does not queue up requests.
+        if entry_buffer_length >= write_entry_count
+            && !state.write_pending.swap(true, Ordering::SeqCst)
+        {
             tx.send(true).unwrap();
         }
 
         glib::ControlFlow::Continue
     });
 
+    // Quitting from the tray icon exits the same way as a signal,
+    // so the buffered entries are written.
+    let _tray_icon = match tray_icon_enabled {
+        true => Some(start_tray_icon(
+            state.clone(),
+            database_file_path.clone(),
+            database_per_year,
+            || {
+                EXIT_SIGNAL_NUMBER.store(libc::SIGTERM, Ordering::SeqCst);
+                gtk::main_quit();
+            },
+        )),
+        false => None,
+    };
+
     println!("Running Time Tracker Recorder...");
     gtk::main();
 
+    // The main loop only stops when a signal is given.
+    warn!(
+        "Received signal {}, exiting gracefully...",
+        EXIT_SIGNAL_NUMBER.load(Ordering::SeqCst)
+    );
+    write_data_before_exit(
+        &state,
+        &database_file_path,
+        database_per_year,
+        max_merged_entry_seconds,
+    );
+    if has_control_socket {
+        remove_control_socket(&control_socket_path);
+    }
+
     Ok(())
 }
 
-/// Print the status of the recorder - can we find any reunning
-/// recorder processes?
-fn print_recorder_status() -> Result<()> {
-    let this_process_id = std::process::id();
-    let this_user_id = get_user_id_running_process_id(this_process_id)?;
-    let running_process_ids = find_process_ids_by_user_and_executable_name(
-        THIS_EXECUTABLE_NAME,
-        this_user_id,
-        this_process_id,
-    )?;
+/// Print the status of the recorder - is a recorder running (holding
+/// the lock file), and can the recorder write to storage?
+fn print_recorder_status(settings: &RecorderAppSettings) -> Result<()> {
+    let lock_file_path = get_settings_lock_file_path(settings)?;
+    let running_process_id = read_locked_process_id(&lock_file_path)?;
 
-    if running_process_ids.is_empty() {
-        println!("{} is not running.", THIS_EXECUTABLE_NAME);
-    } else {
-        println!(
-            "{} is running (pids {:?}).",
-            THIS_EXECUTABLE_NAME, running_process_ids
-        );
+    let format_utc_time_seconds = |utc_time_seconds: u64| match chrono::Local
+        .timestamp_opt(utc_time_seconds as i64, 0)
+        .earliest()
+    {
+        Some(datetime) => datetime.format("%Y-%m-%d %H:%M:%S").to_string(),
+        None => "unknown".to_string(),
+    };
+
+    let is_running = running_process_id.is_some();
+    match running_process_id {
+        Some(process_id) => {
+            println!(
+                "{} is running (pid {}, lock file {:?}).",
+                THIS_EXECUTABLE_NAME, process_id, lock_file_path
+            );
+            match get_process_id_uptime_seconds(process_id) {
+                Ok(uptime_seconds) => println!(
+                    "  Process {} uptime: {}",
+                    process_id,
+                    format_duration(
+                        chrono::Duration::seconds(uptime_seconds as i64),
+                        DurationFormat::HoursMinutesSeconds
+                    )
+                ),
+                Err(err) => warn!(
+                    "Could not find the uptime of process {}. {:?}",
+                    process_id, err
+                ),
+            }
+        }
+        None => println!("{} is not running.", THIS_EXECUTABLE_NAME),
+    }
+
+    let database_file_path = match get_database_file_path(
+        &settings.core.database_dir,
+        &settings.core.database_file_name,
+    ) {
+        Some(value) => value,
+        None => return Ok(()),
+    };
+
+    // The last entry is read even when the recorder is not running,
+    // to show when the recorder last ran.
+    let current_database_file_path =
+        get_current_database_file_path(&database_file_path, settings.core.database_per_year);
+    println!("Database: {:?}", current_database_file_path);
+    let last_entry =
+        Storage::open_as_read_only(&current_database_file_path, DEFAULT_RECORD_INTERVAL_SECONDS)
+            .and_then(|storage| storage.read_last_entry());
+    match last_entry {
+        Ok(Some(entry)) => println!(
+            "  Last entry: {} ({:?}, {})",
+            format_utc_time_seconds(entry.utc_time_seconds + entry.duration_seconds),
+            entry.status,
+            entry
+                .vars
+                .executable
+                .as_deref()
+                .unwrap_or("unknown executable")
+        ),
+        Ok(None) => println!("  Last entry: none"),
+        Err(err) => println!("  Last entry: unknown ({})", err),
+    }
+
+    if !is_running {
+        return Ok(());
+    }
+    let journal_file_path =
+        get_journal_file_path(&settings.recorder.journal_file_path, &database_file_path);
+    let status = match read_buffer_status(&get_status_file_path(&journal_file_path)) {
+        Ok(Some(value)) => value,
+        Ok(None) => return Ok(()),
+        Err(err) => {
+            warn!("Could not read the recorder status file. {:?}", err);
+            return Ok(());
+        }
+    };
+
+    println!(
+        "Buffer status at {}:",
+        format_utc_time_seconds(status.utc_time_seconds)
+    );
+    if let Some(entry_status) = status.entry_status {
+        println!("  User status: {:?}", entry_status);
+    }
+    println!("  Buffered entries: {}", status.buffered_entry_count);
+    println!(
+        "  Journal entries: {} ({:?})",
+        status.journal_entry_count, journal_file_path
+    );
+    println!("  Dropped entries: {}", status.dropped_entry_count);
+    println!("  Spilled entries: {}", status.spilled_entry_count);
+    println!("  Failed writes in a row: {}", status.failed_write_count);
+    match status.last_write_utc_time_seconds {
+        Some(value) => println!("  Last write: {}", format_utc_time_seconds(value)),
+        None => println!("  Last write: never"),
     }
 
     Ok(())
 }
 
-/// Stops recording activity by finding existing processes and sending
-/// a SIGTERM signal.
-fn stop_recording() -> Result<()> {
+/// Stops recording activity by finding the running recorder (holding
+/// the lock file) and sending a SIGTERM signal.
+fn stop_recording(settings: &RecorderAppSettings) -> Result<()> {
     println!("Stopping Time Tracker Recorder...");
 
-    let this_process_id = std::process::id();
-    let this_user_id = get_user_id_running_process_id(this_process_id)?;
-    let running_process_ids = find_process_ids_by_user_and_executable_name(
-        THIS_EXECUTABLE_NAME,
-        this_user_id,
-        this_process_id,
+    let lock_file_path = get_settings_lock_file_path(settings)?;
+    match read_locked_process_id(&lock_file_path)? {
+        Some(process_id) => {
+            info!(
+                "Found running {} process id {}.",
+                THIS_EXECUTABLE_NAME, process_id
+            );
+            terminate_processes(&vec![process_id])?;
+        }
+        None => warn!("No {} processes found to stop.", THIS_EXECUTABLE_NAME),
+    }
+
+    Ok(())
+}
+
+/// The journal file of the recorder, next to which the control
+/// socket and lock file are created (when "XDG_RUNTIME_DIR" is not
+/// set).
+fn get_settings_journal_file_path(settings: &RecorderAppSettings) -> Result<PathBuf> {
+    let database_file_path = match get_database_file_path(
+        &settings.core.database_dir,
+        &settings.core.database_file_name,
+    ) {
+        Some(value) => value,
+        None => bail!(
+            "Database file {:?} not found in {:?}",
+            &settings.core.database_file_name,
+            &settings.core.database_dir
+        ),
+    };
+    Ok(get_journal_file_path(
+        &settings.recorder.journal_file_path,
+        &database_file_path,
+    ))
+}
+
+/// The control socket of the running recorder.
+fn get_settings_control_socket_path(settings: &RecorderAppSettings) -> Result<PathBuf> {
+    Ok(get_control_socket_path(&get_settings_journal_file_path(
+        settings,
+    )?))
+}
+
+/// The lock file of the running recorder.
+fn get_settings_lock_file_path(settings: &RecorderAppSettings) -> Result<PathBuf> {
+    Ok(get_lock_file_path(&get_settings_journal_file_path(
+        settings,
+    )?))
+}
+
+/// Pause the running recorder, for the duration (such as "1h") or
+/// until resumed.
+fn pause_recording(settings: &RecorderAppSettings, duration: Option<&str>) -> Result<()> {
+    let end_utc_time_seconds = match duration {
+        Some(text) => match parse_duration(text) {
+            Some(value) if value.num_seconds() > 0 => {
+                Some(chrono::Utc::now().timestamp() as u64 + value.num_seconds() as u64)
+            }
+            _ => bail!(
+                "Invalid duration {:?}, expected a duration such as \"1h\" or \"30m\".",
+                text
+            ),
+        },
+        None => None,
+    };
+
+    let socket_path = get_settings_control_socket_path(settings)?;
+    send_control_command(
+        &socket_path,
+        ControlCommand::Pause {
+            end_utc_time_seconds,
+        },
     )?;
-    info!(
-        "Found {} running process ids for {}: {:?}.",
-        running_process_ids.len(),
-        THIS_EXECUTABLE_NAME,
-        running_process_ids
-    );
+    match end_utc_time_seconds {
+        Some(value) => match chrono::Local.timestamp_opt(value as i64, 0).earliest() {
+            Some(datetime) => println!(
+                "Recording paused until {}.",
+                datetime.format("%Y-%m-%d %H:%M:%S")
+            ),
+            None => println!("Recording paused."),
+        },
+        None => println!("Recording paused until resumed."),
+    }
+
+    Ok(())
+}
+
+/// Resume the running recorder, after a pause.
+fn resume_recording(settings: &RecorderAppSettings) -> Result<()> {
+    let socket_path = get_settings_control_socket_path(settings)?;
+    send_control_command(&socket_path, ControlCommand::Resume)?;
+    println!("Recording resumed.");
 
-    if running_process_ids.is_empty() {
-        warn!("No {} processes found to stop.", THIS_EXECUTABLE_NAME);
-    } else {
-        terminate_processes(&running_process_ids)?;
+    Ok(())
+}
+
+/// Ask the running recorder to reload the configuration file.
+fn reload_recording(settings: &RecorderAppSettings) -> Result<()> {
+    let socket_path = get_settings_control_socket_path(settings)?;
+    send_control_command(&socket_path, ControlCommand::Reload)?;
+    println!("Reload of the recorder settings requested; errors are written to the recorder log.");
+
+    Ok(())
+}
+
+/// Insert a manual entry covering a period of idle time, so time away
+/// from the computer (such as a meeting) is reported as active time.
+fn annotate(settings: &RecorderAppSettings, from: &str, to: &str, note: &str) -> Result<()> {
+    let parse_datetime = |text: &str| {
+        parse_local_datetime(text).ok_or_else(|| {
+            anyhow!(
+                "Could not parse {:?}, expected \"YYYY-MM-DD HH:MM\" or \"HH:MM\".",
+                text
+            )
+        })
+    };
+    let start_datetime = parse_datetime(from)?;
+    let end_datetime = parse_datetime(to)?;
+    if end_datetime <= start_datetime {
+        bail!(
+            "The end time {:?} must be after the start time {:?}.",
+            to,
+            from
+        );
     }
+    let start_utc_time_seconds = start_datetime.timestamp() as u64;
+    let end_utc_time_seconds = end_datetime.timestamp() as u64;
+
+    let database_file_path = match get_database_file_path(
+        &settings.core.database_dir,
+        &settings.core.database_file_name,
+    ) {
+        Some(value) => value,
+        None => bail!(
+            "Database file {:?} not found in {:?}",
+            &settings.core.database_file_name,
+            &settings.core.database_dir
+        ),
+    };
+    let database_file_path = get_database_file_path_for_time(
+        &database_file_path,
+        settings.core.database_per_year,
+        start_utc_time_seconds,
+    );
+
+    let entry = Entry::new_manual(
+        start_utc_time_seconds,
+        end_utc_time_seconds - start_utc_time_seconds,
+        note,
+    )
+    .with_utc_offset_seconds(Some(start_datetime.offset().local_minus_utc()));
+
+    let mut storage =
+        Storage::open_as_read_write(&database_file_path, DEFAULT_RECORD_INTERVAL_SECONDS)?;
+    let count = storage.insert_manual_entry(&entry)?;
+    storage.close()?;
+
+    println!(
+        "Inserted {:?} from {} to {} (adjusting {} idle entries).",
+        note,
+        start_datetime.format("%Y-%m-%d %H:%M"),
+        end_datetime.format("%Y-%m-%d %H:%M"),
+        count
+    );
 
     Ok(())
 }
 
+/// Run 'command', recording the time until the command exits as
+/// active time, attributed to the command's executable and
+/// environment variables.
+///
+/// Returns the exit code of the command.
+fn wrap_command(settings: &RecorderAppSettings, command: &[String]) -> Result<i32> {
+    let database_file_path = match get_database_file_path(
+        &settings.core.database_dir,
+        &settings.core.database_file_name,
+    ) {
+        Some(value) => value,
+        None => bail!(
+            "Database file {:?} not found in {:?}",
+            &settings.core.database_file_name,
+            &settings.core.database_dir
+        ),
+    };
+    let database_per_year = settings.core.database_per_year;
+    let max_merged_entry_seconds = settings.storage.max_merged_entry_seconds;
+    let record_interval_seconds = settings.recorder.record_interval_seconds;
+
+    // The command runs with the environment of this process, so the
+    // variables are read once, before the command starts.
+    let environ_vars: HashMap<String, String> = std::env::vars().collect();
+    let mut vars = new_variables_list(&settings.core.environment_variables.names);
+    vars.replace_with_environ_vars(&environ_vars);
+    vars.executable = Path::new(&command[0])
+        .file_name()
+        .map(|x| x.to_string_lossy().to_string());
+    VariableFilters::new(&settings.variable_filters)?.apply(&mut vars);
+    let ignore_rules = IgnoreRules::new(&settings.recorder.ignore)?;
+    let is_ignored = ignore_rules.is_ignored(vars.executable.as_deref(), &environ_vars);
+    if is_ignored {
+        vars = new_variables_list(&settings.core.environment_variables.names);
+        vars.executable = Some(IGNORED_EXECUTABLE_NAME.to_string());
+    }
+    let is_skipped = is_ignored && ignore_rules.action == IgnoreAction::Skip;
+
+    let mut child = std::process::Command::new(&command[0])
+        .args(&command[1..])
+        .spawn()
+        .with_context(|| format!("Could not run command {:?}.", command))?;
+
+    // Interrupting (Ctrl+C) the terminal interrupts the command, and
+    // the time recorded so far is written once the command exits.
+    //
+    // NOTE: The signal is ignored only after the command has started,
+    // so the command does not inherit the ignored signal.
+    install_signal_handler(libc::SIGINT, libc::SIG_IGN);
+
+    let new_entry = |start_seconds: u64, end_seconds: u64| {
+        let utc_offset_seconds = chrono::Local
+            .timestamp_opt(start_seconds as i64, 0)
+            .earliest()
+            .map(|x| x.offset().local_minus_utc());
+        Entry::new(
+            start_seconds,
+            end_seconds - start_seconds,
+            EntryStatus::Active,
+            vars.clone(),
+        )
+        .with_utc_offset_seconds(utc_offset_seconds)
+    };
+
+    let mut entries = Vec::new();
+    let mut start_seconds = chrono::Utc::now().timestamp() as u64;
+    let exit_status = loop {
+        thread::sleep(time::Duration::from_secs(1));
+        let exit_status = child.try_wait()?;
+
+        let now_seconds = chrono::Utc::now().timestamp() as u64;
+        if (now_seconds >= start_seconds + record_interval_seconds)
+            || (exit_status.is_some() && now_seconds > start_seconds)
+        {
+            if !is_skipped {
+                entries.push(new_entry(start_seconds, now_seconds));
+            }
+            start_seconds = now_seconds;
+        }
+
+        if let Some(value) = exit_status {
+            break value;
+        }
+        if entries.len() >= ENTRY_BUFFER_MAX_COUNT {
+            match write_entries_to_storage(
+                &database_file_path,
+                database_per_year,
+                max_merged_entry_seconds,
+                record_interval_seconds,
+                &Vec::new(),
+                &entries,
+                None,
+            ) {
+                Ok(()) => entries.clear(),
+                Err(err) => warn!("Could not write to storage, will retry later. {:?}", err),
+            }
+        }
+    };
+
+    write_entries_to_storage(
+        &database_file_path,
+        database_per_year,
+        max_merged_entry_seconds,
+        record_interval_seconds,
+        &Vec::new(),
+        &entries,
+        None,
+    )?;
+
+    match exit_status.code() {
+        Some(value) => Ok(value),
+        None => {
+            warn!("Command {:?} was terminated by a signal.", command);
+            Ok(1)
+        }
+    }
+}
+
 fn main() -> Result<()> {
     let env = env_logger::Env::default()
         .filter_or("TIMETRACKER_LOG", "warn")
@@ -367,9 +1592,34 @@ fn main() -> Result<()> {
     match &args.command {
         CommandModes::Start {
             terminate_existing_processes,
-        } => start_recording(&args, settings, *terminate_existing_processes)?,
-        CommandModes::Status => print_recorder_status()?,
-        CommandModes::Stop => stop_recording()?,
+            no_supervisor,
+            worker,
+            ..
+        } => {
+            // The supervisor holds the lock of the running recorder
+            // (for the worker).
+            if *worker {
+                start_recording(&args, settings)?;
+            } else if let Some(_instance_lock) = acquire_instance_lock(
+                &get_settings_lock_file_path(&settings)?,
+                *terminate_existing_processes,
+            )? {
+                match no_supervisor {
+                    true => start_recording(&args, settings)?,
+                    false => supervise_worker()?,
+                }
+            }
+        }
+        CommandModes::Status => print_recorder_status(&settings)?,
+        CommandModes::Stop => stop_recording(&settings)?,
+        CommandModes::Pause { duration } => pause_recording(&settings, duration.as_deref())?,
+        CommandModes::Resume => resume_recording(&settings)?,
+        CommandModes::Reload => reload_recording(&settings)?,
+        CommandModes::Annotate { from, to, note } => annotate(&settings, from, to, note)?,
+        CommandModes::Wrap { command } => {
+            let exit_code = wrap_command(&settings, command)?;
+            std::process::exit(exit_code);
+        }
     }
 
     Ok(())
@@ -0,0 +1,176 @@
+use log::{debug, warn};
+use std::process::Command;
+use timetracker_core::entries::EntryStatus;
+use timetracker_core::format::format_duration;
+use timetracker_core::format::DurationFormat;
+use timetracker_core::settings::NotificationsSettings;
+
+/// How often the active time of today is read (from the database) to
+/// check the daily target.
+const DAILY_TARGET_CHECK_INTERVAL_SECONDS: u64 = 60;
+
+const NOTIFICATION_APP_NAME: &str = "Timetracker";
+const NOTIFICATION_ICON_NAME: &str = "appointment-soon";
+
+/// Send a desktop notification with 'notify-send', which uses the
+/// desktop's notification service (the same as libnotify).
+pub fn send_notification(summary: &str, body: &str) {
+    debug!("Notification: {:?} {:?}", summary, body);
+    let result = Command::new("notify-send")
+        .arg(format!("--app-name={}", NOTIFICATION_APP_NAME))
+        .arg(format!("--icon={}", NOTIFICATION_ICON_NAME))
+        .arg(summary)
+        .arg(body)
+        .spawn();
+    match result {
+        // Wait in another thread, so the recorder is not blocked by a
+        // slow notification service.
+        Ok(mut child) => {
+            std::thread::spawn(move || child.wait());
+        }
+        Err(err) => warn!("Could not send notification {:?}. {:?}", summary, err),
+    }
+}
+
+fn format_duration_seconds(duration_seconds: u64) -> String {
+    let duration = chrono::Duration::seconds(duration_seconds.try_into().unwrap_or(i64::MAX));
+    format_duration(duration, DurationFormat::HoursMinutes)
+}
+
+/// Notify that writing to storage has started failing, or has started
+/// working again, when the 'failed_write_count' changes.
+pub fn notify_storage_write_status(
+    settings: &NotificationsSettings,
+    previous_failed_write_count: u64,
+    failed_write_count: u64,
+) {
+    if !settings.enabled || !settings.storage_failure {
+        return;
+    }
+    match (previous_failed_write_count, failed_write_count) {
+        (0, 1) => send_notification(
+            "Timetracker cannot write to storage",
+            "Recorded entries are kept in memory until storage can be written.",
+        ),
+        (1.., 0) => send_notification(
+            "Timetracker is writing to storage again",
+            "The entries kept in memory have been written.",
+        ),
+        _ => (),
+    }
+}
+
+/// Sends the daily target and break reminder notifications, from the
+/// status of each recorded sample.
+pub struct Notifier {
+    settings: NotificationsSettings,
+    /// The start of the current active session; the end of the last
+    /// break.
+    session_start_utc_time_seconds: Option<u64>,
+    /// When the user became inactive, or None while active.
+    inactive_start_utc_time_seconds: Option<u64>,
+    /// The last break reminder sent, in the current session.
+    reminder_utc_time_seconds: Option<u64>,
+    last_daily_target_check_utc_time_seconds: u64,
+    /// The day the daily target was last reached.
+    daily_target_date: Option<chrono::NaiveDate>,
+    is_first_daily_target_check: bool,
+}
+
+impl Notifier {
+    pub fn new(settings: NotificationsSettings) -> Notifier {
+        Notifier {
+            settings,
+            session_start_utc_time_seconds: None,
+            inactive_start_utc_time_seconds: None,
+            reminder_utc_time_seconds: None,
+            last_daily_target_check_utc_time_seconds: 0,
+            daily_target_date: None,
+            is_first_daily_target_check: true,
+        }
+    }
+
+    /// Update the active session with the status of a sample, sending
+    /// a break reminder when the session is long.
+    pub fn add_sample(&mut self, utc_time_seconds: u64, status: EntryStatus) {
+        let break_reminder_seconds = self.settings.break_reminder_minutes * 60;
+        if !self.settings.enabled || break_reminder_seconds == 0 {
+            return;
+        }
+
+        if status != EntryStatus::Active {
+            self.inactive_start_utc_time_seconds
+                .get_or_insert(utc_time_seconds);
+            return;
+        }
+
+        // A long enough inactive time is a break, starting a new
+        // session.
+        let break_minimum_seconds = self.settings.break_minimum_minutes * 60;
+        if let Some(inactive_start) = self.inactive_start_utc_time_seconds.take() {
+            if utc_time_seconds.saturating_sub(inactive_start) >= break_minimum_seconds {
+                self.session_start_utc_time_seconds = None;
+                self.reminder_utc_time_seconds = None;
+            }
+        }
+        let session_start = *self
+            .session_start_utc_time_seconds
+            .get_or_insert(utc_time_seconds);
+
+        let last_reminder = self.reminder_utc_time_seconds.unwrap_or(session_start);
+        if utc_time_seconds.saturating_sub(last_reminder) >= break_reminder_seconds {
+            self.reminder_utc_time_seconds = Some(utc_time_seconds);
+            send_notification(
+                "Time for a break",
+                &format!(
+                    "You have been active for {} without a break.",
+                    format_duration_seconds(utc_time_seconds - session_start)
+                ),
+            );
+        }
+    }
+
+    /// Notify when the active time of today (given by
+    /// 'read_today_active_seconds', called at most once a minute)
+    /// reaches the daily target.
+    ///
+    /// A target already reached when the recorder starts is not
+    /// notified, so restarting the recorder does not notify again.
+    pub fn check_daily_target(
+        &mut self,
+        utc_time_seconds: u64,
+        read_today_active_seconds: impl FnOnce() -> u64,
+    ) {
+        let daily_target_seconds = (self.settings.daily_target_hours * 60.0 * 60.0) as u64;
+        if !self.settings.enabled || daily_target_seconds == 0 {
+            return;
+        }
+        if utc_time_seconds
+            < self.last_daily_target_check_utc_time_seconds + DAILY_TARGET_CHECK_INTERVAL_SECONDS
+        {
+            return;
+        }
+        self.last_daily_target_check_utc_time_seconds = utc_time_seconds;
+
+        let today = chrono::Local::now().date_naive();
+        if self.daily_target_date == Some(today) {
+            return;
+        }
+        let today_active_seconds = read_today_active_seconds();
+        if today_active_seconds < daily_target_seconds {
+            self.is_first_daily_target_check = false;
+            return;
+        }
+
+        self.daily_target_date = Some(today);
+        if !std::mem::replace(&mut self.is_first_daily_target_check, false) {
+            send_notification(
+                "Daily target reached",
+                &format!(
+                    "You have been active for {} today.",
+                    format_duration_seconds(today_active_seconds)
+                ),
+            );
+        }
+    }
+}
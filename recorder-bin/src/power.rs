@@ -0,0 +1,137 @@
+use log::{info, warn};
+use std::fs::read_to_string;
+use std::path::Path;
+use std::time::Duration;
+use std::time::Instant;
+
+/// The power supplies (AC adapters and batteries) of the machine;
+/// the same devices read by UPower.
+const POWER_SUPPLY_DIRECTORY: &str = "/sys/class/power_supply";
+
+/// The power supplies are read this often, because running on
+/// battery only changes when the AC adapter is plugged in (or
+/// unplugged).
+const POWER_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The machine is only considered suspended for at least this long,
+/// so a delayed timer is never recorded as a suspend.
+const SUSPEND_MINIMUM_SECONDS: u64 = 5;
+
+fn read_power_supply_value(power_supply_path: &Path, name: &str) -> Option<String> {
+    read_to_string(power_supply_path.join(name))
+        .ok()
+        .map(|value| value.trim().to_string())
+}
+
+/// Is the machine running on battery, read from
+/// "/sys/class/power_supply"? True when a battery is discharging and
+/// no AC adapter ("Mains") is online.
+pub fn is_running_on_battery() -> std::io::Result<bool> {
+    let mut is_discharging = false;
+    for dir_entry in std::fs::read_dir(POWER_SUPPLY_DIRECTORY)? {
+        let power_supply_path = dir_entry?.path();
+        match read_power_supply_value(&power_supply_path, "type").as_deref() {
+            Some("Mains")
+                if read_power_supply_value(&power_supply_path, "online").as_deref()
+                    == Some("1") =>
+            {
+                return Ok(false);
+            }
+            Some("Battery") => {
+                is_discharging |= read_power_supply_value(&power_supply_path, "status").as_deref()
+                    == Some("Discharging");
+            }
+            _ => (),
+        }
+    }
+    Ok(is_discharging)
+}
+
+/// Tracks whether the machine is running on battery, reading the
+/// power supplies at most every 'POWER_CHECK_INTERVAL'.
+pub struct PowerMonitor {
+    is_on_battery: bool,
+    last_check: Option<Instant>,
+}
+
+impl PowerMonitor {
+    pub fn new() -> PowerMonitor {
+        PowerMonitor {
+            is_on_battery: false,
+            last_check: None,
+        }
+    }
+
+    pub fn is_on_battery(&mut self) -> bool {
+        let check_is_due = self
+            .last_check
+            .is_none_or(|x| x.elapsed() >= POWER_CHECK_INTERVAL);
+        if !check_is_due {
+            return self.is_on_battery;
+        }
+        self.last_check = Some(Instant::now());
+
+        match is_running_on_battery() {
+            Ok(value) => {
+                if value != self.is_on_battery {
+                    info!(
+                        "Running on {}.",
+                        match value {
+                            true => "battery",
+                            false => "AC power",
+                        }
+                    );
+                }
+                self.is_on_battery = value;
+            }
+            Err(err) => warn!("Could not read power supplies: err={:?}", err),
+        }
+        self.is_on_battery
+    }
+}
+
+fn clock_seconds(clock_id: libc::clockid_t) -> u64 {
+    let mut time = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    unsafe { libc::clock_gettime(clock_id, &mut time) };
+    time.tv_sec as u64
+}
+
+/// Detects when the machine was suspended (or hibernated), from the
+/// difference between the boot time clock ('CLOCK_BOOTTIME'), which
+/// includes the time suspended, and the monotonic clock, which does
+/// not.
+///
+/// Unlike the wall clock, neither clock changes when the user (or
+/// NTP) changes the time, so a changed time is never recorded as a
+/// suspend.
+pub struct SuspendDetector {
+    suspended_seconds: u64,
+}
+
+impl SuspendDetector {
+    pub fn new() -> SuspendDetector {
+        SuspendDetector {
+            suspended_seconds: Self::read_suspended_seconds(),
+        }
+    }
+
+    /// The total time suspended since the machine booted.
+    fn read_suspended_seconds() -> u64 {
+        clock_seconds(libc::CLOCK_BOOTTIME).saturating_sub(clock_seconds(libc::CLOCK_MONOTONIC))
+    }
+
+    /// The number of seconds the machine was suspended since the last
+    /// call, or None when the machine was not suspended.
+    pub fn take_suspended_seconds(&mut self) -> Option<u64> {
+        let suspended_seconds = Self::read_suspended_seconds();
+        let duration_seconds = suspended_seconds.saturating_sub(self.suspended_seconds);
+        if duration_seconds < SUSPEND_MINIMUM_SECONDS {
+            return None;
+        }
+        self.suspended_seconds = suspended_seconds;
+        Some(duration_seconds)
+    }
+}
@@ -0,0 +1,192 @@
+use anyhow::Result;
+use log::{debug, warn};
+use quick_xml::events::BytesStart;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+const RECENTLY_USED_FILE_NAME: &str = "recently-used.xbel";
+const FILE_URI_PREFIX: &str = "file://";
+
+/// The recently-used files ("recently-used.xbel") written by GTK
+/// applications, following the freedesktop.org "Desktop Bookmark"
+/// specification. The file is found in "$XDG_DATA_HOME", or
+/// "~/.local/share" when "XDG_DATA_HOME" is not set.
+pub fn get_recently_used_file_path() -> Option<PathBuf> {
+    match std::env::var_os("XDG_DATA_HOME") {
+        Some(value) if !value.is_empty() => {
+            Some(PathBuf::from(value).join(RECENTLY_USED_FILE_NAME))
+        }
+        _ => {
+            let home_dir = std::env::var_os("HOME")?;
+            Some(
+                PathBuf::from(home_dir)
+                    .join(".local")
+                    .join("share")
+                    .join(RECENTLY_USED_FILE_NAME),
+            )
+        }
+    }
+}
+
+/// Decode the percent-encoded characters (such as "%20") of a URI.
+fn decode_percent_encoding(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%' && index + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[index + 1..index + 3]).unwrap_or("");
+            if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                decoded.push(byte);
+                index += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[index]);
+        index += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Convert a "file://" URI to a file path. Other URIs (such as
+/// remote files) have no local file path.
+fn file_uri_to_path(uri: &str) -> Option<String> {
+    let path = uri.strip_prefix(FILE_URI_PREFIX)?;
+    // Skip the (optional) host name.
+    let path = &path[path.find('/')?..];
+    Some(decode_percent_encoding(path))
+}
+
+/// The executable name of the command line of a bookmark
+/// application, such as "'gedit %u'" or "/usr/bin/gimp %U".
+fn application_exec_name(exec: &str) -> Option<String> {
+    let command = exec.trim_matches(|c| c == '\'' || c == '"').trim();
+    let program = command.split_whitespace().next()?;
+    let name = Path::new(program).file_name()?.to_string_lossy();
+    Some(name.to_lowercase())
+}
+
+fn attribute_value(element: &BytesStart, name: &[u8]) -> Option<String> {
+    let attribute = element.try_get_attribute(name).ok()??;
+    Some(attribute.unescape_value().ok()?.to_string())
+}
+
+/// The time an application last used a bookmark, in UTC seconds.
+fn parse_modified_time(value: &str) -> Option<i64> {
+    let datetime = chrono::DateTime::parse_from_rfc3339(value).ok()?;
+    Some(datetime.timestamp())
+}
+
+/// Parse the content of a recently-used file, returning the most
+/// recently used document file path of each application (keyed by
+/// the lowercase application and executable names).
+fn parse_recently_used_documents(content: &str) -> Result<HashMap<String, (i64, String)>> {
+    let mut documents = HashMap::<String, (i64, String)>::new();
+    let mut reader = Reader::from_str(content);
+    let mut document_path: Option<String> = None;
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) if element.local_name().as_ref() == b"bookmark" => {
+                document_path =
+                    attribute_value(&element, b"href").and_then(|x| file_uri_to_path(&x));
+            }
+            Event::End(element) if element.local_name().as_ref() == b"bookmark" => {
+                document_path = None;
+            }
+            Event::Start(element) | Event::Empty(element)
+                if element.local_name().as_ref() == b"application" =>
+            {
+                let path = match &document_path {
+                    Some(value) => value,
+                    None => continue,
+                };
+                let modified = attribute_value(&element, b"modified")
+                    .and_then(|x| parse_modified_time(&x))
+                    .unwrap_or(0);
+
+                let mut keys = Vec::new();
+                if let Some(name) = attribute_value(&element, b"name") {
+                    keys.push(name.to_lowercase());
+                }
+                if let Some(exec) = attribute_value(&element, b"exec") {
+                    keys.extend(application_exec_name(&exec));
+                }
+                for key in keys {
+                    let is_newer = documents
+                        .get(&key)
+                        .is_none_or(|(last_modified, _path)| modified > *last_modified);
+                    if is_newer {
+                        documents.insert(key, (modified, path.clone()));
+                    }
+                }
+            }
+            Event::Eof => break,
+            _ => (),
+        }
+    }
+    Ok(documents)
+}
+
+/// Finds the document most recently used by an application, from
+/// the recently-used files.
+///
+/// The file is only read again when it has been modified, so looking
+/// up a document is cheap enough to be done for every entry.
+pub struct RecentlyUsedDocuments {
+    file_path: Option<PathBuf>,
+    file_modified: Option<SystemTime>,
+    documents: HashMap<String, (i64, String)>,
+}
+
+impl RecentlyUsedDocuments {
+    pub fn new() -> RecentlyUsedDocuments {
+        RecentlyUsedDocuments {
+            file_path: get_recently_used_file_path(),
+            file_modified: None,
+            documents: HashMap::new(),
+        }
+    }
+
+    fn refresh(&mut self) {
+        let file_path = match &self.file_path {
+            Some(value) => value,
+            None => return,
+        };
+        let file_modified = match std::fs::metadata(file_path).and_then(|x| x.modified()) {
+            Ok(value) => value,
+            // The file does not exist until an application uses a
+            // file.
+            Err(_) => return,
+        };
+        if self.file_modified == Some(file_modified) {
+            return;
+        }
+        self.file_modified = Some(file_modified);
+
+        debug!("Reading recently-used file: {:?}", file_path);
+        let documents = std::fs::read_to_string(file_path)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| parse_recently_used_documents(&content));
+        match documents {
+            Ok(documents) => self.documents = documents,
+            Err(err) => warn!(
+                "Could not read recently-used file {:?}: err={:?}",
+                file_path, err
+            ),
+        }
+    }
+
+    /// The file path of the document most recently used by the
+    /// executable, or None if the executable has not used a
+    /// (local) file.
+    pub fn document_path(&mut self, executable_name: &str) -> Option<String> {
+        self.refresh();
+        self.documents
+            .get(&executable_name.to_lowercase())
+            .map(|(_modified, path)| path.clone())
+    }
+}
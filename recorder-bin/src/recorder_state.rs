@@ -0,0 +1,119 @@
+use crate::entry_buffer::EntryBuffer;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use timetracker_core::entries::EntryStatus;
+use timetracker_core::entries::HostBusyPeriod;
+use timetracker_core::entries::WindowColorPeriod;
+
+/// The state shared by the thread recording user activity (the main
+/// thread) and the thread writing to storage.
+pub struct RecorderState {
+    /// The buffer of entries stored in memory, waiting to be written
+    /// to storage.
+    pub entry_buffer: Mutex<EntryBuffer>,
+
+    /// Has the main thread asked the storage thread to write the
+    /// buffered entries (and the write has not finished yet)?
+    pub write_pending: AtomicBool,
+
+    /// The executable files seen since the last write to storage,
+    /// with the last time each executable was seen. The versions of
+    /// these executables are detected by the storage thread.
+    pub pending_executable_paths: Mutex<HashMap<PathBuf, u64>>,
+
+    /// The periods the machine was busy, waiting to be written to the
+    /// database.
+    pub host_busy_periods: Mutex<Vec<HostBusyPeriod>>,
+
+    /// The colors sampled from the focused windows, waiting to be
+    /// written to the database.
+    pub window_color_periods: Mutex<Vec<WindowColorPeriod>>,
+
+    /// The status of the user; Is the user active or idle?
+    entry_status: Mutex<EntryStatus>,
+
+    /// The UTC time recording is paused until ('u64::MAX' when
+    /// paused until resumed), or None when not paused.
+    pause_end_utc_time_seconds: Mutex<Option<u64>>,
+
+    /// Has a reload of the settings been requested (with the
+    /// 'reload' control command), and not handled yet?
+    reload_requested: AtomicBool,
+
+    /// The record interval currently used (which is longer when
+    /// running on battery), so the storage thread merges the entries
+    /// recorded with the interval.
+    record_interval_seconds: AtomicU64,
+}
+
+impl RecorderState {
+    pub fn new(entry_buffer: EntryBuffer, record_interval_seconds: u64) -> RecorderState {
+        RecorderState {
+            entry_buffer: Mutex::new(entry_buffer),
+            write_pending: AtomicBool::new(false),
+            pending_executable_paths: Mutex::new(HashMap::new()),
+            host_busy_periods: Mutex::new(Vec::new()),
+            window_color_periods: Mutex::new(Vec::new()),
+            entry_status: Mutex::new(EntryStatus::Uninitialized),
+            pause_end_utc_time_seconds: Mutex::new(None),
+            reload_requested: AtomicBool::new(false),
+            record_interval_seconds: AtomicU64::new(record_interval_seconds),
+        }
+    }
+
+    pub fn record_interval_seconds(&self) -> u64 {
+        self.record_interval_seconds.load(Ordering::SeqCst)
+    }
+
+    pub fn set_record_interval_seconds(&self, record_interval_seconds: u64) {
+        self.record_interval_seconds
+            .store(record_interval_seconds, Ordering::SeqCst);
+    }
+
+    /// Ask the recording loop to reload the settings.
+    pub fn request_reload(&self) {
+        self.reload_requested.store(true, Ordering::SeqCst);
+    }
+
+    /// Return true (once) if a reload of the settings was requested.
+    pub fn take_reload_request(&self) -> bool {
+        self.reload_requested.swap(false, Ordering::SeqCst)
+    }
+
+    pub fn entry_status(&self) -> EntryStatus {
+        *self.entry_status.lock().unwrap()
+    }
+
+    pub fn set_entry_status(&self, entry_status: EntryStatus) {
+        *self.entry_status.lock().unwrap() = entry_status;
+    }
+
+    /// Pause recording until the UTC time, or until resumed when no
+    /// time is given.
+    pub fn pause(&self, end_utc_time_seconds: Option<u64>) {
+        *self.pause_end_utc_time_seconds.lock().unwrap() =
+            Some(end_utc_time_seconds.unwrap_or(u64::MAX));
+    }
+
+    pub fn resume(&self) {
+        *self.pause_end_utc_time_seconds.lock().unwrap() = None;
+    }
+
+    /// Is recording paused at the UTC time? A pause ends
+    /// automatically at the end time given to 'pause'.
+    pub fn is_paused(&self, utc_time_seconds: u64) -> bool {
+        let mut pause_end_utc_time_seconds = self.pause_end_utc_time_seconds.lock().unwrap();
+        match *pause_end_utc_time_seconds {
+            Some(value) if utc_time_seconds < value => true,
+            Some(_) => {
+                *pause_end_utc_time_seconds = None;
+                false
+            }
+            None => false,
+        }
+    }
+}
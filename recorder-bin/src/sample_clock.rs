@@ -0,0 +1,206 @@
+use std::time;
+use timetracker_core::entries::RECORDING_GAP_INTERVAL_COUNT;
+
+/// When the wall clock and the monotonic clock disagree by more than
+/// this many seconds, the wall clock is assumed to have been changed
+/// (or the machine was suspended), and the clock is re-anchored.
+const CLOCK_JUMP_LIMIT_SECONDS: f64 = 2.0;
+
+fn wall_clock_seconds() -> f64 {
+    time::SystemTime::now()
+        .duration_since(time::UNIX_EPOCH)
+        .map(|x| x.as_secs_f64())
+        .unwrap_or(0.0)
+}
+
+/// Calculates the time and duration of each recorded sample.
+///
+/// Times are taken from a monotonic clock anchored to the wall clock,
+/// and each sample's duration is the time actually elapsed since the
+/// previous sample, rather than the fixed record interval. This keeps
+/// the samples contiguous when the timer is delayed under load.
+pub struct SampleClock {
+    record_interval_seconds: u64,
+    compensate_jitter: bool,
+    anchor_wall_seconds: f64,
+    anchor_instant: time::Instant,
+    last_sample_seconds: Option<f64>,
+}
+
+impl SampleClock {
+    pub fn new(record_interval_seconds: u64, compensate_jitter: bool) -> SampleClock {
+        SampleClock::new_at(
+            record_interval_seconds,
+            compensate_jitter,
+            wall_clock_seconds(),
+            time::Instant::now(),
+        )
+    }
+
+    /// Create the clock anchored to the given wall clock (UTC
+    /// seconds) and monotonic clock readings.
+    fn new_at(
+        record_interval_seconds: u64,
+        compensate_jitter: bool,
+        wall_seconds: f64,
+        instant: time::Instant,
+    ) -> SampleClock {
+        SampleClock {
+            record_interval_seconds,
+            compensate_jitter,
+            anchor_wall_seconds: wall_seconds,
+            anchor_instant: instant,
+            last_sample_seconds: None,
+        }
+    }
+
+    /// Change the record interval, such as when the machine starts
+    /// running on battery.
+    pub fn set_record_interval_seconds(&mut self, record_interval_seconds: u64) {
+        self.record_interval_seconds = record_interval_seconds;
+    }
+
+    fn reanchor(&mut self, wall_seconds: f64, instant: time::Instant) {
+        self.anchor_wall_seconds = wall_seconds;
+        self.anchor_instant = instant;
+        self.last_sample_seconds = None;
+    }
+
+    /// Take a sample, returning the (UTC) start time and duration of
+    /// the sample, in seconds.
+    ///
+    /// Returns None when no whole second has elapsed since the
+    /// previous sample.
+    pub fn sample(&mut self) -> Option<(u64, u64)> {
+        self.sample_at(wall_clock_seconds(), time::Instant::now())
+    }
+
+    /// Take a sample at the given wall clock (UTC seconds) and
+    /// monotonic clock readings; see 'sample'.
+    fn sample_at(&mut self, wall_seconds: f64, instant: time::Instant) -> Option<(u64, u64)> {
+        if !self.compensate_jitter {
+            return Some((wall_seconds as u64, self.record_interval_seconds));
+        }
+
+        let mut now_seconds = self.anchor_wall_seconds
+            + instant
+                .saturating_duration_since(self.anchor_instant)
+                .as_secs_f64();
+        if (now_seconds - wall_seconds).abs() > CLOCK_JUMP_LIMIT_SECONDS {
+            self.reanchor(wall_seconds, instant);
+            now_seconds = wall_seconds;
+        }
+
+        let interval_seconds = self.record_interval_seconds as f64;
+        let last_sample_seconds = match self.last_sample_seconds {
+            Some(value)
+                if (now_seconds - value)
+                    <= (interval_seconds * RECORDING_GAP_INTERVAL_COUNT as f64) =>
+            {
+                value
+            }
+            _ => now_seconds - interval_seconds,
+        };
+
+        // Whole seconds are used, with the fraction of a second
+        // carried over to the next sample.
+        let start_seconds = last_sample_seconds.floor() as u64;
+        let end_seconds = now_seconds.floor() as u64;
+        if end_seconds <= start_seconds {
+            return None;
+        }
+
+        self.last_sample_seconds = Some(now_seconds);
+        Some((start_seconds, end_seconds - start_seconds))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START_SECONDS: f64 = 1726149600.0;
+    const RECORD_INTERVAL_SECONDS: u64 = 5;
+
+    /// Take the samples at the (monotonic, wall clock) seconds since
+    /// the clock was created.
+    fn samples(samples_seconds: &[(f64, f64)]) -> Vec<Option<(u64, u64)>> {
+        let start_instant = time::Instant::now();
+        let mut clock =
+            SampleClock::new_at(RECORD_INTERVAL_SECONDS, true, START_SECONDS, start_instant);
+        samples_seconds
+            .iter()
+            .map(|(monotonic_seconds, wall_seconds)| {
+                clock.sample_at(
+                    START_SECONDS + wall_seconds,
+                    start_instant + time::Duration::from_secs_f64(*monotonic_seconds),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_sample_clock_drift() {
+        let start = START_SECONDS as u64;
+        // The timer fires late by a varying amount, and the wall
+        // clock drifts (by less than the jump limit); the samples are
+        // contiguous and follow the monotonic clock.
+        assert_eq!(
+            samples(&[(5.3, 5.3), (10.9, 10.4), (15.2, 14.0), (15.6, 14.4)]),
+            vec![
+                Some((start, 5)),
+                Some((start + 5, 5)),
+                Some((start + 10, 5)),
+                // Less than a second since the previous sample.
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sample_clock_gap() {
+        let start = START_SECONDS as u64;
+        // The timer is delayed by 2 seconds, so the sample covers the
+        // delay, and the next sample is shorter.
+        assert_eq!(
+            samples(&[(5.0, 5.0), (12.0, 12.0), (15.0, 15.0)]),
+            vec![
+                Some((start, 5)),
+                Some((start + 5, 7)),
+                Some((start + 12, 3)),
+            ]
+        );
+        // Clocks 2 seconds apart are not a jump of the wall clock.
+        assert_eq!(
+            samples(&[(5.0, 5.0), (10.0, 12.0)]),
+            vec![Some((start, 5)), Some((start + 5, 5))]
+        );
+    }
+
+    #[test]
+    fn test_sample_clock_wall_clock_jump() {
+        let start = START_SECONDS as u64;
+        // The wall clock jumps forward an hour (such as the clock
+        // being set); the clock is re-anchored to the wall clock,
+        // with the sample ending at the new time.
+        assert_eq!(
+            samples(&[(5.0, 5.0), (10.0, 3610.0), (15.0, 3615.0)]),
+            vec![
+                Some((start, 5)),
+                Some((start + 3605, 5)),
+                Some((start + 3610, 5)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sample_clock_without_jitter_compensation() {
+        let start_instant = time::Instant::now();
+        let mut clock =
+            SampleClock::new_at(RECORD_INTERVAL_SECONDS, false, START_SECONDS, start_instant);
+        assert_eq!(
+            clock.sample_at(START_SECONDS + 7.5, start_instant),
+            Some((START_SECONDS as u64 + 7, RECORD_INTERVAL_SECONDS))
+        );
+    }
+}
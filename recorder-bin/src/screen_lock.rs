@@ -0,0 +1,126 @@
+use crate::linux_x11;
+use anyhow::{bail, Result};
+use log::{debug, warn};
+use std::process::Command;
+use std::process::Stdio;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Running 'dbus-send' is slower than querying X11, so the DBus
+/// screen savers are only queried this often.
+const DBUS_QUERY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The DBus service names (and object paths) of screen savers that
+/// can tell if the screen is locked, tried in order.
+const DBUS_SCREEN_SAVERS: &[(&str, &str)] = &[
+    (
+        "org.freedesktop.ScreenSaver",
+        "/org/freedesktop/ScreenSaver",
+    ),
+    ("org.gnome.ScreenSaver", "/org/gnome/ScreenSaver"),
+    ("org.mate.ScreenSaver", "/org/mate/ScreenSaver"),
+    ("org.cinnamon.ScreenSaver", "/org/cinnamon/ScreenSaver"),
+];
+
+/// Ask the DBus screen saver service if the screen saver (or the
+/// lock screen) is active, with the "GetActive" method.
+fn query_dbus_screen_saver_active(service_name: &str, object_path: &str) -> Result<bool> {
+    let output = Command::new("dbus-send")
+        .arg("--session")
+        .arg("--print-reply")
+        .arg(format!("--dest={}", service_name))
+        .arg(object_path)
+        .arg(format!("{}.GetActive", service_name))
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        bail!("Screen saver {:?} is not available.", service_name);
+    }
+
+    // The reply looks like "method return ... \n   boolean true".
+    let reply = String::from_utf8_lossy(&output.stdout);
+    match reply
+        .split_whitespace()
+        .skip_while(|x| *x != "boolean")
+        .nth(1)
+    {
+        Some("true") => Ok(true),
+        Some("false") => Ok(false),
+        _ => bail!("Could not parse screen saver reply: {:?}", reply),
+    }
+}
+
+/// Detects when the screen is locked, from the X11 screen saver or
+/// the DBus screen saver service of the desktop (such as GNOME or
+/// KDE).
+pub struct ScreenLockDetector {
+    dbus_service_index: Option<usize>,
+    has_dbus_screen_saver: bool,
+    is_dbus_screen_saver_active: bool,
+    last_dbus_query: Option<Instant>,
+}
+
+impl ScreenLockDetector {
+    pub fn new() -> ScreenLockDetector {
+        ScreenLockDetector {
+            dbus_service_index: None,
+            has_dbus_screen_saver: true,
+            is_dbus_screen_saver_active: false,
+            last_dbus_query: None,
+        }
+    }
+
+    fn query_dbus_screen_saver(&mut self) {
+        if !self.has_dbus_screen_saver {
+            return;
+        }
+        let query_is_due = self
+            .last_dbus_query
+            .is_none_or(|x| x.elapsed() >= DBUS_QUERY_INTERVAL);
+        if !query_is_due {
+            return;
+        }
+        self.last_dbus_query = Some(Instant::now());
+
+        // The first service that answers is used from then on.
+        let start_index = self.dbus_service_index.unwrap_or(0);
+        for (index, (service_name, object_path)) in
+            DBUS_SCREEN_SAVERS.iter().enumerate().skip(start_index)
+        {
+            match query_dbus_screen_saver_active(service_name, object_path) {
+                Ok(value) => {
+                    self.dbus_service_index = Some(index);
+                    self.is_dbus_screen_saver_active = value;
+                    return;
+                }
+                Err(err) => debug!("Could not query screen saver: err={:?}", err),
+            }
+            if self.dbus_service_index.is_some() {
+                break;
+            }
+        }
+
+        if self.dbus_service_index.is_none() {
+            warn!("No DBus screen saver found; only the X11 screen saver is used to detect a locked screen.");
+            self.has_dbus_screen_saver = false;
+        }
+        self.is_dbus_screen_saver_active = false;
+    }
+
+    /// Is the screen locked (or the screen saver active)?
+    pub fn is_screen_locked(&mut self) -> bool {
+        if linux_x11::is_screen_saver_active_from_x11() {
+            return true;
+        }
+        self.query_dbus_screen_saver();
+        self.is_dbus_screen_saver_active
+    }
+
+    /// Forget the cached DBus screen saver state, such as when the
+    /// user becomes active (and so the screen cannot be locked).
+    pub fn reset(&mut self) {
+        self.is_dbus_screen_saver_active = false;
+        self.last_dbus_query = None;
+    }
+}
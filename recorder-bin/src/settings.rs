@@ -1,12 +1,19 @@
 use clap::{Parser, Subcommand};
 use config::ConfigError;
 use serde_derive::Deserialize;
+use std::collections::HashMap;
 use timetracker_core::settings::new_core_settings;
 use timetracker_core::settings::new_recorder_settings;
+use timetracker_core::settings::new_sync_settings;
 use timetracker_core::settings::validate_core_settings;
 use timetracker_core::settings::CoreSettings;
+use timetracker_core::settings::NotificationsSettings;
+use timetracker_core::settings::RecorderSettings;
+use timetracker_core::settings::StorageSettings;
+use timetracker_core::settings::SyncSettings;
+use timetracker_core::settings::VariableFilterSettings;
 
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 #[clap(author = "David Cattermole, Copyright 2023-2024", version, about)]
 #[clap(propagate_version = true)]
 pub struct CommandArguments {
@@ -22,26 +29,98 @@ pub struct CommandArguments {
     pub database_file_name: Option<String>,
 }
 
-#[derive(Debug, Subcommand)]
+#[derive(Debug, Clone, Subcommand)]
 pub enum CommandModes {
     /// Start the Recorder.
     Start {
-        /// Automatically terminate (SIGTERM) existing
-        /// timetracker-recorder processes (to ensure only one process
-        /// runs at any one time).
+        /// Automatically terminate (SIGTERM) the running recorder
+        /// (holding the lock file), waiting for it to stop, to ensure
+        /// only one recorder runs at any one time.
         #[clap(long, value_parser, default_value_t = false)]
         terminate_existing_processes: bool,
+
+        /// Record in this process, rather than in a worker process
+        /// that is restarted (by this process) when it crashes.
+        #[clap(long, value_parser, default_value_t = false)]
+        no_supervisor: bool,
+
+        /// Run as the worker process of a supervisor (used
+        /// internally).
+        #[clap(long, value_parser, default_value_t = false, hide = true)]
+        worker: bool,
+
+        /// Override how often (in seconds) the recorder queries the
+        /// system ('recorder.record_interval_seconds').
+        #[clap(long, value_parser)]
+        interval_seconds: Option<u64>,
+
+        /// Override how many seconds the user must be idle before
+        /// the user is idle ('recorder.user_is_idle_limit_seconds').
+        #[clap(long, value_parser)]
+        idle_seconds: Option<u64>,
     },
     /// Status of the recorder.
     Status,
     /// Stop the recorder.
     Stop,
+    /// Pause recording activity (such as for personal tasks),
+    /// without stopping the recorder. The paused time is recorded as
+    /// paused, without any details.
+    Pause {
+        /// Resume automatically after this duration, such as "1h" or
+        /// "30m". Without a duration, recording is paused until
+        /// resumed.
+        #[clap(long = "for", value_parser)]
+        duration: Option<String>,
+    },
+    /// Resume recording activity after a pause.
+    Resume,
+    /// Reload the configuration file in the running recorder (the
+    /// same as sending the recorder 'SIGHUP'), such as after changing
+    /// the environment variables to record, without losing the
+    /// buffered entries.
+    Reload,
+    /// Insert a manual entry covering a period of idle time (such as
+    /// a meeting or phone call away from the computer).
+    Annotate {
+        /// The start of the period, as "YYYY-MM-DD HH:MM", or "HH:MM"
+        /// for today.
+        #[clap(long, value_parser)]
+        from: String,
+
+        /// The end of the period, as "YYYY-MM-DD HH:MM", or "HH:MM"
+        /// for today.
+        #[clap(long, value_parser)]
+        to: String,
+
+        /// What happened in the period, such as "meeting".
+        #[clap(long, value_parser)]
+        note: String,
+    },
+    /// Run a command, recording the time until the command exits as
+    /// active time of the command (regardless of the focused
+    /// window), such as for long renders and batch jobs.
+    Wrap {
+        /// The command (and arguments) to run, after "--".
+        #[clap(value_parser, required = true, last = true)]
+        command: Vec<String>,
+    },
 }
 
 #[derive(Debug, Deserialize)]
 #[allow(unused)]
 pub struct RecorderAppSettings {
     pub core: CoreSettings,
+    pub recorder: RecorderSettings,
+    pub storage: StorageSettings,
+    pub sync: SyncSettings,
+    pub notifications: NotificationsSettings,
+    /// Maps workspace (virtual desktop) names to project variable
+    /// values.
+    pub workspace_projects: HashMap<String, String>,
+    /// Filters for the values of each variable name, applied before
+    /// the values are stored.
+    pub variable_filters: HashMap<String, VariableFilterSettings>,
 }
 
 impl RecorderAppSettings {
@@ -51,10 +130,29 @@ impl RecorderAppSettings {
             arguments.database_file_name.clone(),
             false,
         )?;
-        let builder = new_recorder_settings(builder)?;
+        let mut builder = new_recorder_settings(builder)?;
+        builder = new_sync_settings(builder)?;
+        if let CommandModes::Start {
+            interval_seconds,
+            idle_seconds,
+            ..
+        } = &arguments.command
+        {
+            builder = builder
+                .set_override_option("recorder.record_interval_seconds", *interval_seconds)?
+                .set_override_option("recorder.user_is_idle_limit_seconds", *idle_seconds)?;
+        }
 
         let settings: Self = builder.build()?.try_deserialize()?;
-        validate_core_settings(&settings.core).unwrap();
+        // The recorder reloads the settings while running, so invalid
+        // settings are an error, rather than a panic.
+        validate_core_settings(&settings.core)
+            .map_err(|err| ConfigError::Message(err.to_string()))?;
+        if settings.recorder.record_interval_seconds == 0 {
+            return Err(ConfigError::Message(
+                "'recorder.record_interval_seconds' must be at least 1.".to_string(),
+            ));
+        }
 
         Ok(settings)
     }
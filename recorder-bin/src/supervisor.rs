@@ -0,0 +1,132 @@
+use crate::linux_signal::install_signal_handler;
+use anyhow::Result;
+use log::{error, info, warn};
+use std::os::unix::process::ExitStatusExt;
+use std::process::Command;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time;
+
+/// The command line flag that runs the recorder as the worker
+/// process of a supervisor.
+pub const WORKER_FLAG: &str = "--worker";
+
+/// The delay before the first restart of a worker process, doubled
+/// after each following restart.
+const MIN_RESTART_DELAY_SECONDS: u64 = 1;
+
+/// The longest delay before restarting a worker process.
+const MAX_RESTART_DELAY_SECONDS: u64 = 300;
+
+/// A worker process that ran for at least this long is considered
+/// to have been working, so the restart delay is reset.
+const RESET_RESTART_DELAY_SECONDS: u64 = 600;
+
+/// The running worker process id, so the signal handler can stop the
+/// worker.
+static WORKER_PROCESS_ID: AtomicU32 = AtomicU32::new(0);
+
+/// Has the supervisor been asked to stop?
+static SUPERVISOR_STOPPING: AtomicBool = AtomicBool::new(false);
+
+/// Stops the worker (which writes the buffered entries before
+/// exiting), and stops the supervisor from restarting it.
+extern "C" fn handle_supervisor_signal(_signal_number: libc::c_int) {
+    SUPERVISOR_STOPPING.store(true, Ordering::SeqCst);
+    let process_id = WORKER_PROCESS_ID.load(Ordering::SeqCst);
+    if process_id != 0 {
+        unsafe { libc::kill(process_id as libc::pid_t, libc::SIGTERM) };
+    }
+}
+
+/// Forwards the request to reload the settings ('SIGHUP') to the
+/// worker, which records activity with the settings.
+extern "C" fn handle_supervisor_reload_signal(_signal_number: libc::c_int) {
+    let process_id = WORKER_PROCESS_ID.load(Ordering::SeqCst);
+    if process_id != 0 {
+        unsafe { libc::kill(process_id as libc::pid_t, libc::SIGHUP) };
+    }
+}
+
+/// Describe why a worker process exited, such as "exit code 1" or
+/// "signal 11".
+fn exit_reason(status: &std::process::ExitStatus) -> String {
+    match (status.code(), status.signal()) {
+        (Some(code), _) => format!("exit code {}", code),
+        (None, Some(signal)) => format!("signal {}", signal),
+        (None, None) => "unknown reason".to_string(),
+    }
+}
+
+/// Run the recorder in a worker (child) process, restarting the
+/// worker when it exits abnormally (such as an X11 error on some
+/// desktops), with a delay that increases after each restart.
+///
+/// The worker runs this executable with the same arguments, adding
+/// the '--worker' flag. The supervisor stops when the worker exits
+/// successfully (such as when stopped by 'timetracker-recorder
+/// stop'), or when the supervisor is asked to stop.
+pub fn supervise_worker() -> Result<()> {
+    install_signal_handler(libc::SIGINT, handle_supervisor_signal as *const () as usize);
+    install_signal_handler(
+        libc::SIGTERM,
+        handle_supervisor_signal as *const () as usize,
+    );
+    install_signal_handler(
+        libc::SIGHUP,
+        handle_supervisor_reload_signal as *const () as usize,
+    );
+
+    let executable_path = std::env::current_exe()?;
+    let arguments: Vec<_> = std::env::args_os().skip(1).collect();
+
+    let mut restart_delay_seconds = MIN_RESTART_DELAY_SECONDS;
+    while !SUPERVISOR_STOPPING.load(Ordering::SeqCst) {
+        let start_time = time::Instant::now();
+        let mut child = Command::new(&executable_path)
+            .args(&arguments)
+            .arg(WORKER_FLAG)
+            .spawn()?;
+        WORKER_PROCESS_ID.store(child.id(), Ordering::SeqCst);
+        info!("Started recorder worker process {}.", child.id());
+
+        // The signal may have arrived before the process id was
+        // stored.
+        if SUPERVISOR_STOPPING.load(Ordering::SeqCst) {
+            handle_supervisor_signal(libc::SIGTERM);
+        }
+
+        let status = child.wait()?;
+        WORKER_PROCESS_ID.store(0, Ordering::SeqCst);
+        if status.success() || SUPERVISOR_STOPPING.load(Ordering::SeqCst) {
+            info!("Recorder worker process {} stopped.", child.id());
+            break;
+        }
+
+        let run_seconds = start_time.elapsed().as_secs();
+        if run_seconds >= RESET_RESTART_DELAY_SECONDS {
+            restart_delay_seconds = MIN_RESTART_DELAY_SECONDS;
+        }
+        error!(
+            "Recorder worker process {} exited abnormally ({}) after {} seconds; restarting in {} seconds.",
+            child.id(),
+            exit_reason(&status),
+            run_seconds,
+            restart_delay_seconds
+        );
+
+        // Sleep in short steps, so a stop request is not delayed.
+        let restart_time = time::Instant::now() + time::Duration::from_secs(restart_delay_seconds);
+        while time::Instant::now() < restart_time && !SUPERVISOR_STOPPING.load(Ordering::SeqCst) {
+            thread::sleep(time::Duration::from_millis(100));
+        }
+        restart_delay_seconds = (restart_delay_seconds * 2).min(MAX_RESTART_DELAY_SECONDS);
+    }
+
+    if SUPERVISOR_STOPPING.load(Ordering::SeqCst) {
+        warn!("Recorder supervisor received a signal, exiting.");
+    }
+    Ok(())
+}
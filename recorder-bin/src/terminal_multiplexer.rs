@@ -0,0 +1,168 @@
+use crate::linux_process::find_descendant_process_ids;
+use crate::linux_process::get_process_id_terminal_path;
+use crate::linux_x11::ProcessID;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::io::Read;
+use std::process::Command;
+use std::process::Stdio;
+use std::thread;
+use std::time;
+
+/// The variable names given to the terminal multiplexer details, so
+/// they can be recorded like environment variables.
+pub const TMUX_SESSION_VARIABLE_NAME: &str = "TMUX_SESSION";
+pub const TMUX_WINDOW_VARIABLE_NAME: &str = "TMUX_WINDOW";
+pub const SCREEN_SESSION_VARIABLE_NAME: &str = "SCREEN_SESSION";
+
+/// How long to wait for tmux/screen to answer, before the querying
+/// process is killed.
+const QUERY_TIMEOUT_MILLISECONDS: u64 = 500;
+
+/// How long the variables found for a terminal process are re-used,
+/// before the processes and terminal multiplexer are queried again.
+const CACHE_MAX_AGE_SECONDS: u64 = 5;
+
+/// Caches the terminal multiplexer variables of terminal processes,
+/// so the processes are not searched (and tmux/screen are not run)
+/// every time the recorder samples the active window.
+pub struct TerminalMultiplexerCache {
+    variables: HashMap<ProcessID, (time::Instant, HashMap<String, String>)>,
+}
+
+impl TerminalMultiplexerCache {
+    pub fn new() -> TerminalMultiplexerCache {
+        TerminalMultiplexerCache {
+            variables: HashMap::new(),
+        }
+    }
+
+    /// Get the terminal multiplexer variables of the (terminal)
+    /// process, reading the variables again only when the cached
+    /// variables are older than 'CACHE_MAX_AGE_SECONDS' at 'now'.
+    pub fn get_variables(
+        &mut self,
+        process_id: ProcessID,
+        now: time::Instant,
+    ) -> Result<HashMap<String, String>> {
+        let max_age = time::Duration::from_secs(CACHE_MAX_AGE_SECONDS);
+        self.variables
+            .retain(|_process_id, (read_instant, _variables)| {
+                now.saturating_duration_since(*read_instant) < max_age
+            });
+        if let Some((_read_instant, variables)) = self.variables.get(&process_id) {
+            return Ok(variables.clone());
+        }
+
+        let variables = read_terminal_multiplexer_variables(process_id)?;
+        self.variables.insert(process_id, (now, variables.clone()));
+        Ok(variables)
+    }
+}
+
+/// Run a command and return the standard output, killing the
+/// command if it does not exit quickly.
+///
+/// When 'require_success' is false the output is returned
+/// regardless of the exit code.
+fn run_command_output(program: &str, args: &[&str], require_success: bool) -> Option<String> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+
+    let now = time::Instant::now();
+    let timeout = time::Duration::from_millis(QUERY_TIMEOUT_MILLISECONDS);
+    let exit_status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) => {
+                if now.elapsed() > timeout {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return None;
+                }
+                thread::sleep(time::Duration::from_millis(10));
+            }
+            Err(_) => return None,
+        }
+    };
+    if require_success && !exit_status.success() {
+        return None;
+    }
+
+    let mut output = String::new();
+    child.stdout.take()?.read_to_string(&mut output).ok()?;
+    Some(output)
+}
+
+/// Ask the (user's default) tmux server for the session and window
+/// names shown by the tmux client attached to the terminal.
+fn query_tmux_client(terminal_path: &str) -> Option<(String, String)> {
+    let output = run_command_output(
+        "tmux",
+        &[
+            "display-message",
+            "-p",
+            "-c",
+            terminal_path,
+            "#{session_name}\t#{window_name}",
+        ],
+        true,
+    )?;
+    let line = output.lines().next()?;
+    let (session_name, window_name) = line.split_once('\t')?;
+    Some((session_name.to_string(), window_name.to_string()))
+}
+
+/// Get the name of the attached GNU screen session, if only one
+/// session is attached.
+fn query_screen_attached_session() -> Option<String> {
+    // 'screen -ls' returns a non-zero exit code even on success, so
+    // the output is always used.
+    let output = run_command_output("screen", &["-ls"], false)?;
+
+    // Lines look like "\t12345.session_name\t(Attached)".
+    let sessions: Vec<String> = output
+        .lines()
+        .filter(|x| x.contains("(Attached)"))
+        .filter_map(|x| x.split_whitespace().next())
+        .filter_map(|x| x.split_once('.').map(|(_pid, name)| name.to_string()))
+        .collect();
+    match sessions.len() {
+        1 => sessions.into_iter().next(),
+        _ => None,
+    }
+}
+
+/// Find the terminal multiplexer (tmux or GNU screen) running inside
+/// the (terminal) process, and return the session/window names as
+/// variables.
+fn read_terminal_multiplexer_variables(process_id: ProcessID) -> Result<HashMap<String, String>> {
+    let mut map = HashMap::new();
+
+    for (child_process_id, command_name) in find_descendant_process_ids(process_id)? {
+        // The tmux/screen servers are not children of the terminal,
+        // so only clients can be found here.
+        if command_name.starts_with("tmux") {
+            let terminal_path = get_process_id_terminal_path(child_process_id)?;
+            if let Some((session_name, window_name)) =
+                query_tmux_client(&terminal_path.to_string_lossy())
+            {
+                map.insert(TMUX_SESSION_VARIABLE_NAME.to_string(), session_name);
+                map.insert(TMUX_WINDOW_VARIABLE_NAME.to_string(), window_name);
+            }
+            break;
+        } else if command_name == "screen" {
+            if let Some(session_name) = query_screen_attached_session() {
+                map.insert(SCREEN_SESSION_VARIABLE_NAME.to_string(), session_name);
+            }
+            break;
+        }
+    }
+
+    Ok(map)
+}
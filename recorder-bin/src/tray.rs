@@ -0,0 +1,183 @@
+use crate::active_time::read_today_active_seconds;
+use crate::recorder_state::RecorderState;
+use gtk::glib;
+use gtk::glib::translate::{from_glib_full, ToGlibPtr};
+use gtk::prelude::*;
+use log::warn;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::Arc;
+use timetracker_core::format::format_duration;
+use timetracker_core::format::DurationFormat;
+
+/// How often the status and active time of today shown by the tray
+/// icon are updated.
+const TRAY_ICON_UPDATE_INTERVAL_SECONDS: u32 = 60;
+
+const TRAY_ICON_NAME: &str = "appointment-soon";
+const TRAY_ICON_PAUSED_NAME: &str = "media-playback-pause";
+
+/// The program opened by the "Open Report" menu item.
+const PRINT_GUI_EXECUTABLE_NAME: &str = "timetracker-print-gui";
+
+// The "GtkStatusIcon" is deprecated (since GTK 3.14) and is not
+// wrapped by the 'gtk' crate, but it is still the most widely
+// supported tray icon (using the XEmbed system tray, or the
+// "StatusNotifierItem" compatibility of most desktops).
+glib::wrapper! {
+    pub struct StatusIcon(Object<gtk::ffi::GtkStatusIcon, gtk::ffi::GtkStatusIconClass>);
+
+    match fn {
+        type_ => || gtk::ffi::gtk_status_icon_get_type(),
+    }
+}
+
+impl StatusIcon {
+    fn from_icon_name(icon_name: &str) -> StatusIcon {
+        unsafe {
+            from_glib_full(gtk::ffi::gtk_status_icon_new_from_icon_name(
+                icon_name.to_glib_none().0,
+            ))
+        }
+    }
+
+    fn set_icon_name(&self, icon_name: &str) {
+        self.set_property("icon-name", icon_name);
+    }
+
+    fn set_tooltip_text(&self, text: &str) {
+        self.set_property("tooltip-text", text);
+    }
+}
+
+/// The icon in the system tray, showing the status of the user and
+/// the active time of today, with a menu to control the recorder.
+pub struct TrayIcon {
+    status_icon: StatusIcon,
+    pause_menu_item: gtk::MenuItem,
+    state: Arc<RecorderState>,
+    database_file_path: PathBuf,
+    database_per_year: bool,
+}
+
+/// Open the report, preferring the 'timetracker-print-gui' next to
+/// the recorder executable, over the one found on the PATH.
+fn open_report() {
+    let executable_path = std::env::current_exe()
+        .ok()
+        .and_then(|path| path.parent().map(|dir| dir.join(PRINT_GUI_EXECUTABLE_NAME)))
+        .filter(|path| path.is_file())
+        .unwrap_or_else(|| PathBuf::from(PRINT_GUI_EXECUTABLE_NAME));
+    match Command::new(&executable_path).spawn() {
+        // Wait for the report to close, so the process does not
+        // remain as a zombie process.
+        Ok(mut child) => {
+            std::thread::spawn(move || child.wait());
+        }
+        Err(err) => warn!("Could not open the report {:?}. {:?}", executable_path, err),
+    }
+}
+
+impl TrayIcon {
+    /// Update the icon, tooltip and menu with the recorder state.
+    pub fn update(&self) {
+        let now_seconds = chrono::Utc::now().timestamp() as u64;
+        let is_paused = self.state.is_paused(now_seconds);
+        let status = match is_paused {
+            true => "Paused".to_string(),
+            false => format!("{:?}", self.state.entry_status()),
+        };
+        let today_active_seconds = read_today_active_seconds(
+            &self.state,
+            &self.database_file_path,
+            self.database_per_year,
+        );
+        let today_duration =
+            chrono::Duration::seconds(today_active_seconds.try_into().unwrap_or(i64::MAX));
+
+        self.status_icon.set_icon_name(match is_paused {
+            true => TRAY_ICON_PAUSED_NAME,
+            false => TRAY_ICON_NAME,
+        });
+        self.status_icon.set_tooltip_text(&format!(
+            "Time Tracker: {}\nToday: {}",
+            status,
+            format_duration(today_duration, DurationFormat::HoursMinutes)
+        ));
+        self.pause_menu_item.set_label(match is_paused {
+            true => "Resume Recording",
+            false => "Pause Recording",
+        });
+    }
+}
+
+/// Show the tray icon, updated periodically, until the GTK main loop
+/// exits.
+///
+/// 'quit' is called by the "Quit Recorder" menu item, so the recorder
+/// can write the buffered entries before exiting.
+pub fn start_tray_icon(
+    state: Arc<RecorderState>,
+    database_file_path: PathBuf,
+    database_per_year: bool,
+    quit: impl Fn() + 'static,
+) -> std::rc::Rc<TrayIcon> {
+    let menu = gtk::Menu::new();
+    let open_report_menu_item = gtk::MenuItem::with_label("Open Report");
+    let pause_menu_item = gtk::MenuItem::with_label("Pause Recording");
+    let quit_menu_item = gtk::MenuItem::with_label("Quit Recorder");
+    menu.append(&open_report_menu_item);
+    menu.append(&pause_menu_item);
+    menu.append(&gtk::SeparatorMenuItem::new());
+    menu.append(&quit_menu_item);
+    menu.show_all();
+
+    let tray_icon = std::rc::Rc::new(TrayIcon {
+        status_icon: StatusIcon::from_icon_name(TRAY_ICON_NAME),
+        pause_menu_item: pause_menu_item.clone(),
+        state,
+        database_file_path,
+        database_per_year,
+    });
+
+    open_report_menu_item.connect_activate(|_| open_report());
+
+    let pause_tray_icon = tray_icon.clone();
+    pause_menu_item.connect_activate(move |_| {
+        let state = &pause_tray_icon.state;
+        match state.is_paused(chrono::Utc::now().timestamp() as u64) {
+            true => state.resume(),
+            false => state.pause(None),
+        }
+        pause_tray_icon.update();
+    });
+
+    quit_menu_item.connect_activate(move |_| quit());
+
+    // Both clicking the icon, and the context menu of the icon, show
+    // the menu.
+    let activate_menu = menu.clone();
+    tray_icon
+        .status_icon
+        .connect_local("activate", false, move |_values| {
+            activate_menu.popup_at_pointer(None);
+            None
+        });
+    tray_icon
+        .status_icon
+        .connect_local("popup-menu", false, move |values| {
+            let button = values[1].get::<u32>().unwrap_or(0);
+            let activate_time = values[2].get::<u32>().unwrap_or(0);
+            menu.popup_easy(button, activate_time);
+            None
+        });
+
+    tray_icon.update();
+    let timer_tray_icon = tray_icon.clone();
+    glib::source::timeout_add_seconds_local(TRAY_ICON_UPDATE_INTERVAL_SECONDS, move || {
+        timer_tray_icon.update();
+        glib::ControlFlow::Continue
+    });
+
+    tray_icon
+}
@@ -0,0 +1,78 @@
+use anyhow::{Context, Result};
+use log::debug;
+use regex::Regex;
+use std::collections::HashMap;
+use timetracker_core::entries::EntryVariablesList;
+use timetracker_core::entries::FILTERED_VARIABLE_VALUE;
+use timetracker_core::settings::VariableFilterSettings;
+
+fn compile_regexes(variable_name: &str, patterns: &[String]) -> Result<Vec<Regex>> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            Regex::new(pattern).with_context(|| {
+                format!(
+                    "Invalid regular expression {:?} in variable filter {:?}.",
+                    pattern, variable_name
+                )
+            })
+        })
+        .collect()
+}
+
+/// The allow and deny filters of a single variable.
+#[derive(Debug)]
+struct VariableFilter {
+    allow: Vec<Regex>,
+    deny: Vec<Regex>,
+}
+
+impl VariableFilter {
+    fn is_allowed(&self, value: &str) -> bool {
+        let is_allow_listed = self.allow.is_empty() || self.allow.iter().any(|x| x.is_match(value));
+        is_allow_listed && !self.deny.iter().any(|x| x.is_match(value))
+    }
+}
+
+/// Filters the values of variables before they are stored, replacing
+/// denied values with a placeholder.
+///
+/// The keys of configuration file tables are lowercased when read,
+/// so variable names are compared case-insensitively.
+#[derive(Debug)]
+pub struct VariableFilters {
+    filters: HashMap<String, VariableFilter>,
+}
+
+impl VariableFilters {
+    pub fn new(settings: &HashMap<String, VariableFilterSettings>) -> Result<VariableFilters> {
+        let mut filters = HashMap::new();
+        for (variable_name, filter_settings) in settings {
+            let filter = VariableFilter {
+                allow: compile_regexes(variable_name, &filter_settings.allow)?,
+                deny: compile_regexes(variable_name, &filter_settings.deny)?,
+            };
+            filters.insert(variable_name.to_lowercase(), filter);
+        }
+        Ok(VariableFilters { filters })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty()
+    }
+
+    /// Replace the values of the variables that are not allowed with
+    /// a placeholder.
+    pub fn apply(&self, vars: &mut EntryVariablesList) {
+        vars.replace_variable_values(|name, value| {
+            let filter = self.filters.get(&name.to_lowercase())?;
+            match filter.is_allowed(value) {
+                true => None,
+                false => {
+                    debug!("Variable {:?} value is filtered.", name);
+                    Some(FILTERED_VARIABLE_VALUE.to_string())
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,83 @@
+use crate::linux_x11::get_active_top_window_id_from_x11;
+use crate::linux_x11::get_window_pixel_colors_from_x11;
+use std::collections::HashMap;
+use std::os::raw::c_ulong;
+use std::time::Duration;
+use std::time::Instant;
+
+/// The number of pixels sampled across (and down) the window.
+const PIXEL_GRID_SIZE: u32 = 8;
+
+/// The content of a window changes slowly, so the same window is
+/// only sampled again after this long.
+const RESAMPLE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The number of bits kept for each channel when grouping similar
+/// colors together.
+const QUANTIZE_BITS: u32 = 3;
+
+/// Find the dominant color of the pixels, as a 0xRRGGBB value.
+///
+/// Similar colors are grouped together, and the average of the
+/// largest group is the dominant color.
+fn dominant_color(colors: &[(u8, u8, u8)]) -> Option<u32> {
+    let shift = 8 - QUANTIZE_BITS;
+    let mut groups = HashMap::<(u8, u8, u8), (u32, u32, u32, u32)>::new();
+    for (r, g, b) in colors {
+        let key = (r >> shift, g >> shift, b >> shift);
+        let group = groups.entry(key).or_insert((0, 0, 0, 0));
+        group.0 += *r as u32;
+        group.1 += *g as u32;
+        group.2 += *b as u32;
+        group.3 += 1;
+    }
+
+    // Ties are broken by the key, so the same pixels always give the
+    // same color.
+    let (_key, (r, g, b, count)) = groups
+        .into_iter()
+        .max_by_key(|(key, (_r, _g, _b, count))| (*count, *key))?;
+    Some(((r / count) << 16) | ((g / count) << 8) | (b / count))
+}
+
+/// Samples the dominant color of the focused window.
+///
+/// The pixels read from the window are only used to calculate the
+/// color, and are never stored.
+pub struct WindowColorSampler {
+    window_id: c_ulong,
+    color: Option<u32>,
+    last_sample: Option<Instant>,
+}
+
+impl WindowColorSampler {
+    pub fn new() -> WindowColorSampler {
+        WindowColorSampler {
+            window_id: 0,
+            color: None,
+            last_sample: None,
+        }
+    }
+
+    /// Get the dominant color of the focused window, re-using the
+    /// last sampled color while the same window stays focused.
+    pub fn sample(&mut self) -> Option<u32> {
+        let window_id = get_active_top_window_id_from_x11();
+        if window_id == 0 {
+            return None;
+        }
+
+        let is_stale = self.window_id != window_id
+            || self
+                .last_sample
+                .is_none_or(|x| x.elapsed() >= RESAMPLE_INTERVAL);
+        if is_stale {
+            let colors = get_window_pixel_colors_from_x11(window_id, PIXEL_GRID_SIZE);
+            self.window_id = window_id;
+            self.color = dominant_color(&colors);
+            self.last_sample = Some(Instant::now());
+        }
+
+        self.color
+    }
+}
@@ -0,0 +1,272 @@
+use crate::settings::CommandArguments;
+use crate::settings::ServerAppSettings;
+use anyhow::{anyhow, bail, Result};
+use clap::Parser;
+use log::{debug, error, info, warn};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::PathBuf;
+use timetracker_core::entries::Entry;
+use timetracker_core::errors::StorageError;
+use timetracker_core::filesystem::get_database_file_path;
+use timetracker_core::filesystem::get_database_file_path_for_time;
+use timetracker_core::filesystem::get_database_file_paths_for_range;
+use timetracker_core::http_storage::SYNC_AUTHORIZATION_HEADER;
+use timetracker_core::http_storage::SYNC_ENTRIES_URL_PATH;
+use timetracker_core::multi_storage::MultiStorage;
+use timetracker_core::settings::DEFAULT_RECORD_INTERVAL_SECONDS;
+use timetracker_core::storage::Storage;
+
+mod settings;
+
+/// A response to a request; the HTTP status code and the (JSON)
+/// body.
+type Reply = (u16, String);
+
+/// The largest request body read, in bytes. Requests are handled one
+/// at a time, so a larger body is rejected rather than read into
+/// memory.
+const MAX_REQUEST_BODY_BYTES: usize = 64 * 1024 * 1024;
+
+fn error_reply(status_code: u16, message: &str) -> Reply {
+    (
+        status_code,
+        serde_json::json!({ "error": message }).to_string(),
+    )
+}
+
+fn find_database_file_path(settings: &ServerAppSettings) -> Result<PathBuf> {
+    match get_database_file_path(
+        &settings.core.database_dir,
+        &settings.core.database_file_name,
+    ) {
+        Some(value) => Ok(value),
+        None => bail!(
+            "Database file {:?} not found in {:?}",
+            &settings.core.database_file_name,
+            &settings.core.database_dir
+        ),
+    }
+}
+
+/// Write the entries to the database, to the database file of each
+/// year when 'core.database_per_year' is enabled.
+fn write_entries(settings: &ServerAppSettings, entries: Vec<Entry>) -> Result<()> {
+    let database_file_path = find_database_file_path(settings)?;
+
+    let mut file_entries = BTreeMap::<PathBuf, Vec<Entry>>::new();
+    for entry in entries {
+        let file_path = get_database_file_path_for_time(
+            &database_file_path,
+            settings.core.database_per_year,
+            entry.utc_time_seconds,
+        );
+        file_entries.entry(file_path).or_default().push(entry);
+    }
+
+    for (file_path, entries) in file_entries {
+        let mut storage = Storage::open_as_read_write(&file_path, DEFAULT_RECORD_INTERVAL_SECONDS)?;
+        storage.set_max_merged_entry_seconds(settings.storage.max_merged_entry_seconds);
+        storage.insert_entries(&entries);
+        storage.write_entries()?;
+        storage.close()?;
+    }
+    Ok(())
+}
+
+fn read_entries(
+    settings: &ServerAppSettings,
+    start_utc_time_seconds: u64,
+    end_utc_time_seconds: u64,
+) -> Result<Vec<Entry>> {
+    let database_file_path = find_database_file_path(settings)?;
+    let database_file_paths = get_database_file_paths_for_range(
+        &database_file_path,
+        settings.core.database_per_year,
+        &settings.core.archive_dir,
+        start_utc_time_seconds,
+        end_utc_time_seconds,
+    );
+    let mut storage = match MultiStorage::open_as_read_only(
+        &database_file_paths,
+        DEFAULT_RECORD_INTERVAL_SECONDS,
+    ) {
+        Ok(value) => value,
+        // Nothing has been written yet.
+        Err(StorageError::Missing(_)) => return Ok(Vec::new()),
+        Err(err) => return Err(err.into()),
+    };
+    storage.set_use_recorded_utc_offset(settings.core.use_recorded_utc_offset);
+    let entries = storage.read_entries(start_utc_time_seconds, end_utc_time_seconds)?;
+    storage.close()?;
+    Ok(entries.all_entries().to_vec())
+}
+
+/// Compare the bytes in a time that does not depend on how many of
+/// the bytes match, so the token cannot be guessed from the time
+/// taken to reject it.
+fn constant_time_equal(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn is_authorized(settings: &ServerAppSettings, request: &tiny_http::Request) -> bool {
+    if settings.sync.token.is_empty() {
+        return true;
+    }
+    let expected_value = format!("Bearer {}", settings.sync.token);
+    request.headers().iter().any(|x| {
+        x.field.equiv(SYNC_AUTHORIZATION_HEADER)
+            && constant_time_equal(x.value.as_str().as_bytes(), expected_value.as_bytes())
+    })
+}
+
+/// Read the body of the request, or an error reply if the body is
+/// larger than 'MAX_REQUEST_BODY_BYTES' or cannot be read.
+fn read_request_body(request: &mut tiny_http::Request) -> Result<String, Reply> {
+    let too_large_reply = || {
+        error_reply(
+            413,
+            &format!("Request is larger than {} bytes.", MAX_REQUEST_BODY_BYTES),
+        )
+    };
+    if request
+        .body_length()
+        .is_some_and(|length| length > MAX_REQUEST_BODY_BYTES)
+    {
+        return Err(too_large_reply());
+    }
+
+    // The body may not have a length (such as a chunked body), so
+    // read one more byte than allowed to find out if it is too large.
+    let mut body = String::new();
+    let limit = MAX_REQUEST_BODY_BYTES as u64 + 1;
+    if let Err(err) = request.as_reader().take(limit).read_to_string(&mut body) {
+        return Err(error_reply(
+            400,
+            &format!("Could not read request: {}", err),
+        ));
+    }
+    if body.len() > MAX_REQUEST_BODY_BYTES {
+        return Err(too_large_reply());
+    }
+    Ok(body)
+}
+
+fn parse_query_value(query: &str, name: &str) -> Option<u64> {
+    query
+        .split('&')
+        .filter_map(|x| x.split_once('='))
+        .find(|(key, _value)| *key == name)
+        .and_then(|(_key, value)| value.parse::<u64>().ok())
+}
+
+fn handle_request(settings: &ServerAppSettings, request: &mut tiny_http::Request) -> Reply {
+    if !is_authorized(settings, request) {
+        return error_reply(401, "Invalid or missing token.");
+    }
+
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    if path != SYNC_ENTRIES_URL_PATH {
+        return error_reply(404, "Not found.");
+    }
+
+    match request.method() {
+        tiny_http::Method::Post => {
+            let body = match read_request_body(request) {
+                Ok(value) => value,
+                Err(reply) => return reply,
+            };
+            let entries: Vec<Entry> = match serde_json::from_str(&body) {
+                Ok(value) => value,
+                Err(err) => return error_reply(400, &format!("Invalid entries: {}", err)),
+            };
+            let entry_count = entries.len();
+            match write_entries(settings, entries) {
+                Ok(()) => {
+                    debug!("Written {} entries.", entry_count);
+                    (
+                        200,
+                        serde_json::json!({ "written": entry_count }).to_string(),
+                    )
+                }
+                Err(err) => {
+                    error!("Could not write entries. {:?}", err);
+                    error_reply(500, "Could not write entries.")
+                }
+            }
+        }
+        tiny_http::Method::Get => {
+            let start = parse_query_value(query, "start_utc_time_seconds");
+            let end = parse_query_value(query, "end_utc_time_seconds");
+            let (start, end) = match (start, end) {
+                (Some(start), Some(end)) if start <= end => (start, end),
+                _ => {
+                    return error_reply(
+                        400,
+                        "Expected 'start_utc_time_seconds' and 'end_utc_time_seconds'.",
+                    )
+                }
+            };
+            match read_entries(settings, start, end) {
+                Ok(entries) => match serde_json::to_string(&entries) {
+                    Ok(value) => (200, value),
+                    Err(err) => error_reply(500, &format!("Could not encode entries: {}", err)),
+                },
+                Err(err) => {
+                    error!("Could not read entries. {:?}", err);
+                    error_reply(500, "Could not read entries.")
+                }
+            }
+        }
+        _ => error_reply(405, "Method not allowed."),
+    }
+}
+
+fn main() -> Result<()> {
+    let env = env_logger::Env::default()
+        .filter_or("TIMETRACKER_LOG", "warn")
+        .write_style("TIMETRACKER_LOG_STYLE");
+    env_logger::init_from_env(env);
+
+    let args = CommandArguments::parse();
+
+    let settings = ServerAppSettings::new(&args);
+    if settings.is_err() {
+        bail!("Settings are invalid: {:?}", settings);
+    }
+    let settings = settings?;
+    debug!("Settings validated: {:#?}", settings);
+
+    if settings.sync.token.is_empty() {
+        warn!("No 'sync.token' is set, any client may read and write entries.");
+    }
+    println!("Database file: {:?}", find_database_file_path(&settings)?);
+
+    let server = tiny_http::Server::http(&settings.sync.listen_address).map_err(|err| {
+        anyhow!(
+            "Could not listen on {:?}: {}",
+            settings.sync.listen_address,
+            err
+        )
+    })?;
+    println!("Listening on {}", settings.sync.listen_address);
+
+    let content_type = tiny_http::Header::from_bytes("Content-Type", "application/json")
+        .expect("Header should be valid.");
+    for mut request in server.incoming_requests() {
+        let (status_code, body) = handle_request(&settings, &mut request);
+        info!("{} {} -> {}", request.method(), request.url(), status_code);
+        let response = tiny_http::Response::from_string(body)
+            .with_status_code(status_code)
+            .with_header(content_type.clone());
+        if let Err(err) = request.respond(response) {
+            warn!("Could not send response. {:?}", err);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use clap::Parser;
+use config::ConfigError;
+use serde_derive::Deserialize;
+use timetracker_core::settings::new_core_settings;
+use timetracker_core::settings::new_recorder_settings;
+use timetracker_core::settings::new_sync_settings;
+use timetracker_core::settings::validate_core_settings;
+use timetracker_core::settings::CoreSettings;
+use timetracker_core::settings::StorageSettings;
+use timetracker_core::settings::SyncSettings;
+
+#[derive(Parser, Debug)]
+#[clap(author = "David Cattermole, Copyright 2023-2024", version, about)]
+pub struct CommandArguments {
+    /// The address (and port) to listen on, such as "0.0.0.0:9797".
+    /// Overrides 'sync.listen_address'.
+    #[clap(long, value_parser)]
+    pub listen_address: Option<String>,
+
+    /// Override the directory to search for the database file.
+    #[clap(long, value_parser)]
+    pub database_dir: Option<String>,
+
+    /// Override the name of the database file to open.
+    #[clap(long, value_parser)]
+    pub database_file_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(unused)]
+pub struct ServerAppSettings {
+    pub core: CoreSettings,
+    pub storage: StorageSettings,
+    pub sync: SyncSettings,
+}
+
+impl ServerAppSettings {
+    pub fn new(arguments: &CommandArguments) -> Result<Self, ConfigError> {
+        let builder = new_core_settings(
+            arguments.database_dir.clone(),
+            arguments.database_file_name.clone(),
+            false,
+        )?;
+        // The storage settings are the same as the recorder, so the
+        // entries are merged the same.
+        let builder = new_recorder_settings(builder)?;
+        let builder = new_sync_settings(builder)?
+            .set_override_option("sync.listen_address", arguments.listen_address.clone())?;
+
+        let settings: Self = builder.build()?.try_deserialize()?;
+        validate_core_settings(&settings.core).unwrap();
+
+        Ok(settings)
+    }
+}